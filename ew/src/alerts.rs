@@ -0,0 +1,259 @@
+//! Threshold alerts over worker time series.
+//!
+//! Rules are evaluated each time a worker they depend on reports a new
+//! block to the monitor. Rule states are kept in the `alerts` schema, so
+//! restarts don't notify alerts again, and reported to the monitor.
+pub mod notifier;
+mod readings;
+mod store;
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::SystemTime;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::Sender;
+use tokio_postgres::Client;
+use tokio_postgres::NoTls;
+
+use crate::config::AlertRule;
+use crate::config::AlertsConfig;
+use crate::config::PostgresConfig;
+use crate::core::types::Height;
+use crate::core::types::Timestamp;
+use crate::error::EwError;
+use crate::monitor::events::LiveEvent;
+use crate::monitor::MonitorMessage;
+use crate::shutdown::CancellationToken;
+use notifier::Notifier;
+use readings::Reading;
+
+/// Name the alerter reports failures under.
+pub const ALERTER: &str = "alerts";
+
+/// State of an alert rule.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlertState {
+    pub rule: String,
+    pub firing: bool,
+    /// Last evaluated value of the rule's metric
+    pub value: f64,
+    /// Height of the last evaluated value
+    pub height: Height,
+    /// Time of last change of state, in milliseconds since epoch
+    pub since: Timestamp,
+}
+
+impl AlertState {
+    pub fn status(&self) -> &'static str {
+        match self.firing {
+            true => "firing",
+            false => "resolved",
+        }
+    }
+}
+
+/// Evaluates alert rules and notifies state changes.
+pub struct Alerter {
+    client: Client,
+    rules: Vec<AlertRule>,
+    /// Milliseconds
+    max_block_age: Timestamp,
+    states: HashMap<String, AlertState>,
+    notifier: Box<dyn Notifier>,
+    monitor_tx: Sender<MonitorMessage>,
+}
+
+impl Alerter {
+    pub async fn new(
+        pgconf: &PostgresConfig,
+        config: &AlertsConfig,
+        notifier: Box<dyn Notifier>,
+        monitor_tx: Sender<MonitorMessage>,
+    ) -> Result<Self, EwError> {
+        let (mut client, connection) =
+            tokio_postgres::connect(&pgconf.connection_uri, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                tracing::error!("connection error: {}", e);
+            }
+        });
+        store::schema().init(&mut client).await?;
+        // States of rules dropped from the config are ignored
+        let states = store::get_all(&client)
+            .await?
+            .into_iter()
+            .filter(|state| config.rules.iter().any(|r| r.name == state.rule))
+            .map(|state| (state.rule.clone(), state))
+            .collect();
+        Ok(Self {
+            client,
+            rules: config.rules.clone(),
+            max_block_age: config.max_block_age.as_millis() as Timestamp,
+            states,
+            notifier,
+            monitor_tx,
+        })
+    }
+
+    /// Evaluates rules after each block of the workers they depend on.
+    ///
+    /// * `events` - live events from the monitor
+    #[tracing::instrument(name = "alerts", skip_all)]
+    pub async fn start(
+        &mut self,
+        mut events: broadcast::Receiver<LiveEvent>,
+        shutdown: CancellationToken,
+    ) -> Result<(), EwError> {
+        tracing::info!("starting");
+        for state in self.states.values() {
+            self.monitor_tx
+                .send(MonitorMessage::Alert(state.clone()))
+                .await
+                .unwrap();
+        }
+        // Catch up with blocks included while not running
+        self.evaluate(|_| true).await?;
+        loop {
+            let event = tokio::select! {
+                biased;
+                _ = shutdown.cancelled() => break,
+                event = events.recv() => event,
+            };
+            match event {
+                Ok(LiveEvent::Block { worker, .. }) => {
+                    self.evaluate(|rule| rule.metric.worker().name() == worker)
+                        .await?
+                }
+                Ok(LiveEvent::Rollback { .. }) => (),
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::debug!("missed {n} events");
+                    self.evaluate(|_| true).await?
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+        tracing::info!("stopped");
+        Ok(())
+    }
+
+    /// Evaluates rules matching given `filter`.
+    async fn evaluate(&mut self, filter: impl Fn(&AlertRule) -> bool) -> Result<(), EwError> {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .expect("system time is after unix epoch")
+            .as_millis() as Timestamp;
+        for rule in self.rules.iter().filter(|r| filter(r)) {
+            let Some(reading) = readings::read(&self.client, rule.metric).await? else {
+                continue;
+            };
+            if now - reading.timestamp > self.max_block_age {
+                continue;
+            }
+            let previous = self.states.get(&rule.name);
+            let (state, changed) = next_state(rule, previous, &reading, now);
+            if previous == Some(&state) {
+                continue;
+            }
+            store::upsert(&self.client, &state).await?;
+            if changed {
+                self.notifier.notify(rule, &state).await;
+            }
+            self.monitor_tx
+                .send(MonitorMessage::Alert(state.clone()))
+                .await
+                .unwrap();
+            self.states.insert(rule.name.clone(), state);
+        }
+        Ok(())
+    }
+}
+
+/// Next state of `rule` given a new `reading`, and whether it changed status.
+///
+/// Rules without a previous state only notify when firing.
+fn next_state(
+    rule: &AlertRule,
+    previous: Option<&AlertState>,
+    reading: &Reading,
+    now: Timestamp,
+) -> (AlertState, bool) {
+    let firing = rule.above.is_some_and(|t| reading.value > t)
+        || rule.below.is_some_and(|t| reading.value < t);
+    let changed = match previous {
+        Some(previous) => previous.firing != firing,
+        None => firing,
+    };
+    let since = match previous {
+        Some(previous) if !changed => previous.since,
+        _ => now,
+    };
+    let state = AlertState {
+        rule: rule.name.clone(),
+        firing,
+        value: reading.value,
+        height: reading.height,
+        since,
+    };
+    (state, changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AlertMetric;
+    use pretty_assertions::assert_eq;
+
+    fn rule(above: Option<f64>, below: Option<f64>) -> AlertRule {
+        AlertRule {
+            name: "rr".to_owned(),
+            metric: AlertMetric::SigmaUSDReserveRatio,
+            above,
+            below,
+        }
+    }
+
+    fn reading(height: Height, value: f64) -> Reading {
+        Reading {
+            height,
+            timestamp: 0,
+            value,
+        }
+    }
+
+    #[test]
+    fn test_first_evaluation() {
+        let rule = rule(None, Some(400.0));
+        let (state, changed) = next_state(&rule, None, &reading(10, 450.0), 1000);
+        assert!(!changed);
+        assert!(!state.firing);
+        let (state, changed) = next_state(&rule, None, &reading(10, 350.0), 1000);
+        assert!(changed);
+        assert_eq!(
+            state,
+            AlertState {
+                rule: "rr".to_owned(),
+                firing: true,
+                value: 350.0,
+                height: 10,
+                since: 1000,
+            }
+        );
+    }
+
+    #[test]
+    fn test_status_changes() {
+        let rule = rule(Some(10.0), Some(-10.0));
+        let (firing, _) = next_state(&rule, None, &reading(10, 12.0), 1000);
+        // Still firing, since is kept
+        let (state, changed) = next_state(&rule, Some(&firing), &reading(11, -15.0), 2000);
+        assert!(!changed);
+        assert!(state.firing);
+        assert_eq!(state.since, 1000);
+        assert_eq!(state.value, -15.0);
+        // Resolved
+        let (state, changed) = next_state(&rule, Some(&state), &reading(12, 0.0), 3000);
+        assert!(changed);
+        assert!(!state.firing);
+        assert_eq!(state.since, 3000);
+    }
+}
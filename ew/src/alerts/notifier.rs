@@ -0,0 +1,92 @@
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::AlertState;
+use crate::config::AlertRule;
+use crate::config::AlertsConfig;
+
+/// Receives alerts changing state.
+///
+/// Notifiers handle their own failures, so a notification that could not
+/// be sent doesn't hold back rule evaluation.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn notify(&self, rule: &AlertRule, state: &AlertState);
+}
+
+/// Notifier matching given `config`.
+pub fn from_config(config: &AlertsConfig) -> Box<dyn Notifier> {
+    match &config.notify_url {
+        Some(url) => Box::new(WebhookNotifier::new(url, config)),
+        None => Box::new(LogNotifier {}),
+    }
+}
+
+/// Logs alert changes.
+pub struct LogNotifier {}
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    async fn notify(&self, rule: &AlertRule, state: &AlertState) {
+        match state.firing {
+            true => tracing::warn!(
+                "alert {} firing - {} is {} at height {}",
+                rule.name,
+                rule.metric.name(),
+                state.value,
+                state.height
+            ),
+            false => tracing::info!(
+                "alert {} resolved - {} is {} at height {}",
+                rule.name,
+                rule.metric.name(),
+                state.value,
+                state.height
+            ),
+        }
+    }
+}
+
+/// POSTs alert changes as JSON to a url.
+pub struct WebhookNotifier {
+    url: String,
+    http: reqwest::Client,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: &str, config: &AlertsConfig) -> Self {
+        Self {
+            url: url.to_owned(),
+            http: reqwest::Client::builder()
+                .timeout(config.timeout)
+                .build()
+                .expect("http client settings are valid"),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(&self, rule: &AlertRule, state: &AlertState) {
+        let body = json!({
+            "rule": rule.name,
+            "metric": rule.metric.name(),
+            "status": state.status(),
+            "value": state.value,
+            "height": state.height,
+            "since": state.since,
+            "above": rule.above,
+            "below": rule.below,
+        });
+        let res = self
+            .http
+            .post(&self.url)
+            .json(&body)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status());
+        if let Err(e) = res {
+            tracing::error!("could not notify {} of alert {}: {e}", self.url, rule.name);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use tokio_postgres::GenericClient;
+
+use crate::config::AlertMetric;
+use crate::core::types::Height;
+use crate::core::types::NanoERG;
+use crate::core::types::Timestamp;
+use crate::error::EwError;
+use crate::workers::exchanges;
+use crate::workers::network;
+use crate::workers::sigmausd;
+
+/// 24h in milliseconds
+const DAY: Timestamp = 86_400_000;
+
+/// Value of a metric at some height.
+#[derive(Debug, PartialEq)]
+pub(super) struct Reading {
+    pub height: Height,
+    /// Timestamp of the block at `height`
+    pub timestamp: Timestamp,
+    pub value: f64,
+}
+
+/// Latest available value of given `metric`, if any.
+///
+/// Metrics are read from worker stores, so only reflect committed blocks.
+pub(super) async fn read(
+    client: &impl GenericClient,
+    metric: AlertMetric,
+) -> Result<Option<Reading>, EwError> {
+    let (height, value) = match metric {
+        AlertMetric::SigmaUSDReserveRatio => match sigmausd::api::get_state(client).await? {
+            Some(r) => (r.height, reserve_ratio(r.reserves, r.circ_sc, r.oracle)),
+            None => return Ok(None),
+        },
+        AlertMetric::CexSupplyChange24h => {
+            let Some(now) = exchanges::api::get_supply(client).await? else {
+                return Ok(None);
+            };
+            let then = match height_24h_before(client, now.height).await? {
+                Some(h) => exchanges::api::get_supply_at(client, h).await?,
+                None => None,
+            };
+            let change = then.and_then(|then| {
+                percent_change(now.main + now.deposits, then.main + then.deposits)
+            });
+            (now.height, change)
+        }
+        AlertMetric::HashRateChange24h => {
+            let Some((height, now)) = network::api::get_hash_rate(client).await? else {
+                return Ok(None);
+            };
+            let then = match height_24h_before(client, height).await? {
+                Some(h) => network::api::get_hash_rate_at(client, h).await?,
+                None => None,
+            };
+            (height, then.and_then(|then| percent_change(now, then)))
+        }
+    };
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    let Some(timestamp) = crate::core::api::get_timestamp_at(client, height).await? else {
+        return Ok(None);
+    };
+    Ok(Some(Reading {
+        height,
+        timestamp,
+        value,
+    }))
+}
+
+/// Height of last block at least 24h older than block at given `height`.
+async fn height_24h_before(
+    client: &impl GenericClient,
+    height: Height,
+) -> Result<Option<Height>, EwError> {
+    match crate::core::api::get_timestamp_at(client, height).await? {
+        Some(timestamp) => crate::core::api::get_height_at(client, timestamp - DAY).await,
+        None => Ok(None),
+    }
+}
+
+/// SigmaUSD reserve ratio, in percent.
+///
+/// * `reserves` - bank reserves
+/// * `circ_sc` - circulating stable coins, in cents
+/// * `oracle` - nanoERG per USD
+///
+/// None when no stable coins are in circulation.
+fn reserve_ratio(reserves: NanoERG, circ_sc: i64, oracle: i64) -> Option<f64> {
+    let liabilities = circ_sc as f64 / 100.0 * oracle as f64;
+    (liabilities > 0.0).then(|| reserves as f64 / liabilities * 100.0)
+}
+
+/// Change from `then` to `now`, in percent.
+fn percent_change(now: i64, then: i64) -> Option<f64> {
+    (then != 0).then(|| (now - then) as f64 / then as f64 * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve_ratio() {
+        // 1000 ERG backing 500 SigUSD at 2 ERG per USD
+        let rr = reserve_ratio(1_000_000_000_000, 50_000, 2_000_000_000);
+        assert_eq!(rr, Some(100.0));
+        let rr = reserve_ratio(4_000_000_000_000, 50_000, 2_000_000_000);
+        assert_eq!(rr, Some(400.0));
+        assert_eq!(reserve_ratio(1_000_000_000_000, 0, 2_000_000_000), None);
+    }
+
+    #[test]
+    fn test_percent_change() {
+        assert_eq!(percent_change(110, 100), Some(10.0));
+        assert_eq!(percent_change(75, 100), Some(-25.0));
+        assert_eq!(percent_change(75, 0), None);
+    }
+}
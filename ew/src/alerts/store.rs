@@ -0,0 +1,60 @@
+use tokio_postgres::GenericClient;
+
+use super::AlertState;
+use crate::error::EwError;
+use crate::utils::Schema;
+
+/// Alerts don't follow the chain, so have no header to keep track of.
+pub(super) fn schema() -> Schema {
+    Schema::new("alerts", include_str!("store/schema.sql"))
+}
+
+/// Retrieve all states
+pub(super) async fn get_all(client: &impl GenericClient) -> Result<Vec<AlertState>, EwError> {
+    let sql = "
+        select rule
+            , firing
+            , value
+            , height
+            , since
+        from alerts.states
+        order by rule;";
+    Ok(client
+        .query(sql, &[])
+        .await?
+        .iter()
+        .map(|row| AlertState {
+            rule: row.get(0),
+            firing: row.get(1),
+            value: row.get(2),
+            height: row.get(3),
+            since: row.get(4),
+        })
+        .collect())
+}
+
+/// Insert or update a state
+pub(super) async fn upsert(client: &impl GenericClient, state: &AlertState) -> Result<(), EwError> {
+    tracing::trace!("upsert {state:?}");
+    let sql = "
+        insert into alerts.states (rule, firing, value, height, since)
+        values ($1, $2, $3, $4, $5)
+        on conflict (rule) do update
+        set firing = excluded.firing
+            , value = excluded.value
+            , height = excluded.height
+            , since = excluded.since;";
+    client
+        .execute(
+            sql,
+            &[
+                &state.rule,
+                &state.firing,
+                &state.value,
+                &state.height,
+                &state.since,
+            ],
+        )
+        .await?;
+    Ok(())
+}
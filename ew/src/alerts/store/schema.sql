@@ -0,0 +1,20 @@
+create schema alerts;
+comment on schema alerts is 'Alert rule states';
+create table alerts._rev (
+    singleton int primary key default 1,
+    rev_major integer not null,
+    rev_minor integer not null,
+    check(singleton = 1)
+);
+insert into alerts._rev (rev_major, rev_minor) values (1, 0);
+
+create table alerts.states (
+    rule text primary key,
+    firing boolean not null,
+    -- Last evaluated value of the rule's metric
+    value double precision not null,
+    -- Height of the last evaluated value
+    height integer not null,
+    -- Time of last change of state, in milliseconds since epoch
+    since bigint not null
+);
@@ -0,0 +1,261 @@
+//! Query API, serving worker data over HTTP.
+//!
+//! Handlers query worker stores through their `api` modules, so table
+//! layouts are only known to the workers owning them. The OpenAPI document
+//! is derived from the handlers and served on `/openapi.json`.
+mod addresses;
+mod boxes;
+mod counts;
+mod lists;
+mod sigmausd;
+mod supply;
+
+use axum::extract::Extension;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::routing::get;
+use axum::Json;
+use axum::Router;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::Client;
+use tokio_postgres::NoTls;
+use utoipa::IntoParams;
+use utoipa::OpenApi;
+
+use crate::config::PostgresConfig;
+use crate::core::types::AddressID;
+use crate::core::types::AssetID;
+use crate::error::EwError;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "ErgoWatch",
+        description = "All ERG values are expressed in nanoERG, token values in integer form and timestamps in milliseconds since unix epoch.",
+    ),
+    paths(
+        addresses::balance,
+        addresses::balance_at_height,
+        addresses::balance_at_timestamp,
+        boxes::spending,
+        lists::rich_list,
+        counts::p2pk_count,
+        counts::contracts_count,
+        supply::composition,
+        supply::exchanges,
+        supply::token,
+        sigmausd::state,
+    ),
+    components(schemas(
+        boxes::BoxSpending,
+        boxes::SpendingStatus,
+        lists::AddressBalance,
+        supply::SupplyComposition,
+        supply::ExchangesSupply,
+        supply::TokenSupply,
+        sigmausd::SigmaUSDState,
+        ErrorDetail,
+    )),
+    tags(
+        (name = "addresses", description = "Address specific data"),
+        (name = "boxes", description = "Box specific data"),
+        (name = "lists", description = "Rich lists"),
+        (name = "p2pk", description = "P2PK address stats"),
+        (name = "contracts", description = "P2S & P2SH address stats"),
+        (name = "supply", description = "Supply distribution"),
+        (name = "sigmausd", description = "SigmaUSD protocol"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Query API server.
+pub struct QueryApi {
+    pgconf: PostgresConfig,
+    port: u16,
+}
+
+impl QueryApi {
+    pub fn new(pgconf: &PostgresConfig, port: u16) -> Self {
+        Self {
+            pgconf: pgconf.clone(),
+            port,
+        }
+    }
+
+    /// Spawns the server, connecting to postgres on first request.
+    pub fn start(&self) {
+        let app = Router::new()
+            .route("/openapi.json", get(openapi))
+            .route("/addresses/:address/balance", get(addresses::balance))
+            .route(
+                "/addresses/:address/balance/at/height/:height",
+                get(addresses::balance_at_height),
+            )
+            .route(
+                "/addresses/:address/balance/at/timestamp/:timestamp",
+                get(addresses::balance_at_timestamp),
+            )
+            .route("/boxes/:box_id/spending", get(boxes::spending))
+            .route("/lists/addresses/by/balance", get(lists::rich_list))
+            .route("/p2pk/count", get(counts::p2pk_count))
+            .route("/contracts/count", get(counts::contracts_count))
+            .route("/supply/composition", get(supply::composition))
+            .route("/exchanges/supply", get(supply::exchanges))
+            .route("/tokens/:token_id/supply", get(supply::token))
+            .route("/sigmausd/state", get(sigmausd::state))
+            .layer(Extension(Db::new(&self.pgconf)));
+
+        let address = SocketAddr::from(([0, 0, 0, 0], self.port));
+        tokio::spawn(async move {
+            tracing::info!("query api listening on {}", &address);
+            if let Err(e) = axum::Server::bind(&address)
+                .serve(app.into_make_service())
+                .await
+            {
+                tracing::error!("query api stopped: {e}");
+            }
+        });
+    }
+}
+
+async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Postgres client shared by all handlers.
+///
+/// Queries from concurrent requests are pipelined over a single
+/// connection, which is reopened when lost.
+#[derive(Clone)]
+struct Db {
+    pgconf: PostgresConfig,
+    client: Arc<Mutex<Option<Arc<Client>>>>,
+}
+
+impl Db {
+    fn new(pgconf: &PostgresConfig) -> Self {
+        Self {
+            pgconf: pgconf.clone(),
+            client: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    async fn client(&self) -> Result<Arc<Client>, EwError> {
+        let mut client = self.client.lock().await;
+        if let Some(client) = client.as_ref().filter(|c| !c.is_closed()) {
+            return Ok(client.clone());
+        }
+        tracing::debug!("connecting query api to postgres");
+        let (new_client, connection) =
+            tokio_postgres::connect(&self.pgconf.connection_uri, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                tracing::error!("connection error: {}", e);
+            }
+        });
+        let new_client = Arc::new(new_client);
+        *client = Some(new_client.clone());
+        Ok(new_client)
+    }
+}
+
+/// Error response body.
+#[derive(serde::Serialize, utoipa::ToSchema)]
+struct ErrorDetail {
+    detail: String,
+}
+
+#[derive(Debug)]
+enum ApiError {
+    NotFound(&'static str),
+    Invalid(String),
+    Ew(EwError),
+}
+
+impl From<EwError> for ApiError {
+    fn from(e: EwError) -> Self {
+        match &e {
+            // Tables of disabled or not yet started workers
+            EwError::Postgres(pge) if pge.code() == Some(&SqlState::UNDEFINED_TABLE) => {
+                Self::NotFound("Not indexed by this instance")
+            }
+            _ => Self::Ew(e),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, detail) = match self {
+            Self::NotFound(detail) => (StatusCode::NOT_FOUND, detail.to_owned()),
+            Self::Invalid(detail) => (StatusCode::BAD_REQUEST, detail),
+            Self::Ew(e) => {
+                tracing::error!("query api: {e}");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Internal server error".to_owned(),
+                )
+            }
+        };
+        (status, Json(ErrorDetail { detail })).into_response()
+    }
+}
+
+/// Optional token id, to query a token instead of ERG.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct TokenQuery {
+    /// Optional token id
+    token_id: Option<String>,
+}
+
+/// Id of a known `address`.
+async fn address_id(client: &Client, address: &str) -> Result<Option<AddressID>, ApiError> {
+    if address.is_empty() || !address.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(ApiError::Invalid(format!("invalid address `{address}`")));
+    }
+    Ok(crate::core::api::get_address_id(client, &address.to_owned()).await?)
+}
+
+/// Asset id of a known `token_id`.
+async fn asset_id(client: &Client, token_id: &str) -> Result<AssetID, ApiError> {
+    if token_id.len() != 64 || !token_id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ApiError::Invalid(format!("invalid token id `{token_id}`")));
+    }
+    crate::core::api::get_asset_id(client, &token_id.to_owned())
+        .await?
+        .ok_or(ApiError::NotFound("Token not found"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_document() {
+        let doc = ApiDoc::openapi();
+        let paths: Vec<&String> = doc.paths.paths.keys().collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/addresses/{address}/balance",
+                "/addresses/{address}/balance/at/height/{height}",
+                "/addresses/{address}/balance/at/timestamp/{timestamp}",
+                "/boxes/{box_id}/spending",
+                "/contracts/count",
+                "/exchanges/supply",
+                "/lists/addresses/by/balance",
+                "/p2pk/count",
+                "/sigmausd/state",
+                "/supply/composition",
+                "/tokens/{token_id}/supply",
+            ]
+        );
+        assert!(doc.to_json().unwrap().contains("\"token_id\""));
+    }
+}
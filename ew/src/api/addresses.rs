@@ -0,0 +1,130 @@
+use axum::extract::Extension;
+use axum::extract::Path;
+use axum::extract::Query;
+use axum::Json;
+use tokio_postgres::Client;
+
+use super::address_id;
+use super::asset_id;
+use super::ApiError;
+use super::Db;
+use super::TokenQuery;
+use crate::core::types::Height;
+use crate::core::types::Timestamp;
+use crate::framework::store::StoreDef;
+use crate::workers::erg;
+use crate::workers::erg_diffs;
+use crate::workers::timestamps;
+use crate::workers::tokens;
+
+const DETAIL_404: &str = "No balance found";
+const DETAIL_404_HEIGHT: &str = "Height not indexed yet";
+
+/// Current ERG or token balance of an address.
+#[utoipa::path(
+    get,
+    path = "/addresses/{address}/balance",
+    tag = "addresses",
+    params(("address" = String, Path, description = "Base58 address"), TokenQuery),
+    responses(
+        (status = 200, body = i64),
+        (status = 404, body = ErrorDetail, description = "No balance found"),
+    )
+)]
+pub(super) async fn balance(
+    Extension(db): Extension<Db>,
+    Path(address): Path<String>,
+    Query(query): Query<TokenQuery>,
+) -> Result<Json<i64>, ApiError> {
+    let client = db.client().await?;
+    let address_id = address_id(&client, &address)
+        .await?
+        .ok_or(ApiError::NotFound(DETAIL_404))?;
+    let balance = match query.token_id {
+        None => erg::api::get_balance(client.as_ref(), address_id).await?,
+        Some(token_id) => {
+            let asset_id = asset_id(&client, &token_id).await?;
+            tokens::api::get_balance(client.as_ref(), address_id, asset_id).await?
+        }
+    };
+    balance.map(Json).ok_or(ApiError::NotFound(DETAIL_404))
+}
+
+/// ERG or token balance of an address at a given height.
+#[utoipa::path(
+    get,
+    path = "/addresses/{address}/balance/at/height/{height}",
+    tag = "addresses",
+    params(
+        ("address" = String, Path, description = "Base58 address"),
+        ("height" = i32, Path, minimum = 0),
+        TokenQuery,
+    ),
+    responses(
+        (status = 200, body = i64),
+        (status = 404, body = ErrorDetail, description = "No balance found, or height not indexed yet"),
+    )
+)]
+pub(super) async fn balance_at_height(
+    Extension(db): Extension<Db>,
+    Path((address, height)): Path<(String, Height)>,
+    Query(query): Query<TokenQuery>,
+) -> Result<Json<i64>, ApiError> {
+    if height < 0 {
+        return Err(ApiError::Invalid(format!("invalid height {height}")));
+    }
+    let client = db.client().await?;
+    let address_id = address_id(&client, &address)
+        .await?
+        .ok_or(ApiError::NotFound(DETAIL_404))?;
+    let balance = match query.token_id {
+        None => {
+            ensure_indexed(&client, &erg_diffs::SCHEMA, height).await?;
+            erg_diffs::api::get_balance_at(client.as_ref(), address_id, height).await?
+        }
+        Some(token_id) => {
+            let asset_id = asset_id(&client, &token_id).await?;
+            ensure_indexed(&client, &tokens::SCHEMA, height).await?;
+            tokens::api::get_balance_at(client.as_ref(), address_id, asset_id, height).await?
+        }
+    };
+    balance.map(Json).ok_or(ApiError::NotFound(DETAIL_404))
+}
+
+/// ERG or token balance of an address at a given timestamp.
+#[utoipa::path(
+    get,
+    path = "/addresses/{address}/balance/at/timestamp/{timestamp}",
+    tag = "addresses",
+    params(
+        ("address" = String, Path, description = "Base58 address"),
+        ("timestamp" = i64, Path, description = "Milliseconds since unix epoch", minimum = 1),
+        TokenQuery,
+    ),
+    responses(
+        (status = 200, body = i64),
+        (status = 404, body = ErrorDetail, description = "No balance found, or height at timestamp not indexed yet"),
+    )
+)]
+pub(super) async fn balance_at_timestamp(
+    Extension(db): Extension<Db>,
+    Path((address, timestamp)): Path<(String, Timestamp)>,
+    Query(query): Query<TokenQuery>,
+) -> Result<Json<i64>, ApiError> {
+    if timestamp <= 0 {
+        return Err(ApiError::Invalid(format!("invalid timestamp {timestamp}")));
+    }
+    let client = db.client().await?;
+    let height = timestamps::api::get_height_at(client.as_ref(), timestamp)
+        .await?
+        .ok_or(ApiError::NotFound(DETAIL_404))?;
+    balance_at_height(Extension(db), Path((address, height)), Query(query)).await
+}
+
+/// Errors if `height` is past the last block processed by the worker of `store`.
+async fn ensure_indexed(client: &Client, store: &StoreDef, height: Height) -> Result<(), ApiError> {
+    match store.get_height(client).await? {
+        Some(h) if h >= height => Ok(()),
+        _ => Err(ApiError::NotFound(DETAIL_404_HEIGHT)),
+    }
+}
@@ -0,0 +1,73 @@
+use axum::extract::Extension;
+use axum::extract::Path;
+use axum::Json;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::ApiError;
+use super::Db;
+use crate::core::types::Height;
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum SpendingStatus {
+    Spent,
+    Unspent,
+    /// Box created before transactions were indexed and not spent since
+    Unknown,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(super) struct BoxSpending {
+    status: SpendingStatus,
+    /// Spending transaction, if spent
+    tx_id: Option<String>,
+    /// Height of spending transaction, if spent
+    height: Option<Height>,
+}
+
+/// Transaction spending a box, if any.
+#[utoipa::path(
+    get,
+    path = "/boxes/{box_id}/spending",
+    tag = "boxes",
+    params(("box_id" = String, Path, description = "Base16 box id")),
+    responses(
+        (status = 200, body = BoxSpending),
+        (status = 404, body = ErrorDetail, description = "Box not found"),
+    )
+)]
+pub(super) async fn spending(
+    Extension(db): Extension<Db>,
+    Path(box_id): Path<String>,
+) -> Result<Json<BoxSpending>, ApiError> {
+    if box_id.len() != 64 || !box_id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ApiError::Invalid(format!("invalid box id `{box_id}`")));
+    }
+    let client = db.client().await?;
+    let record = crate::core::api::get_box_spending(client.as_ref(), &box_id)
+        .await?
+        .ok_or(ApiError::NotFound("Box not found"))?;
+    let spending = match record.spent_by {
+        Some((tx_id, height)) => BoxSpending {
+            status: SpendingStatus::Spent,
+            tx_id: Some(tx_id),
+            height: Some(height),
+        },
+        None => {
+            // Boxes created before transactions were indexed may have been
+            // spent before too, without a record of it.
+            let since = crate::core::api::get_transactions_since(client.as_ref()).await?;
+            BoxSpending {
+                status: if record.height < since {
+                    SpendingStatus::Unknown
+                } else {
+                    SpendingStatus::Unspent
+                },
+                tx_id: None,
+                height: None,
+            }
+        }
+    };
+    Ok(Json(spending))
+}
@@ -0,0 +1,86 @@
+use axum::extract::Extension;
+use axum::extract::Query;
+use axum::Json;
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use super::asset_id;
+use super::ApiError;
+use super::Db;
+use crate::workers::erg;
+use crate::workers::tokens;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(super) struct CountQuery {
+    /// Optional token id
+    token_id: Option<String>,
+    /// Only count addresses with a balance greater or equal to *bal_ge*
+    #[param(minimum = 0)]
+    bal_ge: Option<i64>,
+    /// Only count addresses with a balance lower than *bal_lt*
+    #[param(minimum = 0)]
+    bal_lt: Option<i64>,
+}
+
+/// Current number of P2PK addresses holding ERG or a token.
+#[utoipa::path(
+    get,
+    path = "/p2pk/count",
+    tag = "p2pk",
+    params(CountQuery),
+    responses(
+        (status = 200, body = i64),
+        (status = 404, body = ErrorDetail, description = "Token not found"),
+    )
+)]
+pub(super) async fn p2pk_count(
+    Extension(db): Extension<Db>,
+    Query(query): Query<CountQuery>,
+) -> Result<Json<i64>, ApiError> {
+    count(db, query, true).await
+}
+
+/// Current number of contract addresses holding ERG or a token.
+#[utoipa::path(
+    get,
+    path = "/contracts/count",
+    tag = "contracts",
+    params(CountQuery),
+    responses(
+        (status = 200, body = i64),
+        (status = 404, body = ErrorDetail, description = "Token not found"),
+    )
+)]
+pub(super) async fn contracts_count(
+    Extension(db): Extension<Db>,
+    Query(query): Query<CountQuery>,
+) -> Result<Json<i64>, ApiError> {
+    count(db, query, false).await
+}
+
+async fn count(db: Db, query: CountQuery, p2pk: bool) -> Result<Json<i64>, ApiError> {
+    for bound in [query.bal_ge, query.bal_lt].into_iter().flatten() {
+        if bound < 0 {
+            return Err(ApiError::Invalid(format!("invalid balance bound {bound}")));
+        }
+    }
+    let client = db.client().await?;
+    let count = match query.token_id {
+        None => {
+            erg::api::count_addresses(client.as_ref(), p2pk, query.bal_ge, query.bal_lt).await?
+        }
+        Some(token_id) => {
+            let asset_id = asset_id(&client, &token_id).await?;
+            tokens::api::count_addresses(
+                client.as_ref(),
+                asset_id,
+                p2pk,
+                query.bal_ge,
+                query.bal_lt,
+            )
+            .await?
+        }
+    };
+    Ok(Json(count))
+}
@@ -0,0 +1,64 @@
+use axum::extract::Extension;
+use axum::extract::Query;
+use axum::Json;
+use serde::Deserialize;
+use serde::Serialize;
+use utoipa::IntoParams;
+use utoipa::ToSchema;
+
+use super::asset_id;
+use super::ApiError;
+use super::Db;
+use crate::workers::erg;
+use crate::workers::tokens;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(super) struct RichListQuery {
+    /// Optional token id
+    token_id: Option<String>,
+    /// Number of addresses to return
+    #[param(minimum = 1, maximum = 10000, default = 100)]
+    limit: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(super) struct AddressBalance {
+    address: String,
+    balance: i64,
+}
+
+/// Addresses with largest ERG or token balance. Does not include
+/// (re-)emission contracts.
+#[utoipa::path(
+    get,
+    path = "/lists/addresses/by/balance",
+    tag = "lists",
+    params(RichListQuery),
+    responses(
+        (status = 200, body = [AddressBalance]),
+        (status = 404, body = ErrorDetail, description = "Token not found"),
+    )
+)]
+pub(super) async fn rich_list(
+    Extension(db): Extension<Db>,
+    Query(query): Query<RichListQuery>,
+) -> Result<Json<Vec<AddressBalance>>, ApiError> {
+    let limit = query.limit.unwrap_or(100);
+    if !(1..=10000).contains(&limit) {
+        return Err(ApiError::Invalid(format!("invalid limit {limit}")));
+    }
+    let client = db.client().await?;
+    let rows = match query.token_id {
+        None => erg::api::get_rich_list(client.as_ref(), limit).await?,
+        Some(token_id) => {
+            let asset_id = asset_id(&client, &token_id).await?;
+            tokens::api::get_rich_list(client.as_ref(), asset_id, limit).await?
+        }
+    };
+    Ok(Json(
+        rows.into_iter()
+            .map(|(address, balance)| AddressBalance { address, balance })
+            .collect(),
+    ))
+}
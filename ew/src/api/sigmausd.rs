@@ -0,0 +1,47 @@
+use axum::extract::Extension;
+use axum::Json;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::ApiError;
+use super::Db;
+use crate::core::types::Height;
+use crate::workers::sigmausd;
+
+#[derive(Serialize, ToSchema)]
+pub(super) struct SigmaUSDState {
+    height: Height,
+    /// Total nanoERG in reserves
+    reserves: i64,
+    /// Circulating (i.e. minted) SigUSD
+    circ_sigusd: f64,
+    /// Circulating (i.e. minted) SigRSV
+    circ_sigrsv: i64,
+    /// ERG/USD oracle peg rate (nanoERG equivalent to 1 USD)
+    peg_rate_nano: i64,
+}
+
+/// Current SigmaUSD contract state.
+#[utoipa::path(
+    get,
+    path = "/sigmausd/state",
+    tag = "sigmausd",
+    responses(
+        (status = 200, body = SigmaUSDState),
+        (status = 404, body = ErrorDetail, description = "No state yet"),
+    )
+)]
+pub(super) async fn state(Extension(db): Extension<Db>) -> Result<Json<SigmaUSDState>, ApiError> {
+    let client = db.client().await?;
+    let record = sigmausd::api::get_state(client.as_ref())
+        .await?
+        .ok_or(ApiError::NotFound("No state yet"))?;
+    Ok(Json(SigmaUSDState {
+        height: record.height,
+        reserves: record.reserves,
+        // SigUSD has 2 decimals
+        circ_sigusd: record.circ_sc as f64 / 100.,
+        circ_sigrsv: record.circ_rc,
+        peg_rate_nano: record.oracle,
+    }))
+}
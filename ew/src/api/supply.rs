@@ -0,0 +1,119 @@
+use axum::extract::Extension;
+use axum::extract::Path;
+use axum::Json;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::asset_id;
+use super::ApiError;
+use super::Db;
+use crate::core::types::Height;
+use crate::workers::erg;
+use crate::workers::exchanges;
+use crate::workers::tokens;
+
+#[derive(Serialize, ToSchema)]
+pub(super) struct SupplyComposition {
+    height: Height,
+    /// Supply on all P2PK addresses, including exchanges
+    p2pks: i64,
+    /// Supply on non-mining contracts, excluding (re-)emission
+    contracts: i64,
+    /// Supply on mining contracts
+    miners: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(super) struct ExchangesSupply {
+    height: Height,
+    /// Supply on known exchange main addresses
+    main: i64,
+    /// Supply on exchange deposit addresses
+    deposits: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(super) struct TokenSupply {
+    /// Total amount ever minted, sum of other three
+    emitted: i64,
+    in_p2pks: i64,
+    in_contracts: i64,
+    burned: i64,
+}
+
+/// Current distribution of circulating ERG supply across address types.
+#[utoipa::path(
+    get,
+    path = "/supply/composition",
+    tag = "supply",
+    responses(
+        (status = 200, body = SupplyComposition),
+        (status = 404, body = ErrorDetail, description = "No supply yet"),
+    )
+)]
+pub(super) async fn composition(
+    Extension(db): Extension<Db>,
+) -> Result<Json<SupplyComposition>, ApiError> {
+    let client = db.client().await?;
+    let record = erg::api::get_supply_composition(&client)
+        .await?
+        .ok_or(ApiError::NotFound("No supply yet"))?;
+    Ok(Json(SupplyComposition {
+        height: record.height,
+        p2pks: record.p2pks,
+        contracts: record.contracts,
+        miners: record.miners,
+    }))
+}
+
+/// Current ERG supply held by tracked exchanges.
+#[utoipa::path(
+    get,
+    path = "/exchanges/supply",
+    tag = "supply",
+    responses(
+        (status = 200, body = ExchangesSupply),
+        (status = 404, body = ErrorDetail, description = "No supply yet"),
+    )
+)]
+pub(super) async fn exchanges(
+    Extension(db): Extension<Db>,
+) -> Result<Json<ExchangesSupply>, ApiError> {
+    let client = db.client().await?;
+    let record = exchanges::api::get_supply(client.as_ref())
+        .await?
+        .ok_or(ApiError::NotFound("No supply yet"))?;
+    Ok(Json(ExchangesSupply {
+        height: record.height,
+        main: record.main,
+        deposits: record.deposits,
+    }))
+}
+
+/// Token supply breakdown.
+#[utoipa::path(
+    get,
+    path = "/tokens/{token_id}/supply",
+    tag = "supply",
+    params(("token_id" = String, Path, description = "Token id")),
+    responses(
+        (status = 200, body = TokenSupply),
+        (status = 404, body = ErrorDetail, description = "Token not found"),
+    )
+)]
+pub(super) async fn token(
+    Extension(db): Extension<Db>,
+    Path(token_id): Path<String>,
+) -> Result<Json<TokenSupply>, ApiError> {
+    let client = db.client().await?;
+    let asset_id = asset_id(&client, &token_id).await?;
+    let supply = tokens::api::get_supply(client.as_ref(), asset_id)
+        .await?
+        .ok_or(ApiError::NotFound("Token not found"))?;
+    Ok(Json(TokenSupply {
+        emitted: supply.emitted,
+        in_p2pks: supply.in_p2pks,
+        in_contracts: supply.in_contracts,
+        burned: supply.burned,
+    }))
+}
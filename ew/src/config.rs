@@ -1,13 +1,1515 @@
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::core::types::Height;
+use crate::core::types::NanoERG;
+use crate::workers::registry::Registry;
+use crate::workers::registry::WorkerID;
+
+/// Config file used when `EW_CONFIG` is not set.
+pub const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+/// Environment variable pointing to a config file.
+pub const ENV_CONFIG: &str = "EW_CONFIG";
+/// Environment variable overriding `postgres.uri`.
+pub const ENV_POSTGRES_URI: &str = "EW_POSTGRES_URI";
+/// Environment variable overriding `node.url`.
+pub const ENV_NODE_URL: &str = "EW_NODE_URL";
+/// Environment variable overriding `node.api_key`.
+pub const ENV_NODE_API_KEY: &str = "EW_NODE_API_KEY";
+/// Environment variable overriding `monitor.port`.
+pub const ENV_MONITOR_PORT: &str = "EW_MONITOR_PORT";
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Could not read config file `{0}`: {1}")]
+    Read(String, std::io::Error),
+    #[error("Could not parse config file `{0}`: {1}")]
+    Parse(String, toml::de::Error),
+    #[error(
+        "Missing setting `{0}`. Set it in the config file or through the {1} environment variable."
+    )]
+    Missing(&'static str, &'static str),
+    #[error("Invalid value for `{0}`: {1}")]
+    Invalid(&'static str, String),
+}
+
 #[derive(Debug, Clone)]
 pub struct PostgresConfig {
     /// Postgresql connection URI postgresql://[userspec@][hostspec][/dbname][?paramspec]
     pub connection_uri: String,
+    /// Maximum number of blocks committed at once by workers catching up.
+    pub batch_blocks: usize,
 }
 
 impl PostgresConfig {
     pub fn new(uri: &str) -> Self {
         Self {
             connection_uri: uri.to_owned(),
+            batch_blocks: 1,
+        }
+    }
+}
+
+/// Full ew configuration.
+///
+/// Loaded from a TOML file, with some settings overridable through
+/// environment variables. See `config.example.toml` for all options.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub postgres: PostgresConfig,
+    /// Preferred node.
+    pub node: NodeConfig,
+    /// Nodes to fail over to, in config file order.
+    pub fallback_nodes: Vec<NodeConfig>,
+    pub tracker: TrackerConfig,
+    pub monitor: MonitorConfig,
+    pub api: ApiConfig,
+    pub coingecko: CoingeckoConfig,
+    pub webhooks: WebhooksConfig,
+    pub mempool: MempoolConfig,
+    pub alerts: AlertsConfig,
+    pub export: ExportConfig,
+    /// Workers to be started.
+    pub workers: Registry,
+    /// Maximum number of blocks that can be rolled back.
+    pub rollback_horizon: Height,
+}
+
+#[derive(Debug, Clone)]
+pub struct NodeConfig {
+    /// Node API url, e.g. http://localhost:9053
+    pub url: String,
+    /// Node API key, sent with every request if set.
+    pub api_key: Option<String>,
+    /// Maximum duration of a request.
+    pub timeout: Duration,
+    /// Maximum duration to establish a connection.
+    pub connect_timeout: Duration,
+    /// Number of times a request is retried after a connection or server error.
+    pub max_retries: u32,
+    /// Delay before first retry, doubled for each next one.
+    pub retry_delay: Duration,
+    /// Nodes with a lower value are preferred.
+    pub priority: u32,
+    /// Directory to save API responses to, as test fixtures.
+    pub record_dir: Option<PathBuf>,
+}
+
+impl NodeConfig {
+    /// Config for node at given `url`, using default settings.
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_owned(),
+            api_key: None,
+            timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(5),
+            max_retries: 5,
+            retry_delay: Duration::from_millis(500),
+            priority: 0,
+            record_dir: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TrackerConfig {
+    /// Time to wait before polling the node for new blocks again.
+    pub polling_interval: Duration,
+    /// Number of ergo trees kept in core's address cache.
+    pub address_cache_size: usize,
+    /// Number of token id's kept in core's asset cache.
+    pub asset_cache_size: usize,
+    /// Number of blocks a node can be behind the best known node
+    /// before the tracker fails over to another one.
+    pub max_node_lag: Height,
+    /// Number of blocks requested concurrently when syncing blocks beyond
+    /// the rollback horizon. 1 fetches blocks one by one.
+    pub prefetch_blocks: usize,
+}
+
+impl Default for TrackerConfig {
+    fn default() -> Self {
+        Self {
+            polling_interval: Duration::from_millis(5000),
+            address_cache_size: 5000,
+            asset_cache_size: 5000,
+            max_node_lag: 3,
+            prefetch_blocks: 16,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MonitorConfig {
+    /// Port the monitor server listens on.
+    pub port: u16,
+    /// Number of blocks a worker can be behind the tracker before
+    /// readiness checks fail.
+    pub max_lag: Height,
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            port: 3005,
+            max_lag: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ApiConfig {
+    /// Port the query API listens on. The API is not served if not set.
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CoingeckoConfig {
+    /// CoinGecko market_chart/range endpoint.
+    pub url: String,
+    /// Time to wait between two polls of the CoinGecko api.
+    pub polling_interval: Duration,
+}
+
+impl Default for CoingeckoConfig {
+    fn default() -> Self {
+        Self {
+            url: "https://api.coingecko.com/api/v3/coins/ergo/market_chart/range".to_owned(),
+            polling_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhooksConfig {
+    /// Rules to notify webhooks for.
+    pub rules: Vec<WebhookRule>,
+    /// Time to wait between two delivery rounds of pending notifications.
+    pub polling_interval: Duration,
+    /// Maximum duration of a webhook request.
+    pub timeout: Duration,
+    /// Blocks older than this are not evaluated, so that syncing past
+    /// blocks doesn't send any notifications.
+    pub max_block_age: Duration,
+}
+
+impl Default for WebhooksConfig {
+    fn default() -> Self {
+        Self {
+            rules: vec![],
+            polling_interval: Duration::from_secs(5),
+            timeout: Duration::from_secs(10),
+            max_block_age: Duration::from_secs(3600),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MempoolConfig {
+    /// Time to wait between two polls of the node's unconfirmed transactions.
+    pub polling_interval: Duration,
+    /// Number of ergo trees to keep address id's of.
+    pub address_cache_size: usize,
+}
+
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        Self {
+            polling_interval: Duration::from_secs(5),
+            address_cache_size: 5000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookRule {
+    /// Identifies the rule in notifications.
+    pub name: String,
+    /// Url notifications are POSTed to.
+    pub url: String,
+    pub condition: RuleCondition,
+}
+
+/// On-chain events a webhook rule matches.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleCondition {
+    /// An address receiving at least `min_nano` in a single transaction
+    Transfer { min_nano: NanoERG },
+    /// A SigmaUSD mint or redeem moving at least `min_nano` in or out of the bank
+    SigmaUSD { min_nano: NanoERG },
+    /// A new governance proposal
+    Proposal,
+    /// A new CEX deposit address
+    CexDeposit,
+}
+
+impl RuleCondition {
+    /// Worker evaluating the rule.
+    pub fn worker(&self) -> WorkerID {
+        match self {
+            RuleCondition::CexDeposit => WorkerID::WebhooksDiffs,
+            _ => WorkerID::Webhooks,
         }
     }
 }
+
+#[derive(Debug, Clone)]
+pub struct AlertsConfig {
+    /// Rules to evaluate after each block of the workers they depend on.
+    pub rules: Vec<AlertRule>,
+    /// Url alert changes are POSTed to. Alert changes are only logged if none.
+    pub notify_url: Option<String>,
+    /// Maximum duration of a notification request.
+    pub timeout: Duration,
+    /// Metrics of blocks older than this are not evaluated, so that
+    /// syncing past blocks doesn't fire any alerts.
+    pub max_block_age: Duration,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self {
+            rules: vec![],
+            notify_url: None,
+            timeout: Duration::from_secs(10),
+            max_block_age: Duration::from_secs(3600),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertRule {
+    /// Identifies the rule in alert state and notifications.
+    pub name: String,
+    pub metric: AlertMetric,
+    /// Fire when the metric goes above this value.
+    pub above: Option<f64>,
+    /// Fire when the metric goes below this value.
+    pub below: Option<f64>,
+}
+
+/// Worker time series alert rules can watch.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlertMetric {
+    /// SigmaUSD bank reserves over stable coin liabilities, in percent
+    SigmaUSDReserveRatio,
+    /// Change in ERG held by exchanges over the last 24h, in percent
+    CexSupplyChange24h,
+    /// Change in 24h mean hash rate over the last 24h, in percent
+    HashRateChange24h,
+}
+
+impl AlertMetric {
+    pub const ALL: [AlertMetric; 3] = [
+        AlertMetric::SigmaUSDReserveRatio,
+        AlertMetric::CexSupplyChange24h,
+        AlertMetric::HashRateChange24h,
+    ];
+
+    /// Name used in config files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AlertMetric::SigmaUSDReserveRatio => "sigmausd_reserve_ratio",
+            AlertMetric::CexSupplyChange24h => "cex_supply_change_24h",
+            AlertMetric::HashRateChange24h => "hash_rate_change_24h",
+        }
+    }
+
+    /// Worker maintaining the time series.
+    pub fn worker(&self) -> WorkerID {
+        match self {
+            AlertMetric::SigmaUSDReserveRatio => WorkerID::SigmaUSD,
+            AlertMetric::CexSupplyChange24h => WorkerID::Exchanges,
+            AlertMetric::HashRateChange24h => WorkerID::Network,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportConfig {
+    /// Directory files get exported to. Export workers are enabled by
+    /// default when set.
+    pub dir: Option<PathBuf>,
+    /// Number of blocks per file.
+    pub partition_size: Height,
+    /// Convert completed partitions to Parquet.
+    pub parquet: bool,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            partition_size: 10_000,
+            parquet: true,
+        }
+    }
+}
+
+/// Config file layout.
+///
+/// Every setting is optional at this stage. Missing required settings
+/// are reported once environment variables have been applied.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    postgres: PostgresSection,
+    #[serde(default)]
+    node: NodeSection,
+    #[serde(default)]
+    tracker: TrackerSection,
+    #[serde(default)]
+    monitor: MonitorSection,
+    #[serde(default)]
+    api: ApiSection,
+    #[serde(default)]
+    coingecko: CoingeckoSection,
+    #[serde(default)]
+    webhooks: WebhooksSection,
+    #[serde(default)]
+    mempool: MempoolSection,
+    #[serde(default)]
+    alerts: AlertsSection,
+    #[serde(default)]
+    export: ExportSection,
+    #[serde(default)]
+    workers: WorkersSection,
+    #[serde(default)]
+    settings: SettingsSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PostgresSection {
+    uri: Option<String>,
+    batch_blocks: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NodeSection {
+    url: Option<String>,
+    api_key: Option<String>,
+    /// Milliseconds
+    timeout: Option<u64>,
+    /// Milliseconds
+    connect_timeout: Option<u64>,
+    max_retries: Option<u32>,
+    /// Milliseconds
+    retry_delay: Option<u64>,
+    record_dir: Option<PathBuf>,
+    #[serde(default)]
+    fallbacks: Vec<FallbackNodeSection>,
+}
+
+/// Node to fail over to, sharing the main node's client settings.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FallbackNodeSection {
+    url: String,
+    /// Defaults to the main node's api key.
+    api_key: Option<String>,
+    /// Defaults to position in fallbacks list, starting at 1.
+    priority: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TrackerSection {
+    /// Milliseconds
+    polling_interval: Option<u64>,
+    address_cache_size: Option<usize>,
+    asset_cache_size: Option<usize>,
+    max_node_lag: Option<Height>,
+    prefetch_blocks: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MonitorSection {
+    port: Option<u16>,
+    max_lag: Option<Height>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApiSection {
+    port: Option<u16>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CoingeckoSection {
+    url: Option<String>,
+    /// Seconds
+    polling_interval: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WebhooksSection {
+    /// Milliseconds
+    polling_interval: Option<u64>,
+    /// Milliseconds
+    timeout: Option<u64>,
+    /// Seconds
+    max_block_age: Option<u64>,
+    #[serde(default)]
+    rules: Vec<WebhookRuleSection>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MempoolSection {
+    /// Milliseconds
+    polling_interval: Option<u64>,
+    address_cache_size: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WebhookRuleSection {
+    /// Defaults to `kind`.
+    name: Option<String>,
+    url: String,
+    /// One of `transfer`, `sigmausd`, `proposal` or `cex_deposit`.
+    kind: String,
+    /// Required by `transfer` and `sigmausd` rules.
+    min_nano: Option<NanoERG>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AlertsSection {
+    notify_url: Option<String>,
+    /// Milliseconds
+    timeout: Option<u64>,
+    /// Seconds
+    max_block_age: Option<u64>,
+    #[serde(default)]
+    rules: Vec<AlertRuleSection>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AlertRuleSection {
+    name: String,
+    /// One of `AlertMetric` names.
+    metric: String,
+    above: Option<f64>,
+    below: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExportSection {
+    dir: Option<PathBuf>,
+    /// Blocks
+    partition_size: Option<Height>,
+    parquet: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WorkersSection {
+    /// Workers to start, all of them if omitted.
+    enabled: Option<Vec<String>>,
+    /// Workers not to start.
+    disabled: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SettingsSection {
+    rollback_horizon: Option<Height>,
+}
+
+impl Config {
+    /// Load config from file and environment.
+    ///
+    /// The config file is read from the path in `EW_CONFIG` if set, or
+    /// from `config.toml` if present. Environment variables take
+    /// precedence over values from the file.
+    pub fn load() -> Result<Self, ConfigError> {
+        let file = match std::env::var(ENV_CONFIG) {
+            Ok(path) => Some(read_file(&path)?),
+            Err(_) => match std::path::Path::new(DEFAULT_CONFIG_PATH).exists() {
+                true => Some(read_file(DEFAULT_CONFIG_PATH)?),
+                false => None,
+            },
+        };
+        Self::build(file.unwrap_or_default(), |key| std::env::var(key).ok())
+    }
+
+    /// Parse config from a TOML string, ignoring the environment.
+    pub fn from_toml(toml: &str) -> Result<Self, ConfigError> {
+        let file = parse("<string>", toml)?;
+        Self::build(file, |_| None)
+    }
+
+    /// Merge file settings with environment and validate.
+    ///
+    /// * `file` - settings read from config file
+    /// * `env` - environment variables lookup
+    fn build(file: ConfigFile, env: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let uri = env(ENV_POSTGRES_URI)
+            .or(file.postgres.uri)
+            .ok_or(ConfigError::Missing("postgres.uri", ENV_POSTGRES_URI))?;
+        if !(uri.starts_with("postgresql://") || uri.starts_with("postgres://")) {
+            return Err(ConfigError::Invalid(
+                "postgres.uri",
+                "expected a postgresql://[userspec@][hostspec][/dbname][?paramspec] uri".to_owned(),
+            ));
+        }
+        let postgres = PostgresConfig {
+            batch_blocks: file.postgres.batch_blocks.unwrap_or(1),
+            ..PostgresConfig::new(&uri)
+        };
+        validate_non_zero("postgres.batch_blocks", postgres.batch_blocks as u128)?;
+
+        let node_url = env(ENV_NODE_URL)
+            .or(file.node.url)
+            .ok_or(ConfigError::Missing("node.url", ENV_NODE_URL))?;
+        validate_url("node.url", &node_url)?;
+        let node_defaults = NodeConfig::new(&node_url);
+        let node = NodeConfig {
+            api_key: env(ENV_NODE_API_KEY).or(file.node.api_key),
+            timeout: file
+                .node
+                .timeout
+                .map(Duration::from_millis)
+                .unwrap_or(node_defaults.timeout),
+            connect_timeout: file
+                .node
+                .connect_timeout
+                .map(Duration::from_millis)
+                .unwrap_or(node_defaults.connect_timeout),
+            max_retries: file.node.max_retries.unwrap_or(node_defaults.max_retries),
+            retry_delay: file
+                .node
+                .retry_delay
+                .map(Duration::from_millis)
+                .unwrap_or(node_defaults.retry_delay),
+            record_dir: file.node.record_dir,
+            ..node_defaults
+        };
+        validate_api_key("node.api_key", &node.api_key)?;
+        validate_non_zero("node.timeout", node.timeout.as_millis())?;
+        validate_non_zero("node.connect_timeout", node.connect_timeout.as_millis())?;
+        validate_non_zero("node.retry_delay", node.retry_delay.as_millis())?;
+
+        let mut fallback_nodes = vec![];
+        for (i, fallback) in file.node.fallbacks.into_iter().enumerate() {
+            validate_url("node.fallbacks.url", &fallback.url)?;
+            if fallback.url == node.url
+                || fallback_nodes
+                    .iter()
+                    .any(|n: &NodeConfig| n.url == fallback.url)
+            {
+                return Err(ConfigError::Invalid(
+                    "node.fallbacks.url",
+                    format!("`{}` is configured more than once", fallback.url),
+                ));
+            }
+            let api_key = fallback.api_key.or(node.api_key.clone());
+            validate_api_key("node.fallbacks.api_key", &api_key)?;
+            fallback_nodes.push(NodeConfig {
+                url: fallback.url,
+                api_key,
+                priority: fallback.priority.unwrap_or(i as u32 + 1),
+                ..node.clone()
+            });
+        }
+
+        let port = match env(ENV_MONITOR_PORT) {
+            Some(s) => s.parse::<u16>().map_err(|e| {
+                ConfigError::Invalid("monitor.port", format!("{ENV_MONITOR_PORT}={s} ({e})"))
+            })?,
+            None => file.monitor.port.unwrap_or(MonitorConfig::default().port),
+        };
+        let max_lag = file
+            .monitor
+            .max_lag
+            .unwrap_or(MonitorConfig::default().max_lag);
+        if max_lag < 0 {
+            return Err(ConfigError::Invalid(
+                "monitor.max_lag",
+                format!("must not be negative, got {max_lag}"),
+            ));
+        }
+
+        let tracker_defaults = TrackerConfig::default();
+        let tracker = TrackerConfig {
+            polling_interval: file
+                .tracker
+                .polling_interval
+                .map(Duration::from_millis)
+                .unwrap_or(tracker_defaults.polling_interval),
+            address_cache_size: file
+                .tracker
+                .address_cache_size
+                .unwrap_or(tracker_defaults.address_cache_size),
+            asset_cache_size: file
+                .tracker
+                .asset_cache_size
+                .unwrap_or(tracker_defaults.asset_cache_size),
+            max_node_lag: file
+                .tracker
+                .max_node_lag
+                .unwrap_or(tracker_defaults.max_node_lag),
+            prefetch_blocks: file
+                .tracker
+                .prefetch_blocks
+                .unwrap_or(tracker_defaults.prefetch_blocks),
+        };
+        validate_non_zero(
+            "tracker.polling_interval",
+            tracker.polling_interval.as_millis(),
+        )?;
+        validate_non_zero(
+            "tracker.address_cache_size",
+            tracker.address_cache_size as u128,
+        )?;
+        validate_non_zero("tracker.asset_cache_size", tracker.asset_cache_size as u128)?;
+        validate_non_zero("tracker.prefetch_blocks", tracker.prefetch_blocks as u128)?;
+        if tracker.max_node_lag < 0 {
+            return Err(ConfigError::Invalid(
+                "tracker.max_node_lag",
+                format!("must not be negative, got {}", tracker.max_node_lag),
+            ));
+        }
+
+        let coingecko_defaults = CoingeckoConfig::default();
+        let coingecko = CoingeckoConfig {
+            url: file.coingecko.url.unwrap_or(coingecko_defaults.url),
+            polling_interval: file
+                .coingecko
+                .polling_interval
+                .map(Duration::from_secs)
+                .unwrap_or(coingecko_defaults.polling_interval),
+        };
+        validate_url("coingecko.url", &coingecko.url)?;
+        validate_non_zero(
+            "coingecko.polling_interval",
+            coingecko.polling_interval.as_secs() as u128,
+        )?;
+
+        let webhooks = build_webhooks(file.webhooks)?;
+
+        let mempool_defaults = MempoolConfig::default();
+        let mempool = MempoolConfig {
+            polling_interval: file
+                .mempool
+                .polling_interval
+                .map(Duration::from_millis)
+                .unwrap_or(mempool_defaults.polling_interval),
+            address_cache_size: file
+                .mempool
+                .address_cache_size
+                .unwrap_or(mempool_defaults.address_cache_size),
+        };
+        validate_non_zero(
+            "mempool.polling_interval",
+            mempool.polling_interval.as_millis(),
+        )?;
+        validate_non_zero(
+            "mempool.address_cache_size",
+            mempool.address_cache_size as u128,
+        )?;
+
+        // Opt-in workers get enabled by default when configured
+        let mut opted_in: Vec<WorkerID> = webhooks
+            .rules
+            .iter()
+            .map(|r| r.condition.worker())
+            .collect();
+        if file.export.dir.is_some() {
+            opted_in.extend(WorkerID::EXPORT);
+        }
+        let workers = build_registry(file.workers, &opted_in)?;
+
+        for rule in &webhooks.rules {
+            let worker = rule.condition.worker();
+            if !workers.is_enabled(worker) {
+                return Err(ConfigError::Invalid(
+                    "webhooks.rules.kind",
+                    format!("`{}` requires the {worker} worker", rule.name),
+                ));
+            }
+        }
+
+        let export = build_export(file.export, &workers)?;
+
+        let alerts = build_alerts(file.alerts, &workers)?;
+
+        let rollback_horizon = file
+            .settings
+            .rollback_horizon
+            .unwrap_or(crate::constants::settings::ROLLBACK_HORIZON);
+        if rollback_horizon < 1 {
+            return Err(ConfigError::Invalid(
+                "settings.rollback_horizon",
+                format!("must be at least 1, got {rollback_horizon}"),
+            ));
+        }
+
+        Ok(Self {
+            postgres,
+            node,
+            fallback_nodes,
+            tracker,
+            monitor: MonitorConfig { port, max_lag },
+            api: ApiConfig {
+                port: file.api.port,
+            },
+            coingecko,
+            webhooks,
+            mempool,
+            alerts,
+            export,
+            workers,
+            rollback_horizon,
+        })
+    }
+}
+
+impl Config {
+    /// All configured nodes, main node first.
+    pub fn nodes(&self) -> Vec<NodeConfig> {
+        let mut nodes = vec![self.node.clone()];
+        nodes.extend(self.fallback_nodes.iter().cloned());
+        nodes
+    }
+}
+
+/// Checks webhook rules and applies default settings.
+fn build_webhooks(section: WebhooksSection) -> Result<WebhooksConfig, ConfigError> {
+    let defaults = WebhooksConfig::default();
+    let mut rules: Vec<WebhookRule> = vec![];
+    for rule in section.rules {
+        validate_url("webhooks.rules.url", &rule.url)?;
+        let min_nano = || match rule.min_nano {
+            Some(nano) if nano > 0 => Ok(nano),
+            Some(nano) => Err(ConfigError::Invalid(
+                "webhooks.rules.min_nano",
+                format!("must be greater than 0, got {nano}"),
+            )),
+            None => Err(ConfigError::Invalid(
+                "webhooks.rules.min_nano",
+                format!("required by `{}` rules", rule.kind),
+            )),
+        };
+        let condition = match rule.kind.as_str() {
+            "transfer" => RuleCondition::Transfer {
+                min_nano: min_nano()?,
+            },
+            "sigmausd" => RuleCondition::SigmaUSD {
+                min_nano: min_nano()?,
+            },
+            "proposal" => RuleCondition::Proposal,
+            "cex_deposit" => RuleCondition::CexDeposit,
+            kind => {
+                return Err(ConfigError::Invalid(
+                    "webhooks.rules.kind",
+                    format!(
+                        "expected one of transfer, sigmausd, proposal or cex_deposit, got `{kind}`"
+                    ),
+                ))
+            }
+        };
+        let name = rule.name.unwrap_or(rule.kind);
+        if rules.iter().any(|r| r.name == name) {
+            return Err(ConfigError::Invalid(
+                "webhooks.rules.name",
+                format!("`{name}` is used by more than one rule"),
+            ));
+        }
+        rules.push(WebhookRule {
+            name,
+            url: rule.url,
+            condition,
+        });
+    }
+    let webhooks = WebhooksConfig {
+        rules,
+        polling_interval: section
+            .polling_interval
+            .map(Duration::from_millis)
+            .unwrap_or(defaults.polling_interval),
+        timeout: section
+            .timeout
+            .map(Duration::from_millis)
+            .unwrap_or(defaults.timeout),
+        max_block_age: section
+            .max_block_age
+            .map(Duration::from_secs)
+            .unwrap_or(defaults.max_block_age),
+    };
+    validate_non_zero(
+        "webhooks.polling_interval",
+        webhooks.polling_interval.as_millis(),
+    )?;
+    validate_non_zero("webhooks.timeout", webhooks.timeout.as_millis())?;
+    Ok(webhooks)
+}
+
+/// Checks alert rules against enabled workers and applies default settings.
+fn build_alerts(section: AlertsSection, workers: &Registry) -> Result<AlertsConfig, ConfigError> {
+    let defaults = AlertsConfig::default();
+    let mut rules: Vec<AlertRule> = vec![];
+    for rule in section.rules {
+        let metric = AlertMetric::ALL
+            .into_iter()
+            .find(|m| m.name() == rule.metric)
+            .ok_or_else(|| {
+                let names: Vec<&str> = AlertMetric::ALL.iter().map(|m| m.name()).collect();
+                ConfigError::Invalid(
+                    "alerts.rules.metric",
+                    format!(
+                        "expected one of {}, got `{}`",
+                        names.join(", "),
+                        rule.metric
+                    ),
+                )
+            })?;
+        if !workers.is_enabled(metric.worker()) {
+            return Err(ConfigError::Invalid(
+                "alerts.rules.metric",
+                format!(
+                    "`{}` requires the {} worker",
+                    metric.name(),
+                    metric.worker()
+                ),
+            ));
+        }
+        match (rule.above, rule.below) {
+            (None, None) => {
+                return Err(ConfigError::Invalid(
+                    "alerts.rules",
+                    format!("`{}` needs a threshold, `above` or `below`", rule.name),
+                ))
+            }
+            (Some(above), Some(below)) if below >= above => {
+                return Err(ConfigError::Invalid(
+                    "alerts.rules",
+                    format!(
+                        "`{}` would always fire, `below` must be less than `above`",
+                        rule.name
+                    ),
+                ))
+            }
+            _ => (),
+        }
+        if rules.iter().any(|r| r.name == rule.name) {
+            return Err(ConfigError::Invalid(
+                "alerts.rules.name",
+                format!("`{}` is used by more than one rule", rule.name),
+            ));
+        }
+        rules.push(AlertRule {
+            name: rule.name,
+            metric,
+            above: rule.above,
+            below: rule.below,
+        });
+    }
+    if let Some(url) = &section.notify_url {
+        validate_url("alerts.notify_url", url)?;
+    }
+    let alerts = AlertsConfig {
+        rules,
+        notify_url: section.notify_url,
+        timeout: section
+            .timeout
+            .map(Duration::from_millis)
+            .unwrap_or(defaults.timeout),
+        max_block_age: section
+            .max_block_age
+            .map(Duration::from_secs)
+            .unwrap_or(defaults.max_block_age),
+    };
+    validate_non_zero("alerts.timeout", alerts.timeout.as_millis())?;
+    Ok(alerts)
+}
+
+/// Checks export settings against enabled workers and applies default settings.
+fn build_export(section: ExportSection, workers: &Registry) -> Result<ExportConfig, ConfigError> {
+    let defaults = ExportConfig::default();
+    let export = ExportConfig {
+        dir: section.dir,
+        partition_size: section.partition_size.unwrap_or(defaults.partition_size),
+        parquet: section.parquet.unwrap_or(defaults.parquet),
+    };
+    if export.dir.is_none() {
+        if let Some(worker) = WorkerID::EXPORT
+            .into_iter()
+            .find(|w| workers.is_enabled(*w))
+        {
+            return Err(ConfigError::Invalid(
+                "export.dir",
+                format!("required by the {worker} worker"),
+            ));
+        }
+    }
+    if export.partition_size < 1 {
+        return Err(ConfigError::Invalid(
+            "export.partition_size",
+            format!("must be at least 1, got {}", export.partition_size),
+        ));
+    }
+    Ok(export)
+}
+
+/// Resolves enabled workers and checks their dependencies.
+///
+/// Opt-in workers are only enabled by default when part of `opted_in`.
+fn build_registry(section: WorkersSection, opted_in: &[WorkerID]) -> Result<Registry, ConfigError> {
+    let parse = |names: Vec<String>| -> Result<Vec<WorkerID>, ConfigError> {
+        names
+            .iter()
+            .map(|name| name.parse::<WorkerID>())
+            .collect::<Result<Vec<WorkerID>, _>>()
+            .map_err(|e| ConfigError::Invalid("workers", e.to_string()))
+    };
+    let enabled = match section.enabled {
+        Some(names) => parse(names)?,
+        None => WorkerID::ALL
+            .into_iter()
+            .filter(|w| !WorkerID::OPT_IN.contains(w) || opted_in.contains(w))
+            .collect(),
+    };
+    let disabled = parse(section.disabled.unwrap_or_default())?;
+    let workers: Vec<WorkerID> = enabled
+        .into_iter()
+        .filter(|w| !disabled.contains(w))
+        .collect();
+    Registry::new(&workers).map_err(|e| ConfigError::Invalid("workers", e.to_string()))
+}
+
+fn read_file(path: &str) -> Result<ConfigFile, ConfigError> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| ConfigError::Read(path.to_owned(), e))?;
+    parse(path, &content)
+}
+
+fn parse(path: &str, content: &str) -> Result<ConfigFile, ConfigError> {
+    toml::from_str(content).map_err(|e| ConfigError::Parse(path.to_owned(), e))
+}
+
+fn validate_url(key: &'static str, url: &str) -> Result<(), ConfigError> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        Ok(())
+    } else {
+        Err(ConfigError::Invalid(
+            key,
+            format!("expected an http(s):// url, got `{url}`"),
+        ))
+    }
+}
+
+fn validate_api_key(key: &'static str, api_key: &Option<String>) -> Result<(), ConfigError> {
+    match api_key {
+        Some(s) if s.is_empty() || !s.chars().all(|c| c.is_ascii_graphic()) => Err(
+            ConfigError::Invalid(key, "expected non-empty printable ascii".to_owned()),
+        ),
+        _ => Ok(()),
+    }
+}
+
+fn validate_non_zero(key: &'static str, value: u128) -> Result<(), ConfigError> {
+    match value {
+        0 => Err(ConfigError::Invalid(
+            key,
+            "must be greater than 0".to_owned(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::collections::HashMap;
+
+    const MINIMAL: &str = r#"
+        [postgres]
+        uri = "postgresql://user:pw@localhost:5432/ergo"
+
+        [node]
+        url = "http://localhost:9053"
+    "#;
+
+    fn build_with_env(toml: &str, vars: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let env: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Config::build(parse("test", toml)?, |k| env.get(k).cloned())
+    }
+
+    #[test]
+    fn test_minimal_config_uses_defaults() {
+        let config = Config::from_toml(MINIMAL).unwrap();
+        assert_eq!(
+            config.postgres.connection_uri,
+            "postgresql://user:pw@localhost:5432/ergo"
+        );
+        assert_eq!(config.postgres.batch_blocks, 1);
+        assert_eq!(config.node.url, "http://localhost:9053");
+        assert_eq!(config.node.api_key, None);
+        assert_eq!(config.node.timeout, Duration::from_secs(30));
+        assert_eq!(config.node.connect_timeout, Duration::from_secs(5));
+        assert_eq!(config.node.max_retries, 5);
+        assert_eq!(config.node.retry_delay, Duration::from_millis(500));
+        assert_eq!(config.node.priority, 0);
+        assert!(config.fallback_nodes.is_empty());
+        assert_eq!(config.monitor.port, 3005);
+        assert_eq!(config.monitor.max_lag, 10);
+        assert_eq!(config.api.port, None);
+        assert_eq!(config.tracker.polling_interval, Duration::from_millis(5000));
+        assert_eq!(config.tracker.address_cache_size, 5000);
+        assert_eq!(config.tracker.asset_cache_size, 5000);
+        assert_eq!(config.tracker.max_node_lag, 3);
+        assert_eq!(config.tracker.prefetch_blocks, 16);
+        assert_eq!(config.coingecko.polling_interval, Duration::from_secs(60));
+        assert!(config.webhooks.rules.is_empty());
+        assert_eq!(config.webhooks.polling_interval, Duration::from_secs(5));
+        assert_eq!(config.webhooks.timeout, Duration::from_secs(10));
+        assert_eq!(config.webhooks.max_block_age, Duration::from_secs(3600));
+        assert_eq!(config.mempool.polling_interval, Duration::from_secs(5));
+        assert_eq!(config.mempool.address_cache_size, 5000);
+        assert!(config.alerts.rules.is_empty());
+        assert_eq!(config.alerts.notify_url, None);
+        assert_eq!(config.alerts.timeout, Duration::from_secs(10));
+        assert_eq!(config.alerts.max_block_age, Duration::from_secs(3600));
+        assert_eq!(config.export.dir, None);
+        assert_eq!(config.export.partition_size, 10_000);
+        assert!(config.export.parquet);
+        assert_eq!(config.rollback_horizon, 20);
+        // Diffs webhooks and export workers are opt-in
+        assert_eq!(
+            config.workers.enabled(),
+            WorkerID::ALL
+                .into_iter()
+                .filter(|w| !WorkerID::OPT_IN.contains(w))
+                .collect::<Vec<WorkerID>>()
+        );
+    }
+
+    #[test]
+    fn test_full_config() {
+        let toml = r#"
+            [postgres]
+            uri = "postgres://user:pw@db/ergo"
+            batch_blocks = 100
+
+            [node]
+            url = "https://node.example.com"
+            api_key = "hello"
+            timeout = 10000
+            connect_timeout = 1000
+            max_retries = 2
+            retry_delay = 100
+
+            [[node.fallbacks]]
+            url = "http://backup-1:9053"
+
+            [[node.fallbacks]]
+            url = "http://backup-2:9053"
+            api_key = "other"
+            priority = 5
+
+            [tracker]
+            polling_interval = 1000
+            address_cache_size = 100
+            asset_cache_size = 200
+            max_node_lag = 1
+            prefetch_blocks = 4
+
+            [monitor]
+            port = 8080
+            max_lag = 3
+
+            [api]
+            port = 8081
+
+            [coingecko]
+            url = "http://localhost:1234"
+            polling_interval = 30
+
+            [webhooks]
+            polling_interval = 1000
+            timeout = 2000
+            max_block_age = 600
+
+            [[webhooks.rules]]
+            name = "whales"
+            url = "https://example.com/whales"
+            kind = "transfer"
+            min_nano = 100000000000000
+
+            [[webhooks.rules]]
+            url = "https://example.com/governance"
+            kind = "proposal"
+
+            [[webhooks.rules]]
+            url = "https://example.com/deposits"
+            kind = "cex_deposit"
+
+            [mempool]
+            polling_interval = 2000
+            address_cache_size = 300
+
+            [alerts]
+            notify_url = "https://example.com/alerts"
+            timeout = 3000
+            max_block_age = 600
+
+            [[alerts.rules]]
+            name = "undercollateralized"
+            metric = "sigmausd_reserve_ratio"
+            below = 400
+
+            [[alerts.rules]]
+            name = "cex-flows"
+            metric = "cex_supply_change_24h"
+            above = 5.0
+            below = -5.0
+
+            [export]
+            dir = "/data/export"
+            partition_size = 1000
+            parquet = false
+
+            [settings]
+            rollback_horizon = 50
+        "#;
+        let config = Config::from_toml(toml).unwrap();
+        assert_eq!(config.postgres.batch_blocks, 100);
+        assert_eq!(config.node.api_key, Some("hello".to_owned()));
+        assert_eq!(config.node.timeout, Duration::from_secs(10));
+        assert_eq!(config.node.connect_timeout, Duration::from_secs(1));
+        assert_eq!(config.node.max_retries, 2);
+        assert_eq!(config.node.retry_delay, Duration::from_millis(100));
+        assert_eq!(config.tracker.polling_interval, Duration::from_millis(1000));
+        assert_eq!(config.tracker.address_cache_size, 100);
+        assert_eq!(config.tracker.asset_cache_size, 200);
+        assert_eq!(config.tracker.max_node_lag, 1);
+        assert_eq!(config.tracker.prefetch_blocks, 4);
+        let nodes = config.nodes();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[1].url, "http://backup-1:9053");
+        assert_eq!(nodes[1].api_key, Some("hello".to_owned()));
+        assert_eq!(nodes[1].priority, 1);
+        assert_eq!(nodes[1].max_retries, 2);
+        assert_eq!(nodes[2].url, "http://backup-2:9053");
+        assert_eq!(nodes[2].api_key, Some("other".to_owned()));
+        assert_eq!(nodes[2].priority, 5);
+        assert_eq!(config.monitor.port, 8080);
+        assert_eq!(config.monitor.max_lag, 3);
+        assert_eq!(config.api.port, Some(8081));
+        assert_eq!(config.coingecko.url, "http://localhost:1234");
+        assert_eq!(config.coingecko.polling_interval, Duration::from_secs(30));
+        assert_eq!(config.webhooks.polling_interval, Duration::from_secs(1));
+        assert_eq!(config.webhooks.timeout, Duration::from_secs(2));
+        assert_eq!(config.webhooks.max_block_age, Duration::from_secs(600));
+        assert_eq!(config.mempool.polling_interval, Duration::from_secs(2));
+        assert_eq!(config.mempool.address_cache_size, 300);
+        assert_eq!(
+            config.webhooks.rules,
+            vec![
+                WebhookRule {
+                    name: "whales".to_owned(),
+                    url: "https://example.com/whales".to_owned(),
+                    condition: RuleCondition::Transfer {
+                        min_nano: 100000000000000
+                    },
+                },
+                WebhookRule {
+                    name: "proposal".to_owned(),
+                    url: "https://example.com/governance".to_owned(),
+                    condition: RuleCondition::Proposal,
+                },
+                WebhookRule {
+                    name: "cex_deposit".to_owned(),
+                    url: "https://example.com/deposits".to_owned(),
+                    condition: RuleCondition::CexDeposit,
+                },
+            ]
+        );
+        assert_eq!(
+            config.alerts.notify_url,
+            Some("https://example.com/alerts".to_owned())
+        );
+        assert_eq!(config.alerts.timeout, Duration::from_secs(3));
+        assert_eq!(config.alerts.max_block_age, Duration::from_secs(600));
+        assert_eq!(
+            config.alerts.rules,
+            vec![
+                AlertRule {
+                    name: "undercollateralized".to_owned(),
+                    metric: AlertMetric::SigmaUSDReserveRatio,
+                    above: None,
+                    below: Some(400.0),
+                },
+                AlertRule {
+                    name: "cex-flows".to_owned(),
+                    metric: AlertMetric::CexSupplyChange24h,
+                    above: Some(5.0),
+                    below: Some(-5.0),
+                },
+            ]
+        );
+        assert_eq!(config.export.dir, Some(PathBuf::from("/data/export")));
+        assert_eq!(config.export.partition_size, 1000);
+        assert!(!config.export.parquet);
+        assert_eq!(config.workers, Registry::all());
+        assert_eq!(config.rollback_horizon, 50);
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let config = build_with_env(
+            MINIMAL,
+            &[
+                (ENV_POSTGRES_URI, "postgresql://other@db/ergo"),
+                (ENV_NODE_URL, "http://node:9053"),
+                (ENV_NODE_API_KEY, "secret"),
+                (ENV_MONITOR_PORT, "4000"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(config.postgres.connection_uri, "postgresql://other@db/ergo");
+        assert_eq!(config.node.url, "http://node:9053");
+        assert_eq!(config.node.api_key, Some("secret".to_owned()));
+        assert_eq!(config.monitor.port, 4000);
+    }
+
+    #[test]
+    fn test_env_only() {
+        let config = build_with_env(
+            "",
+            &[
+                (ENV_POSTGRES_URI, "postgresql://other@db/ergo"),
+                (ENV_NODE_URL, "http://node:9053"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(config.node.url, "http://node:9053");
+    }
+
+    #[test]
+    fn test_missing_uri() {
+        let err = build_with_env("", &[(ENV_NODE_URL, "http://node:9053")]).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Missing("postgres.uri", ENV_POSTGRES_URI)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        let toml = format!("{MINIMAL}\n[tracker]\nasset_cache_size = 0");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid("tracker.asset_cache_size", _)
+        ));
+
+        let err = build_with_env(MINIMAL, &[(ENV_NODE_URL, "localhost:9053")]).unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid("node.url", _)));
+
+        let err = build_with_env(MINIMAL, &[(ENV_MONITOR_PORT, "abc")]).unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid("monitor.port", _)));
+
+        let err = build_with_env(MINIMAL, &[(ENV_NODE_API_KEY, "with space")]).unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid("node.api_key", _)));
+
+        let toml = MINIMAL.replace("[node]", "[node]\ntimeout = 0");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid("node.timeout", _)));
+
+        let toml = format!("{MINIMAL}\n[[node.fallbacks]]\nurl = \"http://localhost:9053\"");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid("node.fallbacks.url", _)));
+
+        let toml = format!("{MINIMAL}\n[tracker]\nmax_node_lag = -1");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid("tracker.max_node_lag", _)
+        ));
+
+        let toml = MINIMAL.replace("[postgres]", "[postgres]\nbatch_blocks = 0");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid("postgres.batch_blocks", _)
+        ));
+
+        let toml = format!("{MINIMAL}\n[mempool]\npolling_interval = 0");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid("mempool.polling_interval", _)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_webhook_rules() {
+        let rule = |body: &str| format!("{MINIMAL}\n[[webhooks.rules]]\n{body}");
+
+        let toml = rule("url = \"https://example.com\"\nkind = \"transfer\"");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid("webhooks.rules.min_nano", _)
+        ));
+
+        let toml = rule("url = \"https://example.com\"\nkind = \"sigmausd\"\nmin_nano = 0");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid("webhooks.rules.min_nano", _)
+        ));
+
+        let toml = rule("url = \"https://example.com\"\nkind = \"deposit\"");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid("webhooks.rules.kind", _)
+        ));
+
+        let toml = rule("url = \"example.com\"\nkind = \"proposal\"");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid("webhooks.rules.url", _)));
+
+        // Rule of a disabled worker
+        let toml = format!(
+            "{}\n[workers]\ndisabled = [\"webhooks_diffs\"]",
+            rule("url = \"https://example.com\"\nkind = \"cex_deposit\"")
+        );
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid("webhooks.rules.kind", _)
+        ));
+
+        let toml = format!(
+            "{}\n[[webhooks.rules]]\nurl = \"https://example.com/2\"\nkind = \"proposal\"",
+            rule("url = \"https://example.com/1\"\nkind = \"proposal\"")
+        );
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid("webhooks.rules.name", _)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_alert_rules() {
+        let rule = |body: &str| format!("{MINIMAL}\n[[alerts.rules]]\nname = \"a\"\n{body}");
+
+        let toml = rule("metric = \"hash_rate\"\nbelow = -10");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid("alerts.rules.metric", _)
+        ));
+
+        let toml = rule("metric = \"hash_rate_change_24h\"");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid("alerts.rules", _)));
+
+        let toml = rule("metric = \"hash_rate_change_24h\"\nabove = -10\nbelow = 10");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid("alerts.rules", _)));
+
+        let toml = format!(
+            "{}\n[[alerts.rules]]\nname = \"a\"\nmetric = \"hash_rate_change_24h\"\nabove = 10",
+            rule("metric = \"hash_rate_change_24h\"\nbelow = -10")
+        );
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid("alerts.rules.name", _)));
+
+        // Metric of a disabled worker
+        let toml = format!(
+            "{}\n[workers]\ndisabled = [\"network\"]",
+            rule("metric = \"hash_rate_change_24h\"\nbelow = -10")
+        );
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid("alerts.rules.metric", _)
+        ));
+
+        let toml = format!("{MINIMAL}\n[alerts]\nnotify_url = \"localhost\"");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid("alerts.notify_url", _)));
+    }
+
+    #[test]
+    fn test_workers() {
+        let toml = format!("{MINIMAL}\n[workers]\nenabled = [\"tokens\", \"timestamps\"]");
+        let config = Config::from_toml(&toml).unwrap();
+        assert_eq!(
+            config.workers.enabled(),
+            &[WorkerID::Timestamps, WorkerID::Tokens]
+        );
+
+        let toml = format!("{MINIMAL}\n[workers]\ndisabled = [\"coingecko\"]");
+        let config = Config::from_toml(&toml).unwrap();
+        assert!(!config.workers.is_enabled(WorkerID::Coingecko));
+        assert!(config.workers.is_enabled(WorkerID::Erg));
+
+        // Enabled by default when a rule needs it
+        assert!(!config.workers.is_enabled(WorkerID::WebhooksDiffs));
+        let toml = format!(
+            "{MINIMAL}\n[[webhooks.rules]]\nurl = \"https://example.com\"\nkind = \"cex_deposit\""
+        );
+        let config = Config::from_toml(&toml).unwrap();
+        assert!(config.workers.is_enabled(WorkerID::WebhooksDiffs));
+
+        let toml = format!("{MINIMAL}\n[workers]\ndisabled = [\"erg_diffs\"]");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid("workers", _)));
+
+        let toml = format!("{MINIMAL}\n[workers]\nenabled = [\"ergo\"]");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid("workers", _)));
+    }
+
+    #[test]
+    fn test_export() {
+        let toml = format!(
+            "{MINIMAL}\n[export]\ndir = \"export\"\n[workers]\ndisabled = [\"export_diffs\"]"
+        );
+        let config = Config::from_toml(&toml).unwrap();
+        assert!(config.workers.is_enabled(WorkerID::ExportCore));
+        assert!(!config.workers.is_enabled(WorkerID::ExportDiffs));
+
+        // Explicitly enabled export workers need a directory
+        let toml = format!("{MINIMAL}\n[workers]\nenabled = [\"export_core\"]");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid("export.dir", _)));
+
+        let toml = format!("{MINIMAL}\n[export]\ndir = \"export\"\npartition_size = 0");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid("export.partition_size", _)
+        ));
+    }
+
+    #[test]
+    fn test_unknown_key() {
+        let toml = format!("{MINIMAL}\n[tracker]\npoling_interval = 10");
+        let err = Config::from_toml(&toml).unwrap_err();
+        assert!(matches!(err, ConfigError::Parse(_, _)));
+    }
+}
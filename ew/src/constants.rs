@@ -40,9 +40,31 @@ pub mod address_ids {
 }
 
 pub mod settings {
+    use std::sync::OnceLock;
+
     use crate::core::types::Height;
+
+    /// Default maximum number of blocks that can be rolled back.
+    pub const ROLLBACK_HORIZON: Height = 20;
+
+    static CONFIGURED_ROLLBACK_HORIZON: OnceLock<Height> = OnceLock::new();
+
     /// Maximum number of blocks that can be rolled back.
     ///
     /// If exceeded, some workers will have to be resynced from scratch.
-    pub const ROLLBACK_HORIZON: Height = 20;
+    /// Returns [`ROLLBACK_HORIZON`] unless configured otherwise at startup.
+    pub fn rollback_horizon() -> Height {
+        *CONFIGURED_ROLLBACK_HORIZON
+            .get()
+            .unwrap_or(&ROLLBACK_HORIZON)
+    }
+
+    /// Sets the rollback horizon.
+    ///
+    /// Must be called before any worker is started. Later calls are ignored.
+    pub fn set_rollback_horizon(horizon: Height) {
+        if CONFIGURED_ROLLBACK_HORIZON.set(horizon).is_err() {
+            tracing::warn!("rollback horizon already set - ignoring {horizon}");
+        }
+    }
 }
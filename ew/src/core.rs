@@ -1,3 +1,4 @@
+pub mod archive;
 mod ergo;
 mod node;
 mod store;
@@ -5,9 +6,30 @@ pub mod tracking;
 pub mod types;
 
 pub use node::Node;
+pub use node::NodeError;
+
+use crate::config::PostgresConfig;
+use crate::error::EwError;
+use crate::framework::store::PgMigrator;
+
+/// Node API types handled outside of the tracker.
+pub(crate) mod models {
+    pub(crate) use super::node::models::Output;
+    pub(crate) use super::node::models::Transaction;
+}
+pub(crate) use store::api;
+pub(crate) use store::SCHEMA;
 
 /// Makes some node types available for integration tests mockups.
 pub mod testing {
     pub use super::node::models::Block as NodeBlock;
     pub use super::node::models::Header as NodeHeader;
 }
+
+/// Initializes the core store and applies pending migrations.
+pub async fn migrate(pgconf: &PostgresConfig) -> Result<(), EwError> {
+    PgMigrator::new(pgconf, &SCHEMA)
+        .await?
+        .apply_pending()
+        .await
+}
@@ -0,0 +1,287 @@
+//! Offline copy of the chain, as served by a node.
+//!
+//! Blocks are kept as returned by the node API, one JSON file per block,
+//! so they can be replayed without a node (see `ArchiveTracker`). They're
+//! recorded by `ew archive`, or by the tracker as it syncs with
+//! `ew run --record` (see `ArchiveRecorder`).
+//!
+//! Layout of an archive directory:
+//!
+//! ```text
+//! genesis.json                    genesis boxes (`utxo/genesis`)
+//! blocks/<height / 10000>/<height>.json  full blocks (`blocks/<header_id>`)
+//! ```
+use std::path::Path;
+use std::path::PathBuf;
+
+use super::node::models;
+use super::node::Node;
+use super::types::Header;
+use super::types::Height;
+use crate::constants::settings::rollback_horizon;
+use crate::error::EwError;
+use crate::shutdown::CancellationToken;
+
+/// Number of block files per subdirectory
+const BUCKET_SIZE: Height = 10_000;
+
+/// Number of headers requested at once while recording
+const CHAINSLICE_SIZE: Height = 100;
+
+/// A directory of node JSON blocks.
+#[derive(Debug, Clone)]
+pub struct BlockArchive {
+    dir: PathBuf,
+}
+
+impl BlockArchive {
+    /// Archive in given `dir`, created when recording blocks.
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns header of last archived block.
+    ///
+    /// Initial header if the archive is empty, genesis header if it
+    /// contains genesis boxes only.
+    pub async fn header(&self) -> Result<Header, EwError> {
+        if !self.genesis_path().exists() {
+            return Ok(Header::initial());
+        }
+        let bucket = match last_entry(&self.dir.join("blocks")).await? {
+            Some(b) => b,
+            None => return Ok(Header::genesis()),
+        };
+        let height = match last_entry(&self.dir.join("blocks").join(bucket.to_string())).await? {
+            Some(h) => h,
+            None => return Ok(Header::genesis()),
+        };
+        let block = self
+            .block(height)
+            .await?
+            .expect("last archived block exists");
+        Ok(header_of(&block))
+    }
+
+    /// Returns raw genesis boxes.
+    pub(crate) async fn genesis_boxes(&self) -> Result<String, EwError> {
+        let path = self.genesis_path();
+        tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| io_error(&path, e))
+    }
+
+    /// Returns archived block at given `height`, if any.
+    pub(crate) async fn block(&self, height: Height) -> Result<Option<models::Block>, EwError> {
+        let path = self.block_path(height);
+        let json = match tokio::fs::read_to_string(&path).await {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(io_error(&path, e)),
+        };
+        serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|e| EwError::Archive(format!("invalid block in {}: {e}", path.display())))
+    }
+
+    /// Records blocks served by `node`, resuming from the last archived one.
+    ///
+    /// Only blocks beyond the rollback horizon of the node's tip are
+    /// recorded, so archives never contain forks. Stops at height `to`,
+    /// if given, once at the node's settled height, or when `shutdown`
+    /// is cancelled. Returns header of last archived block.
+    pub async fn record(
+        &self,
+        node: &Node,
+        to: Option<Height>,
+        shutdown: &CancellationToken,
+    ) -> Result<Header, EwError> {
+        let mut header = self.header().await?;
+        if header.is_initial() {
+            tracing::info!("recording genesis boxes");
+            self.write_genesis(&node.api.utxo_genesis_raw().await?)
+                .await?;
+            header = Header::genesis();
+        }
+
+        let settled_height = node.api.info().await?.full_height - rollback_horizon();
+        let target = match to {
+            Some(h) => h.min(settled_height),
+            None => settled_height,
+        };
+        tracing::info!("recording blocks {} to {target}", header.height + 1);
+        while header.height < target && !shutdown.is_cancelled() {
+            let to_height = target.min(header.height + CHAINSLICE_SIZE);
+            let node_headers = node.api.chainslice(header.height, to_height).await?;
+            for node_header in node_headers {
+                if node_header.height <= header.height {
+                    continue;
+                }
+                if node_header.height > target || shutdown.is_cancelled() {
+                    break;
+                }
+                if node_header.parent_id != header.header_id {
+                    return Err(EwError::Archive(format!(
+                        "block {} is not a child of archived block {}",
+                        node_header.id, header.header_id
+                    )));
+                }
+                let raw = node.api.block_raw(&node_header.id).await?;
+                self.write_block(node_header.height, &raw).await?;
+                header = Header {
+                    height: node_header.height,
+                    timestamp: node_header.timestamp,
+                    header_id: node_header.id,
+                    parent_id: node_header.parent_id,
+                };
+                if header.height % 1000 == 0 {
+                    tracing::info!("recorded block {}", header.height);
+                }
+            }
+        }
+        Ok(header)
+    }
+
+    /// Writes raw genesis `boxes`, creating the archive directory.
+    pub(crate) async fn write_genesis(&self, boxes: &str) -> Result<(), EwError> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| io_error(&self.dir, e))?;
+        write(&self.genesis_path(), boxes).await
+    }
+
+    /// Writes `raw` block JSON at given `height`.
+    async fn write_block(&self, height: Height, raw: &str) -> Result<(), EwError> {
+        let path = self.block_path(height);
+        if let Some(bucket) = path.parent() {
+            tokio::fs::create_dir_all(bucket)
+                .await
+                .map_err(|e| io_error(bucket, e))?;
+        }
+        write(&path, raw).await
+    }
+
+    fn genesis_path(&self) -> PathBuf {
+        self.dir.join("genesis.json")
+    }
+
+    fn block_path(&self, height: Height) -> PathBuf {
+        self.dir
+            .join("blocks")
+            .join((height / BUCKET_SIZE).to_string())
+            .join(format!("{height}.json"))
+    }
+}
+
+/// Appends settled blocks fetched by the tracker to a `BlockArchive`.
+///
+/// Blocks are only appended if they extend the archive. Blocks served
+/// from the core store never reach the recorder, so an archive started
+/// after the store has gaps that `ew archive` can fill.
+#[derive(Debug)]
+pub struct ArchiveRecorder {
+    archive: BlockArchive,
+    /// Last archived block
+    header: Header,
+    /// Set once a gap has been reported, to not log it for every block
+    gap_reported: bool,
+}
+
+impl ArchiveRecorder {
+    /// Recorder appending to given `archive`.
+    pub async fn new(archive: BlockArchive) -> Result<Self, EwError> {
+        let header = archive.header().await?;
+        Ok(Self {
+            archive,
+            header,
+            gap_reported: false,
+        })
+    }
+
+    /// Returns header of last archived block.
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Appends settled `block`, kept as returned by the node in `raw`.
+    ///
+    /// Blocks archived already, or not following the last archived one,
+    /// are skipped. Errors if `block` is not a child of the last archived
+    /// block, as archives never contain forks.
+    pub(crate) async fn append(&mut self, block: &models::Block, raw: &str) -> Result<(), EwError> {
+        let height = block.header.height;
+        if height <= self.header.height {
+            return Ok(());
+        }
+        if height > self.header.height + 1 {
+            if !self.gap_reported {
+                tracing::warn!(
+                    "not archiving block {height} as archive is at {}, run `ew archive` to fill the gap",
+                    self.header.height
+                );
+                self.gap_reported = true;
+            }
+            return Ok(());
+        }
+        if block.header.parent_id != self.header.header_id {
+            return Err(EwError::Archive(format!(
+                "block {} is not a child of archived block {}",
+                block.header.id, self.header.header_id
+            )));
+        }
+        self.archive.write_block(height, raw).await?;
+        self.header = header_of(block);
+        self.gap_reported = false;
+        Ok(())
+    }
+}
+
+/// Returns header of given node `block`.
+pub(super) fn header_of(block: &models::Block) -> Header {
+    Header {
+        height: block.header.height,
+        timestamp: block.header.timestamp,
+        header_id: block.header.id.clone(),
+        parent_id: block.header.parent_id.clone(),
+    }
+}
+
+/// Returns highest numbered entry of `dir`, if any.
+///
+/// Entries are named after a number, optionally followed by an extension.
+async fn last_entry(dir: &Path) -> Result<Option<Height>, EwError> {
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(io_error(dir, e)),
+    };
+    let mut last: Option<Height> = None;
+    while let Some(entry) = entries.next_entry().await.map_err(|e| io_error(dir, e))? {
+        let name = entry.file_name();
+        let number = name
+            .to_str()
+            .and_then(|s| s.trim_end_matches(".json").parse::<Height>().ok());
+        if let Some(n) = number {
+            last = Some(last.map_or(n, |l| l.max(n)));
+        }
+    }
+    Ok(last)
+}
+
+/// Writes `contents` to `path`, through a temporary file so that an
+/// interrupted write never leaves a partial block behind.
+async fn write(path: &Path, contents: &str) -> Result<(), EwError> {
+    let tmp = path.with_extension("tmp");
+    tokio::fs::write(&tmp, contents)
+        .await
+        .map_err(|e| io_error(&tmp, e))?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .map_err(|e| io_error(path, e))
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> EwError {
+    EwError::Archive(format!("{}: {e}", path.display()))
+}
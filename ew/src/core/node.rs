@@ -1,9 +1,12 @@
 mod api;
 pub(super) mod models;
+mod recorder;
 
 use api::NodeAPI;
 use thiserror::Error;
 
+use crate::config::NodeConfig;
+
 #[derive(Error, Debug)]
 pub enum NodeError {
     #[error("Node is unreachable. Could be a tempory outage but make sure your config is set correctly and the node is running.")]
@@ -12,23 +15,58 @@ pub enum NodeError {
     API400BadRequest(String),
     #[error("Node API request not found: {0}")]
     API404Notfound(String),
+    #[error("Node API request timed out: {0}")]
+    Timeout(String),
+    #[error("Node API server error: {0}")]
+    APIServerError(String),
     #[error("Error while requesting ({0})")]
     APIError(String),
     #[error("Failed parsing response from node")]
     DeserializationError,
 }
 
+impl NodeError {
+    /// Name of the error variant, used as metrics label.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            NodeError::NodeUnreachable => "NodeUnreachable",
+            NodeError::API400BadRequest(_) => "API400BadRequest",
+            NodeError::API404Notfound(_) => "API404Notfound",
+            NodeError::Timeout(_) => "Timeout",
+            NodeError::APIServerError(_) => "APIServerError",
+            NodeError::APIError(_) => "APIError",
+            NodeError::DeserializationError => "DeserializationError",
+        }
+    }
+
+    /// True for errors worth retrying the request for.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            NodeError::NodeUnreachable | NodeError::Timeout(_) | NodeError::APIServerError(_)
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Node {
     pub id: String,
+    /// Nodes with a lower value are preferred.
+    pub priority: u32,
     pub api: NodeAPI,
 }
 
 impl Node {
+    /// Node at given `url`, using default API settings.
     pub fn new(id: &str, url: &str) -> Self {
+        Self::with_config(id, &NodeConfig::new(url))
+    }
+
+    pub fn with_config(id: &str, config: &NodeConfig) -> Self {
         Self {
             id: String::from(id),
-            api: NodeAPI::new(url),
+            priority: config.priority,
+            api: NodeAPI::new(config),
         }
     }
 }
@@ -2,66 +2,101 @@ use super::models::Block;
 use super::models::Header;
 use super::models::NodeInfo;
 use super::models::Output;
+use super::models::Transaction;
+use super::recorder::Recorder;
 use super::NodeError;
+use crate::config::NodeConfig;
 use crate::core::types::HeaderID;
 use crate::core::types::Height;
+use crate::monitor::metrics;
 use reqwest;
+use reqwest::header::HeaderMap;
+use reqwest::header::HeaderValue;
 use reqwest::StatusCode;
+use std::time::Duration;
+
+/// Header carrying the node's API key.
+const API_KEY_HEADER: &str = "api_key";
+
+/// Longest delay between two retries of a request.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
 
 #[derive(Debug, Clone)]
 pub struct NodeAPI {
     url: String,
     qry_info: String,
+    /// Shared client, pooling connections to the node.
+    client: reqwest::Client,
+    max_retries: u32,
+    retry_delay: Duration,
+    /// Saves responses as test fixtures, if set.
+    recorder: Option<Recorder>,
 }
 
 impl NodeAPI {
-    pub fn new(url: &str) -> Self {
+    pub fn new(config: &NodeConfig) -> Self {
+        let url = config.url.as_str();
         tracing::event!(tracing::Level::INFO, url);
+        let mut headers = HeaderMap::new();
+        if let Some(api_key) = &config.api_key {
+            let mut value = HeaderValue::from_str(api_key).expect("api key is validated by config");
+            value.set_sensitive(true);
+            headers.insert(API_KEY_HEADER, value);
+        }
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .timeout(config.timeout)
+            .connect_timeout(config.connect_timeout)
+            .build()
+            .expect("http client can be built");
         Self {
             url: String::from(url),
             qry_info: format!("{}/info", url),
+            client,
+            max_retries: config.max_retries,
+            retry_delay: config.retry_delay,
+            recorder: config.record_dir.as_deref().map(Recorder::new),
         }
     }
 
     /// Get current node info (trimmed down version)
     pub async fn info(&self) -> Result<NodeInfo, NodeError> {
-        let response = self.get(&self.qry_info).await?;
-        let node_info: NodeInfo = response.json().await.unwrap();
-        Ok(node_info)
+        let body = self.get_recorded("info", &self.qry_info, "info").await?;
+        serde_json::from_str(&body).map_err(|_| NodeError::DeserializationError)
     }
 
     /// Get header ID's of blocks at given `height`
     pub async fn blocks_at(&self, height: Height) -> Result<Vec<String>, NodeError> {
         let url = format!("{}/blocks/at/{}", self.url, height);
-        let response = self.get(&url).await?;
-        let header_ids: Vec<String> = response.json().await.unwrap();
-        Ok(header_ids.to_owned())
+        let fixture = format!("blocks/at/{height}");
+        let body = self.get_recorded("blocks/at", &url, &fixture).await?;
+        serde_json::from_str(&body).map_err(|_| NodeError::DeserializationError)
     }
 
     /// Get full block from `header_id`
     pub async fn block(&self, header_id: &HeaderID) -> Result<Block, NodeError> {
-        let url = format!("{}/blocks/{}", self.url, header_id);
-        let response = self.get(&url).await?;
-        response
-            .json()
-            .await
-            .map_err(|_| NodeError::DeserializationError)
+        let (block, _) = self.block_with_raw(header_id).await?;
+        Ok(block)
+    }
+
+    /// Get full block from `header_id`, along with its raw JSON
+    pub async fn block_with_raw(&self, header_id: &HeaderID) -> Result<(Block, String), NodeError> {
+        let body = self.block_raw(header_id).await?;
+        let block = serde_json::from_str(&body).map_err(|_| NodeError::DeserializationError)?;
+        Ok((block, body))
     }
 
     /// Get raw full block JSON from `header_id`
     pub async fn block_raw(&self, header_id: &HeaderID) -> Result<String, NodeError> {
         let url = format!("{}/blocks/{}", self.url, header_id);
-        let response = self.get(&url).await?;
-        response
-            .text()
-            .await
-            .map_err(|_| NodeError::DeserializationError)
+        let fixture = format!("blocks/{header_id}");
+        self.get_recorded("blocks", &url, &fixture).await
     }
 
     /// Get full header from `header_id`
     pub async fn header(&self, header_id: &HeaderID) -> Result<Header, NodeError> {
         let url = format!("{}/blocks/{}/header", self.url, header_id);
-        let response = self.get(&url).await?;
+        let response = self.get("blocks/header", &url).await?;
         response
             .json()
             .await
@@ -76,48 +111,121 @@ impl NodeAPI {
             "{}/blocks/chainSlice?fromHeight={}&toHeight={}",
             self.url, from_h, to_h
         );
-        let response = self.get(&url).await?;
+        let response = self.get("blocks/chainSlice", &url).await?;
         response
             .json()
             .await
             .map_err(|_| NodeError::DeserializationError)
     }
 
-    pub async fn utxo_genesis(&self) -> Result<Vec<Output>, NodeError> {
-        let url = format!("{}/utxo/genesis", self.url);
-        let response = self.get(&url).await?;
+    /// Get a page of unconfirmed transactions from the node's pool
+    pub async fn unconfirmed_transactions(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<Transaction>, NodeError> {
+        let url = format!(
+            "{}/transactions/unconfirmed?offset={}&limit={}",
+            self.url, offset, limit
+        );
+        let response = self.get("transactions/unconfirmed", &url).await?;
         response
             .json()
             .await
             .map_err(|_| NodeError::DeserializationError)
     }
 
+    pub async fn utxo_genesis(&self) -> Result<Vec<Output>, NodeError> {
+        let body = self.utxo_genesis_raw().await?;
+        serde_json::from_str(&body).map_err(|_| NodeError::DeserializationError)
+    }
+
     pub async fn utxo_genesis_raw(&self) -> Result<String, NodeError> {
         let url = format!("{}/utxo/genesis", self.url);
-        let response = self.get(&url).await?;
-        response
-            .text()
+        self.get_recorded("utxo/genesis", &url, "utxo/genesis")
             .await
-            .map_err(|_| NodeError::DeserializationError)
     }
 }
 
 impl NodeAPI {
+    /// Send a GET request and return the response body.
+    ///
+    /// The body is saved as `fixture` when recording responses.
+    async fn get_recorded(
+        &self,
+        endpoint: &str,
+        url: &str,
+        fixture: &str,
+    ) -> Result<String, NodeError> {
+        let body = self
+            .get(endpoint, url)
+            .await?
+            .text()
+            .await
+            .map_err(|_| NodeError::DeserializationError)?;
+        if let Some(recorder) = &self.recorder {
+            recorder.save(fixture, &body).await;
+        }
+        Ok(body)
+    }
+
     /// Send a GET request
-    async fn get(&self, url: &str) -> Result<reqwest::Response, NodeError> {
+    ///
+    /// * `endpoint` - endpoint label used in metrics
+    /// * `url` - request url
+    async fn get(&self, endpoint: &str, url: &str) -> Result<reqwest::Response, NodeError> {
+        let timer = metrics::NODE_REQUEST_SECONDS
+            .with_label_values(&[endpoint])
+            .start_timer();
+        let res = self.send_get_with_retries(endpoint, url).await;
+        timer.observe_duration();
+        if let Err(ref e) = res {
+            metrics::NODE_ERRORS
+                .with_label_values(&[endpoint, e.kind()])
+                .inc();
+        }
+        res
+    }
+
+    /// Send a GET request, retrying transient failures with a backoff.
+    async fn send_get_with_retries(
+        &self,
+        endpoint: &str,
+        url: &str,
+    ) -> Result<reqwest::Response, NodeError> {
+        let mut delay = self.retry_delay;
+        let mut retries = 0;
+        loop {
+            match self.send_get(url).await {
+                Err(e) if e.is_transient() && retries < self.max_retries => {
+                    tracing::warn!("{e} - retrying in {}ms", delay.as_millis());
+                    metrics::NODE_RETRIES.with_label_values(&[endpoint]).inc();
+                    tokio::time::sleep(delay).await;
+                    delay = std::cmp::min(delay * 2, MAX_RETRY_DELAY);
+                    retries += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    async fn send_get(&self, url: &str) -> Result<reqwest::Response, NodeError> {
         tracing::trace!(url);
-        let response = reqwest::get(url)
-            .await
-            // .map_err(|_| NodeError::NodeUnreachable)?;
-            .map_err(|err| {
-                tracing::warn!("{:?}", &err);
-                NodeError::NodeUnreachable
-            })?;
+        let response = self.client.get(url).send().await.map_err(|err| {
+            tracing::debug!("{:?}", &err);
+            match err.is_timeout() {
+                true => NodeError::Timeout(url.to_string()),
+                false => NodeError::NodeUnreachable,
+            }
+        })?;
 
         match response.status() {
             StatusCode::OK => Ok(response),
             StatusCode::BAD_REQUEST => Err(NodeError::API400BadRequest(url.to_string())),
             StatusCode::NOT_FOUND => Err(NodeError::API404Notfound(url.to_string())),
+            status if status.is_server_error() => Err(NodeError::APIServerError(format!(
+                "{url} returned {status}"
+            ))),
             _ => Err(NodeError::APIError(url.to_string())),
         }
     }
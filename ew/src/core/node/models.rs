@@ -1,4 +1,5 @@
 use serde::Deserialize;
+use serde::Serialize;
 
 use crate::core::types::BoxID;
 use crate::core::types::Digest32;
@@ -28,7 +29,7 @@ pub struct Block {
     pub size: i32,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Header {
     pub extension_id: Digest32,
@@ -50,7 +51,7 @@ pub struct Header {
     pub parent_id: HeaderID,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[cfg_attr(feature = "test-utilities", derive(Clone))]
 pub struct POWSolutions {
     pub pk: String,
@@ -118,7 +119,7 @@ pub struct Asset {
     pub amount: i64,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 #[cfg_attr(feature = "test-utilities", derive(Clone))]
 pub struct Extension {
@@ -127,7 +128,7 @@ pub struct Extension {
     pub fields: Vec<ExtensionField>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[cfg_attr(feature = "test-utilities", derive(Clone))]
 pub struct ExtensionField {
     pub key: String,
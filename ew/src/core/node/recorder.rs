@@ -0,0 +1,36 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Saves node API responses as test fixtures.
+///
+/// Responses are saved under a path mirroring the request's, e.g. the
+/// response to `/blocks/at/10` goes to `<dir>/blocks/at/10.json`.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    dir: PathBuf,
+}
+
+impl Recorder {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// Saves `body` of response to request `path` (without leading slash).
+    ///
+    /// Failing to save a fixture is not worth failing the request for,
+    /// so errors are logged only.
+    pub async fn save(&self, path: &str, body: &str) {
+        let file = self.dir.join(format!("{path}.json"));
+        let res = async {
+            if let Some(parent) = file.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&file, body).await
+        };
+        if let Err(e) = res.await {
+            tracing::warn!("could not record {}: {e}", file.display());
+        }
+    }
+}
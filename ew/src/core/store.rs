@@ -1,11 +1,16 @@
 mod addresses;
+mod blocks;
 mod boxes;
+mod data_inputs;
 mod headers;
+mod inputs;
 mod meta;
 mod tokens;
+mod transactions;
 
 use lru::LruCache;
 use std::collections::HashMap;
+use tokio_postgres::GenericClient;
 use tokio_postgres::NoTls;
 
 use super::ergo;
@@ -18,12 +23,42 @@ use super::types::BoxData;
 use super::types::BoxID;
 use super::types::CoreData;
 use super::types::Header;
+use super::types::HeaderID;
 use super::types::Height;
 use super::types::Registers;
 use super::types::TokenID;
 use super::types::Transaction;
 use crate::config::PostgresConfig;
-use crate::utils::Schema;
+use crate::config::TrackerConfig;
+use crate::error::EwError;
+use crate::framework::store::Revision;
+use crate::framework::store::StoreDef;
+use crate::utils::CopyTypes;
+
+/// Lookups used by the query API, alert rules and the mempool worker.
+pub(crate) mod api {
+    pub(crate) use super::addresses::get_id_opt as get_address_id;
+    pub(crate) use super::ergo::ergo_tree::base16_to_address;
+    pub(crate) use super::headers::get_height_at;
+    pub(crate) use super::headers::get_timestamp_at;
+    pub(crate) use super::inputs::get_spending as get_box_spending;
+    pub(crate) use super::tokens::get_id_opt as get_asset_id;
+    pub(crate) use super::transactions::get_since as get_transactions_since;
+    pub(crate) use super::AddressCache;
+}
+
+pub(crate) const SCHEMA: StoreDef = StoreDef {
+    schema_name: "core",
+    worker_id: "core",
+    sql: include_str!("store/schema.sql"),
+    revision: &Revision { major: 1, minor: 3 },
+    migrations: &[
+        &migrations::Mig1_1 {},
+        &migrations::Mig1_2 {},
+        &migrations::Mig1_3 {},
+    ],
+    has_header: false,
+};
 
 #[derive(Debug)]
 pub(super) struct Store {
@@ -31,11 +66,12 @@ pub(super) struct Store {
     header: Header,
     address_cache: AddressCache,
     asset_cache: AssetCache,
+    copy_types: CopyTypes,
 }
 
 #[derive(Debug)]
 /// Cached data to speed up ergo tree to address id conversion.
-struct AddressCache {
+pub(crate) struct AddressCache {
     /// Keep track of highest address id
     pub address_count: i64,
     /// Maps ergo trees to an address id (global index)
@@ -43,10 +79,10 @@ struct AddressCache {
 }
 
 impl AddressCache {
-    pub fn new(last_address_id: AddressID) -> Self {
+    pub fn new(last_address_id: AddressID, size: usize) -> Self {
         Self {
             address_count: last_address_id.sequence_position(),
-            lru: LruCache::new(std::num::NonZeroUsize::new(5000).unwrap()),
+            lru: LruCache::new(std::num::NonZeroUsize::new(size).unwrap()),
         }
     }
 
@@ -55,6 +91,26 @@ impl AddressCache {
         self.lru.clear();
         self.address_count = last_address_id.sequence_position();
     }
+
+    /// Retrieve id of an already indexed `ergo_tree`, if any.
+    ///
+    /// Unlike `map_address_id`, never indexes new addresses, so can be
+    /// used outside of the tracker. Unknown trees are not cached.
+    pub async fn lookup(
+        &mut self,
+        client: &impl GenericClient,
+        ergo_tree: &str,
+    ) -> Result<Option<AddressID>, EwError> {
+        if let Some(id) = self.lru.get(ergo_tree) {
+            return Ok(Some(*id));
+        }
+        let address = ergo::ergo_tree::base16_to_address(ergo_tree);
+        let id = addresses::get_id_opt(client, &address).await?;
+        if let Some(id) = id {
+            self.lru.put(ergo_tree.to_owned(), id);
+        }
+        Ok(id)
+    }
 }
 
 #[derive(Debug)]
@@ -67,10 +123,10 @@ struct AssetCache {
 }
 
 impl AssetCache {
-    pub fn new(last_asset_id: AssetID) -> Self {
+    pub fn new(last_asset_id: AssetID, size: usize) -> Self {
         Self {
             last_id: last_asset_id,
-            lru: LruCache::new(std::num::NonZeroUsize::new(5000).unwrap()),
+            lru: LruCache::new(std::num::NonZeroUsize::new(size).unwrap()),
         }
     }
 
@@ -82,61 +138,62 @@ impl AssetCache {
 }
 
 impl Store {
-    pub async fn new(pgconf: PostgresConfig) -> Self {
+    pub async fn new(pgconf: PostgresConfig, config: &TrackerConfig) -> Result<Self, EwError> {
         tracing::debug!("initializing new store");
-        let (mut client, connection) = tokio_postgres::connect(&pgconf.connection_uri, NoTls)
-            .await
-            .unwrap();
+        let (mut client, connection) =
+            tokio_postgres::connect(&pgconf.connection_uri, NoTls).await?;
 
         tokio::spawn(async move {
             if let Err(e) = connection.await {
-                eprintln!("connection error: {}", e);
+                tracing::error!("connection error: {}", e);
             }
         });
 
-        let schema = Schema::new("core", include_str!("store/schema.sql"));
-        schema.init(&mut client).await;
+        // Ensure migrations are applied
+        super::migrate(&pgconf).await?;
 
         let header = headers::get_last_main(&client)
-            .await
+            .await?
             .unwrap_or(Header::initial());
         tracing::debug!("current header: {:?}", &header);
-        let last_address_id = addresses::get_max_id(&mut client).await;
-        let last_asset_id = tokens::get_max_id(&mut client).await;
+        let last_address_id = addresses::get_max_id(&mut client).await?;
+        let last_asset_id = tokens::get_max_id(&mut client).await?;
 
-        Self {
+        Ok(Self {
             client,
             header,
-            address_cache: AddressCache::new(last_address_id),
-            asset_cache: AssetCache::new(last_asset_id),
-        }
+            address_cache: AddressCache::new(last_address_id, config.address_cache_size),
+            asset_cache: AssetCache::new(last_asset_id, config.asset_cache_size),
+            copy_types: CopyTypes::default(),
+        })
     }
 
     pub(super) fn header(&self) -> &Header {
         &self.header
     }
 
-    pub(super) async fn contains_header(&self, header: &Header) -> bool {
+    pub(super) async fn contains_header(&self, header: &Header) -> Result<bool, EwError> {
         headers::exists_and_is_main_chain(&self.client, header).await
     }
 
-    pub(super) async fn include_genesis_boxes(&mut self, boxes: String) {
+    pub(super) async fn include_genesis_boxes(&mut self, boxes: String) -> Result<(), EwError> {
         tracing::info!("including genesis boxes");
         assert!(self.header.is_initial());
-        self.header = Header::genesis();
-        let height = self.header.height;
+        let header = Header::genesis();
+        let height = header.height;
 
-        let pgtx = self.client.transaction().await.unwrap();
+        let pgtx = self.client.transaction().await?;
 
         // Index dummy header for genesis
-        headers::insert_main(&pgtx, &self.header).await;
+        headers::insert_main(&pgtx, &header).await?;
 
         // Index genesis boxes
-        let node_boxes: Vec<node::models::Output> = serde_json::from_str(&boxes).unwrap();
+        let node_boxes: Vec<node::models::Output> =
+            serde_json::from_str(&boxes).map_err(|_| node::NodeError::DeserializationError)?;
         let mut box_records: Vec<boxes::BoxRecord> = vec![];
         for op in &node_boxes {
             let address_id =
-                map_address_id(&pgtx, &op.ergo_tree, height, &mut self.address_cache).await;
+                map_address_id(&pgtx, &op.ergo_tree, height, &mut self.address_cache).await?;
             box_records.push(boxes::BoxRecord {
                 box_id: &op.box_id,
                 height,
@@ -144,19 +201,26 @@ impl Store {
                 address_id,
                 value: op.value,
                 size: ergo::boxes::calc_box_size(&op).unwrap(),
-                assets: map_asset_ids(&pgtx, &op.assets, height, &mut self.asset_cache).await,
+                assets: map_asset_ids(&pgtx, &op.assets, height, &mut self.asset_cache).await?,
                 registers: &op.additional_registers,
+                tx_id: None,
+                output_idx: None,
             });
         }
-        boxes::insert_many(&pgtx, &box_records).await;
+        boxes::insert_many(&pgtx, &self.copy_types, &box_records).await?;
 
-        pgtx.commit().await.unwrap();
+        pgtx.commit().await?;
+        self.header = header;
+        Ok(())
     }
 
     /// Include and expand block.
     ///
     /// Skips inclusion if block already processed.
-    pub(super) async fn process(&mut self, node_block: node::models::Block) -> CoreData {
+    pub(super) async fn process(
+        &mut self,
+        node_block: node::models::Block,
+    ) -> Result<CoreData, EwError> {
         /*
 
         if next block:
@@ -211,17 +275,24 @@ impl Store {
         }
 
         // Wrap everyting in one db transaction
-        let pgtx = self.client.transaction().await.unwrap();
-
-        // Update head
+        let pgtx = self.client.transaction().await?;
+
+        // New head
+        let header = Header {
+            height: node_block.header.height,
+            timestamp: node_block.header.timestamp,
+            header_id: node_block.header.id.clone(),
+            parent_id: node_block.header.parent_id.clone(),
+        };
         if is_next_block {
-            self.header = Header {
-                height: node_block.header.height,
-                timestamp: node_block.header.timestamp,
-                header_id: node_block.header.id.clone(),
-                parent_id: node_block.header.parent_id.clone(),
-            };
-            headers::insert_main(&pgtx, &self.header).await;
+            headers::insert_main(&pgtx, &header).await?;
+            blocks::insert(
+                &pgtx,
+                &node_block.header,
+                &node_block.extension,
+                node_block.size,
+            )
+            .await?;
         }
 
         // Index/load outputs
@@ -231,8 +302,9 @@ impl Store {
                 &node_block,
                 &mut self.address_cache,
                 &mut self.asset_cache,
+                &self.copy_types,
             )
-            .await
+            .await?
         } else {
             let output_box_ids: Vec<&BoxID> = node_block
                 .block_transactions
@@ -240,9 +312,14 @@ impl Store {
                 .iter()
                 .flat_map(|tx| tx.outputs.iter().map(|op| &op.box_id))
                 .collect();
-            boxes::map_boxes(&pgtx, output_box_ids).await
+            boxes::map_boxes(&pgtx, output_box_ids).await?
         };
 
+        // Index transactions, inputs and data-inputs
+        if is_next_block {
+            index_transactions(&pgtx, &self.copy_types, &node_block).await?;
+        }
+
         // Load data-inputs
         let data_input_box_ids: Vec<&BoxID> = node_block
             .block_transactions
@@ -251,7 +328,7 @@ impl Store {
             .flat_map(|tx| tx.data_inputs.iter().map(|di| &di.box_id))
             .collect();
         let data_inputs: HashMap<BoxID, BoxData> =
-            boxes::map_boxes(&pgtx, data_input_box_ids).await;
+            boxes::map_boxes(&pgtx, data_input_box_ids).await?;
 
         // Load inputs
         // TODO: consider finding some inputs in the outputs (e.g. fee boxes)
@@ -262,7 +339,7 @@ impl Store {
             .iter()
             .flat_map(|tx| tx.inputs.iter().map(|ip| &ip.box_id))
             .collect();
-        let mut inputs: HashMap<BoxID, BoxData> = boxes::map_boxes(&pgtx, input_box_ids).await;
+        let mut inputs: HashMap<BoxID, BoxData> = boxes::map_boxes(&pgtx, input_box_ids).await?;
 
         // Convert to core block
         let core_block = Block {
@@ -298,67 +375,129 @@ impl Store {
             size: node_block.size,
         };
 
-        pgtx.commit().await.unwrap();
+        pgtx.commit().await?;
 
-        CoreData { block: core_block }
+        // Only move head once committed
+        if is_next_block {
+            self.header = header;
+        }
+
+        Ok(CoreData { block: core_block })
+    }
+
+    /// Rebuild processed main chain block `header_id` at `height` from store.
+    ///
+    /// Returns None if the block is not in the store, or was processed
+    /// before block data was kept. Errors if any of its inputs or data-inputs
+    /// is missing, e.g. on a partially restored database.
+    pub(super) async fn get_block(
+        &self,
+        height: Height,
+        header_id: &HeaderID,
+    ) -> Result<Option<CoreData>, EwError> {
+        let record = match blocks::get_main_at(&self.client, height, header_id).await? {
+            Some(r) => r,
+            None => return Ok(None),
+        };
+        let layout = blocks::get_layout_at(&self.client, height).await?;
+
+        // Load all boxes involved in one go
+        let box_ids: Vec<&BoxID> = layout
+            .iter()
+            .flat_map(|tx| tx.outputs.iter().chain(&tx.data_inputs).chain(&tx.inputs))
+            .collect();
+        let boxes: HashMap<BoxID, BoxData> = boxes::map_boxes(&self.client, box_ids).await?;
+        let get_box = |box_id: &BoxID| {
+            boxes
+                .get(box_id)
+                .cloned()
+                .ok_or_else(|| EwError::MissingBox(box_id.clone(), height))
+        };
+
+        let mut transactions = Vec::with_capacity(layout.len());
+        for (i, tx) in layout.into_iter().enumerate() {
+            transactions.push(Transaction {
+                index: i as i32,
+                outputs: tx.outputs.iter().map(get_box).collect::<Result<_, _>>()?,
+                data_inputs: tx
+                    .data_inputs
+                    .iter()
+                    .map(get_box)
+                    .collect::<Result<_, _>>()?,
+                inputs: tx.inputs.iter().map(get_box).collect::<Result<_, _>>()?,
+                id: tx.id,
+            });
+        }
+        let core_block = Block {
+            header: record.header.into(),
+            transactions,
+            extension: record.extension,
+            size: record.size,
+        };
+        Ok(Some(CoreData { block: core_block }))
     }
 
     /// Roll back block with given `header`.
     ///
     /// Must be the last included block.
     /// Returna headwe representing previous block in store.
-    pub(super) async fn roll_back(&mut self, header: &Header) -> Header {
+    pub(super) async fn roll_back(&mut self, header: &Header) -> Result<Header, EwError> {
         assert_eq!(&self.header, header);
 
-        let pgtx = self.client.transaction().await.unwrap();
+        let pgtx = self.client.transaction().await?;
 
         // Delete main chain header at height h
-        headers::delete_main_at(&pgtx, header.height).await;
+        headers::delete_main_at(&pgtx, header.height).await?;
 
-        // Delete boxes registered ar height h
-        boxes::delete_at(&pgtx, header.height).await;
+        // Delete block data, transactions and boxes registered ar height h
+        blocks::delete_at(&pgtx, header.height).await?;
+        transactions::delete_at(&pgtx, header.height).await?;
+        inputs::delete_at(&pgtx, header.height).await?;
+        data_inputs::delete_at(&pgtx, header.height).await?;
+        boxes::delete_at(&pgtx, header.height).await?;
 
         // Delete addresses spotted at height h
-        let n_deleted = addresses::delete_at(&pgtx, header.height).await;
+        let n_deleted = addresses::delete_at(&pgtx, header.height).await?;
         // Reset the cache if there where any new ones in rolled back block.
         // A bit radical, but not taking any risks. This will only affect synced
         // cursors anyway, so block processing time is less of an issue.
         if n_deleted > 0 {
-            self.address_cache.reset(addresses::get_max_id(&pgtx).await)
+            self.address_cache
+                .reset(addresses::get_max_id(&pgtx).await?)
         }
 
         // Delete tokens spotted at height h
-        let n_deleted = tokens::delete_at(&pgtx, header.height).await;
+        let n_deleted = tokens::delete_at(&pgtx, header.height).await?;
         // Reset the cache if there where any new ones in rolled back block.
         // Same comments as for address cache above.
         if n_deleted > 0 {
-            self.asset_cache.reset(tokens::get_max_id(&pgtx).await)
+            self.asset_cache.reset(tokens::get_max_id(&pgtx).await?)
         }
 
-        pgtx.commit().await.unwrap();
+        pgtx.commit().await?;
 
         // Retrieve previous header
         let prev_header = headers::get_last_main(&self.client)
-            .await
+            .await?
             .expect("Rollback implies previous headers");
 
         // Decrement store head and return
         self.header = prev_header.clone();
-        prev_header
+        Ok(prev_header)
     }
 
     /// Checks if store contains genesis boxes
-    pub(super) async fn has_genesis_boxes(&self) -> bool {
+    pub(super) async fn has_genesis_boxes(&self) -> Result<bool, EwError> {
         let qry = "
             select exists(
                 select *
                 from core.boxes
                 where height = 0
             );";
-        self.client.query_one(qry, &[]).await.unwrap().get(0)
+        Ok(self.client.query_one(qry, &[]).await?.get(0))
     }
 
-    pub(super) async fn get_genesis_boxes(&mut self) -> Vec<BoxData> {
+    pub(super) async fn get_genesis_boxes(&mut self) -> Result<Vec<BoxData>, EwError> {
         tracing::debug!("getting genesis boxes");
         boxes::get_genesis_boxes(&self.client).await
     }
@@ -375,13 +514,14 @@ async fn index_outputs(
     node_block: &node::models::Block,
     address_cache: &mut AddressCache,
     asset_cache: &mut AssetCache,
-) -> HashMap<BoxID, BoxData> {
+    copy_types: &CopyTypes,
+) -> Result<HashMap<BoxID, BoxData>, EwError> {
     let height = node_block.header.height;
     let mut box_records: Vec<boxes::BoxRecord> = vec![];
     for tx in &node_block.block_transactions.transactions {
-        for op in &tx.outputs {
-            let address_id = map_address_id(pgtx, &op.ergo_tree, height, address_cache).await;
-            let assets = map_asset_ids(pgtx, &op.assets, height, asset_cache).await;
+        for (i, op) in tx.outputs.iter().enumerate() {
+            let address_id = map_address_id(pgtx, &op.ergo_tree, height, address_cache).await?;
+            let assets = map_asset_ids(pgtx, &op.assets, height, asset_cache).await?;
             let size = match ergo::boxes::calc_box_size(&op) {
                 Some(s) => s,
                 // Current calculation of box size can fail when hitting
@@ -400,11 +540,13 @@ async fn index_outputs(
                 size,
                 assets,
                 registers: &op.additional_registers,
+                tx_id: Some(&tx.id),
+                output_idx: Some(i as i32),
             });
         }
     }
     // Store records
-    boxes::insert_many(&pgtx, &box_records).await;
+    boxes::insert_many(&pgtx, copy_types, &box_records).await?;
 
     // Convert box records to box data and return hashmap
     let mut map = HashMap::new();
@@ -424,7 +566,62 @@ async fn index_outputs(
         };
         map.insert(box_data.box_id.clone(), box_data);
     }
-    map
+    Ok(map)
+}
+
+/// Saves transactions of given `node_block` along with the boxes they spend
+/// and read as data-inputs.
+async fn index_transactions(
+    pgtx: &tokio_postgres::Transaction<'_>,
+    copy_types: &CopyTypes,
+    node_block: &node::models::Block,
+) -> Result<(), EwError> {
+    let height = node_block.header.height;
+    let txs = &node_block.block_transactions.transactions;
+    let tx_records: Vec<transactions::TransactionRecord> = txs
+        .iter()
+        .enumerate()
+        .map(|(i, tx)| transactions::TransactionRecord {
+            tx_id: &tx.id,
+            height,
+            tx_idx: i as i32,
+            size: tx.size,
+        })
+        .collect();
+    transactions::insert_many(pgtx, copy_types, &tx_records).await?;
+
+    let input_records: Vec<inputs::InputRecord> = txs
+        .iter()
+        .flat_map(|tx| {
+            tx.inputs
+                .iter()
+                .enumerate()
+                .map(|(i, ip)| inputs::InputRecord {
+                    box_id: &ip.box_id,
+                    tx_id: &tx.id,
+                    idx: i as i32,
+                    height,
+                })
+        })
+        .collect();
+    inputs::insert_many(pgtx, copy_types, &input_records).await?;
+
+    let data_input_records: Vec<data_inputs::DataInputRecord> = txs
+        .iter()
+        .flat_map(|tx| {
+            tx.data_inputs
+                .iter()
+                .enumerate()
+                .map(|(i, di)| data_inputs::DataInputRecord {
+                    tx_id: &tx.id,
+                    idx: i as i32,
+                    box_id: &di.box_id,
+                    height,
+                })
+        })
+        .collect();
+    data_inputs::insert_many(pgtx, copy_types, &data_input_records).await?;
+    Ok(())
 }
 
 /// Return an address id for the given `ergo_tree`.
@@ -439,16 +636,16 @@ async fn map_address_id(
     ergo_tree: &String,
     spot_height: Height,
     cache: &mut AddressCache,
-) -> AddressID {
+) -> Result<AddressID, EwError> {
     // Try the cache first.
-    match cache.lru.get(ergo_tree) {
+    Ok(match cache.lru.get(ergo_tree) {
         // Sweet, found it in the cache
         Some(id) => *id,
         // Not in the cache
         None => {
             // See if we can find it in the store.
             let address = ergo::ergo_tree::base16_to_address(ergo_tree);
-            let uncached_id = match addresses::get_id_opt(&pgtx, &address).await {
+            let uncached_id = match addresses::get_id_opt(pgtx, &address).await? {
                 // Address was in store already
                 Some(id) => id,
                 // This is a new address - assign new id and index
@@ -459,7 +656,7 @@ async fn map_address_id(
                         &pgtx,
                         &addresses::AddressRecord::new(address_id, spot_height, address),
                     )
-                    .await;
+                    .await?;
                     address_id
                 }
             };
@@ -467,7 +664,7 @@ async fn map_address_id(
             cache.lru.put(ergo_tree.clone(), uncached_id);
             uncached_id
         }
-    }
+    })
 }
 
 /// Converts token id's (Digest32) to gid's (i64).
@@ -482,9 +679,9 @@ async fn map_asset_ids(
     node_assets: &Vec<node::models::Asset>,
     spot_height: Height,
     cache: &mut AssetCache,
-) -> Option<Vec<Asset>> {
+) -> Result<Option<Vec<Asset>>, EwError> {
     if node_assets.is_empty() {
-        return None;
+        return Ok(None);
     }
     let mut assets: Vec<Asset> = Vec::with_capacity(node_assets.len());
     for node_asset in node_assets {
@@ -496,7 +693,7 @@ async fn map_asset_ids(
             None => {
                 // See if we can find it in the store.
                 let token_id = node_asset.token_id.clone();
-                let uncached_id = match tokens::get_id_opt(&pgtx, &token_id).await {
+                let uncached_id = match tokens::get_id_opt(pgtx, &token_id).await? {
                     // Token was in store already
                     Some(id) => id,
                     None => {
@@ -506,7 +703,7 @@ async fn map_asset_ids(
                             &pgtx,
                             &tokens::TokenRecord::new(cache.last_id, spot_height, token_id.clone()),
                         )
-                        .await;
+                        .await?;
                         cache.last_id
                     }
                 };
@@ -520,5 +717,161 @@ async fn map_asset_ids(
             amount: node_asset.amount,
         });
     }
-    Some(assets)
+    Ok(Some(assets))
+}
+
+pub(super) mod migrations {
+
+    use async_trait::async_trait;
+    use tokio_postgres::Transaction;
+
+    use crate::error::EwError;
+    use crate::framework::store::Migration;
+    use crate::framework::store::MigrationEffect;
+    use crate::framework::store::Revision;
+
+    /// Migration for revision 1.1
+    #[derive(Debug)]
+    pub struct Mig1_1 {}
+
+    #[async_trait]
+    impl Migration for Mig1_1 {
+        fn description(&self) -> &'static str {
+            "Keep block data needed to serve lagging cursors"
+        }
+
+        fn revision(&self) -> Revision {
+            Revision::new(1, 1)
+        }
+
+        fn sql(&self) -> Option<&'static str> {
+            Some(
+                "
+                create table core.blocks (
+                    height integer primary key,
+                    header json not null,
+                    extension json not null,
+                    size integer not null,
+                    transactions json not null
+                );",
+            )
+        }
+
+        async fn run(&self, pgtx: &Transaction<'_>) -> Result<MigrationEffect, EwError> {
+            pgtx.batch_execute(self.sql().unwrap()).await?;
+
+            Ok(MigrationEffect::None)
+        }
+    }
+
+    /// Migration for revision 1.2
+    #[derive(Debug)]
+    pub struct Mig1_2 {}
+
+    #[async_trait]
+    impl Migration for Mig1_2 {
+        fn description(&self) -> &'static str {
+            // Only recorded for blocks included from this revision on,
+            // as tracked by core.transactions_since.
+            "Index transactions, spending links and data-inputs"
+        }
+
+        fn revision(&self) -> Revision {
+            Revision::new(1, 2)
+        }
+
+        fn sql(&self) -> Option<&'static str> {
+            Some(
+                "
+                create table core.transactions (
+                    tx_id varchar(64) collate \"C\" primary key,
+                    height integer not null,
+                    tx_idx integer not null,
+                    size integer not null
+                );
+                create index on core.transactions using brin(height);
+
+                create table core.transactions_since (
+                    singleton int primary key default 1,
+                    height integer not null,
+                    check(singleton = 1)
+                );
+                insert into core.transactions_since (height)
+                select coalesce(max(height) + 1, 0)
+                from core.headers
+                where main_chain;
+
+                create table core.inputs (
+                    box_id varchar(64) collate \"C\" primary key,
+                    tx_id varchar(64) collate \"C\" not null,
+                    idx integer not null,
+                    height integer not null
+                );
+                create index on core.inputs (tx_id);
+                create index on core.inputs using brin(height);
+
+                create table core.data_inputs (
+                    tx_id varchar(64) collate \"C\" not null,
+                    idx integer not null,
+                    box_id varchar(64) collate \"C\" not null,
+                    height integer not null,
+                    primary key (tx_id, idx)
+                );
+                create index on core.data_inputs (box_id);
+                create index on core.data_inputs using brin(height);
+
+                alter table core.boxes add column tx_id varchar(64) collate \"C\";",
+            )
+        }
+
+        async fn run(&self, pgtx: &Transaction<'_>) -> Result<MigrationEffect, EwError> {
+            pgtx.batch_execute(self.sql().unwrap()).await?;
+
+            Ok(MigrationEffect::None)
+        }
+    }
+
+    /// Migration for revision 1.3
+    #[derive(Debug)]
+    pub struct Mig1_3 {}
+
+    #[async_trait]
+    impl Migration for Mig1_3 {
+        fn description(&self) -> &'static str {
+            "Derive block layouts from transaction tables"
+        }
+
+        fn revision(&self) -> Revision {
+            Revision::new(1, 3)
+        }
+
+        fn sql(&self) -> Option<&'static str> {
+            // Blocks kept before transactions were indexed can't be rebuilt
+            // anymore and get requested from the node again.
+            Some(
+                "
+                alter table core.boxes add column output_idx integer;
+
+                update core.boxes b
+                set output_idx = o.n - 1
+                from core.blocks k
+                    , json_array_elements(k.transactions) t
+                    , json_array_elements_text(t->'outputs') with ordinality o(box_id, n)
+                where b.box_id = o.box_id;
+
+                delete from core.blocks k
+                where not exists (
+                    select from core.transactions t where t.height = k.height
+                );
+
+                alter table core.blocks drop column transactions;",
+            )
+        }
+
+        async fn run(&self, pgtx: &Transaction<'_>) -> Result<MigrationEffect, EwError> {
+            pgtx.batch_execute(self.sql().unwrap()).await?;
+
+            Ok(MigrationEffect::None)
+        }
+    }
 }
@@ -4,6 +4,7 @@ use tokio_postgres::Transaction;
 use crate::core::types::Address;
 use crate::core::types::AddressID;
 use crate::core::types::Height;
+use crate::error::EwError;
 
 #[derive(Debug)]
 pub struct AddressRecord {
@@ -23,39 +24,42 @@ impl AddressRecord {
 }
 
 /// Retrieve id of a possibly unknown address.
-pub(super) async fn get_id_opt(pgtx: &Transaction<'_>, address: &Address) -> Option<AddressID> {
+pub(crate) async fn get_id_opt(
+    pgtx: &impl GenericClient,
+    address: &Address,
+) -> Result<Option<AddressID>, EwError> {
     let qry = "select core.address_id($1);";
     // core.address_id() will return null for an unknown address,
     // so there's always a row.
-    pgtx.query_one(qry, &[address]).await.unwrap().get(0)
+    Ok(pgtx.query_one(qry, &[address]).await?.get(0))
 }
 
 /// Retrieve highest address id.
-pub(super) async fn get_max_id(client: &impl GenericClient) -> AddressID {
+pub(super) async fn get_max_id(client: &impl GenericClient) -> Result<AddressID, EwError> {
     let qry = "select max(id) from core.addresses;";
-    match client.query_one(qry, &[]).await.unwrap().get(0) {
+    Ok(match client.query_one(qry, &[]).await?.get(0) {
         Some(id) => id,
         None => AddressID::zero(),
-    }
+    })
 }
 
 /// Insert new address and get new id back.
-pub(super) async fn index_new(pgtx: &Transaction<'_>, rec: &AddressRecord) {
+pub(super) async fn index_new(pgtx: &Transaction<'_>, rec: &AddressRecord) -> Result<(), EwError> {
     tracing::trace!("inserting address: {:?}", rec);
     let stmt = "insert into core.addresses(id, spot_height, address) values ($1, $2, $3)";
     pgtx.execute(stmt, &[&rec.id, &rec.spot_height, &rec.address])
-        .await
-        .unwrap();
+        .await?;
+    Ok(())
 }
 
 /// Delete addresses spotted at `height`.
 ///
 /// Returns number of deleted rows
-pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: Height) -> u64 {
-    pgtx.execute(
-        "delete from core.addresses where spot_height = $1;",
-        &[&height],
-    )
-    .await
-    .unwrap()
+pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: Height) -> Result<u64, EwError> {
+    Ok(pgtx
+        .execute(
+            "delete from core.addresses where spot_height = $1;",
+            &[&height],
+        )
+        .await?)
 }
@@ -0,0 +1,130 @@
+use tokio_postgres::Client;
+use tokio_postgres::Transaction;
+
+use crate::core::node::models::Extension;
+use crate::core::node::models::Header;
+use crate::core::types::BoxID;
+use crate::core::types::HeaderID;
+use crate::core::types::Height;
+use crate::core::types::TransactionID;
+use crate::error::EwError;
+
+/// Box ids of a block transaction.
+#[derive(Debug)]
+pub struct TransactionLayout {
+    pub id: TransactionID,
+    pub inputs: Vec<BoxID>,
+    pub data_inputs: Vec<BoxID>,
+    pub outputs: Vec<BoxID>,
+}
+
+/// A record from the `core.blocks` table.
+#[derive(Debug)]
+pub struct BlockRecord {
+    pub header: Header,
+    pub extension: Extension,
+    pub size: i32,
+}
+
+/// Insert block data not held by other core tables.
+pub(super) async fn insert(
+    pgtx: &Transaction<'_>,
+    header: &Header,
+    extension: &Extension,
+    size: i32,
+) -> Result<(), EwError> {
+    tracing::trace!("insert {}", header.height);
+    let stmt = "
+        insert into core.blocks (height, header, extension, size)
+        values ($1, $2, $3, $4);";
+    pgtx.execute(
+        stmt,
+        &[
+            &header.height,
+            &serde_json::to_value(header).unwrap(),
+            &serde_json::to_value(extension).unwrap(),
+            &size,
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+/// Get record of main chain block `header_id` at given `height`, if any.
+pub(super) async fn get_main_at(
+    client: &Client,
+    height: Height,
+    header_id: &HeaderID,
+) -> Result<Option<BlockRecord>, EwError> {
+    tracing::trace!("get_main_at {height} {header_id}");
+    let qry = "
+        select b.header
+            , b.extension
+            , b.size
+        from core.blocks b
+        join core.headers h on h.height = b.height
+        where b.height = $1
+            and h.header_id = $2
+            and h.main_chain;";
+    Ok(client
+        .query_opt(qry, &[&height, header_id])
+        .await?
+        .map(|row| BlockRecord {
+            header: serde_json::from_value(row.get(0)).expect("stored header is valid"),
+            extension: serde_json::from_value(row.get(1)).expect("stored extension is valid"),
+            size: row.get(2),
+        }))
+}
+
+/// Get box ids of transactions included at `height`, in block order.
+///
+/// Derived from the transactions, inputs, data-inputs and boxes tables.
+pub(super) async fn get_layout_at(
+    client: &Client,
+    height: Height,
+) -> Result<Vec<TransactionLayout>, EwError> {
+    tracing::trace!("get_layout_at {height}");
+    let qry = "
+        select t.tx_id
+            , array(
+                select i.box_id
+                from core.inputs i
+                where i.tx_id = t.tx_id
+                order by i.idx
+            )
+            , array(
+                select d.box_id
+                from core.data_inputs d
+                where d.tx_id = t.tx_id
+                order by d.idx
+            )
+            , array(
+                select b.box_id
+                from core.boxes b
+                where b.height = t.height
+                    and b.tx_id = t.tx_id
+                order by b.output_idx
+            )
+        from core.transactions t
+        where t.height = $1
+        order by t.tx_idx;";
+    Ok(client
+        .query(qry, &[&height])
+        .await?
+        .iter()
+        .map(|row| TransactionLayout {
+            id: row.get(0),
+            inputs: row.get(1),
+            data_inputs: row.get(2),
+            outputs: row.get(3),
+        })
+        .collect())
+}
+
+/// Delete block record at `height`
+pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: Height) -> Result<(), EwError> {
+    tracing::trace!("delete_at {height}");
+    pgtx.execute("delete from core.blocks where height = $1;", &[&height])
+        .await?;
+    Ok(())
+}
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
+use tokio_postgres::types::ToSql;
 use tokio_postgres::Client;
+use tokio_postgres::GenericClient;
 use tokio_postgres::Transaction;
 
 use crate::core::types::AddressID;
@@ -10,6 +12,10 @@ use crate::core::types::Digest32;
 use crate::core::types::Height;
 use crate::core::types::NanoERG;
 use crate::core::types::Registers;
+use crate::core::types::TransactionID;
+use crate::error::EwError;
+use crate::utils::copy_in;
+use crate::utils::CopyTypes;
 
 /// A record from the `core.boxes` table.
 pub struct BoxRecord<'a> {
@@ -21,44 +27,61 @@ pub struct BoxRecord<'a> {
     pub size: i32,
     pub assets: Option<Vec<Asset>>,
     pub registers: &'a serde_json::Value,
+    /// Transaction creating the box, none for genesis boxes
+    pub tx_id: Option<&'a TransactionID>,
+    /// Position of the box within its transaction's outputs
+    pub output_idx: Option<i32>,
 }
 
-pub(super) async fn insert_many<'a>(pgtx: &Transaction<'_>, records: &Vec<BoxRecord<'a>>) {
-    let sql = "insert into core.boxes (
-        box_id,
-        height,
-        creation_height,
-        address_id,
-        value,
-        size,
-        assets,
-        registers
-    ) values ($1, $2, $3, $4, $5, $6, $7, $8);";
-    let stmt = pgtx.prepare(sql).await.unwrap();
-    for r in records {
-        pgtx.execute(
-            &stmt,
-            &[
-                &r.box_id,
+pub(super) async fn insert_many<'a>(
+    pgtx: &Transaction<'_>,
+    copy_types: &CopyTypes,
+    records: &Vec<BoxRecord<'a>>,
+) -> Result<(), EwError> {
+    let rows: Vec<Vec<&(dyn ToSql + Sync)>> = records
+        .iter()
+        .map(|r| {
+            vec![
+                r.box_id as &(dyn ToSql + Sync),
                 &r.height,
                 &r.creation_height,
                 &r.address_id,
                 &r.value,
                 &r.size,
                 &r.assets,
-                &r.registers,
-            ],
-        )
-        .await
-        .unwrap();
-    }
+                r.registers,
+                &r.tx_id,
+                &r.output_idx,
+            ]
+        })
+        .collect();
+    copy_in(
+        pgtx,
+        copy_types,
+        "core.boxes",
+        &[
+            "box_id",
+            "height",
+            "creation_height",
+            "address_id",
+            "value",
+            "size",
+            "assets",
+            "registers",
+            "tx_id",
+            "output_idx",
+        ],
+        &rows,
+    )
+    .await?;
+    Ok(())
 }
 
 /// Maps `box_ids` to corresponding BoxData.
 pub(super) async fn map_boxes(
-    pgtx: &Transaction<'_>,
+    client: &impl GenericClient,
     box_ids: Vec<&BoxID>,
-) -> HashMap<BoxID, BoxData> {
+) -> Result<HashMap<BoxID, BoxData>, EwError> {
     // tracing::debug!("mapping boxes");
     let mut map: HashMap<BoxID, BoxData> = HashMap::new();
     let qry = "
@@ -74,7 +97,7 @@ pub(super) async fn map_boxes(
         join core.headers h on h.height = b.height
         where b.box_id = any($1);";
 
-    let rows = pgtx.query(qry, &[&box_ids]).await.unwrap();
+    let rows = client.query(qry, &[&box_ids]).await?;
     for row in rows {
         let box_data = BoxData {
             box_id: row.get(0),
@@ -91,11 +114,11 @@ pub(super) async fn map_boxes(
         }
     }
     // tracing::debug!("mapped {} box(es)", map.len());
-    map
+    Ok(map)
 }
 
 /// Retrieves collection of BoxData representing genesis boxes.
-pub(super) async fn get_genesis_boxes(client: &Client) -> Vec<BoxData> {
+pub(super) async fn get_genesis_boxes(client: &Client) -> Result<Vec<BoxData>, EwError> {
     tracing::trace!("retrieving genesis boxes");
     let qry = "
         select b.box_id
@@ -109,8 +132,9 @@ pub(super) async fn get_genesis_boxes(client: &Client) -> Vec<BoxData> {
         from core.boxes b
         join core.headers h on h.height = b.height
         where h.height = 0;";
-    let rows = client.query(qry, &[]).await.unwrap();
-    rows.iter()
+    let rows = client.query(qry, &[]).await?;
+    Ok(rows
+        .iter()
         .map(|r| BoxData {
             box_id: r.get(0),
             creation_height: r.get(1),
@@ -121,12 +145,12 @@ pub(super) async fn get_genesis_boxes(client: &Client) -> Vec<BoxData> {
             additional_registers: Registers::new(r.get(6)),
             output_timestamp: r.get(7),
         })
-        .collect()
+        .collect())
 }
 
 /// Delete boxes created at `height`
-pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: Height) {
+pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: Height) -> Result<(), EwError> {
     pgtx.query("delete from core.boxes where height = $1;", &[&height])
-        .await
-        .unwrap();
+        .await?;
+    Ok(())
 }
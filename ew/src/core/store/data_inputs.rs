@@ -0,0 +1,47 @@
+use tokio_postgres::types::ToSql;
+use tokio_postgres::Transaction;
+
+use crate::core::types::BoxID;
+use crate::core::types::Height;
+use crate::core::types::TransactionID;
+use crate::error::EwError;
+use crate::utils::copy_in;
+use crate::utils::CopyTypes;
+
+/// A record from the `core.data_inputs` table.
+pub struct DataInputRecord<'a> {
+    pub tx_id: &'a TransactionID,
+    pub idx: i32,
+    pub box_id: &'a BoxID,
+    pub height: Height,
+}
+
+pub(super) async fn insert_many<'a>(
+    pgtx: &Transaction<'_>,
+    copy_types: &CopyTypes,
+    records: &Vec<DataInputRecord<'a>>,
+) -> Result<(), EwError> {
+    let rows: Vec<Vec<&(dyn ToSql + Sync)>> = records
+        .iter()
+        .map(|r| vec![r.tx_id as &(dyn ToSql + Sync), &r.idx, r.box_id, &r.height])
+        .collect();
+    copy_in(
+        pgtx,
+        copy_types,
+        "core.data_inputs",
+        &["tx_id", "idx", "box_id", "height"],
+        &rows,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Delete data-inputs of transactions included at `height`
+pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: Height) -> Result<(), EwError> {
+    pgtx.execute(
+        "delete from core.data_inputs where height = $1;",
+        &[&height],
+    )
+    .await?;
+    Ok(())
+}
@@ -1,11 +1,14 @@
 use tokio_postgres::Client;
+use tokio_postgres::GenericClient;
 use tokio_postgres::Transaction;
 
 use crate::core::types::Header;
 use crate::core::types::Height;
+use crate::core::types::Timestamp;
+use crate::error::EwError;
 
 /// Retrieve head from latest main chain header.
-pub(super) async fn get_last_main(client: &Client) -> Option<Header> {
+pub(super) async fn get_last_main(client: &Client) -> Result<Option<Header>, EwError> {
     tracing::trace!("get_last");
     let qry = "
         select height
@@ -17,16 +20,16 @@ pub(super) async fn get_last_main(client: &Client) -> Option<Header> {
         order by 1 desc
         limit 1;
     ";
-    client.query_opt(qry, &[]).await.unwrap().map(|row| Header {
+    Ok(client.query_opt(qry, &[]).await?.map(|row| Header {
         height: row.get(0),
         timestamp: row.get(1),
         header_id: row.get(2),
         parent_id: row.get(3),
-    })
+    }))
 }
 
 /// Insert new main chain header
-pub async fn insert_main(pgtx: &Transaction<'_>, header: &Header) {
+pub async fn insert_main(pgtx: &Transaction<'_>, header: &Header) -> Result<(), EwError> {
     tracing::trace!("insert {header:?}");
     let stmt = "
         insert into core.headers (height, timestamp, header_id, parent_id, main_chain)
@@ -40,23 +43,23 @@ pub async fn insert_main(pgtx: &Transaction<'_>, header: &Header) {
             &header.parent_id,
         ],
     )
-    .await
-    .unwrap();
+    .await?;
+    Ok(())
 }
 
 /// Delete main chain header at `height`
-pub async fn delete_main_at(pgtx: &Transaction<'_>, height: Height) {
+pub async fn delete_main_at(pgtx: &Transaction<'_>, height: Height) -> Result<(), EwError> {
     tracing::trace!("delete_main_at {height}");
     pgtx.execute(
         "delete from core.headers where height = $1 and main_chain;",
         &[&height],
     )
-    .await
-    .unwrap();
+    .await?;
+    Ok(())
 }
 
 /// Returns `true` if core.headers has a record for given `header` on main chain.
-pub async fn exists_and_is_main_chain(client: &Client, header: &Header) -> bool {
+pub async fn exists_and_is_main_chain(client: &Client, header: &Header) -> Result<bool, EwError> {
     tracing::trace!("exists_and_is_main_chain {header:?}");
     let sql = "
     select exists (
@@ -65,9 +68,38 @@ pub async fn exists_and_is_main_chain(client: &Client, header: &Header) -> bool
         from core.headers
         where height = $1 and header_id = $2 and main_chain
     );";
-    client
+    Ok(client
         .query_one(sql, &[&header.height, &header.header_id])
-        .await
-        .unwrap()
-        .get(0)
+        .await?
+        .get(0))
+}
+
+/// Timestamp of main chain block at given `height`, if any.
+pub(crate) async fn get_timestamp_at(
+    client: &impl GenericClient,
+    height: Height,
+) -> Result<Option<Timestamp>, EwError> {
+    let sql = "select timestamp from core.headers where height = $1 and main_chain;";
+    Ok(client
+        .query_opt(sql, &[&height])
+        .await?
+        .map(|row| row.get(0)))
+}
+
+/// Height of last main chain block with a timestamp not after given `timestamp`, if any.
+pub(crate) async fn get_height_at(
+    client: &impl GenericClient,
+    timestamp: Timestamp,
+) -> Result<Option<Height>, EwError> {
+    let sql = "
+        select height
+        from core.headers
+        where timestamp <= $1
+            and main_chain
+        order by height desc
+        limit 1;";
+    Ok(client
+        .query_opt(sql, &[&timestamp])
+        .await?
+        .map(|row| row.get(0)))
 }
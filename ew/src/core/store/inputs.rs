@@ -0,0 +1,72 @@
+use tokio_postgres::types::ToSql;
+use tokio_postgres::GenericClient;
+use tokio_postgres::Transaction;
+
+use crate::core::types::BoxID;
+use crate::core::types::Height;
+use crate::core::types::TransactionID;
+use crate::error::EwError;
+use crate::utils::copy_in;
+use crate::utils::CopyTypes;
+
+/// Creation height of a box, with the transaction spending it, if any.
+pub struct SpendingRecord {
+    pub height: Height,
+    pub spent_by: Option<(TransactionID, Height)>,
+}
+
+/// A record from the `core.inputs` table.
+pub struct InputRecord<'a> {
+    pub tx_id: &'a TransactionID,
+    pub idx: i32,
+    pub box_id: &'a BoxID,
+    pub height: Height,
+}
+
+pub(super) async fn insert_many<'a>(
+    pgtx: &Transaction<'_>,
+    copy_types: &CopyTypes,
+    records: &Vec<InputRecord<'a>>,
+) -> Result<(), EwError> {
+    let rows: Vec<Vec<&(dyn ToSql + Sync)>> = records
+        .iter()
+        .map(|r| vec![r.tx_id as &(dyn ToSql + Sync), &r.idx, r.box_id, &r.height])
+        .collect();
+    copy_in(
+        pgtx,
+        copy_types,
+        "core.inputs",
+        &["tx_id", "idx", "box_id", "height"],
+        &rows,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Delete inputs of transactions included at `height`
+pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: Height) -> Result<(), EwError> {
+    pgtx.execute("delete from core.inputs where height = $1;", &[&height])
+        .await?;
+    Ok(())
+}
+
+/// Get creation height and spending transaction of box `box_id`, if known.
+pub(crate) async fn get_spending(
+    client: &impl GenericClient,
+    box_id: &BoxID,
+) -> Result<Option<SpendingRecord>, EwError> {
+    let sql = "
+        select b.height
+            , i.tx_id
+            , i.height
+        from core.boxes b
+        left join core.inputs i on i.box_id = b.box_id
+        where b.box_id = $1;";
+    Ok(client.query_opt(sql, &[box_id]).await?.map(|row| {
+        let tx_id: Option<TransactionID> = row.get(1);
+        SpendingRecord {
+            height: row.get(0),
+            spent_by: tx_id.map(|tx_id| (tx_id, row.get(2))),
+        }
+    }))
+}
@@ -1,11 +1,4 @@
 create schema core;
-create table core._rev (
-	singleton int primary key default 1,
-	rev_major integer not null,
-	rev_minor integer not null,
-	check(singleton = 1)
-);
-insert into core._rev (rev_major, rev_minor) values (1, 0);
 
 create table core.headers (
     height integer primary key,
@@ -36,7 +29,11 @@ create table core.boxes (
 	value bigint not null,
 	size integer not null,
 	assets asset[], -- null when no assets
-	registers json not null
+	registers json not null,
+	-- Transaction creating the box, null for genesis boxes
+	tx_id varchar(64) collate "C",
+	-- Position of the box within its transaction's outputs
+	output_idx integer
 );
 create index on core.boxes using brin(height);
 
@@ -68,12 +65,56 @@ create table core.tokens (
 create index on core.tokens(token_id);
 create index on core.addresses using brin(spot_height);
 
--- create table core.transactions (
---     id bigint primary key,
---     spot_height integer not null,
---     base16_id text not null
--- );
--- create index on core.transactions using brin(spot_height);
+-- Block data not held by other core tables, to serve lagging cursors
+-- without requesting blocks from the node again.
+create table core.blocks (
+	height integer primary key,
+	-- Header and extension as returned by the node
+	header json not null,
+	extension json not null,
+	size integer not null
+);
+
+create table core.transactions (
+	tx_id varchar(64) collate "C" primary key,
+	height integer not null,
+	-- Position of the transaction within its block
+	tx_idx integer not null,
+	size integer not null
+);
+create index on core.transactions using brin(height);
+
+-- Lowest height transactions, inputs, data-inputs and box tx_id's are
+-- recorded from. Blocks included before core migration 1.2 have none.
+create table core.transactions_since (
+	singleton int primary key default 1,
+	height integer not null,
+	check(singleton = 1)
+);
+insert into core.transactions_since (height) values (0);
+
+-- Spent boxes, with the transaction spending them
+create table core.inputs (
+	box_id varchar(64) collate "C" primary key,
+	tx_id varchar(64) collate "C" not null,
+	-- Position of the input within its transaction
+	idx integer not null,
+	height integer not null
+);
+create index on core.inputs (tx_id);
+create index on core.inputs using brin(height);
+
+-- Boxes read as data-inputs, by transaction
+create table core.data_inputs (
+	tx_id varchar(64) collate "C" not null,
+	-- Position of the data-input within its transaction
+	idx integer not null,
+	box_id varchar(64) collate "C" not null,
+	height integer not null,
+	primary key (tx_id, idx)
+);
+create index on core.data_inputs (box_id);
+create index on core.data_inputs using brin(height);
 
 -- Don't need metadata for all tokens if using token-specific processing units
 -- core.tokens (
@@ -4,6 +4,7 @@ use tokio_postgres::Transaction;
 use crate::core::types::AssetID;
 use crate::core::types::Height;
 use crate::core::types::TokenID;
+use crate::error::EwError;
 
 #[derive(Debug)]
 pub struct TokenRecord {
@@ -23,40 +24,43 @@ impl TokenRecord {
 }
 
 /// Retrieve id of a possibly unknown token_id.
-pub(super) async fn get_id_opt(pgtx: &Transaction<'_>, token_id: &TokenID) -> Option<AssetID> {
+pub(crate) async fn get_id_opt(
+    pgtx: &impl GenericClient,
+    token_id: &TokenID,
+) -> Result<Option<AssetID>, EwError> {
     let qry = "select asset_id from core.tokens where token_id = $1;";
-    match pgtx.query_opt(qry, &[token_id]).await.unwrap() {
+    Ok(match pgtx.query_opt(qry, &[token_id]).await? {
         Some(row) => Some(row.get(0)),
         None => None,
-    }
+    })
 }
 
 /// Retrieve highest asset id.
-pub(super) async fn get_max_id(client: &impl GenericClient) -> AssetID {
+pub(super) async fn get_max_id(client: &impl GenericClient) -> Result<AssetID, EwError> {
     let qry = "select max(asset_id) from core.tokens;";
-    match client.query_one(qry, &[]).await.unwrap().get(0) {
+    Ok(match client.query_one(qry, &[]).await?.get(0) {
         Some(id) => id,
         None => 0,
-    }
+    })
 }
 
 /// Insert new token and get new id back.
-pub(super) async fn index_new(pgtx: &Transaction<'_>, rec: &TokenRecord) {
+pub(super) async fn index_new(pgtx: &Transaction<'_>, rec: &TokenRecord) -> Result<(), EwError> {
     tracing::trace!("inserting token: {:?}", rec);
     let stmt = "insert into core.tokens(asset_id, spot_height, token_id) values ($1, $2, $3)";
     pgtx.execute(stmt, &[&rec.asset_id, &rec.spot_height, &rec.token_id])
-        .await
-        .unwrap();
+        .await?;
+    Ok(())
 }
 
 /// Delete tokens spotted at `height`.
 ///
 /// Returns number of deleted rows.
-pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: Height) -> u64 {
-    pgtx.execute(
-        "delete from core.tokens where spot_height = $1;",
-        &[&height],
-    )
-    .await
-    .unwrap()
+pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: Height) -> Result<u64, EwError> {
+    Ok(pgtx
+        .execute(
+            "delete from core.tokens where spot_height = $1;",
+            &[&height],
+        )
+        .await?)
 }
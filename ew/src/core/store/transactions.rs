@@ -0,0 +1,62 @@
+use tokio_postgres::types::ToSql;
+use tokio_postgres::GenericClient;
+use tokio_postgres::Transaction;
+
+use crate::core::types::Height;
+use crate::core::types::TransactionID;
+use crate::error::EwError;
+use crate::utils::copy_in;
+use crate::utils::CopyTypes;
+
+/// A record from the `core.transactions` table.
+pub struct TransactionRecord<'a> {
+    pub tx_id: &'a TransactionID,
+    pub height: Height,
+    pub tx_idx: i32,
+    pub size: i32,
+}
+
+pub(super) async fn insert_many<'a>(
+    pgtx: &Transaction<'_>,
+    copy_types: &CopyTypes,
+    records: &Vec<TransactionRecord<'a>>,
+) -> Result<(), EwError> {
+    let rows: Vec<Vec<&(dyn ToSql + Sync)>> = records
+        .iter()
+        .map(|r| {
+            vec![
+                r.tx_id as &(dyn ToSql + Sync),
+                &r.height,
+                &r.tx_idx,
+                &r.size,
+            ]
+        })
+        .collect();
+    copy_in(
+        pgtx,
+        copy_types,
+        "core.transactions",
+        &["tx_id", "height", "tx_idx", "size"],
+        &rows,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Delete transactions included at `height`
+pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: Height) -> Result<(), EwError> {
+    pgtx.execute(
+        "delete from core.transactions where height = $1;",
+        &[&height],
+    )
+    .await?;
+    Ok(())
+}
+
+/// Lowest height transactions and spending links are recorded from.
+///
+/// Blocks included before core migration 1.2 have none.
+pub(crate) async fn get_since(client: &impl GenericClient) -> Result<Height, EwError> {
+    let sql = "select height from core.transactions_since;";
+    Ok(client.query_one(sql, &[]).await?.get(0))
+}
@@ -1,6 +1,10 @@
+mod archive_tracker;
 mod cursor;
 mod messages;
+mod node_watch;
+mod prefetch;
 mod tracker;
 
+pub use archive_tracker::ArchiveTracker;
 pub use messages::TrackingMessage;
 pub use tracker::Tracker;
@@ -0,0 +1,206 @@
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use crate::config::PostgresConfig;
+use crate::config::TrackerConfig;
+use crate::core::archive::BlockArchive;
+use crate::core::store::Store;
+use crate::core::tracking::cursor;
+use crate::core::tracking::cursor::Cursor;
+use crate::core::types::CoreData;
+use crate::core::types::Header;
+use crate::error::EwError;
+use crate::framework::Event;
+use crate::framework::ServedRequests;
+use crate::framework::Source;
+use crate::framework::SourceRequest;
+use crate::framework::SourceRequests;
+use crate::monitor::MonitorMessage;
+use crate::shutdown::CancellationToken;
+
+/// Replays blocks from a `BlockArchive`, without a node.
+///
+/// Serves the same data as a `Tracker` and shares its store. Archived
+/// blocks are settled, so there is nothing to roll back.
+pub struct ArchiveTracker {
+    archive: BlockArchive,
+    store: Store,
+    cursors: Vec<Cursor<CoreData>>,
+    monitor_tx: mpsc::Sender<MonitorMessage>,
+    /// Time to wait for more blocks once at the end of the archive
+    polling_interval: tokio::time::Duration,
+    requests: Option<SourceRequests<CoreData>>,
+}
+
+impl ArchiveTracker {
+    /// Create a new ArchiveTracker with default settings.
+    pub async fn new(
+        archive: BlockArchive,
+        pgconf: PostgresConfig,
+        monitor_tx: mpsc::Sender<MonitorMessage>,
+    ) -> Result<Self, EwError> {
+        Self::new_with(archive, pgconf, &TrackerConfig::default(), monitor_tx).await
+    }
+
+    /// Create a new ArchiveTracker with given `config`.
+    ///
+    /// Node related settings are ignored.
+    pub async fn new_with(
+        archive: BlockArchive,
+        pgconf: PostgresConfig,
+        config: &TrackerConfig,
+        monitor_tx: mpsc::Sender<MonitorMessage>,
+    ) -> Result<Self, EwError> {
+        let mut store = Store::new(pgconf, config).await?;
+
+        // Ensure genesis boxes are included before any worker subscribes.
+        if !store.has_genesis_boxes().await? {
+            let boxes = archive.genesis_boxes().await?;
+            store.include_genesis_boxes(boxes).await?;
+        }
+
+        Ok(Self {
+            archive,
+            store,
+            cursors: vec![],
+            monitor_tx,
+            polling_interval: config.polling_interval,
+            requests: None,
+        })
+    }
+
+    /// Serve given `requests` from source handles once started.
+    ///
+    /// Allows workers to subscribe while the tracker is running.
+    pub fn serve(&mut self, requests: SourceRequests<CoreData>) {
+        self.requests = Some(requests);
+    }
+
+    /// Get head of tracker's store.
+    pub fn header(&self) -> &Header {
+        self.store.header()
+    }
+
+    /// Replay archived blocks until `shutdown` is cancelled.
+    ///
+    /// Once at the end of the archive, keeps checking for blocks recorded
+    /// since. A block being processed is always completed before returning.
+    #[tracing::instrument(name = "archive", skip_all)]
+    pub async fn start(&mut self, shutdown: CancellationToken) -> Result<(), EwError> {
+        tracing::info!("starting");
+        let mut requests = match &self.requests {
+            Some(requests) => requests.lock().await,
+            None => ServedRequests::none(),
+        };
+        // Without any subscribed workers, keep indexing core data on
+        // a cursor of our own.
+        if self.cursors.is_empty() {
+            tracing::info!("no workers subscribed - tracking core only");
+            self.cursors.push(Cursor {
+                id: "main".to_owned(),
+                header: self.store.header().clone(),
+                txs: vec![],
+                monitor_tx: self.monitor_tx.clone(),
+            });
+        }
+        // Before starting, reorder cursors by decreasing position.
+        self.cursors.sort_by_key(|c| -c.header.height);
+        self.cursors[0].id = "main".to_owned();
+
+        // Ensure genesis boxes have been dispatched
+        for cur in &mut self.cursors {
+            cur.ensure_genesis_boxes(&mut self.store).await?;
+        }
+
+        let mut at_end = false;
+        while !shutdown.is_cancelled() {
+            let mut progressed = false;
+            for cur in &mut self.cursors {
+                if shutdown.is_cancelled() {
+                    break;
+                }
+                progressed |= cur.step_archive(&self.archive, &mut self.store).await?;
+            }
+            while let Some(request) = requests.try_recv() {
+                self.handle_request(request).await?;
+            }
+            cursor::merge(&mut self.cursors).await;
+            if progressed {
+                at_end = false;
+                continue;
+            }
+            if !at_end {
+                tracing::info!("reached end of archive at {:?}", self.store.header());
+                at_end = true;
+            }
+            tokio::select! {
+                biased;
+                _ = shutdown.cancelled() => (),
+                request = requests.recv() => self.handle_request(request).await?,
+                _ = tokio::time::sleep(self.polling_interval) => (),
+            }
+        }
+        tracing::info!("stopped");
+        Ok(())
+    }
+
+    /// Respond to a request from a source handle.
+    ///
+    /// Requesters that are gone are ignored.
+    async fn handle_request(&mut self, request: SourceRequest<CoreData>) -> Result<(), EwError> {
+        match request {
+            SourceRequest::Header(tx) => {
+                tx.send(self.store.header().clone()).ok();
+            }
+            SourceRequest::ContainsHeader(header, tx) => {
+                tx.send(self.contains_header(&header).await).ok();
+            }
+            SourceRequest::Subscribe(header, cursor_name, tx) => {
+                let rx = self.subscribe_cursor(header, &cursor_name);
+                // New cursors may start from scratch
+                for cur in &mut self.cursors {
+                    cur.ensure_genesis_boxes(&mut self.store).await?;
+                }
+                tx.send(rx).ok();
+            }
+        }
+        Ok(())
+    }
+
+    fn subscribe_cursor(
+        &mut self,
+        header: Header,
+        cursor_name: &str,
+    ) -> mpsc::Receiver<Event<CoreData>> {
+        cursor::subscribe(
+            &mut self.cursors,
+            self.store.header(),
+            header,
+            cursor_name,
+            &self.monitor_tx,
+        )
+    }
+}
+
+#[async_trait]
+impl Source for ArchiveTracker {
+    type S = CoreData;
+
+    async fn header(&self) -> Result<Header, EwError> {
+        Ok(self.store.header().clone())
+    }
+
+    async fn contains_header(&self, header: &Header) -> Result<bool, EwError> {
+        // Initial head is always contained but will not be stored,
+        // so hande explicitly.
+        Ok(header.is_initial() || self.store.contains_header(header).await?)
+    }
+
+    async fn subscribe(
+        &mut self,
+        header: Header,
+        cursor_name: &str,
+    ) -> Result<mpsc::Receiver<Event<CoreData>>, EwError> {
+        Ok(self.subscribe_cursor(header, cursor_name))
+    }
+}
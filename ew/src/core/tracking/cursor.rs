@@ -1,41 +1,53 @@
+use tokio::sync::mpsc;
+
+use crate::constants::settings::rollback_horizon;
+use crate::core::archive;
+use crate::core::archive::ArchiveRecorder;
+use crate::core::archive::BlockArchive;
 use crate::core::node::Node;
 use crate::core::node::NodeError;
 use crate::core::store::Store;
+use crate::core::tracking::prefetch::BlockPrefetch;
 use crate::core::types::Block;
 use crate::core::types::BlockHeader;
 use crate::core::types::CoreData;
+use crate::core::types::Header;
+use crate::error::EwError;
 pub use crate::framework::Cursor;
+use crate::framework::Event;
 use crate::framework::StampedData;
+use crate::monitor::BlockServedMessage;
+use crate::monitor::MonitorMessage;
+use crate::shutdown::CancellationToken;
 
 impl Cursor<CoreData> {
     /// Progress the cursor.
     ///
     /// Returns immediately if the next block is not available, if the channel
     /// is full, or after the next block was sent.
-    pub(super) async fn step(&mut self, node: &Node, store: &mut Store) {
-        match self.fetch_new_headers(node).await.unwrap() {
-            None => (),
-            Some(new_headers) => self.process_new_headers(new_headers, node, store).await,
-        }
-    }
-
-    /// Watch for new blocks
-    ///
-    /// Syncs cursor to head and keeps polling for new blocks.
-    pub(super) async fn watch(&mut self, node: &Node, store: &mut Store) {
-        tracing::debug!("[{}] starting watch loop", self.id);
-        loop {
-            let new_headers = self.wait_for_new_blocks(node).await;
-            self.process_new_headers(new_headers, node, store).await;
+    pub(super) async fn step(
+        &mut self,
+        node: &Node,
+        store: &mut Store,
+        recorder: Option<&mut ArchiveRecorder>,
+        prefetch: usize,
+        shutdown: &CancellationToken,
+    ) -> Result<(), EwError> {
+        match self.fetch_new_headers(node).await? {
+            None => Ok(()),
+            Some(new_headers) => {
+                self.process_new_headers(new_headers, node, store, recorder, prefetch, shutdown)
+                    .await
+            }
         }
     }
 
     /// Dispatches genesis boxes from the store if needed.
-    pub(super) async fn ensure_genesis_boxes(&mut self, store: &mut Store) {
+    pub(super) async fn ensure_genesis_boxes(&mut self, store: &mut Store) -> Result<(), EwError> {
         if self.header.height > -1 {
-            return;
+            return Ok(());
         };
-        let boxes = store.get_genesis_boxes().await;
+        let boxes = store.get_genesis_boxes().await?;
         let fake_block = Block::from_genesis_boxes(boxes);
         let data = StampedData {
             height: fake_block.header.height,
@@ -45,54 +57,112 @@ impl Cursor<CoreData> {
             data: CoreData { block: fake_block },
         };
         self.include(data).await;
+        Ok(())
     }
 
-    async fn process_new_headers(
+    /// Processes new blocks one by one.
+    ///
+    /// Blocks beyond the rollback horizon of the last new header are
+    /// fetched ahead, up to `prefetch` at a time (fast sync). Others are
+    /// fetched one by one. Settled blocks fetched from the node are
+    /// appended to the archive of `recorder`, if any.
+    ///
+    /// Stops early if `shutdown` is cancelled, but never halfway a block.
+    /// Logs emitted while processing are tagged with the serving node.
+    #[tracing::instrument(name = "blocks", skip_all, fields(node = %node.id))]
+    pub(super) async fn process_new_headers(
         &mut self,
         new_block_headers: Vec<BlockHeader>,
         node: &Node,
         store: &mut Store,
-    ) {
+        mut recorder: Option<&mut ArchiveRecorder>,
+        prefetch: usize,
+        shutdown: &CancellationToken,
+    ) -> Result<(), EwError> {
         tracing::debug!(
             "[{}] processing {} new headers",
             self.id,
             new_block_headers.len()
         );
+        // Headers are on the node's main chain, so blocks this far below
+        // the last one are not going to be rolled back.
+        let settled_height = match new_block_headers.last() {
+            Some(h) => h.height - rollback_horizon(),
+            None => return Ok(()),
+        };
+        let settled = new_block_headers
+            .iter()
+            .take_while(|h| h.height <= settled_height)
+            .count();
+        if settled > 0 {
+            tracing::debug!("[{}] fast syncing {} blocks", self.id, settled);
+        }
+        // Blocks already processed by the store are served from there.
+        let stored = new_block_headers
+            .iter()
+            .take_while(|h| h.height <= store.header().height)
+            .count()
+            .min(settled);
+        let mut blocks = BlockPrefetch::new(node, &new_block_headers[stored..settled], prefetch);
         for new_block_header in new_block_headers {
+            if shutdown.is_cancelled() {
+                break;
+            }
             if new_block_header.height == self.header.height {
                 // Different block at same height, last included block is
                 // not part of main chain anymore, so roll back and start over.
                 tracing::warn!("last included block is not part of main chain anymore");
-                let prev_header = store.roll_back(&self.header).await;
+                let prev_header = store.roll_back(&self.header).await?;
                 self.roll_back(prev_header).await;
+                self.report_tracker_height(store).await;
                 break;
             }
             assert_eq!(new_block_header.height, self.header.height + 1);
             if new_block_header.parent_id != self.header.header_id {
                 // New block is not a child of current last block.
                 tracing::warn!("new block is not a child of current last block");
-                let prev_header = store.roll_back(&self.header).await;
+                let prev_header = store.roll_back(&self.header).await?;
                 self.roll_back(prev_header).await;
+                self.report_tracker_height(store).await;
                 break;
             } else {
-                let block = match node.api.block(&new_block_header.id).await {
-                    Ok(b) => b,
-                    Err(NodeError::API404Notfound(url)) => {
-                        // Block wasn't found. This can happen when at the tip of
-                        // the chain and the header is in but the corresponding
-                        // block not yet.
-                        // Pause for a short time, then break to try again.
-                        tracing::warn!("404 for {}", { url });
-                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-                        break;
-                    }
-                    Err(other_node_error) => {
-                        panic!("{:?}", other_node_error)
+                let stored_data = if new_block_header.height <= store.header().height {
+                    store
+                        .get_block(new_block_header.height, &new_block_header.id)
+                        .await?
+                } else {
+                    None
+                };
+                let from_node = stored_data.is_none();
+                let core_data = match stored_data {
+                    Some(core_data) => core_data,
+                    None => {
+                        let (block, raw) = match blocks
+                            .get(new_block_header.height, &new_block_header.id)
+                            .await
+                        {
+                            Ok(b) => b,
+                            Err(NodeError::API404Notfound(url)) => {
+                                // Block wasn't found. This can happen when at the tip of
+                                // the chain and the header is in but the corresponding
+                                // block not yet.
+                                // Pause for a short time, then break to try again.
+                                tracing::warn!("404 for {}", { url });
+                                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                                break;
+                            }
+                            Err(other_node_error) => return Err(other_node_error.into()),
+                        };
+                        assert_eq!(block.header.height, self.header.height + 1);
+                        if let Some(recorder) = recorder.as_deref_mut() {
+                            if block.header.height <= settled_height {
+                                recorder.append(&block, &raw).await?;
+                            }
+                        }
+                        store.process(block).await?
                     }
                 };
-
-                assert_eq!(block.header.height, self.header.height + 1);
-                let core_data = store.process(block).await;
+                assert_eq!(core_data.block.header.height, self.header.height + 1);
 
                 let data = StampedData {
                     height: core_data.block.header.height,
@@ -103,13 +173,45 @@ impl Cursor<CoreData> {
                 };
 
                 self.include(data).await;
+                if from_node {
+                    self.report_block_source(node);
+                }
+                self.report_tracker_height(store).await;
             }
         }
+        Ok(())
+    }
+
+    /// Reports to the monitor which node served the last block.
+    fn report_block_source(&self, node: &Node) {
+        let msg = BlockServedMessage::new(node.id.clone(), self.header.height);
+        // Not worth holding up the tracker for, so skip if monitor is busy
+        self.monitor_tx
+            .try_send(MonitorMessage::BlockServed(msg))
+            .ok();
+    }
+
+    /// Reports height of tracker's store to the monitor.
+    ///
+    /// Lagging cursors don't move the store's header, so only a cursor
+    /// at the store's tip reports it.
+    async fn report_tracker_height(&self, store: &Store) {
+        if store.header() != &self.header {
+            return;
+        }
+        let msg = MonitorMessage::TrackerHeight(self.header.height);
+        if let Err(e) = self.monitor_tx.send(msg).await {
+            // Monitor may have stopped first during shutdown
+            tracing::debug!("could not report tracker height: {e}");
+        }
     }
 
     /// Return a header id for next height, once available.
-    async fn wait_for_new_blocks(&self, node: &Node) -> Vec<BlockHeader> {
-        let polling_interval = tokio::time::Duration::from_millis(5000);
+    pub(super) async fn wait_for_new_blocks(
+        &self,
+        node: &Node,
+        polling_interval: tokio::time::Duration,
+    ) -> Vec<BlockHeader> {
         loop {
             match self.fetch_new_headers(node).await {
                 Ok(res) => match res {
@@ -154,12 +256,128 @@ impl Cursor<CoreData> {
                 }
             }
             // no headers
-            0 => {
-                assert!(headers.is_empty());
-                panic!("Got empty chainslice");
-            }
+            // Node is behind cursor, e.g. after a resync
+            0 => Err(NodeError::APIError(format!(
+                "empty chainslice from height {fr}"
+            ))),
             // more than 1 header
             _ => Ok(Some(headers)),
         }
     }
+
+    /// Progress the cursor by one block from given `archive`.
+    ///
+    /// Returns false if the next block is not in the archive.
+    pub(super) async fn step_archive(
+        &mut self,
+        archive: &BlockArchive,
+        store: &mut Store,
+    ) -> Result<bool, EwError> {
+        let height = self.header.height + 1;
+        let block = match archive.block(height).await? {
+            Some(block) => block,
+            None => return Ok(false),
+        };
+        if block.header.parent_id != self.header.header_id {
+            return Err(EwError::Archive(format!(
+                "archived block {} is not a child of {}",
+                block.header.id, self.header.header_id
+            )));
+        }
+        let header = archive::header_of(&block);
+        let core_data = match store.get_block(height, &header.header_id).await? {
+            Some(core_data) => core_data,
+            None => store.process(block).await?,
+        };
+        let data = StampedData {
+            height: header.height,
+            timestamp: header.timestamp,
+            header_id: header.header_id,
+            parent_id: header.parent_id,
+            data: core_data,
+        };
+        self.include(data).await;
+        self.report_tracker_height(store).await;
+        Ok(true)
+    }
+}
+
+/// Adds a cursor at given `header` to `cursors` and returns its receiver.
+///
+/// Cursors cannot point past `max_header`, so are capped to it if needed.
+/// Reuses an existing cursor at same position, if any.
+pub(super) fn subscribe(
+    cursors: &mut Vec<Cursor<CoreData>>,
+    max_header: &Header,
+    header: Header,
+    // TODO: cursor name should not be set by caller
+    cursor_name: &str,
+    monitor_tx: &mpsc::Sender<MonitorMessage>,
+) -> mpsc::Receiver<Event<CoreData>> {
+    // Create new channel
+    let (tx, rx) = mpsc::channel(crate::framework::EVENT_CHANNEL_CAPACITY);
+
+    // Workflows may start at a non-zero height and ignore/skip any blocks
+    // prior. The tracker's store could be empty or not having reached the
+    // workflow's start height yet. Because a cursor cannot point past the
+    // tracker's head, we cap it to the current tracker's head if needed.
+    let capped_header = if header.height > max_header.height {
+        tracing::info!("cursor [{cursor_name}] is ahead of tracker - using tracker's height");
+        max_header.clone()
+    } else {
+        header
+    };
+
+    // If there's an existing cursor at same position, we use that one.
+    for cur in cursors.iter_mut() {
+        if cur.is_at(&capped_header) {
+            cur.txs.push(tx);
+            return rx;
+        }
+    }
+
+    // No existing cursors were found, so we make a new one.
+    let cur = Cursor {
+        id: cursor_name.to_owned(),
+        header: capped_header,
+        txs: vec![tx],
+        monitor_tx: monitor_tx.clone(),
+    };
+    cursors.push(cur);
+    rx
+}
+
+/// Merges `cursors` at the same position as the first one into it.
+///
+/// Cursors are expected to be ordered by decreasing position.
+pub(super) async fn merge(cursors: &mut Vec<Cursor<CoreData>>) {
+    // Check if any of the cursors are mergeable.
+    let main_header = &cursors[0].header;
+    if !cursors.iter().skip(1).any(|c| c.is_at(main_header)) {
+        // Nope, stop here.
+        return;
+    }
+
+    // The new collection of cursors with just the first cursor, for now
+    let mut merged: Vec<Cursor<CoreData>> = vec![cursors.remove(0)];
+
+    // Iterate over remaining cursor in existing collections
+    while let Some(cur) = cursors.pop() {
+        if cur.is_on(&merged[0]) {
+            // If encountering an identical cursor, merge it with tip.
+            // We only ever merge with tip, so we could miss the opportunity
+            // to merge identical cursors behind tip. However, the chances of
+            // this occuring are very slim.
+            merged[0].merge(cur).await;
+        } else if cur.header.height > merged[0].header.height {
+            // If next cursor is higher, add to start of new collection
+            merged.insert(0, cur);
+        } else {
+            // Cursor is still behind, just push to back of new collection
+            merged.push(cur);
+        }
+    }
+
+    // Done, take ownership of new cursor collection
+    *cursors = merged;
 }
@@ -0,0 +1,273 @@
+use tokio::sync::mpsc;
+use tokio::sync::watch;
+
+use crate::core::node::Node;
+use crate::core::node::NodeError;
+use crate::core::types::HeaderID;
+use crate::core::types::Height;
+use crate::monitor::MonitorMessage;
+use crate::monitor::NodeStatusMessage;
+use crate::shutdown::CancellationToken;
+
+/// State of a node, relative to the best known one.
+#[derive(Debug, Clone, PartialEq)]
+enum NodeState {
+    /// Node info could not be retrieved
+    Unreachable,
+    /// Node is on the best known chain, at given height
+    Healthy(Height),
+    /// Node is too many blocks behind the best known node, at given height
+    Behind(Height),
+    /// Node's best block, at given height, is not part of the best known chain
+    StaleFork(Height),
+}
+
+impl NodeState {
+    /// Best full height of the node, if known.
+    fn height(&self) -> Option<Height> {
+        match self {
+            NodeState::Unreachable => None,
+            NodeState::Healthy(h) | NodeState::Behind(h) | NodeState::StaleFork(h) => Some(*h),
+        }
+    }
+
+    /// Reason for not following the node, if any.
+    fn issue(&self) -> Option<String> {
+        match self {
+            NodeState::Unreachable => Some("unreachable".to_owned()),
+            NodeState::Healthy(_) => None,
+            NodeState::Behind(_) => Some("behind best known node".to_owned()),
+            NodeState::StaleFork(_) => Some("on a stale fork".to_owned()),
+        }
+    }
+}
+
+/// Checks configured nodes and selects the one the tracker follows.
+pub(super) struct NodeWatch {
+    /// Nodes, by decreasing preference
+    nodes: Vec<Node>,
+    /// Index of followed node
+    active: usize,
+    /// Number of blocks a node can be behind the best known node
+    max_lag: Height,
+    monitor_tx: mpsc::Sender<MonitorMessage>,
+}
+
+impl NodeWatch {
+    /// Watch given `nodes`, ordered by decreasing preference.
+    pub fn new(
+        nodes: Vec<Node>,
+        max_lag: Height,
+        monitor_tx: mpsc::Sender<MonitorMessage>,
+    ) -> Self {
+        assert!(!nodes.is_empty());
+        Self {
+            nodes,
+            active: 0,
+            max_lag,
+            monitor_tx,
+        }
+    }
+
+    /// Selects a node now and keeps checking every `interval`, until `stop`
+    /// is cancelled.
+    ///
+    /// Returns a receiver holding the node to follow.
+    pub async fn spawn(
+        mut self,
+        interval: tokio::time::Duration,
+        stop: CancellationToken,
+    ) -> watch::Receiver<Node> {
+        let (tx, rx) = watch::channel(self.check().await.clone());
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = stop.cancelled() => return,
+                    _ = tokio::time::sleep(interval) => (),
+                };
+                let node = tokio::select! {
+                    _ = stop.cancelled() => return,
+                    node = self.check() => node.clone(),
+                };
+                tx.send_if_modified(|active| match active.id == node.id {
+                    true => false,
+                    false => {
+                        *active = node;
+                        true
+                    }
+                });
+            }
+        });
+        rx
+    }
+
+    /// Assesses all nodes, selects the one to follow and reports to the monitor.
+    ///
+    /// Keeps following the current node if none is healthy.
+    async fn check(&mut self) -> &Node {
+        let states = self.assess().await;
+        match select(&states) {
+            Some(i) if i != self.active => {
+                tracing::warn!(
+                    "switching from node {} ({}) to node {}",
+                    self.nodes[self.active].id,
+                    states[self.active]
+                        .issue()
+                        .unwrap_or("lower priority".to_owned()),
+                    self.nodes[i].id
+                );
+                self.active = i;
+            }
+            Some(_) => (),
+            None => tracing::warn!(
+                "no healthy nodes - staying on {}",
+                self.nodes[self.active].id
+            ),
+        }
+        self.report(&states).await;
+        &self.nodes[self.active]
+    }
+
+    /// Queries all nodes and compares them to the best known one.
+    async fn assess(&self) -> Vec<NodeState> {
+        let handles: Vec<_> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let node = node.clone();
+                tokio::spawn(async move { node.api.info().await })
+            })
+            .collect();
+        let mut infos = vec![];
+        for (node, handle) in self.nodes.iter().zip(handles) {
+            match handle.await {
+                Ok(Ok(info)) => infos.push(Some(info)),
+                Ok(Err(e)) => {
+                    tracing::warn!("could not retrieve info of node {}: {e}", node.id);
+                    infos.push(None);
+                }
+                Err(e) => {
+                    tracing::warn!("could not retrieve info of node {}: {e}", node.id);
+                    infos.push(None);
+                }
+            }
+        }
+
+        // Best node is the highest one, preferred one on ties
+        let best = infos
+            .iter()
+            .enumerate()
+            .filter_map(|(i, info)| info.as_ref().map(|info| (i, info.full_height)))
+            .rev()
+            .max_by_key(|(_, h)| *h);
+        let Some((best, best_height)) = best else {
+            return vec![NodeState::Unreachable; self.nodes.len()];
+        };
+
+        let mut states = vec![];
+        for (i, info) in infos.iter().enumerate() {
+            let state = match info {
+                None => NodeState::Unreachable,
+                Some(info) if best_height - info.full_height > self.max_lag => {
+                    NodeState::Behind(info.full_height)
+                }
+                // A node at the same height may be on a competing fork, which
+                // is fine as long as it's not left behind.
+                Some(info) if info.full_height == best_height => {
+                    NodeState::Healthy(info.full_height)
+                }
+                Some(info) => {
+                    match is_on_chain(
+                        &self.nodes[best],
+                        info.full_height,
+                        &info.best_full_header_id,
+                    )
+                    .await
+                    {
+                        Ok(true) => NodeState::Healthy(info.full_height),
+                        Ok(false) => NodeState::StaleFork(info.full_height),
+                        Err(e) => {
+                            // Can't tell, give it the benefit of the doubt
+                            tracing::debug!(
+                                "could not check chain of node {} against {}: {e}",
+                                self.nodes[i].id,
+                                self.nodes[best].id
+                            );
+                            NodeState::Healthy(info.full_height)
+                        }
+                    }
+                }
+            };
+            states.push(state);
+        }
+        states
+    }
+
+    /// Sends node states to the monitor.
+    async fn report(&self, states: &[NodeState]) {
+        for (i, (node, state)) in self.nodes.iter().zip(states).enumerate() {
+            let msg = NodeStatusMessage::new(
+                node.id.clone(),
+                node.priority,
+                state.height(),
+                state.issue(),
+                i == self.active,
+            );
+            self.monitor_tx
+                .send(MonitorMessage::NodeStatus(msg))
+                .await
+                .ok();
+        }
+        let msg = match states.iter().filter_map(|s| s.height()).max() {
+            Some(height) => MonitorMessage::NodeHeight(height),
+            None => MonitorMessage::NodeUnreachable,
+        };
+        self.monitor_tx.send(msg).await.ok();
+    }
+}
+
+/// Index of the first healthy node, if any.
+fn select(states: &[NodeState]) -> Option<usize> {
+    states
+        .iter()
+        .position(|s| matches!(s, NodeState::Healthy(_)))
+}
+
+/// Checks if header `header_id` at given `height` is part of `node`'s main chain.
+async fn is_on_chain(node: &Node, height: Height, header_id: &HeaderID) -> Result<bool, NodeError> {
+    // Look header up by height as not all nodes include the lower bound
+    let headers = node.api.chainslice(height - 1, height).await?;
+    Ok(headers
+        .iter()
+        .any(|h| h.height == height && &h.id == header_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_prefers_first_healthy_node() {
+        let states = vec![
+            NodeState::Unreachable,
+            NodeState::Behind(90),
+            NodeState::Healthy(100),
+            NodeState::Healthy(100),
+        ];
+        assert_eq!(select(&states), Some(2));
+        let states = vec![NodeState::Healthy(99), NodeState::Healthy(100)];
+        assert_eq!(select(&states), Some(0));
+    }
+
+    #[test]
+    fn test_select_skips_stale_forks() {
+        let states = vec![NodeState::StaleFork(99), NodeState::Healthy(100)];
+        assert_eq!(select(&states), Some(1));
+    }
+
+    #[test]
+    fn test_select_none_healthy() {
+        let states = vec![NodeState::Unreachable, NodeState::Unreachable];
+        assert_eq!(select(&states), None);
+    }
+}
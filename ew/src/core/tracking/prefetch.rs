@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use tokio::task::JoinSet;
+
+use crate::core::node::models::Block;
+use crate::core::node::Node;
+use crate::core::node::NodeError;
+use crate::core::types::BlockHeader;
+use crate::core::types::HeaderID;
+use crate::core::types::Height;
+
+/// A fetched block, along with its raw JSON.
+type Fetched = Result<(Block, String), NodeError>;
+
+/// Fetches blocks concurrently, ahead of them being processed.
+///
+/// Keeps at most `capacity` blocks requested or waiting to be handed out,
+/// and hands them out in height order. Pending requests are aborted
+/// when dropped.
+pub(super) struct BlockPrefetch {
+    node: Node,
+    /// Blocks still to be requested, by increasing height
+    queued: VecDeque<(Height, HeaderID)>,
+    /// Requests in flight
+    requests: JoinSet<(Height, Fetched)>,
+    /// Heights of requests in flight
+    in_flight: HashSet<Height>,
+    /// Fetched blocks, waiting for lower ones to be handed out first
+    buffer: BTreeMap<Height, Fetched>,
+    capacity: usize,
+}
+
+impl BlockPrefetch {
+    /// Start fetching blocks for given `headers` from `node`.
+    pub fn new(node: &Node, headers: &[BlockHeader], capacity: usize) -> Self {
+        let mut prefetch = Self {
+            node: node.clone(),
+            queued: headers.iter().map(|h| (h.height, h.id.clone())).collect(),
+            requests: JoinSet::new(),
+            in_flight: HashSet::new(),
+            buffer: BTreeMap::new(),
+            capacity,
+        };
+        prefetch.request_more();
+        prefetch
+    }
+
+    /// Returns block `header_id` at given `height`, along with its raw JSON.
+    ///
+    /// Blocks that were not prefetched are requested directly, without
+    /// waiting for pending requests.
+    pub async fn get(
+        &mut self,
+        height: Height,
+        header_id: &HeaderID,
+    ) -> Result<(Block, String), NodeError> {
+        loop {
+            if let Some(res) = self.buffer.remove(&height) {
+                self.request_more();
+                return res;
+            }
+            if !self.in_flight.contains(&height) && !self.is_queued(height) {
+                return self.node.api.block_with_raw(header_id).await;
+            }
+            match self.requests.join_next().await {
+                Some(Ok((h, res))) => {
+                    self.in_flight.remove(&h);
+                    self.buffer.insert(h, res);
+                }
+                Some(Err(e)) => {
+                    return Err(NodeError::APIError(format!(
+                        "block request for height {height} did not complete: {e}"
+                    )))
+                }
+                None => return self.node.api.block_with_raw(header_id).await,
+            }
+        }
+    }
+
+    /// Requests queued blocks until at capacity.
+    fn request_more(&mut self) {
+        while self.requests.len() + self.buffer.len() < self.capacity {
+            let Some((height, header_id)) = self.queued.pop_front() else {
+                return;
+            };
+            let node = self.node.clone();
+            self.in_flight.insert(height);
+            self.requests
+                .spawn(async move { (height, node.api.block_with_raw(&header_id).await) });
+        }
+    }
+
+    /// Checks if block at `height` is still to be requested.
+    fn is_queued(&self, height: Height) -> bool {
+        self.queued
+            .binary_search_by_key(&height, |(h, _)| *h)
+            .is_ok()
+    }
+}
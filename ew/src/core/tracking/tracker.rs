@@ -1,48 +1,119 @@
 use async_trait::async_trait;
 use tokio::sync::mpsc;
-use tracing::info;
+use tokio::sync::watch;
 
 use crate::config::PostgresConfig;
+use crate::config::TrackerConfig;
+use crate::core::archive::ArchiveRecorder;
+use crate::core::archive::BlockArchive;
 use crate::core::node::Node;
 use crate::core::store::Store;
+use crate::core::tracking::cursor;
 use crate::core::tracking::cursor::Cursor;
+use crate::core::tracking::node_watch::NodeWatch;
 use crate::core::types::CoreData;
 use crate::core::types::Header;
+use crate::core::types::Height;
+use crate::error::EwError;
 use crate::framework::Event;
+use crate::framework::ServedRequests;
 use crate::framework::Source;
+use crate::framework::SourceRequest;
+use crate::framework::SourceRequests;
 use crate::monitor::MonitorMessage;
+use crate::shutdown::CancellationToken;
 
 pub struct Tracker {
-    node: Node,
+    /// Nodes to follow, by decreasing preference
+    nodes: Vec<Node>,
+    /// Node currently followed
+    active_node: watch::Receiver<Node>,
     store: Store,
     cursors: Vec<Cursor<CoreData>>,
     monitor_tx: mpsc::Sender<MonitorMessage>,
-    // pub polling_interval: tokio::time::Duration,
+    polling_interval: tokio::time::Duration,
+    max_node_lag: Height,
+    /// Number of blocks fetched ahead during fast sync
+    prefetch: usize,
+    requests: Option<SourceRequests<CoreData>>,
+    /// Archives settled blocks fetched from nodes, if set
+    recorder: Option<ArchiveRecorder>,
 }
 
 impl Tracker {
+    /// Create a new Tracker with default settings.
+    ///
+    /// Follows the preferred healthy node out of `nodes`, see `new_with`.
     pub async fn new(
-        node: Node,
+        nodes: Vec<Node>,
         pgconf: PostgresConfig,
         monitor_tx: mpsc::Sender<MonitorMessage>,
-    ) -> Self {
-        let mut store = Store::new(pgconf).await;
+    ) -> Result<Self, EwError> {
+        Self::new_with(nodes, pgconf, &TrackerConfig::default(), monitor_tx).await
+    }
+
+    /// Create a new Tracker with given `config`.
+    ///
+    /// Nodes with the lowest priority are preferred. The tracker fails over
+    /// to the next one when a node is unreachable, falls behind, or is
+    /// stuck on a stale fork.
+    ///
+    /// Panics if `nodes` is empty.
+    pub async fn new_with(
+        mut nodes: Vec<Node>,
+        pgconf: PostgresConfig,
+        config: &TrackerConfig,
+        monitor_tx: mpsc::Sender<MonitorMessage>,
+    ) -> Result<Self, EwError> {
+        assert!(!nodes.is_empty(), "tracker needs at least one node");
+        nodes.sort_by_key(|n| n.priority);
+
+        let mut store = Store::new(pgconf, config).await?;
 
         // Ensure genesis boxes are included.
         // We do this now, before the tracker can be used by downstream
         // workers, which may request genesis data right away.
-        if !store.has_genesis_boxes().await {
-            let boxes = node.api.utxo_genesis_raw().await.unwrap();
-            store.include_genesis_boxes(boxes).await;
+        if !store.has_genesis_boxes().await? {
+            let boxes = fetch_genesis_boxes(&nodes).await?;
+            store.include_genesis_boxes(boxes).await?;
         }
 
-        Self {
-            node,
+        // Replaced by node watch once started
+        let (_, active_node) = watch::channel(nodes[0].clone());
+
+        Ok(Self {
+            nodes,
+            active_node,
             store,
             cursors: vec![],
             monitor_tx,
-            // polling_interval: tokio::time::Duration::from_millis(5000),
+            polling_interval: config.polling_interval,
+            max_node_lag: config.max_node_lag,
+            prefetch: config.prefetch_blocks,
+            requests: None,
+            recorder: None,
+        })
+    }
+
+    /// Serve given `requests` from source handles once started.
+    ///
+    /// Allows workers to subscribe while the tracker is running.
+    pub fn serve(&mut self, requests: SourceRequests<CoreData>) {
+        self.requests = Some(requests);
+    }
+
+    /// Append settled blocks fetched from nodes to given `archive`.
+    ///
+    /// Genesis boxes are recorded first if the archive is empty.
+    pub async fn record_to(&mut self, archive: BlockArchive) -> Result<(), EwError> {
+        if archive.header().await?.is_initial() {
+            let boxes = fetch_genesis_boxes(&self.nodes).await?;
+            archive.write_genesis(&boxes).await?;
         }
+        let recorder = ArchiveRecorder::new(archive).await?;
+        tracing::info!("archiving blocks from {}", recorder.header().height + 1);
+        self.recorder = Some(recorder);
+        Ok(())
     }
 
     /// Get head of tracker's store.
@@ -57,17 +128,47 @@ impl Tracker {
     //     head.is_initial() || self.store.contains_head(head).await
     // }
 
+    /// Track the node until `shutdown` is cancelled.
+    ///
+    /// A block being processed is always completed before returning.
     #[tracing::instrument(name = "tracker", skip_all)]
-    pub async fn start(&mut self) {
+    pub async fn start(&mut self, shutdown: CancellationToken) -> Result<(), EwError> {
         tracing::info!("starting");
+        let mut requests = match &self.requests {
+            Some(requests) => requests.lock().await,
+            None => ServedRequests::none(),
+        };
+        // Without any subscribed workers, keep indexing core data on
+        // a cursor of our own.
+        if self.cursors.is_empty() {
+            tracing::info!("no workers subscribed - tracking core only");
+            self.cursors.push(Cursor {
+                id: "main".to_owned(),
+                header: self.store.header().clone(),
+                txs: vec![],
+                monitor_tx: self.monitor_tx.clone(),
+            });
+        }
         // Before starting, reorder cursors by decreasing position.
         self.cursors.sort_by_key(|c| -c.header.height);
         // Rename first cursor to `main` as any other will be merged into it.
         self.cursors[0].id = "main".to_owned();
 
+        // Keep selecting the node to follow and inform monitor of node
+        // heights, until tracker stops.
+        let node_watch = shutdown.child_token();
+        self.active_node = NodeWatch::new(
+            self.nodes.clone(),
+            self.max_node_lag,
+            self.monitor_tx.clone(),
+        )
+        .spawn(self.polling_interval, node_watch.clone())
+        .await;
+        let _node_watch_guard = node_watch.drop_guard();
+
         // Ensure genesis boxes have been dispatched
         for cur in &mut self.cursors {
-            cur.ensure_genesis_boxes(&mut self.store).await;
+            cur.ensure_genesis_boxes(&mut self.store).await?;
         }
 
         tracing::debug!(
@@ -76,67 +177,179 @@ impl Tracker {
             self.cursors.iter().map(|c| &c.id).collect::<Vec<&String>>()
         );
 
-        if self.cursors.len() > 1 {
-            self.join_cursors().await;
+        // Workers subscribing later on may add lagging cursors,
+        // so keep switching between both modes as needed.
+        while !shutdown.is_cancelled() {
+            if self.cursors.len() > 1 {
+                self.join_cursors(&mut requests, &shutdown).await?;
+            } else {
+                self.single_cursor(&mut requests, &shutdown).await?;
+            }
         }
-        self.single_cursor().await;
+        tracing::info!("stopped");
+        Ok(())
     }
 
     /// Progresses multiple cursors until they're all at the same position.
     #[tracing::instrument(skip_all)]
-    async fn join_cursors(&mut self) {
+    async fn join_cursors(
+        &mut self,
+        requests: &mut ServedRequests<CoreData>,
+        shutdown: &CancellationToken,
+    ) -> Result<(), EwError> {
         loop {
-            for cur in &mut self.cursors {
+            for i in 0..self.cursors.len() {
+                if shutdown.is_cancelled() {
+                    return Ok(());
+                }
+                let node = self.active_node.borrow().clone();
+                let cur = &mut self.cursors[i];
                 tracing::debug!("stepping cursor {}", cur.id);
-                cur.step(&self.node, &mut self.store).await;
+                let res = cur
+                    .step(
+                        &node,
+                        &mut self.store,
+                        self.recorder.as_mut(),
+                        self.prefetch,
+                        shutdown,
+                    )
+                    .await;
+                if !self.recover_from_node_failure(res, &node, shutdown).await? {
+                    break;
+                }
                 // TODO: drop cursors having no more downtstream receivers (e.g. they all panicked).
             }
-            self.merge_cursors().await;
+            while let Some(request) = requests.try_recv() {
+                self.handle_request(request).await?;
+            }
+            cursor::merge(&mut self.cursors).await;
             if self.cursors.len() == 1 {
-                break;
+                return Ok(());
             }
         }
     }
 
-    /// Attempts to merge cursors when at the same height
-    async fn merge_cursors(&mut self) {
-        // Check if any of the cursors are mergeable.
-        let main_header = &self.cursors[0].header;
-        if !self.cursors.iter().skip(1).any(|c| c.is_at(main_header)) {
-            // Nope, stop here.
-            return;
+    /// Progress single cursor when only one left
+    ///
+    /// Syncs cursor to head and keeps polling for new blocks, until `shutdown`
+    /// is cancelled or a new subscription adds a lagging cursor.
+    async fn single_cursor(
+        &mut self,
+        requests: &mut ServedRequests<CoreData>,
+        shutdown: &CancellationToken,
+    ) -> Result<(), EwError> {
+        assert!(self.cursors.len() == 1);
+        tracing::debug!("[{}] starting watch loop", self.cursors[0].id);
+        loop {
+            let node = self.active_node.borrow_and_update().clone();
+            // Only waiting for blocks is cancellable, not processing them.
+            let new_headers = tokio::select! {
+                biased;
+                _ = shutdown.cancelled() => return Ok(()),
+                request = requests.recv() => {
+                    self.handle_request(request).await?;
+                    if self.cursors.len() > 1 {
+                        return Ok(());
+                    }
+                    continue;
+                }
+                Ok(()) = self.active_node.changed() => continue,
+                new_headers = self.cursors[0].wait_for_new_blocks(&node, self.polling_interval) => new_headers,
+            };
+            let res = self.cursors[0]
+                .process_new_headers(
+                    new_headers,
+                    &node,
+                    &mut self.store,
+                    self.recorder.as_mut(),
+                    self.prefetch,
+                    shutdown,
+                )
+                .await;
+            self.recover_from_node_failure(res, &node, shutdown).await?;
         }
+    }
 
-        // The new collection of cursors with just the first cursor, for now
-        let mut merged: Vec<Cursor<CoreData>> = vec![self.cursors.remove(0)];
-
-        // Iterate over remaining cursor in existing collections
-        while let Some(cur) = self.cursors.pop() {
-            if cur.is_on(&merged[0]) {
-                // If encountering an identical cursor, merge it with tip.
-                // We only ever merge with tip, so we could miss the opportunity
-                // to merge identical cursors behind tip. However, the chances of
-                // this occuring are very slim.
-                merged[0].merge(cur).await;
-            } else if cur.header.height > merged[0].header.height {
-                // If next cursor is higher, add to start of new collection
-                merged.insert(0, cur);
-            } else {
-                // Cursor is still behind, just push to back of new collection
-                merged.push(cur);
+    /// Passes on result of processing blocks from `node`, unless it failed
+    /// because of the node and there are other nodes to fail over to.
+    ///
+    /// In that case, waits for the node watch to select another node, or
+    /// for the polling interval to elapse, and returns false.
+    async fn recover_from_node_failure(
+        &mut self,
+        res: Result<(), EwError>,
+        node: &Node,
+        shutdown: &CancellationToken,
+    ) -> Result<bool, EwError> {
+        match res {
+            Err(EwError::Node(e)) if e.is_transient() && self.nodes.len() > 1 => {
+                tracing::warn!("node {} failed: {e}", node.id);
+                tokio::select! {
+                    _ = shutdown.cancelled() => (),
+                    _ = self.active_node.changed() => (),
+                    _ = tokio::time::sleep(self.polling_interval) => (),
+                }
+                Ok(false)
             }
+            res => res.map(|_| true),
         }
+    }
 
-        // Done, take ownership of new cursor collection
-        self.cursors = merged;
+    /// Respond to a request from a source handle.
+    ///
+    /// Requesters that are gone are ignored.
+    async fn handle_request(&mut self, request: SourceRequest<CoreData>) -> Result<(), EwError> {
+        match request {
+            SourceRequest::Header(tx) => {
+                tx.send(self.store.header().clone()).ok();
+            }
+            SourceRequest::ContainsHeader(header, tx) => {
+                tx.send(self.contains_header(&header).await).ok();
+            }
+            SourceRequest::Subscribe(header, cursor_name, tx) => {
+                let rx = self.subscribe_cursor(header, &cursor_name);
+                // New cursors may start from scratch
+                for cur in &mut self.cursors {
+                    cur.ensure_genesis_boxes(&mut self.store).await?;
+                }
+                tx.send(rx).ok();
+            }
+        }
+        Ok(())
     }
 
-    /// Progress single cursor when only one left
-    async fn single_cursor(&mut self) {
-        assert!(self.cursors.len() == 1);
-        let cur = &mut self.cursors[0];
-        cur.watch(&self.node, &mut self.store).await;
+    fn subscribe_cursor(
+        &mut self,
+        header: Header,
+        cursor_name: &str,
+    ) -> mpsc::Receiver<Event<CoreData>> {
+        cursor::subscribe(
+            &mut self.cursors,
+            self.store.header(),
+            header,
+            cursor_name,
+            &self.monitor_tx,
+        )
+    }
+}
+
+/// Retrieves genesis boxes from the first node able to serve them.
+async fn fetch_genesis_boxes(nodes: &[Node]) -> Result<String, EwError> {
+    let mut last_error = None;
+    for node in nodes {
+        match node.api.utxo_genesis_raw().await {
+            Ok(boxes) => return Ok(boxes),
+            Err(e) if e.is_transient() => {
+                tracing::warn!(
+                    "could not retrieve genesis boxes from node {}: {e}",
+                    node.id
+                );
+                last_error = Some(e);
+            }
+            Err(e) => return Err(e.into()),
+        }
     }
+    Err(last_error.expect("at least one node").into())
 }
 
 /// Dummy impl to satisfy ErgWorker for now
@@ -144,53 +357,21 @@ impl Tracker {
 impl Source for Tracker {
     type S = CoreData;
 
-    fn header(&self) -> &Header {
-        self.store.header()
+    async fn header(&self) -> Result<Header, EwError> {
+        Ok(self.store.header().clone())
     }
 
-    async fn contains_header(&self, header: &Header) -> bool {
+    async fn contains_header(&self, header: &Header) -> Result<bool, EwError> {
         // Initial head is always contained but will not be stored,
         // so hande explicitly.
-        header.is_initial() || self.store.contains_header(header).await
+        Ok(header.is_initial() || self.store.contains_header(header).await?)
     }
 
     async fn subscribe(
         &mut self,
         header: Header,
-        // TODO: cursor name should not be set by caller
         cursor_name: &str,
-    ) -> mpsc::Receiver<Event<CoreData>> {
-        // Create new channel
-        let (tx, rx) = tokio::sync::mpsc::channel(crate::framework::EVENT_CHANNEL_CAPACITY);
-
-        // Workflows may start at a non-zero height and ignore/skip any blocks
-        // prior. The tracker's store could be empty or not having reached the
-        // workflow's start height yet. Because a cursor cannot point past the
-        // tracker's head, we cap it to the current tracker's head if needed.
-        let max_header = self.store.header().clone();
-        let capped_header = if header.height > max_header.height {
-            info!("cursor [{cursor_name}] is ahead of tracker - using tracker's height");
-            max_header
-        } else {
-            header
-        };
-
-        // If there's an existing cursor at same position, we use that one.
-        for cur in &mut self.cursors {
-            if cur.is_at(&capped_header) {
-                cur.txs.push(tx);
-                return rx;
-            }
-        }
-
-        // No existing cursors were found, so we make a new one.
-        let cur = Cursor {
-            id: cursor_name.to_owned(),
-            header: capped_header.clone(),
-            txs: vec![tx],
-            monitor_tx: self.monitor_tx.clone(),
-        };
-        self.cursors.push(cur);
-        rx
+    ) -> Result<mpsc::Receiver<Event<CoreData>>, EwError> {
+        Ok(self.subscribe_cursor(header, cursor_name))
     }
 }
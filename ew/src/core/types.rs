@@ -705,7 +705,7 @@ pub mod testutils {
 
     impl Registers {
         pub fn dummy() -> Self {
-            Self("{}".into())
+            Self(serde_json::Value::Object(serde_json::Map::new()))
         }
     }
 
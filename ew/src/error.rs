@@ -0,0 +1,39 @@
+use thiserror::Error;
+
+use crate::core::types::BoxID;
+use crate::core::types::Height;
+use crate::core::NodeError;
+
+/// Errors that can stop the tracker or a worker.
+///
+/// Most are transient (db connection lost, node restarting) and the
+/// affected worker can be restarted from its last persisted position.
+#[derive(Error, Debug)]
+pub enum EwError {
+    #[error("Postgres error: {0}")]
+    Postgres(#[from] tokio_postgres::Error),
+    #[error(transparent)]
+    Node(#[from] NodeError),
+    #[error("Store {0} is lagging behind revision {1}. Ensure all migrations have been applied.")]
+    StoreRevisionLagging(String, String),
+    #[error("Store {0} is at revision {1}, which is not supported by this version of ew.")]
+    StoreRevisionUnsupported(String, String),
+    #[error(
+        "Store {0} was moved to height {1} by another connection. Restart to catch up from there."
+    )]
+    StoreHeaderMoved(String, Height),
+    #[error(
+        "Store {0} discarded uncommitted blocks of an interrupted write, back to height {1}. Restart to catch up from there."
+    )]
+    StoreInterrupted(String, Height),
+    #[error("Box {0} of stored block {1} is missing from the core store.")]
+    MissingBox(BoxID, Height),
+    #[error("Block archive error: {0}")]
+    Archive(String),
+    #[error("Export error: {0}")]
+    Export(String),
+    #[error("Upstream source is down")]
+    UpstreamDown,
+    #[error("Query handler is down")]
+    QueryHandlerDown,
+}
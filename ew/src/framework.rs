@@ -2,6 +2,8 @@ mod cursor;
 mod event;
 mod event_emission;
 mod event_handling;
+#[cfg(feature = "test-utilities")]
+pub mod fuzzing;
 mod query_emission;
 mod query_handling;
 mod source;
@@ -19,11 +21,17 @@ pub use event::StampedData;
 pub use event_emission::EventEmission;
 pub use event_handling::EventHandler;
 pub use event_handling::EventHandling;
+pub use event_handling::Rewinding;
 pub use query_emission::QuerySender;
 pub use query_emission::QueryWrapper;
 pub use query_emission::Querying;
 pub use query_handling::QueryHandler;
+pub use source::source_channel;
+pub(crate) use source::ServedRequests;
 pub use source::Source;
+pub use source::SourceHandle;
+pub use source::SourceRequest;
+pub use source::SourceRequests;
 pub use utils::BlockRange;
 pub use worker::LeafWorker;
 pub use worker::SourceWorker;
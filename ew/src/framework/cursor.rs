@@ -40,11 +40,10 @@ impl<D> Cursor<D> {
     pub async fn merge(&mut self, mut other: Self) {
         tracing::info!("Merging cursors [{}] and [{}]", self.id, other.id);
         // Signal to monitor that the other cursor will get dropped.
-        other
-            .monitor_tx
-            .send(MonitorMessage::CursorDrop(other.id))
-            .await
-            .unwrap();
+        let msg = MonitorMessage::CursorDrop(other.id);
+        if let Err(e) = other.monitor_tx.send(msg).await {
+            tracing::debug!("could not report cursor drop: {e}");
+        }
         // Take over channels of other cursor.
         self.txs.append(&mut other.txs);
     }
@@ -80,13 +79,11 @@ impl<D> Cursor<D> {
         assert_eq!(previous_header.height, self.header.height - 1);
 
         // Report rollback to monitor
-        self.monitor_tx
-            .send(MonitorMessage::Rollback(CursorRollback::new(
-                self.id.clone(),
-                self.header.height,
-            )))
-            .await
-            .unwrap();
+        let msg =
+            MonitorMessage::Rollback(CursorRollback::new(self.id.clone(), self.header.height));
+        if let Err(e) = self.monitor_tx.send(msg).await {
+            tracing::debug!("could not report rollback: {e}");
+        }
 
         // Rollback event carries the height to be rolled back
         self.send(Event::Rollback(self.header.height)).await;
@@ -120,12 +117,10 @@ impl<D> Cursor<D> {
     }
 
     async fn report_status(&self) {
-        self.monitor_tx
-            .send(MonitorMessage::Cursor(CursorMessage::new(
-                self.id.clone(),
-                self.header.height,
-            )))
-            .await
-            .unwrap();
+        let msg = MonitorMessage::Cursor(CursorMessage::new(self.id.clone(), self.header.height));
+        // Monitor may have stopped first during shutdown
+        if let Err(e) = self.monitor_tx.send(msg).await {
+            tracing::debug!("could not report cursor status: {e}");
+        }
     }
 }
@@ -8,6 +8,7 @@ use super::event_handling::EventHandling;
 use super::utils::BlockRange;
 use super::Cursor;
 use crate::core::types::Header;
+use crate::error::EwError;
 use crate::monitor::MonitorMessage;
 
 #[async_trait]
@@ -15,12 +16,15 @@ pub trait EventEmission {
     type S;
 
     /// Returns true if data for `header` has been included.
-    async fn contains_header(&self, header: &Header) -> bool;
+    async fn contains_header(&self, header: &Header) -> Result<bool, EwError>;
 
     /// Get data for given height range.
     ///
     /// Used by lagging cursors to retrieve data.
-    async fn get_slice(&self, blcok_range: &BlockRange) -> Vec<StampedData<Self::S>>;
+    async fn get_slice(
+        &self,
+        blcok_range: &BlockRange,
+    ) -> Result<Vec<StampedData<Self::S>>, EwError>;
 }
 
 pub(super) struct EventEmitter<W: EventHandling + EventEmission> {
@@ -73,7 +77,11 @@ impl<W: EventHandling + EventEmission> EventEmitter<W> {
 
     /// Step lagging cursors by n blocks
     #[tracing::instrument(skip_all)]
-    pub(super) async fn progress_lagging_cursors(&mut self, workflow: &W, n: i32) {
+    pub(super) async fn progress_lagging_cursors(
+        &mut self,
+        workflow: &W,
+        n: i32,
+    ) -> Result<(), EwError> {
         // In any case, do not go past current source position
         let max_height = workflow.header().height;
 
@@ -83,13 +91,14 @@ impl<W: EventHandling + EventEmission> EventEmitter<W> {
             let last_height = cursor.header.height + steps;
 
             let block_range = BlockRange::new(first_height, last_height);
-            let slice = workflow.get_slice(&block_range).await;
+            let slice = workflow.get_slice(&block_range).await?;
             for data in slice {
                 cursor.include(data).await;
             }
         }
         // merge cursors where possible
         self.merge_cursors(workflow).await;
+        Ok(())
     }
 
     /// Attempts to merge cursors when at the same height
@@ -108,7 +117,8 @@ impl<W: EventHandling + EventEmission> EventEmitter<W> {
             .map(|(i, _c)| i)
             .collect();
 
-        for cursor_index in ready {
+        // Remove from the back so remaining indices stay valid
+        for cursor_index in ready.into_iter().rev() {
             let other = self.lagging_cursors.remove(cursor_index);
             if self.tracking_cursor.is_some() {
                 // There already is a tracking cursor, so merge the other into it.
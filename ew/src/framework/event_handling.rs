@@ -12,6 +12,9 @@ use super::Source;
 use crate::config::PostgresConfig;
 use crate::core::types::Header;
 use crate::core::types::Height;
+use crate::error::EwError;
+use crate::monitor::events::BlockSummary;
+use crate::monitor::events::Figures;
 use crate::monitor::MonitorMessage;
 use crate::monitor::WorkerMessage;
 
@@ -22,16 +25,44 @@ pub trait EventHandling {
     type D: Send + Sync; // downstream data () for a sink
 
     /// Create and initialize a new event handling workflow.
-    async fn new(pgconf: &PostgresConfig) -> Self;
+    async fn new(pgconf: &PostgresConfig) -> Result<Self, EwError>
+    where
+        Self: Sized;
 
     /// Process new block data.
-    async fn include_block(&mut self, data: &StampedData<Self::U>) -> Self::D;
+    async fn include_block(&mut self, data: &StampedData<Self::U>) -> Result<Self::D, EwError>;
 
     /// Roll back a block and return previous head.
-    async fn roll_back(&mut self, height: Height) -> Header;
+    async fn roll_back(&mut self, height: Height) -> Result<Header, EwError>;
 
     /// Get last processed header.
     fn header<'a>(&'a self) -> &'a Header;
+
+    /// Commit any blocks held back by batched writes.
+    ///
+    /// Called whenever no upstream events are pending.
+    async fn flush(&mut self) -> Result<(), EwError>;
+
+    /// Key figures of last included block, pushed to live event subscribers.
+    fn figures(&self) -> Option<Figures> {
+        None
+    }
+
+    /// Returns true while included blocks await a batched commit.
+    ///
+    /// Reports of such blocks are held back until committed.
+    fn has_uncommitted(&self) -> bool {
+        false
+    }
+}
+
+/// Workflows able to discard many blocks at once
+#[async_trait]
+pub trait Rewinding: EventHandling {
+    /// Discard all blocks above given `height` and return new head.
+    ///
+    /// Unlike rollbacks, not limited by the rollback horizon.
+    async fn rewind(&mut self, height: Height) -> Result<Header, EwError>;
 }
 
 pub struct EventHandler<W: EventHandling> {
@@ -39,6 +70,8 @@ pub struct EventHandler<W: EventHandling> {
     workflow: W,
     rx: Receiver<Event<W::U>>,
     monitor_tx: Sender<MonitorMessage>,
+    /// Reports of included blocks awaiting commit
+    held_reports: Vec<WorkerMessage>,
 }
 
 impl<W: EventHandling> EventHandler<W> {
@@ -53,8 +86,8 @@ impl<W: EventHandling> EventHandler<W> {
         pgconf: &PostgresConfig,
         source: &mut impl Source<S = W::U>,
         monitor_tx: Sender<MonitorMessage>,
-    ) -> Self {
-        let workflow = W::new(pgconf).await;
+    ) -> Result<Self, EwError> {
+        let workflow = W::new(pgconf).await?;
         Self::new_with(id, workflow, source, monitor_tx).await
     }
 
@@ -72,27 +105,28 @@ impl<W: EventHandling> EventHandler<W> {
         mut workflow: W,
         source: &mut impl Source<S = W::U>,
         monitor_tx: Sender<MonitorMessage>,
-    ) -> Self {
+    ) -> Result<Self, EwError> {
         // Ensure workflow is on main chain
-        Self::ensure_main_chain(id, &mut workflow, source).await;
+        Self::ensure_main_chain(id, &mut workflow, source).await?;
         // Subscribe to source from current position
-        let rx = source.subscribe(workflow.header().clone(), id).await;
+        let rx = source.subscribe(workflow.header().clone(), id).await?;
 
-        Self {
+        Ok(Self {
             id,
             rx,
             workflow,
             monitor_tx,
-        }
+            held_reports: vec![],
+        })
     }
 
     /// Wait for and handle next event.
     ///
     /// Consider using lower level functions when behind a tokio::select!
     /// blocks with multiple branches to avoid event drops from cancellation.
-    pub async fn recv_and_handle(&mut self) {
-        let event = self.rx.recv().await.unwrap();
-        self.process_upstream_event(&event).await;
+    pub async fn recv_and_handle(&mut self) -> Result<(), EwError> {
+        let event = self.rx.recv().await.ok_or(EwError::UpstreamDown)?;
+        self.process_upstream_event(&event).await
     }
 
     /// Wait for return next event.
@@ -107,20 +141,37 @@ impl<W: EventHandling> EventHandler<W> {
         self.rx.try_recv()
     }
 
-    pub async fn process_upstream_event(&mut self, event: &Event<W::U>) {
+    /// Let the workflow commit batched writes, unless more events are pending.
+    pub async fn flush_if_idle(&mut self) -> Result<(), EwError> {
+        if self.rx.is_empty() {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Let the workflow commit batched writes.
+    pub async fn flush(&mut self) -> Result<(), EwError> {
+        self.workflow.flush().await?;
+        self.release_reports().await;
+        Ok(())
+    }
+
+    pub async fn process_upstream_event(&mut self, event: &Event<W::U>) -> Result<(), EwError> {
         match event {
             Event::Include(stamped_data) => {
                 // Capped cursor may dispatch events prior to workflow's head. Ignore them.
                 if stamped_data.height <= self.workflow.header().height {
-                    return;
+                    return Ok(());
                 }
-                self.handle_include(&stamped_data).await;
+                self.handle_include(&stamped_data).await?;
+                self.report_status(true).await;
             }
             Event::Rollback(height) => {
-                self.handle_rollback(*height).await;
+                self.handle_rollback(*height).await?;
+                self.report_status(false).await;
             }
         };
-        self.report_status().await;
+        Ok(())
     }
 
     /// Ensure the workflow head is on the main chain.
@@ -128,26 +179,27 @@ impl<W: EventHandling> EventHandler<W> {
         id: &'static str,
         workflow: &mut W,
         source: &mut impl Source<S = W::U>,
-    ) {
+    ) -> Result<(), EwError> {
         // A worker could crash on a rollback while the tracker gets passed it.
         // In such a case, the workflow's head wouldn't be on the main chain anymore.
         // Here, we check for such cases and roll back the workflow until back
         // on the main chain again.
         // Skip this is if the workflow is ahead of the tracker.
-        if workflow.header().height <= source.header().height {
+        if workflow.header().height <= source.header().await?.height {
             // Rolling back any blocks past the split.
-            while !source.contains_header(workflow.header()).await {
+            while !source.contains_header(workflow.header()).await? {
                 tracing::info!(
                     "workflow `{}` is not on main chain - rolling back {:?}",
                     &id,
                     workflow.header(),
                 );
-                workflow.roll_back(workflow.header().height).await;
+                workflow.roll_back(workflow.header().height).await?;
             }
         }
+        Ok(())
     }
 
-    async fn handle_include(&mut self, payload: &StampedData<W::U>) -> W::D {
+    async fn handle_include(&mut self, payload: &StampedData<W::U>) -> Result<W::D, EwError> {
         tracing::debug!("handling new block {}", payload.height);
         // Check next block is indeed child of last included one
         let head = self.workflow.header();
@@ -157,18 +209,48 @@ impl<W: EventHandling> EventHandler<W> {
         self.workflow.include_block(&payload).await
     }
 
-    async fn handle_rollback(&mut self, height: Height) -> Header {
+    async fn handle_rollback(&mut self, height: Height) -> Result<Header, EwError> {
         assert_eq!(height, self.workflow.header().height);
         self.workflow.roll_back(height).await
     }
 
     /// Reports worker's status to monitor.
-    async fn report_status(&self) {
+    ///
+    /// Included blocks are only reported once committed, so that live
+    /// subscribers never see blocks that could still be discarded.
+    ///
+    /// * `included` - true if the workflow just included a block
+    async fn report_status(&mut self, included: bool) {
+        let header = self.workflow.header();
+        let mut msg = WorkerMessage::new(self.id, header.height);
+        if included {
+            msg = msg.with_block(BlockSummary {
+                header_id: header.header_id.clone(),
+                figures: self.workflow.figures(),
+            });
+            if self.workflow.has_uncommitted() {
+                self.held_reports.push(msg);
+                return;
+            }
+        }
+        // Anything held back got committed by now, rollbacks included
+        self.release_reports().await;
+        self.send_report(msg).await;
+    }
+
+    /// Sends reports of blocks that got committed since being held back.
+    async fn release_reports(&mut self) {
+        if self.workflow.has_uncommitted() {
+            return;
+        }
+        for msg in std::mem::take(&mut self.held_reports) {
+            self.send_report(msg).await;
+        }
+    }
+
+    async fn send_report(&self, msg: WorkerMessage) {
         self.monitor_tx
-            .send(MonitorMessage::Worker(WorkerMessage::new(
-                self.id,
-                self.workflow.header().height,
-            )))
+            .send(MonitorMessage::Worker(msg))
             .await
             .unwrap();
     }
@@ -196,9 +278,9 @@ impl<W: EventHandling> FwdEventHandler<W> {
         pgconf: &PostgresConfig,
         source: &mut impl Source<S = W::U>,
         monitor_tx: Sender<MonitorMessage>,
-    ) -> Self {
-        let base_handler = EventHandler::new(id, pgconf, source, monitor_tx).await;
-        Self { base_handler }
+    ) -> Result<Self, EwError> {
+        let base_handler = EventHandler::new(id, pgconf, source, monitor_tx).await?;
+        Ok(Self { base_handler })
     }
 
     pub(super) fn workflow(&self) -> &W {
@@ -215,34 +297,157 @@ impl<W: EventHandling> FwdEventHandler<W> {
     }
 
     /// Handle given `event` and return resulting event for downstream workers.
-    pub async fn handle_event(&mut self, event: Event<W::U>) -> HandledEvent<W::D> {
+    pub async fn handle_event(
+        &mut self,
+        event: Event<W::U>,
+    ) -> Result<HandledEvent<W::D>, EwError> {
         self.process_upstream_event(&event).await
     }
 
-    /// Try reveiving and handling a pending event.
+    /// Try reveiving a pending event.
     ///
     /// Does not wait for events if none pending.
-    pub async fn try_next(&mut self) -> Result<HandledEvent<W::D>, TryRecvError> {
-        let event = self.base_handler.try_recv()?;
-        Ok(self.process_upstream_event(&event).await)
+    pub fn try_recv(&mut self) -> Result<Event<W::U>, TryRecvError> {
+        self.base_handler.try_recv()
+    }
+
+    /// Let the workflow commit batched writes, unless more events are pending.
+    pub async fn flush_if_idle(&mut self) -> Result<(), EwError> {
+        self.base_handler.flush_if_idle().await
     }
 
-    async fn process_upstream_event(&mut self, event: &Event<W::U>) -> HandledEvent<W::D> {
+    /// Let the workflow commit batched writes.
+    pub async fn flush(&mut self) -> Result<(), EwError> {
+        self.base_handler.flush().await
+    }
+
+    async fn process_upstream_event(
+        &mut self,
+        event: &Event<W::U>,
+    ) -> Result<HandledEvent<W::D>, EwError> {
         let ds_event = match event {
             Event::Include(stamped_data) => {
                 // Capped cursor may dispatch events prior to workflow's head. Ignore them.
                 if stamped_data.height <= self.base_handler.workflow.header().height {
-                    return HandledEvent::Skipped;
+                    return Ok(HandledEvent::Skipped);
                 }
-                let downstream_data = self.base_handler.handle_include(&stamped_data).await;
+                let downstream_data = self.base_handler.handle_include(&stamped_data).await?;
+                self.base_handler.report_status(true).await;
                 HandledEvent::Include(stamped_data.wrap(downstream_data))
             }
             Event::Rollback(height) => {
-                let prev_header = self.base_handler.handle_rollback(*height).await;
+                let prev_header = self.base_handler.handle_rollback(*height).await?;
+                self.base_handler.report_status(false).await;
                 HandledEvent::Rollback(prev_header)
             }
         };
-        self.base_handler.report_status().await;
-        ds_event
+        Ok(ds_event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    /// Source handing out a prepared event channel.
+    struct DummySource {
+        rx: Option<Receiver<Event<()>>>,
+    }
+
+    #[async_trait]
+    impl Source for DummySource {
+        type S = ();
+
+        async fn header(&self) -> Result<Header, EwError> {
+            Ok(Header::initial())
+        }
+
+        async fn contains_header(&self, _header: &Header) -> Result<bool, EwError> {
+            Ok(true)
+        }
+
+        async fn subscribe(
+            &mut self,
+            _header: Header,
+            _cursor_name: &str,
+        ) -> Result<Receiver<Event<()>>, EwError> {
+            Ok(self.rx.take().unwrap())
+        }
+    }
+
+    /// Workflow committing included blocks on flush only.
+    struct BatchingWorkflow {
+        header: Header,
+        uncommitted: usize,
+    }
+
+    #[async_trait]
+    impl EventHandling for BatchingWorkflow {
+        type U = ();
+        type D = ();
+
+        async fn new(_pgconf: &PostgresConfig) -> Result<Self, EwError> {
+            unimplemented!()
+        }
+
+        async fn include_block(&mut self, data: &StampedData<()>) -> Result<(), EwError> {
+            self.header = Header::from(data);
+            self.uncommitted += 1;
+            Ok(())
+        }
+
+        async fn roll_back(&mut self, _height: Height) -> Result<Header, EwError> {
+            unimplemented!()
+        }
+
+        fn header(&self) -> &Header {
+            &self.header
+        }
+
+        async fn flush(&mut self) -> Result<(), EwError> {
+            self.uncommitted = 0;
+            Ok(())
+        }
+
+        fn has_uncommitted(&self) -> bool {
+            self.uncommitted > 0
+        }
+    }
+
+    #[tokio::test]
+    async fn test_uncommitted_blocks_reported_once_flushed() {
+        let (_tx, rx) = mpsc::channel(1);
+        let (monitor_tx, mut monitor_rx) = mpsc::channel(10);
+        let workflow = BatchingWorkflow {
+            header: Header::initial(),
+            uncommitted: 0,
+        };
+        let mut source = DummySource { rx: Some(rx) };
+        let mut handler = EventHandler::new_with("batching", workflow, &mut source, monitor_tx)
+            .await
+            .unwrap();
+
+        for height in 0..2 {
+            let data = StampedData {
+                height,
+                timestamp: 0,
+                header_id: format!("header-{height}"),
+                parent_id: handler.workflow.header().header_id.clone(),
+                data: (),
+            };
+            handler
+                .process_upstream_event(&Event::Include(std::sync::Arc::new(data)))
+                .await
+                .unwrap();
+        }
+        assert!(monitor_rx.try_recv().is_err());
+
+        handler.flush().await.unwrap();
+        let mut heights = vec![];
+        while let Ok(MonitorMessage::Worker(msg)) = monitor_rx.try_recv() {
+            heights.push(msg.height());
+        }
+        assert_eq!(heights, vec![0, 1]);
     }
 }
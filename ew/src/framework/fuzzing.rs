@@ -0,0 +1,561 @@
+//! (test-util) Fork fuzzing of event handling workflows.
+//!
+//! Generates random chains with random reorgs and checks that a workflow
+//! fed with them ends up in the same state as a fresh sync of the winning
+//! chain.
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use rand::SeedableRng;
+use tokio_postgres::Client;
+use tokio_postgres::NoTls;
+
+use super::Event;
+use super::EventHandling;
+use super::StampedData;
+use crate::config::PostgresConfig;
+use crate::constants::address_ids::EMISSION;
+use crate::constants::settings::rollback_horizon;
+use crate::constants::GENESIS_TIMESTAMP;
+use crate::core::types::AddressID;
+use crate::core::types::AssetID;
+use crate::core::types::Block;
+use crate::core::types::BoxData;
+use crate::core::types::CoreData;
+use crate::core::types::Header;
+use crate::core::types::Height;
+use crate::core::types::NanoERG;
+use crate::core::types::Timestamp;
+use crate::core::types::Transaction;
+use crate::error::EwError;
+
+pub use rand::rngs::StdRng;
+pub use rand::Rng;
+
+/// Events of a random chain with reorgs, along with the winning chain.
+pub struct Scenario<U> {
+    /// Seed the scenario was generated from
+    pub seed: u64,
+    /// Header the chain builds on, initial one for chains starting at genesis
+    pub start: Header,
+    /// Events as a worker would receive them while following the chain
+    pub events: Vec<Event<U>>,
+    /// Blocks of the winning chain
+    pub main_chain: Vec<Arc<StampedData<U>>>,
+}
+
+impl<U> Scenario<U> {
+    /// Events of a fresh sync of the winning chain.
+    pub fn sync_events(&self) -> Vec<Event<U>> {
+        self.main_chain
+            .iter()
+            .map(|data| Event::Include(data.clone()))
+            .collect()
+    }
+
+    /// Number of rollbacks in the scenario.
+    pub fn rollbacks(&self) -> usize {
+        self.events
+            .iter()
+            .filter(|e| matches!(e, Event::Rollback(_)))
+            .count()
+    }
+}
+
+/// Unspent boxes and next asset id of a branch, after a given block.
+#[derive(Clone)]
+struct BranchState {
+    utxos: Vec<BoxData>,
+    emission_box: BoxData,
+    next_asset_id: AssetID,
+}
+
+/// Generates worker specific transactions for a block at given height and
+/// timestamp.
+pub type TxGenerator = Box<dyn FnMut(&mut StdRng, Height, Timestamp) -> Vec<Transaction>>;
+
+/// Random chain generator for `CoreData` workflows.
+///
+/// Blocks spend and create boxes of a small set of addresses, mint and
+/// burn tokens and reference data-inputs. Value and tokens are conserved
+/// by transactions, except for the miner reward paid by the emission
+/// contract in every block.
+pub struct ChainFuzzer {
+    seed: u64,
+    rng: StdRng,
+    start: Option<Header>,
+    length: Height,
+    reorg_probability: f64,
+    max_depth: Height,
+    extra_txs: Option<TxGenerator>,
+}
+
+impl ChainFuzzer {
+    /// Addresses used by generated boxes
+    const ADDRESSES: [AddressID; 8] = [
+        AddressID(11),
+        AddressID(21),
+        AddressID(31),
+        AddressID(41),
+        AddressID(51),
+        AddressID(61),
+        AddressID(73),
+        AddressID(83),
+    ];
+    const MINER: AddressID = AddressID(92);
+    const EMISSION_VALUE: NanoERG = 90_000_000_000_000_000;
+    const REWARD: NanoERG = 67_500_000_000;
+
+    /// Create a new generator for given `seed`.
+    ///
+    /// Defaults to a 100 blocks chain starting at genesis, with a reorg
+    /// chance of 10% per block and reorgs up to the rollback horizon.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            start: None,
+            length: 100,
+            reorg_probability: 0.1,
+            max_depth: rollback_horizon(),
+            extra_txs: None,
+        }
+    }
+
+    /// Build the chain on top of given `header` instead of genesis.
+    ///
+    /// For workers ignoring blocks up to a given height. Generated blocks
+    /// spend boxes that were never created.
+    pub fn start_at(mut self, header: Header) -> Self {
+        self.start = Some(header);
+        self
+    }
+
+    /// Set number of blocks of the winning chain, genesis excluded.
+    pub fn length(mut self, length: Height) -> Self {
+        self.length = length;
+        self
+    }
+
+    /// Set probability of a reorg before each new block.
+    pub fn reorg_probability(mut self, p: f64) -> Self {
+        self.reorg_probability = p;
+        self
+    }
+
+    /// Set max number of blocks rolled back by a reorg.
+    ///
+    /// Capped to the rollback horizon.
+    pub fn max_depth(mut self, depth: Height) -> Self {
+        self.max_depth = depth.min(rollback_horizon());
+        self
+    }
+
+    /// Append transactions from `generator` to each block.
+    pub fn extra_txs(
+        mut self,
+        generator: impl FnMut(&mut StdRng, Height, Timestamp) -> Vec<Transaction> + 'static,
+    ) -> Self {
+        self.extra_txs = Some(Box::new(generator));
+        self
+    }
+
+    /// Generate a new scenario.
+    pub fn generate(&mut self) -> Scenario<CoreData> {
+        let mut events = vec![];
+
+        let boxes: Vec<BoxData> = Self::ADDRESSES
+            .iter()
+            .map(|address_id| {
+                BoxData::dummy()
+                    .address_id(*address_id)
+                    .value(self.rng.gen_range(1..1000) * 1_000_000_000)
+                    .timestamp(GENESIS_TIMESTAMP)
+            })
+            .collect();
+        let emission_box = BoxData::dummy()
+            .address_id(EMISSION)
+            .value(Self::EMISSION_VALUE)
+            .timestamp(GENESIS_TIMESTAMP);
+        let state = BranchState {
+            utxos: boxes.clone(),
+            emission_box: emission_box.clone(),
+            next_asset_id: 1,
+        };
+
+        // Root of the chain, never rolled back
+        let (root, start) = match &self.start {
+            None => {
+                let mut boxes = boxes;
+                boxes.push(emission_box);
+                let genesis = Block::from_genesis_boxes(boxes);
+                let genesis = Arc::new(StampedData::from(CoreData { block: genesis }));
+                events.push(Event::Include(genesis.clone()));
+                (genesis, Header::initial())
+            }
+            Some(header) => {
+                let mut block = Block::dummy()
+                    .height(header.height)
+                    .timestamp(header.timestamp)
+                    .parent_id(&header.parent_id);
+                block.header.id = header.header_id.clone();
+                (
+                    Arc::new(StampedData::from(CoreData { block })),
+                    header.clone(),
+                )
+            }
+        };
+        let tip_height = root.height + self.length;
+
+        // Blocks of current branch and state after each of them
+        let mut chain = vec![(root, state)];
+        // Blocks more than a horizon below the highest tip are settled
+        let mut highest = chain[0].0.height;
+        while chain.last().unwrap().0.height < tip_height {
+            let tip = chain.len() as Height - 1;
+            let height = chain[tip as usize].0.height;
+            let max_depth = self
+                .max_depth
+                .min(tip)
+                .min(height - highest + rollback_horizon());
+            if max_depth > 0 && self.rng.gen_bool(self.reorg_probability) {
+                let depth = self.rng.gen_range(1..=max_depth);
+                for _ in 0..depth {
+                    let (data, _) = chain.pop().unwrap();
+                    events.push(Event::Rollback(data.height));
+                }
+            }
+            let (parent, state) = chain.last().unwrap();
+            let mut state = state.clone();
+            let block = self.child_block(&parent.data.block, &mut state);
+            let data = Arc::new(StampedData::from(CoreData { block }));
+            highest = highest.max(data.height);
+            events.push(Event::Include(data.clone()));
+            chain.push((data, state));
+        }
+
+        let skip = if start.is_initial() { 0 } else { 1 };
+        Scenario {
+            seed: self.seed,
+            start,
+            events,
+            main_chain: chain.into_iter().skip(skip).map(|(data, _)| data).collect(),
+        }
+    }
+
+    /// Generate a random child of `parent`, updating branch `state`.
+    fn child_block(&mut self, parent: &Block, state: &mut BranchState) -> Block {
+        let height = parent.header.height + 1;
+        let timestamp = parent.header.timestamp + self.rng.gen_range(60_000..180_000);
+        let mut block = Block::child_of(parent).timestamp(timestamp);
+
+        let emission_box = BoxData::dummy()
+            .address_id(EMISSION)
+            .value(state.emission_box.value - Self::REWARD)
+            .creation_height(height)
+            .timestamp(timestamp);
+        let reward = Transaction::dummy()
+            .add_input(std::mem::replace(
+                &mut state.emission_box,
+                emission_box.clone(),
+            ))
+            .add_output(emission_box)
+            .add_output(
+                BoxData::dummy()
+                    .address_id(Self::MINER)
+                    .value(Self::REWARD)
+                    .creation_height(height)
+                    .timestamp(timestamp),
+            );
+        let mut txs = vec![reward];
+        for _ in 0..self.rng.gen_range(0..4) {
+            if let Some(tx) = self.random_tx(state, height, timestamp) {
+                txs.push(tx);
+            }
+        }
+        if let Some(generator) = &mut self.extra_txs {
+            txs.extend(generator(&mut self.rng, height, timestamp));
+        }
+
+        // Outputs are only spendable in later blocks
+        for (index, mut tx) in txs.into_iter().enumerate() {
+            tx.index = index as i32;
+            state.utxos.extend(
+                tx.outputs
+                    .iter()
+                    .filter(|bx| bx.address_id != EMISSION)
+                    .cloned(),
+            );
+            block = block.add_tx(tx);
+        }
+        block
+    }
+
+    /// Generate a random transaction spending boxes from `state`.
+    ///
+    /// Returns `None` if there is nothing left to spend.
+    fn random_tx(
+        &mut self,
+        state: &mut BranchState,
+        height: Height,
+        timestamp: Timestamp,
+    ) -> Option<Transaction> {
+        if state.utxos.is_empty() {
+            return None;
+        }
+        let mut tx = Transaction::dummy();
+
+        let n_inputs = self.rng.gen_range(1..=2).min(state.utxos.len());
+        let mut value = 0;
+        let mut assets: HashMap<AssetID, i64> = HashMap::new();
+        for _ in 0..n_inputs {
+            let i = self.rng.gen_range(0..state.utxos.len());
+            let input = state.utxos.swap_remove(i);
+            value += input.value;
+            for asset in &input.assets {
+                *assets.entry(asset.asset_id).or_default() += asset.amount;
+            }
+            tx = tx.add_input(input);
+        }
+        if !state.utxos.is_empty() && self.rng.gen_bool(0.2) {
+            let i = self.rng.gen_range(0..state.utxos.len());
+            tx = tx.add_data_input(state.utxos[i].clone());
+        }
+
+        // Mint a new token or burn an existing one
+        if self.rng.gen_bool(0.2) {
+            assets.insert(state.next_asset_id, self.rng.gen_range(1..1_000_000));
+            state.next_asset_id += 1;
+        } else if !assets.is_empty() && self.rng.gen_bool(0.1) {
+            let asset_id = *assets.keys().min().unwrap();
+            assets.remove(&asset_id);
+        }
+
+        // Split value and tokens over outputs
+        let n_outputs = self.rng.gen_range(1..=3);
+        let mut outputs: Vec<BoxData> = (0..n_outputs)
+            .map(|_| {
+                let address_id = Self::ADDRESSES[self.rng.gen_range(0..Self::ADDRESSES.len())];
+                BoxData::dummy()
+                    .address_id(address_id)
+                    .value(0)
+                    .creation_height(height)
+                    .timestamp(timestamp)
+            })
+            .collect();
+        for output in outputs.iter_mut().take(n_outputs - 1) {
+            output.value = self.rng.gen_range(0..=value / n_outputs as i64);
+            value -= output.value;
+        }
+        outputs[n_outputs - 1].value = value;
+        let mut assets: Vec<(AssetID, i64)> = assets.into_iter().collect();
+        assets.sort();
+        for (asset_id, amount) in assets {
+            let i = self.rng.gen_range(0..n_outputs);
+            let part = self.rng.gen_range(0..=amount);
+            if part > 0 {
+                outputs[i] = outputs[i].add_asset(asset_id, part);
+            }
+            if amount > part {
+                let j = self.rng.gen_range(0..n_outputs);
+                outputs[j] = outputs[j].add_asset(asset_id, amount - part);
+            }
+        }
+        for output in outputs {
+            tx = tx.add_output(output);
+        }
+        Some(tx)
+    }
+}
+
+/// Checks that workflows of type `W` reach the same state whether they
+/// follow the forks of `scenario` or sync its winning chain only.
+///
+/// Runs one workflow on the `fuzzed` database and another on the `fresh`
+/// one, then compares all tables of given `schemas`, as well as their
+/// `ew.headers` records. Both databases are expected to have a core schema.
+///
+/// Returns the downstream events of the fuzzed workflow, to be passed on to
+/// downstream workflows.
+///
+/// Panics if states differ.
+pub async fn assert_fork_consistency<W: EventHandling>(
+    scenario: &Scenario<W::U>,
+    fuzzed: &PostgresConfig,
+    fresh: &PostgresConfig,
+    schemas: &[&str],
+) -> Result<Scenario<W::D>, EwError> {
+    let fuzzed_client = connect(fuzzed).await?;
+    let fresh_client = connect(fresh).await?;
+    if !scenario.start.is_initial() {
+        set_core_header(&fuzzed_client, &scenario.start).await?;
+        set_core_header(&fresh_client, &scenario.start).await?;
+    }
+
+    let mut workflow = W::new(fuzzed).await?;
+    let events = replay(&mut workflow, &fuzzed_client, &scenario.events).await?;
+
+    let mut workflow = W::new(fresh).await?;
+    let main_chain = replay(&mut workflow, &fresh_client, &scenario.sync_events())
+        .await?
+        .into_iter()
+        .map(|event| match event {
+            Event::Include(data) => data,
+            Event::Rollback(_) => unreachable!("fresh sync has no rollbacks"),
+        })
+        .collect();
+
+    let fuzzed_state = Snapshot::take(&fuzzed_client, schemas).await?;
+    let fresh_state = Snapshot::take(&fresh_client, schemas).await?;
+    if let Some(diff) = fuzzed_state.diff(&fresh_state) {
+        panic!(
+            "state after {} rollbacks differs from fresh sync (seed {}):\n{diff}",
+            scenario.rollbacks(),
+            scenario.seed,
+        );
+    }
+
+    Ok(Scenario {
+        seed: scenario.seed,
+        start: scenario.start.clone(),
+        events,
+        main_chain,
+    })
+}
+
+/// Feed `events` to `workflow` and return resulting downstream events.
+///
+/// Keeps core.headers in line with the workflow's chain, as needed for
+/// rollbacks.
+pub async fn replay<W: EventHandling>(
+    workflow: &mut W,
+    client: &Client,
+    events: &[Event<W::U>],
+) -> Result<Vec<Event<W::D>>, EwError> {
+    let mut downstream = vec![];
+    for event in events {
+        downstream.push(replay_event(workflow, client, event).await?);
+    }
+    workflow.flush().await?;
+    Ok(downstream)
+}
+
+/// Feed a single `event` to `workflow` and return resulting downstream event.
+///
+/// Allows driving dependent workflows in lockstep. Does not flush.
+pub async fn replay_event<W: EventHandling>(
+    workflow: &mut W,
+    client: &Client,
+    event: &Event<W::U>,
+) -> Result<Event<W::D>, EwError> {
+    match event {
+        Event::Include(data) => {
+            let header = Header {
+                height: data.height,
+                timestamp: data.timestamp,
+                header_id: data.header_id.clone(),
+                parent_id: data.parent_id.clone(),
+            };
+            set_core_header(client, &header).await?;
+            let d = workflow.include_block(data).await?;
+            Ok(Event::Include(Arc::new(data.wrap(d))))
+        }
+        Event::Rollback(height) => {
+            workflow.roll_back(*height).await?;
+            client
+                .execute("delete from core.headers where height = $1;", &[height])
+                .await?;
+            Ok(Event::Rollback(*height))
+        }
+    }
+}
+
+/// Insert main chain `header`, replacing any header at same height.
+///
+/// Several workflows may share the same core.headers table.
+pub async fn set_core_header(client: &Client, header: &Header) -> Result<(), EwError> {
+    let stmt = "
+        insert into core.headers (height, timestamp, header_id, parent_id, main_chain)
+        values ($1, $2, $3, $4, True)
+        on conflict (height) do update
+        set timestamp = excluded.timestamp
+            , header_id = excluded.header_id
+            , parent_id = excluded.parent_id;";
+    client
+        .execute(
+            stmt,
+            &[
+                &header.height,
+                &header.timestamp,
+                &header.header_id,
+                &header.parent_id,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+/// Sorted rows of each table of some schemas.
+pub struct Snapshot(BTreeMap<String, Vec<String>>);
+
+impl Snapshot {
+    pub async fn take(client: &Client, schemas: &[&str]) -> Result<Self, EwError> {
+        let mut tables = BTreeMap::new();
+        let qry = "
+            select table_schema || '.' || table_name
+            from information_schema.tables
+            where table_schema = any($1)
+                and table_type = 'BASE TABLE';";
+        for row in client.query(qry, &[&schemas]).await? {
+            let table: String = row.get(0);
+            let qry = format!("select _row::text from {table} _row order by 1;");
+            let rows = client.query(&qry, &[]).await?;
+            tables.insert(table, rows.iter().map(|r| r.get(0)).collect());
+        }
+        let qry = "
+            select _row::text
+            from ew.headers _row
+            where schema_name = any($1)
+            order by 1;";
+        let rows = client.query(qry, &[&schemas]).await?;
+        tables.insert(
+            "ew.headers".to_owned(),
+            rows.iter().map(|r| r.get(0)).collect(),
+        );
+        Ok(Self(tables))
+    }
+
+    /// Describes rows differing from `other`, if any.
+    pub fn diff(&self, other: &Self) -> Option<String> {
+        let mut report = String::new();
+        let empty = vec![];
+        let tables = self.0.keys().chain(other.0.keys());
+        for table in tables.collect::<std::collections::BTreeSet<_>>() {
+            let rows = self.0.get(table).unwrap_or(&empty);
+            let other_rows = other.0.get(table).unwrap_or(&empty);
+            if rows == other_rows {
+                continue;
+            }
+            report.push_str(&format!("{table}:\n"));
+            for row in rows.iter().filter(|r| !other_rows.contains(r)) {
+                report.push_str(&format!("  + {row}\n"));
+            }
+            for row in other_rows.iter().filter(|r| !rows.contains(r)) {
+                report.push_str(&format!("  - {row}\n"));
+            }
+        }
+        (!report.is_empty()).then_some(report)
+    }
+}
+
+/// Connects to postgres and spawns the connection task.
+pub async fn connect(pgconf: &PostgresConfig) -> Result<Client, EwError> {
+    let (client, connection) = tokio_postgres::connect(&pgconf.connection_uri, NoTls).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            tracing::error!("connection error: {}", e);
+        }
+    });
+    Ok(client)
+}
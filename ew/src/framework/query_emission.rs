@@ -3,6 +3,8 @@ use async_trait::async_trait;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
 
+use crate::error::EwError;
+
 #[derive(Debug)]
 pub struct QueryWrapper<Q: std::fmt::Debug, R: std::fmt::Debug> {
     pub query: Q,
@@ -14,6 +16,14 @@ pub struct QuerySender<Q: std::fmt::Debug, R: std::fmt::Debug> {
     tx: mpsc::Sender<QueryWrapper<Q, R>>,
 }
 
+impl<Q: std::fmt::Debug, R: std::fmt::Debug> Clone for QuerySender<Q, R> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
 impl<Q: std::fmt::Debug, R: std::fmt::Debug> QuerySender<Q, R> {
     /// Create a new query sender from provided MPSC sender.
     ///
@@ -30,12 +40,15 @@ impl<Q: std::fmt::Debug, R: std::fmt::Debug> QuerySender<Q, R> {
 
     /// Sends query to query handler and returns a oneshot receiver
     /// through wich the query response can be received.
-    pub async fn send(&self, query: Q) -> oneshot::Receiver<R> {
+    pub async fn send(&self, query: Q) -> Result<oneshot::Receiver<R>, EwError> {
         tracing::debug!("sending query {query:?}");
         let (response_tx, response_rx) = oneshot::channel();
         let qw = QueryWrapper { query, response_tx };
-        self.tx.send(qw).await.unwrap();
-        response_rx
+        self.tx
+            .send(qw)
+            .await
+            .map_err(|_| EwError::QueryHandlerDown)?;
+        Ok(response_rx)
     }
 }
 
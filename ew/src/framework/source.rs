@@ -1,17 +1,160 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
+use tokio::sync::mpsc;
 use tokio::sync::mpsc::Receiver;
+use tokio::sync::oneshot;
+use tokio::sync::Mutex;
+use tokio::sync::OwnedMutexGuard;
 
 use super::event::Event;
 use crate::core::types::Header;
+use crate::error::EwError;
 
 #[async_trait]
 pub trait Source {
     type S: Send;
 
-    fn header(&self) -> &Header;
+    /// Returns the source's last processed header.
+    async fn header(&self) -> Result<Header, EwError>;
 
     /// Returns true if `head` is part of source's processed main cahin.
-    async fn contains_header(&self, header: &Header) -> bool;
+    async fn contains_header(&self, header: &Header) -> Result<bool, EwError>;
+
+    async fn subscribe(
+        &mut self,
+        header: Header,
+        cursor_name: &str,
+    ) -> Result<Receiver<Event<Self::S>>, EwError>;
+}
+
+/// Requests sent by a `SourceHandle` to a running source.
+pub enum SourceRequest<S> {
+    Header(oneshot::Sender<Header>),
+    ContainsHeader(Header, oneshot::Sender<Result<bool, EwError>>),
+    Subscribe(Header, String, oneshot::Sender<Receiver<Event<S>>>),
+}
+
+/// Creates a handle to a source, along with the requests it will send.
+///
+/// The requests are to be served by the source, see `serve` on `Tracker`
+/// and `SourceWorker`.
+pub fn source_channel<S>() -> (SourceHandle<S>, SourceRequests<S>) {
+    let (tx, rx) = mpsc::channel(super::EVENT_CHANNEL_CAPACITY);
+    (
+        SourceHandle { tx },
+        SourceRequests {
+            rx: Arc::new(Mutex::new(rx)),
+        },
+    )
+}
+
+/// A `Source` backed by a running source task.
+///
+/// Used to (re)subscribe to a source after it was started, e.g. when a
+/// failed worker gets restarted.
+pub struct SourceHandle<S> {
+    tx: mpsc::Sender<SourceRequest<S>>,
+}
+
+impl<S> Clone for SourceHandle<S> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+impl<S> SourceHandle<S> {
+    async fn request<R>(
+        &self,
+        make_request: impl FnOnce(oneshot::Sender<R>) -> SourceRequest<S>,
+    ) -> Result<R, EwError> {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.tx
+            .send(make_request(response_tx))
+            .await
+            .map_err(|_| EwError::UpstreamDown)?;
+        response_rx.await.map_err(|_| EwError::UpstreamDown)
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> Source for SourceHandle<S> {
+    type S = S;
+
+    async fn header(&self) -> Result<Header, EwError> {
+        self.request(SourceRequest::Header).await
+    }
+
+    async fn contains_header(&self, header: &Header) -> Result<bool, EwError> {
+        let header = header.clone();
+        self.request(|tx| SourceRequest::ContainsHeader(header, tx))
+            .await?
+    }
+
+    async fn subscribe(
+        &mut self,
+        header: Header,
+        cursor_name: &str,
+    ) -> Result<Receiver<Event<S>>, EwError> {
+        let cursor_name = cursor_name.to_owned();
+        self.request(|tx| SourceRequest::Subscribe(header, cursor_name, tx))
+            .await
+    }
+}
+
+/// Receiving end of `SourceHandle`s.
+///
+/// Outlives the source serving it, so a restarted source keeps serving
+/// existing handles.
+pub struct SourceRequests<S> {
+    rx: Arc<Mutex<Receiver<SourceRequest<S>>>>,
+}
+
+impl<S> Clone for SourceRequests<S> {
+    fn clone(&self) -> Self {
+        Self {
+            rx: self.rx.clone(),
+        }
+    }
+}
+
+impl<S> SourceRequests<S> {
+    /// Lock requests for a running source.
+    pub(crate) async fn lock(&self) -> ServedRequests<S> {
+        ServedRequests {
+            rx: Some(self.rx.clone().lock_owned().await),
+        }
+    }
+}
+
+/// Requests being served by a running source.
+pub(crate) struct ServedRequests<S> {
+    rx: Option<OwnedMutexGuard<Receiver<SourceRequest<S>>>>,
+}
+
+impl<S> ServedRequests<S> {
+    /// Requests of a source without any handles.
+    pub(crate) fn none() -> Self {
+        Self { rx: None }
+    }
+
+    /// Wait for next request.
+    ///
+    /// Never returns if there are no handles left.
+    pub(crate) async fn recv(&mut self) -> SourceRequest<S> {
+        if let Some(rx) = self.rx.as_mut() {
+            if let Some(request) = rx.recv().await {
+                return request;
+            }
+            self.rx = None;
+        }
+        std::future::pending().await
+    }
 
-    async fn subscribe(&mut self, header: Header, cursor_name: &str) -> Receiver<Event<Self::S>>;
+    /// Returns a pending request, if any.
+    pub(crate) fn try_recv(&mut self) -> Option<SourceRequest<S>> {
+        self.rx.as_mut().and_then(|rx| rx.try_recv().ok())
+    }
 }
@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use std::fmt;
 use tokio_postgres::Client;
+use tokio_postgres::GenericClient;
 use tokio_postgres::NoTls;
 use tokio_postgres::Transaction;
 
@@ -9,6 +10,9 @@ use super::StampedData;
 use crate::config::PostgresConfig;
 use crate::core::types::Header;
 use crate::core::types::Height;
+use crate::error::EwError;
+use crate::monitor::metrics;
+use crate::utils::CopyTypes;
 
 impl<D> From<&StampedData<D>> for Header {
     fn from(value: &StampedData<D>) -> Self {
@@ -27,107 +31,333 @@ pub trait BatchStore {
 
     async fn new() -> Self;
 
-    async fn persist(&mut self, pgtx: &Transaction<'_>, stamped_batch: &StampedData<Self::B>);
+    /// Write a batch.
+    ///
+    /// `pgtx` is open, committed by the calling `PgStore`.
+    async fn persist(
+        &mut self,
+        pgtx: &StoreTransaction,
+        stamped_batch: &StampedData<Self::B>,
+    ) -> Result<(), EwError>;
+
+    /// Remove batch of block with given `header`.
+    ///
+    /// `pgtx` is open, committed by the calling `PgStore`.
+    async fn roll_back(&mut self, pgtx: &StoreTransaction, header: &Header) -> Result<(), EwError>;
+}
+
+/// Db transaction of a `PgStore`, possibly spanning several blocks.
+///
+/// Owns the store's client and can only be begun, committed or rolled back
+/// by the `PgStore` itself. A transaction interrupted mid-write, by an error
+/// or a dropped future, is rolled back before the store writes again. One
+/// left open when the store is dropped gets rolled back by the server once
+/// the connection closes.
+pub struct StoreTransaction {
+    client: Client,
+    state: TransactionState,
+    copy_types: CopyTypes,
+}
 
-    async fn roll_back(&mut self, pgtx: &Transaction<'_>, header: &Header);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TransactionState {
+    /// No transaction open
+    Idle,
+    /// Open, holding complete blocks only
+    Open,
+    /// Write or transaction control in progress, or interrupted
+    Dirty,
+}
+
+impl StoreTransaction {
+    fn new(client: Client) -> Self {
+        Self {
+            client,
+            state: TransactionState::Idle,
+            copy_types: CopyTypes::default(),
+        }
+    }
+
+    /// Client to write through, within the open transaction.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Column types cache for `utils::copy_in` through this client.
+    pub fn copy_types(&self) -> &CopyTypes {
+        &self.copy_types
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.state == TransactionState::Dirty
+    }
+
+    async fn begin(&mut self) -> Result<(), EwError> {
+        assert_eq!(self.state, TransactionState::Idle);
+        self.state = TransactionState::Dirty;
+        self.client.batch_execute("begin;").await?;
+        self.state = TransactionState::Open;
+        Ok(())
+    }
+
+    fn start_write(&mut self) {
+        assert_eq!(self.state, TransactionState::Open);
+        self.state = TransactionState::Dirty;
+    }
+
+    fn end_write(&mut self) {
+        assert_eq!(self.state, TransactionState::Dirty);
+        self.state = TransactionState::Open;
+    }
+
+    async fn commit(&mut self) -> Result<(), EwError> {
+        assert_eq!(self.state, TransactionState::Open);
+        self.state = TransactionState::Dirty;
+        self.client.batch_execute("commit;").await?;
+        self.state = TransactionState::Idle;
+        Ok(())
+    }
+
+    /// Rolls back the transaction, if any.
+    ///
+    /// Safe to call when unsure, postgres only warns about rollbacks
+    /// outside of a transaction.
+    async fn rollback(&mut self) -> Result<(), EwError> {
+        if self.state != TransactionState::Idle {
+            self.state = TransactionState::Dirty;
+            self.client.batch_execute("rollback;").await?;
+            self.state = TransactionState::Idle;
+        }
+        Ok(())
+    }
 }
 
 pub struct PgStore<B: BatchStore> {
-    client: Client,
+    pgtx: StoreTransaction,
     schema: &'static str,
     worker_id: &'static str,
     header: Header,
     batch_store: B,
+    /// Maximum number of blocks to commit at once
+    batch_blocks: usize,
+    /// Number of persisted blocks awaiting commit
+    uncommitted: usize,
 }
 
 impl<B: BatchStore> PgStore<B> {
     pub fn get_client(&self) -> &Client {
-        &self.client
+        self.pgtx.client()
     }
 
+    /// Client for use outside of the store's own transactions.
     pub fn get_mut_client(&mut self) -> &mut Client {
-        &mut self.client
+        assert_eq!(self.pgtx.state, TransactionState::Idle);
+        &mut self.pgtx.client
     }
 
-    pub async fn new(pgconf: &PostgresConfig, store: &StoreDef) -> Self {
+    pub async fn new(pgconf: &PostgresConfig, store: &StoreDef) -> Result<Self, EwError> {
         tracing::debug!("initializing store {store}");
 
         // init client
-        let (mut client, connection) = tokio_postgres::connect(&pgconf.connection_uri, NoTls)
-            .await
-            .unwrap();
-
-        tokio::spawn(async move {
-            if let Err(e) = connection.await {
-                eprintln!("connection error: {}", e);
-            }
-        });
+        let mut client = connect(pgconf).await?;
 
         // Prepare store schema if needed
-        store.init(&mut client).await;
+        store.init(&mut client).await?;
 
         // Check revision
-        let rev = revisions::get(&client, &store).await;
+        let rev = revisions::get(&client, &store).await?;
+        store.check_supported(&rev)?;
         if &rev != store.revision {
-            panic!("Store revision is lagging. Ensure all migrations have been applied.")
+            return Err(EwError::StoreRevisionLagging(
+                store.to_string(),
+                store.revision.to_string(),
+            ));
         }
 
         // Retrieve header
-        let header = headers::get(&client, store.schema_name, store.worker_id).await;
+        let header = headers::get(&client, store.schema_name, store.worker_id).await?;
         tracing::debug!("store {store} is at {header:?}",);
 
-        Self {
-            client,
+        Ok(Self {
+            pgtx: StoreTransaction::new(client),
             schema: store.schema_name,
             worker_id: store.worker_id,
             header,
             batch_store: B::new().await,
-        }
+            batch_blocks: 1,
+            uncommitted: 0,
+        })
     }
 
     pub fn get_header(&self) -> &Header {
         &self.header
     }
 
-    pub async fn persist(&mut self, data: &StampedData<<B as BatchStore>::B>) {
+    /// Commit persisted blocks by groups of up to `n` blocks.
+    ///
+    /// Blocks are visible through the store's own client as soon as
+    /// persisted, but not to other connections before being committed.
+    /// Only suitable for stores not read by other workers, whose
+    /// workflows call `flush` once caught up.
+    pub fn set_batch_blocks(&mut self, n: usize) {
+        assert!(n > 0);
+        self.batch_blocks = n;
+    }
+
+    pub async fn persist(
+        &mut self,
+        data: &StampedData<<B as BatchStore>::B>,
+    ) -> Result<(), EwError> {
         tracing::trace!("persisiting data for {:?}", Header::from(data));
+        self.recover().await?;
         assert_eq!(self.header.height + 1, data.height);
         assert_eq!(self.header.header_id, data.parent_id);
 
-        // Start db tx
-        let pgtx = self.client.transaction().await.unwrap();
+        let timer = metrics::STORE_PERSIST_SECONDS
+            .with_label_values(&[self.schema, self.worker_id])
+            .start_timer();
+
+        // Start db tx, unless still open from previous blocks
+        if self.uncommitted == 0 {
+            self.begin().await?;
+        }
+
+        // Header is written in the same db tx as the data
+        let header = Header::from(data);
+        self.pgtx.start_write();
+        let res = match self.batch_store.persist(&self.pgtx, &data).await {
+            Ok(()) => {
+                headers::update(self.pgtx.client(), &self.schema, &self.worker_id, &header).await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = res {
+            self.abort().await?;
+            return Err(e);
+        }
+        self.pgtx.end_write();
+        self.header = header;
+        self.uncommitted += 1;
+
+        // Commit db tx once batch is full
+        if self.uncommitted >= self.batch_blocks {
+            self.commit().await?;
+        }
+        timer.observe_duration();
+        Ok(())
+    }
+
+    /// Returns true if persisted blocks are awaiting commit.
+    pub fn has_uncommitted(&self) -> bool {
+        self.uncommitted > 0
+    }
+
+    /// Commit any persisted blocks not committed yet.
+    pub async fn flush(&mut self) -> Result<(), EwError> {
+        self.recover().await?;
+        if self.uncommitted > 0 {
+            self.commit().await?;
+        }
+        Ok(())
+    }
+
+    /// Starts a db tx, holding a lock on the store's header until committed.
+    ///
+    /// Fails if the header got moved by another connection, i.e. a rewind,
+    /// in which case the store must be reloaded.
+    async fn begin(&mut self) -> Result<(), EwError> {
+        self.pgtx.begin().await?;
+        let header = headers::lock(self.pgtx.client(), self.schema, self.worker_id).await?;
+        if header != self.header {
+            self.pgtx.rollback().await?;
+            return Err(EwError::StoreHeaderMoved(
+                format!("{}.{}", self.schema, self.worker_id),
+                header.height,
+            ));
+        }
+        Ok(())
+    }
 
-        self.batch_store.persist(&pgtx, &data).await;
+    async fn commit(&mut self) -> Result<(), EwError> {
+        tracing::trace!("committing {} block(s)", self.uncommitted);
+        self.uncommitted = 0;
+        self.pgtx.commit().await
+    }
 
-        // Update header
-        self.header = Header::from(data);
-        headers::update(&pgtx, &self.schema, &self.worker_id, &self.header).await;
+    /// Discards blocks of an interrupted db tx, failed commits included.
+    ///
+    /// Errors if that moved the store's header back.
+    async fn recover(&mut self) -> Result<(), EwError> {
+        if !self.pgtx.is_dirty() {
+            return Ok(());
+        }
+        let height = self.header.height;
+        self.abort().await?;
+        if self.header.height != height {
+            return Err(EwError::StoreInterrupted(
+                format!("{}.{}", self.schema, self.worker_id),
+                self.header.height,
+            ));
+        }
+        Ok(())
+    }
 
-        // Commit db tx
-        pgtx.commit().await.unwrap();
+    /// Discards uncommitted blocks and resets header to the committed one.
+    async fn abort(&mut self) -> Result<(), EwError> {
+        tracing::debug!("discarding {} uncommitted block(s)", self.uncommitted);
+        self.pgtx.rollback().await?;
+        self.uncommitted = 0;
+        self.header = headers::get(self.pgtx.client(), self.schema, self.worker_id).await?;
+        Ok(())
     }
 
-    pub async fn roll_back(&mut self, height: Height) {
+    pub async fn roll_back(&mut self, height: Height) -> Result<(), EwError> {
         tracing::trace!("rolling back height {height}");
+
+        // Rollbacks are always committed on their own
+        self.flush().await?;
         assert_eq!(self.header.height, height);
 
-        let parent_header = core_headers::get(&self.client, &self.header.parent_id)
-            .await
-            .unwrap();
+        let parent_header = core_headers::get(self.pgtx.client(), &self.header.parent_id)
+            .await?
+            .expect("parent of a processed block is in core.headers");
 
         // Start db tx
-        let pgtx = self.client.transaction().await.unwrap();
+        self.begin().await?;
 
-        self.batch_store.roll_back(&pgtx, &self.header).await;
+        self.pgtx.start_write();
+        let res = match self.batch_store.roll_back(&self.pgtx, &self.header).await {
+            Ok(()) => {
+                let client = self.pgtx.client();
+                headers::update(client, &self.schema, &self.worker_id, &parent_header).await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = res {
+            self.abort().await?;
+            return Err(e);
+        }
+        self.pgtx.end_write();
 
-        // Update header
-        self.header = parent_header;
-        headers::update(&pgtx, &self.schema, &self.worker_id, &self.header).await;
+        self.pgtx.commit().await?;
 
-        pgtx.commit().await.unwrap();
+        // Only move header once committed
+        self.header = parent_header;
+        Ok(())
     }
 }
 
+/// Connects to postgres and spawns the connection task.
+async fn connect(pgconf: &PostgresConfig) -> Result<Client, EwError> {
+    let (client, connection) = tokio_postgres::connect(&pgconf.connection_uri, NoTls).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            tracing::error!("connection error: {}", e);
+        }
+    });
+    Ok(client)
+}
+
 #[async_trait]
 pub trait SourcableStore {
     type S;
@@ -135,26 +365,33 @@ pub trait SourcableStore {
     /// Get data for given height range.
     ///
     /// Used by lagging cursors to retrieve data.
-    async fn get_slice(&self, client: &Client, block_range: &BlockRange) -> Vec<Self::S>;
+    async fn get_slice(
+        &self,
+        client: &Client,
+        block_range: &BlockRange,
+    ) -> Result<Vec<Self::S>, EwError>;
 }
 
 impl<B: BatchStore + SourcableStore> PgStore<B> {
     /// Returns true if given `header` is part of main chain
-    pub async fn is_main_chain(&self, header: &Header) -> bool {
-        core_headers::is_main_chain(&self.client, &header).await
+    pub async fn is_main_chain(&self, header: &Header) -> Result<bool, EwError> {
+        core_headers::is_main_chain(self.pgtx.client(), &header).await
     }
 
     pub async fn get_slice(
         &self,
         block_range: &BlockRange,
-    ) -> Vec<StampedData<<B as SourcableStore>::S>> {
-        let headers = core_headers::get_slice(&self.client, block_range).await;
-        let datas = self.batch_store.get_slice(&self.client, block_range).await;
-        headers
+    ) -> Result<Vec<StampedData<<B as SourcableStore>::S>>, EwError> {
+        let headers = core_headers::get_slice(self.pgtx.client(), block_range).await?;
+        let datas = self
+            .batch_store
+            .get_slice(self.pgtx.client(), block_range)
+            .await?;
+        Ok(headers
             .into_iter()
             .zip(datas.into_iter())
             .map(|(h, d)| StampedData::new(h, d))
-            .collect()
+            .collect())
     }
 }
 
@@ -169,14 +406,73 @@ impl<S: BatchStore + PatchableStore> PgStore<S> {
     }
 }
 
+#[async_trait]
+pub trait RewindableStore {
+    /// Remove data of all blocks above given `height` at once.
+    ///
+    /// `pgtx` is open, committed by the calling `PgStore`.
+    async fn rewind(&mut self, pgtx: &StoreTransaction, height: Height) -> Result<(), EwError>;
+}
+
+impl<B: BatchStore + RewindableStore> PgStore<B> {
+    /// Discards all blocks above given `height` in a single db transaction.
+    ///
+    /// Safe to use while another instance of the worker is running. That
+    /// instance fails on its next block and, once restarted, catches up
+    /// from `height`.
+    pub async fn rewind(&mut self, height: Height) -> Result<(), EwError> {
+        // Pending blocks are committed first
+        self.flush().await?;
+
+        let header = core_headers::get_main_at(self.pgtx.client(), height)
+            .await?
+            .expect("rewind height is on main chain");
+
+        // Locking the header holds off other instances of the worker,
+        // which may have moved since this store got loaded.
+        self.pgtx.begin().await?;
+        self.header = headers::lock(self.pgtx.client(), self.schema, self.worker_id).await?;
+        if height >= self.header.height {
+            self.pgtx.rollback().await?;
+            return Ok(());
+        }
+        tracing::debug!("rewinding from height {} to {height}", self.header.height);
+
+        self.pgtx.start_write();
+        let res = match self.batch_store.rewind(&self.pgtx, height).await {
+            Ok(()) => {
+                headers::update(self.pgtx.client(), self.schema, self.worker_id, &header).await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = res {
+            self.abort().await?;
+            return Err(e);
+        }
+        self.pgtx.end_write();
+
+        self.pgtx.commit().await?;
+
+        // Only move header once committed
+        self.header = header;
+        Ok(())
+    }
+}
+
 pub struct StoreDef {
     pub schema_name: &'static str,
     pub worker_id: &'static str,
     pub sql: &'static str,
     pub revision: &'static Revision,
+    /// Migrations from revision 1.0 up to `revision`, in order.
+    pub migrations: &'static [&'static (dyn Migration + Sync)],
+    /// Keeps track of its position in `ew.headers`.
+    ///
+    /// Only core doesn't, as it follows the chain in `core.headers`.
+    pub has_header: bool,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Revision {
     pub major: i32,
     pub minor: i32,
@@ -188,35 +484,142 @@ impl Revision {
     }
 }
 
+impl fmt::Display for Revision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
 impl StoreDef {
     /// Initialize (worker's part of) schema if not declared yet.
     ///
     /// Executes schema's sql and add initial header
-    pub async fn init(&self, client: &mut Client) {
+    pub async fn init(&self, client: &mut Client) -> Result<(), EwError> {
         tracing::trace!("initializing schema {self}");
 
         // First off, check if ew schema is present.
-        if !schema_exists(client, "ew").await {
+        if !schema_exists(client, "ew").await? {
             tracing::debug!("loading ew schema");
-            let tx = client.transaction().await.unwrap();
-            tx.batch_execute(include_str!("ew.sql")).await.unwrap();
-            tx.commit().await.unwrap();
+            let tx = client.transaction().await?;
+            tx.batch_execute(include_str!("ew.sql")).await?;
+            tx.commit().await?;
+        }
+
+        if !self.is_initialized(client).await? {
+            let mut pgtx = client.transaction().await?;
+            match legacy_revision(&pgtx, self.schema_name).await? {
+                Some(rev) => {
+                    tracing::debug!("moving revision {rev} of {self} to ew.revisions");
+                    revisions::insert(&mut pgtx, self, &rev).await?;
+                    pgtx.batch_execute(&format!("drop table {}._rev;", self.schema_name))
+                        .await?;
+                }
+                None => {
+                    tracing::debug!("loading schema for {self}");
+                    pgtx.batch_execute(self.sql).await?;
+                    revisions::insert(&mut pgtx, self, self.revision).await?;
+                }
+            }
+            if self.has_header {
+                headers::insert_initial(&mut pgtx, &self.schema_name, &self.worker_id).await?;
+            }
+            pgtx.commit().await?;
+        }
+        Ok(())
+    }
+
+    /// Errors if given revision `rev` is newer than the store's.
+    ///
+    /// Happens when running an older version of ew against a database
+    /// migrated by a more recent one.
+    fn check_supported(&self, rev: &Revision) -> Result<(), EwError> {
+        if rev.major != self.revision.major || rev.minor > self.revision.minor {
+            return Err(EwError::StoreRevisionUnsupported(
+                self.to_string(),
+                rev.to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the store's header, if initialized.
+    pub async fn get_header(&self, client: &Client) -> Result<Option<Header>, EwError> {
+        if !self.has_header
+            || !schema_exists(client, "ew").await?
+            || !self.is_initialized(client).await?
+        {
+            return Ok(None);
+        }
+        Ok(Some(
+            headers::get(client, self.schema_name, self.worker_id).await?,
+        ))
+    }
+
+    /// Returns the store's height, if it has a header.
+    ///
+    /// Unlike [`Self::get_header`], takes a single query and errors if the
+    /// ew schema does not exist.
+    pub async fn get_height(&self, client: &Client) -> Result<Option<Height>, EwError> {
+        headers::get_height(client, self.schema_name, self.worker_id).await
+    }
+
+    /// Returns the store's revision, if initialized.
+    pub async fn get_revision(&self, client: &Client) -> Result<Option<Revision>, EwError> {
+        if !schema_exists(client, "ew").await? || !self.is_initialized(client).await? {
+            return legacy_revision(client, self.schema_name).await;
         }
+        Ok(Some(revisions::get(client, self).await?))
+    }
 
-        if !self.is_initialized(client).await {
-            tracing::debug!("loading schema for {self}");
-            let mut pgtx = client.transaction().await.unwrap();
-            pgtx.batch_execute(self.sql).await.unwrap();
-            revisions::insert(&mut pgtx, &self).await;
-            headers::insert_initial(&mut pgtx, &self.schema_name, &self.worker_id).await;
-            pgtx.commit().await.unwrap();
+    /// Remove all traces of the store, as if it never ran.
+    ///
+    /// Drops the store's schema, or only the tables declared by the store
+    /// if other stores live in the same schema. The store gets initialized
+    /// again, at its latest revision, next time it is used.
+    pub async fn purge(&self, client: &mut Client) -> Result<(), EwError> {
+        if !schema_exists(client, "ew").await? || !self.is_initialized(client).await? {
+            tracing::debug!("store {self} is not initialized, nothing to purge");
+            return Ok(());
         }
+        tracing::info!("purging store {self}");
+        let qry = "
+            select exists(
+                select *
+                from ew.revisions
+                where schema_name = $1 and worker_id <> $2
+        );";
+        let shared: bool = client
+            .query_one(qry, &[&self.schema_name, &self.worker_id])
+            .await?
+            .get(0);
+
+        let pgtx = client.transaction().await?;
+        if shared {
+            for table in declared_tables(self.sql) {
+                tracing::debug!("dropping table {table}");
+                pgtx.batch_execute(&format!("drop table if exists {table} cascade;"))
+                    .await?;
+            }
+        } else {
+            tracing::debug!("dropping schema {}", self.schema_name);
+            pgtx.batch_execute(&format!(
+                "drop schema if exists {} cascade;",
+                self.schema_name
+            ))
+            .await?;
+        }
+        if self.has_header {
+            headers::delete(&pgtx, self.schema_name, self.worker_id).await?;
+        }
+        revisions::delete(&pgtx, self.schema_name, self.worker_id).await?;
+        pgtx.commit().await?;
+        Ok(())
     }
 
     /// Checks if the store's relations have been initialized already.
     ///
     /// Checks for presence of schema/worker_id pair in the ew.revisions table.
-    async fn is_initialized(&self, client: &Client) -> bool {
+    async fn is_initialized(&self, client: &Client) -> Result<bool, EwError> {
         tracing::trace!("checking store initialization for {self}",);
         let qry = "
             select exists(
@@ -224,11 +627,10 @@ impl StoreDef {
                 from ew.revisions
                 where schema_name = $1 and worker_id = $2
         );";
-        client
+        Ok(client
             .query_one(qry, &[&self.schema_name, &self.worker_id])
-            .await
-            .unwrap()
-            .get(0)
+            .await?
+            .get(0))
     }
 }
 
@@ -245,8 +647,9 @@ mod revisions {
 
     use super::Revision;
     use super::StoreDef;
+    use crate::error::EwError;
 
-    pub(super) async fn get(client: &Client, store: &StoreDef) -> Revision {
+    pub(super) async fn get(client: &Client, store: &StoreDef) -> Result<Revision, EwError> {
         let qry = "
             select major, minor
             from ew.revisions
@@ -255,15 +658,18 @@ mod revisions {
         // Revision is set during schema declaration, so guaranteed to be present.
         let row = client
             .query_one(qry, &[&store.schema_name, &store.worker_id])
-            .await
-            .unwrap();
-        Revision {
+            .await?;
+        Ok(Revision {
             major: row.get(0),
             minor: row.get(1),
-        }
+        })
     }
 
-    pub(super) async fn insert(pgtx: &mut Transaction<'_>, schema: &StoreDef) {
+    pub(super) async fn insert(
+        pgtx: &mut Transaction<'_>,
+        schema: &StoreDef,
+        rev: &Revision,
+    ) -> Result<(), EwError> {
         tracing::trace!("insert for {schema}");
         let sql = "
             insert into ew.revisions (schema_name, worker_id, major, minor)
@@ -273,12 +679,12 @@ mod revisions {
             &[
                 &schema.schema_name,
                 &schema.worker_id,
-                &schema.revision.major,
-                &schema.revision.minor,
+                &rev.major,
+                &rev.minor,
             ],
         )
-        .await
-        .unwrap();
+        .await?;
+        Ok(())
     }
 
     pub(super) async fn update(
@@ -286,7 +692,7 @@ mod revisions {
         schema_name: &str,
         worker_id: &str,
         rev: &Revision,
-    ) {
+    ) -> Result<(), EwError> {
         tracing::trace!("update {schema_name} to {rev:?}");
         let sql = "
             update ew.revisions
@@ -294,34 +700,41 @@ mod revisions {
             where schema_name = $3 and worker_id = $4;";
         assert_eq!(
             pgtx.execute(sql, &[&rev.major, &rev.minor, &schema_name, &worker_id])
-                .await
-                .unwrap(),
+                .await?,
             1
         );
+        Ok(())
     }
 
-    pub(super) async fn delete(pgtx: &Transaction<'_>, schema_name: &str, worker_id: &str) {
+    pub(super) async fn delete(
+        pgtx: &Transaction<'_>,
+        schema_name: &str,
+        worker_id: &str,
+    ) -> Result<(), EwError> {
         tracing::trace!("delete {schema_name}");
         let sql = "
             delete from ew.revisions
             where schema_name = $1 and worker_id = $2;";
-        assert_eq!(
-            pgtx.execute(sql, &[&schema_name, &worker_id])
-                .await
-                .unwrap(),
-            1
-        );
+        assert_eq!(pgtx.execute(sql, &[&schema_name, &worker_id]).await?, 1);
+        Ok(())
     }
 }
 
 /// Access ew.headers table
 mod headers {
     use super::Header;
+    use super::Height;
+    use crate::error::EwError;
     use tokio_postgres::Client;
+    use tokio_postgres::GenericClient;
     use tokio_postgres::Transaction;
 
     /// Get header for given `schema` and `worker_id`.
-    pub(super) async fn get(client: &Client, schema: &str, worker_id: &str) -> Header {
+    pub(super) async fn get(
+        client: &Client,
+        schema: &str,
+        worker_id: &str,
+    ) -> Result<Header, EwError> {
         tracing::trace!("get {schema} {worker_id}");
         let qry = "
             select height
@@ -330,17 +743,63 @@ mod headers {
                 , parent_id
             from ew.headers
             where schema_name = $1 and worker_id = $2;";
-        let row = client.query_one(qry, &[&schema, &worker_id]).await.unwrap();
-        Header {
+        let row = client.query_one(qry, &[&schema, &worker_id]).await?;
+        Ok(Header {
             height: row.get(0),
             timestamp: row.get(1),
             header_id: row.get(2),
             parent_id: row.get(3),
-        }
+        })
+    }
+
+    /// Get height for given `schema` and `worker_id`, if any.
+    pub(super) async fn get_height(
+        client: &Client,
+        schema: &str,
+        worker_id: &str,
+    ) -> Result<Option<Height>, EwError> {
+        tracing::trace!("get height {schema} {worker_id}");
+        let qry = "
+            select height
+            from ew.headers
+            where schema_name = $1 and worker_id = $2;";
+        Ok(client
+            .query_opt(qry, &[&schema, &worker_id])
+            .await?
+            .map(|row| row.get(0)))
+    }
+
+    /// Get header for given `schema` and `worker_id` and lock it until the
+    /// end of the current db transaction.
+    pub(super) async fn lock(
+        client: &Client,
+        schema: &str,
+        worker_id: &str,
+    ) -> Result<Header, EwError> {
+        tracing::trace!("lock {schema} {worker_id}");
+        let qry = "
+            select height
+                , timestamp
+                , header_id
+                , parent_id
+            from ew.headers
+            where schema_name = $1 and worker_id = $2
+            for update;";
+        let row = client.query_one(qry, &[&schema, &worker_id]).await?;
+        Ok(Header {
+            height: row.get(0),
+            timestamp: row.get(1),
+            header_id: row.get(2),
+            parent_id: row.get(3),
+        })
     }
 
     /// Insert initial header.
-    pub(super) async fn insert_initial(pgtx: &Transaction<'_>, schema: &str, worker_id: &str) {
+    pub(super) async fn insert_initial(
+        pgtx: &Transaction<'_>,
+        schema: &str,
+        worker_id: &str,
+    ) -> Result<(), EwError> {
         tracing::trace!("insert initial for {schema}/{worker_id}");
         let h = Header::initial();
         let sql = "
@@ -360,17 +819,17 @@ mod headers {
                 &h.parent_id,
             ],
         )
-        .await
-        .unwrap();
+        .await?;
+        Ok(())
     }
 
     /// Update header for given `schema` and `worker_id`.
     pub(super) async fn update(
-        pgtx: &Transaction<'_>,
+        pgtx: &impl GenericClient,
         schema: &str,
         worker_id: &str,
         header: &Header,
-    ) {
+    ) -> Result<(), EwError> {
         tracing::trace!("update {schema} {worker_id} {header:?}");
         let sql = "
             update ew.headers
@@ -391,16 +850,21 @@ mod headers {
                     &worker_id,
                 ],
             )
-            .await
-            .unwrap();
+            .await?;
         assert_eq!(n_modified, 1);
+        Ok(())
     }
 
-    pub(super) async fn delete(pgtx: &Transaction<'_>, schema: &str, worker_id: &str) {
+    pub(super) async fn delete(
+        pgtx: &Transaction<'_>,
+        schema: &str,
+        worker_id: &str,
+    ) -> Result<(), EwError> {
         tracing::debug!("deleting record from ew.headers for {schema}-{worker_id}");
         let sql = "delete from ew.headers where schema_name = $1 and worker_id = $2;";
-        let n_deleted = pgtx.execute(sql, &[&schema, &worker_id]).await.unwrap();
+        let n_deleted = pgtx.execute(sql, &[&schema, &worker_id]).await?;
         assert_eq!(n_deleted, 1);
+        Ok(())
     }
 }
 
@@ -409,11 +873,12 @@ mod core_headers {
     use super::BlockRange;
     use super::Header;
     use super::Height;
+    use crate::error::EwError;
     use tokio_postgres::Client;
-    use tokio_postgres::Transaction;
+    use tokio_postgres::GenericClient;
 
     /// Get header with given `header_id`
-    pub async fn get(client: &Client, header_id: &str) -> Option<Header> {
+    pub async fn get(client: &Client, header_id: &str) -> Result<Option<Header>, EwError> {
         tracing::trace!("get {header_id}");
         let qry = "
             select height
@@ -424,20 +889,22 @@ mod core_headers {
             where header_id = $1
             order by height desc
             limit 1;";
-        client
+        Ok(client
             .query_opt(qry, &[&header_id])
-            .await
-            .unwrap()
+            .await?
             .map(|row| Header {
                 height: row.get(0),
                 timestamp: row.get(1),
                 header_id: row.get(2),
                 parent_id: row.get(3),
-            })
+            }))
     }
 
     /// Get main chain header for given `height`
-    pub async fn get_main_at(pgtx: &Transaction<'_>, height: Height) -> Option<Header> {
+    pub async fn get_main_at(
+        pgtx: &impl GenericClient,
+        height: Height,
+    ) -> Result<Option<Header>, EwError> {
         tracing::trace!("get_main_at {height}");
         let qry = "
             select height
@@ -447,19 +914,19 @@ mod core_headers {
             from core.headers
             where height = $1
                 and main_chain;";
-        pgtx.query_opt(qry, &[&height])
-            .await
-            .unwrap()
-            .map(|row| Header {
-                height: row.get(0),
-                timestamp: row.get(1),
-                header_id: row.get(2),
-                parent_id: row.get(3),
-            })
+        Ok(pgtx.query_opt(qry, &[&height]).await?.map(|row| Header {
+            height: row.get(0),
+            timestamp: row.get(1),
+            header_id: row.get(2),
+            parent_id: row.get(3),
+        }))
     }
 
     /// Get main chain headers for given `block_range`
-    pub async fn get_slice(client: &Client, block_range: &BlockRange) -> Vec<Header> {
+    pub async fn get_slice(
+        client: &Client,
+        block_range: &BlockRange,
+    ) -> Result<Vec<Header>, EwError> {
         tracing::trace!("get_at {block_range:?}");
         let qry = "
             select height
@@ -471,10 +938,9 @@ mod core_headers {
                 and height <= $2
                 and main_chain;
         ";
-        client
+        Ok(client
             .query(qry, &[&block_range.first_height, &block_range.last_height])
-            .await
-            .unwrap()
+            .await?
             .iter()
             .map(|row| Header {
                 height: row.get(0),
@@ -482,24 +948,34 @@ mod core_headers {
                 header_id: row.get(2),
                 parent_id: row.get(3),
             })
-            .collect()
+            .collect())
     }
 
-    pub async fn is_main_chain(client: &Client, header: &Header) -> bool {
+    pub async fn is_main_chain(client: &Client, header: &Header) -> Result<bool, EwError> {
         tracing::trace!("is_main_chain {header:?}");
         let qry = "
             select main_chain
             from core.headers
             where header_id = $1;";
-        match client.query_opt(qry, &[&header.header_id]).await.unwrap() {
+        Ok(match client.query_opt(qry, &[&header.header_id]).await? {
             Some(row) => row.get(0),
             None => false,
-        }
+        })
     }
 }
 
+/// Names of tables created by given schema `sql`.
+fn declared_tables(sql: &str) -> Vec<&str> {
+    sql.split("create table ")
+        .skip(1)
+        .map(|s| s.trim_start_matches("if not exists "))
+        .filter_map(|s| s.split(|c: char| c == '(' || c.is_whitespace()).next())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
 /// Returns True if a schema with given `name` exists.
-async fn schema_exists(client: &Client, name: &str) -> bool {
+async fn schema_exists(client: &Client, name: &str) -> Result<bool, EwError> {
     tracing::trace!("checking for existing {} schema", &name);
     let qry = "
     select exists(
@@ -507,7 +983,24 @@ async fn schema_exists(client: &Client, name: &str) -> bool {
         from information_schema.schemata
         where schema_name = $1
     );";
-    client.query_one(qry, &[&name]).await.unwrap().get(0)
+    Ok(client.query_one(qry, &[&name]).await?.get(0))
+}
+
+/// Revision of a schema created before `ew.revisions` kept track of it.
+///
+/// Such schemas hold their revision in a `_rev` table of their own.
+async fn legacy_revision(
+    client: &impl GenericClient,
+    schema_name: &str,
+) -> Result<Option<Revision>, EwError> {
+    let qry = "select to_regclass($1) is not null;";
+    let table = format!("{schema_name}._rev");
+    if !client.query_one(qry, &[&table]).await?.get::<_, bool>(0) {
+        return Ok(None);
+    }
+    let qry = format!("select rev_major, rev_minor from {table};");
+    let row = client.query_one(&qry, &[]).await?;
+    Ok(Some(Revision::new(row.get(0), row.get(1))))
 }
 
 #[async_trait]
@@ -516,10 +1009,17 @@ pub trait Migration: std::fmt::Debug {
 
     fn revision(&self) -> Revision;
 
-    async fn run(&self, pgtx: &Transaction<'_>) -> MigrationEffect;
+    /// SQL executed by the migration, if known upfront.
+    ///
+    /// Shown by dry runs. Migrations depending on store contents return `None`,
+    /// evaluating dry runs still report their effect.
+    fn sql(&self) -> Option<&'static str>;
+
+    async fn run(&self, pgtx: &Transaction<'_>) -> Result<MigrationEffect, EwError>;
 }
 
 /// Describes migration effect on store height and modifies `ew.headers` table accordingly.
+#[derive(Debug, PartialEq, Eq)]
 pub enum MigrationEffect {
     /// No content changes.
     None,
@@ -532,47 +1032,140 @@ pub enum MigrationEffect {
     Purge,
 }
 
+/// A migration that would be applied to a store.
+#[derive(Debug)]
+pub struct PendingMigration {
+    /// Store being migrated, as `schema:worker_id`
+    pub store: String,
+    pub revision: Revision,
+    pub description: &'static str,
+    pub sql: Option<&'static str>,
+    /// Effect the migration would have, given current store contents,
+    /// if evaluated
+    pub effect: Option<MigrationEffect>,
+}
+
+impl fmt::Display for PendingMigration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "-- {} {} - {}",
+            self.store, self.revision, self.description
+        )?;
+        match &self.effect {
+            Some(effect) => writeln!(f, "-- effect: {effect:?}")?,
+            None => writeln!(f, "-- effect: not evaluated")?,
+        }
+        match self.sql {
+            Some(sql) => writeln!(f, "{}", sql.trim()),
+            None => writeln!(f, "-- (no sql, migration depends on store contents)"),
+        }
+    }
+}
+
 /// Applies migrations to a PgStore.
 pub struct PgMigrator {
     client: Client,
-    schema: &'static str,
-    worker_id: &'static str,
+    store: &'static StoreDef,
     revision: Revision,
 }
 
 impl PgMigrator {
-    pub async fn new(pgconf: &PostgresConfig, store: &StoreDef) -> Self {
+    pub async fn new(pgconf: &PostgresConfig, store: &'static StoreDef) -> Result<Self, EwError> {
         tracing::debug!("initializing migrator {store}");
 
         // init client
-        let (mut client, connection) = tokio_postgres::connect(&pgconf.connection_uri, NoTls)
-            .await
-            .unwrap();
-
-        tokio::spawn(async move {
-            if let Err(e) = connection.await {
-                eprintln!("connection error: {}", e);
-            }
-        });
+        let mut client = connect(pgconf).await?;
 
         // Prepare store schema if needed
-        store.init(&mut client).await;
+        store.init(&mut client).await?;
 
         // Retrieve header
-        let revision = revisions::get(&client, store).await;
+        let revision = revisions::get(&client, store).await?;
         tracing::debug!("store {store} has revision {revision:?}");
 
-        Self {
+        Ok(Self {
             client,
-            schema: store.schema_name,
-            worker_id: store.worker_id,
+            store,
             revision,
+        })
+    }
+
+    /// Applies all pending migrations of the store, each in its own transaction.
+    pub async fn apply_pending(&mut self) -> Result<(), EwError> {
+        self.store.check_supported(&self.revision)?;
+        for mig in self.store.migrations {
+            self.apply(*mig).await?;
+        }
+        Ok(())
+    }
+
+    /// Lists pending migrations of given `store`, without modifying it.
+    ///
+    /// With `evaluate`, migrations are run in a transaction that gets rolled
+    /// back, to report their effect. Data migrations can take long on large
+    /// stores, so they are not run otherwise. A store that doesn't exist yet
+    /// would be created at its latest revision, reported as such.
+    pub async fn dry_run(
+        pgconf: &PostgresConfig,
+        store: &'static StoreDef,
+        evaluate: bool,
+    ) -> Result<Vec<PendingMigration>, EwError> {
+        let mut client = connect(pgconf).await?;
+        let revision = match store.get_revision(&client).await? {
+            Some(rev) => rev,
+            None => {
+                return Ok(vec![PendingMigration {
+                    store: store.to_string(),
+                    revision: store.revision.clone(),
+                    description: "Create store",
+                    sql: Some(store.sql),
+                    effect: Some(MigrationEffect::None),
+                }])
+            }
+        };
+        store.check_supported(&revision)?;
+        let pending_migrations = store
+            .migrations
+            .iter()
+            .filter(|mig| mig.revision().minor > revision.minor);
+
+        if !evaluate {
+            return Ok(pending_migrations
+                .map(|mig| PendingMigration {
+                    store: store.to_string(),
+                    revision: mig.revision(),
+                    description: mig.description(),
+                    sql: mig.sql(),
+                    effect: None,
+                })
+                .collect());
+        }
+
+        let pgtx = client.transaction().await?;
+        let mut pending = vec![];
+        for mig in pending_migrations {
+            let effect = mig.run(&pgtx).await?;
+            // Purged stores get created again at their latest revision
+            let purged = effect == MigrationEffect::Purge;
+            pending.push(PendingMigration {
+                store: store.to_string(),
+                revision: mig.revision(),
+                description: mig.description(),
+                sql: mig.sql(),
+                effect: Some(effect),
+            });
+            if purged {
+                break;
+            }
         }
+        pgtx.rollback().await?;
+        Ok(pending)
     }
 
     /// Execute migration in a single transaction
     #[tracing::instrument(name = "migration", skip(self))]
-    pub async fn apply(&mut self, mig: &impl Migration) {
+    pub async fn apply<M: Migration + ?Sized>(&mut self, mig: &M) -> Result<(), EwError> {
         tracing::trace!("evaluating migration {:?}", mig.revision());
         // Major migrations not supported yet
         assert_eq!(mig.revision().major, self.revision.major);
@@ -580,51 +1173,93 @@ impl PgMigrator {
         // Skip if migration already applied
         if mig.revision().minor <= self.revision.minor {
             tracing::trace!("skipping migration {:?}", mig.revision());
-            return;
+            return Ok(());
         }
 
         // Check migration to be applied is next in line.
         assert_eq!(mig.revision().minor, self.revision.minor + 1);
         tracing::info!(
-            "applying {} migration {:?} - {}",
-            self.worker_id,
+            "applying {} migration {} - {}",
+            self.store.worker_id,
             mig.revision(),
             mig.description(),
         );
 
         // Starting db transaction
-        let pgtx = self.client.transaction().await.unwrap();
+        let pgtx = self.client.transaction().await?;
 
         // Apply migration
-        let effect = mig.run(&pgtx).await;
+        let effect = mig.run(&pgtx).await?;
 
         // Reflect migration in store's header
         match effect {
             MigrationEffect::None => {
                 // Nothing to do
             }
-            MigrationEffect::Trimmed(height) => {
-                let header = core_headers::get_main_at(&pgtx, height).await.unwrap();
-                headers::update(&pgtx, self.schema, self.worker_id, &header).await;
+            MigrationEffect::Trimmed(height) if self.store.has_header => {
+                let header = core_headers::get_main_at(&pgtx, height)
+                    .await?
+                    .expect("trimmed height is on main chain");
+                headers::update(&pgtx, self.store.schema_name, self.store.worker_id, &header)
+                    .await?;
             }
-            MigrationEffect::Reset => {
+            MigrationEffect::Reset if self.store.has_header => {
                 // Reset worker by resetting its header
-                headers::delete(&pgtx, self.schema, self.worker_id).await;
-                headers::insert_initial(&pgtx, self.schema, self.worker_id).await;
+                headers::delete(&pgtx, self.store.schema_name, self.store.worker_id).await?;
+                headers::insert_initial(&pgtx, self.store.schema_name, self.store.worker_id)
+                    .await?;
             }
             MigrationEffect::Purge => {
-                headers::delete(&pgtx, self.schema, self.worker_id).await;
-                revisions::delete(&pgtx, self.schema, self.worker_id).await;
+                if self.store.has_header {
+                    headers::delete(&pgtx, self.store.schema_name, self.store.worker_id).await?;
+                }
+                revisions::delete(&pgtx, self.store.schema_name, self.store.worker_id).await?;
+            }
+            MigrationEffect::Trimmed(_) | MigrationEffect::Reset => {
+                // No header to reflect it in
             }
         };
 
         // Update store's revision
         if !matches!(effect, MigrationEffect::Purge) {
-            self.revision = mig.revision();
-            revisions::update(&pgtx, self.schema, self.worker_id, &self.revision).await;
+            revisions::update(
+                &pgtx,
+                self.store.schema_name,
+                self.store.worker_id,
+                &mig.revision(),
+            )
+            .await?;
         }
 
         // Commit db transaction
-        pgtx.commit().await.unwrap();
+        pgtx.commit().await?;
+        if matches!(effect, MigrationEffect::Purge) {
+            // Recreate store at its latest revision, leaving nothing to migrate
+            self.store.init(&mut self.client).await?;
+            self.revision = revisions::get(&self.client, self.store).await?;
+        } else {
+            self.revision = mig.revision();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_declared_tables() {
+        let sql = "
+            create schema if not exists erg;
+            create table erg.balances (
+                address_id bigint primary key not null
+            );
+            create table if not exists erg.balance_diffs(height integer);
+            create index on erg.balance_diffs using brin(height);";
+        assert_eq!(
+            declared_tables(sql),
+            vec!["erg.balances", "erg.balance_diffs"]
+        );
     }
 }
@@ -12,10 +12,15 @@ use super::event_handling::EventHandling;
 use super::event_handling::FwdEventHandler;
 use super::query_emission::Querying;
 use super::query_handling::QueryHandler;
+use super::source::ServedRequests;
+use super::source::SourceRequest;
+use super::source::SourceRequests;
 use super::Source;
 use crate::config::PostgresConfig;
 use crate::core::types::Header;
+use crate::error::EwError;
 use crate::monitor::MonitorMessage;
+use crate::shutdown::CancellationToken;
 
 pub struct LeafWorker<W: EventHandling> {
     id: &'static str,
@@ -34,23 +39,53 @@ impl<W: EventHandling> LeafWorker<W> {
         pgconf: &PostgresConfig,
         source: &mut impl Source<S = W::U>,
         monitor_tx: Sender<MonitorMessage>,
-    ) -> Self {
-        let event_handler = EventHandler::new(id, pgconf, source, monitor_tx).await;
-        Self { id, event_handler }
+    ) -> Result<Self, EwError> {
+        let event_handler = EventHandler::new(id, pgconf, source, monitor_tx).await?;
+        Ok(Self { id, event_handler })
     }
 
-    #[tracing::instrument(name="worker", skip(self), fields(worker=self.id))]
-    pub async fn start(&mut self) {
+    /// Create a new LeafWorker driving given `workflow`.
+    ///
+    /// * `id` - name of the worker
+    /// * `workflow` - the workflow to be driven by new worker
+    /// * `source` - the upstream source to track
+    /// * `monitor_tx` - a monitor channel
+    pub async fn new_with(
+        id: &'static str,
+        workflow: W,
+        source: &mut impl Source<S = W::U>,
+        monitor_tx: Sender<MonitorMessage>,
+    ) -> Result<Self, EwError> {
+        let event_handler = EventHandler::new_with(id, workflow, source, monitor_tx).await?;
+        Ok(Self { id, event_handler })
+    }
+
+    /// Handle upstream events until `shutdown` is cancelled.
+    ///
+    /// An event being handled is always completed before returning.
+    #[tracing::instrument(name="worker", skip_all, fields(worker=self.id))]
+    pub async fn start(&mut self, shutdown: CancellationToken) -> Result<(), EwError> {
         tracing::info!("starting");
         loop {
-            tokio::select! {
-                _ = tokio::signal::ctrl_c() => {
-                    tracing::info!("got a ctrl-c message");
-                    return;
-                },
-                _ = self.event_handler.recv_and_handle() => {}
+            // Commit batched writes before waiting for new blocks
+            self.event_handler.flush_if_idle().await?;
+            // Only waiting for events is cancellable, not handling them.
+            let event = tokio::select! {
+                biased;
+                _ = shutdown.cancelled() => break,
+                event = self.event_handler.recv() => event,
+            };
+            match event {
+                Some(event) => self.event_handler.process_upstream_event(&event).await?,
+                None => {
+                    tracing::warn!("Upstream is down :( stopping this worker");
+                    return Err(EwError::UpstreamDown);
+                }
             }
         }
+        self.event_handler.flush().await?;
+        tracing::info!("stopped");
+        Ok(())
     }
 }
 
@@ -67,6 +102,7 @@ pub struct SourceWorker<W: EventHandling + EventEmission<S = W::D>> {
     id: &'static str,
     event_handler: FwdEventHandler<W>,
     event_emitter: EventEmitter<W>,
+    requests: Option<SourceRequests<W::D>>,
 }
 
 impl<W: EventHandling + EventEmission<S = W::D>> SourceWorker<W> {
@@ -81,55 +117,118 @@ impl<W: EventHandling + EventEmission<S = W::D>> SourceWorker<W> {
         pgconf: &PostgresConfig,
         source: &mut impl Source<S = W::U>,
         monitor_tx: Sender<MonitorMessage>,
-    ) -> Self {
-        let event_handler = FwdEventHandler::new(id, pgconf, source, monitor_tx).await;
+    ) -> Result<Self, EwError> {
+        let event_handler = FwdEventHandler::new(id, pgconf, source, monitor_tx).await?;
         let event_emitter = EventEmitter::new();
-        Self {
+        Ok(Self {
             id,
             event_handler,
             event_emitter,
-        }
+            requests: None,
+        })
     }
 
-    #[tracing::instrument(name="worker", skip(self), fields(worker=self.id))]
-    pub async fn start(&mut self) {
+    /// Serve given `requests` from source handles once started.
+    pub fn serve(&mut self, requests: SourceRequests<W::D>) {
+        self.requests = Some(requests);
+    }
+
+    /// Handle and forward upstream events until `shutdown` is cancelled.
+    ///
+    /// An event being handled is always completed before returning.
+    #[tracing::instrument(name="worker", skip_all, fields(worker=self.id))]
+    pub async fn start(&mut self, shutdown: CancellationToken) -> Result<(), EwError> {
         tracing::info!("starting");
+        let mut requests = match &self.requests {
+            Some(requests) => requests.lock().await,
+            None => ServedRequests::none(),
+        };
         // Progress lagging cursors while waiting for new blocks
         let n = 10;
-        while self.event_emitter.has_lagging_cursors() {
-            // Handle up to `n` upstream events
-            for _ in 0..n {
-                match self.event_handler.try_next().await {
-                    Ok(handled_event) => self.event_emitter.forward(handled_event).await,
-                    Err(TryRecvError::Empty) => {
-                        // No events from upstream, move on.
-                        break;
-                    }
-                    Err(TryRecvError::Disconnected) => {
-                        tracing::warn!("Upstream is down :( stopping this worker");
-                        return;
+        loop {
+            if shutdown.is_cancelled() {
+                break;
+            }
+            if self.event_emitter.has_lagging_cursors() {
+                // Handle up to `n` upstream events
+                for _ in 0..n {
+                    match self.event_handler.try_recv() {
+                        Ok(event) => {
+                            let handled_event = self.event_handler.handle_event(event).await?;
+                            self.event_emitter.forward(handled_event).await;
+                        }
+                        Err(TryRecvError::Empty) => {
+                            // No events from upstream, move on.
+                            break;
+                        }
+                        Err(TryRecvError::Disconnected) => {
+                            tracing::warn!("Upstream is down :( stopping this worker");
+                            return Err(EwError::UpstreamDown);
+                        }
                     }
                 }
+                while let Some(request) = requests.try_recv() {
+                    self.handle_request(request).await;
+                }
+                // step lagging cursors by `n` and merge where possible
+                self.event_emitter
+                    .progress_lagging_cursors(self.event_handler.workflow(), n)
+                    .await?;
+                continue;
             }
-            // step lagging cursors by `n` and merge where possible
-            self.event_emitter
-                .progress_lagging_cursors(self.event_handler.workflow(), n)
-                .await;
-        }
-
-        loop {
+            // Commit batched writes before waiting for new blocks
+            self.event_handler.flush_if_idle().await?;
             tokio::select! {
-                _ = tokio::signal::ctrl_c() => {
-                    tracing::info!("got a ctrl-c message");
-                    return;
-                },
+                biased;
+                _ = shutdown.cancelled() => break,
+                request = requests.recv() => self.handle_request(request).await,
                 msg = self.event_handler.recv() => {
-                    let event = msg.unwrap();
-                    let handled_event = self.event_handler.handle_event(event).await;
+                    let Some(event) = msg else {
+                        tracing::warn!("Upstream is down :( stopping this worker");
+                        return Err(EwError::UpstreamDown);
+                    };
+                    let handled_event = self.event_handler.handle_event(event).await?;
                     self.event_emitter.forward(handled_event).await;
                 }
             }
         }
+        self.event_handler.flush().await?;
+        tracing::info!("stopped");
+        Ok(())
+    }
+
+    /// Respond to a request from a source handle.
+    ///
+    /// Requesters that are gone are ignored.
+    async fn handle_request(&mut self, request: SourceRequest<W::D>) {
+        match request {
+            SourceRequest::Header(tx) => {
+                tx.send(self.event_handler.workflow().header().clone()).ok();
+            }
+            SourceRequest::ContainsHeader(header, tx) => {
+                let res = self.event_handler.workflow().contains_header(&header).await;
+                tx.send(res).ok();
+            }
+            SourceRequest::Subscribe(header, cursor_name, tx) => {
+                let rx = self.subscribe_cursor(header, &cursor_name).await;
+                tx.send(rx).ok();
+            }
+        }
+    }
+
+    async fn subscribe_cursor(
+        &mut self,
+        header: Header,
+        cursor_name: &str,
+    ) -> Receiver<Event<W::D>> {
+        self.event_emitter
+            .subscribe(
+                header,
+                cursor_name,
+                self.event_handler.workflow(),
+                self.event_handler.monitor_tx(),
+            )
+            .await
     }
 }
 
@@ -137,23 +236,20 @@ impl<W: EventHandling + EventEmission<S = W::D>> SourceWorker<W> {
 impl<W: EventHandling + EventEmission<S = W::D> + Send + Sync> Source for SourceWorker<W> {
     type S = W::D;
 
-    fn header(&self) -> &Header {
-        &self.event_handler.workflow().header()
+    async fn header(&self) -> Result<Header, EwError> {
+        Ok(self.event_handler.workflow().header().clone())
     }
 
-    async fn contains_header(&self, header: &Header) -> bool {
+    async fn contains_header(&self, header: &Header) -> Result<bool, EwError> {
         self.event_handler.workflow().contains_header(header).await
     }
 
     // TODO: cursor name should not be set by caller
-    async fn subscribe(&mut self, header: Header, cursor_name: &str) -> Receiver<Event<Self::S>> {
-        self.event_emitter
-            .subscribe(
-                header,
-                cursor_name,
-                self.event_handler.workflow(),
-                self.event_handler.monitor_tx(),
-            )
-            .await
+    async fn subscribe(
+        &mut self,
+        header: Header,
+        cursor_name: &str,
+    ) -> Result<Receiver<Event<Self::S>>, EwError> {
+        Ok(self.subscribe_cursor(header, cursor_name).await)
     }
 }
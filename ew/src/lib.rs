@@ -1,7 +1,12 @@
+pub mod alerts;
+pub mod api;
 pub mod config;
 pub mod constants;
 pub mod core;
+pub mod error;
 pub mod framework;
 pub mod monitor;
+pub mod shutdown;
+pub mod supervisor;
 pub mod utils;
 pub mod workers;
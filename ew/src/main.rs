@@ -1,13 +1,104 @@
+use clap::Parser;
+use clap::Subcommand;
 use std::env;
+use std::path::PathBuf;
 use tokio;
 
+use ew::alerts;
+use ew::alerts::Alerter;
+use ew::api::QueryApi;
+use ew::config::Config;
+use ew::core::archive::BlockArchive;
+use ew::core::tracking::ArchiveTracker;
 use ew::core::tracking::Tracker;
+use ew::core::types::Header;
+use ew::core::types::Height;
 use ew::core::Node;
+use ew::error::EwError;
+use ew::framework::source_channel;
 use ew::monitor::Monitor;
-use ew::workers;
+use ew::shutdown::CancellationToken;
+use ew::supervisor::supervise;
+use ew::workers::admin;
+use ew::workers::admin::AdminError;
+use ew::workers::registry::WorkerID;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Ergo blockchain indexer.
+///
+/// Configured through `config.toml`, or the file in `EW_CONFIG`, and
+/// environment variables.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Start the tracker and enabled workers (default)
+    Run {
+        /// Replay blocks from an archive directory instead of following
+        /// a node
+        #[arg(long)]
+        archive: Option<PathBuf>,
+        /// Append settled blocks fetched from the node to an archive
+        /// directory
+        #[arg(long, conflicts_with = "archive")]
+        record: Option<PathBuf>,
+    },
+    /// Record settled blocks from the node to an archive directory, to
+    /// replay them later with `run --archive`. Resumes from the last
+    /// archived block.
+    Archive {
+        #[arg(long)]
+        dir: PathBuf,
+        /// Height to stop at, defaults to the node's settled height
+        #[arg(long)]
+        to: Option<Height>,
+    },
+    /// Print position of enabled workers against the tracker
+    Status,
+    /// Apply pending store migrations of core and enabled workers
+    Migrate {
+        /// Print pending migrations without applying them
+        #[arg(long)]
+        dry_run: bool,
+        /// Run pending migrations in a rolled back transaction to report
+        /// their effect. Data migrations can take long on large stores.
+        #[arg(long, requires = "dry_run")]
+        evaluate: bool,
+    },
+    /// Check stored state of enabled workers against core data and balance
+    /// diffs. Exits with an error if any mismatch is found.
+    Verify,
+    /// Roll a worker back to a given height. Stop running instances first.
+    Rollback {
+        #[arg(long)]
+        worker: WorkerID,
+        /// Height to roll back to
+        #[arg(long)]
+        to: Height,
+    },
+    /// Discard a worker's blocks above a given height at once, to process
+    /// them again. Running instances restart and catch up by themselves.
+    Rewind {
+        #[arg(long)]
+        worker: WorkerID,
+        /// Height to rewind to
+        #[arg(long)]
+        to: Height,
+    },
+    /// Remove all data of a worker, to sync it again from scratch. Stop
+    /// running instances first.
+    Reset {
+        #[arg(long)]
+        worker: WorkerID,
+    },
+}
+
 /// Gives some time to tracing subscriber
 async fn sleep_some() {
     let ms = 100;
@@ -17,6 +108,8 @@ async fn sleep_some() {
 
 #[tokio::main]
 async fn main() -> Result<(), &'static str> {
+    let cli = Cli::parse();
+
     // Configure tracing subscriber
     let filter = env::var("EW_LOG").unwrap_or(String::from("ew=info"));
     let subscriber = tracing_subscriber::fmt()
@@ -27,6 +120,99 @@ async fn main() -> Result<(), &'static str> {
 
     let _guard = tracing::subscriber::set_global_default(subscriber);
 
+    // Config
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::error!("{e}");
+            return Err("invalid configuration");
+        }
+    };
+    ew::constants::settings::set_rollback_horizon(config.rollback_horizon);
+
+    let res = match cli.command.unwrap_or(Command::Run {
+        archive: None,
+        record: None,
+    }) {
+        Command::Run { archive, record } => return run(config, archive, record).await,
+        Command::Archive { dir, to } => record_archive(&config, dir, to)
+            .await
+            .map(|header| tracing::info!("archive is at {header:?}"))
+            .map_err(AdminError::from),
+        Command::Status => admin::status(&config.postgres, config.workers.enabled())
+            .await
+            .map(|status| print!("{status}")),
+        Command::Migrate { dry_run: false, .. } => {
+            admin::migrate(&config.postgres, config.workers.enabled()).await
+        }
+        Command::Migrate {
+            dry_run: true,
+            evaluate,
+        } => admin::pending_migrations(&config.postgres, config.workers.enabled(), evaluate)
+            .await
+            .map(|pending| match pending.is_empty() {
+                true => println!("-- nothing to migrate"),
+                false => pending.iter().for_each(|mig| println!("{mig}")),
+            }),
+        Command::Verify => admin::verify(&config.postgres, config.workers.enabled())
+            .await
+            .and_then(|verification| {
+                print!("{verification}");
+                match verification.mismatches() {
+                    0 => Ok(()),
+                    n => Err(AdminError::VerificationFailed(n)),
+                }
+            }),
+        Command::Rollback { worker, to } => admin::roll_back(&config.postgres, worker, to)
+            .await
+            .map(|header| tracing::info!("{worker} is at {header:?}")),
+        Command::Rewind { worker, to } => admin::rewind(&config.postgres, worker, to)
+            .await
+            .map(|header| tracing::info!("{worker} is at {header:?}")),
+        Command::Reset { worker } => admin::reset(&config.postgres, worker).await,
+    };
+    if let Err(e) = res {
+        tracing::error!("{e}");
+        return Err("command failed");
+    }
+    Ok(())
+}
+
+/// Records blocks from the preferred node to an archive in `dir`.
+///
+/// Stops early on ctrl-c or SIGTERM.
+async fn record_archive(
+    config: &Config,
+    dir: PathBuf,
+    to: Option<Height>,
+) -> Result<Header, EwError> {
+    let node_config = config
+        .nodes()
+        .into_iter()
+        .min_by_key(|n| n.priority)
+        .expect("at least one node");
+    let node = Node::with_config(&node_config.url, &node_config);
+
+    let shutdown = CancellationToken::new();
+    let stop = shutdown.clone();
+    tokio::spawn(async move {
+        ew::shutdown::signal().await;
+        stop.cancel();
+    });
+
+    BlockArchive::new(dir).record(&node, to, &shutdown).await
+}
+
+/// Runs the tracker and enabled workers until stopped.
+///
+/// Blocks are replayed from the `archive` directory if given, instead of
+/// being requested from the configured nodes. Otherwise, settled blocks
+/// requested from nodes are appended to the `record` directory, if given.
+async fn run(
+    config: Config,
+    archive: Option<PathBuf>,
+    record: Option<PathBuf>,
+) -> Result<(), &'static str> {
     tracing::info!("starting ew v{VERSION}");
     if cfg!(feature = "test-utilities") {
         tracing::warn!("build includes test-utilities, use cargo's `--no-default-features` flag");
@@ -34,46 +220,28 @@ async fn main() -> Result<(), &'static str> {
         tracing::debug!("compiled without test-utilities");
     }
 
-    // Env variables
-    let pg_uri = env::var("EW_POSTGRES_URI").unwrap();
-    tracing::debug!("found EW_POSTGRES_URI environment variable");
+    let mut monitor = Monitor::with_config(&config.monitor);
 
-    let node_url = env::var("EW_NODE_URL").unwrap();
-    tracing::debug!("found EW_NODE_URL environment variable");
+    // Shared by tracker and workers to stop gracefully
+    let shutdown = CancellationToken::new();
 
-    let mut monitor = Monitor::new();
-
-    tracing::info!("configuring tracker");
-    let node = Node::new("local-node", &node_url);
-    let pgconf = ew::config::PostgresConfig::new(&pg_uri);
-    let mut tracker = Tracker::new(node, pgconf.clone(), monitor.sender()).await;
+    // Workers subscribe to the tracker through a handle, so they can
+    // resubscribe after a restart.
+    let (tracker_source, tracker_requests) = source_channel();
 
     // Workers
-    let mut timestamps =
-        workers::timestamps::Worker::new("timestamps", &pgconf, &mut tracker, monitor.sender())
-            .await;
-
-    let mut network =
-        workers::network::Worker::new("network", &pgconf, &mut tracker, monitor.sender()).await;
-
-    let mut erg_diffs =
-        workers::erg_diffs::Worker::new("erg_diffs", &pgconf, &mut tracker, monitor.sender()).await;
-    let mut erg_diffs_query_handler = workers::erg_diffs::QueryWorker::new(&pgconf).await;
-
-    let mut erg = workers::erg::Worker::new("erg", &pgconf, &mut erg_diffs, monitor.sender()).await;
-
-    let mut cex =
-        workers::exchanges::Worker::new("cex", &pgconf, &mut erg_diffs, monitor.sender()).await;
-    cex.connect_query_sender(&erg_diffs_query_handler);
+    let worker_handles =
+        config
+            .workers
+            .spawn(&config, &tracker_source, &monitor.sender(), &shutdown);
 
-    let mut tokens =
-        workers::tokens::Worker::new("tokens", &pgconf, &mut tracker, monitor.sender()).await;
-
-    let mut sigmausd =
-        workers::sigmausd::Worker::new("sigmausd", &pgconf, &mut tracker, monitor.sender()).await;
-
-    let mut coingecko =
-        workers::coingecko::Worker::new(&pgconf, &mut tracker, monitor.sender(), None).await;
+    // Tracker
+    let (pgconf, tracker_config) = (config.postgres.clone(), config.tracker.clone());
+    let monitor_tx = monitor.sender();
+    let failures_tx = monitor.sender();
+    let tracker_shutdown = shutdown.clone();
+    let alerts_events = monitor.subscribe();
+    let alerts_tx = monitor.sender();
 
     // Start monitor
     tokio::spawn(async move {
@@ -81,46 +249,160 @@ async fn main() -> Result<(), &'static str> {
         sleep_some().await;
     });
 
+    // Start query api
+    if let Some(port) = config.api.port {
+        QueryApi::new(&config.postgres, port).start();
+    }
+
+    // Start alerter
+    let alerter_handle = match config.alerts.rules.is_empty() {
+        true => None,
+        false => {
+            let (pgconf, alerts_config) = (config.postgres.clone(), config.alerts.clone());
+            let (monitor_tx, alerter_shutdown) = (alerts_tx.clone(), shutdown.clone());
+            let run_alerter = move || {
+                let (pgconf, alerts_config) = (pgconf.clone(), alerts_config.clone());
+                let (monitor_tx, shutdown) = (monitor_tx.clone(), alerter_shutdown.clone());
+                let events = alerts_events.resubscribe();
+                async move {
+                    let notifier = alerts::notifier::from_config(&alerts_config);
+                    let mut alerter =
+                        Alerter::new(&pgconf, &alerts_config, notifier, monitor_tx).await?;
+                    alerter.start(events, shutdown).await
+                }
+            };
+            Some(tokio::spawn(supervise(
+                alerts::ALERTER,
+                alerts_tx,
+                shutdown.clone(),
+                run_alerter,
+            )))
+        }
+    };
+
     // Start tracker
-    tokio::spawn(async move {
-        sleep_some().await;
-        tracker.start().await;
-    });
+    let tracker_handle = match archive {
+        None => {
+            // Nodes are identified by their url in logs and on the monitor
+            let nodes: Vec<Node> = config
+                .nodes()
+                .iter()
+                .map(|node_config| Node::with_config(&node_config.url, node_config))
+                .collect();
+            let record = record.map(BlockArchive::new);
+            let run_tracker = move || {
+                let (nodes, pgconf, tracker_config) =
+                    (nodes.clone(), pgconf.clone(), tracker_config.clone());
+                let (monitor_tx, shutdown) = (monitor_tx.clone(), tracker_shutdown.clone());
+                let (requests, record) = (tracker_requests.clone(), record.clone());
+                async move {
+                    tracing::info!("configuring tracker");
+                    let mut tracker =
+                        Tracker::new_with(nodes, pgconf, &tracker_config, monitor_tx).await?;
+                    if let Some(archive) = record {
+                        tracker.record_to(archive).await?;
+                    }
+                    tracker.serve(requests);
+                    tracker.start(shutdown).await
+                }
+            };
+            tokio::spawn(supervise(
+                ew::monitor::TRACKER,
+                failures_tx,
+                shutdown.clone(),
+                run_tracker,
+            ))
+        }
+        Some(dir) => {
+            let archive = BlockArchive::new(dir);
+            let run_tracker = move || {
+                let (archive, pgconf, tracker_config) =
+                    (archive.clone(), pgconf.clone(), tracker_config.clone());
+                let (monitor_tx, shutdown) = (monitor_tx.clone(), tracker_shutdown.clone());
+                let requests = tracker_requests.clone();
+                async move {
+                    tracing::info!("configuring archive tracker");
+                    let mut tracker =
+                        ArchiveTracker::new_with(archive, pgconf, &tracker_config, monitor_tx)
+                            .await?;
+                    tracker.serve(requests);
+                    tracker.start(shutdown).await
+                }
+            };
+            tokio::spawn(supervise(
+                ew::monitor::TRACKER,
+                failures_tx,
+                shutdown.clone(),
+                run_tracker,
+            ))
+        }
+    };
 
-    // Start workers
-    tokio::spawn(async move {
-        timestamps.start().await;
-    });
-    tokio::spawn(async move {
-        network.start().await;
-    });
-    tokio::spawn(async move {
-        erg_diffs_query_handler.start().await;
-    });
-    tokio::spawn(async move {
-        erg_diffs.start().await;
-    });
-    tokio::spawn(async move {
-        erg.start().await;
-    });
-    tokio::spawn(async move {
-        cex.start().await;
-    });
-    tokio::spawn(async move {
-        tokens.start().await;
-    });
-    tokio::spawn(async move {
-        sigmausd.start().await;
-    });
-    tokio::spawn(async move {
-        coingecko.start().await;
-    });
+    // Wait for ctrl-c or SIGTERM
+    ew::shutdown::signal().await;
+
+    // Tasks that are done already stopped without being asked to.
+    let mut failed = false;
+    for (worker, handle) in &worker_handles {
+        if handle.is_finished() {
+            tracing::error!("worker {worker} stopped unexpectedly");
+            failed = true;
+        }
+    }
+    if tracker_handle.is_finished() {
+        tracing::error!("tracker stopped unexpectedly");
+        failed = true;
+    }
+    if alerter_handle.as_ref().is_some_and(|h| h.is_finished()) {
+        tracing::error!("alerter stopped unexpectedly");
+        failed = true;
+    }
+
+    // Let tracker and workers finish their current block
+    tracing::info!("stopping tracker and workers");
+    shutdown.cancel();
+    // Tasks restarted after a failure count as failed too.
+    match tracker_handle.await {
+        Ok(0) => (),
+        Ok(n) => {
+            tracing::error!("tracker failed {n} time(s)");
+            failed = true;
+        }
+        Err(e) => {
+            tracing::error!("tracker failed: {e}");
+            failed = true;
+        }
+    }
+    for (worker, handle) in worker_handles {
+        match handle.await {
+            Ok(0) => tracing::debug!("worker {worker} stopped"),
+            Ok(n) => {
+                tracing::error!("worker {worker} failed {n} time(s)");
+                failed = true;
+            }
+            Err(e) => {
+                tracing::error!("worker {worker} failed: {e}");
+                failed = true;
+            }
+        }
+    }
+    if let Some(handle) = alerter_handle {
+        match handle.await {
+            Ok(0) => (),
+            Ok(n) => {
+                tracing::error!("alerter failed {n} time(s)");
+                failed = true;
+            }
+            Err(e) => {
+                tracing::error!("alerter failed: {e}");
+                failed = true;
+            }
+        }
+    }
 
-    // Wait for ctrl-c
-    _ = tokio::signal::ctrl_c().await;
-    for i in [3, 2, 1] {
-        tracing::info!("stopping in {i}");
-        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+    if failed {
+        tracing::error!("exiting after failure");
+        return Err("some tasks failed");
     }
     tracing::info!("exiting");
     Ok(())
@@ -1,4 +1,10 @@
+pub mod events;
+pub mod metrics;
+
 use axum::extract::Extension;
+use axum::http::header;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
 use axum::routing::get;
 use axum::Json;
 use axum::Router;
@@ -7,9 +13,17 @@ use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::sync::RwLock;
+use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 
+use crate::alerts::AlertState;
+use crate::config::MonitorConfig;
 use crate::core::types::Height;
+use events::BlockSummary;
+use events::LiveEvent;
+
+/// Name the tracker reports failures under.
+pub const TRACKER: &str = "tracker";
 
 #[derive(Debug)]
 pub enum MonitorMessage {
@@ -18,6 +32,20 @@ pub enum MonitorMessage {
     /// Holds name of dropped cursor
     CursorDrop(String),
     Rollback(CursorRollback),
+    /// Holds best full height reported by the nodes
+    NodeHeight(Height),
+    /// Tracker could not reach any node
+    NodeUnreachable,
+    /// Holds state of a node, as seen by the tracker
+    NodeStatus(NodeStatusMessage),
+    /// Holds node that served a block to the tracker
+    BlockServed(BlockServedMessage),
+    /// Holds height of tracker's header
+    TrackerHeight(Height),
+    /// Tracker or worker stopped with an error and is being restarted
+    Failure(FailureMessage),
+    /// Holds new state of an alert rule
+    Alert(AlertState),
 }
 
 #[derive(Debug)]
@@ -48,20 +76,216 @@ impl CursorRollback {
 pub struct WorkerMessage {
     name: &'static str,
     height: Height,
+    /// Summary of the block just included, if any
+    #[serde(skip_serializing)]
+    block: Option<BlockSummary>,
 }
 
 impl WorkerMessage {
     pub fn new(name: &'static str, height: Height) -> Self {
-        Self { name, height }
+        Self {
+            name,
+            height,
+            block: None,
+        }
+    }
+
+    pub fn height(&self) -> Height {
+        self.height
+    }
+
+    /// Attach a summary of the block just included, to be pushed to live subscribers.
+    pub fn with_block(mut self, block: BlockSummary) -> Self {
+        self.block = Some(block);
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct NodeStatusMessage {
+    id: String,
+    priority: u32,
+    /// Best full height, if node is reachable
+    height: Option<Height>,
+    /// Reason for not following the node, if any
+    issue: Option<String>,
+    /// True if followed by the tracker
+    active: bool,
+}
+
+impl NodeStatusMessage {
+    pub fn new(
+        id: String,
+        priority: u32,
+        height: Option<Height>,
+        issue: Option<String>,
+        active: bool,
+    ) -> Self {
+        Self {
+            id,
+            priority,
+            height,
+            issue,
+            active,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BlockServedMessage {
+    /// Id of node the block was retrieved from
+    node: String,
+    height: Height,
+}
+
+impl BlockServedMessage {
+    pub fn new(node: String, height: Height) -> Self {
+        Self { node, height }
     }
 }
 
+#[derive(Debug)]
+pub struct FailureMessage {
+    /// Name of failed task
+    name: &'static str,
+    error: String,
+}
+
+impl FailureMessage {
+    pub fn new(name: &'static str, error: String) -> Self {
+        Self { name, error }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+struct FailureStatus {
+    /// Number of failures since start
+    failures: u32,
+    last_error: String,
+    /// True until the task makes progress again
+    failing: bool,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+struct NodeStatus {
+    priority: u32,
+    /// Best full height, if node is reachable
+    height: Option<Height>,
+    /// Reason for not following the node, if any
+    issue: Option<String>,
+    /// True if followed by the tracker
+    active: bool,
+    /// Number of blocks served since start
+    blocks_served: u64,
+    /// Height of last block served
+    last_block_height: Option<Height>,
+}
+
 #[derive(Default, Serialize)]
 struct MonitorData {
     /// Cursor specific timers
     cursors: HashMap<String, CursorStatus>,
     /// Workers
     workers: HashMap<&'static str, Height>,
+    /// Best full height reported by the nodes
+    node_height: Option<Height>,
+    /// True if last attempt to reach any node failed
+    node_unreachable: bool,
+    /// Nodes known to the tracker
+    nodes: HashMap<String, NodeStatus>,
+    /// Height of tracker's header
+    tracker_height: Option<Height>,
+    /// Failed tasks
+    failures: HashMap<&'static str, FailureStatus>,
+    /// Alert rule states
+    alerts: HashMap<String, AlertState>,
+}
+
+impl MonitorData {
+    /// Records a failure of given task.
+    fn log_failure(&mut self, msg: FailureMessage) {
+        self.failures
+            .entry(msg.name)
+            .and_modify(|fs| {
+                fs.failures += 1;
+                fs.last_error = msg.error.clone();
+                fs.failing = true;
+            })
+            .or_insert(FailureStatus {
+                failures: 1,
+                last_error: msg.error,
+                failing: true,
+            });
+    }
+
+    /// Records the state of a node.
+    fn log_node_status(&mut self, msg: NodeStatusMessage) {
+        let status = self.nodes.entry(msg.id).or_default();
+        status.priority = msg.priority;
+        status.height = msg.height;
+        status.issue = msg.issue;
+        status.active = msg.active;
+    }
+
+    /// Records a block served by a node.
+    fn log_block_served(&mut self, msg: &BlockServedMessage) {
+        let status = self.nodes.entry(msg.node.clone()).or_default();
+        status.blocks_served += 1;
+        status.last_block_height = Some(msg.height);
+    }
+
+    /// Marks given task as recovered, if it failed before.
+    fn log_progress(&mut self, name: &str) {
+        if let Some(fs) = self.failures.get_mut(name) {
+            fs.failing = false;
+        }
+    }
+
+    /// Refreshes cursor lag metrics
+    fn update_lag_metrics(&self) {
+        if let Some(node_height) = self.node_height {
+            for (name, cs) in &self.cursors {
+                metrics::CURSOR_LAG
+                    .with_label_values(&[name])
+                    .set((node_height - cs.height).into());
+            }
+        }
+    }
+
+    /// Returns reasons for not being ready, if any.
+    ///
+    /// * `max_lag` - number of blocks workers are allowed to be behind the tracker
+    fn readiness_issues(&self, max_lag: Height) -> Vec<String> {
+        let mut issues = vec![];
+        if self.node_unreachable {
+            issues.push("node is unreachable".to_owned());
+        }
+        let mut failures: Vec<(&&str, &FailureStatus)> =
+            self.failures.iter().filter(|(_, fs)| fs.failing).collect();
+        failures.sort_by_key(|(name, _)| **name);
+        for (name, fs) in failures {
+            issues.push(format!(
+                "{name} failed and is restarting: {}",
+                fs.last_error
+            ));
+        }
+        match self.tracker_height {
+            None => issues.push("tracker has not reported its height yet".to_owned()),
+            Some(tracker_height) => {
+                let mut workers: Vec<(&&str, &Height)> = self.workers.iter().collect();
+                workers.sort();
+                for (name, height) in workers {
+                    let lag = tracker_height - height;
+                    if lag > max_lag {
+                        issues.push(format!(
+                            "worker {name} is {lag} blocks behind tracker (max {max_lag})"
+                        ));
+                    }
+                }
+            }
+        }
+        issues
+    }
 }
 
 #[derive(Serialize, Clone)]
@@ -78,6 +302,8 @@ struct CursorStatus {
     timer_since_start: std::time::Instant,
     #[serde(skip_serializing)]
     timer_last_100: std::time::Instant,
+    #[serde(skip_serializing)]
+    timer_last_block: std::time::Instant,
 }
 
 impl CursorStatus {
@@ -90,11 +316,16 @@ impl CursorStatus {
             bps_last_100: 0f32,
             timer_since_start: std::time::Instant::now(),
             timer_last_100: std::time::Instant::now(),
+            timer_last_block: std::time::Instant::now(),
         }
     }
 
     /// Save time elapsed since last update
-    pub fn update(&mut self, height: Height) {
+    pub fn update(&mut self, name: &str, height: Height) {
+        metrics::CURSOR_BLOCK_SECONDS
+            .with_label_values(&[name])
+            .observe(self.timer_last_block.elapsed().as_secs_f64());
+        self.timer_last_block = std::time::Instant::now();
         self.height = height;
         self.blocks_since_start += 1;
         self.bps_since_start =
@@ -115,18 +346,52 @@ type SharedState = Arc<RwLock<MonitorData>>;
 pub struct Monitor {
     tx: mpsc::Sender<MonitorMessage>,
     rx: mpsc::Receiver<MonitorMessage>,
+    /// Live events, streamed on `/events`
+    events: broadcast::Sender<LiveEvent>,
+    port: u16,
+    max_lag: Height,
+}
+
+impl Default for Monitor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Monitor {
+    /// Create a new monitor listening on default port.
     pub fn new() -> Self {
+        Self::with_config(&MonitorConfig::default())
+    }
+
+    /// Create a new monitor from given `config`.
+    pub fn with_config(config: &MonitorConfig) -> Self {
         let (tx, rx) = mpsc::channel(32);
-        Self { tx, rx }
+        let (events, _) = broadcast::channel(events::CAPACITY);
+        Self {
+            tx,
+            rx,
+            events,
+            port: config.port,
+            max_lag: config.max_lag,
+        }
     }
 
     pub fn sender(&self) -> mpsc::Sender<MonitorMessage> {
         self.tx.clone()
     }
 
+    /// Subscribe to live events.
+    pub fn subscribe(&self) -> broadcast::Receiver<LiveEvent> {
+        self.events.subscribe()
+    }
+
+    /// Pushes an event to live subscribers, if any.
+    fn push(&self, event: LiveEvent) {
+        // Fails only when nobody is listening
+        _ = self.events.send(event);
+    }
+
     pub async fn start(&mut self) {
         let state = SharedState::default();
 
@@ -138,19 +403,38 @@ impl Monitor {
                     let mut data = state.write().unwrap();
                     data.cursors
                         .entry(msg.name.clone())
-                        .and_modify(|cs| cs.update(msg.height))
+                        .and_modify(|cs| cs.update(&msg.name, msg.height))
                         .or_insert(CursorStatus::new());
+                    metrics::CURSOR_HEIGHT
+                        .with_label_values(&[&msg.name])
+                        .set(msg.height.into());
+                    data.update_lag_metrics();
                 }
                 MonitorMessage::CursorDrop(cursor_name) => {
                     let mut data = state.write().unwrap();
-                    data.cursors.remove(&cursor_name).unwrap();
+                    data.cursors.remove(&cursor_name);
+                    // Dropped cursors are not reported anymore
+                    _ = metrics::CURSOR_HEIGHT.remove_label_values(&[&cursor_name]);
+                    _ = metrics::CURSOR_LAG.remove_label_values(&[&cursor_name]);
                 }
                 MonitorMessage::Worker(msg) => {
                     let mut data = state.write().unwrap();
+                    data.log_progress(msg.name);
                     data.workers
                         .entry(msg.name)
                         .and_modify(|h| *h = msg.height)
                         .or_insert(msg.height);
+                    metrics::WORKER_HEIGHT
+                        .with_label_values(&[msg.name])
+                        .set(msg.height.into());
+                    if let Some(block) = msg.block {
+                        self.push(LiveEvent::Block {
+                            worker: msg.name,
+                            height: msg.height,
+                            header_id: block.header_id,
+                            figures: block.figures,
+                        });
+                    }
                 }
                 MonitorMessage::Rollback(msg) => {
                     let mut data = state.write().unwrap();
@@ -158,6 +442,55 @@ impl Monitor {
                         .entry(msg.name.clone())
                         .and_modify(|cs| cs.log_rollback(msg.height))
                         .or_insert(CursorStatus::new());
+                    metrics::CURSOR_ROLLBACKS
+                        .with_label_values(&[&msg.name])
+                        .inc();
+                    self.push(LiveEvent::Rollback {
+                        cursor: msg.name,
+                        height: msg.height,
+                    });
+                }
+                MonitorMessage::NodeHeight(height) => {
+                    let mut data = state.write().unwrap();
+                    data.node_height = Some(height);
+                    data.node_unreachable = false;
+                    metrics::NODE_HEIGHT.set(height.into());
+                    data.update_lag_metrics();
+                }
+                MonitorMessage::NodeUnreachable => {
+                    let mut data = state.write().unwrap();
+                    data.node_unreachable = true;
+                }
+                MonitorMessage::NodeStatus(msg) => {
+                    metrics::NODE_ACTIVE
+                        .with_label_values(&[&msg.id])
+                        .set(msg.active.into());
+                    let mut data = state.write().unwrap();
+                    data.log_node_status(msg);
+                }
+                MonitorMessage::BlockServed(msg) => {
+                    metrics::NODE_BLOCKS_SERVED
+                        .with_label_values(&[&msg.node])
+                        .inc();
+                    let mut data = state.write().unwrap();
+                    data.log_block_served(&msg);
+                }
+                MonitorMessage::TrackerHeight(height) => {
+                    let mut data = state.write().unwrap();
+                    data.log_progress(TRACKER);
+                    data.tracker_height = Some(height);
+                }
+                MonitorMessage::Failure(msg) => {
+                    metrics::TASK_FAILURES.with_label_values(&[msg.name]).inc();
+                    let mut data = state.write().unwrap();
+                    data.log_failure(msg);
+                }
+                MonitorMessage::Alert(alert) => {
+                    metrics::ALERT_FIRING
+                        .with_label_values(&[&alert.rule])
+                        .set(alert.firing.into());
+                    let mut data = state.write().unwrap();
+                    data.alerts.insert(alert.rule.clone(), alert);
                 }
             };
         }
@@ -167,12 +500,23 @@ impl Monitor {
         let app = Router::new()
             .route(
                 "/",
-                get(|| async { "Hey there, you're probably after /status" }),
+                get(|| async {
+                    "Hey there, you're probably after /status, /metrics, /events or /alerts"
+                }),
             )
             .route("/status", get(status))
-            .layer(Extension(state));
+            .route("/metrics", get(prometheus_metrics))
+            .route("/healthz", get(|| async { "ok" }))
+            .route("/readyz", get(readiness))
+            .route("/events", get(events::stream))
+            .route("/alerts", get(alerts))
+            .layer(Extension(state))
+            .layer(Extension(self.events.clone()))
+            .layer(Extension(Readiness {
+                max_lag: self.max_lag,
+            }));
 
-        let address = SocketAddr::from(([0, 0, 0, 0], 3005));
+        let address = SocketAddr::from(([0, 0, 0, 0], self.port));
         tokio::spawn(async move {
             tracing::info!("listening on {}", &address);
 
@@ -189,6 +533,8 @@ struct Status {
     // cursors: Vec<CursorStatus>,
     cursors: HashMap<String, CursorStatus>,
     workers: Vec<WorkerMessage>,
+    nodes: HashMap<String, NodeStatus>,
+    failures: HashMap<&'static str, FailureStatus>,
 }
 
 async fn status(Extension(state): Extension<SharedState>) -> Json<Status> {
@@ -198,7 +544,195 @@ async fn status(Extension(state): Extension<SharedState>) -> Json<Status> {
     let workers: Vec<WorkerMessage> = data
         .workers
         .iter()
-        .map(|(k, v)| WorkerMessage::new(*k, *v))
+        .map(|(k, v)| WorkerMessage::new(k, *v))
         .collect();
-    Json(Status { cursors, workers })
+    let nodes = data.nodes.clone();
+    let failures = data.failures.clone();
+    Json(Status {
+        cursors,
+        workers,
+        nodes,
+        failures,
+    })
+}
+
+/// Alert rule states, by rule name
+async fn alerts(Extension(state): Extension<SharedState>) -> Json<Vec<AlertState>> {
+    let mut alerts: Vec<AlertState> = state.read().unwrap().alerts.values().cloned().collect();
+    alerts.sort_by(|a, b| a.rule.cmp(&b.rule));
+    Json(alerts)
+}
+
+/// Metrics in Prometheus text format
+async fn prometheus_metrics() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::render(),
+    )
+}
+
+#[derive(Clone)]
+struct Readiness {
+    /// Number of blocks workers are allowed to be behind the tracker
+    max_lag: Height,
+}
+
+/// Ready when node is reachable and no worker is lagging
+async fn readiness(
+    Extension(state): Extension<SharedState>,
+    Extension(readiness): Extension<Readiness>,
+) -> (StatusCode, String) {
+    let issues = state.read().unwrap().readiness_issues(readiness.max_lag);
+    if issues.is_empty() {
+        (StatusCode::OK, "ready".to_owned())
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, issues.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_not_ready_before_tracker_reports() {
+        let data = MonitorData::default();
+        assert_eq!(
+            data.readiness_issues(10),
+            vec!["tracker has not reported its height yet"]
+        );
+    }
+
+    #[test]
+    fn test_readiness_lagging_worker() {
+        let mut data = MonitorData {
+            tracker_height: Some(100),
+            ..Default::default()
+        };
+        data.workers.insert("erg", 90);
+        data.workers.insert("tokens", 89);
+        assert_eq!(
+            data.readiness_issues(10),
+            vec!["worker tokens is 11 blocks behind tracker (max 10)"]
+        );
+        data.workers.insert("tokens", 100);
+        assert!(data.readiness_issues(10).is_empty());
+    }
+
+    #[test]
+    fn test_readiness_node_unreachable() {
+        let data = MonitorData {
+            tracker_height: Some(100),
+            node_unreachable: true,
+            ..Default::default()
+        };
+        assert_eq!(data.readiness_issues(10), vec!["node is unreachable"]);
+    }
+
+    #[test]
+    fn test_readiness_failed_worker() {
+        let mut data = MonitorData {
+            tracker_height: Some(100),
+            ..Default::default()
+        };
+        data.workers.insert("erg", 100);
+        data.log_failure(FailureMessage::new(
+            "erg",
+            "Upstream source is down".to_owned(),
+        ));
+        assert_eq!(
+            data.readiness_issues(10),
+            vec!["erg failed and is restarting: Upstream source is down"]
+        );
+        data.log_progress("erg");
+        assert!(data.readiness_issues(10).is_empty());
+        assert_eq!(data.failures["erg"].failures, 1);
+    }
+
+    #[test]
+    fn test_node_status() {
+        let mut data = MonitorData::default();
+        data.log_node_status(NodeStatusMessage::new(
+            "main".to_owned(),
+            0,
+            None,
+            Some("unreachable".to_owned()),
+            false,
+        ));
+        data.log_node_status(NodeStatusMessage::new(
+            "backup".to_owned(),
+            1,
+            Some(100),
+            None,
+            true,
+        ));
+        data.log_block_served(&BlockServedMessage::new("backup".to_owned(), 99));
+        data.log_block_served(&BlockServedMessage::new("backup".to_owned(), 100));
+        assert_eq!(
+            data.nodes["backup"],
+            NodeStatus {
+                priority: 1,
+                height: Some(100),
+                issue: None,
+                active: true,
+                blocks_served: 2,
+                last_block_height: Some(100),
+            }
+        );
+        assert_eq!(data.nodes["main"].issue, Some("unreachable".to_owned()));
+        assert_eq!(data.nodes["main"].blocks_served, 0);
+    }
+
+    #[tokio::test]
+    async fn test_live_events() {
+        let mut monitor = Monitor::with_config(&MonitorConfig {
+            port: 3107,
+            max_lag: 10,
+        });
+        let tx = monitor.sender();
+        tokio::spawn(async move { monitor.start().await });
+
+        // Server might not be listening yet
+        let mut res = loop {
+            if let Ok(res) = reqwest::get("http://localhost:3107/events").await {
+                break res;
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+        };
+
+        let summary = BlockSummary {
+            header_id: "header10".to_owned(),
+            figures: Some(events::Figures::CexSupply {
+                main: 1000,
+                deposits: 50,
+            }),
+        };
+        let msg = WorkerMessage::new("exchanges", 10).with_block(summary);
+        tx.send(MonitorMessage::Worker(msg)).await.unwrap();
+        // Rolled back workers report their status without a block
+        let msg = WorkerMessage::new("exchanges", 9);
+        tx.send(MonitorMessage::Worker(msg)).await.unwrap();
+        let msg = CursorRollback::new("main".to_owned(), 10);
+        tx.send(MonitorMessage::Rollback(msg)).await.unwrap();
+
+        let mut body = String::new();
+        while body.matches("\n\n").count() < 2 {
+            let chunk = tokio::time::timeout(tokio::time::Duration::from_secs(5), res.chunk())
+                .await
+                .expect("live events within 5 seconds")
+                .unwrap()
+                .unwrap();
+            body.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+        assert_eq!(
+            body,
+            concat!(
+                r#"data:{"type":"block","worker":"exchanges","height":10,"header_id":"header10","figures":{"main":1000,"deposits":50}}"#,
+                "\n\n",
+                r#"data:{"type":"rollback","cursor":"main","height":10}"#,
+                "\n\n",
+            )
+        );
+    }
 }
@@ -0,0 +1,143 @@
+//! Live block events, pushed to subscribers of `/events` as Server-Sent Events.
+use axum::extract::Extension;
+use axum::response::sse::Event;
+use axum::response::sse::KeepAlive;
+use axum::response::sse::Sse;
+use futures_util::stream::Stream;
+use serde::Serialize;
+use std::convert::Infallible;
+use tokio::sync::broadcast;
+
+use crate::core::types::HeaderID;
+use crate::core::types::Height;
+use crate::core::types::NanoERG;
+
+/// Number of events buffered for slow subscribers before they start missing some.
+pub(super) const CAPACITY: usize = 256;
+
+/// Key figures of a block, specific to the worker that included it.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum Figures {
+    /// Supply composition, from the erg worker
+    SupplyComposition {
+        p2pks: NanoERG,
+        contracts: NanoERG,
+        miners: NanoERG,
+    },
+    /// Supply on exchange addresses, from the exchanges worker
+    CexSupply { main: NanoERG, deposits: NanoERG },
+    /// SigmaUSD bank state, from the sigmausd worker
+    SigmaUSD {
+        reserves: NanoERG,
+        circ_sc: i64,
+        circ_rc: i64,
+        oracle: i64,
+    },
+}
+
+/// Summary of a block included by a worker.
+#[derive(Debug)]
+pub struct BlockSummary {
+    pub header_id: HeaderID,
+    pub figures: Option<Figures>,
+}
+
+/// Event pushed to live subscribers.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveEvent {
+    /// A worker included a block
+    Block {
+        worker: &'static str,
+        height: Height,
+        header_id: HeaderID,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        figures: Option<Figures>,
+    },
+    /// A cursor rolled back the block at given height
+    Rollback { cursor: String, height: Height },
+}
+
+/// Streams live events to a new subscriber.
+///
+/// Subscribers only receive events emitted after they connected.
+pub(super) async fn stream(
+    Extension(events): Extension<broadcast::Sender<LiveEvent>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let rx = events.subscribe();
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    let sse = Event::default()
+                        .json_data(&event)
+                        .expect("live events are serializable");
+                    return Some((Ok(sse), rx));
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!("live event subscriber is lagging - skipped {n} events");
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[test]
+    fn test_block_event_json() {
+        let event = LiveEvent::Block {
+            worker: "erg",
+            height: 100,
+            header_id: "abc".to_owned(),
+            figures: Some(Figures::SupplyComposition {
+                p2pks: 1,
+                contracts: 2,
+                miners: 3,
+            }),
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({
+                "type": "block",
+                "worker": "erg",
+                "height": 100,
+                "header_id": "abc",
+                "figures": {"p2pks": 1, "contracts": 2, "miners": 3},
+            })
+        );
+    }
+
+    #[test]
+    fn test_block_event_json_without_figures() {
+        let event = LiveEvent::Block {
+            worker: "tokens",
+            height: 100,
+            header_id: "abc".to_owned(),
+            figures: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({"type": "block", "worker": "tokens", "height": 100, "header_id": "abc"})
+        );
+    }
+
+    #[test]
+    fn test_rollback_event_json() {
+        let event = LiveEvent::Rollback {
+            cursor: "main".to_owned(),
+            height: 100,
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({"type": "rollback", "cursor": "main", "height": 100})
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use prometheus::exponential_buckets;
+use prometheus::histogram_opts;
+use prometheus::opts;
+use prometheus::Encoder;
+use prometheus::HistogramVec;
+use prometheus::IntCounterVec;
+use prometheus::IntGauge;
+use prometheus::IntGaugeVec;
+use prometheus::Registry;
+use prometheus::TextEncoder;
+use std::sync::LazyLock;
+
+/// Registry holding all ew metrics.
+static REGISTRY: LazyLock<Registry> =
+    LazyLock::new(|| Registry::new_custom(Some("ew".to_owned()), None).unwrap());
+
+/// Height of last block processed by each worker.
+pub static WORKER_HEIGHT: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register(IntGaugeVec::new(
+        opts!("worker_height", "Height of last block processed by worker"),
+        &["worker"],
+    ))
+});
+
+/// Height of each cursor.
+pub static CURSOR_HEIGHT: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register(IntGaugeVec::new(
+        opts!("cursor_height", "Height of last block dispatched by cursor"),
+        &["cursor"],
+    ))
+});
+
+/// Number of blocks each cursor is behind the node.
+pub static CURSOR_LAG: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register(IntGaugeVec::new(
+        opts!(
+            "cursor_lag_blocks",
+            "Number of blocks cursor is behind node tip"
+        ),
+        &["cursor"],
+    ))
+});
+
+/// Number of rollbacks for each cursor.
+pub static CURSOR_ROLLBACKS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(IntCounterVec::new(
+        opts!(
+            "cursor_rollbacks_total",
+            "Number of blocks rolled back by cursor"
+        ),
+        &["cursor"],
+    ))
+});
+
+/// Time between two consecutive blocks of each cursor.
+///
+/// Inverse of blocks per second.
+pub static CURSOR_BLOCK_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(HistogramVec::new(
+        histogram_opts!(
+            "cursor_block_seconds",
+            "Time between two consecutive blocks dispatched by cursor",
+            // 1ms to ~16min
+            exponential_buckets(0.001, 4.0, 11).unwrap()
+        ),
+        &["cursor"],
+    ))
+});
+
+/// Best full height reported by the nodes.
+pub static NODE_HEIGHT: LazyLock<IntGauge> = LazyLock::new(|| {
+    register(IntGauge::with_opts(opts!(
+        "node_height",
+        "Best full block height reported by node"
+    )))
+});
+
+/// Node followed by the tracker.
+pub static NODE_ACTIVE: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register(IntGaugeVec::new(
+        opts!(
+            "node_active",
+            "1 for the node followed by the tracker, 0 otherwise"
+        ),
+        &["node"],
+    ))
+});
+
+/// Blocks served by each node.
+pub static NODE_BLOCKS_SERVED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(IntCounterVec::new(
+        opts!(
+            "node_blocks_served_total",
+            "Number of blocks retrieved from node by the tracker"
+        ),
+        &["node"],
+    ))
+});
+
+/// Duration of node API requests, retries included.
+pub static NODE_REQUEST_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(HistogramVec::new(
+        histogram_opts!(
+            "node_request_seconds",
+            "Duration of node API requests",
+            exponential_buckets(0.001, 2.0, 15).unwrap()
+        ),
+        &["endpoint"],
+    ))
+});
+
+/// Failed node API requests, by error.
+pub static NODE_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(IntCounterVec::new(
+        opts!("node_errors_total", "Number of failed node API requests"),
+        &["endpoint", "error"],
+    ))
+});
+
+/// Retried node API requests.
+pub static NODE_RETRIES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(IntCounterVec::new(
+        opts!("node_retries_total", "Number of retried node API requests"),
+        &["endpoint"],
+    ))
+});
+
+/// Duration of store transactions persisting a block.
+pub static STORE_PERSIST_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(HistogramVec::new(
+        histogram_opts!(
+            "store_persist_seconds",
+            "Duration of postgres transactions persisting a block",
+            exponential_buckets(0.0005, 2.0, 15).unwrap()
+        ),
+        &["schema", "worker"],
+    ))
+});
+
+/// Alert rules currently firing.
+pub static ALERT_FIRING: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register(IntGaugeVec::new(
+        opts!("alert_firing", "1 if alert rule is firing, 0 otherwise"),
+        &["rule"],
+    ))
+});
+
+/// Number of failures of the tracker and each worker.
+pub static TASK_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(IntCounterVec::new(
+        opts!(
+            "task_failures_total",
+            "Number of times a task stopped with an error and got restarted"
+        ),
+        &["task"],
+    ))
+});
+
+/// Adds a metric to the registry.
+fn register<M: prometheus::core::Collector + Clone + 'static>(metric: prometheus::Result<M>) -> M {
+    let metric = metric.unwrap();
+    REGISTRY.register(Box::new(metric.clone())).unwrap();
+    metric
+}
+
+/// Renders all metrics in Prometheus text format.
+pub fn render() -> String {
+    // Metrics get registered on first use, so make sure all are declared.
+    LazyLock::force(&WORKER_HEIGHT);
+    LazyLock::force(&CURSOR_HEIGHT);
+    LazyLock::force(&CURSOR_LAG);
+    LazyLock::force(&CURSOR_ROLLBACKS);
+    LazyLock::force(&CURSOR_BLOCK_SECONDS);
+    LazyLock::force(&NODE_HEIGHT);
+    LazyLock::force(&NODE_ACTIVE);
+    LazyLock::force(&NODE_BLOCKS_SERVED);
+    LazyLock::force(&NODE_REQUEST_SECONDS);
+    LazyLock::force(&NODE_ERRORS);
+    LazyLock::force(&NODE_RETRIES);
+    LazyLock::force(&STORE_PERSIST_SECONDS);
+    LazyLock::force(&TASK_FAILURES);
+    LazyLock::force(&ALERT_FIRING);
+
+    let mut buffer = vec![];
+    TextEncoder::new()
+        .encode(&REGISTRY.gather(), &mut buffer)
+        .unwrap();
+    String::from_utf8(buffer).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        WORKER_HEIGHT.with_label_values(&["test_worker"]).set(123);
+        NODE_ERRORS
+            .with_label_values(&["info", "NodeUnreachable"])
+            .inc();
+        STORE_PERSIST_SECONDS
+            .with_label_values(&["test_schema", "test_worker"])
+            .observe(0.01);
+        let text = render();
+        assert!(text.contains("ew_worker_height{worker=\"test_worker\"} 123"));
+        assert!(
+            text.contains("ew_node_errors_total{endpoint=\"info\",error=\"NodeUnreachable\"} 1")
+        );
+        assert!(text.contains(
+            "ew_store_persist_seconds_count{schema=\"test_schema\",worker=\"test_worker\"} 1"
+        ));
+    }
+}
@@ -0,0 +1,30 @@
+//! Coordinated shutdown of the tracker and workers.
+//!
+//! A single [`CancellationToken`] is shared by all long running tasks.
+//! Tasks check it between blocks, so a block is always fully persisted
+//! before a task returns.
+pub use tokio_util::sync::CancellationToken;
+
+/// Completes when receiving a ctrl-c or, on unix, a SIGTERM signal.
+pub async fn signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to listen for ctrl-c");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("got a ctrl-c signal"),
+        _ = terminate => tracing::info!("got a SIGTERM signal"),
+    }
+}
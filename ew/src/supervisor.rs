@@ -0,0 +1,134 @@
+use std::future::Future;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::sync::mpsc::Sender;
+
+use crate::error::EwError;
+use crate::monitor::FailureMessage;
+use crate::monitor::MonitorMessage;
+use crate::shutdown::CancellationToken;
+
+/// Delay before first restart of a failed task.
+const INITIAL_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest delay between two restarts.
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Runs a task until `shutdown` is cancelled, restarting it when it fails.
+///
+/// `run` builds and starts the task. Tracker and workers resume from their
+/// persisted position, so a restarted task picks up where it failed.
+/// Restarts are delayed with an exponential backoff, reset once the task
+/// ran long enough. Failures are reported to the monitor.
+///
+/// Returns the number of failures, not counting those during shutdown.
+pub async fn supervise<F, Fut>(
+    name: &'static str,
+    monitor_tx: Sender<MonitorMessage>,
+    shutdown: CancellationToken,
+    mut run: F,
+) -> usize
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), EwError>>,
+{
+    let mut backoff = Backoff::new(INITIAL_DELAY, MAX_DELAY);
+    let mut failures = 0;
+    loop {
+        let started = Instant::now();
+        let error = match run().await {
+            Ok(()) => return failures,
+            Err(e) => e,
+        };
+        if shutdown.is_cancelled() {
+            // Upstream tasks may have stopped first
+            tracing::debug!("{name} stopped during shutdown: {error}");
+            return failures;
+        }
+        failures += 1;
+        tracing::error!("{name} failed: {error}");
+        let msg = MonitorMessage::Failure(FailureMessage::new(name, error.to_string()));
+        monitor_tx.send(msg).await.ok();
+
+        if started.elapsed() > MAX_DELAY {
+            backoff.reset();
+        }
+        let delay = backoff.next();
+        tracing::info!("restarting {name} in {}s", delay.as_secs());
+        tokio::select! {
+            _ = shutdown.cancelled() => return failures,
+            _ = tokio::time::sleep(delay) => (),
+        }
+    }
+}
+
+/// Exponential backoff.
+#[derive(Debug)]
+struct Backoff {
+    initial: Duration,
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            next: initial,
+        }
+    }
+
+    /// Returns next delay and doubles the following one, up to `max`.
+    fn next(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = std::cmp::min(self.next * 2, self.max);
+        delay
+    }
+
+    fn reset(&mut self) {
+        self.next = self.initial;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(5));
+        assert_eq!(backoff.next(), Duration::from_secs(1));
+        assert_eq!(backoff.next(), Duration::from_secs(2));
+        assert_eq!(backoff.next(), Duration::from_secs(4));
+        assert_eq!(backoff.next(), Duration::from_secs(5));
+        assert_eq!(backoff.next(), Duration::from_secs(5));
+        backoff.reset();
+        assert_eq!(backoff.next(), Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_supervise_restarts_failed_task() {
+        let (monitor_tx, mut monitor_rx) = tokio::sync::mpsc::channel(8);
+        let shutdown = CancellationToken::new();
+        let mut runs = 0;
+        let n_failures = supervise("dummy", monitor_tx, shutdown, || {
+            runs += 1;
+            let res = match runs {
+                1 | 2 => Err(EwError::UpstreamDown),
+                _ => Ok(()),
+            };
+            async move { res }
+        })
+        .await;
+        assert_eq!(runs, 3);
+        assert_eq!(n_failures, 2);
+        let mut failures = 0;
+        while let Ok(msg) = monitor_rx.try_recv() {
+            assert!(matches!(msg, MonitorMessage::Failure(_)));
+            failures += 1;
+        }
+        assert_eq!(failures, 2);
+    }
+}
@@ -1,4 +1,12 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio_postgres::binary_copy::BinaryCopyInWriter;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::types::Type;
 use tokio_postgres::Client;
+use tokio_postgres::GenericClient;
+
+use crate::error::EwError;
 
 pub struct Schema {
     name: String,
@@ -18,29 +26,32 @@ impl Schema {
         }
     }
 
-    pub async fn init(&self, client: &mut Client) {
-        if !self.schema_exists(client).await {
-            self.load_schema(client).await;
+    pub async fn init(&self, client: &mut Client) -> Result<(), EwError> {
+        if !self.schema_exists(client).await? {
+            self.load_schema(client).await?;
         }
-        let rev = self.schema_revision(client).await;
-        if rev.major > 1 || rev.minor > 0 {
-            todo!("apply miggrations")
+        // Anything past 1.0 is from a newer ew
+        let rev = self.schema_revision(client).await?;
+        if rev.major != 1 || rev.minor != 0 {
+            return Err(EwError::StoreRevisionUnsupported(
+                self.name.clone(),
+                format!("{}.{}", rev.major, rev.minor),
+            ));
         }
+        Ok(())
     }
 
-    async fn schema_revision(&self, client: &Client) -> Revision {
+    async fn schema_revision(&self, client: &Client) -> Result<Revision, EwError> {
         tracing::debug!("reading current revision");
         let qry = format!("select rev_major, rev_minor from {}._rev;", self.name);
-        match client.query_one(&qry, &[]).await {
-            Ok(row) => Revision {
-                major: row.get(0),
-                minor: row.get(1),
-            },
-            Err(err) => panic!("{:?}", err),
-        }
+        let row = client.query_one(&qry, &[]).await?;
+        Ok(Revision {
+            major: row.get(0),
+            minor: row.get(1),
+        })
     }
 
-    async fn schema_exists(&self, client: &Client) -> bool {
+    async fn schema_exists(&self, client: &Client) -> Result<bool, EwError> {
         tracing::debug!("checking for existing schema");
         let qry = "
         select exists(
@@ -48,13 +59,97 @@ impl Schema {
             from information_schema.schemata
             where schema_name = $1
         );";
-        client.query_one(qry, &[&self.name]).await.unwrap().get(0)
+        Ok(client.query_one(qry, &[&self.name]).await?.get(0))
     }
 
-    async fn load_schema(&self, client: &mut Client) {
+    async fn load_schema(&self, client: &mut Client) -> Result<(), EwError> {
         tracing::debug!("loading schema");
-        let tx = client.transaction().await.unwrap();
-        tx.batch_execute(self.sql).await.unwrap();
-        tx.commit().await.unwrap();
+        let tx = client.transaction().await?;
+        tx.batch_execute(self.sql).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// Column types of `copy_in` targets, by table and column list.
+///
+/// Custom types have database specific oids, so a cache belongs to a
+/// single connection. Entries of a table are dropped when copying to it
+/// fails, in case it got recreated with new types since.
+#[derive(Debug, Default)]
+pub struct CopyTypes {
+    types: Mutex<HashMap<String, Vec<Type>>>,
+}
+
+impl CopyTypes {
+    /// Returns types of `columns` (comma separated) of `table`.
+    ///
+    /// Looked up from the table on first use only.
+    async fn get(
+        &self,
+        client: &impl GenericClient,
+        table: &str,
+        columns: &str,
+    ) -> Result<Vec<Type>, EwError> {
+        let key = format!("{table} ({columns})");
+        if let Some(types) = self.types.lock().unwrap().get(&key) {
+            return Ok(types.clone());
+        }
+        let stmt = client
+            .prepare(&format!("select {columns} from {table} limit 0;"))
+            .await?;
+        let types: Vec<Type> = stmt.columns().iter().map(|c| c.type_().clone()).collect();
+        self.types.lock().unwrap().insert(key, types.clone());
+        Ok(types)
+    }
+
+    /// Drops cached types of `columns` of `table`.
+    fn evict(&self, table: &str, columns: &str) {
+        self.types
+            .lock()
+            .unwrap()
+            .remove(&format!("{table} ({columns})"));
+    }
+}
+
+/// Writes `rows` to `columns` of `table` through a binary `COPY ... FROM STDIN`.
+///
+/// Much faster than row by row inserts for large collections. Column types
+/// are looked up from the table once and kept in `copy_types`, so custom
+/// types are supported too.
+/// Returns the number of copied rows.
+pub async fn copy_in(
+    client: &impl GenericClient,
+    copy_types: &CopyTypes,
+    table: &str,
+    columns: &[&str],
+    rows: &[Vec<&(dyn ToSql + Sync)>],
+) -> Result<u64, EwError> {
+    let columns = columns.join(", ");
+    let types = copy_types.get(client, table, &columns).await?;
+    let res = write_binary(client, table, &columns, &types, rows).await;
+    if res.is_err() {
+        // Types may be stale, so look them up again next time
+        copy_types.evict(table, &columns);
+    }
+    res
+}
+
+async fn write_binary(
+    client: &impl GenericClient,
+    table: &str,
+    columns: &str,
+    types: &[Type],
+    rows: &[Vec<&(dyn ToSql + Sync)>],
+) -> Result<u64, EwError> {
+    let sink = client
+        .client()
+        .copy_in(&format!("copy {table} ({columns}) from stdin binary;"))
+        .await?;
+    let writer = BinaryCopyInWriter::new(sink, types);
+    let mut writer = std::pin::pin!(writer);
+    for row in rows {
+        writer.as_mut().write(row).await?;
     }
+    Ok(writer.as_mut().finish().await?)
 }
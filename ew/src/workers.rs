@@ -1,8 +1,13 @@
+pub mod admin;
 pub mod coingecko;
 pub mod erg;
 pub mod erg_diffs;
 pub mod exchanges;
+pub mod export;
+pub mod mempool;
 pub mod network;
+pub mod registry;
 pub mod sigmausd;
 pub mod timestamps;
 pub mod tokens;
+pub mod webhooks;
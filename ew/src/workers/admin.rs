@@ -0,0 +1,699 @@
+use std::fmt;
+use thiserror::Error;
+use tokio_postgres::Client;
+use tokio_postgres::NoTls;
+
+use crate::config::PostgresConfig;
+use crate::constants::address_ids::EMISSION_CONTRACTS;
+use crate::constants::settings::rollback_horizon;
+use crate::core;
+use crate::core::types::CoreData;
+use crate::core::types::Header;
+use crate::core::types::Height;
+use crate::error::EwError;
+use crate::framework::store::PendingMigration;
+use crate::framework::store::PgMigrator;
+use crate::framework::store::Revision;
+use crate::framework::EventHandling;
+use crate::framework::QueryHandler;
+use crate::framework::Querying;
+use crate::framework::Rewinding;
+use crate::workers::coingecko;
+use crate::workers::erg;
+use crate::workers::erg_diffs;
+use crate::workers::erg_diffs::types::DiffData;
+use crate::workers::exchanges;
+use crate::workers::export::ExportWorkFlow;
+use crate::workers::mempool;
+use crate::workers::network;
+use crate::workers::registry::WorkerID;
+use crate::workers::sigmausd;
+use crate::workers::timestamps;
+use crate::workers::tokens;
+use crate::workers::webhooks;
+
+#[derive(Error, Debug)]
+pub enum AdminError {
+    #[error(transparent)]
+    Ew(#[from] EwError),
+    #[error("Postgres error: {0}")]
+    Postgres(#[from] tokio_postgres::Error),
+    #[error("Worker `{0}` has no store yet.")]
+    NotInitialized(WorkerID),
+    #[error("Worker `{0}` is at height {1}, cannot roll back to {2}.")]
+    InvalidTarget(WorkerID, Height, Height),
+    #[error("Worker `{0}` is at height {1}, rolling back to {2} exceeds the rollback horizon of {3} blocks. Reset the worker instead.")]
+    BeyondHorizon(WorkerID, Height, Height, Height),
+    #[error("Worker `{1}` depends on `{0}` and is at height {2}. Roll it back or reset it first.")]
+    DependentAhead(WorkerID, WorkerID, Height),
+    #[error("Worker `{0}` cannot be rewound. Roll it back or reset it instead.")]
+    NotRewindable(WorkerID),
+    #[error("Worker `{0}` is not on the main chain. Let it catch up or roll it back first.")]
+    NotOnMainChain(WorkerID),
+    #[error("Verification found {0} mismatches.")]
+    VerificationFailed(i64),
+}
+
+/// Workers supporting rewinds.
+const REWINDABLE: [WorkerID; 4] = [
+    WorkerID::Timestamps,
+    WorkerID::ErgDiffs,
+    WorkerID::Exchanges,
+    WorkerID::Tokens,
+];
+
+/// Position of a worker relative to the main chain.
+#[derive(Debug)]
+pub struct WorkerStatus {
+    pub worker: WorkerID,
+    /// Last processed header, if the worker's store exists
+    pub header: Option<Header>,
+    /// Revision of the worker's store, if it exists
+    pub revision: Option<Revision>,
+    /// Whether the last processed header is part of the main chain
+    pub main_chain: bool,
+}
+
+/// Positions of the core tracker and workers.
+#[derive(Debug)]
+pub struct Status {
+    /// Last main chain header in `core.headers`, if any
+    pub core: Option<Header>,
+    pub workers: Vec<WorkerStatus>,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tip = self.core.as_ref().map(|h| h.height);
+        match &self.core {
+            Some(h) => writeln!(f, "{:<12} {:>9}  {}", "core", h.height, h.header_id)?,
+            None => writeln!(f, "{:<12} {:>9}", "core", "-")?,
+        }
+        for ws in &self.workers {
+            let name = ws.worker.name();
+            let (header, revision) = match (&ws.header, &ws.revision) {
+                (Some(h), Some(r)) => (h, r),
+                _ => {
+                    writeln!(f, "{name:<12} {:>9}  not initialized", "-")?;
+                    continue;
+                }
+            };
+            let rev = format!("rev {}.{}", revision.major, revision.minor);
+            let position = match (tip, header.is_initial()) {
+                (_, true) => "not started".to_owned(),
+                (_, false) if !ws.main_chain => "not on main chain".to_owned(),
+                (Some(tip), false) => format!("{} behind", tip - header.height),
+                (None, false) => String::new(),
+            };
+            writeln!(f, "{name:<12} {:>9}  {rev:<9} {position}", header.height)?;
+        }
+        Ok(())
+    }
+}
+
+/// Maximum number of mismatches listed per check.
+const MAX_REPORTED_MISMATCHES: i64 = 10;
+
+/// Outcome of a data integrity check.
+#[derive(Debug)]
+pub enum CheckOutcome {
+    Passed,
+    /// Check could not run, with reason
+    Skipped(String),
+    /// Total number of mismatches and description of the first ones
+    Failed(i64, Vec<String>),
+}
+
+/// Data integrity check of derived tables against core data and diffs.
+#[derive(Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub outcome: CheckOutcome,
+}
+
+/// Outcome of all checks run by `verify`.
+#[derive(Debug)]
+pub struct Verification {
+    pub checks: Vec<Check>,
+}
+
+impl Verification {
+    /// Total number of mismatches found.
+    pub fn mismatches(&self) -> i64 {
+        self.checks
+            .iter()
+            .map(|c| match c.outcome {
+                CheckOutcome::Failed(n, _) => n,
+                _ => 0,
+            })
+            .sum()
+    }
+}
+
+impl fmt::Display for Verification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let name = check.name;
+            match &check.outcome {
+                CheckOutcome::Passed => writeln!(f, "{name:<20} ok")?,
+                CheckOutcome::Skipped(reason) => writeln!(f, "{name:<20} skipped ({reason})")?,
+                CheckOutcome::Failed(n, mismatches) => {
+                    writeln!(f, "{name:<20} {n} mismatches")?;
+                    for mismatch in mismatches {
+                        writeln!(f, "    {mismatch}")?;
+                    }
+                    if *n > mismatches.len() as i64 {
+                        writeln!(f, "    ...")?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reports position of given `workers` against the core tracker.
+pub async fn status(pgconf: &PostgresConfig, workers: &[WorkerID]) -> Result<Status, AdminError> {
+    let client = connect(pgconf).await?;
+    let core = get_core_tip(&client).await?;
+    let mut statuses = vec![];
+    for worker in workers {
+        let header = worker.store().get_header(&client).await?;
+        let revision = worker.store().get_revision(&client).await?;
+        let main_chain = match &header {
+            Some(h) if core.is_some() => is_main_chain(&client, h).await?,
+            _ => false,
+        };
+        statuses.push(WorkerStatus {
+            worker: *worker,
+            header,
+            revision,
+            main_chain,
+        });
+    }
+    Ok(Status {
+        core,
+        workers: statuses,
+    })
+}
+
+/// Initializes core and stores of given `workers` and applies pending migrations.
+pub async fn migrate(pgconf: &PostgresConfig, workers: &[WorkerID]) -> Result<(), AdminError> {
+    tracing::info!("migrating core");
+    core::migrate(pgconf).await?;
+    for worker in workers {
+        tracing::info!("migrating {worker}");
+        worker.migrate(pgconf).await?;
+    }
+    Ok(())
+}
+
+/// Lists migrations that `migrate` would apply to core and given `workers`.
+///
+/// Migrations are only run, and rolled back, to report their effect when
+/// `evaluate` is set.
+pub async fn pending_migrations(
+    pgconf: &PostgresConfig,
+    workers: &[WorkerID],
+    evaluate: bool,
+) -> Result<Vec<PendingMigration>, AdminError> {
+    let mut pending = PgMigrator::dry_run(pgconf, &core::SCHEMA, evaluate).await?;
+    for worker in workers {
+        pending.extend(PgMigrator::dry_run(pgconf, worker.store(), evaluate).await?);
+    }
+    Ok(pending)
+}
+
+/// Rolls `worker` back to given `height`, one block at a time.
+///
+/// Goes through the worker's own rollback logic, so only possible within
+/// the rollback horizon. Workers depending on `worker` must be at `height`
+/// or lower. Returns the worker's new header.
+pub async fn roll_back(
+    pgconf: &PostgresConfig,
+    worker: WorkerID,
+    height: Height,
+) -> Result<Header, AdminError> {
+    let client = connect(pgconf).await?;
+    let header = worker
+        .store()
+        .get_header(&client)
+        .await?
+        .ok_or(AdminError::NotInitialized(worker))?;
+    if height < 0 || height > header.height {
+        return Err(AdminError::InvalidTarget(worker, header.height, height));
+    }
+    if header.height - height > rollback_horizon() {
+        return Err(AdminError::BeyondHorizon(
+            worker,
+            header.height,
+            height,
+            rollback_horizon(),
+        ));
+    }
+    check_dependents(&client, worker, height).await?;
+
+    // Ensure migrations are applied
+    worker.migrate(pgconf).await?;
+
+    tracing::info!("rolling back {worker} from {} to {height}", header.height);
+    let header = match worker {
+        WorkerID::Timestamps => {
+            let mut workflow = timestamps::TimestampsWorkFlow::new(pgconf).await?;
+            roll_back_workflow(&mut workflow, height).await?
+        }
+        WorkerID::Network => {
+            let mut workflow = network::Network::new(pgconf).await?;
+            roll_back_workflow(&mut workflow, height).await?
+        }
+        WorkerID::ErgDiffs => {
+            let mut workflow = erg_diffs::ErgDiffsWorkFlow::new(pgconf).await?;
+            roll_back_workflow(&mut workflow, height).await?
+        }
+        WorkerID::Erg => {
+            let mut workflow = erg::ErgWorkFlow::new(pgconf).await?;
+            roll_back_workflow(&mut workflow, height).await?
+        }
+        WorkerID::Exchanges => {
+            // Rollbacks query erg_diffs to revert deposit supply
+            let mut workflow = exchanges::CexWorkFlow::new(pgconf).await?;
+            let mut query_handler = erg_diffs::QueryWorker::new(pgconf).await?;
+            workflow.set_query_sender(query_handler.connect());
+            tokio::select! {
+                biased;
+                res = roll_back_workflow(&mut workflow, height) => res?,
+                res = query_handler.start() => {
+                    res?;
+                    return Err(EwError::QueryHandlerDown.into());
+                }
+            }
+        }
+        WorkerID::Tokens => {
+            let mut workflow = tokens::TokensWorkFlow::new(pgconf).await?;
+            roll_back_workflow(&mut workflow, height).await?
+        }
+        WorkerID::SigmaUSD => {
+            let mut workflow = sigmausd::SigmaUSD::new(pgconf).await?;
+            roll_back_workflow(&mut workflow, height).await?
+        }
+        WorkerID::Coingecko => {
+            let mut workflow = coingecko::Workflow::new(pgconf).await?;
+            roll_back_workflow(&mut workflow, height).await?
+        }
+        WorkerID::Webhooks => {
+            let mut workflow = webhooks::WebhooksWorkFlow::new(pgconf).await?;
+            roll_back_workflow(&mut workflow, height).await?
+        }
+        WorkerID::WebhooksDiffs => {
+            let mut workflow = webhooks::WebhooksDiffsWorkFlow::new(pgconf).await?;
+            roll_back_workflow(&mut workflow, height).await?
+        }
+        WorkerID::Mempool => {
+            let mut workflow = mempool::MempoolWorkFlow::new(pgconf).await?;
+            roll_back_workflow(&mut workflow, height).await?
+        }
+        WorkerID::ExportCore => {
+            let mut workflow = ExportWorkFlow::<CoreData>::new(pgconf).await?;
+            roll_back_workflow(&mut workflow, height).await?
+        }
+        WorkerID::ExportDiffs => {
+            let mut workflow = ExportWorkFlow::<DiffData>::new(pgconf).await?;
+            roll_back_workflow(&mut workflow, height).await?
+        }
+    };
+    Ok(header)
+}
+
+/// Rewinds `worker` to given `height`, discarding later blocks at once.
+///
+/// Unlike rollbacks, not limited by the rollback horizon. Safe to use while
+/// ew is running: the running worker restarts on its next block and catches
+/// up from `height` while other workers keep going. Workers depending on
+/// `worker` must be at `height` or lower. Returns the worker's new header.
+pub async fn rewind(
+    pgconf: &PostgresConfig,
+    worker: WorkerID,
+    height: Height,
+) -> Result<Header, AdminError> {
+    if !REWINDABLE.contains(&worker) {
+        return Err(AdminError::NotRewindable(worker));
+    }
+    let client = connect(pgconf).await?;
+    let header = worker
+        .store()
+        .get_header(&client)
+        .await?
+        .ok_or(AdminError::NotInitialized(worker))?;
+    if height < 0 || height > header.height {
+        return Err(AdminError::InvalidTarget(worker, header.height, height));
+    }
+    // Blocks get discarded down to the main chain header at `height`,
+    // so the worker must be on the same chain.
+    if !is_main_chain(&client, &header).await? {
+        return Err(AdminError::NotOnMainChain(worker));
+    }
+    check_dependents(&client, worker, height).await?;
+
+    // Ensure migrations are applied
+    worker.migrate(pgconf).await?;
+
+    tracing::info!("rewinding {worker} from {} to {height}", header.height);
+    let header = match worker {
+        WorkerID::Timestamps => {
+            let mut workflow = timestamps::TimestampsWorkFlow::new(pgconf).await?;
+            workflow.rewind(height).await?
+        }
+        WorkerID::ErgDiffs => {
+            let mut workflow = erg_diffs::ErgDiffsWorkFlow::new(pgconf).await?;
+            workflow.rewind(height).await?
+        }
+        WorkerID::Exchanges => {
+            let mut workflow = exchanges::CexWorkFlow::new(pgconf).await?;
+            workflow.rewind(height).await?
+        }
+        WorkerID::Tokens => {
+            let mut workflow = tokens::TokensWorkFlow::new(pgconf).await?;
+            workflow.rewind(height).await?
+        }
+        _ => unreachable!("not a rewindable worker"),
+    };
+    Ok(header)
+}
+
+/// Removes all data of `worker`, which will sync from scratch next time it runs.
+///
+/// Workers depending on `worker` must be reset first.
+pub async fn reset(pgconf: &PostgresConfig, worker: WorkerID) -> Result<(), AdminError> {
+    let mut client = connect(pgconf).await?;
+    check_dependents(&client, worker, Header::initial().height).await?;
+    worker.store().purge(&mut client).await?;
+    Ok(())
+}
+
+/// Checks stored state of given `workers` against core data and diffs.
+///
+/// Erg and token balances are recomputed from balance diffs, up to the
+/// worker's height. Supply composition plus emission contract balances must
+/// add up to the total supply of genesis boxes at every height. Headers of
+/// all workers in `ew.headers`, enabled or not, must be part of the main
+/// chain. Other checks of disabled or uninitialized workers are skipped.
+pub async fn verify(
+    pgconf: &PostgresConfig,
+    workers: &[WorkerID],
+) -> Result<Verification, AdminError> {
+    let client = connect(pgconf).await?;
+    let mut checks = vec![];
+    if workers.contains(&WorkerID::Erg) {
+        checks.push(Check {
+            name: "erg balances",
+            outcome: verify_erg_balances(&client).await?,
+        });
+        checks.push(Check {
+            name: "supply composition",
+            outcome: verify_supply_composition(&client).await?,
+        });
+    }
+    if workers.contains(&WorkerID::Tokens) {
+        checks.push(Check {
+            name: "token balances",
+            outcome: verify_token_balances(&client).await?,
+        });
+    }
+    checks.push(Check {
+        name: "main chain",
+        outcome: verify_main_chain(&client).await?,
+    });
+    Ok(Verification { checks })
+}
+
+/// Compares erg balances with the sum of balance diffs.
+async fn verify_erg_balances(client: &Client) -> Result<CheckOutcome, AdminError> {
+    let header = match get_started_header(client, WorkerID::Erg).await? {
+        Some(header) => header,
+        None => return Ok(CheckOutcome::Skipped("erg not started".to_owned())),
+    };
+    let qry = "
+        with diffs as (
+            select address_id
+                , sum(nano)::bigint as nano
+            from erg.balance_diffs
+            where height <= $1
+            group by 1
+            having sum(nano) <> 0
+        )
+        select coalesce(d.address_id, b.address_id)
+            , d.nano
+            , b.nano
+            , count(*) over ()
+        from diffs d
+        full outer join erg.balances b on b.address_id = d.address_id
+        where d.nano is distinct from b.nano
+        order by 1
+        limit $2;";
+    let rows = client
+        .query(qry, &[&header.height, &MAX_REPORTED_MISMATCHES])
+        .await?;
+    Ok(outcome(&rows, |row| {
+        format!(
+            "address {}: diffs {:?}, balance {:?}",
+            row.get::<_, i64>(0),
+            row.get::<_, Option<i64>>(1),
+            row.get::<_, Option<i64>>(2),
+        )
+    }))
+}
+
+/// Compares token balances with the sum of balance diffs.
+async fn verify_token_balances(client: &Client) -> Result<CheckOutcome, AdminError> {
+    let header = match get_started_header(client, WorkerID::Tokens).await? {
+        Some(header) => header,
+        None => return Ok(CheckOutcome::Skipped("tokens not started".to_owned())),
+    };
+    let qry = "
+        with diffs as (
+            select address_id
+                , asset_id
+                , sum(value)::bigint as value
+            from tokens.balance_diffs
+            where height <= $1
+            group by 1, 2
+            having sum(value) <> 0
+        )
+        select coalesce(d.address_id, b.address_id)
+            , coalesce(d.asset_id, b.asset_id)
+            , d.value
+            , b.value
+            , count(*) over ()
+        from diffs d
+        full outer join tokens.balances b
+            on b.address_id = d.address_id
+            and b.asset_id = d.asset_id
+        where d.value is distinct from b.value
+        order by 1, 2
+        limit $2;";
+    let rows = client
+        .query(qry, &[&header.height, &MAX_REPORTED_MISMATCHES])
+        .await?;
+    Ok(outcome(&rows, |row| {
+        format!(
+            "address {}, asset {}: diffs {:?}, balance {:?}",
+            row.get::<_, i64>(0),
+            row.get::<_, i64>(1),
+            row.get::<_, Option<i64>>(2),
+            row.get::<_, Option<i64>>(3),
+        )
+    }))
+}
+
+/// Checks supply composition and emission contracts add up to total supply.
+///
+/// Total supply is the value of genesis boxes in core.boxes. Emission
+/// contract balances are derived from balance diffs.
+async fn verify_supply_composition(client: &Client) -> Result<CheckOutcome, AdminError> {
+    let header = match get_started_header(client, WorkerID::Erg).await? {
+        Some(header) => header,
+        None => return Ok(CheckOutcome::Skipped("erg not started".to_owned())),
+    };
+    let qry = "select sum(value)::bigint from core.boxes where height = 0;";
+    let total_supply: i64 = match client.query_one(qry, &[]).await?.get(0) {
+        Some(nano) => nano,
+        None => return Ok(CheckOutcome::Skipped("no genesis boxes".to_owned())),
+    };
+    let emission_contracts: Vec<i64> = EMISSION_CONTRACTS.iter().map(|a| a.0).collect();
+    let qry = "
+        with emission as (
+            select h.height
+                , coalesce(sum(d.nano), 0) as nano
+            from generate_series(0, $1) as h(height)
+            left join erg.balance_diffs d
+                on d.height = h.height
+                and d.address_id = any($2)
+            group by 1
+        ), supply as (
+            select e.height
+                , (s.p2pks + s.contracts + s.miners
+                    + sum(e.nano) over (order by e.height))::bigint as nano
+            from emission e
+            left join erg.supply_composition s on s.height = e.height
+        )
+        select height
+            , nano
+            , count(*) over ()
+        from supply
+        where nano is distinct from $3
+        order by 1
+        limit $4;";
+    let rows = client
+        .query(
+            qry,
+            &[
+                &header.height,
+                &emission_contracts,
+                &total_supply,
+                &MAX_REPORTED_MISMATCHES,
+            ],
+        )
+        .await?;
+    Ok(outcome(&rows, |row| {
+        format!(
+            "height {}: supply {:?}, expected {total_supply}",
+            row.get::<_, i32>(0),
+            row.get::<_, Option<i64>>(1),
+        )
+    }))
+}
+
+/// Checks all worker headers in `ew.headers` are part of the main chain.
+///
+/// Headers of workers that haven't processed any block yet are left out.
+async fn verify_main_chain(client: &Client) -> Result<CheckOutcome, AdminError> {
+    if get_core_tip(client).await?.is_none() {
+        return Ok(CheckOutcome::Skipped("core not initialized".to_owned()));
+    }
+    let qry = "
+        select w.schema_name
+            , w.worker_id
+            , w.height
+            , w.header_id
+            , count(*) over ()
+        from ew.headers w
+        left join core.headers h
+            on h.height = w.height
+            and h.header_id = w.header_id
+            and h.main_chain
+        where w.height >= 0
+            and h.height is null
+        order by 1, 2
+        limit $1;";
+    let rows = client.query(qry, &[&MAX_REPORTED_MISMATCHES]).await?;
+    Ok(outcome(&rows, |row| {
+        format!(
+            "{}:{} at height {}: {} not on main chain",
+            row.get::<_, String>(0),
+            row.get::<_, String>(1),
+            row.get::<_, Height>(2),
+            row.get::<_, String>(3),
+        )
+    }))
+}
+
+/// Header of `worker`, if it processed any block.
+async fn get_started_header(
+    client: &Client,
+    worker: WorkerID,
+) -> Result<Option<Header>, AdminError> {
+    Ok(worker
+        .store()
+        .get_header(client)
+        .await?
+        .filter(|header| !header.is_initial()))
+}
+
+/// Check outcome from mismatch `rows`, having the total count as last column.
+fn outcome(
+    rows: &[tokio_postgres::Row],
+    describe: impl Fn(&tokio_postgres::Row) -> String,
+) -> CheckOutcome {
+    match rows.first() {
+        None => CheckOutcome::Passed,
+        Some(row) => {
+            CheckOutcome::Failed(row.get(row.len() - 1), rows.iter().map(describe).collect())
+        }
+    }
+}
+
+/// Rolls back blocks of `workflow` until at given `height`.
+async fn roll_back_workflow<W: EventHandling>(
+    workflow: &mut W,
+    height: Height,
+) -> Result<Header, EwError> {
+    while workflow.header().height > height {
+        let h = workflow.header().height;
+        tracing::debug!("rolling back block {h}");
+        workflow.roll_back(h).await?;
+    }
+    Ok(workflow.header().clone())
+}
+
+/// Ensures workers depending on `worker` are not ahead of given `height`.
+async fn check_dependents(
+    client: &Client,
+    worker: WorkerID,
+    height: Height,
+) -> Result<(), AdminError> {
+    for dependent in worker.dependents() {
+        if let Some(header) = dependent.store().get_header(client).await? {
+            if header.height > height {
+                return Err(AdminError::DependentAhead(worker, dependent, header.height));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Last main chain header of the core tracker, if any.
+async fn get_core_tip(client: &Client) -> Result<Option<Header>, AdminError> {
+    let qry = "select to_regclass('core.headers') is not null;";
+    if !client.query_one(qry, &[]).await?.get::<_, bool>(0) {
+        return Ok(None);
+    }
+    let qry = "
+        select height
+            , timestamp
+            , header_id
+            , parent_id
+        from core.headers
+        where main_chain
+        order by height desc
+        limit 1;";
+    Ok(client.query_opt(qry, &[]).await?.map(|row| Header {
+        height: row.get(0),
+        timestamp: row.get(1),
+        header_id: row.get(2),
+        parent_id: row.get(3),
+    }))
+}
+
+/// Returns true if given `header` is part of the main chain.
+async fn is_main_chain(client: &Client, header: &Header) -> Result<bool, AdminError> {
+    let qry = "
+        select exists(
+            select *
+            from core.headers
+            where height = $1 and header_id = $2 and main_chain
+        );";
+    Ok(client
+        .query_one(qry, &[&header.height, &header.header_id])
+        .await?
+        .get(0))
+}
+
+async fn connect(pgconf: &PostgresConfig) -> Result<Client, AdminError> {
+    let (client, connection) = tokio_postgres::connect(&pgconf.connection_uri, NoTls).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            tracing::error!("connection error: {}", e);
+        }
+    });
+    Ok(client)
+}
@@ -2,6 +2,8 @@ mod service;
 mod store;
 pub mod types;
 
+pub(crate) use store::SCHEMA;
+
 use async_trait::async_trait;
 use std::sync::Arc;
 use std::time::Duration;
@@ -9,16 +11,20 @@ use tokio::sync::mpsc::Sender;
 use tokio::sync::Mutex;
 use tokio::sync::RwLock;
 
+use crate::config::CoingeckoConfig;
 use crate::config::PostgresConfig;
 use crate::core::types::CoreData;
 use crate::core::types::Header;
 use crate::core::types::Height;
 use crate::core::types::Timestamp;
+use crate::error::EwError;
+use crate::framework::store::PgMigrator;
 use crate::framework::EventHandler;
 use crate::framework::EventHandling;
 use crate::framework::Source;
 use crate::framework::StampedData;
 use crate::monitor::MonitorMessage;
+use crate::shutdown::CancellationToken;
 use service::CoingeckoService;
 use store::Store;
 use types::Batch;
@@ -28,11 +34,17 @@ use types::ProvisionalBlockRecord;
 
 const WORKER_ID: &'static str = "coingecko";
 
-const SIXTY_SECONDS: tokio::time::Duration = Duration::from_secs(60);
-
 type SharedCache = Arc<RwLock<Cache>>;
 type SharedStore = Arc<Mutex<Store>>;
 
+/// Initializes the worker's store and applies pending migrations.
+pub async fn migrate(pgconf: &PostgresConfig) -> Result<(), EwError> {
+    PgMigrator::new(pgconf, &store::SCHEMA)
+        .await?
+        .apply_pending()
+        .await
+}
+
 pub struct Worker {
     tracker: Tracker,
     event_handler: EventHandler<Workflow>,
@@ -43,13 +55,16 @@ impl Worker {
         pgconf: &PostgresConfig,
         source: &mut impl Source<S = CoreData>,
         monitor_tx: Sender<MonitorMessage>,
-        custom_coingecko_url: Option<&str>,
-    ) -> Self {
-        let store = Store::new(pgconf, &store::SCHEMA).await;
+        config: &CoingeckoConfig,
+    ) -> Result<Self, EwError> {
+        // Ensure migrations are applied
+        migrate(pgconf).await?;
+
+        let store = Store::new(pgconf, &store::SCHEMA).await?;
         // Seed hourly data
-        store.seed_hourly_data().await;
+        store.seed_hourly_data().await?;
         // Then loading the cache
-        let cache = store.load_cache().await;
+        let cache = store.load_cache().await?;
         let header = store.get_header().clone();
         // Wrap store and cache to be shared between tracker and event handler's workflow
         let store = SharedStore::new(Mutex::new(store));
@@ -59,29 +74,32 @@ impl Worker {
             cache: cache.clone(),
             store: store.clone(),
         };
-        Self {
-            tracker: Tracker::new(cache, store, custom_coingecko_url),
-            event_handler: EventHandler::new_with("coingecko", workflow, source, monitor_tx).await,
-        }
+        let event_handler =
+            EventHandler::new_with("coingecko", workflow, source, monitor_tx).await?;
+        Ok(Self {
+            tracker: Tracker::new(cache, store, config),
+            event_handler,
+        })
     }
 
     #[tracing::instrument(name = "coingecko", skip_all)]
-    pub async fn start(&mut self) {
+    pub async fn start(&mut self, shutdown: CancellationToken) -> Result<(), EwError> {
         let mut throttle = false;
         loop {
             tokio::select! {
-                _ = tokio::signal::ctrl_c() => {
-                    tracing::info!("got a ctrl-c message");
-                    return;
+                biased;
+                _ = shutdown.cancelled() => {
+                    tracing::info!("stopped");
+                    return Ok(());
                 },
-                _ = tokio::time::sleep(SIXTY_SECONDS), if throttle => {
+                _ = tokio::time::sleep(self.tracker.polling_interval), if throttle => {
                     tracing::trace!("throttling off");
                     throttle = false;
                 }
                 _ = self.tracker.time_to_sync(), if !throttle => {
                     match self.tracker.poll().await {
                         Some(data) => {
-                            self.tracker.handle(data).await
+                            self.tracker.handle(data).await?
                         },
                         None => {
                             // Coingecko is down or we're too early
@@ -91,7 +109,8 @@ impl Worker {
                     }
                 },
                 event = self.event_handler.recv() => {
-                    self.event_handler.process_upstream_event(&event.unwrap()).await;
+                    let event = event.ok_or(EwError::UpstreamDown)?;
+                    self.event_handler.process_upstream_event(&event).await?;
                 }
             }
         }
@@ -144,19 +163,31 @@ impl EventHandling for Workflow {
     type D = ();
 
     /// Create and initialize a new event handling workflow.
-    async fn new(_pgconf: &PostgresConfig) -> Self {
-        todo!("not needed") // See todo for EventHandler::new_with()
+    ///
+    /// Store and cache are not shared with a coingecko tracker, so only
+    /// suitable for handling rollbacks. See `Worker::new` otherwise.
+    async fn new(pgconf: &PostgresConfig) -> Result<Self, EwError> {
+        // Ensure migrations are applied
+        migrate(pgconf).await?;
+
+        let store = Store::new(pgconf, &store::SCHEMA).await?;
+        let cache = store.load_cache().await?;
+        Ok(Self {
+            header: store.get_header().clone(),
+            cache: SharedCache::new(RwLock::new(cache)),
+            store: SharedStore::new(Mutex::new(store)),
+        })
     }
 
     /// Process new block data.
     #[tracing::instrument(skip_all, level=tracing::Level::TRACE)]
-    async fn include_block(&mut self, data: &StampedData<CoreData>) {
+    async fn include_block(&mut self, data: &StampedData<CoreData>) -> Result<(), EwError> {
         tracing::trace!("include_block {}", data.height);
         let mut cache = self.cache.write().await;
 
         let batch = prepare_batch(data.height, data.timestamp, &cache.recent_hourly_records);
         let stamped_batch = data.wrap(batch);
-        self.store.lock().await.persist(&stamped_batch).await;
+        self.store.lock().await.persist(&stamped_batch).await?;
 
         // Update cache
         if let Some(ref pr) = stamped_batch.data.provisional_block_record {
@@ -166,13 +197,14 @@ impl EventHandling for Workflow {
 
         // Update header
         self.header = self.store.lock().await.get_header().clone();
+        Ok(())
     }
 
     /// Roll back a block and return previous head.
     #[tracing::instrument(skip_all, level=tracing::Level::TRACE)]
-    async fn roll_back(&mut self, height: Height) -> Header {
+    async fn roll_back(&mut self, height: Height) -> Result<Header, EwError> {
         let mut store = self.store.lock().await;
-        store.roll_back(height).await;
+        store.roll_back(height).await?;
 
         // Update cache
         let mut cache = self.cache.write().await;
@@ -180,13 +212,17 @@ impl EventHandling for Workflow {
 
         let header = store.get_header().clone();
         self.header = header.clone();
-        header
+        Ok(header)
     }
 
     /// Get last processed header.
     fn header<'a>(&'a self) -> &'a Header {
         &self.header
     }
+
+    async fn flush(&mut self) -> Result<(), EwError> {
+        self.store.lock().await.flush().await
+    }
 }
 
 fn prepare_batch(
@@ -225,16 +261,16 @@ struct Tracker {
     cache: SharedCache,
     store: SharedStore,
     service: CoingeckoService,
+    polling_interval: Duration,
 }
 
 impl Tracker {
-    pub fn new(cache: SharedCache, store: SharedStore, custom_url: Option<&str>) -> Self {
-        let coingecko_url =
-            custom_url.unwrap_or("https://api.coingecko.com/api/v3/coins/ergo/market_chart/range");
+    pub fn new(cache: SharedCache, store: SharedStore, config: &CoingeckoConfig) -> Self {
         Self {
             cache,
             store,
-            service: CoingeckoService::new(coingecko_url),
+            service: CoingeckoService::new(&config.url),
+            polling_interval: config.polling_interval,
         }
     }
 
@@ -269,7 +305,7 @@ impl Tracker {
     ///
     /// Saves hourly records to db and interpolates block records.
     #[tracing::instrument(skip_all, level=tracing::Level::INFO)]
-    async fn handle(&self, hourly_records: Vec<HourlyRecord>) {
+    async fn handle(&self, hourly_records: Vec<HourlyRecord>) -> Result<(), EwError> {
         let mut cache = self.cache.write().await;
 
         // Append new hourly records to cache
@@ -313,7 +349,7 @@ impl Tracker {
             .lock()
             .await
             .persist_tracker_data(&hourly_records, &updates)
-            .await;
+            .await?;
 
         // Remove interpolated block records from provisional cache
         let last_interpolated_height = updates.last().and_then(|tbr| Some(tbr.height)).unwrap_or(0);
@@ -323,6 +359,7 @@ impl Tracker {
 
         // Remove unneeded hourly records from cache
         cache.trim_hourly_records();
+        Ok(())
     }
 
     /// Sleeps untill new data is expected to be available
@@ -332,7 +369,7 @@ impl Tracker {
                 break;
             }
             tracing::trace!("waiting until until time to sync");
-            tokio::time::sleep(SIXTY_SECONDS).await;
+            tokio::time::sleep(self.polling_interval).await;
         }
     }
 
@@ -3,13 +3,14 @@ mod ergusd_hourly;
 mod ergusd_provisional;
 
 use async_trait::async_trait;
-use tokio_postgres::Transaction;
 
 use crate::core::types::Header;
+use crate::error::EwError;
 use crate::framework::store::BatchStore;
 use crate::framework::store::PgStore;
 use crate::framework::store::Revision;
 use crate::framework::store::StoreDef;
+use crate::framework::store::StoreTransaction;
 use crate::framework::StampedData;
 
 use super::types::Batch;
@@ -17,11 +18,13 @@ use super::types::BlockRecord;
 use super::types::HourlyRecord;
 use super::Cache;
 
-pub(super) const SCHEMA: StoreDef = StoreDef {
+pub(crate) const SCHEMA: StoreDef = StoreDef {
     schema_name: super::WORKER_ID,
     worker_id: super::WORKER_ID,
     sql: include_str!("store/schema.sql"),
     revision: &Revision { major: 1, minor: 0 },
+    migrations: &[],
+    has_header: true,
 };
 
 pub(super) struct InnerStore {}
@@ -36,18 +39,24 @@ impl BatchStore for InnerStore {
         Self {}
     }
 
-    async fn persist(&mut self, pgtx: &Transaction<'_>, stamped_batch: &StampedData<Self::B>) {
-        ergusd_block::insert(pgtx, &stamped_batch.data.block_record).await;
+    async fn persist(
+        &mut self,
+        pgtx: &StoreTransaction,
+        stamped_batch: &StampedData<Self::B>,
+    ) -> Result<(), EwError> {
+        ergusd_block::insert(pgtx.client(), &stamped_batch.data.block_record).await?;
         if let Some(ref pr) = stamped_batch.data.provisional_block_record {
-            ergusd_provisional::insert(pgtx, pr).await;
+            ergusd_provisional::insert(pgtx.client(), pr).await?;
         }
+        Ok(())
     }
 
-    async fn roll_back(&mut self, pgtx: &Transaction<'_>, header: &Header) {
+    async fn roll_back(&mut self, pgtx: &StoreTransaction, header: &Header) -> Result<(), EwError> {
         let height = header.height;
         tracing::debug!("rolling back block {}", height);
-        ergusd_block::delete_at(pgtx, header.height).await;
-        ergusd_provisional::delete_at(pgtx, height).await;
+        ergusd_block::delete_at(pgtx.client(), header.height).await?;
+        ergusd_provisional::delete_at(pgtx.client(), height).await?;
+        Ok(())
     }
 }
 
@@ -57,64 +66,66 @@ impl Store {
         &mut self,
         hourly_records: &Vec<HourlyRecord>,
         block_updates: &Vec<BlockRecord>,
-    ) {
+    ) -> Result<(), EwError> {
         let client = self.get_mut_client();
-        let pgtx = client.transaction().await.unwrap();
+        let pgtx = client.transaction().await?;
 
         // First sync will yield a large timeseries, so process in chunks.
         for record_chunk in hourly_records.chunks(5000) {
-            ergusd_hourly::insert_many(&pgtx, record_chunk).await;
+            ergusd_hourly::insert_many(&pgtx, record_chunk).await?;
         }
 
         // Update block records
-        ergusd_block::update_many(&pgtx, block_updates).await;
+        ergusd_block::update_many(&pgtx, block_updates).await?;
 
         // And remove updated blocks from provisional table
         ergusd_provisional::delete_many_at(
             &pgtx,
             &block_updates.iter().map(|br| br.height).collect(),
         )
-        .await;
+        .await?;
 
-        pgtx.commit().await.unwrap();
+        pgtx.commit().await?;
+        Ok(())
     }
 
     /// Inserts initial hourly record with genesis timestamp.
     ///
     /// Coingecko data starts a few minutes after Ergo's genesis block.
     /// This ensures all block timestamps are covered by hourly data.
-    pub(super) async fn seed_hourly_data(&self) {
+    pub(super) async fn seed_hourly_data(&self) -> Result<(), EwError> {
         let client = self.get_client();
-        match ergusd_hourly::get_latest(client).await {
+        match ergusd_hourly::get_latest(client).await? {
             Some(_) => (),
-            None => ergusd_hourly::insert(client, &HourlyRecord::genesis()).await,
+            None => ergusd_hourly::insert(client, &HourlyRecord::genesis()).await?,
         }
+        Ok(())
     }
 
-    pub(super) async fn load_cache(&self) -> Cache {
+    pub(super) async fn load_cache(&self) -> Result<Cache, EwError> {
         let client = self.get_client();
 
-        let provisional_records = ergusd_provisional::get_all(client).await;
+        let provisional_records = ergusd_provisional::get_all(client).await?;
 
         let recent_hourly_records = match provisional_records.first() {
             Some(first_provisional_record) => {
                 let since =
                     ergusd_hourly::get_last_prior_to(client, first_provisional_record.timestamp)
-                        .await
+                        .await?
                         .unwrap_or(HourlyRecord::genesis());
-                ergusd_hourly::get_since(client, since.timestamp).await
+                ergusd_hourly::get_since(client, since.timestamp).await?
             }
             None => {
                 let last_hourly_record = ergusd_hourly::get_latest(client)
-                    .await
+                    .await?
                     .unwrap_or(HourlyRecord::genesis());
                 vec![last_hourly_record]
             }
         };
 
-        Cache {
+        Ok(Cache {
             recent_hourly_records,
             provisional_records,
-        }
+        })
     }
 }
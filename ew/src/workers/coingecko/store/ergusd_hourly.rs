@@ -1,18 +1,22 @@
 use super::super::types::HourlyRecord;
 use crate::core::types::Timestamp;
+use crate::error::EwError;
 use tokio_postgres::Client;
-use tokio_postgres::Transaction;
+use tokio_postgres::GenericClient;
 
-pub(super) async fn insert(client: &Client, record: &HourlyRecord) {
+pub(super) async fn insert(client: &Client, record: &HourlyRecord) -> Result<(), EwError> {
     tracing::trace!("insert {record:?}");
     let sql = "insert into coingecko.ergusd_hourly (timestamp, value) values ($1, $2);";
     client
         .execute(sql, &[&record.timestamp, &record.usd])
-        .await
-        .unwrap();
+        .await?;
+    Ok(())
 }
 
-pub(super) async fn insert_many(pgtx: &Transaction<'_>, records: &[HourlyRecord]) {
+pub(super) async fn insert_many(
+    pgtx: &impl GenericClient,
+    records: &[HourlyRecord],
+) -> Result<(), EwError> {
     tracing::trace!("insert_many {records:?}");
     let sql = format!(
         "
@@ -25,11 +29,12 @@ pub(super) async fn insert_many(pgtx: &Transaction<'_>, records: &[HourlyRecord]
             .join(",")
     );
 
-    pgtx.execute(&sql, &[]).await.unwrap();
+    pgtx.execute(&sql, &[]).await?;
+    Ok(())
 }
 
 /// Return latest hourly record.
-pub(super) async fn get_latest(client: &Client) -> Option<HourlyRecord> {
+pub(super) async fn get_latest(client: &Client) -> Result<Option<HourlyRecord>, EwError> {
     let sql = "
         select timestamp
             , value
@@ -37,19 +42,19 @@ pub(super) async fn get_latest(client: &Client) -> Option<HourlyRecord> {
         order by 1 desc
         limit 1
     ";
-    client.query_opt(sql, &[]).await.unwrap().and_then(|row| {
+    Ok(client.query_opt(sql, &[]).await?.and_then(|row| {
         Some(HourlyRecord {
             timestamp: row.get(0),
             usd: row.get(1),
         })
-    })
+    }))
 }
 
 /// Get last hourly record on or prior to given `timestamp`.
 pub(super) async fn get_last_prior_to(
     client: &Client,
     timestamp: Timestamp,
-) -> Option<HourlyRecord> {
+) -> Result<Option<HourlyRecord>, EwError> {
     tracing::trace!("get_last_prior_to {timestamp}");
     let sql = "
         select timestamp
@@ -58,20 +63,19 @@ pub(super) async fn get_last_prior_to(
         where timestamp <= $1
         order by timestamp desc
         limit 1;";
-    client
-        .query_opt(sql, &[&timestamp])
-        .await
-        .unwrap()
-        .and_then(|row| {
-            Some(HourlyRecord {
-                timestamp: row.get(0),
-                usd: row.get(1),
-            })
+    Ok(client.query_opt(sql, &[&timestamp]).await?.and_then(|row| {
+        Some(HourlyRecord {
+            timestamp: row.get(0),
+            usd: row.get(1),
         })
+    }))
 }
 
 /// Get hourly records since given `timestamp`.
-pub(super) async fn get_since(client: &Client, timestamp: Timestamp) -> Vec<HourlyRecord> {
+pub(super) async fn get_since(
+    client: &Client,
+    timestamp: Timestamp,
+) -> Result<Vec<HourlyRecord>, EwError> {
     tracing::trace!("get_since {timestamp}");
     let sql = "
         select timestamp
@@ -80,14 +84,13 @@ pub(super) async fn get_since(client: &Client, timestamp: Timestamp) -> Vec<Hour
         where timestamp >= $1
         order by 1;
     ";
-    client
+    Ok(client
         .query(sql, &[&timestamp])
-        .await
-        .unwrap()
+        .await?
         .iter()
         .map(|row| HourlyRecord {
             timestamp: row.get(0),
             usd: row.get(1),
         })
-        .collect()
+        .collect())
 }
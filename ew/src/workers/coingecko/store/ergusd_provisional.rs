@@ -1,44 +1,52 @@
 use tokio_postgres::Client;
-use tokio_postgres::Transaction;
+use tokio_postgres::GenericClient;
 
 use super::super::types::ProvisionalBlockRecord;
 use crate::core::types::Height;
+use crate::error::EwError;
 
-pub(super) async fn insert(pgtx: &Transaction<'_>, record: &ProvisionalBlockRecord) {
+pub(super) async fn insert(
+    pgtx: &impl GenericClient,
+    record: &ProvisionalBlockRecord,
+) -> Result<(), EwError> {
     tracing::trace!("insert {record:?}");
     let sql = "insert into coingecko.ergusd_provisional_blocks(height, timestamp) values ($1, $2);";
     pgtx.execute(sql, &[&record.height, &record.timestamp])
-        .await
-        .unwrap();
+        .await?;
+    Ok(())
 }
 
-pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: Height) {
+pub(super) async fn delete_at(pgtx: &impl GenericClient, height: Height) -> Result<(), EwError> {
     tracing::trace!("delete_at {height}");
     let sql = "delete from coingecko.ergusd_provisional_blocks where height = $1;";
-    pgtx.execute(sql, &[&height]).await.unwrap();
+    pgtx.execute(sql, &[&height]).await?;
+    Ok(())
 }
 
-pub(super) async fn delete_many_at(pgtx: &Transaction<'_>, heights: &Vec<Height>) {
+pub(super) async fn delete_many_at(
+    pgtx: &impl GenericClient,
+    heights: &Vec<Height>,
+) -> Result<(), EwError> {
     tracing::trace!("delete_updated {heights:?}");
     let sql = "delete from coingecko.ergusd_provisional_blocks where height = any($1);";
-    pgtx.execute(sql, &[&heights]).await.unwrap();
+    pgtx.execute(sql, &[&heights]).await?;
+    Ok(())
 }
 
-pub(super) async fn get_all(client: &Client) -> Vec<ProvisionalBlockRecord> {
+pub(super) async fn get_all(client: &Client) -> Result<Vec<ProvisionalBlockRecord>, EwError> {
     let sql = "
         select height
             , timestamp
         from coingecko.ergusd_provisional_blocks
         order by 1;
     ";
-    client
+    Ok(client
         .query(sql, &[])
-        .await
-        .unwrap()
+        .await?
         .iter()
         .map(|row| ProvisionalBlockRecord {
             height: row.get(0),
             timestamp: row.get(1),
         })
-        .collect()
+        .collect())
 }
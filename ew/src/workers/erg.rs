@@ -23,14 +23,19 @@ pub mod testing {
     pub use store::SCHEMA;
 }
 
+pub(crate) use store::api;
+pub(crate) use store::SCHEMA;
+
 use async_trait::async_trait;
 
 use crate::config::PostgresConfig;
 use crate::core::types::Header;
 use crate::core::types::Height;
+use crate::error::EwError;
 use crate::framework::store::PgMigrator;
 use crate::framework::EventHandling;
 use crate::framework::StampedData;
+use crate::monitor::events::Figures;
 use crate::workers::erg_diffs::types::DiffData;
 use parsing::Parser;
 use store::Store;
@@ -40,6 +45,14 @@ const WORKER_ID: &'static str = "erg";
 
 pub type Worker = crate::framework::LeafWorker<ErgWorkFlow>;
 
+/// Initializes the worker's store and applies pending migrations.
+pub async fn migrate(pgconf: &PostgresConfig) -> Result<(), EwError> {
+    PgMigrator::new(pgconf, &store::SCHEMA)
+        .await?
+        .apply_pending()
+        .await
+}
+
 pub struct ErgWorkFlow {
     parser: Parser,
     store: Store,
@@ -50,36 +63,54 @@ impl EventHandling for ErgWorkFlow {
     type U = DiffData;
     type D = ();
 
-    async fn new(pgconf: &PostgresConfig) -> Self {
+    async fn new(pgconf: &PostgresConfig) -> Result<Self, EwError> {
         // Ensure migrations are applied
-        let mut migrator = PgMigrator::new(pgconf, &store::SCHEMA).await;
-        migrator.apply(&store::migrations::Mig1_1 {}).await;
+        migrate(pgconf).await?;
 
-        let store = Store::new(pgconf, &store::SCHEMA).await;
-        let cache = store::load_parser_cache(store.get_client()).await;
+        let mut store = Store::new(pgconf, &store::SCHEMA).await?;
+        store.set_batch_blocks(pgconf.batch_blocks);
+        let cache = store::load_parser_cache(store.get_client()).await?;
         let parser = Parser::new(cache);
-        Self { parser, store }
+        Ok(Self { parser, store })
     }
 
-    async fn include_block(&mut self, data: &StampedData<DiffData>) -> Self::D {
+    async fn include_block(&mut self, data: &StampedData<DiffData>) -> Result<Self::D, EwError> {
         // Get current balances for all addresses within block
         let balances = self
             .store
             .map_balance_records(data.data.diffed_addresses())
-            .await;
+            .await?;
         let stamped_batch = self.parser.extract_batch(data, balances);
-        self.store.persist(&stamped_batch).await;
+        self.store.persist(&stamped_batch).await?;
+        Ok(())
     }
 
-    async fn roll_back(&mut self, height: Height) -> Header {
-        self.store.roll_back(height).await;
+    async fn roll_back(&mut self, height: Height) -> Result<Header, EwError> {
+        self.store.roll_back(height).await?;
         // Refresh parser cache to reflect rollback
-        let cache = store::load_parser_cache(self.store.get_client()).await;
+        let cache = store::load_parser_cache(self.store.get_client()).await?;
         self.parser = Parser::new(cache);
-        self.store.get_header().clone()
+        Ok(self.store.get_header().clone())
     }
 
     fn header<'a>(&'a self) -> &'a Header {
         self.store.get_header()
     }
+
+    async fn flush(&mut self) -> Result<(), EwError> {
+        self.store.flush().await
+    }
+
+    fn has_uncommitted(&self) -> bool {
+        self.store.has_uncommitted()
+    }
+
+    fn figures(&self) -> Option<Figures> {
+        let record = self.parser.supply_composition();
+        Some(Figures::SupplyComposition {
+            p2pks: record.p2pks,
+            contracts: record.contracts,
+            miners: record.miners,
+        })
+    }
 }
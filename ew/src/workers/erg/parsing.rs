@@ -18,6 +18,8 @@ mod balances;
 mod composition;
 mod counts;
 
+pub(super) use composition::derive_record;
+
 pub struct Parser {
     cache: ParserCache,
 }
@@ -114,6 +116,11 @@ impl Parser {
         Self { cache }
     }
 
+    /// Supply composition as of last parsed block.
+    pub fn supply_composition(&self) -> &CompositionRecord {
+        &self.cache.last_supply_composition
+    }
+
     /// Create a batch from core data.
     pub fn extract_batch(
         &mut self,
@@ -3,7 +3,7 @@ use crate::constants::address_ids::EMISSION_CONTRACTS;
 use crate::core::types::AddressType;
 use crate::workers::erg_diffs::types::DiffRecord;
 
-pub(super) fn derive_record(
+pub(in crate::workers::erg) fn derive_record(
     cache: &CompositionRecord,
     diffs: &Vec<DiffRecord>,
 ) -> CompositionRecord {
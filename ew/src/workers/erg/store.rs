@@ -1,22 +1,24 @@
 use async_trait::async_trait;
 use std::collections::HashMap;
 use tokio_postgres::Client;
-use tokio_postgres::Transaction;
 
 use crate::core::types::AddressID;
 use crate::core::types::Header;
+use crate::error::EwError;
 use crate::framework::store::BatchStore;
 use crate::framework::store::PgStore;
 use crate::framework::store::Revision;
 use crate::framework::store::StoreDef;
+use crate::framework::store::StoreTransaction;
 use crate::framework::StampedData;
 
 use super::parsing::ParserCache;
 use super::types::BalanceRecord;
 use super::Batch;
 use super::WORKER_ID;
-use crate::constants::settings::ROLLBACK_HORIZON;
+use crate::constants::settings::rollback_horizon;
 
+pub(crate) mod api;
 mod balances;
 mod composition;
 mod counts;
@@ -25,7 +27,9 @@ pub const SCHEMA: StoreDef = StoreDef {
     schema_name: "erg",
     worker_id: WORKER_ID,
     sql: include_str!("store/schema.sql"),
-    revision: &Revision { major: 1, minor: 1 },
+    revision: &Revision { major: 1, minor: 2 },
+    migrations: &[&migrations::Mig1_1 {}, &migrations::Mig1_2 {}],
+    has_header: true,
 };
 
 pub(super) type Store = PgStore<SpecStore>;
@@ -39,9 +43,13 @@ impl BatchStore for SpecStore {
         Self {}
     }
 
-    async fn persist(&mut self, pgtx: &Transaction<'_>, stamped_batch: &StampedData<Self::B>) {
+    async fn persist(
+        &mut self,
+        pgtx: &StoreTransaction,
+        stamped_batch: &StampedData<Self::B>,
+    ) -> Result<(), EwError> {
         let batch = &stamped_batch.data;
-        // timestamps::insert(&pgtx, stamped_batch.height, stamped_batch.timestamp).await;
+        // timestamps::insert(pgtx.client(), stamped_batch.height, stamped_batch.timestamp).await;
 
         /*
            h = height of current batch
@@ -58,43 +66,50 @@ impl BatchStore for SpecStore {
             .map(|r| r.address_id)
             .collect();
         // Log addresses that will get created
-        balances::logs::log_new_balances(pgtx, height, &batch.new_addresses).await;
+        balances::logs::log_new_balances(pgtx.client(), height, &batch.new_addresses).await?;
         // Log current balance of addresses that will get modified
-        balances::logs::log_existing_balances(pgtx, height, &modified_addresses).await;
+        balances::logs::log_existing_balances(pgtx.client(), height, &modified_addresses).await?;
         // Log current balance of addresses that will get spent
-        balances::logs::log_existing_balances(pgtx, height, &batch.spent_addresses).await;
+        balances::logs::log_existing_balances(pgtx.client(), height, &batch.spent_addresses)
+            .await?;
         // Delete old logs
-        balances::logs::delete_logs_prior_to(pgtx, height - ROLLBACK_HORIZON).await;
+        balances::logs::delete_logs_prior_to(pgtx.client(), height - rollback_horizon()).await?;
 
-        balances::upsert_many(&pgtx, &batch.balance_records).await;
-        balances::delete_many(&pgtx, &batch.spent_addresses).await;
+        balances::upsert_many(pgtx.client(), &batch.balance_records).await?;
+        balances::delete_many(pgtx.client(), &batch.spent_addresses).await?;
 
-        counts::insert(&pgtx, &batch.address_counts).await;
-        composition::insert(&pgtx, &batch.supply_composition).await;
+        counts::insert(pgtx.client(), &batch.address_counts).await?;
+        composition::insert(pgtx.client(), &batch.supply_composition).await?;
+        Ok(())
     }
 
-    async fn roll_back(&mut self, pgtx: &Transaction<'_>, header: &Header) {
+    async fn roll_back(&mut self, pgtx: &StoreTransaction, header: &Header) -> Result<(), EwError> {
         tracing::debug!("rolling back block {}", header.height);
 
         let height = header.height;
-        balances::upsert_many(&pgtx, &balances::logs::get_balances_at(pgtx, height).await).await;
+        balances::upsert_many(
+            pgtx.client(),
+            &balances::logs::get_balances_at(pgtx.client(), height).await?,
+        )
+        .await?;
         balances::delete_many(
-            &pgtx,
-            &balances::logs::get_addresses_created_at(pgtx, height).await,
+            pgtx.client(),
+            &balances::logs::get_addresses_created_at(pgtx.client(), height).await?,
         )
-        .await;
-        balances::logs::delete_logs_at(pgtx, height).await;
+        .await?;
+        balances::logs::delete_logs_at(pgtx.client(), height).await?;
 
-        counts::delete_at(&pgtx, header.height).await;
-        composition::delete_at(&pgtx, header.height).await;
+        counts::delete_at(pgtx.client(), header.height).await?;
+        composition::delete_at(pgtx.client(), header.height).await?;
+        Ok(())
     }
 }
 
-pub(super) async fn load_parser_cache(client: &Client) -> ParserCache {
-    ParserCache {
-        last_address_counts: counts::get_last(&client).await,
-        last_supply_composition: composition::get_last(&client).await,
-    }
+pub(super) async fn load_parser_cache(client: &Client) -> Result<ParserCache, EwError> {
+    Ok(ParserCache {
+        last_address_counts: counts::get_last(&client).await?,
+        last_supply_composition: composition::get_last(client).await?,
+    })
 }
 
 impl Store {
@@ -104,25 +119,34 @@ impl Store {
     pub(super) async fn map_balance_records(
         &self,
         address_ids: Vec<AddressID>,
-    ) -> HashMap<AddressID, BalanceRecord> {
+    ) -> Result<HashMap<AddressID, BalanceRecord>, EwError> {
         // TODO: cache
-        let recs = balances::get_many(self.get_client(), &address_ids).await;
+        let recs = balances::get_many(self.get_client(), &address_ids).await?;
         let mut map = HashMap::new();
         for r in recs {
             map.insert(r.address_id, r);
         }
-        map
+        Ok(map)
     }
 }
 
 pub(super) mod migrations {
+
+    use crate::error::EwError;
     use async_trait::async_trait;
     use tokio_postgres::Transaction;
 
+    use std::collections::HashMap;
+
+    use super::super::parsing::derive_record;
+    use super::composition;
+    use crate::core::types::AddressID;
     use crate::core::types::Height;
     use crate::framework::store::Migration;
     use crate::framework::store::MigrationEffect;
     use crate::framework::store::Revision;
+    use crate::utils::CopyTypes;
+    use crate::workers::erg_diffs::types::DiffRecord;
 
     /// Migration for revision 1.1
     #[derive(Debug)]
@@ -138,7 +162,12 @@ pub(super) mod migrations {
             Revision::new(1, 1)
         }
 
-        async fn run(&self, pgtx: &Transaction<'_>) -> MigrationEffect {
+        fn sql(&self) -> Option<&'static str> {
+            // Reset depends on how far behind erg_diffs the store is
+            None
+        }
+
+        async fn run(&self, pgtx: &Transaction<'_>) -> Result<MigrationEffect, EwError> {
             // Check if erg worker is lagging behind others.
             // If it is, then it is likely we ran into the rollback issue
             // of v1.1.0 and the erg worker state is now corrupt.
@@ -152,12 +181,11 @@ pub(super) mod migrations {
                     "select height from ew.headers where worker_id = 'erg';",
                     &[],
                 )
-                .await
-                .unwrap()
+                .await?
             {
                 Some(row) => row.get(0),
                 None => {
-                    return MigrationEffect::None;
+                    return Ok(MigrationEffect::None);
                 }
             };
 
@@ -167,11 +195,10 @@ pub(super) mod migrations {
                     "select height from ew.headers where worker_id = 'erg_diffs';",
                     &[],
                 )
-                .await
-                .map(|row| row.get(0))
-                .unwrap();
+                .await?
+                .get(0);
 
-            if upstream_height - current_height > 10 {
+            Ok(if upstream_height - current_height > 10 {
                 // Too far behind upstream worker.
                 // Consider we got hit by the rollback issue and reset the whole erg store.
                 let tables = vec![
@@ -189,12 +216,121 @@ pub(super) mod migrations {
                 for table in tables {
                     tracing::debug!("truncating table {table}");
                     let stmt = format!("truncate table {table};");
-                    pgtx.execute(&stmt, &[]).await.unwrap();
+                    pgtx.execute(&stmt, &[]).await?;
                 }
                 MigrationEffect::Reset
             } else {
                 MigrationEffect::None
+            })
+        }
+    }
+
+    /// Migration for revision 1.2
+    #[derive(Debug)]
+    pub struct Mig1_2 {}
+
+    #[async_trait]
+    impl Migration for Mig1_2 {
+        fn description(&self) -> &'static str {
+            "Fix supply composition heights"
+        }
+
+        fn revision(&self) -> Revision {
+            Revision::new(1, 2)
+        }
+
+        fn sql(&self) -> Option<&'static str> {
+            // Records get derived again from balance diffs
+            None
+        }
+
+        async fn run(&self, pgtx: &Transaction<'_>) -> Result<MigrationEffect, EwError> {
+            // Supply composition records used to be stored one height
+            // above their block. Rolling back a block deleted its parent's
+            // record instead of its own, leaving a gap, and records derived
+            // after it include the rolled back block.
+            //
+            // Records up to the first gap are moved down by one height.
+            // Later ones are derived again from erg_diffs balance diffs.
+            let current_height: Height = match pgtx
+                .query_opt(
+                    "select height from ew.headers where worker_id = 'erg';",
+                    &[],
+                )
+                .await?
+            {
+                Some(row) => row.get(0),
+                None => {
+                    return Ok(MigrationEffect::None);
+                }
+            };
+
+            // First height missing a record, if any
+            let gap: Option<Height> = pgtx
+                .query_one(
+                    "
+                    select min(c.height) + 1
+                    from erg.supply_composition c
+                    where c.height < (select max(height) from erg.supply_composition)
+                        and not exists (
+                            select
+                            from erg.supply_composition n
+                            where n.height = c.height + 1
+                        );",
+                    &[],
+                )
+                .await?
+                .get(0);
+            if let Some(height) = gap {
+                tracing::info!("deriving supply composition again from height {height}");
+                pgtx.execute(
+                    "delete from erg.supply_composition where height >= $1;",
+                    &[&height],
+                )
+                .await?;
+            }
+
+            // Shifting in two steps avoids primary key conflicts
+            pgtx.batch_execute(
+                "
+                update erg.supply_composition set height = -height;
+                update erg.supply_composition set height = -height - 1;",
+            )
+            .await?;
+
+            // Derive missing records, by chunks of blocks
+            let copy_types = CopyTypes::default();
+            let mut last = composition::get_last(pgtx).await?;
+            while last.height < current_height {
+                let from = last.height + 1;
+                let to = current_height.min(from + 9_999);
+                let mut diffs: HashMap<Height, Vec<DiffRecord>> = HashMap::new();
+                for row in pgtx
+                    .query(
+                        "
+                        select address_id, height, tx_idx, nano
+                        from erg.balance_diffs
+                        where height >= $1 and height <= $2;",
+                        &[&from, &to],
+                    )
+                    .await?
+                {
+                    let height: Height = row.get(1);
+                    diffs.entry(height).or_default().push(DiffRecord::new(
+                        row.get::<usize, AddressID>(0),
+                        height,
+                        row.get(2),
+                        row.get(3),
+                    ));
+                }
+                let mut records = vec![];
+                for height in from..=to {
+                    last = derive_record(&last, &diffs.remove(&height).unwrap_or_default());
+                    records.push(last.clone());
+                }
+                composition::insert_many(pgtx, &copy_types, &records).await?;
             }
+            Ok(MigrationEffect::None)
         }
     }
 }
@@ -0,0 +1,74 @@
+//! Read-only queries served by the query API.
+use tokio_postgres::Client;
+use tokio_postgres::GenericClient;
+
+use super::super::types::CompositionRecord;
+use super::composition;
+use crate::constants::address_ids::EMISSION_CONTRACTS;
+use crate::core::types::Address;
+use crate::core::types::AddressID;
+use crate::core::types::NanoERG;
+use crate::error::EwError;
+
+/// Current balance of given `address_id`, if any.
+pub(crate) async fn get_balance(
+    client: &impl GenericClient,
+    address_id: AddressID,
+) -> Result<Option<NanoERG>, EwError> {
+    let sql = "select nano from erg.balances where address_id = $1;";
+    Ok(client
+        .query_opt(sql, &[&address_id])
+        .await?
+        .map(|row| row.get(0)))
+}
+
+/// Addresses with largest balance, excluding (re-)emission contracts.
+pub(crate) async fn get_rich_list(
+    client: &impl GenericClient,
+    limit: i64,
+) -> Result<Vec<(Address, NanoERG)>, EwError> {
+    let emission_contracts: Vec<i64> = EMISSION_CONTRACTS.iter().map(|a| a.0).collect();
+    let sql = "
+        select a.address
+            , b.nano
+        from erg.balances b
+        join core.addresses a on a.id = b.address_id
+        where b.address_id <> all($1)
+        order by b.nano desc
+        limit $2;";
+    Ok(client
+        .query(sql, &[&emission_contracts, &limit])
+        .await?
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect())
+}
+
+/// Number of P2PK (or other) addresses with a balance within given bounds.
+///
+/// * `p2pk` - count P2PK addresses if true, all others if false
+/// * `ge` - lower bound, inclusive
+/// * `lt` - upper bound, exclusive
+pub(crate) async fn count_addresses(
+    client: &impl GenericClient,
+    p2pk: bool,
+    ge: Option<NanoERG>,
+    lt: Option<NanoERG>,
+) -> Result<i64, EwError> {
+    let sql = "
+        select count(*)
+        from erg.balances
+        where (address_id % 10 = 1) = $1
+            and ($2::bigint is null or nano >= $2)
+            and ($3::bigint is null or nano < $3);";
+    Ok(client.query_one(sql, &[&p2pk, &ge, &lt]).await?.get(0))
+}
+
+/// Supply composition at the worker's height, if any.
+pub(crate) async fn get_supply_composition(
+    client: &Client,
+) -> Result<Option<CompositionRecord>, EwError> {
+    let record = composition::get_last(client).await?;
+    // Placeholder record when no blocks were processed yet
+    Ok((record.height >= 0).then_some(record))
+}
@@ -1,15 +1,15 @@
 use tokio_postgres::types::Type;
 use tokio_postgres::GenericClient;
-use tokio_postgres::Transaction;
 
 use super::super::types::BalanceRecord;
 use crate::core::types::AddressID;
+use crate::error::EwError;
 
 /// Get collection of balance records for given `address_ids`.
 pub async fn get_many(
     client: &impl GenericClient,
     address_ids: &Vec<AddressID>,
-) -> Vec<BalanceRecord> {
+) -> Result<Vec<BalanceRecord>, EwError> {
     tracing::trace!("get_many {address_ids:?}");
     let sql = "
         select address_id
@@ -17,17 +17,19 @@ pub async fn get_many(
             , mean_age_timestamp
         from erg.balances
         where address_id = any($1);";
-    client
+    Ok(client
         .query(sql, &[&address_ids])
-        .await
-        .unwrap()
+        .await?
         .iter()
         .map(|r| BalanceRecord::new(r.get(0), r.get(1), r.get(2)))
-        .collect()
+        .collect())
 }
 
 /// Upsert collection of balance records.
-pub async fn upsert_many(pgtx: &Transaction<'_>, records: &Vec<BalanceRecord>) {
+pub async fn upsert_many(
+    pgtx: &impl GenericClient,
+    records: &Vec<BalanceRecord>,
+) -> Result<(), EwError> {
     tracing::trace!("upsert_many {records:?}");
     let sql = "
         insert into erg.balances (address_id, nano, mean_age_timestamp)
@@ -38,72 +40,77 @@ pub async fn upsert_many(pgtx: &Transaction<'_>, records: &Vec<BalanceRecord>) {
         ;";
     let stmt = pgtx
         .prepare_typed(sql, &[Type::INT8, Type::INT8, Type::INT8])
-        .await
-        .unwrap();
+        .await?;
     for r in records {
         pgtx.execute(&stmt, &[&r.address_id, &r.nano, &r.mean_age_timestamp])
-            .await
-            .unwrap();
+            .await?;
     }
+    Ok(())
 }
 
 /// Delete balances for given address id's.
-pub async fn delete_many(pgtx: &Transaction<'_>, address_ids: &Vec<AddressID>) {
+pub async fn delete_many(
+    pgtx: &impl GenericClient,
+    address_ids: &Vec<AddressID>,
+) -> Result<(), EwError> {
     tracing::trace!("delete_many {address_ids:?}");
     pgtx.execute(
         "delete from erg.balances where address_id = any($1);",
         &[&address_ids],
     )
-    .await
-    .unwrap();
+    .await?;
+    Ok(())
 }
 
 pub mod logs {
+
     use super::*;
     use crate::core::types::Height;
+    use crate::error::EwError;
 
     /// Log given `address_ids` as being created at `height`.
     ///
     /// Must be called prior to modifying balances for `height`.
     pub async fn log_new_balances(
-        pgtx: &Transaction<'_>,
+        pgtx: &impl GenericClient,
         height: Height,
         address_ids: &Vec<AddressID>,
-    ) {
+    ) -> Result<(), EwError> {
         tracing::trace!("log_new_balances {height} {address_ids:?}");
         let sql = "
             insert into erg._log_balances_created_at(height, address_id)
             select $1, unnest($2::bigint[]);";
-        pgtx.execute(sql, &[&height, &address_ids]).await.unwrap();
+        pgtx.execute(sql, &[&height, &address_ids]).await?;
+        Ok(())
     }
 
     /// Get `address_ids` that where created at `height`.
     pub async fn get_addresses_created_at(
-        pgtx: &Transaction<'_>,
+        pgtx: &impl GenericClient,
         height: Height,
-    ) -> Vec<AddressID> {
+    ) -> Result<Vec<AddressID>, EwError> {
         tracing::trace!("get_addresses_created_at {height}");
         let sql = "
             select address_id
             from erg._log_balances_created_at
             where height = $1;
         ";
-        pgtx.query(sql, &[&height])
-            .await
-            .unwrap()
+        Ok(pgtx
+            .query(sql, &[&height])
+            .await?
             .iter()
             .map(|r| r.get(0))
-            .collect()
+            .collect())
     }
 
     /// Log state of existing balances for given `address_ids`.
     ///
     /// Must be called prior to modifying balances for `height`.
     pub async fn log_existing_balances(
-        pgtx: &Transaction<'_>,
+        pgtx: &impl GenericClient,
         height: Height,
         address_ids: &Vec<AddressID>,
-    ) {
+    ) -> Result<(), EwError> {
         tracing::trace!("log_existing_balances {height} {address_ids:?}");
         let sql = "
             insert into erg._log_balances_previous_state_at(
@@ -119,11 +126,15 @@ pub mod logs {
             from erg.balances
             where address_id = any($2);
             ;";
-        pgtx.execute(sql, &[&height, &address_ids]).await.unwrap();
+        pgtx.execute(sql, &[&height, &address_ids]).await?;
+        Ok(())
     }
 
     /// Get balances logged at `height`.
-    pub async fn get_balances_at(pgtx: &Transaction<'_>, height: Height) -> Vec<BalanceRecord> {
+    pub async fn get_balances_at(
+        pgtx: &impl GenericClient,
+        height: Height,
+    ) -> Result<Vec<BalanceRecord>, EwError> {
         tracing::trace!("get_balances_at {height}");
         let sql = "
             select address_id
@@ -134,8 +145,7 @@ pub mod logs {
         ";
         let balances: Vec<BalanceRecord> = pgtx
             .query(sql, &[&height])
-            .await
-            .unwrap()
+            .await?
             .iter()
             .map(|r| BalanceRecord {
                 address_id: r.get(0),
@@ -146,28 +156,33 @@ pub mod logs {
         if balances.is_empty() {
             panic!("Rollback horizon reached!");
         }
-        balances
+        Ok(balances)
     }
 
     /// Delete log records prior to given `height`.
-    pub async fn delete_logs_prior_to(pgtx: &Transaction<'_>, height: Height) {
+    pub async fn delete_logs_prior_to(
+        pgtx: &impl GenericClient,
+        height: Height,
+    ) -> Result<(), EwError> {
         tracing::trace!("delete_logs_prior_to {height}");
 
         let sql = "delete from erg._log_balances_previous_state_at where height < $1;";
-        pgtx.execute(sql, &[&height]).await.unwrap();
+        pgtx.execute(sql, &[&height]).await?;
 
         let sql = "delete from erg._log_balances_created_at where height < $1;";
-        pgtx.execute(sql, &[&height]).await.unwrap();
+        pgtx.execute(sql, &[&height]).await?;
+        Ok(())
     }
 
     /// Delete log records for given `height`.
-    pub async fn delete_logs_at(pgtx: &Transaction<'_>, height: Height) {
+    pub async fn delete_logs_at(pgtx: &impl GenericClient, height: Height) -> Result<(), EwError> {
         tracing::trace!("delete_logs_at {height}");
 
         let sql = "delete from erg._log_balances_previous_state_at where height = $1;";
-        pgtx.execute(sql, &[&height]).await.unwrap();
+        pgtx.execute(sql, &[&height]).await?;
 
         let sql = "delete from erg._log_balances_created_at where height = $1;";
-        pgtx.execute(sql, &[&height]).await.unwrap();
+        pgtx.execute(sql, &[&height]).await?;
+        Ok(())
     }
 }
@@ -1,10 +1,13 @@
-use tokio_postgres::Client;
-use tokio_postgres::Transaction;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::GenericClient;
 
 use super::super::types::CompositionRecord;
 use crate::core::types::Height;
+use crate::error::EwError;
+use crate::utils::copy_in;
+use crate::utils::CopyTypes;
 
-pub(super) async fn get_last(client: &Client) -> CompositionRecord {
+pub(super) async fn get_last(client: &impl GenericClient) -> Result<CompositionRecord, EwError> {
     let sql = "
         select height
             , p2pks
@@ -14,23 +17,27 @@ pub(super) async fn get_last(client: &Client) -> CompositionRecord {
         order by height desc
         limit 1;
     ";
-    match client.query_opt(sql, &[]).await.unwrap() {
+    Ok(match client.query_opt(sql, &[]).await? {
         Some(row) => CompositionRecord {
             height: row.get(0),
             p2pks: row.get(1),
             contracts: row.get(2),
             miners: row.get(3),
         },
+        // Next record will be for genesis
         None => CompositionRecord {
-            height: 0,
+            height: -1,
             p2pks: 0,
             contracts: 0,
             miners: 0,
         },
-    }
+    })
 }
 
-pub(super) async fn insert(pgtx: &Transaction<'_>, record: &CompositionRecord) {
+pub(super) async fn insert(
+    pgtx: &impl GenericClient,
+    record: &CompositionRecord,
+) -> Result<(), EwError> {
     let sql = "
         insert into erg.supply_composition (height, p2pks, contracts, miners)
         values ($1, $2, $3, $4)
@@ -44,12 +51,41 @@ pub(super) async fn insert(pgtx: &Transaction<'_>, record: &CompositionRecord) {
             &record.miners,
         ],
     )
-    .await
-    .unwrap();
+    .await?;
+    Ok(())
+}
+
+/// Insert collection of records.
+pub(super) async fn insert_many(
+    pgtx: &impl GenericClient,
+    copy_types: &CopyTypes,
+    records: &[CompositionRecord],
+) -> Result<(), EwError> {
+    let rows: Vec<Vec<&(dyn ToSql + Sync)>> = records
+        .iter()
+        .map(|r| {
+            vec![
+                &r.height as &(dyn ToSql + Sync),
+                &r.p2pks,
+                &r.contracts,
+                &r.miners,
+            ]
+        })
+        .collect();
+    copy_in(
+        pgtx,
+        copy_types,
+        "erg.supply_composition",
+        &["height", "p2pks", "contracts", "miners"],
+        &rows,
+    )
+    .await?;
+    Ok(())
 }
 
 /// Delete record for given `height`.
-pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: Height) {
+pub(super) async fn delete_at(pgtx: &impl GenericClient, height: Height) -> Result<(), EwError> {
     let sql = "delete from erg.supply_composition where height = $1;";
-    pgtx.execute(sql, &[&height]).await.unwrap();
+    pgtx.execute(sql, &[&height]).await?;
+    Ok(())
 }
@@ -1,63 +1,72 @@
 use postgres_from_row::FromRow;
 use tokio_postgres::Client;
-use tokio_postgres::Transaction;
+use tokio_postgres::GenericClient;
 
 use super::super::types::AddressCounts;
 use super::super::types::AddressCountsRecord;
 use crate::core::types::Height;
+use crate::error::EwError;
 
-pub(super) async fn get_last(client: &Client) -> AddressCounts {
-    AddressCounts {
-        p2pk: get_last_p2pk(client).await,
-        contracts: get_last_contracts(client).await,
-        miners: get_last_miners(client).await,
-    }
+pub(super) async fn get_last(client: &Client) -> Result<AddressCounts, EwError> {
+    Ok(AddressCounts {
+        p2pk: get_last_p2pk(client).await?,
+        contracts: get_last_contracts(client).await?,
+        miners: get_last_miners(client).await?,
+    })
 }
 
-async fn get_last_p2pk(client: &Client) -> AddressCountsRecord {
+async fn get_last_p2pk(client: &Client) -> Result<AddressCountsRecord, EwError> {
     let sql = "
         select *
         from erg.address_counts_by_balance_p2pk
         order by height desc limit 1;
     ";
-    match client.query_opt(sql, &[]).await.unwrap() {
+    Ok(match client.query_opt(sql, &[]).await? {
         Some(row) => AddressCountsRecord::from_row(&row),
         None => AddressCountsRecord::blank(),
-    }
+    })
 }
 
-async fn get_last_contracts(client: &Client) -> AddressCountsRecord {
+async fn get_last_contracts(client: &Client) -> Result<AddressCountsRecord, EwError> {
     let sql = "
         select *
         from erg.address_counts_by_balance_contracts
         order by height desc limit 1;
     ";
-    match client.query_opt(sql, &[]).await.unwrap() {
+    Ok(match client.query_opt(sql, &[]).await? {
         Some(row) => AddressCountsRecord::from_row(&row),
         None => AddressCountsRecord::blank(),
-    }
+    })
 }
 
-async fn get_last_miners(client: &Client) -> AddressCountsRecord {
+async fn get_last_miners(client: &Client) -> Result<AddressCountsRecord, EwError> {
     let sql = "
         select *
         from erg.address_counts_by_balance_miners
         order by height desc limit 1;
     ";
-    match client.query_opt(sql, &[]).await.unwrap() {
+    Ok(match client.query_opt(sql, &[]).await? {
         Some(row) => AddressCountsRecord::from_row(&row),
         None => AddressCountsRecord::blank(),
-    }
+    })
 }
 
-pub(super) async fn insert(pgtx: &Transaction<'_>, counts: &AddressCounts) {
-    insert_record(pgtx, &counts.p2pk, "p2pk").await;
-    insert_record(pgtx, &counts.contracts, "contracts").await;
-    insert_record(pgtx, &counts.miners, "miners").await;
+pub(super) async fn insert(
+    pgtx: &impl GenericClient,
+    counts: &AddressCounts,
+) -> Result<(), EwError> {
+    insert_record(pgtx, &counts.p2pk, "p2pk").await?;
+    insert_record(pgtx, &counts.contracts, "contracts").await?;
+    insert_record(pgtx, &counts.miners, "miners").await?;
+    Ok(())
 }
 
 /// Inserts a record into table matching given `label`.
-async fn insert_record(pgtx: &Transaction<'_>, record: &AddressCountsRecord, label: &str) {
+async fn insert_record(
+    pgtx: &impl GenericClient,
+    record: &AddressCountsRecord,
+    label: &str,
+) -> Result<(), EwError> {
     let sql = format!(
         "
         insert into erg.address_counts_by_balance_{label} (
@@ -93,33 +102,31 @@ async fn insert_record(pgtx: &Transaction<'_>, record: &AddressCountsRecord, lab
             &record.ge_1m,
         ],
     )
-    .await
-    .unwrap();
+    .await?;
+    Ok(())
 }
 
 /// Delete records for given `height`.
-pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: Height) {
+pub(super) async fn delete_at(pgtx: &impl GenericClient, height: Height) -> Result<(), EwError> {
     // P2PK's
     pgtx.execute(
         "delete from erg.address_counts_by_balance_p2pk where height = $1;",
         &[&height],
     )
-    .await
-    .unwrap();
+    .await?;
 
     // Contracts
     pgtx.execute(
         "delete from erg.address_counts_by_balance_contracts where height = $1;",
         &[&height],
     )
-    .await
-    .unwrap();
+    .await?;
 
     // Miners
     pgtx.execute(
         "delete from erg.address_counts_by_balance_miners where height = $1;",
         &[&height],
     )
-    .await
-    .unwrap();
+    .await?;
+    Ok(())
 }
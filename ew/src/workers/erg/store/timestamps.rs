@@ -1,19 +1,26 @@
+use tokio_postgres::GenericClient;
 use tokio_postgres::Transaction;
 
 use crate::core::types::Height;
 use crate::core::types::Timestamp;
+use crate::error::EwError;
 
-pub async fn insert(pgtx: &Transaction<'_>, height: Height, timestamp: Timestamp) {
+pub async fn insert(
+    pgtx: &impl GenericClient,
+    height: Height,
+    timestamp: Timestamp,
+) -> Result<(), EwError> {
     tracing::trace!("insert {height} {timestamp}");
     let stmt = "
         insert into erg.timestamps (height, timestamp)
         values ($1, $2);";
-    pgtx.execute(stmt, &[&height, &timestamp]).await.unwrap();
+    pgtx.execute(stmt, &[&height, &timestamp]).await?;
+    Ok(())
 }
 
-pub async fn delete_at(pgtx: &Transaction<'_>, height: Height) {
+pub async fn delete_at(pgtx: &impl GenericClient, height: Height) -> Result<(), EwError> {
     tracing::trace!("delete_at {height}");
     pgtx.execute("delete from erg.timestamps where height = $1;", &[&height])
-        .await
-        .unwrap();
+        .await?;
+    Ok(())
 }
@@ -3,6 +3,9 @@ pub mod queries;
 mod store;
 pub mod types;
 
+pub(crate) use store::api;
+pub(crate) use store::SCHEMA;
+
 use async_trait::async_trait;
 use tokio::sync::mpsc;
 
@@ -14,12 +17,15 @@ use crate::config::PostgresConfig;
 use crate::core::types::CoreData;
 use crate::core::types::Header;
 use crate::core::types::Height;
+use crate::error::EwError;
+use crate::framework::store::PgMigrator;
 use crate::framework::BlockRange;
 use crate::framework::EventEmission;
 use crate::framework::EventHandling;
 use crate::framework::QueryHandler;
 use crate::framework::QuerySender;
 use crate::framework::QueryWrapper;
+use crate::framework::Rewinding;
 use crate::framework::SourceWorker;
 use crate::framework::StampedData;
 use parsing::Parser;
@@ -29,6 +35,14 @@ const WORKER_ID: &'static str = "erg_diffs";
 
 pub type Worker = SourceWorker<ErgDiffsWorkFlow>;
 
+/// Initializes the worker's store and applies pending migrations.
+pub async fn migrate(pgconf: &PostgresConfig) -> Result<(), EwError> {
+    PgMigrator::new(pgconf, &store::SCHEMA)
+        .await?
+        .apply_pending()
+        .await
+}
+
 pub struct ErgDiffsWorkFlow {
     parser: Parser,
     store: Store,
@@ -39,28 +53,44 @@ impl EventHandling for ErgDiffsWorkFlow {
     type U = CoreData;
     type D = DiffData;
 
-    async fn new(pgconf: &PostgresConfig) -> Self {
-        let store = Store::new(pgconf, &store::SCHEMA).await;
+    async fn new(pgconf: &PostgresConfig) -> Result<Self, EwError> {
+        // Ensure migrations are applied
+        migrate(pgconf).await?;
+
+        let store = Store::new(pgconf, &store::SCHEMA).await?;
         let parser = Parser::new();
-        Self { parser, store }
+        Ok(Self { parser, store })
     }
 
-    async fn include_block(&mut self, data: &StampedData<CoreData>) -> Self::D {
+    async fn include_block(&mut self, data: &StampedData<CoreData>) -> Result<Self::D, EwError> {
         let stamped_batch = self.parser.extract_batch(data);
-        self.store.persist(&stamped_batch).await;
+        self.store.persist(&stamped_batch).await?;
 
-        DiffData::from(stamped_batch.data)
+        Ok(DiffData::from(stamped_batch.data))
     }
 
-    async fn roll_back(&mut self, height: Height) -> Header {
-        self.store.roll_back(height).await;
+    async fn roll_back(&mut self, height: Height) -> Result<Header, EwError> {
+        self.store.roll_back(height).await?;
         self.parser = Parser::new();
-        self.store.get_header().clone()
+        Ok(self.store.get_header().clone())
     }
 
     fn header<'a>(&'a self) -> &'a Header {
         self.store.get_header()
     }
+
+    async fn flush(&mut self) -> Result<(), EwError> {
+        self.store.flush().await
+    }
+}
+
+#[async_trait]
+impl Rewinding for ErgDiffsWorkFlow {
+    async fn rewind(&mut self, height: Height) -> Result<Header, EwError> {
+        self.store.rewind(height).await?;
+        self.parser = Parser::new();
+        Ok(self.store.get_header().clone())
+    }
 }
 
 #[async_trait]
@@ -68,17 +98,20 @@ impl EventEmission for ErgDiffsWorkFlow {
     type S = DiffData;
 
     /// Returns true if data for `header` has been included.
-    async fn contains_header(&self, header: &Header) -> bool {
+    async fn contains_header(&self, header: &Header) -> Result<bool, EwError> {
         // Initial header is always contained but will not be stored,
         // so handle explicitly.
-        header.is_initial() || self.store.is_main_chain(header).await
+        Ok(header.is_initial() || self.store.is_main_chain(header).await?)
     }
 
     /// Get data for given height range.
     ///
     /// Used by lagging cursors to retrieve data.
     #[tracing::instrument(level = tracing::Level::DEBUG, skip(self))]
-    async fn get_slice(&self, block_range: &BlockRange) -> Vec<StampedData<Self::S>> {
+    async fn get_slice(
+        &self,
+        block_range: &BlockRange,
+    ) -> Result<Vec<StampedData<Self::S>>, EwError> {
         self.store.get_slice(block_range).await
     }
 }
@@ -90,26 +123,28 @@ pub struct QueryWorker {
 }
 
 impl QueryWorker {
-    pub async fn new(pgconf: &PostgresConfig) -> Self {
+    pub async fn new(pgconf: &PostgresConfig) -> Result<Self, EwError> {
         let (query_tx, query_rx) = mpsc::channel(8);
 
-        Self {
-            store: QueryStore::new(pgconf).await,
+        Ok(Self {
+            store: QueryStore::new(pgconf).await?,
             query_tx,
             query_rx,
-        }
+        })
     }
 
     #[tracing::instrument(name = "erg_diffs query handler", skip_all, level=tracing::Level::DEBUG)]
-    pub async fn start(&mut self) {
+    pub async fn start(&mut self) -> Result<(), EwError> {
         tracing::debug!("starting");
-        loop {
-            tracing::debug!("waiting for next query");
-            let qw = self.query_rx.recv().await.unwrap();
-            let response = self.store.query_balance_diffs(qw.query).await;
+        // Runs until all query senders are gone
+        while let Some(qw) = self.query_rx.recv().await {
+            let response = self.store.query_balance_diffs(qw.query).await?;
             tracing::debug!("sending response");
-            qw.response_tx.send(response).unwrap();
+            // Querying worker may have stopped in the meantime
+            qw.response_tx.send(response).ok();
         }
+        tracing::debug!("stopped");
+        Ok(())
     }
 }
 
@@ -1,19 +1,23 @@
+pub(crate) mod api;
 mod diffs;
 
 use async_trait::async_trait;
 
 use tokio_postgres::Client;
 use tokio_postgres::NoTls;
-use tokio_postgres::Transaction;
 
 use super::types::DiffRecord;
 use crate::config::PostgresConfig;
 use crate::core::types::Header;
+use crate::core::types::Height;
+use crate::error::EwError;
 use crate::framework::store::BatchStore;
 use crate::framework::store::PgStore;
 use crate::framework::store::Revision;
+use crate::framework::store::RewindableStore;
 use crate::framework::store::SourcableStore;
 use crate::framework::store::StoreDef;
+use crate::framework::store::StoreTransaction;
 use crate::framework::utils::BlockRange;
 use crate::framework::StampedData;
 
@@ -22,11 +26,13 @@ use super::types::Batch;
 use super::types::DiffData;
 use super::WORKER_ID;
 
-pub(super) const SCHEMA: StoreDef = StoreDef {
+pub(crate) const SCHEMA: StoreDef = StoreDef {
     schema_name: "erg",
     worker_id: WORKER_ID,
     sql: include_str!("store/schema.sql"),
     revision: &Revision { major: 1, minor: 0 },
+    migrations: &[],
+    has_header: true,
 };
 
 pub(super) type Store = PgStore<InnerStore>;
@@ -40,14 +46,29 @@ impl BatchStore for InnerStore {
         Self {}
     }
 
-    async fn persist(&mut self, pgtx: &Transaction<'_>, stamped_batch: &StampedData<Self::B>) {
+    async fn persist(
+        &mut self,
+        pgtx: &StoreTransaction,
+        stamped_batch: &StampedData<Self::B>,
+    ) -> Result<(), EwError> {
         let batch = &stamped_batch.data;
-        diffs::insert_many(&pgtx, &batch.diff_records).await;
+        diffs::insert_many(pgtx.client(), pgtx.copy_types(), &batch.diff_records).await?;
+        Ok(())
     }
 
-    async fn roll_back(&mut self, pgtx: &Transaction<'_>, header: &Header) {
+    async fn roll_back(&mut self, pgtx: &StoreTransaction, header: &Header) -> Result<(), EwError> {
         tracing::debug!("rolling back block {}", header.height);
-        diffs::delete_at(&pgtx, header.height).await;
+        diffs::delete_at(pgtx.client(), header.height).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RewindableStore for InnerStore {
+    async fn rewind(&mut self, pgtx: &StoreTransaction, height: Height) -> Result<(), EwError> {
+        tracing::debug!("rewinding to block {height}");
+        diffs::delete_after(pgtx.client(), height).await?;
+        Ok(())
     }
 }
 
@@ -55,10 +76,17 @@ impl BatchStore for InnerStore {
 impl SourcableStore for InnerStore {
     type S = DiffData;
 
-    async fn get_slice(&self, client: &Client, block_range: &BlockRange) -> Vec<Self::S> {
+    async fn get_slice(
+        &self,
+        client: &Client,
+        block_range: &BlockRange,
+    ) -> Result<Vec<Self::S>, EwError> {
         let diff_records =
-            diffs::select_slice(client, block_range.first_height, block_range.last_height).await;
-        partition_diff_records(diff_records, block_range.size() as usize)
+            diffs::select_slice(client, block_range.first_height, block_range.last_height).await?;
+        Ok(partition_diff_records(
+            diff_records,
+            block_range.size() as usize,
+        ))
     }
 }
 
@@ -67,13 +95,11 @@ pub(super) struct QueryStore {
 }
 
 impl QueryStore {
-    pub async fn new(pgconf: &PostgresConfig) -> Self {
+    pub async fn new(pgconf: &PostgresConfig) -> Result<Self, EwError> {
         tracing::debug!("initializing query store");
 
         // init client
-        let (client, connection) = tokio_postgres::connect(&pgconf.connection_uri, NoTls)
-            .await
-            .unwrap();
+        let (client, connection) = tokio_postgres::connect(&pgconf.connection_uri, NoTls).await?;
 
         tokio::spawn(async move {
             if let Err(e) = connection.await {
@@ -81,14 +107,14 @@ impl QueryStore {
             }
         });
 
-        Self { client }
+        Ok(Self { client })
     }
 
     #[tracing::instrument(skip(self), level=tracing::Level::DEBUG)]
     pub(super) async fn query_balance_diffs(
         &self,
         query: queries::DiffsQuery,
-    ) -> queries::DiffsQueryResponse {
+    ) -> Result<queries::DiffsQueryResponse, EwError> {
         diffs::select_aggregate_series(&self.client, &query.address_ids, query.max_height).await
     }
 }
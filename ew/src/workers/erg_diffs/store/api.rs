@@ -0,0 +1,20 @@
+//! Read-only queries served by the query API.
+use tokio_postgres::GenericClient;
+
+use crate::core::types::AddressID;
+use crate::core::types::Height;
+use crate::core::types::NanoERG;
+use crate::error::EwError;
+
+/// Balance of given `address_id` at `height`, if it ever had any.
+pub(crate) async fn get_balance_at(
+    client: &impl GenericClient,
+    address_id: AddressID,
+    height: Height,
+) -> Result<Option<NanoERG>, EwError> {
+    let sql = "
+        select sum(nano)::bigint
+        from erg.balance_diffs
+        where address_id = $1 and height <= $2;";
+    Ok(client.query_one(sql, &[&address_id, &height]).await?.get(0))
+}
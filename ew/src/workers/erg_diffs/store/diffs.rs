@@ -1,39 +1,64 @@
-use tokio_postgres::types::Type;
+use tokio_postgres::types::ToSql;
 use tokio_postgres::Client;
 use tokio_postgres::GenericClient;
-use tokio_postgres::Transaction;
 
 use super::super::types::DiffRecord;
 use super::super::types::SupplyDiff;
 use crate::core::types::AddressID;
 use crate::core::types::Height;
+use crate::error::EwError;
+use crate::utils::copy_in;
+use crate::utils::CopyTypes;
 
 /// Insert collection of diff records.
-pub async fn insert_many(pgtx: &Transaction<'_>, records: &Vec<DiffRecord>) {
+pub async fn insert_many(
+    pgtx: &impl GenericClient,
+    copy_types: &CopyTypes,
+    records: &Vec<DiffRecord>,
+) -> Result<(), EwError> {
     tracing::trace!("insert_many {records:?}");
-    let sql = "
-        insert into erg.balance_diffs (address_id, height, tx_idx, nano)
-        values ($1, $2, $3, $4);";
-    let stmt = pgtx
-        .prepare_typed(sql, &[Type::INT8, Type::INT4, Type::INT2, Type::INT8])
-        .await
-        .unwrap();
-    for r in records {
-        pgtx.execute(&stmt, &[&r.address_id, &r.height, &r.tx_idx, &r.nano])
-            .await
-            .unwrap();
-    }
+    let rows: Vec<Vec<&(dyn ToSql + Sync)>> = records
+        .iter()
+        .map(|r| {
+            vec![
+                &r.address_id as &(dyn ToSql + Sync),
+                &r.height,
+                &r.tx_idx,
+                &r.nano,
+            ]
+        })
+        .collect();
+    copy_in(
+        pgtx,
+        copy_types,
+        "erg.balance_diffs",
+        &["address_id", "height", "tx_idx", "nano"],
+        &rows,
+    )
+    .await?;
+    Ok(())
 }
 
 /// Delete diff records at given `height`.
-pub async fn delete_at(pgtx: &Transaction<'_>, height: Height) {
+pub async fn delete_at(pgtx: &impl GenericClient, height: Height) -> Result<(), EwError> {
     tracing::trace!("delete_at {height}");
     pgtx.execute(
         "delete from erg.balance_diffs where height = $1;",
         &[&height],
     )
-    .await
-    .unwrap();
+    .await?;
+    Ok(())
+}
+
+/// Delete diff records after given `height`.
+pub async fn delete_after(pgtx: &impl GenericClient, height: Height) -> Result<(), EwError> {
+    tracing::trace!("delete_after {height}");
+    pgtx.execute(
+        "delete from erg.balance_diffs where height > $1;",
+        &[&height],
+    )
+    .await?;
+    Ok(())
 }
 
 /// Get diff records for given `height`.
@@ -41,7 +66,7 @@ pub async fn select_slice(
     client: &impl GenericClient,
     ge_height: Height,
     le_height: Height,
-) -> Vec<DiffRecord> {
+) -> Result<Vec<DiffRecord>, EwError> {
     tracing::trace!("select_slice {ge_height} {le_height}");
     let rows = client
         .query(
@@ -53,16 +78,16 @@ pub async fn select_slice(
         where height >= $1 and height <= $2;",
             &[&ge_height, &le_height],
         )
-        .await
-        .unwrap();
-    rows.iter()
+        .await?;
+    Ok(rows
+        .iter()
         .map(|r| DiffRecord {
             address_id: r.get(0),
             height: r.get(1),
             tx_idx: r.get(2),
             nano: r.get(3),
         })
-        .collect()
+        .collect())
 }
 
 /// Get aggregate series of balance diffs for given addresses,
@@ -71,7 +96,7 @@ pub async fn select_aggregate_series(
     client: &Client,
     address_ids: &Vec<AddressID>,
     max_height: Height,
-) -> Vec<SupplyDiff> {
+) -> Result<Vec<SupplyDiff>, EwError> {
     let sql = "
         select height
             , sum(nano)::bigint
@@ -80,14 +105,13 @@ pub async fn select_aggregate_series(
         group by 1
         order by 1;
     ";
-    client
+    Ok(client
         .query(sql, &[&address_ids, &max_height])
-        .await
-        .unwrap()
+        .await?
         .iter()
         .map(|r| SupplyDiff {
             height: r.get(0),
             nano: r.get(1),
         })
-        .collect()
+        .collect())
 }
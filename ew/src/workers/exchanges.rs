@@ -9,6 +9,12 @@ pub mod testing {
     pub use store::SCHEMA;
 }
 
+pub(crate) use parsing::spot_deposit_addresses;
+pub(crate) use parsing::ParserCache;
+pub(crate) use store::api;
+pub(crate) use store::SCHEMA;
+pub(crate) use types::ExchangeID;
+
 use async_trait::async_trait;
 use tokio::sync::oneshot;
 
@@ -16,11 +22,14 @@ use crate::config::PostgresConfig;
 use crate::core::types::AddressID;
 use crate::core::types::Header;
 use crate::core::types::Height;
+use crate::error::EwError;
 use crate::framework::store::PgMigrator;
 use crate::framework::EventHandling;
 use crate::framework::QuerySender;
 use crate::framework::Querying;
+use crate::framework::Rewinding;
 use crate::framework::StampedData;
+use crate::monitor::events::Figures;
 use crate::workers::erg_diffs::queries::DiffsQuery;
 use crate::workers::erg_diffs::queries::DiffsQueryResponse;
 use crate::workers::erg_diffs::types::DiffData;
@@ -36,6 +45,14 @@ const WORKER_ID: &'static str = "exchanges";
 
 pub type Worker = crate::framework::LeafWorker<CexWorkFlow>;
 
+/// Initializes the worker's store and applies pending migrations.
+pub async fn migrate(pgconf: &PostgresConfig) -> Result<(), EwError> {
+    PgMigrator::new(pgconf, &store::SCHEMA)
+        .await?
+        .apply_pending()
+        .await
+}
+
 pub struct CexWorkFlow {
     parser: Parser,
     store: Store,
@@ -47,30 +64,23 @@ impl EventHandling for CexWorkFlow {
     type U = DiffData;
     type D = ();
 
-    async fn new(pgconf: &PostgresConfig) -> Self {
+    async fn new(pgconf: &PostgresConfig) -> Result<Self, EwError> {
         // Ensure migrations are applied
-        let mut migrator = PgMigrator::new(pgconf, &store::SCHEMA).await;
-        migrator.apply(&store::migrations::Mig1_1 {}).await;
-        migrator.apply(&store::migrations::Mig1_2 {}).await;
-        migrator.apply(&store::migrations::Mig1_3 {}).await;
-        migrator.apply(&store::migrations::Mig1_4 {}).await;
-        migrator.apply(&store::migrations::Mig1_5 {}).await;
-        migrator.apply(&store::migrations::Mig1_6 {}).await;
-        migrator.apply(&store::migrations::Mig1_7 {}).await;
+        migrate(pgconf).await?;
 
         // Create store
-        let store = Store::new(pgconf, &store::SCHEMA).await;
-        let cache = store::load_parser_cache(store.get_client()).await;
+        let store = Store::new(pgconf, &store::SCHEMA).await?;
+        let cache = store::load_parser_cache(store.get_client()).await?;
         let parser = Parser::new(cache);
-        Self {
+        Ok(Self {
             parser,
             store,
             query_sender: QuerySender::placeholder(),
-        }
+        })
     }
 
     #[tracing::instrument(skip(self, data), fields(height = data.height))]
-    async fn include_block(&mut self, data: &StampedData<DiffData>) -> Self::D {
+    async fn include_block(&mut self, data: &StampedData<DiffData>) -> Result<Self::D, EwError> {
         // Obtain deposit address spottings.
         // Supply on new deposit addresses must be added retroactively to total deposit supply.
         // Supply on addresses spotted as inter-block conflicts must be subtracted from
@@ -85,7 +95,7 @@ impl EventHandling for CexWorkFlow {
                     spottings.new_deposits.keys().map(|k| *k).collect(),
                     data.height,
                 );
-                let rx = self.query_sender.send(query).await;
+                let rx = self.query_sender.send(query).await?;
                 Some(rx)
             }
         };
@@ -98,7 +108,7 @@ impl EventHandling for CexWorkFlow {
                     spottings.inter_conflicts.keys().map(|a| *a).collect(),
                     data.height,
                 );
-                let rx = self.query_sender.send(query).await;
+                let rx = self.query_sender.send(query).await?;
                 Some(rx)
             }
         };
@@ -108,14 +118,14 @@ impl EventHandling for CexWorkFlow {
             None => vec![],
             Some(rx) => {
                 tracing::debug!("waiting for pos query response");
-                rx.await.unwrap()
+                rx.await.map_err(|_| EwError::QueryHandlerDown)?
             }
         };
         let neg_diffs = match neg_rx {
             None => vec![],
             Some(rx) => {
                 tracing::debug!("waiting for neg query response");
-                rx.await.unwrap()
+                rx.await.map_err(|_| EwError::QueryHandlerDown)?
             }
         };
 
@@ -123,10 +133,11 @@ impl EventHandling for CexWorkFlow {
         let stamped_batch = self
             .parser
             .extract_batch(data, spottings, pos_diffs, neg_diffs);
-        self.store.persist(&stamped_batch).await;
+        self.store.persist(&stamped_batch).await?;
+        Ok(())
     }
 
-    async fn roll_back(&mut self, height: Height) -> Header {
+    async fn roll_back(&mut self, height: Height) -> Result<Header, EwError> {
         // let self.store.get_deposit_addresses_spotted_at(height);
         // self.store.get_deposit_conflicts_spotted_at(height);
 
@@ -135,30 +146,30 @@ impl EventHandling for CexWorkFlow {
             .query_balance_diffs(
                 self.store
                     .get_deposit_conflicts_spotted_at(height)
-                    .await
+                    .await?
                     .iter()
                     .map(|r| r.address_id)
                     .collect(),
                 height,
             )
-            .await;
+            .await?;
 
         // Query balance changes for deposit addresses to be rolled back (negative supply changes)
         let neg_rx = self
             .query_balance_diffs(
-                self.store.get_deposit_addresses_spotted_at(height).await,
+                self.store.get_deposit_addresses_spotted_at(height).await?,
                 height,
             )
-            .await;
+            .await?;
 
         // Wait for queries to be processed
         let pos_diffs = match pos_rx {
             None => vec![],
-            Some(rx) => rx.await.unwrap(),
+            Some(rx) => rx.await.map_err(|_| EwError::QueryHandlerDown)?,
         };
         let neg_diffs = match neg_rx {
             None => vec![],
-            Some(rx) => rx.await.unwrap(),
+            Some(rx) => rx.await.map_err(|_| EwError::QueryHandlerDown)?,
         };
 
         let patch = parsing::calculate_net_supply_patch(pos_diffs, neg_diffs);
@@ -166,16 +177,41 @@ impl EventHandling for CexWorkFlow {
         // Stage patch to be rolled back within rollback transaction
         self.store.stage_rollback_patch(patch);
 
-        self.store.roll_back(height).await;
+        self.store.roll_back(height).await?;
         // Refresh parser cache to reflect rollback
-        let cache = store::load_parser_cache(self.store.get_client()).await;
+        let cache = store::load_parser_cache(self.store.get_client()).await?;
         self.parser = Parser::new(cache);
-        self.store.get_header().clone()
+        Ok(self.store.get_header().clone())
     }
 
     fn header<'a>(&'a self) -> &'a Header {
         self.store.get_header()
     }
+
+    async fn flush(&mut self) -> Result<(), EwError> {
+        self.store.flush().await
+    }
+
+    fn figures(&self) -> Option<Figures> {
+        let record = self.parser.supply();
+        Some(Figures::CexSupply {
+            main: record.main,
+            deposits: record.deposits,
+        })
+    }
+}
+
+#[async_trait]
+impl Rewinding for CexWorkFlow {
+    async fn rewind(&mut self, height: Height) -> Result<Header, EwError> {
+        // Supply patch is derived from erg_diffs within the rewind transaction
+        self.store.rewind(height).await?;
+
+        // Refresh parser cache to reflect rewind
+        let cache = store::load_parser_cache(self.store.get_client()).await?;
+        self.parser = Parser::new(cache);
+        Ok(self.store.get_header().clone())
+    }
 }
 
 #[async_trait]
@@ -194,14 +230,14 @@ impl CexWorkFlow {
         &self,
         address_ids: Vec<AddressID>,
         max_height: Height,
-    ) -> Option<oneshot::Receiver<Vec<SupplyDiff>>> {
+    ) -> Result<Option<oneshot::Receiver<Vec<SupplyDiff>>>, EwError> {
         if address_ids.is_empty() {
-            return None;
+            return Ok(None);
         }
-        Some(
+        Ok(Some(
             self.query_sender
                 .send(DiffsQuery::new(address_ids, max_height))
-                .await,
-        )
+                .await?,
+        ))
     }
 }
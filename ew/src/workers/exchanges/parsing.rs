@@ -80,6 +80,11 @@ impl Parser {
         Self { cache }
     }
 
+    /// Exchange supply as of last parsed block.
+    pub fn supply(&self) -> &SupplyRecord {
+        &self.cache.supply
+    }
+
     pub(super) fn extract_batch(
         &mut self,
         stamped_data: &StampedData<DiffData>,
@@ -176,7 +181,7 @@ pub struct Spottings {
     pub intra_conflicts: HashSet<AddressID>,
 }
 
-pub(super) fn spot_deposit_addresses(diffs: &Vec<DiffRecord>, cache: &ParserCache) -> Spottings {
+pub(crate) fn spot_deposit_addresses(diffs: &Vec<DiffRecord>, cache: &ParserCache) -> Spottings {
     // Detect new deposit addresses
     // Get idx of txs sending to a main
     let candidate_txs: HashSet<i16> = HashSet::from_iter(
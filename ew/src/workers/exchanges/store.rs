@@ -1,3 +1,4 @@
+pub(crate) mod api;
 mod deposit_addresses;
 mod deposit_conflicts;
 mod deposit_ignored;
@@ -7,18 +8,21 @@ mod supply;
 
 use async_trait::async_trait;
 use tokio_postgres::Client;
-use tokio_postgres::Transaction;
 
 use crate::core::types::AddressID;
 use crate::core::types::Header;
 use crate::core::types::Height;
+use crate::error::EwError;
 use crate::framework::store::BatchStore;
 use crate::framework::store::PatchableStore;
 use crate::framework::store::PgStore;
 use crate::framework::store::Revision;
+use crate::framework::store::RewindableStore;
 use crate::framework::store::StoreDef;
+use crate::framework::store::StoreTransaction;
 use crate::framework::StampedData;
 
+use super::parsing;
 use super::parsing::ParserCache;
 use super::types::Batch;
 use super::types::DepositAddressConflict;
@@ -33,6 +37,16 @@ pub const SCHEMA: StoreDef = StoreDef {
     worker_id: WORKER_ID,
     sql: include_str!("store/schema.sql"),
     revision: &Revision { major: 1, minor: 7 },
+    migrations: &[
+        &migrations::Mig1_1 {},
+        &migrations::Mig1_2 {},
+        &migrations::Mig1_3 {},
+        &migrations::Mig1_4 {},
+        &migrations::Mig1_5 {},
+        &migrations::Mig1_6 {},
+        &migrations::Mig1_7 {},
+    ],
+    has_header: true,
 };
 
 pub(super) type Store = PgStore<SpecStore>;
@@ -50,18 +64,22 @@ impl BatchStore for SpecStore {
         }
     }
 
-    async fn persist(&mut self, pgtx: &Transaction<'_>, stamped_batch: &StampedData<Self::B>) {
+    async fn persist(
+        &mut self,
+        pgtx: &StoreTransaction,
+        stamped_batch: &StampedData<Self::B>,
+    ) -> Result<(), EwError> {
         let batch = &stamped_batch.data;
 
         // Insert new record *before* applying patch
-        supply::insert(pgtx, &batch.supply).await;
+        supply::insert(pgtx.client(), &batch.supply).await?;
         // Apply patch *after* inserting new record
         if !batch.supply_patch.is_empty() {
-            supply::patch_deposits(pgtx, &batch.supply_patch).await;
+            supply::patch_deposits(pgtx.client(), &batch.supply_patch).await?;
         }
 
         // New deposit addresses
-        deposit_addresses::insert_many(pgtx, &batch.deposit_addresses).await;
+        deposit_addresses::insert_many(pgtx.client(), &batch.deposit_addresses).await?;
 
         // Deposit conflicts
         for conflict in &batch.deposit_conflicts {
@@ -69,20 +87,21 @@ impl BatchStore for SpecStore {
             let address_spot_height = match conflict {
                 // Inter-block conflicts have always been spotted as a deposit in an earlier block
                 DepositAddressConflict::Inter(conflict) => {
-                    deposit_addresses::get_one(pgtx, conflict.address_id)
-                        .await
+                    deposit_addresses::get_one(pgtx.client(), conflict.address_id)
+                        .await?
                         .spot_height
                 }
                 // Intra-block confllicts are always spotted in current block
                 DepositAddressConflict::Intra(_) => stamped_batch.height,
             };
             let record = &conflict.to_record(address_spot_height);
-            deposit_addresses::delete_one(pgtx, record.address_id).await;
-            deposit_conflicts::insert(pgtx, &record).await;
+            deposit_addresses::delete_one(pgtx.client(), record.address_id).await?;
+            deposit_conflicts::insert(pgtx.client(), &record).await?;
         }
+        Ok(())
     }
 
-    async fn roll_back(&mut self, pgtx: &Transaction<'_>, header: &Header) {
+    async fn roll_back(&mut self, pgtx: &StoreTransaction, header: &Header) -> Result<(), EwError> {
         tracing::debug!("rolling back block {}", header.height);
 
         // for any deposits spotted at h --> query supply diffs
@@ -92,29 +111,30 @@ impl BatchStore for SpecStore {
         // eventually make it to this worker and delete any stale records.
 
         // Delete supply record of rolled back block
-        supply::delete_at(pgtx, header.height).await;
+        supply::delete_at(pgtx.client(), header.height).await?;
 
         // Delete deposit records spotted in rolled back block
-        deposit_addresses::delete_spotted_at(pgtx, header.height).await;
+        deposit_addresses::delete_spotted_at(pgtx.client(), header.height).await?;
 
         // Restore deleted conflicts as deposits (if their address spot height < than h)
         let deposits_to_restore: Vec<DepositAddressRecord> =
-            deposit_conflicts::get_conflicted_at(pgtx, header.height)
-                .await
+            deposit_conflicts::get_conflicted_at(pgtx.client(), header.height)
+                .await?
                 .into_iter()
                 // ignore intra-block conflicts
                 .filter(|c| c.deposit_spot_height != header.height)
                 .map(|c| c.into())
                 .collect();
-        deposit_addresses::insert_many(pgtx, &deposits_to_restore).await;
+        deposit_addresses::insert_many(pgtx.client(), &deposits_to_restore).await?;
 
         // Delete conflict records spotted in rolled back block
-        deposit_conflicts::delete_conflicted_at(pgtx, header.height).await;
+        deposit_conflicts::delete_conflicted_at(pgtx.client(), header.height).await?;
 
         // Apply supply patch if one was prepared
         if let Some(ref patch) = self.rollback_patch {
-            supply::patch_deposits(pgtx, patch).await;
+            supply::patch_deposits(pgtx.client(), patch).await?;
         }
+        Ok(())
     }
 }
 
@@ -130,41 +150,113 @@ impl PatchableStore for SpecStore {
     }
 }
 
-pub(super) async fn load_parser_cache(client: &Client) -> ParserCache {
-    let supply = supply::get_latest(client).await.unwrap_or(SupplyRecord {
+#[async_trait]
+impl RewindableStore for SpecStore {
+    async fn rewind(&mut self, pgtx: &StoreTransaction, height: Height) -> Result<(), EwError> {
+        tracing::debug!("rewinding to block {height}");
+
+        // Deposits spotted after `height` have their supply removed from
+        // remaining supply records (negative supply changes).
+        let neg_ids = deposit_addresses::get_spotted_after(pgtx.client(), height).await?;
+
+        // Conflicts spotted after `height` on addresses that were deposits at
+        // `height` become deposits again (positive supply changes). Other
+        // conflicts never contributed to remaining supply records.
+        let deposits_to_restore: Vec<DepositAddressRecord> =
+            deposit_conflicts::get_conflicted_after(pgtx.client(), height)
+                .await?
+                .into_iter()
+                .filter(|c| c.deposit_spot_height <= height)
+                .map(|c| c.into())
+                .collect();
+        let pos_ids: Vec<AddressID> = deposits_to_restore.iter().map(|r| r.address_id).collect();
+
+        // Delete supply records after `height` before patching remaining ones
+        supply::delete_from(pgtx.client(), height + 1).await?;
+        let patch = parsing::calculate_net_supply_patch(
+            get_balance_diffs(pgtx.client(), &pos_ids, height).await?,
+            get_balance_diffs(pgtx.client(), &neg_ids, height).await?,
+        );
+        if !patch.is_empty() {
+            supply::patch_deposits(pgtx.client(), &patch).await?;
+        }
+
+        deposit_addresses::delete_spotted_after(pgtx.client(), height).await?;
+        deposit_addresses::insert_many(pgtx.client(), &deposits_to_restore).await?;
+        deposit_conflicts::delete_conflicted_after(pgtx.client(), height).await?;
+        Ok(())
+    }
+}
+
+/// Retrieve balance diffs series of given `address_ids`, up to `max_height`.
+///
+/// Reads upstream store (erg_diffs), which is guaranteed to be
+/// at same height, at least.
+async fn get_balance_diffs(
+    client: &Client,
+    address_ids: &Vec<AddressID>,
+    max_height: Height,
+) -> Result<Vec<SupplyDiff>, EwError> {
+    tracing::trace!("get_balance_diffs {address_ids:?} {max_height}");
+    if address_ids.is_empty() {
+        return Ok(vec![]);
+    }
+    let qry = "
+        select height
+            , sum(nano)::bigint
+        from erg.balance_diffs
+        where address_id = any ($1) and height <= $2
+        group by 1
+        order by 1;
+    ";
+    Ok(client
+        .query(qry, &[address_ids, &max_height])
+        .await?
+        .iter()
+        .map(|r| SupplyDiff::new(r.get(0), r.get(1)))
+        .collect())
+}
+
+pub(super) async fn load_parser_cache(client: &Client) -> Result<ParserCache, EwError> {
+    let supply = supply::get_latest(client).await?.unwrap_or(SupplyRecord {
         height: -1,
         main: 0,
         deposits: 0,
     });
-    let main_addresses = main_addresses::map_all(client).await;
-    let deposit_addresses = deposit_addresses::map_all(client).await;
-    let deposit_conflicts = deposit_conflicts::map_all(client).await;
-    let deposit_ignored = deposit_ignored::get_all(client).await;
+    let main_addresses = main_addresses::map_all(client).await?;
+    let deposit_addresses = deposit_addresses::map_all(client).await?;
+    let deposit_conflicts = deposit_conflicts::map_all(client).await?;
+    let deposit_ignored = deposit_ignored::get_all(client).await?;
 
-    ParserCache {
+    Ok(ParserCache {
         supply,
         main_addresses,
         deposit_addresses,
         deposit_conflicts,
         deposit_ignored,
-    }
+    })
 }
 
 impl Store {
-    pub(super) async fn get_deposit_addresses_spotted_at(&self, height: Height) -> Vec<AddressID> {
+    pub(super) async fn get_deposit_addresses_spotted_at(
+        &self,
+        height: Height,
+    ) -> Result<Vec<AddressID>, EwError> {
         deposit_addresses::get_spotted_at(self.get_client(), height).await
     }
 
     pub(super) async fn get_deposit_conflicts_spotted_at(
         &self,
         height: Height,
-    ) -> Vec<DepositAddressConflictRecord> {
+    ) -> Result<Vec<DepositAddressConflictRecord>, EwError> {
         deposit_conflicts::get_conflicted_at(self.get_client(), height).await
     }
 }
 
 pub(super) mod migrations {
 
+    use crate::error::EwError;
+
     use super::super::types::ExchangeID;
     use super::super::types::MainAddressRecord;
     use super::exchanges;
@@ -199,9 +291,13 @@ pub(super) mod migrations {
             Revision::new(1, 1)
         }
 
-        async fn run(&self, pgtx: &Transaction<'_>) -> MigrationEffect {
+        fn sql(&self) -> Option<&'static str> {
+            None
+        }
+
+        async fn run(&self, pgtx: &Transaction<'_>) -> Result<MigrationEffect, EwError> {
             // Get current store height from last supply record
-            let pre_mig_height = supply::get_latest(pgtx).await.map(|r| r.height);
+            let pre_mig_height = supply::get_latest(pgtx).await?.map(|r| r.height);
 
             let new_main_addresses = vec![
                 MainAddressRecord::new(
@@ -247,21 +343,23 @@ pub(super) mod migrations {
             ];
 
             for record in new_main_addresses {
-                add_main_address(pgtx, record).await;
+                add_main_address(pgtx, record).await?;
             }
 
             // Get new store height from last supply record
-            let post_mig_height = supply::get_latest(pgtx).await.map(|r| r.height);
-
-            // Determine migration effect to return
-            if post_mig_height == pre_mig_height {
-                MigrationEffect::None
-            } else {
-                match post_mig_height {
-                    Some(h) => MigrationEffect::Trimmed(h),
-                    None => MigrationEffect::Reset,
-                }
-            }
+            let post_mig_height = supply::get_latest(pgtx).await?.map(|r| r.height);
+
+            Ok(
+                // Determine migration effect to return
+                if post_mig_height == pre_mig_height {
+                    MigrationEffect::None
+                } else {
+                    match post_mig_height {
+                        Some(h) => MigrationEffect::Trimmed(h),
+                        None => MigrationEffect::Reset,
+                    }
+                },
+            )
         }
     }
 
@@ -280,9 +378,13 @@ pub(super) mod migrations {
             Revision::new(1, 2)
         }
 
-        async fn run(&self, pgtx: &Transaction<'_>) -> MigrationEffect {
+        fn sql(&self) -> Option<&'static str> {
+            None
+        }
+
+        async fn run(&self, pgtx: &Transaction<'_>) -> Result<MigrationEffect, EwError> {
             // Get current store height from last supply record
-            let pre_mig_height = supply::get_latest(pgtx).await.map(|r| r.height);
+            let pre_mig_height = supply::get_latest(pgtx).await?.map(|r| r.height);
 
             let new_main_addresses = vec![
                 MainAddressRecord::new(
@@ -298,21 +400,23 @@ pub(super) mod migrations {
             ];
 
             for record in new_main_addresses {
-                add_main_address(pgtx, record).await;
+                add_main_address(pgtx, record).await?;
             }
 
             // Get new store height from last supply record
-            let post_mig_height = supply::get_latest(pgtx).await.map(|r| r.height);
-
-            // Determine migration effect to return
-            if post_mig_height == pre_mig_height {
-                MigrationEffect::None
-            } else {
-                match post_mig_height {
-                    Some(h) => MigrationEffect::Trimmed(h),
-                    None => MigrationEffect::Reset,
-                }
-            }
+            let post_mig_height = supply::get_latest(pgtx).await?.map(|r| r.height);
+
+            Ok(
+                // Determine migration effect to return
+                if post_mig_height == pre_mig_height {
+                    MigrationEffect::None
+                } else {
+                    match post_mig_height {
+                        Some(h) => MigrationEffect::Trimmed(h),
+                        None => MigrationEffect::Reset,
+                    }
+                },
+            )
         }
     }
 
@@ -330,9 +434,13 @@ pub(super) mod migrations {
             Revision::new(1, 3)
         }
 
-        async fn run(&self, pgtx: &Transaction<'_>) -> MigrationEffect {
+        fn sql(&self) -> Option<&'static str> {
+            None
+        }
+
+        async fn run(&self, pgtx: &Transaction<'_>) -> Result<MigrationEffect, EwError> {
             // Get current store height from last supply record
-            let pre_mig_height = supply::get_latest(pgtx).await.map(|r| r.height);
+            let pre_mig_height = supply::get_latest(pgtx).await?.map(|r| r.height);
 
             // Insert new exchange
             exchanges::insert(
@@ -343,7 +451,7 @@ pub(super) mod migrations {
                     name: "Xeggex".to_owned(),
                 },
             )
-            .await;
+            .await?;
 
             // Main exchange address
             add_main_address(
@@ -354,20 +462,22 @@ pub(super) mod migrations {
                     "9hphYTmicjazd45pz2ovoHVPz5LTq9EvXoEK9JMGsfWuMtX6eDu",
                 ),
             )
-            .await;
+            .await?;
 
             // Get new store height from last supply record
-            let post_mig_height = supply::get_latest(pgtx).await.map(|r| r.height);
-
-            // Determine migration effect to return
-            if post_mig_height == pre_mig_height {
-                MigrationEffect::None
-            } else {
-                match post_mig_height {
-                    Some(h) => MigrationEffect::Trimmed(h),
-                    None => MigrationEffect::Reset,
-                }
-            }
+            let post_mig_height = supply::get_latest(pgtx).await?.map(|r| r.height);
+
+            Ok(
+                // Determine migration effect to return
+                if post_mig_height == pre_mig_height {
+                    MigrationEffect::None
+                } else {
+                    match post_mig_height {
+                        Some(h) => MigrationEffect::Trimmed(h),
+                        None => MigrationEffect::Reset,
+                    }
+                },
+            )
         }
     }
 
@@ -385,9 +495,13 @@ pub(super) mod migrations {
             Revision::new(1, 4)
         }
 
-        async fn run(&self, pgtx: &Transaction<'_>) -> MigrationEffect {
+        fn sql(&self) -> Option<&'static str> {
+            None
+        }
+
+        async fn run(&self, pgtx: &Transaction<'_>) -> Result<MigrationEffect, EwError> {
             // Get current store height from last supply record
-            let pre_mig_height = supply::get_latest(pgtx).await.map(|r| r.height);
+            let pre_mig_height = supply::get_latest(pgtx).await?.map(|r| r.height);
 
             // Main exchange address
             add_main_address(
@@ -398,20 +512,22 @@ pub(super) mod migrations {
                     "9haE48wKvgYzc3WdBXRU9ERw2ZWWkGzJT8jGHcXvzQggftiQQdC",
                 ),
             )
-            .await;
+            .await?;
 
             // Get new store height from last supply record
-            let post_mig_height = supply::get_latest(pgtx).await.map(|r| r.height);
-
-            // Determine migration effect to return
-            if post_mig_height == pre_mig_height {
-                MigrationEffect::None
-            } else {
-                match post_mig_height {
-                    Some(h) => MigrationEffect::Trimmed(h),
-                    None => MigrationEffect::Reset,
-                }
-            }
+            let post_mig_height = supply::get_latest(pgtx).await?.map(|r| r.height);
+
+            Ok(
+                // Determine migration effect to return
+                if post_mig_height == pre_mig_height {
+                    MigrationEffect::None
+                } else {
+                    match post_mig_height {
+                        Some(h) => MigrationEffect::Trimmed(h),
+                        None => MigrationEffect::Reset,
+                    }
+                },
+            )
         }
     }
 
@@ -429,9 +545,13 @@ pub(super) mod migrations {
             Revision::new(1, 5)
         }
 
-        async fn run(&self, pgtx: &Transaction<'_>) -> MigrationEffect {
+        fn sql(&self) -> Option<&'static str> {
+            None
+        }
+
+        async fn run(&self, pgtx: &Transaction<'_>) -> Result<MigrationEffect, EwError> {
             // Get current store height from last supply record
-            let pre_mig_height = supply::get_latest(pgtx).await.map(|r| r.height);
+            let pre_mig_height = supply::get_latest(pgtx).await?.map(|r| r.height);
 
             // Insert new exchange
             exchanges::insert(
@@ -442,7 +562,7 @@ pub(super) mod migrations {
                     name: "SevenSeas".to_owned(),
                 },
             )
-            .await;
+            .await?;
 
             // Main exchange address
             add_main_address(
@@ -453,20 +573,22 @@ pub(super) mod migrations {
                     "9hYpa8qu3GihemMA1c4RVZuRGqcmBQKChgokFm6a81R3mFafqgi",
                 ),
             )
-            .await;
+            .await?;
 
             // Get new store height from last supply record
-            let post_mig_height = supply::get_latest(pgtx).await.map(|r| r.height);
-
-            // Determine migration effect to return
-            if post_mig_height == pre_mig_height {
-                MigrationEffect::None
-            } else {
-                match post_mig_height {
-                    Some(h) => MigrationEffect::Trimmed(h),
-                    None => MigrationEffect::Reset,
-                }
-            }
+            let post_mig_height = supply::get_latest(pgtx).await?.map(|r| r.height);
+
+            Ok(
+                // Determine migration effect to return
+                if post_mig_height == pre_mig_height {
+                    MigrationEffect::None
+                } else {
+                    match post_mig_height {
+                        Some(h) => MigrationEffect::Trimmed(h),
+                        None => MigrationEffect::Reset,
+                    }
+                },
+            )
         }
     }
 
@@ -484,9 +606,13 @@ pub(super) mod migrations {
             Revision::new(1, 6)
         }
 
-        async fn run(&self, pgtx: &Transaction<'_>) -> MigrationEffect {
+        fn sql(&self) -> Option<&'static str> {
+            None
+        }
+
+        async fn run(&self, pgtx: &Transaction<'_>) -> Result<MigrationEffect, EwError> {
             // Get current store height from last supply record
-            let pre_mig_height = supply::get_latest(pgtx).await.map(|r| r.height);
+            let pre_mig_height = supply::get_latest(pgtx).await?.map(|r| r.height);
 
             // Insert new exchange
             exchanges::insert(
@@ -497,7 +623,7 @@ pub(super) mod migrations {
                     name: "NonKYC".to_owned(),
                 },
             )
-            .await;
+            .await?;
 
             // Main exchange address
             add_main_address(
@@ -508,20 +634,22 @@ pub(super) mod migrations {
                     "9hmS5u1Khhc4PFERTA2dGzSkDuwUrrENQEUAveF6gHj8xCi9qy3",
                 ),
             )
-            .await;
+            .await?;
 
             // Get new store height from last supply record
-            let post_mig_height = supply::get_latest(pgtx).await.map(|r| r.height);
-
-            // Determine migration effect to return
-            if post_mig_height == pre_mig_height {
-                MigrationEffect::None
-            } else {
-                match post_mig_height {
-                    Some(h) => MigrationEffect::Trimmed(h),
-                    None => MigrationEffect::Reset,
-                }
-            }
+            let post_mig_height = supply::get_latest(pgtx).await?.map(|r| r.height);
+
+            Ok(
+                // Determine migration effect to return
+                if post_mig_height == pre_mig_height {
+                    MigrationEffect::None
+                } else {
+                    match post_mig_height {
+                        Some(h) => MigrationEffect::Trimmed(h),
+                        None => MigrationEffect::Reset,
+                    }
+                },
+            )
         }
     }
 
@@ -539,9 +667,13 @@ pub(super) mod migrations {
             Revision::new(1, 7)
         }
 
-        async fn run(&self, pgtx: &Transaction<'_>) -> MigrationEffect {
+        fn sql(&self) -> Option<&'static str> {
+            None
+        }
+
+        async fn run(&self, pgtx: &Transaction<'_>) -> Result<MigrationEffect, EwError> {
             // Get current store height from last supply record
-            let pre_mig_height = supply::get_latest(pgtx).await.map(|r| r.height);
+            let pre_mig_height = supply::get_latest(pgtx).await?.map(|r| r.height);
 
             // Main exchange address
             add_main_address(
@@ -552,20 +684,22 @@ pub(super) mod migrations {
                     "9gD9khJaxi3SvcX9VVPQ3vnV3xUTonVQe3Fvg5X7cGGbXMRgd8i",
                 ),
             )
-            .await;
+            .await?;
 
             // Get new store height from last supply record
-            let post_mig_height = supply::get_latest(pgtx).await.map(|r| r.height);
-
-            // Determine migration effect to return
-            if post_mig_height == pre_mig_height {
-                MigrationEffect::None
-            } else {
-                match post_mig_height {
-                    Some(h) => MigrationEffect::Trimmed(h),
-                    None => MigrationEffect::Reset,
-                }
-            }
+            let post_mig_height = supply::get_latest(pgtx).await?.map(|r| r.height);
+
+            Ok(
+                // Determine migration effect to return
+                if post_mig_height == pre_mig_height {
+                    MigrationEffect::None
+                } else {
+                    match post_mig_height {
+                        Some(h) => MigrationEffect::Trimmed(h),
+                        None => MigrationEffect::Reset,
+                    }
+                },
+            )
         }
     }
 
@@ -573,38 +707,42 @@ pub(super) mod migrations {
     ///
     /// Rolls back store to start of new address transactions.
     /// Returns height of first tx involving new main address.
-    async fn add_main_address(pgtx: &Transaction<'_>, record: MainAddressRecord) -> Option<Height> {
+    async fn add_main_address(
+        pgtx: &Transaction<'_>,
+        record: MainAddressRecord,
+    ) -> Result<Option<Height>, EwError> {
         tracing::trace!("add_main_address {record:?}");
 
         // Insert new main address
-        main_addresses::insert(pgtx, &record).await;
+        main_addresses::insert(pgtx, &record).await?;
 
         // Delete address from known deposit addresses,
         // just in case it got falsely registered as such.
-        deposit_addresses::delete_one(pgtx, record.address_id).await;
+        deposit_addresses::delete_one(pgtx, record.address_id).await?;
 
         // Determine how far data needs to be rolled back by getting
         // height of first tx involving the new main address.
-        let first_tx_height = get_first_tx_height(pgtx, record.address_id).await;
+        let first_tx_height = get_first_tx_height(pgtx, record.address_id).await?;
 
         // Rollback supply if new address is already in use
         if let Some(h) = first_tx_height {
-            supply::delete_from(pgtx, h).await;
+            supply::delete_from(pgtx, h).await?;
         }
 
-        first_tx_height
+        Ok(first_tx_height)
     }
 
     /// Retrieve height of first tx involving given `address_id`.
     ///
     /// Reads upstream store (erg_diffs), which is guaranteed to be
     /// at same height, at least.
-    async fn get_first_tx_height(pgtx: &Transaction<'_>, address_id: AddressID) -> Option<Height> {
+    async fn get_first_tx_height(
+        pgtx: &Transaction<'_>,
+        address_id: AddressID,
+    ) -> Result<Option<Height>, EwError> {
         tracing::trace!("get_first_tx_height {address_id:?}");
         let sql = "select min(height) from erg.balance_diffs where address_id = $1;";
-        pgtx.query_one(sql, &[&address_id])
-            .await
-            .map(|row| row.get::<usize, Option<Height>>(0))
-            .unwrap()
+        let row = pgtx.query_one(sql, &[&address_id]).await?;
+        Ok(row.get::<usize, Option<Height>>(0))
     }
 }
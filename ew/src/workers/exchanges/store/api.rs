@@ -0,0 +1,22 @@
+//! Read-only queries served by the query API and evaluated by alert rules.
+use tokio_postgres::GenericClient;
+
+use super::super::types::SupplyRecord;
+use super::supply;
+use crate::core::types::Height;
+use crate::error::EwError;
+
+/// Supply on main and deposit addresses at the worker's height, if any.
+pub(crate) async fn get_supply(
+    client: &impl GenericClient,
+) -> Result<Option<SupplyRecord>, EwError> {
+    supply::get_latest(client).await
+}
+
+/// Supply on main and deposit addresses at given `height`, if any.
+pub(crate) async fn get_supply_at(
+    client: &impl GenericClient,
+    height: Height,
+) -> Result<Option<SupplyRecord>, EwError> {
+    supply::get_at(client, height).await
+}
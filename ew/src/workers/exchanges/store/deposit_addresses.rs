@@ -3,18 +3,21 @@ use std::collections::HashMap;
 use tokio_postgres::types::Type;
 use tokio_postgres::Client;
 use tokio_postgres::GenericClient;
-use tokio_postgres::Transaction;
 
 use super::super::types::DepositAddressRecord;
 use super::super::types::ExchangeID;
 use crate::core::types::AddressID;
 use crate::core::types::Height;
+use crate::error::EwError;
 
-pub(super) async fn insert_many(pgtx: &Transaction<'_>, records: &Vec<DepositAddressRecord>) {
+pub(super) async fn insert_many(
+    pgtx: &impl GenericClient,
+    records: &Vec<DepositAddressRecord>,
+) -> Result<(), EwError> {
     tracing::trace!("insert_many {records:?}");
     if records.is_empty() {
         // Nothing to do, return.
-        return;
+        return Ok(());
     }
     let sql = "
         insert into exchanges.deposit_addresses (address_id, cex_id, spot_height)
@@ -22,33 +25,36 @@ pub(super) async fn insert_many(pgtx: &Transaction<'_>, records: &Vec<DepositAdd
     ";
     let stmt = pgtx
         .prepare_typed(sql, &[Type::INT8, Type::INT4, Type::INT4])
-        .await
-        .unwrap();
+        .await?;
     for rec in records {
         pgtx.execute(&stmt, &[&rec.address_id, &rec.cex_id, &rec.spot_height])
-            .await
-            .unwrap();
+            .await?;
     }
+    Ok(())
 }
 
-pub(super) async fn map_all(client: &impl GenericClient) -> HashMap<AddressID, ExchangeID> {
+pub(super) async fn map_all(
+    client: &impl GenericClient,
+) -> Result<HashMap<AddressID, ExchangeID>, EwError> {
     tracing::trace!("map_all");
     let qry = "
         select address_id
             , cex_id
         from exchanges.deposit_addresses;
     ";
-    HashMap::from_iter(
+    Ok(HashMap::from_iter(
         client
             .query(qry, &[])
-            .await
-            .unwrap()
+            .await?
             .into_iter()
             .map(|r| (r.get(0), r.get(1))),
-    )
+    ))
 }
 
-pub(super) async fn get_one(pgtx: &Transaction<'_>, address_id: AddressID) -> DepositAddressRecord {
+pub(super) async fn get_one(
+    pgtx: &impl GenericClient,
+    address_id: AddressID,
+) -> Result<DepositAddressRecord, EwError> {
     tracing::trace!("get_one {address_id:?}");
     let qry = "
         select address_id
@@ -57,35 +63,76 @@ pub(super) async fn get_one(pgtx: &Transaction<'_>, address_id: AddressID) -> De
         from exchanges.deposit_addresses
         where address_id = $1;
     ";
-    DepositAddressRecord::from_row(&pgtx.query_one(qry, &[&address_id]).await.unwrap())
+    Ok(DepositAddressRecord::from_row(
+        &pgtx.query_one(qry, &[&address_id]).await?,
+    ))
 }
 
-pub(super) async fn get_spotted_at(client: &Client, height: Height) -> Vec<AddressID> {
+pub(super) async fn get_spotted_at(
+    client: &Client,
+    height: Height,
+) -> Result<Vec<AddressID>, EwError> {
     tracing::trace!("get_spotted_at {height}");
     let qry = "
         select address_id
         from exchanges.deposit_addresses
         where spot_height = $1;
     ";
-    let rows = client.query(qry, &[&height]).await.unwrap();
-    rows.iter().map(|r| r.get(0)).collect()
+    let rows = client.query(qry, &[&height]).await?;
+    Ok(rows.iter().map(|r| r.get(0)).collect())
 }
 
-pub(super) async fn delete_spotted_at(pgtx: &Transaction<'_>, height: Height) {
+pub(super) async fn delete_spotted_at(
+    pgtx: &impl GenericClient,
+    height: Height,
+) -> Result<(), EwError> {
     tracing::trace!("delete_spotted_at {height}");
     let sql = "
         delete from exchanges.deposit_addresses
         where spot_height = $1;
     ";
-    pgtx.query(sql, &[&height]).await.unwrap();
+    pgtx.query(sql, &[&height]).await?;
+    Ok(())
+}
+
+/// Returns addresses spotted after given `height`
+pub(super) async fn get_spotted_after(
+    client: &impl GenericClient,
+    height: Height,
+) -> Result<Vec<AddressID>, EwError> {
+    tracing::trace!("get_spotted_after {height}");
+    let qry = "
+        select address_id
+        from exchanges.deposit_addresses
+        where spot_height > $1;
+    ";
+    let rows = client.query(qry, &[&height]).await?;
+    Ok(rows.iter().map(|r| r.get(0)).collect())
+}
+
+/// Deletes addresses spotted after given `height`
+pub(super) async fn delete_spotted_after(
+    pgtx: &impl GenericClient,
+    height: Height,
+) -> Result<(), EwError> {
+    tracing::trace!("delete_spotted_after {height}");
+    let sql = "
+        delete from exchanges.deposit_addresses
+        where spot_height > $1;
+    ";
+    pgtx.execute(sql, &[&height]).await?;
+    Ok(())
 }
 
-pub(super) async fn delete_one(pgtx: &Transaction<'_>, address_id: AddressID) {
+pub(super) async fn delete_one(
+    pgtx: &impl GenericClient,
+    address_id: AddressID,
+) -> Result<(), EwError> {
     tracing::trace!("delete_one {address_id:?}");
     pgtx.execute(
         "delete from exchanges.deposit_addresses where address_id = $1",
         &[&address_id],
     )
-    .await
-    .unwrap();
+    .await?;
+    Ok(())
 }
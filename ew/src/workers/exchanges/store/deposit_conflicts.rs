@@ -1,13 +1,16 @@
 use std::collections::HashMap;
 use tokio_postgres::GenericClient;
-use tokio_postgres::Transaction;
 
 use super::super::types::DepositAddressConflictRecord;
 use crate::core::types::AddressID;
 use crate::core::types::Height;
+use crate::error::EwError;
 use crate::workers::exchanges::types::ExchangeID;
 
-pub(super) async fn insert(pgtx: &Transaction<'_>, record: &DepositAddressConflictRecord) {
+pub(super) async fn insert(
+    pgtx: &impl GenericClient,
+    record: &DepositAddressConflictRecord,
+) -> Result<(), EwError> {
     tracing::trace!("insert {record:?}");
     let sql = "
         insert into exchanges.deposit_addresses_excluded (
@@ -27,32 +30,33 @@ pub(super) async fn insert(pgtx: &Transaction<'_>, record: &DepositAddressConfli
             &record.conflict_spot_height,
         ],
     )
-    .await
-    .unwrap();
+    .await?;
+    Ok(())
 }
 
-pub(super) async fn map_all(client: &impl GenericClient) -> HashMap<AddressID, Option<ExchangeID>> {
+pub(super) async fn map_all(
+    client: &impl GenericClient,
+) -> Result<HashMap<AddressID, Option<ExchangeID>>, EwError> {
     tracing::trace!("get_all");
     let qry = "
         select address_id
             , first_cex_id
         from exchanges.deposit_addresses_excluded;
     ";
-    HashMap::from_iter(
+    Ok(HashMap::from_iter(
         client
             .query(qry, &[])
-            .await
-            .unwrap()
+            .await?
             .into_iter()
             .map(|r| (r.get(0), r.get(1))),
-    )
+    ))
 }
 
 /// Returns records for which a conflict was spotted at given `h`
 pub(super) async fn get_conflicted_at(
     client: &impl GenericClient,
     height: Height,
-) -> Vec<DepositAddressConflictRecord> {
+) -> Result<Vec<DepositAddressConflictRecord>, EwError> {
     tracing::trace!("get_conflicted_at {height}");
     let qry = "
         select address_id
@@ -62,10 +66,9 @@ pub(super) async fn get_conflicted_at(
         from exchanges.deposit_addresses_excluded
         where conflict_spot_height = $1;
     ";
-    client
+    Ok(client
         .query(qry, &[&height])
-        .await
-        .unwrap()
+        .await?
         .iter()
         .map(|r| DepositAddressConflictRecord {
             address_id: r.get(0),
@@ -73,15 +76,60 @@ pub(super) async fn get_conflicted_at(
             deposit_spot_height: r.get(2),
             conflict_spot_height: r.get(3),
         })
-        .collect()
+        .collect())
 }
 
 /// Deletes addresses for which a conflict was spotted at given `h`
-pub(super) async fn delete_conflicted_at(pgtx: &Transaction<'_>, height: Height) {
+pub(super) async fn delete_conflicted_at(
+    pgtx: &impl GenericClient,
+    height: Height,
+) -> Result<(), EwError> {
     tracing::trace!("delete_conflicted_at {height}");
     let sql = "
         delete from exchanges.deposit_addresses_excluded
         where conflict_spot_height = $1;
     ";
-    pgtx.query(sql, &[&height]).await.unwrap();
+    pgtx.query(sql, &[&height]).await?;
+    Ok(())
+}
+
+/// Returns records for which a conflict was spotted after given `h`
+pub(super) async fn get_conflicted_after(
+    client: &impl GenericClient,
+    height: Height,
+) -> Result<Vec<DepositAddressConflictRecord>, EwError> {
+    tracing::trace!("get_conflicted_after {height}");
+    let qry = "
+        select address_id
+            , first_cex_id
+            , deposit_spot_height
+            , conflict_spot_height
+        from exchanges.deposit_addresses_excluded
+        where conflict_spot_height > $1;
+    ";
+    Ok(client
+        .query(qry, &[&height])
+        .await?
+        .iter()
+        .map(|r| DepositAddressConflictRecord {
+            address_id: r.get(0),
+            first_cex_id: r.get(1),
+            deposit_spot_height: r.get(2),
+            conflict_spot_height: r.get(3),
+        })
+        .collect())
+}
+
+/// Deletes addresses for which a conflict was spotted after given `h`
+pub(super) async fn delete_conflicted_after(
+    pgtx: &impl GenericClient,
+    height: Height,
+) -> Result<(), EwError> {
+    tracing::trace!("delete_conflicted_after {height}");
+    let sql = "
+        delete from exchanges.deposit_addresses_excluded
+        where conflict_spot_height > $1;
+    ";
+    pgtx.execute(sql, &[&height]).await?;
+    Ok(())
 }
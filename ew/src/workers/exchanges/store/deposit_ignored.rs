@@ -2,19 +2,15 @@ use std::collections::HashSet;
 use tokio_postgres::GenericClient;
 
 use crate::core::types::AddressID;
+use crate::error::EwError;
 
-pub(super) async fn get_all(client: &impl GenericClient) -> HashSet<AddressID> {
+pub(super) async fn get_all(client: &impl GenericClient) -> Result<HashSet<AddressID>, EwError> {
     tracing::trace!("get_all");
     let qry = "
         select address_id
         from exchanges.deposit_addresses_ignored;
     ";
-    HashSet::from_iter(
-        client
-            .query(qry, &[])
-            .await
-            .unwrap()
-            .into_iter()
-            .map(|r| r.get(0)),
-    )
+    Ok(HashSet::from_iter(
+        client.query(qry, &[]).await?.into_iter().map(|r| r.get(0)),
+    ))
 }
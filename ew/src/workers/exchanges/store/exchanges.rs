@@ -2,7 +2,12 @@ use tokio_postgres::GenericClient;
 
 use super::super::types::ExchangeRecord;
 
-pub(super) async fn insert(client: &impl GenericClient, record: &ExchangeRecord) {
+use crate::error::EwError;
+
+pub(super) async fn insert(
+    client: &impl GenericClient,
+    record: &ExchangeRecord,
+) -> Result<(), EwError> {
     tracing::trace!("insert {record:?}");
     let sql = "
         insert into exchanges.exchanges (id, text_id, name)
@@ -10,6 +15,6 @@ pub(super) async fn insert(client: &impl GenericClient, record: &ExchangeRecord)
     ";
     client
         .execute(sql, &[&record.id, &record.text_id, &record.name])
-        .await
-        .unwrap();
+        .await?;
+    Ok(())
 }
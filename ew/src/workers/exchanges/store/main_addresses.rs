@@ -4,25 +4,30 @@ use tokio_postgres::GenericClient;
 use super::super::types::ExchangeID;
 use super::super::types::MainAddressRecord;
 use crate::core::types::AddressID;
+use crate::error::EwError;
 
-pub(super) async fn map_all(client: &impl GenericClient) -> HashMap<AddressID, ExchangeID> {
+pub(super) async fn map_all(
+    client: &impl GenericClient,
+) -> Result<HashMap<AddressID, ExchangeID>, EwError> {
     tracing::trace!("map_all");
     let qry = "
         select address_id
             , cex_id
         from exchanges.main_addresses;
     ";
-    HashMap::from_iter(
+    Ok(HashMap::from_iter(
         client
             .query(qry, &[])
-            .await
-            .unwrap()
+            .await?
             .into_iter()
             .map(|r| (r.get(0), r.get(1))),
-    )
+    ))
 }
 
-pub(super) async fn insert(client: &impl GenericClient, record: &MainAddressRecord) {
+pub(super) async fn insert(
+    client: &impl GenericClient,
+    record: &MainAddressRecord,
+) -> Result<(), EwError> {
     tracing::trace!("insert {record:?}");
     let sql = "
         insert into exchanges.main_addresses (address_id, cex_id, address)
@@ -30,6 +35,6 @@ pub(super) async fn insert(client: &impl GenericClient, record: &MainAddressReco
     ";
     client
         .execute(sql, &[&record.address_id, &record.cex_id, &record.address])
-        .await
-        .unwrap();
+        .await?;
+    Ok(())
 }
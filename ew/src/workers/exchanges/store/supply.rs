@@ -1,12 +1,14 @@
 use itertools::Itertools;
 use tokio_postgres::GenericClient;
-use tokio_postgres::Transaction;
 
 use super::super::types::SupplyDiff;
 use super::super::types::SupplyRecord;
 use crate::core::types::Height;
+use crate::error::EwError;
 
-pub(super) async fn get_latest(client: &impl GenericClient) -> Option<SupplyRecord> {
+pub(super) async fn get_latest(
+    client: &impl GenericClient,
+) -> Result<Option<SupplyRecord>, EwError> {
     tracing::trace!("get_latest");
     let qry = "
     select height
@@ -16,34 +18,64 @@ pub(super) async fn get_latest(client: &impl GenericClient) -> Option<SupplyReco
     order by 1 desc
     limit 1;
     ";
-    client.query_opt(qry, &[]).await.unwrap().and_then(|row| {
+    Ok(client.query_opt(qry, &[]).await?.and_then(|row| {
         Some(SupplyRecord {
             height: row.get(0),
             main: row.get(1),
             deposits: row.get(2),
         })
-    })
+    }))
 }
 
-pub(super) async fn insert(pgtx: &Transaction<'_>, record: &SupplyRecord) {
+/// Latest record at or before given `height`, if any.
+pub(super) async fn get_at(
+    client: &impl GenericClient,
+    height: Height,
+) -> Result<Option<SupplyRecord>, EwError> {
+    tracing::trace!("get_at {height}");
+    let qry = "
+    select height
+    , main
+    , deposits
+    from exchanges.supply
+    where height <= $1
+    order by 1 desc
+    limit 1;
+    ";
+    Ok(client
+        .query_opt(qry, &[&height])
+        .await?
+        .map(|row| SupplyRecord {
+            height: row.get(0),
+            main: row.get(1),
+            deposits: row.get(2),
+        }))
+}
+
+pub(super) async fn insert(
+    pgtx: &impl GenericClient,
+    record: &SupplyRecord,
+) -> Result<(), EwError> {
     tracing::trace!("insert {record:?}");
     let stmt = "insert into exchanges.supply (height, main, deposits) values ($1, $2, $3);";
     pgtx.execute(stmt, &[&record.height, &record.main, &record.deposits])
-        .await
-        .unwrap();
+        .await?;
+    Ok(())
 }
 
-pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: Height) {
+pub(super) async fn delete_at(pgtx: &impl GenericClient, height: Height) -> Result<(), EwError> {
     tracing::trace!("delete_at {height}");
     let sql = "delete from exchanges.supply where height = $1;";
-    pgtx.execute(sql, &[&height]).await.unwrap();
+    pgtx.execute(sql, &[&height]).await?;
+    Ok(())
 }
 
 /// Deletes all records at or avove goven `height`
-pub(super) async fn delete_from(pgtx: &Transaction<'_>, height: Height) {
+pub(super) async fn delete_from(pgtx: &impl GenericClient, height: Height) -> Result<(), EwError> {
     tracing::trace!("delete_from {height}");
     let sql = "delete from exchanges.supply where height >= $1;";
-    pgtx.execute(sql, &[&height]).await.unwrap();
+    pgtx.execute(sql, &[&height]).await?;
+    Ok(())
 }
 
 /*
@@ -93,7 +125,10 @@ pub(super) async fn delete_from(pgtx: &Transaction<'_>, height: Height) {
         ```
 */
 /// Patch deposits supply with given balance diffs series.
-pub(super) async fn patch_deposits(pgtx: &Transaction<'_>, patch: &Vec<SupplyDiff>) {
+pub(super) async fn patch_deposits(
+    pgtx: &impl GenericClient,
+    patch: &Vec<SupplyDiff>,
+) -> Result<(), EwError> {
     tracing::trace!("patch_deposits {patch:?}");
 
     let min_height = patch.iter().min_by_key(|sd| sd.height).unwrap().height;
@@ -112,5 +147,6 @@ pub(super) async fn patch_deposits(pgtx: &Transaction<'_>, patch: &Vec<SupplyDif
             left join (values {}) as p(h, v) on p.h = series_h
         ) p
         where s.height = p.height;", &string_patch);
-    pgtx.execute(&sql, &[&min_height]).await.unwrap();
+    pgtx.execute(&sql, &[&min_height]).await?;
+    Ok(())
 }
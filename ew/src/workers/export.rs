@@ -0,0 +1,164 @@
+//! Exports per-block outputs of a source as files.
+//!
+//! Rows of each block are appended to height-partitioned NDJSON files,
+//! converted to Parquet once their partition is complete. A manifest
+//! records the last exported header.
+//!
+//! Layout of an export directory:
+//!
+//! ```text
+//! <worker>/manifest.json                 last exported header
+//! <worker>/<table>/<first>-<last>.ndjson    rows of blocks `first` to `last`
+//! <worker>/<table>/<first>-<last>.parquet   same, once `last` is exported
+//! ```
+//!
+//! Exported blocks are tracked in the store header, like any other worker.
+//! Files are aligned to it on startup and after each rollback, dropping
+//! rows of blocks that are not part of it anymore.
+mod files;
+mod store;
+mod tables;
+
+pub(crate) use store::CORE_SCHEMA;
+pub(crate) use store::DIFFS_SCHEMA;
+pub use tables::Lookups;
+pub use tables::Table;
+
+use async_trait::async_trait;
+use std::marker::PhantomData;
+
+use crate::config::ExportConfig;
+use crate::config::PostgresConfig;
+use crate::core::types::AddressID;
+use crate::core::types::AssetID;
+use crate::core::types::CoreData;
+use crate::core::types::Header;
+use crate::core::types::Height;
+use crate::error::EwError;
+use crate::framework::store::PgMigrator;
+use crate::framework::store::StoreDef;
+use crate::framework::EventHandling;
+use crate::framework::LeafWorker;
+use crate::framework::StampedData;
+use crate::workers::erg_diffs::types::DiffData;
+use files::Exporter;
+use store::Store;
+
+/// Exports blocks, transactions and boxes of the tracker.
+pub type CoreWorker = LeafWorker<ExportWorkFlow<CoreData>>;
+
+/// Exports balance diffs of the erg_diffs worker.
+pub type DiffsWorker = LeafWorker<ExportWorkFlow<DiffData>>;
+
+/// Source data that can be exported.
+pub trait Exportable: Send + Sync + 'static {
+    /// Definition of the store tracking exported blocks.
+    fn store() -> &'static StoreDef;
+
+    /// Tables rows get exported to.
+    fn tables() -> &'static [Table];
+
+    /// Addresses referred to by the block's rows.
+    fn address_ids(&self) -> Vec<AddressID>;
+
+    /// Assets referred to by the block's rows.
+    fn asset_ids(&self) -> Vec<AssetID> {
+        vec![]
+    }
+
+    /// Rows of the block at given `height`, as JSON lines, for each table.
+    fn rows(&self, height: Height, lookups: &Lookups) -> Vec<(Table, Vec<String>)>;
+}
+
+/// Initializes the export stores and applies pending migrations.
+pub async fn migrate(pgconf: &PostgresConfig, store: &'static StoreDef) -> Result<(), EwError> {
+    PgMigrator::new(pgconf, store).await?.apply_pending().await
+}
+
+pub struct ExportWorkFlow<T: Exportable> {
+    /// Absent when no export directory is configured
+    exporter: Option<Exporter>,
+    store: Store,
+    _source: PhantomData<T>,
+}
+
+impl<T: Exportable> ExportWorkFlow<T> {
+    pub async fn with_config(
+        pgconf: &PostgresConfig,
+        config: &ExportConfig,
+    ) -> Result<Self, EwError> {
+        // Ensure migrations are applied
+        migrate(pgconf, T::store()).await?;
+
+        let store = Store::new(pgconf, T::store()).await?;
+        let exporter = match &config.dir {
+            Some(dir) => {
+                let exporter = Exporter::new(
+                    dir.join(T::store().worker_id),
+                    T::tables(),
+                    config.partition_size,
+                    config.parquet,
+                );
+                exporter.open(store.get_header()).await?;
+                Some(exporter)
+            }
+            None => None,
+        };
+        Ok(Self {
+            exporter,
+            store,
+            _source: PhantomData,
+        })
+    }
+}
+
+#[async_trait]
+impl<T: Exportable> EventHandling for ExportWorkFlow<T> {
+    type U = T;
+    type D = ();
+
+    /// Create and initialize a new event handling workflow.
+    ///
+    /// Has no export directory, so only suitable for handling rollbacks,
+    /// files being aligned next time the worker starts. See `with_config`
+    /// otherwise.
+    async fn new(pgconf: &PostgresConfig) -> Result<Self, EwError> {
+        Self::with_config(pgconf, &ExportConfig::default()).await
+    }
+
+    async fn include_block(&mut self, data: &StampedData<T>) -> Result<(), EwError> {
+        if let Some(exporter) = &self.exporter {
+            let lookups = store::get_lookups(
+                self.store.get_client(),
+                &data.data.address_ids(),
+                &data.data.asset_ids(),
+            )
+            .await?;
+            exporter
+                .append(&data.data.rows(data.height, &lookups))
+                .await?;
+        }
+        // Files are ahead of the store until committed
+        self.store.persist(&data.wrap(())).await?;
+        if let Some(exporter) = &self.exporter {
+            exporter.commit(self.store.get_header()).await?;
+        }
+        Ok(())
+    }
+
+    async fn roll_back(&mut self, height: Height) -> Result<Header, EwError> {
+        self.store.roll_back(height).await?;
+        if let Some(exporter) = &self.exporter {
+            exporter.align(self.store.get_header()).await?;
+        }
+        Ok(self.store.get_header().clone())
+    }
+
+    fn header(&self) -> &Header {
+        self.store.get_header()
+    }
+
+    async fn flush(&mut self) -> Result<(), EwError> {
+        self.store.flush().await
+    }
+}
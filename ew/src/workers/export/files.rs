@@ -0,0 +1,317 @@
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde::Deserialize;
+use serde::Serialize;
+use std::io::BufReader;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+
+use super::tables::Table;
+use crate::core::types::Header;
+use crate::core::types::Height;
+use crate::core::types::Timestamp;
+use crate::error::EwError;
+
+/// Last exported header and layout of an export directory.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Manifest {
+    height: Height,
+    header_id: String,
+    parent_id: String,
+    timestamp: Timestamp,
+    /// Number of blocks per partition
+    partition_size: Height,
+    tables: Vec<String>,
+}
+
+/// Only field needed to align rows to a header.
+#[derive(Deserialize)]
+struct Row {
+    height: Height,
+}
+
+/// Height-partitioned files of a worker's tables.
+pub(super) struct Exporter {
+    dir: PathBuf,
+    tables: &'static [Table],
+    partition_size: Height,
+    parquet: bool,
+}
+
+impl Exporter {
+    pub fn new(
+        dir: PathBuf,
+        tables: &'static [Table],
+        partition_size: Height,
+        parquet: bool,
+    ) -> Self {
+        Self {
+            dir,
+            tables,
+            partition_size,
+            parquet,
+        }
+    }
+
+    /// Prepares the export directory and aligns its files to given `header`.
+    ///
+    /// Fails if existing files were exported with another partition size.
+    pub async fn open(&self, header: &Header) -> Result<(), EwError> {
+        let path = self.manifest_path();
+        match tokio::fs::read_to_string(&path).await {
+            Ok(json) => {
+                let manifest: Manifest = serde_json::from_str(&json).map_err(|e| {
+                    EwError::Export(format!("invalid manifest {}: {e}", path.display()))
+                })?;
+                if manifest.partition_size != self.partition_size {
+                    return Err(EwError::Export(format!(
+                        "{} was exported with partitions of {} blocks, got {}",
+                        self.dir.display(),
+                        manifest.partition_size,
+                        self.partition_size
+                    )));
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+            Err(e) => return Err(io_error(&path, e)),
+        }
+        for table in self.tables {
+            let dir = self.dir.join(table.name());
+            tokio::fs::create_dir_all(&dir)
+                .await
+                .map_err(|e| io_error(&dir, e))?;
+        }
+        self.align(header).await
+    }
+
+    /// Appends rows of a block to the NDJSON files of its partition.
+    pub async fn append(&self, rows: &[(Table, Vec<String>)]) -> Result<(), EwError> {
+        for (table, lines) in rows {
+            let Some(first) = lines.first() else {
+                continue;
+            };
+            let height = row_height(first)?;
+            let path = self.path(*table, height, "ndjson");
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .await
+                .map_err(|e| io_error(&path, e))?;
+            let mut content = lines.join("\n");
+            content.push('\n');
+            file.write_all(content.as_bytes())
+                .await
+                .map_err(|e| io_error(&path, e))?;
+            file.flush().await.map_err(|e| io_error(&path, e))?;
+        }
+        Ok(())
+    }
+
+    /// Records `header` as exported, completing its partition if last of it.
+    pub async fn commit(&self, header: &Header) -> Result<(), EwError> {
+        if self.parquet && self.last_of_partition(header.height) == header.height {
+            for table in self.tables {
+                let path = self.path(*table, header.height, "ndjson");
+                if path.exists() {
+                    write_parquet(*table, &path).await?;
+                }
+            }
+        }
+        self.write_manifest(header).await
+    }
+
+    /// Aligns files to given `header`, dropping rows of later blocks.
+    ///
+    /// The partition holding later blocks gets rewritten without them, and
+    /// loses its Parquet files. Completed partitions missing Parquet files
+    /// get converted.
+    pub async fn align(&self, header: &Header) -> Result<(), EwError> {
+        let height = header.height;
+        for table in self.tables {
+            let dir = self.dir.join(table.name());
+            for path in list_dir(&dir).await? {
+                let Some((first, last)) = parse_partition(&path) else {
+                    continue;
+                };
+                let ext = path.extension().and_then(|e| e.to_str());
+                match ext {
+                    _ if first > height => remove(&path).await?,
+                    Some("ndjson") if last > height => truncate(&path, height).await?,
+                    Some("ndjson") if self.parquet && !path.with_extension("parquet").exists() => {
+                        write_parquet(*table, &path).await?
+                    }
+                    Some("parquet") if last > height => remove(&path).await?,
+                    Some("tmp") => remove(&path).await?,
+                    _ => (),
+                }
+            }
+        }
+        self.write_manifest(header).await
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        self.dir.join("manifest.json")
+    }
+
+    async fn write_manifest(&self, header: &Header) -> Result<(), EwError> {
+        let manifest = Manifest {
+            height: header.height,
+            header_id: header.header_id.clone(),
+            parent_id: header.parent_id.clone(),
+            timestamp: header.timestamp,
+            partition_size: self.partition_size,
+            tables: self.tables.iter().map(|t| t.name().to_owned()).collect(),
+        };
+        let json = serde_json::to_string_pretty(&manifest).expect("manifest is serializable");
+        write(&self.manifest_path(), json.as_bytes()).await
+    }
+
+    fn last_of_partition(&self, height: Height) -> Height {
+        (height / self.partition_size + 1) * self.partition_size - 1
+    }
+
+    /// Path of file holding rows of given `table` at `height`.
+    fn path(&self, table: Table, height: Height, ext: &str) -> PathBuf {
+        let first = height / self.partition_size * self.partition_size;
+        let last = self.last_of_partition(height);
+        self.dir
+            .join(table.name())
+            .join(format!("{first:08}-{last:08}.{ext}"))
+    }
+}
+
+/// First and last height of the partition held by file at `path`.
+fn parse_partition(path: &Path) -> Option<(Height, Height)> {
+    let stem = path.file_stem()?.to_str()?;
+    let (first, last) = stem.split_once('-')?;
+    Some((first.parse().ok()?, last.parse().ok()?))
+}
+
+fn row_height(line: &str) -> Result<Height, EwError> {
+    serde_json::from_str::<Row>(line)
+        .map(|row| row.height)
+        .map_err(|e| EwError::Export(format!("invalid row `{line}`: {e}")))
+}
+
+async fn list_dir(dir: &Path) -> Result<Vec<PathBuf>, EwError> {
+    let mut entries = tokio::fs::read_dir(dir)
+        .await
+        .map_err(|e| io_error(dir, e))?;
+    let mut paths = vec![];
+    while let Some(entry) = entries.next_entry().await.map_err(|e| io_error(dir, e))? {
+        paths.push(entry.path());
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Rewrites NDJSON file at `path` without rows above given `height`, if any.
+///
+/// Removes the file if no rows are left.
+async fn truncate(path: &Path, height: Height) -> Result<(), EwError> {
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| io_error(path, e))?;
+    let mut kept = String::new();
+    for line in content.lines() {
+        // Rows are in height order
+        if row_height(line)? > height {
+            break;
+        }
+        kept.push_str(line);
+        kept.push('\n');
+    }
+    if kept.len() == content.len() {
+        return Ok(());
+    }
+    tracing::debug!("dropping rows above {height} from {}", path.display());
+    match kept.is_empty() {
+        true => remove(path).await,
+        false => write(path, kept.as_bytes()).await,
+    }
+}
+
+/// Converts NDJSON file at `path` to a Parquet file next to it.
+async fn write_parquet(table: Table, path: &Path) -> Result<(), EwError> {
+    tracing::debug!("converting {} to parquet", path.display());
+    let src = path.to_path_buf();
+    let dst = path.with_extension("parquet");
+    tokio::task::spawn_blocking(move || {
+        let schema = Arc::new(table.schema());
+        let tmp = dst.with_extension("tmp");
+        let file = std::fs::File::open(&src).map_err(|e| io_error(&src, e))?;
+        let reader = arrow_json::ReaderBuilder::new(schema.clone())
+            .build(BufReader::new(file))
+            .map_err(|e| parquet_error(&src, e))?;
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let out = std::fs::File::create(&tmp).map_err(|e| io_error(&tmp, e))?;
+        let mut writer =
+            ArrowWriter::try_new(out, schema, Some(props)).map_err(|e| parquet_error(&tmp, e))?;
+        for batch in reader {
+            let batch = batch.map_err(|e| parquet_error(&src, e))?;
+            writer.write(&batch).map_err(|e| parquet_error(&tmp, e))?;
+        }
+        writer.close().map_err(|e| parquet_error(&tmp, e))?;
+        std::fs::rename(&tmp, &dst).map_err(|e| io_error(&dst, e))
+    })
+    .await
+    .expect("parquet conversion does not panic")
+}
+
+/// Writes `contents` to `path`, through a temporary file so that an
+/// interrupted write never leaves a partial file behind.
+async fn write(path: &Path, contents: &[u8]) -> Result<(), EwError> {
+    let tmp = path.with_extension("tmp");
+    tokio::fs::write(&tmp, contents)
+        .await
+        .map_err(|e| io_error(&tmp, e))?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .map_err(|e| io_error(path, e))
+}
+
+async fn remove(path: &Path) -> Result<(), EwError> {
+    tracing::debug!("removing {}", path.display());
+    tokio::fs::remove_file(path)
+        .await
+        .map_err(|e| io_error(path, e))
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> EwError {
+    EwError::Export(format!("{}: {e}", path.display()))
+}
+
+fn parquet_error(path: &Path, e: impl std::fmt::Display) -> EwError {
+    EwError::Export(format!("{}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_partition_paths() {
+        let exporter = Exporter::new(PathBuf::from("x"), &[Table::Blocks], 1000, true);
+        assert_eq!(
+            exporter.path(Table::Blocks, 0, "ndjson"),
+            PathBuf::from("x/blocks/00000000-00000999.ndjson")
+        );
+        assert_eq!(
+            exporter.path(Table::Blocks, 1999, "parquet"),
+            PathBuf::from("x/blocks/00001000-00001999.parquet")
+        );
+        assert_eq!(exporter.last_of_partition(1000), 1999);
+        assert_eq!(
+            parse_partition(&exporter.path(Table::Blocks, 1500, "ndjson")),
+            Some((1000, 1999))
+        );
+        assert_eq!(parse_partition(Path::new("x/manifest.json")), None);
+    }
+}
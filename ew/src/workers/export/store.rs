@@ -0,0 +1,96 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use tokio_postgres::Client;
+
+use super::tables::Lookups;
+use crate::core::types::AddressID;
+use crate::core::types::AssetID;
+use crate::core::types::Header;
+use crate::error::EwError;
+use crate::framework::store::BatchStore;
+use crate::framework::store::PgStore;
+use crate::framework::store::Revision;
+use crate::framework::store::StoreDef;
+use crate::framework::store::StoreTransaction;
+use crate::framework::StampedData;
+
+/// Blocks exported from the tracker.
+pub(crate) const CORE_SCHEMA: StoreDef = StoreDef {
+    schema_name: "export",
+    worker_id: "export_core",
+    sql: include_str!("store/schema.sql"),
+    revision: &Revision { major: 1, minor: 0 },
+    migrations: &[],
+    has_header: true,
+};
+
+/// Blocks exported from erg_diffs.
+pub(crate) const DIFFS_SCHEMA: StoreDef = StoreDef {
+    schema_name: "export",
+    worker_id: "export_diffs",
+    sql: include_str!("store/schema.sql"),
+    revision: &Revision { major: 1, minor: 0 },
+    migrations: &[],
+    has_header: true,
+};
+
+/// Exported data lives in files, so only the header is stored.
+pub(super) struct InnerStore {}
+
+pub(super) type Store = PgStore<InnerStore>;
+
+#[async_trait]
+impl BatchStore for InnerStore {
+    type B = ();
+
+    async fn new() -> Self {
+        Self {}
+    }
+
+    async fn persist(
+        &mut self,
+        _pgtx: &StoreTransaction,
+        _stamped_batch: &StampedData<Self::B>,
+    ) -> Result<(), EwError> {
+        Ok(())
+    }
+
+    async fn roll_back(
+        &mut self,
+        _pgtx: &StoreTransaction,
+        _header: &Header,
+    ) -> Result<(), EwError> {
+        Ok(())
+    }
+}
+
+/// Resolves given address and asset ids from core tables.
+pub(super) async fn get_lookups(
+    client: &Client,
+    address_ids: &[AddressID],
+    asset_ids: &[AssetID],
+) -> Result<Lookups, EwError> {
+    let ids: Vec<i64> = address_ids.iter().map(|a| a.0).collect();
+    let addresses: HashMap<AddressID, String> = client
+        .query(
+            "select id, address from core.addresses where id = any($1);",
+            &[&ids],
+        )
+        .await?
+        .iter()
+        .map(|row| (AddressID(row.get(0)), row.get(1)))
+        .collect();
+    let tokens: HashMap<AssetID, String> = match asset_ids.is_empty() {
+        true => HashMap::new(),
+        false => client
+            .query(
+                "select asset_id, token_id from core.tokens where asset_id = any($1);",
+                &[&asset_ids],
+            )
+            .await?
+            .iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect(),
+    };
+    Ok(Lookups { addresses, tokens })
+}
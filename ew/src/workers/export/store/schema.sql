@@ -0,0 +1,2 @@
+create schema if not exists export;
+comment on schema export is 'Headers of blocks exported as files';
@@ -0,0 +1,373 @@
+use arrow_schema::DataType;
+use arrow_schema::Field;
+use arrow_schema::Fields;
+use arrow_schema::Schema;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::Exportable;
+use crate::core::types::AddressID;
+use crate::core::types::AssetID;
+use crate::core::types::BoxData;
+use crate::core::types::CoreData;
+use crate::core::types::Height;
+use crate::framework::store::StoreDef;
+use crate::workers::erg_diffs::types::DiffData;
+
+/// Exported tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Table {
+    Blocks,
+    Transactions,
+    Boxes,
+    BalanceDiffs,
+}
+
+impl Table {
+    /// Name of the table's directory.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Table::Blocks => "blocks",
+            Table::Transactions => "transactions",
+            Table::Boxes => "boxes",
+            Table::BalanceDiffs => "balance_diffs",
+        }
+    }
+
+    /// Arrow schema of the table's rows, used for Parquet files.
+    ///
+    /// Fields are in the order rows are serialized in.
+    pub fn schema(&self) -> Schema {
+        let ids = || DataType::List(Arc::new(Field::new("item", DataType::Utf8, true)));
+        match self {
+            Table::Blocks => Schema::new(vec![
+                Field::new("height", DataType::Int32, false),
+                Field::new("header_id", DataType::Utf8, false),
+                Field::new("parent_id", DataType::Utf8, false),
+                Field::new("timestamp", DataType::Int64, false),
+                Field::new("difficulty", DataType::Utf8, false),
+                Field::new("version", DataType::UInt8, false),
+                Field::new("size", DataType::Int32, false),
+                Field::new("transactions", DataType::Int32, false),
+            ]),
+            Table::Transactions => Schema::new(vec![
+                Field::new("height", DataType::Int32, false),
+                Field::new("tx_id", DataType::Utf8, false),
+                Field::new("index", DataType::Int32, false),
+                Field::new("inputs", ids(), false),
+                Field::new("data_inputs", ids(), false),
+                Field::new("outputs", ids(), false),
+            ]),
+            Table::Boxes => Schema::new(vec![
+                Field::new("height", DataType::Int32, false),
+                Field::new("tx_id", DataType::Utf8, false),
+                Field::new("output_index", DataType::Int32, false),
+                Field::new("box_id", DataType::Utf8, false),
+                Field::new("creation_height", DataType::Int32, false),
+                Field::new("address", DataType::Utf8, false),
+                Field::new("value", DataType::Int64, false),
+                Field::new("size", DataType::Int32, false),
+                Field::new(
+                    "assets",
+                    DataType::List(Arc::new(Field::new(
+                        "item",
+                        DataType::Struct(Fields::from(vec![
+                            Field::new("token_id", DataType::Utf8, false),
+                            Field::new("amount", DataType::Int64, false),
+                        ])),
+                        true,
+                    ))),
+                    false,
+                ),
+                Field::new("registers", DataType::Utf8, false),
+            ]),
+            Table::BalanceDiffs => Schema::new(vec![
+                Field::new("height", DataType::Int32, false),
+                Field::new("tx_index", DataType::Int16, false),
+                Field::new("address", DataType::Utf8, false),
+                Field::new("nano", DataType::Int64, false),
+            ]),
+        }
+    }
+}
+
+/// Addresses and token ids of a block's rows.
+pub struct Lookups {
+    pub(super) addresses: HashMap<AddressID, String>,
+    pub(super) tokens: HashMap<AssetID, String>,
+}
+
+impl Lookups {
+    pub fn address(&self, address_id: AddressID) -> &str {
+        self.addresses
+            .get(&address_id)
+            .expect("address is in core.addresses")
+    }
+
+    pub fn token_id(&self, asset_id: AssetID) -> &str {
+        self.tokens.get(&asset_id).expect("asset is in core.tokens")
+    }
+}
+
+#[derive(Serialize)]
+struct BlockRow<'a> {
+    height: Height,
+    header_id: &'a str,
+    parent_id: &'a str,
+    timestamp: i64,
+    difficulty: &'a str,
+    version: u8,
+    size: i32,
+    transactions: i32,
+}
+
+#[derive(Serialize)]
+struct TransactionRow<'a> {
+    height: Height,
+    tx_id: &'a str,
+    index: i32,
+    inputs: Vec<&'a str>,
+    data_inputs: Vec<&'a str>,
+    outputs: Vec<&'a str>,
+}
+
+#[derive(Serialize)]
+struct BoxRow<'a> {
+    height: Height,
+    tx_id: &'a str,
+    output_index: i32,
+    box_id: &'a str,
+    creation_height: Height,
+    address: &'a str,
+    value: i64,
+    size: i32,
+    assets: Vec<AssetRow<'a>>,
+    /// Registers JSON, as a string
+    registers: String,
+}
+
+#[derive(Serialize)]
+struct AssetRow<'a> {
+    token_id: &'a str,
+    amount: i64,
+}
+
+#[derive(Serialize)]
+struct BalanceDiffRow<'a> {
+    height: Height,
+    tx_index: i16,
+    address: &'a str,
+    nano: i64,
+}
+
+fn to_line(row: &impl Serialize) -> String {
+    serde_json::to_string(row).expect("rows are serializable")
+}
+
+fn box_ids(boxes: &[BoxData]) -> Vec<&str> {
+    boxes.iter().map(|bx| bx.box_id.as_str()).collect()
+}
+
+impl Exportable for CoreData {
+    fn store() -> &'static StoreDef {
+        &super::CORE_SCHEMA
+    }
+
+    fn tables() -> &'static [Table] {
+        &[Table::Blocks, Table::Transactions, Table::Boxes]
+    }
+
+    fn address_ids(&self) -> Vec<AddressID> {
+        let mut address_ids: Vec<AddressID> = self
+            .block
+            .transactions
+            .iter()
+            .flat_map(|tx| tx.outputs.iter().map(|bx| bx.address_id))
+            .collect();
+        address_ids.sort_by_key(|a| a.0);
+        address_ids.dedup();
+        address_ids
+    }
+
+    fn asset_ids(&self) -> Vec<AssetID> {
+        let mut asset_ids: Vec<AssetID> = self
+            .block
+            .transactions
+            .iter()
+            .flat_map(|tx| tx.outputs.iter())
+            .flat_map(|bx| bx.assets.iter().map(|a| a.asset_id))
+            .collect();
+        asset_ids.sort();
+        asset_ids.dedup();
+        asset_ids
+    }
+
+    fn rows(&self, height: Height, lookups: &Lookups) -> Vec<(Table, Vec<String>)> {
+        let block = &self.block;
+        let blocks = vec![to_line(&BlockRow {
+            height,
+            header_id: &block.header.id,
+            parent_id: &block.header.parent_id,
+            timestamp: block.header.timestamp,
+            difficulty: &block.header.difficulty,
+            version: block.header.version,
+            size: block.size,
+            transactions: block.transactions.len() as i32,
+        })];
+        let mut transactions = vec![];
+        let mut boxes = vec![];
+        for tx in &block.transactions {
+            transactions.push(to_line(&TransactionRow {
+                height,
+                tx_id: &tx.id,
+                index: tx.index,
+                inputs: box_ids(&tx.inputs),
+                data_inputs: box_ids(&tx.data_inputs),
+                outputs: box_ids(&tx.outputs),
+            }));
+            for (output_index, bx) in tx.outputs.iter().enumerate() {
+                boxes.push(to_line(&BoxRow {
+                    height,
+                    tx_id: &tx.id,
+                    output_index: output_index as i32,
+                    box_id: &bx.box_id,
+                    creation_height: bx.creation_height,
+                    address: lookups.address(bx.address_id),
+                    value: bx.value,
+                    size: bx.size,
+                    assets: bx
+                        .assets
+                        .iter()
+                        .map(|a| AssetRow {
+                            token_id: lookups.token_id(a.asset_id),
+                            amount: a.amount,
+                        })
+                        .collect(),
+                    registers: to_line(&bx.additional_registers),
+                }));
+            }
+        }
+        vec![
+            (Table::Blocks, blocks),
+            (Table::Transactions, transactions),
+            (Table::Boxes, boxes),
+        ]
+    }
+}
+
+impl Exportable for DiffData {
+    fn store() -> &'static StoreDef {
+        &super::DIFFS_SCHEMA
+    }
+
+    fn tables() -> &'static [Table] {
+        &[Table::BalanceDiffs]
+    }
+
+    fn address_ids(&self) -> Vec<AddressID> {
+        self.diffed_addresses()
+    }
+
+    fn rows(&self, height: Height, lookups: &Lookups) -> Vec<(Table, Vec<String>)> {
+        let diffs = self
+            .diff_records
+            .iter()
+            .map(|r| {
+                to_line(&BalanceDiffRow {
+                    height,
+                    tx_index: r.tx_idx,
+                    address: lookups.address(r.address_id),
+                    nano: r.nano,
+                })
+            })
+            .collect();
+        vec![(Table::BalanceDiffs, diffs)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::types::Block;
+    use crate::core::types::Transaction;
+    use crate::workers::erg_diffs::types::DiffRecord;
+    use pretty_assertions::assert_eq;
+    use serde_json::Value;
+
+    fn lookups() -> Lookups {
+        Lookups {
+            addresses: HashMap::from([
+                (AddressID(11), "addressA".to_owned()),
+                (AddressID(21), "addressB".to_owned()),
+            ]),
+            tokens: HashMap::from([(5, "tokenA".to_owned())]),
+        }
+    }
+
+    /// Field names of a JSON line, in order.
+    fn keys(line: &str) -> Vec<String> {
+        let map: serde_json::Map<String, Value> = serde_json::from_str(line).unwrap();
+        // Maps are sorted, so order keys by position in the line
+        let mut keys: Vec<&String> = map.keys().collect();
+        keys.sort_by_key(|k| line.find(&format!("\"{k}\":")).unwrap());
+        keys.into_iter().cloned().collect()
+    }
+
+    fn field_names(table: Table) -> Vec<String> {
+        table
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_core_rows_match_schemas() {
+        let tx = Transaction::dummy()
+            .add_input(BoxData::dummy().address_id(AddressID(11)).value(5000))
+            .add_output(
+                BoxData::dummy()
+                    .address_id(AddressID(21))
+                    .value(5000)
+                    .add_asset(5, 100),
+            );
+        let block = Block::dummy().height(10).add_tx(tx.clone());
+        let data = CoreData { block };
+        assert_eq!(data.address_ids(), vec![AddressID(21)]);
+        assert_eq!(data.asset_ids(), vec![5]);
+        let rows = data.rows(10, &lookups());
+        assert_eq!(rows.len(), 3);
+        for (table, lines) in &rows {
+            assert_eq!(lines.len(), 1, "{table:?}");
+            assert_eq!(keys(&lines[0]), field_names(*table), "{table:?}");
+        }
+        let bx: Value = serde_json::from_str(&rows[2].1[0]).unwrap();
+        assert_eq!(bx["address"], "addressB");
+        assert_eq!(bx["tx_id"], Value::String(tx.id));
+        assert_eq!(
+            bx["assets"],
+            serde_json::json!([{"token_id": "tokenA", "amount": 100}])
+        );
+    }
+
+    #[test]
+    fn test_diff_rows_match_schema() {
+        let data = DiffData {
+            diff_records: vec![DiffRecord {
+                address_id: AddressID(11),
+                height: 10,
+                tx_idx: 0,
+                nano: -5000,
+            }],
+        };
+        let rows = data.rows(10, &lookups());
+        assert_eq!(rows[0].0, Table::BalanceDiffs);
+        assert_eq!(
+            rows[0].1,
+            vec![r#"{"height":10,"tx_index":0,"address":"addressA","nano":-5000}"#]
+        );
+        assert_eq!(keys(&rows[0].1[0]), field_names(Table::BalanceDiffs));
+    }
+}
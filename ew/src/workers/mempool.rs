@@ -0,0 +1,136 @@
+mod polling;
+mod store;
+mod types;
+
+pub use polling::Poller;
+pub(crate) use store::SCHEMA;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc::Sender;
+
+use crate::config::MempoolConfig;
+use crate::config::NodeConfig;
+use crate::config::PostgresConfig;
+use crate::core::types::CoreData;
+use crate::core::types::Header;
+use crate::core::types::Height;
+use crate::error::EwError;
+use crate::framework::store::PgMigrator;
+use crate::framework::Event;
+use crate::framework::EventHandler;
+use crate::framework::EventHandling;
+use crate::framework::Source;
+use crate::framework::StampedData;
+use crate::monitor::MonitorMessage;
+use crate::shutdown::CancellationToken;
+use store::Batch;
+use store::Store;
+
+const WORKER_ID: &str = "mempool";
+
+/// Initializes the worker's store and applies pending migrations.
+pub async fn migrate(pgconf: &PostgresConfig) -> Result<(), EwError> {
+    PgMigrator::new(pgconf, &store::SCHEMA)
+        .await?
+        .apply_pending()
+        .await
+}
+
+/// Tracks unconfirmed transactions of the node's pool.
+///
+/// Pending ERG and token diffs of each address, fees and first-seen times
+/// are kept in the mempool schema. Transactions are evicted as soon as a
+/// block confirms them, or when a poll finds them gone from the pool.
+/// Rollbacks clear the pool, which is polled again right away.
+pub struct Worker {
+    event_handler: EventHandler<MempoolWorkFlow>,
+    poller: Poller,
+}
+
+impl Worker {
+    pub async fn new(
+        pgconf: &PostgresConfig,
+        node: &NodeConfig,
+        source: &mut impl Source<S = CoreData>,
+        monitor_tx: Sender<MonitorMessage>,
+        config: &MempoolConfig,
+    ) -> Result<Self, EwError> {
+        let workflow = MempoolWorkFlow::new(pgconf).await?;
+        let event_handler = EventHandler::new_with(WORKER_ID, workflow, source, monitor_tx).await?;
+        Ok(Self {
+            event_handler,
+            poller: Poller::new(pgconf, node, config).await?,
+        })
+    }
+
+    #[tracing::instrument(name = "mempool", skip_all)]
+    pub async fn start(&mut self, shutdown: CancellationToken) -> Result<(), EwError> {
+        tracing::info!("starting");
+        loop {
+            tokio::select! {
+                biased;
+                _ = shutdown.cancelled() => {
+                    tracing::info!("stopped");
+                    return Ok(());
+                },
+                event = self.event_handler.recv() => {
+                    let event = event.ok_or(EwError::UpstreamDown)?;
+                    self.event_handler.process_upstream_event(&event).await?;
+                    if let Event::Rollback(_) = event {
+                        // Rolled back blocks dropped the pool
+                        self.poller.reset();
+                    }
+                }
+                _ = self.poller.tick() => {
+                    self.poller.poll().await?;
+                },
+            }
+        }
+    }
+}
+
+pub struct MempoolWorkFlow {
+    store: Store,
+}
+
+#[async_trait]
+impl EventHandling for MempoolWorkFlow {
+    type U = CoreData;
+    type D = ();
+
+    async fn new(pgconf: &PostgresConfig) -> Result<Self, EwError> {
+        // Ensure migrations are applied
+        migrate(pgconf).await?;
+
+        let store = Store::new(pgconf, &store::SCHEMA).await?;
+        Ok(Self { store })
+    }
+
+    /// Evicts transactions confirmed by the block.
+    async fn include_block(&mut self, data: &StampedData<CoreData>) -> Result<(), EwError> {
+        let batch = Batch {
+            tx_ids: data
+                .data
+                .block
+                .transactions
+                .iter()
+                .map(|tx| tx.id.clone())
+                .collect(),
+        };
+        self.store.persist(&data.wrap(batch)).await?;
+        Ok(())
+    }
+
+    async fn roll_back(&mut self, height: Height) -> Result<Header, EwError> {
+        self.store.roll_back(height).await?;
+        Ok(self.store.get_header().clone())
+    }
+
+    fn header(&self) -> &Header {
+        self.store.get_header()
+    }
+
+    async fn flush(&mut self) -> Result<(), EwError> {
+        self.store.flush().await
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::time::SystemTime;
+use tokio::time::Interval;
+use tokio::time::MissedTickBehavior;
+use tokio_postgres::Client;
+use tokio_postgres::GenericClient;
+use tokio_postgres::NoTls;
+
+use super::store::pool;
+use super::types::Owner;
+use super::types::PoolBox;
+use super::types::PoolTransaction;
+use crate::config::MempoolConfig;
+use crate::config::NodeConfig;
+use crate::config::PostgresConfig;
+use crate::core::api::base16_to_address;
+use crate::core::api::AddressCache;
+use crate::core::models::Output;
+use crate::core::models::Transaction;
+use crate::core::types::AddressID;
+use crate::core::types::BoxID;
+use crate::core::types::Timestamp;
+use crate::core::Node;
+use crate::error::EwError;
+
+/// Number of transactions requested at once.
+const PAGE_SIZE: usize = 100;
+
+/// Mirrors the node's pool of unconfirmed transactions.
+///
+/// Each poll evicts transactions that left the pool, whether confirmed or
+/// dropped, and adds new ones. Inputs are resolved against confirmed boxes
+/// and outputs of other unconfirmed transactions. Transactions with inputs
+/// that can't be resolved yet are left for a later poll. Diffs of addresses
+/// not indexed yet get their address id once core indexes them.
+pub struct Poller {
+    client: Client,
+    node: Node,
+    interval: Interval,
+    /// Read-only: unknown ergo trees are not indexed
+    address_cache: AddressCache,
+}
+
+impl Poller {
+    pub async fn new(
+        pgconf: &PostgresConfig,
+        node: &NodeConfig,
+        config: &MempoolConfig,
+    ) -> Result<Self, EwError> {
+        let (client, connection) = tokio_postgres::connect(&pgconf.connection_uri, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                tracing::error!("connection error: {}", e);
+            }
+        });
+        let mut interval = tokio::time::interval(config.polling_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Ok(Self {
+            client,
+            node: Node::with_config("mempool", node),
+            interval,
+            // Address count is only used when indexing new addresses
+            address_cache: AddressCache::new(AddressID::zero(), config.address_cache_size),
+        })
+    }
+
+    /// Waits for next poll.
+    pub async fn tick(&mut self) {
+        self.interval.tick().await;
+    }
+
+    /// Drops cached address id's, which could be reassigned after a rollback,
+    /// and schedules next poll right away to refill the pool.
+    pub fn reset(&mut self) {
+        self.address_cache.lru.clear();
+        self.interval.reset_immediately();
+    }
+
+    /// Syncs the mempool tables with the node's pool.
+    ///
+    /// An unreachable node is logged and skipped, leaving tables untouched.
+    pub async fn poll(&mut self) -> Result<(), EwError> {
+        let txs = match self.fetch().await {
+            Ok(txs) => txs,
+            Err(e) => {
+                tracing::warn!("could not retrieve unconfirmed transactions: {e}");
+                return Ok(());
+            }
+        };
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .expect("system time is after unix epoch")
+            .as_millis() as Timestamp;
+
+        let pgtx = self.client.transaction().await?;
+        let tx_ids: Vec<String> = txs.iter().map(|tx| tx.id.clone()).collect();
+        let evicted = pool::delete_others(&pgtx, &tx_ids).await?;
+        // New addresses of pending diffs may have been indexed since
+        let resolved = pool::resolve_addresses(&pgtx).await?;
+        let known = pool::get_ids(&pgtx).await?;
+        let new_txs: Vec<&Transaction> = txs.iter().filter(|tx| !known.contains(&tx.id)).collect();
+
+        // Transactions confirmed since the node was polled
+        let first_outputs: Vec<BoxID> = new_txs
+            .iter()
+            .filter_map(|tx| tx.outputs.first())
+            .map(|op| op.box_id.clone())
+            .collect();
+        let confirmed = pool::get_core_boxes(&pgtx, &first_outputs).await?;
+
+        // Inputs can be outputs of other unconfirmed transactions
+        let pool_outputs: HashMap<&str, &Output> = txs
+            .iter()
+            .flat_map(|tx| tx.outputs.iter())
+            .map(|op| (op.box_id.as_str(), op))
+            .collect();
+        let core_input_ids: Vec<BoxID> = new_txs
+            .iter()
+            .flat_map(|tx| tx.inputs.iter())
+            .filter(|ip| !pool_outputs.contains_key(ip.box_id.as_str()))
+            .map(|ip| ip.box_id.clone())
+            .collect();
+        let core_inputs = pool::get_core_boxes(&pgtx, &core_input_ids).await?;
+
+        let mut added = 0;
+        for tx in new_txs {
+            if tx
+                .outputs
+                .first()
+                .is_some_and(|op| confirmed.contains_key(&op.box_id))
+            {
+                continue;
+            }
+            let mut inputs = vec![];
+            for ip in &tx.inputs {
+                let bx = match pool_outputs.get(ip.box_id.as_str()) {
+                    Some(op) => Some(pool_box(&pgtx, &mut self.address_cache, op).await?),
+                    None => core_inputs.get(&ip.box_id).cloned(),
+                };
+                match bx {
+                    Some(bx) => inputs.push(bx),
+                    None => break,
+                }
+            }
+            if inputs.len() < tx.inputs.len() {
+                tracing::debug!("skipping {} - unknown inputs", tx.id);
+                continue;
+            }
+            let mut outputs = vec![];
+            for op in &tx.outputs {
+                outputs.push(pool_box(&pgtx, &mut self.address_cache, op).await?);
+            }
+            let pool_tx = PoolTransaction::new(tx.id.clone(), tx.size, &inputs, &outputs);
+            pool::insert(&pgtx, &pool_tx, now).await?;
+            added += 1;
+        }
+        pgtx.commit().await?;
+        if added > 0 || evicted > 0 {
+            tracing::debug!("added {added} and evicted {evicted} transactions");
+        }
+        if resolved > 0 {
+            tracing::debug!("resolved addresses of {resolved} diffs");
+        }
+        Ok(())
+    }
+
+    /// All transactions in the node's pool.
+    async fn fetch(&self) -> Result<Vec<Transaction>, EwError> {
+        let mut txs: Vec<Transaction> = vec![];
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut offset = 0;
+        loop {
+            let page = self
+                .node
+                .api
+                .unconfirmed_transactions(offset, PAGE_SIZE)
+                .await?;
+            let n = page.len();
+            // The pool can change between pages
+            txs.extend(page.into_iter().filter(|tx| seen.insert(tx.id.clone())));
+            if n < PAGE_SIZE {
+                return Ok(txs);
+            }
+            offset += n;
+        }
+    }
+}
+
+async fn pool_box(
+    client: &impl GenericClient,
+    address_cache: &mut AddressCache,
+    output: &Output,
+) -> Result<PoolBox, EwError> {
+    let owner = match address_cache.lookup(client, &output.ergo_tree).await? {
+        Some(address_id) => Owner::Known(address_id),
+        None => Owner::New(base16_to_address(&output.ergo_tree)),
+    };
+    Ok(PoolBox {
+        owner,
+        value: output.value,
+        tokens: output
+            .assets
+            .iter()
+            .map(|a| (a.token_id.clone(), a.amount))
+            .collect(),
+    })
+}
@@ -0,0 +1,58 @@
+pub(super) mod pool;
+
+use async_trait::async_trait;
+
+use crate::core::types::Header;
+use crate::core::types::TransactionID;
+use crate::error::EwError;
+use crate::framework::store::BatchStore;
+use crate::framework::store::PgStore;
+use crate::framework::store::Revision;
+use crate::framework::store::StoreDef;
+use crate::framework::store::StoreTransaction;
+use crate::framework::StampedData;
+
+pub(crate) const SCHEMA: StoreDef = StoreDef {
+    schema_name: super::WORKER_ID,
+    worker_id: super::WORKER_ID,
+    sql: include_str!("store/schema.sql"),
+    revision: &Revision { major: 1, minor: 0 },
+    migrations: &[],
+    has_header: true,
+};
+
+/// Transactions confirmed by a block.
+pub(super) struct Batch {
+    pub(super) tx_ids: Vec<TransactionID>,
+}
+
+pub(super) struct InnerStore {}
+
+pub(super) type Store = PgStore<InnerStore>;
+
+#[async_trait]
+impl BatchStore for InnerStore {
+    type B = Batch;
+
+    async fn new() -> Self {
+        Self {}
+    }
+
+    async fn persist(
+        &mut self,
+        pgtx: &StoreTransaction,
+        stamped_batch: &StampedData<Self::B>,
+    ) -> Result<(), EwError> {
+        pool::delete(pgtx.client(), &stamped_batch.data.tx_ids).await?;
+        Ok(())
+    }
+
+    async fn roll_back(&mut self, pgtx: &StoreTransaction, header: &Header) -> Result<(), EwError> {
+        // Address ids of pending diffs may get reassigned by the rollback,
+        // so the whole pool is dropped. Its transactions, and those of the
+        // rolled back block, get picked up again by the next poll.
+        tracing::debug!("rolling back block {}", header.height);
+        pool::delete_all(pgtx.client()).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use tokio_postgres::GenericClient;
+
+use super::super::types::Owner;
+use super::super::types::PoolBox;
+use super::super::types::PoolTransaction;
+use crate::core::types::AddressID;
+use crate::core::types::BoxID;
+use crate::core::types::Timestamp;
+use crate::core::types::TokenID;
+use crate::core::types::TransactionID;
+use crate::error::EwError;
+
+/// Ids of transactions currently in the table
+pub(in super::super) async fn get_ids(
+    client: &impl GenericClient,
+) -> Result<HashSet<TransactionID>, EwError> {
+    let qry = "select tx_id from mempool.transactions;";
+    let rows = client.query(qry, &[]).await?;
+    Ok(rows.iter().map(|r| r.get(0)).collect())
+}
+
+/// Insert a transaction and its diffs
+pub(in super::super) async fn insert(
+    pgtx: &impl GenericClient,
+    tx: &PoolTransaction,
+    first_seen: Timestamp,
+) -> Result<(), EwError> {
+    tracing::trace!("insert {}", tx.tx_id);
+    let stmt = "
+        insert into mempool.transactions (tx_id, first_seen, size, fee, fee_per_byte)
+        values ($1, $2, $3, $4, $5);";
+    pgtx.execute(
+        stmt,
+        &[
+            &tx.tx_id,
+            &first_seen,
+            &tx.size,
+            &tx.fee,
+            &tx.fee_per_byte(),
+        ],
+    )
+    .await?;
+    let stmt = "
+        insert into mempool.erg_diffs (tx_id, address_id, address, nano)
+        values ($1, $2, $3, $4);";
+    for (owner, nano) in &tx.erg_diffs {
+        let (address_id, address) = owner.columns();
+        pgtx.execute(stmt, &[&tx.tx_id, &address_id, &address, nano])
+            .await?;
+    }
+    let stmt = "
+        insert into mempool.token_diffs (tx_id, address_id, address, token_id, value)
+        values ($1, $2, $3, $4, $5);";
+    for (owner, token_id, value) in &tx.token_diffs {
+        let (address_id, address) = owner.columns();
+        pgtx.execute(stmt, &[&tx.tx_id, &address_id, &address, token_id, value])
+            .await?;
+    }
+    Ok(())
+}
+
+/// Delete given transactions, if present
+///
+/// Returns number of deleted transactions.
+pub(in super::super) async fn delete(
+    pgtx: &impl GenericClient,
+    tx_ids: &[TransactionID],
+) -> Result<u64, EwError> {
+    tracing::trace!("delete {} transactions", tx_ids.len());
+    let stmt = "delete from mempool.transactions where tx_id = any($1);";
+    Ok(pgtx.execute(stmt, &[&tx_ids]).await?)
+}
+
+/// Delete transactions not in given ones
+///
+/// Returns number of deleted transactions.
+pub(in super::super) async fn delete_others(
+    pgtx: &impl GenericClient,
+    tx_ids: &[TransactionID],
+) -> Result<u64, EwError> {
+    let stmt = "delete from mempool.transactions where tx_id <> all($1);";
+    Ok(pgtx.execute(stmt, &[&tx_ids]).await?)
+}
+
+/// Delete all transactions
+///
+/// Returns number of deleted transactions.
+pub(in super::super) async fn delete_all(pgtx: &impl GenericClient) -> Result<u64, EwError> {
+    tracing::trace!("delete all transactions");
+    let stmt = "delete from mempool.transactions;";
+    Ok(pgtx.execute(stmt, &[]).await?)
+}
+
+/// Replace addresses of diffs by their id, for those indexed since
+///
+/// Returns number of updated diffs.
+pub(in super::super) async fn resolve_addresses(pgtx: &impl GenericClient) -> Result<u64, EwError> {
+    let mut n = 0;
+    for table in ["erg_diffs", "token_diffs"] {
+        let stmt = format!(
+            "
+            update mempool.{table} d
+            set address_id = a.id
+                , address = null
+            from core.addresses a
+            where d.address is not null
+                and md5(a.address) = md5(d.address)
+                and a.address = d.address;"
+        );
+        n += pgtx.execute(&stmt, &[]).await?;
+    }
+    Ok(n)
+}
+
+/// Confirmed boxes among given ones
+pub(in super::super) async fn get_core_boxes(
+    client: &impl GenericClient,
+    box_ids: &[BoxID],
+) -> Result<HashMap<BoxID, PoolBox>, EwError> {
+    let qry = "
+        select b.box_id
+            , b.address_id
+            , b.value
+            , array(
+                select t.token_id
+                from unnest(b.assets) with ordinality a
+                join core.tokens t on t.asset_id = a.asset_id
+                order by a.ordinality
+            )
+            , array(
+                select a.amount
+                from unnest(b.assets) with ordinality a
+                order by a.ordinality
+            )
+        from core.boxes b
+        where b.box_id = any($1);";
+    let rows = client.query(qry, &[&box_ids]).await?;
+    Ok(rows
+        .iter()
+        .map(|r| {
+            let address_id: AddressID = r.get(1);
+            let token_ids: Vec<TokenID> = r.get(3);
+            let amounts: Vec<i64> = r.get(4);
+            let bx = PoolBox {
+                owner: Owner::Known(address_id),
+                value: r.get(2),
+                tokens: token_ids.into_iter().zip(amounts).collect(),
+            };
+            (r.get(0), bx)
+        })
+        .collect())
+}
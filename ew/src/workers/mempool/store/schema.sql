@@ -0,0 +1,58 @@
+create schema if not exists mempool;
+comment on schema mempool is 'Unconfirmed transactions';
+
+-- Transactions in the node's pool, as of last poll.
+-- Rows are evicted once their transaction confirms or drops out of the pool.
+create table mempool.transactions (
+    tx_id text primary key,
+    -- Timestamp of the poll the transaction was first seen in
+    first_seen bigint not null,
+    size integer not null,
+    -- Nano ERG paid to the fee contract
+    fee bigint not null,
+    fee_per_byte double precision not null
+);
+create index on mempool.transactions (fee_per_byte);
+
+-- Pending balance changes of each address involved in a transaction.
+-- Addresses not seen on chain yet have no id, so their address is kept instead.
+create table mempool.erg_diffs (
+    tx_id text not null references mempool.transactions (tx_id) on delete cascade,
+    address_id bigint,
+    address text,
+    nano bigint not null,
+    check ((address_id is null) <> (address is null))
+);
+create index on mempool.erg_diffs (tx_id);
+create index on mempool.erg_diffs (address_id);
+
+create table mempool.token_diffs (
+    tx_id text not null references mempool.transactions (tx_id) on delete cascade,
+    address_id bigint,
+    address text,
+    token_id text not null,
+    value bigint not null,
+    check ((address_id is null) <> (address is null))
+);
+create index on mempool.token_diffs (tx_id);
+create index on mempool.token_diffs (address_id);
+
+-- Pending ERG diffs and number of pending transactions of each address.
+create view mempool.addresses as
+    select d.address_id
+        , d.address
+        , count(*) as tx_count
+        , sum(d.nano)::bigint as nano
+    from mempool.erg_diffs d
+    group by 1, 2;
+
+-- Distribution of fees per byte over pending transactions.
+create view mempool.fees as
+    select count(*) as tx_count
+        , min(fee_per_byte) as min
+        , percentile_cont(0.25) within group (order by fee_per_byte) as p25
+        , percentile_cont(0.5) within group (order by fee_per_byte) as p50
+        , percentile_cont(0.75) within group (order by fee_per_byte) as p75
+        , percentile_cont(0.9) within group (order by fee_per_byte) as p90
+        , max(fee_per_byte) as max
+    from mempool.transactions;
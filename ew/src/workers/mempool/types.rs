@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+
+use crate::constants::address_ids::FEES;
+use crate::core::types::Address;
+use crate::core::types::AddressID;
+use crate::core::types::NanoERG;
+use crate::core::types::TokenID;
+use crate::core::types::TransactionID;
+
+/// Owner of a box spent or created by an unconfirmed transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) enum Owner {
+    /// Address already indexed by core
+    Known(AddressID),
+    /// Address not seen on chain yet
+    New(Address),
+}
+
+impl Owner {
+    /// Address id and address, only one of them being set.
+    pub(super) fn columns(&self) -> (Option<AddressID>, Option<&str>) {
+        match self {
+            Owner::Known(address_id) => (Some(*address_id), None),
+            Owner::New(address) => (None, Some(address)),
+        }
+    }
+
+    /// Orders new addresses first, then known ones by id.
+    fn sort_key(&self) -> (Option<i64>, Option<&str>) {
+        let (address_id, address) = self.columns();
+        (address_id.map(|id| id.0), address)
+    }
+}
+
+/// Box spent or created by an unconfirmed transaction.
+#[derive(Debug, Clone)]
+pub(super) struct PoolBox {
+    pub(super) owner: Owner,
+    pub(super) value: NanoERG,
+    pub(super) tokens: Vec<(TokenID, i64)>,
+}
+
+/// Unconfirmed transaction with resolved inputs.
+#[derive(Debug)]
+pub(super) struct PoolTransaction {
+    pub(super) tx_id: TransactionID,
+    pub(super) size: i32,
+    /// Nano ERG paid to the fee contract
+    pub(super) fee: NanoERG,
+    /// ERG diff of each address involved, zero ones included
+    pub(super) erg_diffs: Vec<(Owner, NanoERG)>,
+    /// Non-zero token diffs of each address
+    pub(super) token_diffs: Vec<(Owner, TokenID, i64)>,
+}
+
+impl PoolTransaction {
+    pub(super) fn new(
+        tx_id: TransactionID,
+        size: i32,
+        inputs: &[PoolBox],
+        outputs: &[PoolBox],
+    ) -> Self {
+        let fee = outputs
+            .iter()
+            .filter(|bx| bx.owner == Owner::Known(FEES))
+            .map(|bx| bx.value)
+            .sum();
+        let mut erg: HashMap<&Owner, NanoERG> = HashMap::new();
+        let mut tokens: HashMap<(&Owner, &TokenID), i64> = HashMap::new();
+        let boxes = inputs
+            .iter()
+            .map(|bx| (bx, -1))
+            .chain(outputs.iter().map(|bx| (bx, 1)));
+        for (bx, sign) in boxes {
+            *erg.entry(&bx.owner).or_default() += sign * bx.value;
+            for (token_id, amount) in &bx.tokens {
+                *tokens.entry((&bx.owner, token_id)).or_default() += sign * amount;
+            }
+        }
+        let mut erg_diffs: Vec<(Owner, NanoERG)> = erg
+            .into_iter()
+            .map(|(owner, nano)| (owner.clone(), nano))
+            .collect();
+        erg_diffs.sort_by(|a, b| a.0.sort_key().cmp(&b.0.sort_key()));
+        let mut token_diffs: Vec<(Owner, TokenID, i64)> = tokens
+            .into_iter()
+            .filter(|(_, amount)| *amount != 0)
+            .map(|((owner, token_id), amount)| (owner.clone(), token_id.clone(), amount))
+            .collect();
+        token_diffs.sort_by(|a, b| (a.0.sort_key(), &a.1).cmp(&(b.0.sort_key(), &b.1)));
+        Self {
+            tx_id,
+            size,
+            fee,
+            erg_diffs,
+            token_diffs,
+        }
+    }
+
+    pub(super) fn fee_per_byte(&self) -> f64 {
+        match self.size {
+            0 => 0.0,
+            size => self.fee as f64 / size as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn pool_box(owner: Owner, value: NanoERG, tokens: &[(&str, i64)]) -> PoolBox {
+        PoolBox {
+            owner,
+            value,
+            tokens: tokens.iter().map(|(t, a)| (t.to_string(), *a)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_diffs() {
+        let a = Owner::Known(AddressID(11));
+        let b = Owner::New("addressB".to_owned());
+        let inputs = vec![
+            pool_box(a.clone(), 1000, &[("tokenX", 10), ("tokenY", 5)]),
+            pool_box(a.clone(), 500, &[]),
+        ];
+        let outputs = vec![
+            pool_box(b.clone(), 1000, &[("tokenX", 4)]),
+            pool_box(a.clone(), 400, &[("tokenX", 6), ("tokenY", 5)]),
+            pool_box(Owner::Known(FEES), 100, &[]),
+        ];
+        let tx = PoolTransaction::new("tx".to_owned(), 50, &inputs, &outputs);
+        assert_eq!(tx.fee, 100);
+        assert_eq!(tx.fee_per_byte(), 2.0);
+        assert_eq!(
+            tx.erg_diffs,
+            vec![
+                (b.clone(), 1000),
+                (a.clone(), -1100),
+                (Owner::Known(FEES), 100)
+            ]
+        );
+        // Zero token diffs are dropped
+        assert_eq!(
+            tx.token_diffs,
+            vec![(b, "tokenX".to_owned(), 4), (a, "tokenX".to_owned(), -4)]
+        );
+    }
+
+    #[test]
+    fn test_zero_erg_diffs_are_kept() {
+        let a = Owner::Known(AddressID(11));
+        let inputs = vec![pool_box(a.clone(), 1000, &[])];
+        let outputs = vec![pool_box(a.clone(), 1000, &[])];
+        let tx = PoolTransaction::new("tx".to_owned(), 0, &inputs, &outputs);
+        assert_eq!(tx.fee, 0);
+        assert_eq!(tx.fee_per_byte(), 0.0);
+        assert_eq!(tx.erg_diffs, vec![(a, 0)]);
+        assert!(tx.token_diffs.is_empty());
+    }
+}
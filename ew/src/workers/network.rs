@@ -2,12 +2,17 @@ mod parsing;
 mod store;
 mod types;
 
+pub(crate) use store::api;
+pub(crate) use store::SCHEMA;
+
 use async_trait::async_trait;
 
 use crate::config::PostgresConfig;
 use crate::core::types::CoreData;
 use crate::core::types::Header;
 use crate::core::types::Height;
+use crate::error::EwError;
+use crate::framework::store::PgMigrator;
 use crate::framework::EventHandling;
 use crate::framework::StampedData;
 use parsing::Parser;
@@ -18,6 +23,14 @@ const WORKER_ID: &'static str = "network";
 
 pub type Worker = crate::framework::LeafWorker<Network>;
 
+/// Initializes the worker's store and applies pending migrations.
+pub async fn migrate(pgconf: &PostgresConfig) -> Result<(), EwError> {
+    PgMigrator::new(pgconf, &store::SCHEMA)
+        .await?
+        .apply_pending()
+        .await
+}
+
 pub struct Network {
     parser: Parser,
     store: Store,
@@ -28,27 +41,40 @@ impl EventHandling for Network {
     type U = CoreData;
     type D = ();
 
-    async fn new(pgconf: &PostgresConfig) -> Self {
-        let store = Store::new(pgconf, &store::SCHEMA).await;
-        let cache = store::load_parser_cache(store.get_client()).await;
+    async fn new(pgconf: &PostgresConfig) -> Result<Self, EwError> {
+        // Ensure migrations are applied
+        migrate(pgconf).await?;
+
+        let mut store = Store::new(pgconf, &store::SCHEMA).await?;
+        store.set_batch_blocks(pgconf.batch_blocks);
+        let cache = store::load_parser_cache(store.get_client()).await?;
         let parser = Parser::new(cache);
-        Self { parser, store }
+        Ok(Self { parser, store })
     }
 
-    async fn include_block(&mut self, data: &StampedData<CoreData>) {
+    async fn include_block(&mut self, data: &StampedData<CoreData>) -> Result<(), EwError> {
         let stamped_batch = self.parser.extract_batch(&data);
-        self.store.persist(&stamped_batch).await;
+        self.store.persist(&stamped_batch).await?;
+        Ok(())
     }
 
-    async fn roll_back(&mut self, height: Height) -> Header {
-        self.store.roll_back(height).await;
+    async fn roll_back(&mut self, height: Height) -> Result<Header, EwError> {
+        self.store.roll_back(height).await?;
         // Refresh parser cache to reflect rollback
-        let cache = store::load_parser_cache(self.store.get_client()).await;
+        let cache = store::load_parser_cache(self.store.get_client()).await?;
         self.parser = Parser::new(cache);
-        self.store.get_header().clone()
+        Ok(self.store.get_header().clone())
     }
 
     fn header<'a>(&'a self) -> &'a Header {
         self.store.get_header()
     }
+
+    async fn flush(&mut self) -> Result<(), EwError> {
+        self.store.flush().await
+    }
+
+    fn has_uncommitted(&self) -> bool {
+        self.store.has_uncommitted()
+    }
 }
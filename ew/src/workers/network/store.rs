@@ -1,3 +1,4 @@
+pub(crate) mod api;
 mod mining;
 mod parameters;
 mod proposals;
@@ -7,24 +8,28 @@ mod votes;
 
 use async_trait::async_trait;
 use tokio_postgres::Client;
-use tokio_postgres::Transaction;
 
 use super::parsing::ParserCache;
 use super::types::Difficulty;
 use super::types::Proposal;
 use super::Batch;
+use crate::constants::VOTING_EPOCH_LENGTH;
 use crate::core::types::Header;
+use crate::error::EwError;
 use crate::framework::store::BatchStore;
 use crate::framework::store::PgStore;
 use crate::framework::store::Revision;
 use crate::framework::store::StoreDef;
+use crate::framework::store::StoreTransaction;
 use crate::framework::StampedData;
 
-pub(super) const SCHEMA: StoreDef = StoreDef {
+pub(crate) const SCHEMA: StoreDef = StoreDef {
     schema_name: super::WORKER_ID,
     worker_id: super::WORKER_ID,
     sql: include_str!("store/schema.sql"),
     revision: &Revision { major: 1, minor: 0 },
+    migrations: &[],
+    has_header: true,
 };
 
 pub(super) struct InnerStore {}
@@ -39,69 +44,82 @@ impl BatchStore for InnerStore {
         Self {}
     }
 
-    async fn persist(&mut self, pgtx: &Transaction<'_>, stamped_batch: &StampedData<Self::B>) {
+    async fn persist(
+        &mut self,
+        pgtx: &StoreTransaction,
+        stamped_batch: &StampedData<Self::B>,
+    ) -> Result<(), EwError> {
         let batch = match &stamped_batch.data {
-            Batch::Genesis => return,
+            Batch::Genesis => return Ok(()),
             Batch::Block(data) => data,
         };
 
         // Network parameters
         if let Some(record) = &batch.parameters {
-            parameters::insert(pgtx, &record).await;
+            parameters::insert(pgtx.client(), &record).await?;
         }
 
         // Votes
-        votes::insert(pgtx, &batch.votes).await;
+        votes::insert(pgtx.client(), &batch.votes).await?;
 
         // Proposal
         match &batch.proposal {
-            Proposal::New(record) => proposals::insert(pgtx, record).await,
-            Proposal::Tally(record) => proposals::update(pgtx, record).await,
+            Proposal::New(record) => proposals::insert(pgtx.client(), record).await?,
+            Proposal::Tally(record) => proposals::update(pgtx.client(), record).await?,
             Proposal::Empty => (),
         }
 
         // Mining
-        mining::insert(pgtx, &batch.mining).await;
+        mining::insert(pgtx.client(), &batch.mining).await?;
 
         // Unhandled extensions
-        unhandled_extensions::insert_many(pgtx, &batch.unhandled_extensions).await;
+        unhandled_extensions::insert_many(pgtx.client(), &batch.unhandled_extensions).await?;
 
         // Transaction counts
-        transactions::insert(pgtx, &batch.transactions).await;
+        transactions::insert(pgtx.client(), &batch.transactions).await?;
+        Ok(())
     }
 
-    async fn roll_back(&mut self, pgtx: &Transaction<'_>, header: &Header) {
+    async fn roll_back(&mut self, pgtx: &StoreTransaction, header: &Header) -> Result<(), EwError> {
         let height = header.height;
         tracing::debug!("rolling back block {}", height);
 
         // Read last proposal
-        let proposal = proposals::get_last(pgtx).await.unwrap();
-        if proposal.height == height {
-            // Delete proposal created in rolled back block
-            proposals::delete_at(pgtx, height).await;
-        } else {
-            // Proposal created in earlier block, reverse tally
-            let votes = votes::get_at(pgtx, height).await.unwrap();
-            let previous = proposal.withdraw_votes(votes.pack());
-            proposals::update(pgtx, &previous).await;
+        match proposals::get_last(pgtx.client()).await? {
+            Some(proposal) if proposal.height == height => {
+                // Delete proposal created in rolled back block
+                proposals::delete_at(pgtx.client(), height).await?;
+            }
+            Some(proposal) if proposal.epoch == height / VOTING_EPOCH_LENGTH => {
+                // Proposal created in earlier block, reverse tally
+                let votes = votes::get_at(pgtx.client(), height).await?.unwrap();
+                let previous = proposal.withdraw_votes(votes.pack());
+                proposals::update(pgtx.client(), &previous).await?;
+            }
+            // No proposal in rolled back block's voting epoch
+            _ => (),
         }
 
         // Delete records at height
-        parameters::delete_at(pgtx, height).await;
-        votes::delete_at(pgtx, height).await;
-        mining::delete_at(pgtx, height).await;
-        unhandled_extensions::delete_at(pgtx, height).await;
-        transactions::delete_at(pgtx, height).await;
+        parameters::delete_at(pgtx.client(), height).await?;
+        votes::delete_at(pgtx.client(), height).await?;
+        mining::delete_at(pgtx.client(), height).await?;
+        unhandled_extensions::delete_at(pgtx.client(), height).await?;
+        transactions::delete_at(pgtx.client(), height).await?;
+        Ok(())
     }
 }
 
-pub(super) async fn load_parser_cache(client: &Client) -> ParserCache {
-    let difficulties = load_diff_cache(client).await;
-    ParserCache::new(proposals::get_last(client).await, difficulties)
+pub(super) async fn load_parser_cache(client: &Client) -> Result<ParserCache, EwError> {
+    let difficulties = load_diff_cache(client).await?;
+    Ok(ParserCache::new(
+        proposals::get_last(client).await?,
+        difficulties,
+    ))
 }
 
 /// Loads timestamp and difficulty from last recorded 24h window.
-async fn load_diff_cache(client: &Client) -> Vec<(i64, Difficulty)> {
+async fn load_diff_cache(client: &Client) -> Result<Vec<(i64, Difficulty)>, EwError> {
     let sql = "
         select h.timestamp,
             m.difficulty
@@ -115,11 +133,10 @@ async fn load_diff_cache(client: &Client) -> Vec<(i64, Difficulty)> {
         )
         order by m.height;
     ";
-    client
+    Ok(client
         .query(sql, &[])
-        .await
-        .unwrap()
+        .await?
         .iter()
         .map(|row| (row.get(0), row.get(1)))
-        .collect()
+        .collect())
 }
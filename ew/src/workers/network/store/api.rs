@@ -0,0 +1,38 @@
+//! Read-only queries evaluated by alert rules.
+use tokio_postgres::GenericClient;
+
+use crate::core::types::Height;
+use crate::error::EwError;
+
+/// Height and 24h mean hash rate of last mining record, if any.
+pub(crate) async fn get_hash_rate(
+    client: &impl GenericClient,
+) -> Result<Option<(Height, i64)>, EwError> {
+    let sql = "
+        select height
+            , hash_rate_24h_mean
+        from network.mining
+        order by height desc
+        limit 1;";
+    Ok(client
+        .query_opt(sql, &[])
+        .await?
+        .map(|row| (row.get(0), row.get(1))))
+}
+
+/// 24h mean hash rate of last mining record at or before given `height`, if any.
+pub(crate) async fn get_hash_rate_at(
+    client: &impl GenericClient,
+    height: Height,
+) -> Result<Option<i64>, EwError> {
+    let sql = "
+        select hash_rate_24h_mean
+        from network.mining
+        where height <= $1
+        order by height desc
+        limit 1;";
+    Ok(client
+        .query_opt(sql, &[&height])
+        .await?
+        .map(|row| row.get(0)))
+}
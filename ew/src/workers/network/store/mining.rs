@@ -1,10 +1,14 @@
 use crate::core::types::Height;
-use tokio_postgres::Transaction;
+use crate::error::EwError;
+use tokio_postgres::GenericClient;
 
 use super::super::types::MiningRecord;
 
 /// Insert a record
-pub(super) async fn insert(pgtx: &Transaction<'_>, record: &MiningRecord) {
+pub(super) async fn insert(
+    pgtx: &impl GenericClient,
+    record: &MiningRecord,
+) -> Result<(), EwError> {
     tracing::trace!("insert {record:?}");
     let stmt = "
         insert into network.mining (
@@ -29,13 +33,14 @@ pub(super) async fn insert(pgtx: &Transaction<'_>, record: &MiningRecord) {
             &record.tx_fees,
         ],
     )
-    .await
-    .unwrap();
+    .await?;
+    Ok(())
 }
 
 /// Delete record for given `height`
-pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: Height) {
+pub(super) async fn delete_at(pgtx: &impl GenericClient, height: Height) -> Result<(), EwError> {
     tracing::trace!("delete_at {height}");
     let sql = "delete from network.mining where height = $1;";
-    pgtx.execute(sql, &[&height]).await.unwrap();
+    pgtx.execute(sql, &[&height]).await?;
+    Ok(())
 }
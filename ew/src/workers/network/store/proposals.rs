@@ -1,11 +1,14 @@
 use crate::core::types::Height;
+use crate::error::EwError;
 use tokio_postgres::GenericClient;
-use tokio_postgres::Transaction;
 
 use super::super::types::ProposalRecord;
 
 /// Insert a record
-pub(super) async fn insert(pgtx: &Transaction<'_>, record: &ProposalRecord) {
+pub(super) async fn insert(
+    pgtx: &impl GenericClient,
+    record: &ProposalRecord,
+) -> Result<(), EwError> {
     tracing::trace!("insert {record:?}");
     let stmt = "
         insert into network.proposals (
@@ -31,12 +34,15 @@ pub(super) async fn insert(pgtx: &Transaction<'_>, record: &ProposalRecord) {
             &record.tally[2],
         ],
     )
-    .await
-    .unwrap();
+    .await?;
+    Ok(())
 }
 
 /// Update proposal tally
-pub(super) async fn update(pgtx: &Transaction<'_>, record: &ProposalRecord) {
+pub(super) async fn update(
+    pgtx: &impl GenericClient,
+    record: &ProposalRecord,
+) -> Result<(), EwError> {
     tracing::trace!("update {record:?}");
     let stmt = "
         update network.proposals
@@ -53,19 +59,22 @@ pub(super) async fn update(pgtx: &Transaction<'_>, record: &ProposalRecord) {
             &record.tally[2],
         ],
     )
-    .await
-    .unwrap();
+    .await?;
+    Ok(())
 }
 
 /// Delete a proposal created at given height
-pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: Height) {
+pub(super) async fn delete_at(pgtx: &impl GenericClient, height: Height) -> Result<(), EwError> {
     tracing::trace!("delete_at {height}");
     let stmt = "delete from network.proposals where height = $1;";
-    pgtx.execute(stmt, &[&height]).await.unwrap();
+    pgtx.execute(stmt, &[&height]).await?;
+    Ok(())
 }
 
 /// Insert a record
-pub(super) async fn get_last(client: &impl GenericClient) -> Option<ProposalRecord> {
+pub(super) async fn get_last(
+    client: &impl GenericClient,
+) -> Result<Option<ProposalRecord>, EwError> {
     tracing::trace!("get_last");
     let stmt = "
         select epoch
@@ -80,7 +89,7 @@ pub(super) async fn get_last(client: &impl GenericClient) -> Option<ProposalReco
         from network.proposals
         order by height desc
         limit 1;";
-    client.query_opt(stmt, &[]).await.unwrap().and_then(|row| {
+    Ok(client.query_opt(stmt, &[]).await?.and_then(|row| {
         Some(ProposalRecord {
             epoch: row.get(0),
             height: row.get(1),
@@ -88,5 +97,5 @@ pub(super) async fn get_last(client: &impl GenericClient) -> Option<ProposalReco
             slots: [row.get(3), row.get(4), row.get(5)],
             tally: [row.get(6), row.get(7), row.get(8)],
         })
-    })
+    }))
 }
@@ -1,14 +1,18 @@
 use crate::core::types::Height;
+use crate::error::EwError;
 use tokio_postgres::types::Type;
-use tokio_postgres::Transaction;
+use tokio_postgres::GenericClient;
 
 use super::super::types::UnhandledExtensionRecord;
 
 /// Insert a record
-pub(super) async fn insert_many(pgtx: &Transaction<'_>, records: &[UnhandledExtensionRecord]) {
+pub(super) async fn insert_many(
+    pgtx: &impl GenericClient,
+    records: &[UnhandledExtensionRecord],
+) -> Result<(), EwError> {
     tracing::trace!("insert_many {records:?}");
     if records.is_empty() {
-        return;
+        return Ok(());
     }
     let sql = "
         insert into network._unhandled_extension_fields (
@@ -19,18 +23,18 @@ pub(super) async fn insert_many(pgtx: &Transaction<'_>, records: &[UnhandledExte
         values ($1, $2, $3);";
     let stmt = pgtx
         .prepare_typed(sql, &[Type::INT4, Type::INT2, Type::TEXT])
-        .await
-        .unwrap();
+        .await?;
     for record in records {
         pgtx.execute(&stmt, &[&record.height, &record.key, &record.value])
-            .await
-            .unwrap();
+            .await?;
     }
+    Ok(())
 }
 
 /// Delete records for given `height`
-pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: Height) {
+pub(super) async fn delete_at(pgtx: &impl GenericClient, height: Height) -> Result<(), EwError> {
     tracing::trace!("delete_at {height}");
     let sql = "delete from network._unhandled_extension_fields where height = $1;";
-    pgtx.execute(sql, &[&height]).await.unwrap();
+    pgtx.execute(sql, &[&height]).await?;
+    Ok(())
 }
@@ -1,10 +1,11 @@
 use crate::core::types::Height;
-use tokio_postgres::Transaction;
+use crate::error::EwError;
+use tokio_postgres::GenericClient;
 
 use super::super::types::VotesRecord;
 
 /// Insert a record
-pub(super) async fn insert(pgtx: &Transaction<'_>, record: &VotesRecord) {
+pub(super) async fn insert(pgtx: &impl GenericClient, record: &VotesRecord) -> Result<(), EwError> {
     tracing::trace!("insert {record:?}");
     let stmt = "
         insert into network.votes (height, slots)
@@ -13,12 +14,15 @@ pub(super) async fn insert(pgtx: &Transaction<'_>, record: &VotesRecord) {
         stmt,
         &[&record.height, &record.slot1, &record.slot2, &record.slot3],
     )
-    .await
-    .unwrap();
+    .await?;
+    Ok(())
 }
 
 /// Get votes for given `height`
-pub(super) async fn get_at(pgtx: &Transaction<'_>, height: Height) -> Option<VotesRecord> {
+pub(super) async fn get_at(
+    pgtx: &impl GenericClient,
+    height: Height,
+) -> Result<Option<VotesRecord>, EwError> {
     tracing::trace!("get_at {height}");
     let stmt = "
         select height
@@ -27,22 +31,20 @@ pub(super) async fn get_at(pgtx: &Transaction<'_>, height: Height) -> Option<Vot
             , slots[3]
         from network.votes
         where height = $1";
-    pgtx.query_opt(stmt, &[&height])
-        .await
-        .unwrap()
-        .and_then(|row| {
-            Some(VotesRecord {
-                height: row.get(0),
-                slot1: row.get(1),
-                slot2: row.get(2),
-                slot3: row.get(3),
-            })
+    Ok(pgtx.query_opt(stmt, &[&height]).await?.and_then(|row| {
+        Some(VotesRecord {
+            height: row.get(0),
+            slot1: row.get(1),
+            slot2: row.get(2),
+            slot3: row.get(3),
         })
+    }))
 }
 
 /// Delete record for given `height`
-pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: Height) {
+pub(super) async fn delete_at(pgtx: &impl GenericClient, height: Height) -> Result<(), EwError> {
     tracing::trace!("delete_at {height}");
     let sql = "delete from network.votes where height = $1;";
-    pgtx.execute(sql, &[&height]).await.unwrap();
+    pgtx.execute(sql, &[&height]).await?;
+    Ok(())
 }
@@ -0,0 +1,647 @@
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::mpsc::Sender;
+use tokio::task::JoinHandle;
+
+use crate::config::Config;
+use crate::config::PostgresConfig;
+use crate::core::types::CoreData;
+use crate::error::EwError;
+use crate::framework::source_channel;
+use crate::framework::store::StoreDef;
+use crate::framework::SourceHandle;
+use crate::monitor::MonitorMessage;
+use crate::shutdown::CancellationToken;
+use crate::supervisor::supervise;
+use crate::workers::coingecko;
+use crate::workers::erg;
+use crate::workers::erg_diffs;
+use crate::workers::exchanges;
+use crate::workers::export;
+use crate::workers::export::ExportWorkFlow;
+use crate::workers::mempool;
+use crate::workers::network;
+use crate::workers::sigmausd;
+use crate::workers::timestamps;
+use crate::workers::tokens;
+use crate::workers::webhooks;
+
+/// Identifies a worker that can be enabled or disabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum WorkerID {
+    Timestamps,
+    Network,
+    ErgDiffs,
+    Erg,
+    Exchanges,
+    Tokens,
+    SigmaUSD,
+    Coingecko,
+    Webhooks,
+    WebhooksDiffs,
+    Mempool,
+    ExportCore,
+    ExportDiffs,
+}
+
+impl WorkerID {
+    /// All workers, upstream ones first.
+    pub const ALL: [WorkerID; 13] = [
+        WorkerID::Timestamps,
+        WorkerID::Network,
+        WorkerID::ErgDiffs,
+        WorkerID::Erg,
+        WorkerID::Exchanges,
+        WorkerID::Tokens,
+        WorkerID::SigmaUSD,
+        WorkerID::Coingecko,
+        WorkerID::Webhooks,
+        WorkerID::WebhooksDiffs,
+        WorkerID::Mempool,
+        WorkerID::ExportCore,
+        WorkerID::ExportDiffs,
+    ];
+
+    /// Workers exporting files, only enabled by default when configured.
+    pub const EXPORT: [WorkerID; 2] = [WorkerID::ExportCore, WorkerID::ExportDiffs];
+
+    /// Workers only enabled by default when configured.
+    pub const OPT_IN: [WorkerID; 3] = [
+        WorkerID::WebhooksDiffs,
+        WorkerID::ExportCore,
+        WorkerID::ExportDiffs,
+    ];
+
+    /// Name used in config files and reported to the monitor.
+    pub fn name(&self) -> &'static str {
+        match self {
+            WorkerID::Timestamps => "timestamps",
+            WorkerID::Network => "network",
+            WorkerID::ErgDiffs => "erg_diffs",
+            WorkerID::Erg => "erg",
+            WorkerID::Exchanges => "cex",
+            WorkerID::Tokens => "tokens",
+            WorkerID::SigmaUSD => "sigmausd",
+            WorkerID::Coingecko => "coingecko",
+            WorkerID::Webhooks => "webhooks",
+            WorkerID::WebhooksDiffs => "webhooks_diffs",
+            WorkerID::Mempool => "mempool",
+            WorkerID::ExportCore => "export_core",
+            WorkerID::ExportDiffs => "export_diffs",
+        }
+    }
+
+    /// Workers this worker subscribes to, sends queries to or reads the store of.
+    ///
+    /// The tracker is not listed as it is always running.
+    pub fn dependencies(&self) -> &'static [WorkerID] {
+        match self {
+            WorkerID::Erg => &[WorkerID::ErgDiffs],
+            WorkerID::Exchanges => &[WorkerID::ErgDiffs],
+            WorkerID::WebhooksDiffs => &[WorkerID::ErgDiffs, WorkerID::Exchanges],
+            WorkerID::ExportDiffs => &[WorkerID::ErgDiffs],
+            _ => &[],
+        }
+    }
+
+    /// Workers subscribing to or sending queries to this worker.
+    pub fn dependents(&self) -> Vec<WorkerID> {
+        WorkerID::ALL
+            .into_iter()
+            .filter(|w| w.dependencies().contains(self))
+            .collect()
+    }
+
+    /// Definition of the worker's store.
+    pub fn store(&self) -> &'static StoreDef {
+        match self {
+            WorkerID::Timestamps => &timestamps::SCHEMA,
+            WorkerID::Network => &network::SCHEMA,
+            WorkerID::ErgDiffs => &erg_diffs::SCHEMA,
+            WorkerID::Erg => &erg::SCHEMA,
+            WorkerID::Exchanges => &exchanges::SCHEMA,
+            WorkerID::Tokens => &tokens::SCHEMA,
+            WorkerID::SigmaUSD => &sigmausd::SCHEMA,
+            WorkerID::Coingecko => &coingecko::SCHEMA,
+            WorkerID::Webhooks => &webhooks::SCHEMA,
+            WorkerID::WebhooksDiffs => &webhooks::DIFFS_SCHEMA,
+            WorkerID::Mempool => &mempool::SCHEMA,
+            WorkerID::ExportCore => &export::CORE_SCHEMA,
+            WorkerID::ExportDiffs => &export::DIFFS_SCHEMA,
+        }
+    }
+
+    /// Initializes the worker's store and applies pending migrations.
+    ///
+    /// Workers do this themselves when starting.
+    pub async fn migrate(&self, pgconf: &PostgresConfig) -> Result<(), EwError> {
+        match self {
+            WorkerID::Timestamps => timestamps::migrate(pgconf).await,
+            WorkerID::Network => network::migrate(pgconf).await,
+            WorkerID::ErgDiffs => erg_diffs::migrate(pgconf).await,
+            WorkerID::Erg => erg::migrate(pgconf).await,
+            WorkerID::Exchanges => exchanges::migrate(pgconf).await,
+            WorkerID::Tokens => tokens::migrate(pgconf).await,
+            WorkerID::SigmaUSD => sigmausd::migrate(pgconf).await,
+            WorkerID::Coingecko => coingecko::migrate(pgconf).await,
+            WorkerID::Webhooks => webhooks::migrate(pgconf, &webhooks::SCHEMA).await,
+            WorkerID::WebhooksDiffs => webhooks::migrate(pgconf, &webhooks::DIFFS_SCHEMA).await,
+            WorkerID::Mempool => mempool::migrate(pgconf).await,
+            WorkerID::ExportCore => export::migrate(pgconf, &export::CORE_SCHEMA).await,
+            WorkerID::ExportDiffs => export::migrate(pgconf, &export::DIFFS_SCHEMA).await,
+        }
+    }
+}
+
+impl fmt::Display for WorkerID {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for WorkerID {
+    type Err = RegistryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WorkerID::ALL
+            .into_iter()
+            .find(|w| w.name() == s)
+            .ok_or(RegistryError::UnknownWorker(s.to_owned()))
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum RegistryError {
+    #[error("Unknown worker `{0}`. Expected one of: timestamps, network, erg_diffs, erg, cex, tokens, sigmausd, coingecko, webhooks, webhooks_diffs, mempool, export_core, export_diffs.")]
+    UnknownWorker(String),
+    #[error("Worker `{0}` depends on `{1}`, which is not enabled.")]
+    MissingDependency(WorkerID, WorkerID),
+}
+
+/// Collection of enabled workers.
+///
+/// Guarantees dependencies of enabled workers are enabled too.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Registry {
+    /// Enabled workers, upstream ones first.
+    enabled: Vec<WorkerID>,
+}
+
+impl Registry {
+    /// Create a registry for given workers after checking their dependencies.
+    pub fn new(workers: &[WorkerID]) -> Result<Self, RegistryError> {
+        let mut enabled: Vec<WorkerID> = workers.to_vec();
+        enabled.sort();
+        enabled.dedup();
+        for worker in &enabled {
+            for dep in worker.dependencies() {
+                if !enabled.contains(dep) {
+                    return Err(RegistryError::MissingDependency(*worker, *dep));
+                }
+            }
+        }
+        Ok(Self { enabled })
+    }
+
+    /// A registry with all workers enabled.
+    pub fn all() -> Self {
+        Self {
+            enabled: WorkerID::ALL.to_vec(),
+        }
+    }
+
+    /// Enabled workers, upstream ones first.
+    pub fn enabled(&self) -> &[WorkerID] {
+        &self.enabled
+    }
+
+    pub fn is_enabled(&self, worker: WorkerID) -> bool {
+        self.enabled.contains(&worker)
+    }
+
+    /// Spawns all enabled workers.
+    ///
+    /// Workers subscribe to the tracker (or to each other) through source
+    /// handles, so the tracker must be serving `tracker` requests for them
+    /// to get going. Disabled workers are not built, meaning their schemas
+    /// don't get created.
+    ///
+    /// Each worker is supervised: a failed worker is rebuilt and resumes
+    /// from its persisted header. Workers run until `shutdown` is cancelled.
+    /// Returns a handle for each spawned worker, and for the dispatchers of
+    /// webhooks workers.
+    pub fn spawn(
+        &self,
+        config: &Config,
+        tracker: &SourceHandle<CoreData>,
+        monitor_tx: &Sender<MonitorMessage>,
+        shutdown: &CancellationToken,
+    ) -> Vec<(WorkerID, JoinHandle<usize>)> {
+        let mut handles = vec![];
+
+        for worker in self.enabled() {
+            tracing::info!("enabling worker {worker}");
+        }
+
+        let spawner = Spawner {
+            config: Arc::new(config.clone()),
+            monitor_tx,
+            shutdown,
+        };
+
+        if self.is_enabled(WorkerID::Timestamps) {
+            handles.push(
+                spawner.spawn(WorkerID::Timestamps, tracker, |mut ctx| async move {
+                    let pgconf = &ctx.config.postgres;
+                    let mut w =
+                        timestamps::Worker::new(ctx.name, pgconf, &mut ctx.source, ctx.monitor_tx)
+                            .await?;
+                    w.start(ctx.shutdown).await
+                }),
+            );
+        }
+
+        if self.is_enabled(WorkerID::Network) {
+            handles.push(
+                spawner.spawn(WorkerID::Network, tracker, |mut ctx| async move {
+                    let pgconf = &ctx.config.postgres;
+                    let mut w =
+                        network::Worker::new(ctx.name, pgconf, &mut ctx.source, ctx.monitor_tx)
+                            .await?;
+                    w.start(ctx.shutdown).await
+                }),
+            );
+        }
+
+        if self.is_enabled(WorkerID::ErgDiffs) {
+            // Downstream workers subscribe to erg_diffs through its own handle
+            let (erg_diffs, erg_diffs_requests) = source_channel();
+
+            handles.push(spawner.spawn(WorkerID::ErgDiffs, tracker, move |mut ctx| {
+                let requests = erg_diffs_requests.clone();
+                async move {
+                    let pgconf = &ctx.config.postgres;
+                    let mut w =
+                        erg_diffs::Worker::new(ctx.name, pgconf, &mut ctx.source, ctx.monitor_tx)
+                            .await?;
+                    w.serve(requests);
+                    w.start(ctx.shutdown).await
+                }
+            }));
+
+            if self.is_enabled(WorkerID::Erg) {
+                handles.push(
+                    spawner.spawn(WorkerID::Erg, &erg_diffs, |mut ctx| async move {
+                        let pgconf = &ctx.config.postgres;
+                        let mut w =
+                            erg::Worker::new(ctx.name, pgconf, &mut ctx.source, ctx.monitor_tx)
+                                .await?;
+                        w.start(ctx.shutdown).await
+                    }),
+                );
+            }
+
+            if self.is_enabled(WorkerID::Exchanges) {
+                handles.push(spawner.spawn(
+                    WorkerID::Exchanges,
+                    &erg_diffs,
+                    |mut ctx| async move {
+                        let pgconf = &ctx.config.postgres;
+                        // Exchanges worker queries erg_diffs through a dedicated query worker
+                        let mut query_handler = erg_diffs::QueryWorker::new(pgconf).await?;
+                        let mut w = exchanges::Worker::new(
+                            ctx.name,
+                            pgconf,
+                            &mut ctx.source,
+                            ctx.monitor_tx,
+                        )
+                        .await?;
+                        w.connect_query_sender(&query_handler);
+                        // The query worker is read-only and only needs to
+                        // serve the exchanges worker while it is running.
+                        tokio::select! {
+                            biased;
+                            res = w.start(ctx.shutdown) => res,
+                            res = query_handler.start() => res,
+                        }
+                    },
+                ));
+            }
+
+            if self.is_enabled(WorkerID::WebhooksDiffs) {
+                handles.push(spawner.spawn(
+                    WorkerID::WebhooksDiffs,
+                    &erg_diffs,
+                    |mut ctx| async move {
+                        let config = &ctx.config;
+                        let mut w = webhooks::DiffsWorker::new(
+                            &config.postgres,
+                            &mut ctx.source,
+                            ctx.monitor_tx,
+                            &config.webhooks,
+                        )
+                        .await?;
+                        w.start(ctx.shutdown).await
+                    },
+                ));
+                handles.push(
+                    spawner.spawn_dispatcher(WorkerID::WebhooksDiffs, "webhooks_diffs_dispatcher"),
+                );
+            }
+
+            if self.is_enabled(WorkerID::ExportDiffs) {
+                handles.push(spawner.spawn(
+                    WorkerID::ExportDiffs,
+                    &erg_diffs,
+                    |mut ctx| async move {
+                        let config = &ctx.config;
+                        let workflow =
+                            ExportWorkFlow::with_config(&config.postgres, &config.export).await?;
+                        let mut w = export::DiffsWorker::new_with(
+                            ctx.name,
+                            workflow,
+                            &mut ctx.source,
+                            ctx.monitor_tx,
+                        )
+                        .await?;
+                        w.start(ctx.shutdown).await
+                    },
+                ));
+            }
+        }
+
+        if self.is_enabled(WorkerID::Tokens) {
+            handles.push(
+                spawner.spawn(WorkerID::Tokens, tracker, |mut ctx| async move {
+                    let pgconf = &ctx.config.postgres;
+                    let mut w =
+                        tokens::Worker::new(ctx.name, pgconf, &mut ctx.source, ctx.monitor_tx)
+                            .await?;
+                    w.start(ctx.shutdown).await
+                }),
+            );
+        }
+
+        if self.is_enabled(WorkerID::SigmaUSD) {
+            handles.push(
+                spawner.spawn(WorkerID::SigmaUSD, tracker, |mut ctx| async move {
+                    let pgconf = &ctx.config.postgres;
+                    let mut w =
+                        sigmausd::Worker::new(ctx.name, pgconf, &mut ctx.source, ctx.monitor_tx)
+                            .await?;
+                    w.start(ctx.shutdown).await
+                }),
+            );
+        }
+
+        if self.is_enabled(WorkerID::Coingecko) {
+            handles.push(
+                spawner.spawn(WorkerID::Coingecko, tracker, |mut ctx| async move {
+                    let config = &ctx.config;
+                    let mut w = coingecko::Worker::new(
+                        &config.postgres,
+                        &mut ctx.source,
+                        ctx.monitor_tx,
+                        &config.coingecko,
+                    )
+                    .await?;
+                    w.start(ctx.shutdown).await
+                }),
+            );
+        }
+
+        if self.is_enabled(WorkerID::Webhooks) {
+            handles.push(
+                spawner.spawn(WorkerID::Webhooks, tracker, |mut ctx| async move {
+                    let config = &ctx.config;
+                    let mut w = webhooks::CoreWorker::new(
+                        &config.postgres,
+                        &mut ctx.source,
+                        ctx.monitor_tx,
+                        &config.webhooks,
+                    )
+                    .await?;
+                    w.start(ctx.shutdown).await
+                }),
+            );
+            handles.push(spawner.spawn_dispatcher(WorkerID::Webhooks, "webhooks_dispatcher"));
+        }
+
+        if self.is_enabled(WorkerID::Mempool) {
+            handles.push(
+                spawner.spawn(WorkerID::Mempool, tracker, |mut ctx| async move {
+                    let config = &ctx.config;
+                    let mut w = mempool::Worker::new(
+                        &config.postgres,
+                        &config.node,
+                        &mut ctx.source,
+                        ctx.monitor_tx,
+                        &config.mempool,
+                    )
+                    .await?;
+                    w.start(ctx.shutdown).await
+                }),
+            );
+        }
+
+        if self.is_enabled(WorkerID::ExportCore) {
+            handles.push(
+                spawner.spawn(WorkerID::ExportCore, tracker, |mut ctx| async move {
+                    let config = &ctx.config;
+                    let workflow =
+                        ExportWorkFlow::with_config(&config.postgres, &config.export).await?;
+                    let mut w = export::CoreWorker::new_with(
+                        ctx.name,
+                        workflow,
+                        &mut ctx.source,
+                        ctx.monitor_tx,
+                    )
+                    .await?;
+                    w.start(ctx.shutdown).await
+                }),
+            );
+        }
+
+        handles
+    }
+}
+
+/// What a worker gets each time it is (re)built by its supervisor.
+struct WorkerContext<S> {
+    name: &'static str,
+    config: Arc<Config>,
+    /// Upstream source of the worker
+    source: SourceHandle<S>,
+    monitor_tx: Sender<MonitorMessage>,
+    shutdown: CancellationToken,
+}
+
+/// Spawns supervised workers sharing the same config, monitor and shutdown.
+struct Spawner<'a> {
+    config: Arc<Config>,
+    monitor_tx: &'a Sender<MonitorMessage>,
+    shutdown: &'a CancellationToken,
+}
+
+impl Spawner<'_> {
+    /// Spawns `worker`, built and run by `run` from a fresh context on each
+    /// (re)start, with `source` as upstream.
+    fn spawn<S, F, Fut>(
+        &self,
+        worker: WorkerID,
+        source: &SourceHandle<S>,
+        mut run: F,
+    ) -> (WorkerID, JoinHandle<usize>)
+    where
+        S: Send + Sync + 'static,
+        F: FnMut(WorkerContext<S>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), EwError>> + Send + 'static,
+    {
+        let config = self.config.clone();
+        let source = source.clone();
+        let monitor_tx = self.monitor_tx.clone();
+        let shutdown = self.shutdown.clone();
+        supervised(worker, self.monitor_tx, self.shutdown, move || {
+            run(WorkerContext {
+                name: worker.name(),
+                config: config.clone(),
+                source: source.clone(),
+                monitor_tx: monitor_tx.clone(),
+                shutdown: shutdown.clone(),
+            })
+        })
+    }
+
+    /// Spawns the dispatcher of webhooks `worker`, supervised as `name`.
+    ///
+    /// Delivery runs apart from the worker, so webhooks being slow or down
+    /// don't hold up indexing.
+    fn spawn_dispatcher(
+        &self,
+        worker: WorkerID,
+        name: &'static str,
+    ) -> (WorkerID, JoinHandle<usize>) {
+        let config = self.config.clone();
+        let shutdown = self.shutdown.clone();
+        let handle = tokio::spawn(supervise(
+            name,
+            self.monitor_tx.clone(),
+            self.shutdown.clone(),
+            move || {
+                let config = config.clone();
+                let shutdown = shutdown.clone();
+                async move {
+                    let mut dispatcher =
+                        webhooks::Dispatcher::new(&config.postgres, &config.webhooks, worker)
+                            .await?;
+                    dispatcher.start(shutdown).await
+                }
+            },
+        ));
+        (worker, handle)
+    }
+}
+
+/// Spawns a supervised worker task.
+fn supervised<F, Fut>(
+    worker: WorkerID,
+    monitor_tx: &Sender<MonitorMessage>,
+    shutdown: &CancellationToken,
+    run: F,
+) -> (WorkerID, JoinHandle<usize>)
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), EwError>> + Send + 'static,
+{
+    let handle = tokio::spawn(supervise(
+        worker.name(),
+        monitor_tx.clone(),
+        shutdown.clone(),
+        run,
+    ));
+    (worker, handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framework::store::Revision;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_worker_id_from_str() {
+        for worker in WorkerID::ALL {
+            assert_eq!(WorkerID::from_str(worker.name()), Ok(worker));
+        }
+        assert_eq!(
+            WorkerID::from_str("dummy"),
+            Err(RegistryError::UnknownWorker("dummy".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_worker_id_dependents() {
+        assert_eq!(
+            WorkerID::ErgDiffs.dependents(),
+            vec![
+                WorkerID::Erg,
+                WorkerID::Exchanges,
+                WorkerID::WebhooksDiffs,
+                WorkerID::ExportDiffs
+            ]
+        );
+        assert_eq!(
+            WorkerID::Exchanges.dependents(),
+            vec![WorkerID::WebhooksDiffs]
+        );
+        assert!(WorkerID::Erg.dependents().is_empty());
+    }
+
+    #[test]
+    fn test_worker_migrations_lead_to_store_revision() {
+        for worker in WorkerID::ALL {
+            let store = worker.store();
+            let mut rev = Revision::new(1, 0);
+            for mig in store.migrations {
+                assert_eq!(mig.revision(), Revision::new(rev.major, rev.minor + 1));
+                rev = mig.revision();
+            }
+            assert_eq!(&rev, store.revision, "{worker}");
+        }
+    }
+
+    #[test]
+    fn test_registry_without_dependencies() {
+        let registry = Registry::new(&[WorkerID::Tokens, WorkerID::Timestamps]).unwrap();
+        assert_eq!(
+            registry.enabled(),
+            &[WorkerID::Timestamps, WorkerID::Tokens]
+        );
+        assert!(!registry.is_enabled(WorkerID::ErgDiffs));
+    }
+
+    #[test]
+    fn test_registry_missing_dependency() {
+        assert_eq!(
+            Registry::new(&[WorkerID::Exchanges]),
+            Err(RegistryError::MissingDependency(
+                WorkerID::Exchanges,
+                WorkerID::ErgDiffs
+            ))
+        );
+    }
+
+    #[test]
+    fn test_registry_with_dependencies() {
+        let registry = Registry::new(&[WorkerID::Erg, WorkerID::ErgDiffs]).unwrap();
+        assert_eq!(registry.enabled(), &[WorkerID::ErgDiffs, WorkerID::Erg]);
+    }
+
+    #[test]
+    fn test_registry_all() {
+        assert_eq!(Registry::new(&WorkerID::ALL).unwrap(), Registry::all());
+    }
+}
@@ -3,15 +3,27 @@ mod parsing;
 mod store;
 mod types;
 
+/// Makes migrations available for testing
+pub mod testing {
+    use super::store;
+    pub use store::migrations::*;
+    pub use store::SCHEMA;
+}
+
+pub(crate) use store::api;
+pub(crate) use store::SCHEMA;
+
 use async_trait::async_trait;
 
 use crate::config::PostgresConfig;
 use crate::core::types::CoreData;
 use crate::core::types::Header;
 use crate::core::types::Height;
+use crate::error::EwError;
 use crate::framework::store::PgMigrator;
 use crate::framework::EventHandling;
 use crate::framework::StampedData;
+use crate::monitor::events::Figures;
 use constants::CONTRACT_CREATION_HEIGHT;
 use parsing::Parser;
 use store::Store;
@@ -21,6 +33,14 @@ const WORKER_ID: &'static str = "sigmausd";
 
 pub type Worker = crate::framework::LeafWorker<SigmaUSD>;
 
+/// Initializes the worker's store and applies pending migrations.
+pub async fn migrate(pgconf: &PostgresConfig) -> Result<(), EwError> {
+    PgMigrator::new(pgconf, &store::SCHEMA)
+        .await?
+        .apply_pending()
+        .await
+}
+
 pub struct SigmaUSD {
     parser: Parser,
     store: Store,
@@ -31,35 +51,51 @@ impl EventHandling for SigmaUSD {
     type U = CoreData;
     type D = ();
 
-    async fn new(pgconf: &PostgresConfig) -> Self {
+    async fn new(pgconf: &PostgresConfig) -> Result<Self, EwError> {
         // Ensure migrations are applied
-        let mut migrator = PgMigrator::new(pgconf, &store::SCHEMA).await;
-        migrator.apply(&store::migrations::Mig1_1 {}).await;
-        migrator.apply(&store::migrations::Mig1_2 {}).await;
+        migrate(pgconf).await?;
 
-        let store = Store::new(pgconf, &store::SCHEMA).await;
-        let cache = store::load_parser_cache(store.get_client()).await;
+        let store = Store::new(pgconf, &store::SCHEMA).await?;
+        let cache = store::load_parser_cache(store.get_client()).await?;
         let parser = Parser::new(cache);
-        Self { parser, store }
+        Ok(Self { parser, store })
     }
 
-    async fn include_block(&mut self, data: &StampedData<CoreData>) {
+    async fn include_block(&mut self, data: &StampedData<CoreData>) -> Result<(), EwError> {
         // Ignore all data until after contract creation
         if data.height > CONTRACT_CREATION_HEIGHT {
             let stamped_batch = self.parser.extract_batch(data);
-            self.store.persist(&stamped_batch).await;
+            self.store.persist(&stamped_batch).await?;
         }
+        Ok(())
     }
 
-    async fn roll_back(&mut self, height: Height) -> Header {
-        self.store.roll_back(height).await;
+    async fn roll_back(&mut self, height: Height) -> Result<Header, EwError> {
+        self.store.roll_back(height).await?;
         // Refresh parser cache to reflect rollback
-        let cache = store::load_parser_cache(self.store.get_client()).await;
+        let cache = store::load_parser_cache(self.store.get_client()).await?;
         self.parser = Parser::new(cache);
-        self.store.get_header().clone()
+        Ok(self.store.get_header().clone())
     }
 
     fn header<'a>(&'a self) -> &'a Header {
         self.store.get_header()
     }
+
+    async fn flush(&mut self) -> Result<(), EwError> {
+        self.store.flush().await
+    }
+
+    fn figures(&self) -> Option<Figures> {
+        if self.header().height <= CONTRACT_CREATION_HEIGHT {
+            return None;
+        }
+        let record = self.parser.history_record();
+        Some(Figures::SigmaUSD {
+            reserves: record.reserves,
+            circ_sc: record.circ_sc,
+            circ_rc: record.circ_rc,
+            oracle: record.oracle,
+        })
+    }
 }
@@ -50,6 +50,11 @@ impl Parser {
         Self { cache }
     }
 
+    /// Last history record, reflecting current bank state.
+    pub fn history_record(&self) -> &HistoryRecord {
+        &self.cache.last_history_record
+    }
+
     pub(super) fn extract_batch(
         &mut self,
         stamped_data: &StampedData<CoreData>,
@@ -1,18 +1,20 @@
 use async_trait::async_trait;
 use tokio_postgres::Client;
-use tokio_postgres::Transaction;
 
 use crate::core::types::Header;
+use crate::error::EwError;
 use crate::framework::store::BatchStore;
 use crate::framework::store::PgStore;
 use crate::framework::store::Revision;
 use crate::framework::store::StoreDef;
+use crate::framework::store::StoreTransaction;
 use crate::framework::StampedData;
 
 use super::parsing::ParserCache;
 use super::types::Event;
 use super::Batch;
 
+pub(crate) mod api;
 mod bank_transactions;
 mod history;
 mod noop_bank_transactions;
@@ -20,11 +22,17 @@ mod ohlcs;
 mod oracle_postings;
 mod services;
 
-pub(super) const SCHEMA: StoreDef = StoreDef {
+pub const SCHEMA: StoreDef = StoreDef {
     schema_name: super::WORKER_ID,
     worker_id: super::WORKER_ID,
     sql: include_str!("store/schema.sql"),
-    revision: &Revision { major: 1, minor: 2 },
+    revision: &Revision { major: 1, minor: 3 },
+    migrations: &[
+        &migrations::Mig1_1 {},
+        &migrations::Mig1_2 {},
+        &migrations::Mig1_3 {},
+    ],
+    has_header: true,
 };
 
 pub(super) struct SpecStore {}
@@ -39,68 +47,78 @@ impl BatchStore for SpecStore {
         Self {}
     }
 
-    async fn persist(&mut self, pgtx: &Transaction<'_>, stamped_batch: &StampedData<Self::B>) {
+    async fn persist(
+        &mut self,
+        pgtx: &StoreTransaction,
+        stamped_batch: &StampedData<Self::B>,
+    ) -> Result<(), EwError> {
         let batch = &stamped_batch.data;
         // Events
         for event in &batch.events {
             match event {
-                Event::Oracle(op) => oracle_postings::insert(&pgtx, op).await,
-                Event::BankTx(btx) => bank_transactions::insert(&pgtx, btx).await,
-                Event::NoopBankTx(ntx) => noop_bank_transactions::insert(&pgtx, ntx).await,
+                Event::Oracle(op) => oracle_postings::insert(pgtx.client(), op).await?,
+                Event::BankTx(btx) => bank_transactions::insert(pgtx.client(), btx).await?,
+                Event::NoopBankTx(ntx) => {
+                    noop_bank_transactions::insert(pgtx.client(), ntx).await?
+                }
             }
         }
 
         // History record
         if let Some(ref hr) = batch.history_record {
-            history::insert(&pgtx, &hr).await;
+            history::insert(pgtx.client(), &hr).await?;
         }
 
         // OHLC's
         let height = stamped_batch.height;
-        ohlcs::upsert_daily_records(&pgtx, &batch.daily_ohlc_records, height).await;
-        ohlcs::upsert_weekly_records(&pgtx, &batch.weekly_ohlc_records, height).await;
-        ohlcs::upsert_monthly_records(&pgtx, &batch.monthly_ohlc_records, height).await;
+        ohlcs::upsert_daily_records(pgtx.client(), &batch.daily_ohlc_records, height).await?;
+        ohlcs::upsert_weekly_records(pgtx.client(), &batch.weekly_ohlc_records, height).await?;
+        ohlcs::upsert_monthly_records(pgtx.client(), &batch.monthly_ohlc_records, height).await?;
 
         // Service diffs
         for diff in &batch.service_diffs {
-            services::upsert(&pgtx, diff).await;
+            services::upsert(pgtx.client(), diff).await?;
         }
+        Ok(())
     }
 
-    async fn roll_back(&mut self, pgtx: &Transaction<'_>, header: &Header) {
+    async fn roll_back(&mut self, pgtx: &StoreTransaction, header: &Header) -> Result<(), EwError> {
         let height = header.height;
         tracing::debug!("rolling back block {}", height);
         // assert_eq!(self.head.height, height);
 
         // Delete bank txs at h
-        bank_transactions::detele_at(&pgtx, height).await;
-        noop_bank_transactions::detele_at(&pgtx, height).await;
+        bank_transactions::detele_at(pgtx.client(), height).await?;
+        noop_bank_transactions::detele_at(pgtx.client(), height).await?;
 
         // Delete oracle postings at h
-        oracle_postings::delete_at(&pgtx, height).await;
+        oracle_postings::delete_at(pgtx.client(), height).await?;
 
         // Delete history at h
-        history::delete_at(&pgtx, height).await;
+        history::delete_at(pgtx.client(), height).await?;
 
         // Recreate service stats from scratch
-        services::refresh(&pgtx).await;
+        services::refresh(pgtx.client()).await?;
 
         // Restore ohlc from log
-        ohlcs::roll_back_daily(&pgtx, height).await;
-        ohlcs::roll_back_weekly(&pgtx, height).await;
-        ohlcs::roll_back_monthly(&pgtx, height).await;
+        ohlcs::roll_back_daily(pgtx.client(), height).await?;
+        ohlcs::roll_back_weekly(pgtx.client(), height).await?;
+        ohlcs::roll_back_monthly(pgtx.client(), height).await?;
+        Ok(())
     }
 }
 
-pub(super) async fn load_parser_cache(client: &Client) -> ParserCache {
-    ParserCache {
-        bank_transaction_count: bank_transactions::get_count(client).await,
-        last_history_record: history::get_latest(client).await,
-        last_ohlc_group: ohlcs::get_latest_group(client).await,
-    }
+pub(super) async fn load_parser_cache(client: &Client) -> Result<ParserCache, EwError> {
+    Ok(ParserCache {
+        bank_transaction_count: bank_transactions::get_count(client).await?,
+        last_history_record: history::get_latest(client).await?,
+        last_ohlc_group: ohlcs::get_latest_group(client).await?,
+    })
 }
 
 pub(super) mod migrations {
+
+    use crate::error::EwError;
     use async_trait::async_trait;
     use tokio_postgres::Transaction;
 
@@ -122,13 +140,15 @@ pub(super) mod migrations {
             Revision::new(1, 1)
         }
 
-        async fn run(&self, pgtx: &Transaction<'_>) -> MigrationEffect {
+        fn sql(&self) -> Option<&'static str> {
             // Remove sigmausd schema
-            pgtx.execute("drop schema if exists sigmausd cascade;", &[])
-                .await
-                .unwrap();
+            Some("drop schema if exists sigmausd cascade;")
+        }
 
-            MigrationEffect::Purge
+        async fn run(&self, pgtx: &Transaction<'_>) -> Result<MigrationEffect, EwError> {
+            pgtx.batch_execute(self.sql().unwrap()).await?;
+
+            Ok(MigrationEffect::Purge)
         }
     }
 
@@ -146,9 +166,8 @@ pub(super) mod migrations {
             Revision::new(1, 2)
         }
 
-        async fn run(&self, pgtx: &Transaction<'_>) -> MigrationEffect {
-            // Remove sigmausd schema
-            pgtx.execute(
+        fn sql(&self) -> Option<&'static str> {
+            Some(
                 "
                 create table sigmausd.noop_bank_transactions (
                     height integer not null,
@@ -156,12 +175,49 @@ pub(super) mod migrations {
                     tx_id text primary key,
                     box_id text not null
                 );",
-                &[],
             )
-            .await
-            .unwrap();
+        }
+
+        async fn run(&self, pgtx: &Transaction<'_>) -> Result<MigrationEffect, EwError> {
+            pgtx.batch_execute(self.sql().unwrap()).await?;
+
+            Ok(MigrationEffect::None)
+        }
+    }
+
+    /// Migration for revision 1.3
+    #[derive(Debug)]
+    pub struct Mig1_3 {}
+
+    #[async_trait]
+    impl Migration for Mig1_3 {
+        fn description(&self) -> &'static str {
+            "Log initial OHLC records"
+        }
+
+        fn revision(&self) -> Revision {
+            Revision::new(1, 3)
+        }
+
+        fn sql(&self) -> Option<&'static str> {
+            Some(
+                "
+                insert into sigmausd._log_rc_ohlc_daily (height, t, o, h, l, c)
+                values (453064, '2021-03-25', 1000000, 1000000, 1000000, 1000000)
+                on conflict do nothing;
+                insert into sigmausd._log_rc_ohlc_weekly (height, t, o, h, l, c)
+                values (453064, '2021-03-22', 1000000, 1000000, 1000000, 1000000)
+                on conflict do nothing;
+                insert into sigmausd._log_rc_ohlc_monthly (height, t, o, h, l, c)
+                values (453064, '2021-03-01', 1000000, 1000000, 1000000, 1000000)
+                on conflict do nothing;",
+            )
+        }
+
+        async fn run(&self, pgtx: &Transaction<'_>) -> Result<MigrationEffect, EwError> {
+            pgtx.batch_execute(self.sql().unwrap()).await?;
 
-            MigrationEffect::None
+            Ok(MigrationEffect::None)
         }
     }
 }
@@ -0,0 +1,31 @@
+//! Read-only queries served by the query API and evaluated by alert rules.
+use tokio_postgres::GenericClient;
+
+use super::super::types::HistoryRecord;
+use crate::error::EwError;
+
+/// Contract state at the worker's height, if any.
+pub(crate) async fn get_state(
+    client: &impl GenericClient,
+) -> Result<Option<HistoryRecord>, EwError> {
+    let sql = "
+        select height
+            , oracle
+            , circ_sc
+            , circ_rc
+            , reserves
+            , sc_nano_net
+            , rc_nano_net
+        from sigmausd.history
+        order by height desc
+        limit 1;";
+    Ok(client.query_opt(sql, &[]).await?.map(|row| HistoryRecord {
+        height: row.get(0),
+        oracle: row.get(1),
+        circ_sc: row.get(2),
+        circ_rc: row.get(3),
+        reserves: row.get(4),
+        sc_net: row.get(5),
+        rc_net: row.get(6),
+    }))
+}
@@ -1,20 +1,20 @@
 use tokio_postgres::Client;
-use tokio_postgres::Transaction;
+use tokio_postgres::GenericClient;
 
 use crate::core::types::Height;
+use crate::error::EwError;
 
 use super::super::types::BankTransaction;
 
-pub(super) async fn get_count(client: &Client) -> i32 {
+pub(super) async fn get_count(client: &Client) -> Result<i32, EwError> {
     let sql = "select count(*) from sigmausd.bank_transactions;";
-    client
-        .query_one(sql, &[])
-        .await
-        .unwrap()
-        .get::<usize, i64>(0) as i32
+    Ok(client.query_one(sql, &[]).await?.get::<usize, i64>(0) as i32)
 }
 
-pub(super) async fn insert(pgtx: &Transaction<'_>, btx: &BankTransaction) {
+pub(super) async fn insert(
+    pgtx: &impl GenericClient,
+    btx: &BankTransaction,
+) -> Result<(), EwError> {
     let sql = "
         insert into sigmausd.bank_transactions (
             idx,
@@ -43,13 +43,14 @@ pub(super) async fn insert(pgtx: &Transaction<'_>, btx: &BankTransaction) {
             &btx.service_address_id,
         ],
     )
-    .await
-    .unwrap();
+    .await?;
+    Ok(())
 }
 
 /// Delete records at `height`.
-pub(super) async fn detele_at(pgtx: &Transaction<'_>, height: Height) {
+pub(super) async fn detele_at(pgtx: &impl GenericClient, height: Height) -> Result<(), EwError> {
     let sql = "delete from sigmausd.bank_transactions where height = $1;";
 
-    pgtx.execute(sql, &[&height]).await.unwrap();
+    pgtx.execute(sql, &[&height]).await?;
+    Ok(())
 }
@@ -1,10 +1,11 @@
 use tokio_postgres::Client;
-use tokio_postgres::Transaction;
+use tokio_postgres::GenericClient;
 
 use super::super::types::HistoryRecord;
 use crate::core::types::Height;
+use crate::error::EwError;
 
-pub(super) async fn get_latest(client: &Client) -> HistoryRecord {
+pub(super) async fn get_latest(client: &Client) -> Result<HistoryRecord, EwError> {
     let sql = "
         select height
             , oracle
@@ -16,8 +17,8 @@ pub(super) async fn get_latest(client: &Client) -> HistoryRecord {
         from sigmausd.history
         order by height desc limit 1;
     ";
-    let row = client.query_one(sql, &[]).await.unwrap();
-    HistoryRecord {
+    let row = client.query_one(sql, &[]).await?;
+    Ok(HistoryRecord {
         height: row.get(0),
         oracle: row.get(1),
         circ_sc: row.get(2),
@@ -25,10 +26,10 @@ pub(super) async fn get_latest(client: &Client) -> HistoryRecord {
         reserves: row.get(4),
         sc_net: row.get(5),
         rc_net: row.get(6),
-    }
+    })
 }
 
-pub(super) async fn insert(pgtx: &Transaction<'_>, hr: &HistoryRecord) {
+pub(super) async fn insert(pgtx: &impl GenericClient, hr: &HistoryRecord) -> Result<(), EwError> {
     let sql = "
         insert into sigmausd.history (
             height,
@@ -52,11 +53,12 @@ pub(super) async fn insert(pgtx: &Transaction<'_>, hr: &HistoryRecord) {
             &hr.rc_net,
         ],
     )
-    .await
-    .unwrap();
+    .await?;
+    Ok(())
 }
 
-pub(super) async fn delete_at(pgtx: &Transaction<'_>, at: Height) {
+pub(super) async fn delete_at(pgtx: &impl GenericClient, at: Height) -> Result<(), EwError> {
     let sql = "delete from sigmausd.history where height = $1;";
-    pgtx.execute(sql, &[&at]).await.unwrap();
+    pgtx.execute(sql, &[&at]).await?;
+    Ok(())
 }
@@ -1,10 +1,14 @@
-use tokio_postgres::Transaction;
+use tokio_postgres::GenericClient;
 
 use crate::core::types::Height;
+use crate::error::EwError;
 
 use super::super::types::NoopBankTransaction;
 
-pub(super) async fn insert(pgtx: &Transaction<'_>, ntx: &NoopBankTransaction) {
+pub(super) async fn insert(
+    pgtx: &impl GenericClient,
+    ntx: &NoopBankTransaction,
+) -> Result<(), EwError> {
     let sql = "
         insert into sigmausd.noop_bank_transactions (
             height,
@@ -15,13 +19,14 @@ pub(super) async fn insert(pgtx: &Transaction<'_>, ntx: &NoopBankTransaction) {
         values ($1, $2, $3, $4);
     ";
     pgtx.execute(sql, &[&ntx.height, &ntx.tx_idx, &ntx.tx_id, &ntx.box_id])
-        .await
-        .unwrap();
+        .await?;
+    Ok(())
 }
 
 /// Delete records at `height`.
-pub(super) async fn detele_at(pgtx: &Transaction<'_>, height: Height) {
+pub(super) async fn detele_at(pgtx: &impl GenericClient, height: Height) -> Result<(), EwError> {
     let sql = "delete from sigmausd.noop_bank_transactions where height = $1;";
 
-    pgtx.execute(sql, &[&height]).await.unwrap();
+    pgtx.execute(sql, &[&height]).await?;
+    Ok(())
 }
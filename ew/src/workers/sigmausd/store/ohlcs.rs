@@ -1,5 +1,5 @@
 use tokio_postgres::Client;
-use tokio_postgres::Transaction;
+use tokio_postgres::GenericClient;
 
 use super::super::types::DailyOHLC;
 use super::super::types::MonthlyOHLC;
@@ -7,16 +7,17 @@ use super::super::types::OHLCGroup;
 use super::super::types::WeeklyOHLC;
 use super::super::types::OHLC;
 use crate::core::types::Height;
+use crate::error::EwError;
 
-pub(super) async fn get_latest_group(client: &Client) -> OHLCGroup {
-    OHLCGroup {
-        daily: get_latest_daily(client).await,
-        weekly: get_latest_weekly(client).await,
-        monthly: get_latest_monthly(client).await,
-    }
+pub(super) async fn get_latest_group(client: &Client) -> Result<OHLCGroup, EwError> {
+    Ok(OHLCGroup {
+        daily: get_latest_daily(client).await?,
+        weekly: get_latest_weekly(client).await?,
+        monthly: get_latest_monthly(client).await?,
+    })
 }
 
-async fn get_latest_daily(client: &Client) -> DailyOHLC {
+async fn get_latest_daily(client: &Client) -> Result<DailyOHLC, EwError> {
     let qry = "
         select t
             , o
@@ -25,17 +26,17 @@ async fn get_latest_daily(client: &Client) -> DailyOHLC {
             , c
         from sigmausd.rc_ohlc_daily order by 1 desc limit 1;
     ";
-    let row = client.query_one(qry, &[]).await.unwrap();
-    DailyOHLC(OHLC {
+    let row = client.query_one(qry, &[]).await?;
+    Ok(DailyOHLC(OHLC {
         t: row.get(0),
         o: row.get(1),
         h: row.get(2),
         l: row.get(3),
         c: row.get(4),
-    })
+    }))
 }
 
-async fn get_latest_weekly(client: &Client) -> WeeklyOHLC {
+async fn get_latest_weekly(client: &Client) -> Result<WeeklyOHLC, EwError> {
     let qry = "
         select t
             , o
@@ -44,17 +45,17 @@ async fn get_latest_weekly(client: &Client) -> WeeklyOHLC {
             , c
         from sigmausd.rc_ohlc_weekly order by 1 desc limit 1;
     ";
-    let row = client.query_one(qry, &[]).await.unwrap();
-    WeeklyOHLC(OHLC {
+    let row = client.query_one(qry, &[]).await?;
+    Ok(WeeklyOHLC(OHLC {
         t: row.get(0),
         o: row.get(1),
         h: row.get(2),
         l: row.get(3),
         c: row.get(4),
-    })
+    }))
 }
 
-async fn get_latest_monthly(client: &Client) -> MonthlyOHLC {
+async fn get_latest_monthly(client: &Client) -> Result<MonthlyOHLC, EwError> {
     let qry = "
         select t
             , o
@@ -63,24 +64,24 @@ async fn get_latest_monthly(client: &Client) -> MonthlyOHLC {
             , c
         from sigmausd.rc_ohlc_monthly order by 1 desc limit 1;
     ";
-    let row = client.query_one(qry, &[]).await.unwrap();
-    MonthlyOHLC(OHLC {
+    let row = client.query_one(qry, &[]).await?;
+    Ok(MonthlyOHLC(OHLC {
         t: row.get(0),
         o: row.get(1),
         h: row.get(2),
         l: row.get(3),
         c: row.get(4),
-    })
+    }))
 }
 
 /// Add records to daily OHLC series.
 ///
 /// Replaces possible existing record for same date.
 pub(super) async fn upsert_daily_records(
-    pgtx: &Transaction<'_>,
+    pgtx: &impl GenericClient,
     recs: &Vec<DailyOHLC>,
     height: Height,
-) {
+) -> Result<(), EwError> {
     // Upsert new records
     let sql = "
         insert into sigmausd.rc_ohlc_daily (t, o, h, l, c)
@@ -94,8 +95,7 @@ pub(super) async fn upsert_daily_records(
         ";
     for rec in recs {
         pgtx.execute(sql, &[&rec.0.t, &rec.0.o, &rec.0.h, &rec.0.l, &rec.0.c])
-            .await
-            .unwrap();
+            .await?;
     }
     // Log last record
     if let Some(rec) = &recs.last() {
@@ -107,19 +107,19 @@ pub(super) async fn upsert_daily_records(
             sql,
             &[&height, &rec.0.t, &rec.0.o, &rec.0.h, &rec.0.l, &rec.0.c],
         )
-        .await
-        .unwrap();
+        .await?;
     }
+    Ok(())
 }
 
 /// Add records to weekly OHLC series.
 ///
 /// Replaces possible existing record for same date.
 pub(super) async fn upsert_weekly_records(
-    pgtx: &Transaction<'_>,
+    pgtx: &impl GenericClient,
     recs: &Vec<WeeklyOHLC>,
     height: Height,
-) {
+) -> Result<(), EwError> {
     // Upsert new records
     let sql = "
         insert into sigmausd.rc_ohlc_weekly (t, o, h, l, c)
@@ -133,8 +133,7 @@ pub(super) async fn upsert_weekly_records(
     ";
     for rec in recs {
         pgtx.execute(sql, &[&rec.0.t, &rec.0.o, &rec.0.h, &rec.0.l, &rec.0.c])
-            .await
-            .unwrap();
+            .await?;
     }
     // Log last record
     if let Some(rec) = recs.last() {
@@ -146,19 +145,19 @@ pub(super) async fn upsert_weekly_records(
             sql,
             &[&height, &rec.0.t, &rec.0.o, &rec.0.h, &rec.0.l, &rec.0.c],
         )
-        .await
-        .unwrap();
+        .await?;
     }
+    Ok(())
 }
 
 /// Add records to monthly OHLC series.
 ///
 /// Replaces possible existing record for same date.
 pub(super) async fn upsert_monthly_records(
-    pgtx: &Transaction<'_>,
+    pgtx: &impl GenericClient,
     recs: &Vec<MonthlyOHLC>,
     height: Height,
-) {
+) -> Result<(), EwError> {
     // Upsert new records
     let sql = "
         insert into sigmausd.rc_ohlc_monthly (t, o, h, l, c)
@@ -172,8 +171,7 @@ pub(super) async fn upsert_monthly_records(
     ";
     for rec in recs {
         pgtx.execute(sql, &[&rec.0.t, &rec.0.o, &rec.0.h, &rec.0.l, &rec.0.c])
-            .await
-            .unwrap();
+            .await?;
     }
     // Log last record
     if let Some(rec) = recs.last() {
@@ -185,27 +183,39 @@ pub(super) async fn upsert_monthly_records(
             sql,
             &[&height, &rec.0.t, &rec.0.o, &rec.0.h, &rec.0.l, &rec.0.c],
         )
-        .await
-        .unwrap();
+        .await?;
     }
+    Ok(())
 }
 
 /// Restores previous known state if current block modified it.
-pub(super) async fn roll_back_daily(pgtx: &Transaction<'_>, height: Height) {
-    roll_back(pgtx, height, "daily").await;
+pub(super) async fn roll_back_daily(
+    pgtx: &impl GenericClient,
+    height: Height,
+) -> Result<(), EwError> {
+    roll_back(pgtx, height, "daily").await?;
+    Ok(())
 }
 
 /// Restores previous known state if current block modified it.
-pub(super) async fn roll_back_weekly(pgtx: &Transaction<'_>, height: Height) {
-    roll_back(pgtx, height, "weekly").await;
+pub(super) async fn roll_back_weekly(
+    pgtx: &impl GenericClient,
+    height: Height,
+) -> Result<(), EwError> {
+    roll_back(pgtx, height, "weekly").await?;
+    Ok(())
 }
 
 /// Restores previous known state if current block modified it.
-pub(super) async fn roll_back_monthly(pgtx: &Transaction<'_>, height: Height) {
-    roll_back(pgtx, height, "monthly").await;
+pub(super) async fn roll_back_monthly(
+    pgtx: &impl GenericClient,
+    height: Height,
+) -> Result<(), EwError> {
+    roll_back(pgtx, height, "monthly").await?;
+    Ok(())
 }
 
-async fn roll_back(pgtx: &Transaction<'_>, height: Height, window: &str) {
+async fn roll_back(pgtx: &impl GenericClient, height: Height, window: &str) -> Result<(), EwError> {
     // Get log entries for height to roll back
     let sql = format!(
         "
@@ -215,8 +225,7 @@ async fn roll_back(pgtx: &Transaction<'_>, height: Height, window: &str) {
     );
     let dates: Vec<time::Date> = pgtx
         .query(&sql, &[&height])
-        .await
-        .unwrap()
+        .await?
         .iter()
         .map(|row| row.get(0))
         .collect();
@@ -228,7 +237,7 @@ async fn roll_back(pgtx: &Transaction<'_>, height: Height, window: &str) {
             delete from sigmausd.rc_ohlc_{window}
             where t = $1;"
         );
-        pgtx.execute(&sql, &[date]).await.unwrap();
+        pgtx.execute(&sql, &[date]).await?;
     }
 
     // then delete from logs
@@ -237,7 +246,21 @@ async fn roll_back(pgtx: &Transaction<'_>, height: Height, window: &str) {
         delete from sigmausd._log_rc_ohlc_{window}
         where height = $1;"
     );
-    pgtx.execute(&sql, &[&height]).await.unwrap();
+    pgtx.execute(&sql, &[&height]).await?;
+
+    // Only the last record of a block gets logged, so also
+    // delete any gap-filled ones following the remaining logs.
+    let sql = format!(
+        "
+        delete from sigmausd.rc_ohlc_{window}
+        where t > (
+            select t
+            from sigmausd._log_rc_ohlc_{window}
+            order by height desc
+            limit 1
+        );"
+    );
+    pgtx.execute(&sql, &[]).await?;
 
     // insert back most recent logs
     // conflicts can be ignored as record would be the same
@@ -250,5 +273,6 @@ async fn roll_back(pgtx: &Transaction<'_>, height: Height, window: &str) {
         where height = (select max(height) from sigmausd._log_rc_ohlc_{window})
         on conflict do nothing;"
     );
-    pgtx.execute(&sql, &[]).await.unwrap();
+    pgtx.execute(&sql, &[]).await?;
+    Ok(())
 }
@@ -1,19 +1,21 @@
-use tokio_postgres::Transaction;
+use tokio_postgres::GenericClient;
 
 use super::super::types::OraclePosting;
 use crate::core::types::Height;
+use crate::error::EwError;
 
-pub async fn insert(pgtx: &Transaction<'_>, op: &OraclePosting) {
+pub async fn insert(pgtx: &impl GenericClient, op: &OraclePosting) -> Result<(), EwError> {
     let stmt = "
         insert into sigmausd.oracle_postings (height, datapoint, box_id)
         values ($1, $2, $3);
         ";
     pgtx.execute(stmt, &[&op.height, &op.datapoint, &op.box_id])
-        .await
-        .unwrap();
+        .await?;
+    Ok(())
 }
 
-pub async fn delete_at(pgtx: &Transaction<'_>, at: Height) {
+pub async fn delete_at(pgtx: &impl GenericClient, at: Height) -> Result<(), EwError> {
     let stmt = "delete from sigmausd.oracle_postings where height = $1;";
-    pgtx.execute(stmt, &[&at]).await.unwrap();
+    pgtx.execute(stmt, &[&at]).await?;
+    Ok(())
 }
@@ -213,4 +213,12 @@ values (
     1000000,
     1000000,
     1000000
-);  
+);
+
+-- Log initial OHLC records so rollbacks can restore them
+insert into sigmausd._log_rc_ohlc_daily (height, t, o, h, l, c)
+select 453064, t, o, h, l, c from sigmausd.rc_ohlc_daily;
+insert into sigmausd._log_rc_ohlc_weekly (height, t, o, h, l, c)
+select 453064, t, o, h, l, c from sigmausd.rc_ohlc_weekly;
+insert into sigmausd._log_rc_ohlc_monthly (height, t, o, h, l, c)
+select 453064, t, o, h, l, c from sigmausd.rc_ohlc_monthly;
@@ -1,8 +1,10 @@
-use tokio_postgres::Transaction;
+use tokio_postgres::GenericClient;
 
 use super::super::types::ServiceStats;
 
-pub(super) async fn upsert(pgtx: &Transaction<'_>, diff: &ServiceStats) {
+use crate::error::EwError;
+
+pub(super) async fn upsert(pgtx: &impl GenericClient, diff: &ServiceStats) -> Result<(), EwError> {
     let sql = "
         insert into sigmausd.services as t (
             address_id,
@@ -30,15 +32,13 @@ pub(super) async fn upsert(pgtx: &Transaction<'_>, diff: &ServiceStats) {
             &diff.volume,
         ],
     )
-    .await
-    .unwrap();
+    .await?;
+    Ok(())
 }
 
 /// Truncates and repopulates service stats from a bank transactions query.
-pub(super) async fn refresh(pgtx: &Transaction<'_>) {
-    pgtx.execute("truncate sigmausd.services;", &[])
-        .await
-        .unwrap();
+pub(super) async fn refresh(pgtx: &impl GenericClient) -> Result<(), EwError> {
+    pgtx.execute("truncate sigmausd.services;", &[]).await?;
     let qry = "
         insert into sigmausd.services (
             address_id,
@@ -59,5 +59,6 @@ pub(super) async fn refresh(pgtx: &Transaction<'_>) {
         -- order by first_tx to reproduce order of appearance
         order by 3;
     ";
-    pgtx.execute(qry, &[]).await.unwrap();
+    pgtx.execute(qry, &[]).await?;
+    Ok(())
 }
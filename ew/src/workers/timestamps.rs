@@ -2,14 +2,27 @@ mod parsing;
 mod store;
 mod types;
 
+/// Makes migrations available for testing
+pub mod testing {
+    use super::store;
+    pub use store::migrations::*;
+    pub use store::SCHEMA;
+}
+
+pub(crate) use store::api;
+pub(crate) use store::SCHEMA;
+
 use async_trait::async_trait;
 
 use crate::config::PostgresConfig;
 use crate::core::types::CoreData;
 use crate::core::types::Header;
 use crate::core::types::Height;
+use crate::error::EwError;
+use crate::framework::store::PgMigrator;
 use crate::framework::EventHandling;
 use crate::framework::LeafWorker;
+use crate::framework::Rewinding;
 use crate::framework::StampedData;
 use parsing::Parser;
 use store::Store;
@@ -18,6 +31,14 @@ const WORKER_ID: &'static str = "timestamps";
 
 pub type Worker = LeafWorker<TimestampsWorkFlow>;
 
+/// Initializes the worker's store and applies pending migrations.
+pub async fn migrate(pgconf: &PostgresConfig) -> Result<(), EwError> {
+    PgMigrator::new(pgconf, &store::SCHEMA)
+        .await?
+        .apply_pending()
+        .await
+}
+
 pub struct TimestampsWorkFlow {
     parser: Parser,
     store: Store,
@@ -28,28 +49,52 @@ impl EventHandling for TimestampsWorkFlow {
     type U = CoreData;
     type D = ();
 
-    async fn new(pgconf: &PostgresConfig) -> Self {
-        let store = Store::new(pgconf, &store::SCHEMA).await;
-        let cache = store::load_parser_cache(store.get_client()).await;
+    async fn new(pgconf: &PostgresConfig) -> Result<Self, EwError> {
+        // Ensure migrations are applied
+        migrate(pgconf).await?;
+
+        let mut store = Store::new(pgconf, &store::SCHEMA).await?;
+        store.set_batch_blocks(pgconf.batch_blocks);
+        let cache = store::load_parser_cache(store.get_client()).await?;
         let parser = Parser::new(cache);
-        Self { parser, store }
+        Ok(Self { parser, store })
     }
 
-    async fn include_block(&mut self, data: &StampedData<CoreData>) {
+    async fn include_block(&mut self, data: &StampedData<CoreData>) -> Result<(), EwError> {
         // Make extract_batch return StampedData<Batch>
         let stamped_batch = self.parser.extract_batch(&data);
-        self.store.persist(&stamped_batch).await;
+        self.store.persist(&stamped_batch).await?;
+        Ok(())
     }
 
-    async fn roll_back(&mut self, height: Height) -> Header {
-        self.store.roll_back(height).await;
+    async fn roll_back(&mut self, height: Height) -> Result<Header, EwError> {
+        self.store.roll_back(height).await?;
         // Refresh parser cache to reflect rollback
-        let cache = store::load_parser_cache(self.store.get_client()).await;
+        let cache = store::load_parser_cache(self.store.get_client()).await?;
         self.parser = Parser::new(cache);
-        self.store.get_header().clone()
+        Ok(self.store.get_header().clone())
     }
 
     fn header<'a>(&'a self) -> &'a Header {
         self.store.get_header()
     }
+
+    async fn flush(&mut self) -> Result<(), EwError> {
+        self.store.flush().await
+    }
+
+    fn has_uncommitted(&self) -> bool {
+        self.store.has_uncommitted()
+    }
+}
+
+#[async_trait]
+impl Rewinding for TimestampsWorkFlow {
+    async fn rewind(&mut self, height: Height) -> Result<Header, EwError> {
+        self.store.rewind(height).await?;
+        // Refresh parser cache to reflect rewind
+        let cache = store::load_parser_cache(self.store.get_client()).await?;
+        self.parser = Parser::new(cache);
+        Ok(self.store.get_header().clone())
+    }
 }
@@ -1,12 +1,15 @@
 use async_trait::async_trait;
 use tokio_postgres::Client;
-use tokio_postgres::Transaction;
 
 use crate::core::types::Header;
+use crate::core::types::Height;
+use crate::error::EwError;
 use crate::framework::store::BatchStore;
 use crate::framework::store::PgStore;
 use crate::framework::store::Revision;
+use crate::framework::store::RewindableStore;
 use crate::framework::store::StoreDef;
+use crate::framework::store::StoreTransaction;
 use crate::framework::StampedData;
 
 use super::parsing::ParserCache;
@@ -15,16 +18,19 @@ use super::types::Batch;
 use super::types::TimestampRecord;
 use super::WORKER_ID;
 
+pub(crate) mod api;
 mod daily;
 mod hourly;
 mod timestamps;
 mod weekly;
 
-pub(super) const SCHEMA: StoreDef = StoreDef {
+pub const SCHEMA: StoreDef = StoreDef {
     schema_name: WORKER_ID,
     worker_id: WORKER_ID,
     sql: include_str!("store/schema.sql"),
-    revision: &Revision { major: 1, minor: 0 },
+    revision: &Revision { major: 1, minor: 1 },
+    migrations: &[&migrations::Mig1_1 {}],
+    has_header: true,
 };
 
 pub(super) struct SpecStore {}
@@ -39,40 +45,45 @@ impl BatchStore for SpecStore {
         Self {}
     }
 
-    async fn persist(&mut self, pgtx: &Transaction<'_>, stamped_batch: &StampedData<Self::B>) {
+    async fn persist(
+        &mut self,
+        pgtx: &StoreTransaction,
+        stamped_batch: &StampedData<Self::B>,
+    ) -> Result<(), EwError> {
         // Timestamps
-        timestamps::insert(&pgtx, stamped_batch.height, stamped_batch.timestamp).await;
+        timestamps::insert(pgtx.client(), stamped_batch.height, stamped_batch.timestamp).await?;
 
         // Hourly
         for action in &stamped_batch.data.hourly {
             // tracing::debug!("hourly...");
             match action {
-                Action::INSERT(record) => hourly::insert(&pgtx, record).await,
-                Action::UPDATE(record) => hourly::update(&pgtx, record).await,
-                Action::DELETE(height) => hourly::delete_at(&pgtx, height).await,
+                Action::INSERT(record) => hourly::insert(pgtx.client(), record).await?,
+                Action::UPDATE(record) => hourly::update(pgtx.client(), record).await?,
+                Action::DELETE(height) => hourly::delete_at(pgtx.client(), height).await?,
             }
         }
 
         // Daily
         for action in &stamped_batch.data.daily {
             match action {
-                Action::INSERT(record) => daily::insert(&pgtx, record).await,
-                Action::UPDATE(record) => daily::update(&pgtx, record).await,
-                Action::DELETE(height) => daily::delete_at(&pgtx, height).await,
+                Action::INSERT(record) => daily::insert(pgtx.client(), record).await?,
+                Action::UPDATE(record) => daily::update(pgtx.client(), record).await?,
+                Action::DELETE(height) => daily::delete_at(pgtx.client(), height).await?,
             }
         }
 
         // Weekly
         for action in &stamped_batch.data.weekly {
             match action {
-                Action::INSERT(record) => weekly::insert(&pgtx, record).await,
-                Action::UPDATE(record) => weekly::update(&pgtx, record).await,
-                Action::DELETE(height) => weekly::delete_at(&pgtx, height).await,
+                Action::INSERT(record) => weekly::insert(pgtx.client(), record).await?,
+                Action::UPDATE(record) => weekly::update(pgtx.client(), record).await?,
+                Action::DELETE(height) => weekly::delete_at(pgtx.client(), height).await?,
             }
         }
+        Ok(())
     }
 
-    async fn roll_back(&mut self, pgtx: &Transaction<'_>, header: &Header) {
+    async fn roll_back(&mut self, pgtx: &StoreTransaction, header: &Header) -> Result<(), EwError> {
         // Delete timestamps after parent's block's timestamp and reinsert
         // parent block's timestamp if necessary.
         // In examples below, we roll back block 8.
@@ -92,66 +103,101 @@ impl BatchStore for SpecStore {
         // before :        5        8
         // after  :        5     7
 
-        let height = header.height;
-        tracing::debug!("rolling back block {}", height);
-
-        timestamps::delete_at(&pgtx, height).await;
-        let parent_height = height - 1;
-        let parent_timestamp = timestamps::get_at(pgtx, parent_height).await;
-
-        // Delete timestamps past new latest one
-        hourly::delete_after(&pgtx, parent_timestamp).await;
-        daily::delete_after(&pgtx, parent_timestamp).await;
-        weekly::delete_after(&pgtx, parent_timestamp).await;
-
-        // Reinsert last hourly timestamp if needed
-        let last = hourly::get_last(pgtx)
-            .await
-            .expect("always data left after a roll back");
-        if last.height < parent_height {
-            hourly::insert(
-                &pgtx,
-                &TimestampRecord::new(parent_height, parent_timestamp),
-            )
-            .await;
-        }
+        tracing::debug!("rolling back block {}", header.height);
+        truncate(pgtx, header.height - 1).await
+    }
+}
 
-        // Reinsert last daily timestamp if needed
-        let last = daily::get_last(pgtx)
-            .await
-            .expect("always data left after a roll back");
-        if last.height < parent_height {
-            daily::insert(
-                &pgtx,
-                &TimestampRecord::new(parent_height, parent_timestamp),
-            )
-            .await;
-        }
+#[async_trait]
+impl RewindableStore for SpecStore {
+    async fn rewind(&mut self, pgtx: &StoreTransaction, height: Height) -> Result<(), EwError> {
+        tracing::debug!("rewinding to block {height}");
+        truncate(pgtx, height).await
+    }
+}
 
-        // Reinsert last weekly timestamp if needed
-        let last = weekly::get_last(pgtx)
-            .await
-            .expect("always data left after a roll back");
-        if last.height < parent_height {
-            weekly::insert(
-                &pgtx,
-                &TimestampRecord::new(parent_height, parent_timestamp),
-            )
-            .await;
-        }
+/// Delete timestamps after block at given `height` and reinsert its
+/// timestamp where needed.
+async fn truncate(pgtx: &StoreTransaction, height: Height) -> Result<(), EwError> {
+    timestamps::delete_after(pgtx.client(), height).await?;
+    let timestamp = timestamps::get_at(pgtx.client(), height).await?;
+
+    // Delete timestamps past new latest one
+    hourly::delete_after(pgtx.client(), timestamp).await?;
+    daily::delete_after(pgtx.client(), timestamp).await?;
+    weekly::delete_after(pgtx.client(), timestamp).await?;
+
+    // Reinsert last hourly timestamp if needed
+    let last = hourly::get_last(pgtx.client())
+        .await?
+        .expect("always data left after a roll back");
+    if last.height < height {
+        hourly::insert(pgtx.client(), &TimestampRecord::new(height, timestamp)).await?;
+    }
+
+    // Reinsert last daily timestamp if needed
+    let last = daily::get_last(pgtx.client())
+        .await?
+        .expect("always data left after a roll back");
+    if last.height < height {
+        daily::insert(pgtx.client(), &TimestampRecord::new(height, timestamp)).await?;
     }
+
+    // Reinsert last weekly timestamp if needed
+    let last = weekly::get_last(pgtx.client())
+        .await?
+        .expect("always data left after a roll back");
+    if last.height < height {
+        weekly::insert(pgtx.client(), &TimestampRecord::new(height, timestamp)).await?;
+    }
+    Ok(())
 }
 
-pub(super) async fn load_parser_cache(client: &Client) -> ParserCache {
-    ParserCache {
+pub(super) async fn load_parser_cache(client: &Client) -> Result<ParserCache, EwError> {
+    Ok(ParserCache {
         last_hourly: hourly::get_last(client)
-            .await
+            .await?
             .unwrap_or(TimestampRecord::initial()),
         last_daily: daily::get_last(client)
-            .await
+            .await?
             .unwrap_or(TimestampRecord::initial()),
         last_weekly: weekly::get_last(client)
-            .await
+            .await?
             .unwrap_or(TimestampRecord::initial()),
+    })
+}
+
+pub(super) mod migrations {
+    use async_trait::async_trait;
+    use tokio_postgres::Transaction;
+
+    use crate::error::EwError;
+    use crate::framework::store::Migration;
+    use crate::framework::store::MigrationEffect;
+    use crate::framework::store::Revision;
+
+    /// Migration for revision 1.1
+    #[derive(Debug)]
+    pub struct Mig1_1 {}
+
+    #[async_trait]
+    impl Migration for Mig1_1 {
+        fn description(&self) -> &'static str {
+            "Index block timestamps"
+        }
+
+        fn revision(&self) -> Revision {
+            Revision::new(1, 1)
+        }
+
+        fn sql(&self) -> Option<&'static str> {
+            Some("create index on timestamps.timestamps (timestamp);")
+        }
+
+        async fn run(&self, pgtx: &Transaction<'_>) -> Result<MigrationEffect, EwError> {
+            pgtx.batch_execute(self.sql().unwrap()).await?;
+
+            Ok(MigrationEffect::None)
+        }
     }
 }
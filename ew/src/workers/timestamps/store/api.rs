@@ -0,0 +1,23 @@
+//! Read-only queries served by the query API.
+use tokio_postgres::GenericClient;
+
+use crate::core::types::Height;
+use crate::core::types::Timestamp;
+use crate::error::EwError;
+
+/// Height of last block with a timestamp not after given `timestamp`, if any.
+pub(crate) async fn get_height_at(
+    client: &impl GenericClient,
+    timestamp: Timestamp,
+) -> Result<Option<Height>, EwError> {
+    let sql = "
+        select height
+        from timestamps.timestamps
+        where timestamp <= $1
+        order by timestamp desc
+        limit 1;";
+    Ok(client
+        .query_opt(sql, &[&timestamp])
+        .await?
+        .map(|row| row.get(0)))
+}
@@ -5,6 +5,7 @@ create table timestamps.timestamps (
     height integer primary key,
     timestamp bigint not null
 );
+create index on timestamps.timestamps (timestamp);
 
 -- Hourly timestamps
 -- Holds height of last block at each round hour.
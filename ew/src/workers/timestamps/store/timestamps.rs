@@ -1,34 +1,40 @@
-use tokio_postgres::Transaction;
+use tokio_postgres::GenericClient;
 
 use crate::core::types::Height;
 use crate::core::types::Timestamp;
+use crate::error::EwError;
 
-pub async fn get_at(pgtx: &Transaction<'_>, height: Height) -> Timestamp {
+pub async fn get_at(pgtx: &impl GenericClient, height: Height) -> Result<Timestamp, EwError> {
     tracing::trace!("get_at {height}");
     let qry = "
         select timestamp
         from timestamps.timestamps
         where height = $1;";
-    pgtx.query_one(qry, &[&height])
-        .await
-        .unwrap()
-        .get::<usize, Timestamp>(0)
+    Ok(pgtx
+        .query_one(qry, &[&height])
+        .await?
+        .get::<usize, Timestamp>(0))
 }
 
-pub async fn insert(pgtx: &Transaction<'_>, height: Height, timestamp: Timestamp) {
+pub async fn insert(
+    pgtx: &impl GenericClient,
+    height: Height,
+    timestamp: Timestamp,
+) -> Result<(), EwError> {
     tracing::trace!("insert {height} {timestamp}");
     let stmt = "
         insert into timestamps.timestamps (height, timestamp)
         values ($1, $2);";
-    pgtx.execute(stmt, &[&height, &timestamp]).await.unwrap();
+    pgtx.execute(stmt, &[&height, &timestamp]).await?;
+    Ok(())
 }
 
-pub async fn delete_at(pgtx: &Transaction<'_>, height: Height) {
-    tracing::trace!("delete_at {height}");
+pub async fn delete_after(pgtx: &impl GenericClient, height: Height) -> Result<(), EwError> {
+    tracing::trace!("delete_after {height}");
     pgtx.execute(
-        "delete from timestamps.timestamps where height = $1;",
+        "delete from timestamps.timestamps where height > $1;",
         &[&height],
     )
-    .await
-    .unwrap();
+    .await?;
+    Ok(())
 }
@@ -1,12 +1,14 @@
 use crate::core::types::Height;
 use crate::core::types::Timestamp;
+use crate::error::EwError;
 use tokio_postgres::GenericClient;
-use tokio_postgres::Transaction;
 
 use super::super::types::TimestampRecord;
 
 /// Returns record with latest timestamp, if any
-pub(super) async fn get_last(client: &impl GenericClient) -> Option<TimestampRecord> {
+pub(super) async fn get_last(
+    client: &impl GenericClient,
+) -> Result<Option<TimestampRecord>, EwError> {
     tracing::trace!("get last");
     let qry = "
         select height
@@ -14,51 +16,61 @@ pub(super) async fn get_last(client: &impl GenericClient) -> Option<TimestampRec
         from timestamps.weekly
         order by timestamp desc
         limit 1;";
-    client
+    Ok(client
         .query_opt(qry, &[])
-        .await
-        .unwrap()
+        .await?
         .map(|row| TimestampRecord {
             height: row.get(0),
             timestamp: row.get(1),
-        })
+        }))
 }
 
 /// Insert
-pub(super) async fn insert(pgtx: &Transaction<'_>, record: &TimestampRecord) {
+pub(super) async fn insert(
+    pgtx: &impl GenericClient,
+    record: &TimestampRecord,
+) -> Result<(), EwError> {
     tracing::trace!("insert {record:?}");
     let stmt = "insert into timestamps.weekly (height, timestamp) values ($1, $2);";
     pgtx.execute(stmt, &[&record.height, &record.timestamp])
-        .await
-        .unwrap();
+        .await?;
+    Ok(())
 }
 
 /// Update timestamp of record with same height.
-pub(super) async fn update(pgtx: &Transaction<'_>, record: &TimestampRecord) {
+pub(super) async fn update(
+    pgtx: &impl GenericClient,
+    record: &TimestampRecord,
+) -> Result<(), EwError> {
     tracing::trace!("update {record:?}");
     let stmt = "
         update timestamps.weekly
         set timestamp = $2
         where height = $1;";
     pgtx.execute(stmt, &[&record.height, &record.timestamp])
-        .await
-        .unwrap();
+        .await?;
+    Ok(())
 }
 
 /// Delete records at `height`.
-pub(super) async fn delete_at(pgtx: &Transaction<'_>, height: &Height) {
+pub(super) async fn delete_at(pgtx: &impl GenericClient, height: &Height) -> Result<(), EwError> {
     tracing::trace!("delete at {height}");
     let stmt = "
         delete from timestamps.weekly
         where height = $1;";
-    pgtx.execute(stmt, &[height]).await.unwrap();
+    pgtx.execute(stmt, &[height]).await?;
+    Ok(())
 }
 
 /// Delete all records with a timestamp after `timestamp`
-pub(super) async fn delete_after(pgtx: &Transaction<'_>, timestamp: Timestamp) {
+pub(super) async fn delete_after(
+    pgtx: &impl GenericClient,
+    timestamp: Timestamp,
+) -> Result<(), EwError> {
     tracing::trace!("delete after {timestamp}");
     let stmt = "
         delete from timestamps.weekly
         where timestamp > $1;";
-    pgtx.execute(stmt, &[&timestamp]).await.unwrap();
+    pgtx.execute(stmt, &[&timestamp]).await?;
+    Ok(())
 }
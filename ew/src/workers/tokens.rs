@@ -2,14 +2,20 @@ mod parsing;
 mod store;
 pub mod types;
 
+pub(crate) use store::api;
+pub(crate) use store::SCHEMA;
+
 use async_trait::async_trait;
 
 use crate::config::PostgresConfig;
 use crate::core::types::CoreData;
 use crate::core::types::Header;
 use crate::core::types::Height;
+use crate::error::EwError;
+use crate::framework::store::PgMigrator;
 use crate::framework::EventHandling;
 use crate::framework::LeafWorker;
+use crate::framework::Rewinding;
 use crate::framework::StampedData;
 use parsing::Parser;
 use store::Store;
@@ -18,6 +24,14 @@ const WORKER_ID: &'static str = "tokens";
 
 pub type Worker = LeafWorker<TokensWorkFlow>;
 
+/// Initializes the worker's store and applies pending migrations.
+pub async fn migrate(pgconf: &PostgresConfig) -> Result<(), EwError> {
+    PgMigrator::new(pgconf, &store::SCHEMA)
+        .await?
+        .apply_pending()
+        .await
+}
+
 pub struct TokensWorkFlow {
     parser: Parser,
     store: Store,
@@ -28,31 +42,53 @@ impl EventHandling for TokensWorkFlow {
     type U = CoreData;
     type D = ();
 
-    async fn new(pgconf: &PostgresConfig) -> Self {
-        let store = Store::new(pgconf, &store::SCHEMA).await;
+    async fn new(pgconf: &PostgresConfig) -> Result<Self, EwError> {
+        // Ensure migrations are applied
+        migrate(pgconf).await?;
+
+        let mut store = Store::new(pgconf, &store::SCHEMA).await?;
+        store.set_batch_blocks(pgconf.batch_blocks);
         let parser = Parser::new();
-        Self { parser, store }
+        Ok(Self { parser, store })
     }
 
-    async fn include_block(&mut self, data: &StampedData<CoreData>) -> Self::D {
+    async fn include_block(&mut self, data: &StampedData<CoreData>) -> Result<Self::D, EwError> {
         // First, get diff records
         let diff_records = self.parser.extract_diffs(&data);
         // Then get current balances for all address/assets within block
         let balances = self
             .store
             .map_balance_records(parsing::diffed_address_assets(&diff_records))
-            .await;
+            .await?;
         let stamped_batch = self.parser.extract_batch(data, diff_records, balances);
-        self.store.persist(&stamped_batch).await;
+        self.store.persist(&stamped_batch).await?;
+        Ok(())
     }
 
-    async fn roll_back(&mut self, height: Height) -> Header {
-        self.store.roll_back(height).await;
+    async fn roll_back(&mut self, height: Height) -> Result<Header, EwError> {
+        self.store.roll_back(height).await?;
         self.parser = Parser::new();
-        self.store.get_header().clone()
+        Ok(self.store.get_header().clone())
     }
 
     fn header<'a>(&'a self) -> &'a Header {
         self.store.get_header()
     }
+
+    async fn flush(&mut self) -> Result<(), EwError> {
+        self.store.flush().await
+    }
+
+    fn has_uncommitted(&self) -> bool {
+        self.store.has_uncommitted()
+    }
+}
+
+#[async_trait]
+impl Rewinding for TokensWorkFlow {
+    async fn rewind(&mut self, height: Height) -> Result<Header, EwError> {
+        self.store.rewind(height).await?;
+        self.parser = Parser::new();
+        Ok(self.store.get_header().clone())
+    }
 }
@@ -0,0 +1,129 @@
+//! Read-only queries served by the query API.
+use tokio_postgres::GenericClient;
+
+use super::super::types::TokenSupply;
+use crate::core::types::Address;
+use crate::core::types::AddressID;
+use crate::core::types::AssetID;
+use crate::core::types::Height;
+use crate::core::types::Value;
+use crate::error::EwError;
+
+/// Current balance of given `asset_id` in `address_id`, if any.
+pub(crate) async fn get_balance(
+    client: &impl GenericClient,
+    address_id: AddressID,
+    asset_id: AssetID,
+) -> Result<Option<Value>, EwError> {
+    let sql = "
+        select value
+        from tokens.balances
+        where address_id = $1 and asset_id = $2;";
+    Ok(client
+        .query_opt(sql, &[&address_id, &asset_id])
+        .await?
+        .map(|row| row.get(0)))
+}
+
+/// Balance of given `asset_id` in `address_id` at `height`, if it ever had any.
+pub(crate) async fn get_balance_at(
+    client: &impl GenericClient,
+    address_id: AddressID,
+    asset_id: AssetID,
+    height: Height,
+) -> Result<Option<Value>, EwError> {
+    let sql = "
+        select sum(value)::bigint
+        from tokens.balance_diffs
+        where address_id = $1
+            and asset_id = $2
+            and height <= $3;";
+    Ok(client
+        .query_one(sql, &[&address_id, &asset_id, &height])
+        .await?
+        .get(0))
+}
+
+/// Addresses with largest balance of given `asset_id`.
+pub(crate) async fn get_rich_list(
+    client: &impl GenericClient,
+    asset_id: AssetID,
+    limit: i64,
+) -> Result<Vec<(Address, Value)>, EwError> {
+    let sql = "
+        select a.address
+            , b.value
+        from tokens.balances b
+        join core.addresses a on a.id = b.address_id
+        where b.asset_id = $1
+        order by b.value desc
+        limit $2;";
+    Ok(client
+        .query(sql, &[&asset_id, &limit])
+        .await?
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect())
+}
+
+/// Number of P2PK (or other) addresses holding given `asset_id`, with a
+/// balance within given bounds.
+///
+/// * `p2pk` - count P2PK addresses if true, all others if false
+/// * `ge` - lower bound, inclusive
+/// * `lt` - upper bound, exclusive
+pub(crate) async fn count_addresses(
+    client: &impl GenericClient,
+    asset_id: AssetID,
+    p2pk: bool,
+    ge: Option<Value>,
+    lt: Option<Value>,
+) -> Result<i64, EwError> {
+    let sql = "
+        select count(*)
+        from tokens.balances
+        where asset_id = $1
+            and (address_id % 10 = 1) = $2
+            and ($3::bigint is null or value >= $3)
+            and ($4::bigint is null or value < $4);";
+    Ok(client
+        .query_one(sql, &[&asset_id, &p2pk, &ge, &lt])
+        .await?
+        .get(0))
+}
+
+/// Supply breakdown of given `asset_id`, if it was ever minted.
+///
+/// Net balance changes of a transaction are positive when minting and
+/// negative when burning.
+pub(crate) async fn get_supply(
+    client: &impl GenericClient,
+    asset_id: AssetID,
+) -> Result<Option<TokenSupply>, EwError> {
+    let sql = "
+        with txs as (
+            select sum(value) as value
+            from tokens.balance_diffs
+            where asset_id = $1
+            group by height, tx_idx
+        )
+        select (select sum(value) filter (where value > 0) from txs)::bigint
+            , (select -sum(value) filter (where value < 0) from txs)::bigint
+            , (
+                select sum(value) filter (where address_id % 10 = 1)
+                from tokens.balances
+                where asset_id = $1
+            )::bigint
+            , (
+                select sum(value) filter (where address_id % 10 <> 1)
+                from tokens.balances
+                where asset_id = $1
+            )::bigint;";
+    let row = client.query_one(sql, &[&asset_id]).await?;
+    Ok(row.get::<_, Option<Value>>(0).map(|emitted| TokenSupply {
+        emitted,
+        burned: row.get::<_, Option<Value>>(1).unwrap_or(0),
+        in_p2pks: row.get::<_, Option<Value>>(2).unwrap_or(0),
+        in_contracts: row.get::<_, Option<Value>>(3).unwrap_or(0),
+    }))
+}
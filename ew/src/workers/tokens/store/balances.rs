@@ -1,18 +1,20 @@
 use tokio_postgres::types::Type;
 use tokio_postgres::GenericClient;
-use tokio_postgres::Transaction;
 
 use super::super::types::AddressAsset;
 use super::super::types::BalanceRecord;
 
+use crate::core::types::Height;
+use crate::error::EwError;
+
 /// Get collection of balance records for given `address_ids`.
 pub async fn get_many(
     client: &impl GenericClient,
     address_assets: &Vec<AddressAsset>,
-) -> Vec<BalanceRecord> {
+) -> Result<Vec<BalanceRecord>, EwError> {
     tracing::trace!("get_many {address_assets:?}");
     if address_assets.is_empty() {
-        return vec![];
+        return Ok(vec![]);
     }
     let sql = format!(
         "
@@ -27,17 +29,19 @@ pub async fn get_many(
             .collect::<Vec<_>>()
             .join(",")
     );
-    client
+    Ok(client
         .query(&sql, &[])
-        .await
-        .unwrap()
+        .await?
         .iter()
         .map(|r| BalanceRecord::new(r.get(0), r.get(1), r.get(2)))
-        .collect()
+        .collect())
 }
 
 /// Upsert collection of balance records.
-pub async fn upsert_many(pgtx: &Transaction<'_>, records: &Vec<BalanceRecord>) {
+pub async fn upsert_many(
+    pgtx: &impl GenericClient,
+    records: &Vec<BalanceRecord>,
+) -> Result<(), EwError> {
     tracing::trace!("upsert_many {records:?}");
     let sql = "
         insert into tokens.balances (address_id, asset_id, value)
@@ -47,20 +51,22 @@ pub async fn upsert_many(pgtx: &Transaction<'_>, records: &Vec<BalanceRecord>) {
         ;";
     let stmt = pgtx
         .prepare_typed(sql, &[Type::INT8, Type::INT8, Type::INT8])
-        .await
-        .unwrap();
+        .await?;
     for r in records {
         pgtx.execute(&stmt, &[&r.address_id, &r.asset_id, &r.value])
-            .await
-            .unwrap();
+            .await?;
     }
+    Ok(())
 }
 
 /// Delete balances for given address id's.
-pub async fn delete_many(pgtx: &Transaction<'_>, address_assets: &Vec<AddressAsset>) {
+pub async fn delete_many(
+    pgtx: &impl GenericClient,
+    address_assets: &Vec<AddressAsset>,
+) -> Result<(), EwError> {
     tracing::trace!("delete_many {address_assets:?}");
     if address_assets.is_empty() {
-        return;
+        return Ok(());
     }
     let sql = format!(
         "delete from tokens.balances where (address_id, asset_id) = any(values {});",
@@ -70,5 +76,44 @@ pub async fn delete_many(pgtx: &Transaction<'_>, address_assets: &Vec<AddressAss
             .collect::<Vec<_>>()
             .join(",")
     );
-    pgtx.execute(&sql, &[]).await.unwrap();
+    pgtx.execute(&sql, &[]).await?;
+    Ok(())
+}
+
+/// Reset balances of address/asset pairs diffed after given `height`
+/// to their value at `height`.
+///
+/// Relies on diffs after `height` being still present.
+pub async fn restore_diffed_after(
+    pgtx: &impl GenericClient,
+    height: Height,
+) -> Result<(), EwError> {
+    tracing::trace!("restore_diffed_after {height}");
+    let sql = "
+        delete from tokens.balances b
+        using (
+            select distinct address_id
+                , asset_id
+            from tokens.balance_diffs
+            where height > $1
+        ) d
+        where d.address_id = b.address_id
+            and d.asset_id = b.asset_id;";
+    pgtx.execute(sql, &[&height]).await?;
+    let sql = "
+        insert into tokens.balances (address_id, asset_id, value)
+        select address_id
+            , asset_id
+            , sum(value)::bigint
+        from tokens.balance_diffs
+        where height <= $1
+            and (address_id, asset_id) in (
+                select address_id
+                    , asset_id
+                from tokens.balance_diffs
+                where height > $1
+            )
+        group by 1, 2 having sum(value) <> 0;";
+    pgtx.execute(sql, &[&height]).await?;
+    Ok(())
 }
@@ -1,78 +1,102 @@
-use tokio_postgres::types::Type;
-use tokio_postgres::Transaction;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::GenericClient;
 
 use super::super::types::AddressAsset;
 use super::super::types::BalanceRecord;
 use super::super::types::DiffRecord;
 use crate::core::types::Height;
+use crate::error::EwError;
+use crate::utils::copy_in;
+use crate::utils::CopyTypes;
 
 /// Insert collection of diff records.
-pub async fn insert_many(pgtx: &Transaction<'_>, records: &Vec<DiffRecord>) {
+pub async fn insert_many(
+    pgtx: &impl GenericClient,
+    copy_types: &CopyTypes,
+    records: &Vec<DiffRecord>,
+) -> Result<(), EwError> {
     tracing::trace!("insert_many {records:?}");
-    let sql = "
-        insert into tokens.balance_diffs (address_id, asset_id, height, tx_idx, value)
-        values ($1, $2, $3, $4, $5);";
-    let stmt = pgtx
-        .prepare_typed(
-            sql,
-            &[Type::INT8, Type::INT8, Type::INT4, Type::INT2, Type::INT8],
-        )
-        .await
-        .unwrap();
-    for r in records {
-        pgtx.execute(
-            &stmt,
-            &[&r.address_id, &r.asset_id, &r.height, &r.tx_idx, &r.value],
-        )
-        .await
-        .unwrap();
-    }
+    let rows: Vec<Vec<&(dyn ToSql + Sync)>> = records
+        .iter()
+        .map(|r| {
+            vec![
+                &r.address_id as &(dyn ToSql + Sync),
+                &r.asset_id,
+                &r.height,
+                &r.tx_idx,
+                &r.value,
+            ]
+        })
+        .collect();
+    copy_in(
+        pgtx,
+        copy_types,
+        "tokens.balance_diffs",
+        &["address_id", "asset_id", "height", "tx_idx", "value"],
+        &rows,
+    )
+    .await?;
+    Ok(())
 }
 
 /// Delete diff records at given `height`.
-pub async fn delete_at(pgtx: &Transaction<'_>, height: Height) {
+pub async fn delete_at(pgtx: &impl GenericClient, height: Height) -> Result<(), EwError> {
     tracing::trace!("delete_at {height}");
     pgtx.execute(
         "delete from tokens.balance_diffs where height = $1;",
         &[&height],
     )
-    .await
-    .unwrap();
+    .await?;
+    Ok(())
+}
+
+/// Delete diff records after given `height`.
+pub async fn delete_after(pgtx: &impl GenericClient, height: Height) -> Result<(), EwError> {
+    tracing::trace!("delete_after {height}");
+    pgtx.execute(
+        "delete from tokens.balance_diffs where height > $1;",
+        &[&height],
+    )
+    .await?;
+    Ok(())
 }
 
 /// Return all diff records for given `height`.
-pub async fn get_many_at(pgtx: &Transaction<'_>, height: Height) -> Vec<DiffRecord> {
+pub async fn get_many_at(
+    pgtx: &impl GenericClient,
+    height: Height,
+) -> Result<Vec<DiffRecord>, EwError> {
     tracing::trace!("get_many_at {height}");
-    pgtx.query(
-        "select address_id
+    Ok(pgtx
+        .query(
+            "select address_id
             , asset_id
             , height
             , tx_idx
             , value
         from tokens.balance_diffs where height = $1;",
-        &[&height],
-    )
-    .await
-    .unwrap()
-    .iter()
-    .map(|r| DiffRecord {
-        address_id: r.get(0),
-        asset_id: r.get(1),
-        height: r.get(2),
-        tx_idx: r.get(3),
-        value: r.get(4),
-    })
-    .collect()
+            &[&height],
+        )
+        .await?
+        .iter()
+        .map(|r| DiffRecord {
+            address_id: r.get(0),
+            asset_id: r.get(1),
+            height: r.get(2),
+            tx_idx: r.get(3),
+            value: r.get(4),
+        })
+        .collect())
 }
 
 /// Calculate balances from diffs for given address/asset pairs.
 pub async fn get_non_zero_balances_for(
-    pgtx: &Transaction<'_>,
+    pgtx: &impl GenericClient,
     address_assets: &[AddressAsset],
-) -> Vec<BalanceRecord> {
+) -> Result<Vec<BalanceRecord>, EwError> {
     tracing::trace!("get_balances_for {address_assets:?}");
     if address_assets.is_empty() {
-        return vec![];
+        return Ok(vec![]);
     }
     let sql = format!(
         "
@@ -89,14 +113,14 @@ pub async fn get_non_zero_balances_for(
             .collect::<Vec<_>>()
             .join(",")
     );
-    pgtx.query(&sql, &[])
-        .await
-        .unwrap()
+    Ok(pgtx
+        .query(&sql, &[])
+        .await?
         .iter()
         .map(|r| BalanceRecord {
             address_id: r.get(0),
             asset_id: r.get(1),
             value: r.get(2),
         })
-        .collect()
+        .collect())
 }
@@ -64,3 +64,16 @@ impl BalanceRecord {
         }
     }
 }
+
+/// Supply breakdown of a token.
+#[derive(Debug, PartialEq)]
+pub struct TokenSupply {
+    /// Total amount ever minted
+    pub emitted: Value,
+    /// Supply on P2PK addresses
+    pub in_p2pks: Value,
+    /// Supply on all other addresses
+    pub in_contracts: Value,
+    /// Total amount ever burned
+    pub burned: Value,
+}
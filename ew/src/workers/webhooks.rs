@@ -0,0 +1,237 @@
+mod dispatch;
+mod parsing;
+mod store;
+mod types;
+
+pub use dispatch::Dispatcher;
+pub(crate) use store::DIFFS_SCHEMA;
+pub(crate) use store::SCHEMA;
+
+use async_trait::async_trait;
+use std::time::SystemTime;
+use tokio::sync::mpsc::Sender;
+
+use crate::config::PostgresConfig;
+use crate::config::WebhooksConfig;
+use crate::core::types::CoreData;
+use crate::core::types::Header;
+use crate::core::types::Height;
+use crate::core::types::Timestamp;
+use crate::error::EwError;
+use crate::framework::store::PgMigrator;
+use crate::framework::store::StoreDef;
+use crate::framework::EventHandler;
+use crate::framework::EventHandling;
+use crate::framework::Source;
+use crate::framework::StampedData;
+use crate::monitor::MonitorMessage;
+use crate::shutdown::CancellationToken;
+use crate::workers::erg_diffs::types::DiffData;
+use crate::workers::registry::WorkerID;
+use parsing::Parser;
+use store::Store;
+use types::Batch;
+
+const WORKER_ID: &str = "webhooks";
+const DIFFS_WORKER_ID: &str = "webhooks_diffs";
+
+/// Evaluates rules against tracker blocks.
+pub type CoreWorker = Worker<WebhooksWorkFlow>;
+
+/// Evaluates rules against balance diffs of the erg_diffs worker.
+pub type DiffsWorker = Worker<WebhooksDiffsWorkFlow>;
+
+/// Initializes given store and applies pending migrations.
+pub async fn migrate(pgconf: &PostgresConfig, store: &'static StoreDef) -> Result<(), EwError> {
+    PgMigrator::new(pgconf, store).await?.apply_pending().await
+}
+
+/// Notifies webhooks of recent on-chain events matching configured rules.
+///
+/// Matching events are queued in an outbox, in the same db transaction as
+/// the block they belong to, and delivered from there by a [`Dispatcher`].
+/// Notifications of rolled back blocks are dropped if not delivered yet,
+/// retracted otherwise.
+pub struct Worker<W: EventHandling> {
+    event_handler: EventHandler<W>,
+}
+
+impl Worker<WebhooksWorkFlow> {
+    pub async fn new(
+        pgconf: &PostgresConfig,
+        source: &mut impl Source<S = CoreData>,
+        monitor_tx: Sender<MonitorMessage>,
+        config: &WebhooksConfig,
+    ) -> Result<Self, EwError> {
+        let workflow = WebhooksWorkFlow::with_config(pgconf, config).await?;
+        let event_handler = EventHandler::new_with(WORKER_ID, workflow, source, monitor_tx).await?;
+        Ok(Self { event_handler })
+    }
+}
+
+impl Worker<WebhooksDiffsWorkFlow> {
+    pub async fn new(
+        pgconf: &PostgresConfig,
+        source: &mut impl Source<S = DiffData>,
+        monitor_tx: Sender<MonitorMessage>,
+        config: &WebhooksConfig,
+    ) -> Result<Self, EwError> {
+        let workflow = WebhooksDiffsWorkFlow::with_config(pgconf, config).await?;
+        let event_handler =
+            EventHandler::new_with(DIFFS_WORKER_ID, workflow, source, monitor_tx).await?;
+        Ok(Self { event_handler })
+    }
+}
+
+impl<W: EventHandling> Worker<W> {
+    #[tracing::instrument(name = "webhooks", skip_all)]
+    pub async fn start(&mut self, shutdown: CancellationToken) -> Result<(), EwError> {
+        tracing::info!("starting");
+        loop {
+            tokio::select! {
+                biased;
+                _ = shutdown.cancelled() => {
+                    tracing::info!("stopped");
+                    return Ok(());
+                },
+                event = self.event_handler.recv() => {
+                    let event = event.ok_or(EwError::UpstreamDown)?;
+                    self.event_handler.process_upstream_event(&event).await?;
+                }
+            }
+        }
+    }
+}
+
+pub struct WebhooksWorkFlow {
+    parser: Parser,
+    store: Store<CoreData>,
+}
+
+impl WebhooksWorkFlow {
+    pub async fn with_config(
+        pgconf: &PostgresConfig,
+        config: &WebhooksConfig,
+    ) -> Result<Self, EwError> {
+        // Ensure migrations are applied
+        migrate(pgconf, &store::SCHEMA).await?;
+
+        let store = Store::new(pgconf, &store::SCHEMA).await?;
+        Ok(Self {
+            parser: Parser::new(config, WorkerID::Webhooks),
+            store,
+        })
+    }
+}
+
+/// Milliseconds since epoch
+fn now() -> Timestamp {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("system time is after unix epoch")
+        .as_millis() as Timestamp
+}
+
+#[async_trait]
+impl EventHandling for WebhooksWorkFlow {
+    type U = CoreData;
+    type D = ();
+
+    /// Create and initialize a new event handling workflow.
+    ///
+    /// Has no rules, so only suitable for handling rollbacks.
+    /// See `Worker::new` otherwise.
+    async fn new(pgconf: &PostgresConfig) -> Result<Self, EwError> {
+        Self::with_config(pgconf, &WebhooksConfig::default()).await
+    }
+
+    async fn include_block(&mut self, data: &StampedData<CoreData>) -> Result<(), EwError> {
+        let stamped_batch = self.parser.extract_batch(data, now());
+        self.store.persist(&stamped_batch).await?;
+        Ok(())
+    }
+
+    async fn roll_back(&mut self, height: Height) -> Result<Header, EwError> {
+        self.store.roll_back(height).await?;
+        Ok(self.store.get_header().clone())
+    }
+
+    fn header(&self) -> &Header {
+        self.store.get_header()
+    }
+
+    async fn flush(&mut self) -> Result<(), EwError> {
+        self.store.flush().await
+    }
+}
+
+/// Evaluates rules against balance diffs, such as new CEX deposit addresses.
+///
+/// Deposit addresses are spotted like the exchanges worker does, from its
+/// state prior to the block being evaluated.
+pub struct WebhooksDiffsWorkFlow {
+    parser: Parser,
+    store: Store<DiffData>,
+}
+
+impl WebhooksDiffsWorkFlow {
+    pub async fn with_config(
+        pgconf: &PostgresConfig,
+        config: &WebhooksConfig,
+    ) -> Result<Self, EwError> {
+        // Ensure migrations are applied
+        migrate(pgconf, &store::DIFFS_SCHEMA).await?;
+
+        let store = Store::new(pgconf, &store::DIFFS_SCHEMA).await?;
+        Ok(Self {
+            parser: Parser::new(config, WorkerID::WebhooksDiffs),
+            store,
+        })
+    }
+}
+
+#[async_trait]
+impl EventHandling for WebhooksDiffsWorkFlow {
+    type U = DiffData;
+    type D = ();
+
+    /// Create and initialize a new event handling workflow.
+    ///
+    /// Has no rules, so only suitable for handling rollbacks.
+    /// See `Worker::new` otherwise.
+    async fn new(pgconf: &PostgresConfig) -> Result<Self, EwError> {
+        Self::with_config(pgconf, &WebhooksConfig::default()).await
+    }
+
+    async fn include_block(&mut self, data: &StampedData<DiffData>) -> Result<(), EwError> {
+        let stamped_batch = match self.parser.is_evaluated(data.timestamp, now()) {
+            true => {
+                let cache = store::get_deposit_cache(
+                    self.store.get_client(),
+                    data.height,
+                    &data.data.diffed_addresses(),
+                )
+                .await?;
+                self.parser.extract_diffs_batch(data, &cache)
+            }
+            false => data.wrap(Batch {
+                notifications: vec![],
+            }),
+        };
+        self.store.persist(&stamped_batch).await?;
+        Ok(())
+    }
+
+    async fn roll_back(&mut self, height: Height) -> Result<Header, EwError> {
+        self.store.roll_back(height).await?;
+        Ok(self.store.get_header().clone())
+    }
+
+    fn header(&self) -> &Header {
+        self.store.get_header()
+    }
+
+    async fn flush(&mut self) -> Result<(), EwError> {
+        self.store.flush().await
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashSet;
+use tokio::time::Interval;
+use tokio::time::MissedTickBehavior;
+use tokio_postgres::Client;
+use tokio_postgres::NoTls;
+
+use super::store::outbox;
+use crate::config::PostgresConfig;
+use crate::config::WebhooksConfig;
+use crate::constants::settings::rollback_horizon;
+use crate::error::EwError;
+use crate::shutdown::CancellationToken;
+use crate::workers::registry::WorkerID;
+
+/// Delivers outbox notifications of a webhooks worker to their webhooks.
+///
+/// Notifications are delivered in order for each url. A notification is
+/// retried on later rounds until its webhook accepts it, holding back any
+/// later ones for the same url. Delivery is at-least-once: receivers can
+/// deduplicate on notification ids.
+///
+/// Delivered notifications are deleted once their block is past the
+/// rollback horizon.
+pub struct Dispatcher {
+    client: Client,
+    worker: WorkerID,
+    /// Schema of the outbox to deliver from
+    schema: &'static str,
+    http: reqwest::Client,
+    interval: Interval,
+}
+
+impl Dispatcher {
+    /// Create a dispatcher for notifications queued by given `worker`.
+    pub async fn new(
+        pgconf: &PostgresConfig,
+        config: &WebhooksConfig,
+        worker: WorkerID,
+    ) -> Result<Self, EwError> {
+        let (client, connection) = tokio_postgres::connect(&pgconf.connection_uri, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                tracing::error!("connection error: {}", e);
+            }
+        });
+        let http = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .expect("http client settings are valid");
+        let mut interval = tokio::time::interval(config.polling_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Ok(Self {
+            client,
+            worker,
+            schema: worker.store().schema_name,
+            http,
+            interval,
+        })
+    }
+
+    /// Delivers pending notifications every polling interval until
+    /// `shutdown` is cancelled.
+    ///
+    /// Runs as a task of its own, so slow or unreachable webhooks don't hold
+    /// up the worker queuing notifications.
+    #[tracing::instrument(name = "dispatcher", skip_all, fields(schema = self.schema))]
+    pub async fn start(&mut self, shutdown: CancellationToken) -> Result<(), EwError> {
+        tracing::info!("starting");
+        loop {
+            tokio::select! {
+                biased;
+                _ = shutdown.cancelled() => {
+                    tracing::info!("stopped");
+                    return Ok(());
+                },
+                _ = self.interval.tick() => {
+                    // Outbox is created by the worker
+                    if self.worker.store().get_header(&self.client).await?.is_none() {
+                        tracing::debug!("waiting for worker to initialize its store");
+                        continue;
+                    }
+                    self.dispatch().await?;
+                }
+            }
+        }
+    }
+
+    /// Delivers pending notifications and returns number of deliveries.
+    pub async fn dispatch(&mut self) -> Result<usize, EwError> {
+        let mut delivered = 0;
+        // Urls not to be sent anything else this round
+        let mut failing: HashSet<String> = HashSet::new();
+        loop {
+            let ids = outbox::get_next_pending_ids(&self.client, self.schema).await?;
+            let mut progress = false;
+            for id in ids {
+                // Lock held until delivery is recorded
+                let pgtx = self.client.transaction().await?;
+                let Some(record) = outbox::lock_pending(&pgtx, self.schema, id).await? else {
+                    continue;
+                };
+                if failing.contains(&record.url) {
+                    continue;
+                }
+                let res = self
+                    .http
+                    .post(&record.url)
+                    .json(&record)
+                    .send()
+                    .await
+                    .and_then(|res| res.error_for_status());
+                match res {
+                    Ok(_) => {
+                        outbox::set_delivered(&pgtx, self.schema, record.id).await?;
+                        pgtx.commit().await?;
+                        delivered += 1;
+                        progress = true;
+                    }
+                    Err(e) => {
+                        tracing::warn!("could not notify {} - will retry: {e}", record.url);
+                        failing.insert(record.url);
+                    }
+                }
+            }
+            if !progress {
+                break;
+            }
+        }
+        if delivered > 0 {
+            tracing::debug!("delivered {delivered} notifications");
+        }
+        self.prune().await?;
+        Ok(delivered)
+    }
+
+    /// Deletes delivered notifications that can't be rolled back anymore.
+    ///
+    /// Notified deposit addresses are kept until the exchanges worker has
+    /// processed their block, as they stand in for its own until then.
+    async fn prune(&self) -> Result<(), EwError> {
+        let Some(height) = self.worker.store().get_height(&self.client).await? else {
+            return Ok(());
+        };
+        let mut below = height - rollback_horizon();
+        if self.worker == WorkerID::WebhooksDiffs {
+            let exchanges = WorkerID::Exchanges.store();
+            let Some(exchanges_height) = exchanges.get_height(&self.client).await? else {
+                return Ok(());
+            };
+            below = below.min(exchanges_height + 1);
+        }
+        let pruned = outbox::delete_delivered_below(&self.client, self.schema, below).await?;
+        if pruned > 0 {
+            tracing::debug!("pruned {pruned} delivered notifications");
+        }
+        Ok(())
+    }
+}
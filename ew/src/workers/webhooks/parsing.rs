@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+
+use super::types::Batch;
+use super::types::Event;
+use super::types::Notification;
+use crate::config::RuleCondition;
+use crate::config::WebhookRule;
+use crate::config::WebhooksConfig;
+use crate::constants::VOTING_EPOCH_LENGTH;
+use crate::core::types::AddressID;
+use crate::core::types::AssetID;
+use crate::core::types::Block;
+use crate::core::types::BlockHeader;
+use crate::core::types::BoxData;
+use crate::core::types::CoreData;
+use crate::core::types::NanoERG;
+use crate::core::types::Timestamp;
+use crate::framework::StampedData;
+use crate::workers::erg_diffs::types::DiffData;
+use crate::workers::exchanges::spot_deposit_addresses;
+use crate::workers::exchanges::ExchangeID;
+use crate::workers::exchanges::ParserCache;
+use crate::workers::registry::WorkerID;
+use crate::workers::sigmausd::constants::BANK_NFT;
+use crate::workers::sigmausd::constants::CONTRACT_ADDRESS_ID;
+use crate::workers::sigmausd::constants::RC_ASSET_ID;
+use crate::workers::sigmausd::constants::SC_ASSET_ID;
+
+pub struct Parser {
+    /// Rules evaluated by the worker
+    rules: Vec<WebhookRule>,
+    /// Milliseconds
+    max_block_age: Timestamp,
+}
+
+impl Parser {
+    /// Create a parser for rules evaluated by given `worker`.
+    pub fn new(config: &WebhooksConfig, worker: WorkerID) -> Self {
+        Self {
+            rules: config
+                .rules
+                .iter()
+                .filter(|rule| rule.condition.worker() == worker)
+                .cloned()
+                .collect(),
+            max_block_age: config.max_block_age.as_millis() as Timestamp,
+        }
+    }
+
+    /// Whether a block with given `timestamp` has rules to evaluate.
+    ///
+    /// Blocks older than `max_block_age` are not evaluated.
+    ///
+    /// * `now` - current time, in milliseconds since epoch
+    pub(super) fn is_evaluated(&self, timestamp: Timestamp, now: Timestamp) -> bool {
+        !self.rules.is_empty() && now - timestamp <= self.max_block_age
+    }
+
+    /// Evaluate rules against a block, unless older than `max_block_age`.
+    ///
+    /// * `now` - current time, in milliseconds since epoch
+    pub(super) fn extract_batch(
+        &self,
+        stamped_data: &StampedData<CoreData>,
+        now: Timestamp,
+    ) -> StampedData<Batch> {
+        let block = &stamped_data.data.block;
+        let notifications = match self.is_evaluated(block.header.timestamp, now) {
+            true => self.notify(|condition| evaluate(condition, block)),
+            false => vec![],
+        };
+        stamped_data.wrap(Batch { notifications })
+    }
+
+    /// Evaluate rules against balance diffs of a block.
+    ///
+    /// `cache` holds exchange addresses as known prior to the block.
+    /// See `is_evaluated` for blocks to be evaluated.
+    pub(super) fn extract_diffs_batch(
+        &self,
+        stamped_data: &StampedData<DiffData>,
+        cache: &ParserCache,
+    ) -> StampedData<Batch> {
+        let deposits = extract_deposits(&stamped_data.data, cache);
+        let notifications = self.notify(|condition| match condition {
+            RuleCondition::CexDeposit => deposits.clone(),
+            _ => vec![],
+        });
+        stamped_data.wrap(Batch { notifications })
+    }
+
+    /// Notifications of events matching each rule.
+    fn notify(&self, events: impl Fn(&RuleCondition) -> Vec<Event>) -> Vec<Notification> {
+        self.rules
+            .iter()
+            .flat_map(|rule| {
+                events(&rule.condition)
+                    .into_iter()
+                    .map(|event| Notification {
+                        rule: rule.name.clone(),
+                        url: rule.url.clone(),
+                        event,
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Returns events of `block` matching given `condition`.
+fn evaluate(condition: &RuleCondition, block: &Block) -> Vec<Event> {
+    match condition {
+        RuleCondition::Transfer { min_nano } => extract_transfers(block, *min_nano),
+        RuleCondition::SigmaUSD { min_nano } => extract_bank_transactions(block, *min_nano),
+        RuleCondition::Proposal => extract_proposal(&block.header).into_iter().collect(),
+        // Evaluated against balance diffs
+        RuleCondition::CexDeposit => vec![],
+    }
+}
+
+/// Addresses receiving at least `min_nano` in a transaction, net of their inputs.
+fn extract_transfers(block: &Block, min_nano: NanoERG) -> Vec<Event> {
+    let mut events = vec![];
+    for tx in &block.transactions {
+        let mut diffs: HashMap<AddressID, NanoERG> = HashMap::new();
+        for output in &tx.outputs {
+            *diffs.entry(output.address_id).or_insert(0) += output.value;
+        }
+        for input in &tx.inputs {
+            *diffs.entry(input.address_id).or_insert(0) -= input.value;
+        }
+        let mut received: Vec<(AddressID, NanoERG)> = diffs
+            .into_iter()
+            .filter(|(_, nano)| *nano >= min_nano)
+            .collect();
+        received.sort_by_key(|(address_id, _)| address_id.0);
+        events.extend(
+            received
+                .into_iter()
+                .map(|(address_id, nano)| Event::Transfer {
+                    tx_id: tx.id.clone(),
+                    address_id,
+                    nano,
+                }),
+        );
+    }
+    events
+}
+
+/// SigmaUSD mints and redeems changing bank reserves by at least `min_nano`.
+fn extract_bank_transactions(block: &Block, min_nano: NanoERG) -> Vec<Event> {
+    block
+        .transactions
+        .iter()
+        .filter_map(|tx| {
+            let input = tx.inputs.iter().find(|bx| is_bank_box(bx))?;
+            let output = tx.outputs.iter().find(|bx| is_bank_box(bx))?;
+            let reserves_diff = output.value - input.value;
+            if reserves_diff.abs() < min_nano {
+                return None;
+            }
+            Some(Event::SigmaUSD {
+                tx_id: tx.id.clone(),
+                reserves_diff,
+                // Tokens leaving the bank are in circulation
+                circ_sc_diff: amount(input, SC_ASSET_ID) - amount(output, SC_ASSET_ID),
+                circ_rc_diff: amount(input, RC_ASSET_ID) - amount(output, RC_ASSET_ID),
+            })
+        })
+        .collect()
+}
+
+fn is_bank_box(bx: &BoxData) -> bool {
+    bx.address_id == CONTRACT_ADDRESS_ID && bx.assets.iter().any(|a| a.asset_id == BANK_NFT)
+}
+
+/// Amount of `asset_id` held by a box.
+fn amount(bx: &BoxData, asset_id: AssetID) -> i64 {
+    bx.assets
+        .iter()
+        .filter(|a| a.asset_id == asset_id)
+        .map(|a| a.amount)
+        .sum()
+}
+
+/// Addresses spotted as deposit address of a CEX for the first time.
+///
+/// Uses the same logic as the exchanges worker, so conflicting spottings
+/// and ignored addresses are not reported.
+fn extract_deposits(diffs: &DiffData, cache: &ParserCache) -> Vec<Event> {
+    let spottings = spot_deposit_addresses(&diffs.diff_records, cache);
+    let mut deposits: Vec<(AddressID, ExchangeID)> = spottings.new_deposits.into_iter().collect();
+    deposits.sort_by_key(|(address_id, _)| address_id.0);
+    deposits
+        .into_iter()
+        .map(|(address_id, cex_id)| Event::CexDeposit { address_id, cex_id })
+        .collect()
+}
+
+/// New proposal, if any.
+///
+/// Proposals are made by the first block of a voting epoch.
+fn extract_proposal(header: &BlockHeader) -> Option<Event> {
+    if header.height % VOTING_EPOCH_LENGTH != 0 || header.votes == [0, 0, 0] {
+        return None;
+    }
+    Some(Event::Proposal {
+        epoch: header.height / VOTING_EPOCH_LENGTH,
+        slots: header.votes.map(|v| v as i16),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::types::Transaction;
+    use crate::workers::erg_diffs::types::DiffRecord;
+    use crate::workers::exchanges::SupplyRecord;
+    use pretty_assertions::assert_eq;
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    const NOW: Timestamp = 1700000000000;
+
+    fn parser(worker: WorkerID, rules: Vec<(&str, RuleCondition)>) -> Parser {
+        Parser::new(
+            &WebhooksConfig {
+                rules: rules
+                    .into_iter()
+                    .map(|(name, condition)| WebhookRule {
+                        name: name.to_owned(),
+                        url: format!("http://localhost/{name}"),
+                        condition,
+                    })
+                    .collect(),
+                max_block_age: Duration::from_secs(60),
+                ..WebhooksConfig::default()
+            },
+            worker,
+        )
+    }
+
+    fn bank_box(value: NanoERG, sc: i64, rc: i64) -> BoxData {
+        BoxData::dummy()
+            .address_id(CONTRACT_ADDRESS_ID)
+            .value(value)
+            .add_asset(BANK_NFT, 1)
+            .add_asset(SC_ASSET_ID, sc)
+            .add_asset(RC_ASSET_ID, rc)
+    }
+
+    #[test]
+    fn test_transfers() {
+        let tx = Transaction::dummy()
+            .add_input(BoxData::dummy().address_id(AddressID(11)).value(5000))
+            .add_output(BoxData::dummy().address_id(AddressID(21)).value(3000))
+            .add_output(BoxData::dummy().address_id(AddressID(31)).value(500))
+            // Change is not a transfer
+            .add_output(BoxData::dummy().address_id(AddressID(11)).value(1500));
+        let block = Block::dummy().timestamp(NOW).add_tx(tx.clone());
+        let batch = parser(
+            WorkerID::Webhooks,
+            vec![("whales", RuleCondition::Transfer { min_nano: 500 })],
+        )
+        .extract_batch(&CoreData { block }.into(), NOW);
+        assert_eq!(
+            batch.data.notifications,
+            vec![
+                Notification {
+                    rule: "whales".to_owned(),
+                    url: "http://localhost/whales".to_owned(),
+                    event: Event::Transfer {
+                        tx_id: tx.id.clone(),
+                        address_id: AddressID(21),
+                        nano: 3000
+                    },
+                },
+                Notification {
+                    rule: "whales".to_owned(),
+                    url: "http://localhost/whales".to_owned(),
+                    event: Event::Transfer {
+                        tx_id: tx.id.clone(),
+                        address_id: AddressID(31),
+                        nano: 500
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_old_blocks_are_ignored() {
+        let tx =
+            Transaction::dummy().add_output(BoxData::dummy().address_id(AddressID(21)).value(3000));
+        let block = Block::dummy().timestamp(NOW - 61_000).add_tx(tx);
+        let batch = parser(
+            WorkerID::Webhooks,
+            vec![("whales", RuleCondition::Transfer { min_nano: 500 })],
+        )
+        .extract_batch(&CoreData { block }.into(), NOW);
+        assert!(batch.data.notifications.is_empty());
+    }
+
+    #[test]
+    fn test_bank_transactions() {
+        let mint = Transaction::dummy()
+            .add_input(bank_box(1000, 500, 800))
+            .add_input(BoxData::dummy().address_id(AddressID(21)).value(400))
+            .add_output(bank_box(1300, 450, 800))
+            .add_output(BoxData::dummy().address_id(AddressID(21)).value(100));
+        let small_redeem = Transaction::dummy()
+            .add_input(bank_box(1300, 450, 800))
+            .add_output(bank_box(1250, 450, 810));
+        let redeem = Transaction::dummy()
+            .add_input(bank_box(1250, 450, 810))
+            .add_output(bank_box(900, 450, 900));
+        let block = Block::dummy()
+            .add_tx(mint.clone())
+            .add_tx(small_redeem)
+            .add_tx(redeem.clone());
+        assert_eq!(
+            extract_bank_transactions(&block, 100),
+            vec![
+                Event::SigmaUSD {
+                    tx_id: mint.id,
+                    reserves_diff: 300,
+                    circ_sc_diff: 50,
+                    circ_rc_diff: 0,
+                },
+                Event::SigmaUSD {
+                    tx_id: redeem.id,
+                    reserves_diff: -350,
+                    circ_sc_diff: 0,
+                    circ_rc_diff: -90,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cex_deposits() {
+        let cache = ParserCache {
+            supply: SupplyRecord {
+                height: 9,
+                main: 0,
+                deposits: 0,
+            },
+            main_addresses: HashMap::from([(AddressID(901), 1), (AddressID(902), 2)]),
+            deposit_addresses: HashMap::from([(AddressID(21), 1)]),
+            deposit_conflicts: HashMap::new(),
+            deposit_ignored: HashSet::from([AddressID(41)]),
+        };
+        let diffs = StampedData {
+            height: 10,
+            timestamp: NOW,
+            header_id: "header10".to_owned(),
+            parent_id: "header9".to_owned(),
+            data: DiffData {
+                diff_records: vec![
+                    // New deposit address
+                    DiffRecord::new(AddressID(31), 10, 0, -1000),
+                    DiffRecord::new(AddressID(901), 10, 0, 1000),
+                    // Known one
+                    DiffRecord::new(AddressID(21), 10, 1, -500),
+                    DiffRecord::new(AddressID(901), 10, 1, 500),
+                    // Ignored one
+                    DiffRecord::new(AddressID(41), 10, 2, -500),
+                    DiffRecord::new(AddressID(902), 10, 2, 500),
+                ],
+            },
+        };
+        let parser = parser(
+            WorkerID::WebhooksDiffs,
+            vec![
+                ("deposits", RuleCondition::CexDeposit),
+                // Evaluated by the other worker
+                ("whales", RuleCondition::Transfer { min_nano: 500 }),
+            ],
+        );
+        assert!(parser.is_evaluated(NOW, NOW));
+        let batch = parser.extract_diffs_batch(&diffs, &cache);
+        assert_eq!(
+            batch.data.notifications,
+            vec![Notification {
+                rule: "deposits".to_owned(),
+                url: "http://localhost/deposits".to_owned(),
+                event: Event::CexDeposit {
+                    address_id: AddressID(31),
+                    cex_id: 1
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_proposal() {
+        let block = Block::dummy().height(1024 * 500).votes([4, 0, -2]);
+        assert_eq!(
+            extract_proposal(&block.header),
+            Some(Event::Proposal {
+                epoch: 500,
+                slots: [4, 0, -2]
+            })
+        );
+        // No proposal
+        let block = Block::dummy().height(1024 * 500);
+        assert_eq!(extract_proposal(&block.header), None);
+        // Votes within an epoch
+        let block = Block::dummy().height(1024 * 500 + 1).votes([4, 0, 0]);
+        assert_eq!(extract_proposal(&block.header), None);
+    }
+}
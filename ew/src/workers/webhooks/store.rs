@@ -0,0 +1,253 @@
+pub(super) mod outbox;
+
+use async_trait::async_trait;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::marker::PhantomData;
+use tokio_postgres::Client;
+
+use super::types::Batch;
+use super::types::Event;
+use crate::core::types::AddressID;
+use crate::core::types::CoreData;
+use crate::core::types::Header;
+use crate::core::types::Height;
+use crate::error::EwError;
+use crate::framework::store::BatchStore;
+use crate::framework::store::PgStore;
+use crate::framework::store::Revision;
+use crate::framework::store::StoreDef;
+use crate::framework::store::StoreTransaction;
+use crate::framework::StampedData;
+use crate::workers::erg_diffs::types::DiffData;
+use crate::workers::exchanges::ExchangeID;
+use crate::workers::exchanges::ParserCache;
+use crate::workers::exchanges::SupplyRecord;
+
+/// Notifications of rules evaluated against tracker blocks.
+pub(crate) const SCHEMA: StoreDef = StoreDef {
+    schema_name: super::WORKER_ID,
+    worker_id: super::WORKER_ID,
+    sql: include_str!("store/schema.sql"),
+    revision: &Revision { major: 1, minor: 0 },
+    migrations: &[],
+    has_header: true,
+};
+
+/// Notifications of rules evaluated against erg_diffs balance diffs.
+pub(crate) const DIFFS_SCHEMA: StoreDef = StoreDef {
+    schema_name: super::DIFFS_WORKER_ID,
+    worker_id: super::DIFFS_WORKER_ID,
+    sql: include_str!("store/diffs_schema.sql"),
+    revision: &Revision { major: 1, minor: 0 },
+    migrations: &[],
+    has_header: true,
+};
+
+/// Upstream data webhook rules are evaluated against.
+pub(super) trait Upstream: Send + Sync + 'static {
+    /// Definition of the store holding resulting notifications.
+    fn store() -> &'static StoreDef;
+}
+
+impl Upstream for CoreData {
+    fn store() -> &'static StoreDef {
+        &SCHEMA
+    }
+}
+
+impl Upstream for DiffData {
+    fn store() -> &'static StoreDef {
+        &DIFFS_SCHEMA
+    }
+}
+
+pub(super) struct InnerStore<U: Upstream> {
+    _upstream: PhantomData<U>,
+}
+
+pub(super) type Store<U> = PgStore<InnerStore<U>>;
+
+#[async_trait]
+impl<U: Upstream> BatchStore for InnerStore<U> {
+    type B = Batch;
+
+    async fn new() -> Self {
+        Self {
+            _upstream: PhantomData,
+        }
+    }
+
+    async fn persist(
+        &mut self,
+        pgtx: &StoreTransaction,
+        stamped_batch: &StampedData<Self::B>,
+    ) -> Result<(), EwError> {
+        for notification in &stamped_batch.data.notifications {
+            let event = event_json(pgtx.client(), &notification.event).await?;
+            outbox::insert(
+                pgtx.client(),
+                U::store().schema_name,
+                stamped_batch.height,
+                &stamped_batch.header_id,
+                notification,
+                &event,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn roll_back(&mut self, pgtx: &StoreTransaction, header: &Header) -> Result<(), EwError> {
+        let height = header.height;
+        let schema = U::store().schema_name;
+        tracing::debug!("rolling back block {}", height);
+        // Notifications not sent yet can simply be dropped
+        outbox::delete_pending_at(pgtx.client(), schema, height).await?;
+        // Delivered ones get retracted
+        outbox::insert_retractions_at(pgtx.client(), schema, height).await?;
+        Ok(())
+    }
+}
+
+/// Event payload, as delivered to webhooks.
+async fn event_json(client: &Client, event: &Event) -> Result<Value, EwError> {
+    Ok(match event {
+        Event::Transfer {
+            tx_id,
+            address_id,
+            nano,
+        } => json!({
+            "tx_id": tx_id,
+            "address": get_address(client, *address_id).await?,
+            "nano": nano,
+        }),
+        Event::SigmaUSD {
+            tx_id,
+            reserves_diff,
+            circ_sc_diff,
+            circ_rc_diff,
+        } => json!({
+            "tx_id": tx_id,
+            "reserves_diff": reserves_diff,
+            "circ_sc_diff": circ_sc_diff,
+            "circ_rc_diff": circ_rc_diff,
+        }),
+        Event::Proposal { epoch, slots } => json!({
+            "epoch": epoch,
+            "slots": slots,
+        }),
+        Event::CexDeposit { address_id, cex_id } => json!({
+            "address": get_address(client, *address_id).await?,
+            "cex": get_cex(client, *cex_id).await?,
+        }),
+    })
+}
+
+async fn get_address(client: &Client, address_id: AddressID) -> Result<String, EwError> {
+    let row = client
+        .query_one(
+            "select address from core.addresses where id = $1;",
+            &[&address_id],
+        )
+        .await?;
+    Ok(row.get(0))
+}
+
+/// Text id of an exchange, as used by the api.
+async fn get_cex(client: &Client, cex_id: ExchangeID) -> Result<String, EwError> {
+    let row = client
+        .query_one(
+            "select text_id from exchanges.exchanges where id = $1;",
+            &[&cex_id],
+        )
+        .await?;
+    Ok(row.get(0))
+}
+
+/// Exchange addresses needed to spot new deposit addresses among
+/// `address_ids` in block at given `height`.
+///
+/// Reflects the exchanges worker's state prior to `height`, whether it is
+/// behind or ahead of it. Deposit addresses notified already are included
+/// too, as the exchanges worker may not have processed their block yet.
+pub(super) async fn get_deposit_cache(
+    client: &Client,
+    height: Height,
+    address_ids: &[AddressID],
+) -> Result<ParserCache, EwError> {
+    let ids: Vec<i64> = address_ids.iter().map(|a| a.0).collect();
+    let main_addresses: HashMap<AddressID, ExchangeID> = client
+        .query(
+            "select address_id, cex_id from exchanges.main_addresses;",
+            &[],
+        )
+        .await?
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect();
+
+    let qry = "
+        select address_id
+            , cex_id
+        from exchanges.deposit_addresses
+        where address_id = any($1)
+            and spot_height < $2
+        union
+        select a.id
+            , e.id
+        from webhooks_diffs.outbox o
+        join core.addresses a on a.address = o.event->>'address'
+        join exchanges.exchanges e on e.text_id = o.event->>'cex'
+        where o.kind = 'cex_deposit'
+            and o.height < $2
+            and a.id = any($1)
+            and not exists (
+                select from webhooks_diffs.outbox r where r.retracts = o.id
+            );";
+    let deposit_addresses: HashMap<AddressID, ExchangeID> = client
+        .query(qry, &[&ids, &height])
+        .await?
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect();
+
+    let qry = "
+        select address_id
+            , first_cex_id
+        from exchanges.deposit_addresses_excluded
+        where address_id = any($1)
+            and conflict_spot_height < $2;";
+    let deposit_conflicts: HashMap<AddressID, Option<ExchangeID>> = client
+        .query(qry, &[&ids, &height])
+        .await?
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect();
+
+    let qry = "
+        select address_id
+        from exchanges.deposit_addresses_ignored
+        where address_id = any($1);";
+    let deposit_ignored: HashSet<AddressID> = client
+        .query(qry, &[&ids])
+        .await?
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+
+    Ok(ParserCache {
+        // Not involved in spotting deposit addresses
+        supply: SupplyRecord {
+            height,
+            main: 0,
+            deposits: 0,
+        },
+        main_addresses,
+        deposit_addresses,
+        deposit_conflicts,
+        deposit_ignored,
+    })
+}
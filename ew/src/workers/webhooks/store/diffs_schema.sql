@@ -0,0 +1,23 @@
+create schema if not exists webhooks_diffs;
+comment on schema webhooks_diffs is 'Webhook notifications of balance diffs rules';
+
+-- Notifications to be delivered, in order, to each url.
+-- Delivered rows are kept so rolled back ones can be retracted,
+-- until past the rollback horizon.
+create table webhooks_diffs.outbox (
+    id bigserial primary key,
+    height integer not null,
+    header_id text not null,
+    rule text not null,
+    url text not null,
+    -- cex_deposit or retraction
+    kind text not null,
+    -- Id of the retracted notification, for retractions only
+    retracts bigint,
+    event jsonb not null,
+    delivered boolean not null default false
+);
+create index on webhooks_diffs.outbox (height);
+create index on webhooks_diffs.outbox (url, id) where not delivered;
+-- Deposit addresses notified already
+create index on webhooks_diffs.outbox ((event->>'address')) where kind = 'cex_deposit';
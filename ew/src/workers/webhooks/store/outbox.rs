@@ -0,0 +1,170 @@
+use serde_json::Value;
+use tokio_postgres::GenericClient;
+
+use super::super::types::Notification;
+use super::super::types::OutboxRecord;
+use crate::core::types::HeaderID;
+use crate::core::types::Height;
+use crate::error::EwError;
+
+/// Insert a notification, with its `event` payload
+pub(super) async fn insert(
+    pgtx: &impl GenericClient,
+    schema: &str,
+    height: Height,
+    header_id: &HeaderID,
+    notification: &Notification,
+    event: &Value,
+) -> Result<(), EwError> {
+    let (rule, url, kind) = (
+        &notification.rule,
+        &notification.url,
+        notification.event.kind(),
+    );
+    tracing::trace!("insert {rule} {kind} at {height}");
+    let stmt = format!(
+        "
+        insert into {schema}.outbox (height, header_id, rule, url, kind, event)
+        values ($1, $2, $3, $4, $5, $6);"
+    );
+    pgtx.execute(&stmt, &[&height, header_id, rule, url, &kind, event])
+        .await?;
+    Ok(())
+}
+
+/// Delete notifications at given height that haven't been delivered yet
+pub(super) async fn delete_pending_at(
+    pgtx: &impl GenericClient,
+    schema: &str,
+    height: Height,
+) -> Result<(), EwError> {
+    tracing::trace!("delete_pending_at {height}");
+    let stmt = format!(
+        "
+        delete from {schema}.outbox
+        where height = $1
+            and not delivered
+            and retracts is null;"
+    );
+    pgtx.execute(&stmt, &[&height]).await?;
+    Ok(())
+}
+
+/// Queue retractions of delivered notifications at given height
+///
+/// Notifications already retracted are left alone.
+pub(super) async fn insert_retractions_at(
+    pgtx: &impl GenericClient,
+    schema: &str,
+    height: Height,
+) -> Result<(), EwError> {
+    tracing::trace!("insert_retractions_at {height}");
+    let stmt = format!(
+        "
+        insert into {schema}.outbox (height, header_id, rule, url, kind, retracts, event)
+        select o.height
+            , o.header_id
+            , o.rule
+            , o.url
+            , 'retraction'
+            , o.id
+            , o.event
+        from {schema}.outbox o
+        where o.height = $1
+            and o.delivered
+            and o.retracts is null
+            and not exists (
+                select from {schema}.outbox r where r.retracts = o.id
+            )
+        order by o.id;"
+    );
+    pgtx.execute(&stmt, &[&height]).await?;
+    Ok(())
+}
+
+/// Id of oldest pending notification of each url
+pub(crate) async fn get_next_pending_ids(
+    client: &impl GenericClient,
+    schema: &str,
+) -> Result<Vec<i64>, EwError> {
+    let stmt = format!(
+        "
+        select distinct on (url) id
+        from {schema}.outbox
+        where not delivered
+        order by url, id;"
+    );
+    Ok(client
+        .query(&stmt, &[])
+        .await?
+        .iter()
+        .map(|row| row.get(0))
+        .collect())
+}
+
+/// Lock a notification, unless delivered or dropped by a rollback since
+///
+/// Rollbacks wait for the lock to be released, so a notification being
+/// delivered is retracted rather than dropped.
+pub(crate) async fn lock_pending(
+    pgtx: &impl GenericClient,
+    schema: &str,
+    id: i64,
+) -> Result<Option<OutboxRecord>, EwError> {
+    let stmt = format!(
+        "
+        select id
+            , kind
+            , retracts
+            , rule
+            , height
+            , header_id
+            , event
+            , url
+        from {schema}.outbox
+        where id = $1
+            and not delivered
+        for update;"
+    );
+    Ok(pgtx
+        .query_opt(&stmt, &[&id])
+        .await?
+        .map(|row| OutboxRecord {
+            id: row.get(0),
+            kind: row.get(1),
+            retracts: row.get(2),
+            rule: row.get(3),
+            height: row.get(4),
+            header_id: row.get(5),
+            event: row.get(6),
+            url: row.get(7),
+        }))
+}
+
+/// Flag a notification as delivered
+pub(crate) async fn set_delivered(
+    client: &impl GenericClient,
+    schema: &str,
+    id: i64,
+) -> Result<(), EwError> {
+    tracing::trace!("set_delivered {id}");
+    let stmt = format!("update {schema}.outbox set delivered = true where id = $1;");
+    client.execute(&stmt, &[&id]).await?;
+    Ok(())
+}
+
+/// Delete delivered notifications, retractions included, below given height
+pub(crate) async fn delete_delivered_below(
+    client: &impl GenericClient,
+    schema: &str,
+    height: Height,
+) -> Result<u64, EwError> {
+    tracing::trace!("delete_delivered_below {height}");
+    let stmt = format!(
+        "
+        delete from {schema}.outbox
+        where height < $1
+            and delivered;"
+    );
+    Ok(client.execute(&stmt, &[&height]).await?)
+}
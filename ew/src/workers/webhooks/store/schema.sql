@@ -0,0 +1,21 @@
+create schema if not exists webhooks;
+comment on schema webhooks is 'Webhook notifications';
+
+-- Notifications to be delivered, in order, to each url.
+-- Delivered rows are kept so rolled back ones can be retracted,
+-- until past the rollback horizon.
+create table webhooks.outbox (
+    id bigserial primary key,
+    height integer not null,
+    header_id text not null,
+    rule text not null,
+    url text not null,
+    -- transfer, sigmausd, proposal or retraction
+    kind text not null,
+    -- Id of the retracted notification, for retractions only
+    retracts bigint,
+    event jsonb not null,
+    delivered boolean not null default false
+);
+create index on webhooks.outbox (height);
+create index on webhooks.outbox (url, id) where not delivered;
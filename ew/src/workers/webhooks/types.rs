@@ -0,0 +1,75 @@
+use serde::Serialize;
+
+use crate::core::types::AddressID;
+use crate::core::types::Digest32;
+use crate::core::types::HeaderID;
+use crate::core::types::Height;
+use crate::core::types::NanoERG;
+use crate::workers::exchanges::ExchangeID;
+
+pub(super) struct Batch {
+    pub(super) notifications: Vec<Notification>,
+}
+
+/// An event matched by a rule, to be sent to the rule's url.
+#[derive(Debug, PartialEq)]
+pub(super) struct Notification {
+    pub(super) rule: String,
+    pub(super) url: String,
+    pub(super) event: Event,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Event {
+    /// Address receiving ERG in a transaction
+    Transfer {
+        tx_id: Digest32,
+        address_id: AddressID,
+        nano: NanoERG,
+    },
+    /// SigmaUSD bank transaction
+    ///
+    /// Positive diffs for mints, negative ones for redeems.
+    SigmaUSD {
+        tx_id: Digest32,
+        reserves_diff: NanoERG,
+        circ_sc_diff: i64,
+        circ_rc_diff: i64,
+    },
+    /// New governance proposal
+    Proposal { epoch: i32, slots: [i16; 3] },
+    /// Address spotted sending to a CEX main address for the first time
+    CexDeposit {
+        address_id: AddressID,
+        cex_id: ExchangeID,
+    },
+}
+
+impl Event {
+    pub(super) fn kind(&self) -> &'static str {
+        match self {
+            Self::Transfer { .. } => "transfer",
+            Self::SigmaUSD { .. } => "sigmausd",
+            Self::Proposal { .. } => "proposal",
+            Self::CexDeposit { .. } => "cex_deposit",
+        }
+    }
+}
+
+/// Outbox entry, as delivered to webhooks.
+#[derive(Debug, Serialize)]
+pub(super) struct OutboxRecord {
+    /// Unique and increasing, to deduplicate notifications delivered more than once
+    pub id: i64,
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Id of the retracted notification, for retractions only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retracts: Option<i64>,
+    pub rule: String,
+    pub height: Height,
+    pub header_id: HeaderID,
+    pub event: serde_json::Value,
+    #[serde(skip_serializing)]
+    pub url: String,
+}
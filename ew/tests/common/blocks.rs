@@ -19,15 +19,15 @@ pub use genesis::GENESIS_BOXES;
 // pub type TestBlock = &'static str;
 pub struct TestBlock {
     /// Json string as returned by node API
-    str: &'static str,
+    str: String,
     /// Deserialized instance
     block: NodeBlock,
 }
 
 impl TestBlock {
-    pub fn new(str: &'static str) -> Self {
+    pub fn new(str: &str) -> Self {
         Self {
-            str,
+            str: str.to_owned(),
             block: serde_json::from_str(str).unwrap(),
         }
     }
@@ -69,6 +69,6 @@ impl TestBlock {
 
     /// Returns block as json object
     pub fn to_json(&self) -> serde_json::Value {
-        serde_json::from_str(self.str).unwrap()
+        serde_json::from_str(&self.str).unwrap()
     }
 }
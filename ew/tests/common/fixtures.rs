@@ -0,0 +1,93 @@
+//! Node API responses recorded from a real node.
+//!
+//! Record fixtures by running `ew` with `node.record_dir` set. Responses
+//! are saved under paths mirroring the requests':
+//!
+//! ```text
+//! info.json
+//! utxo/genesis.json
+//! blocks/at/<height>.json
+//! blocks/<header_id>.json
+//! ```
+//!
+//! Then load them with `Fixtures::at` and serve them with
+//! `TestNode::run_fixtures`.
+use std::path::Path;
+use std::path::PathBuf;
+
+use super::blocks::TestBlock;
+
+#[derive(Debug, Clone)]
+pub struct Fixtures {
+    dir: PathBuf,
+}
+
+#[allow(dead_code)] // Not used by all tests
+impl Fixtures {
+    /// Fixtures in given `dir`.
+    pub fn at(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns recorded block with given `header_id`.
+    ///
+    /// Panics if not recorded.
+    pub fn block(&self, header_id: &str) -> TestBlock {
+        let path = self.dir.join("blocks").join(format!("{header_id}.json"));
+        let json = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("no fixture for block {header_id} ({e})"));
+        TestBlock::new(&json)
+    }
+
+    /// Returns recorded genesis boxes, if any.
+    pub fn genesis_boxes(&self) -> Option<String> {
+        std::fs::read_to_string(self.dir.join("utxo").join("genesis.json")).ok()
+    }
+
+    /// Returns header ids of recorded main chain blocks, by increasing height.
+    ///
+    /// Where several blocks were recorded at the same height, the first one
+    /// listed by the recorded `blocks/at` response is taken.
+    pub fn main_chain(&self) -> Vec<String> {
+        let mut blocks: Vec<(i32, String)> = std::fs::read_dir(self.dir.join("blocks"))
+            .expect("fixtures have blocks")
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .map(|path| {
+                let header_id = path.file_stem().unwrap().to_str().unwrap().to_owned();
+                (self.block(&header_id).height(), header_id)
+            })
+            .collect();
+        blocks.sort();
+        let mut chain: Vec<String> = vec![];
+        for (height, header_id) in blocks {
+            let recorded = self.header_ids_at(height);
+            // Blocks missing from the recorded list come last
+            let rank = |id: &str| recorded.iter().position(|r| r == id).unwrap_or(usize::MAX);
+            match chain.last() {
+                Some(last) if self.block(last).height() == height => {
+                    if rank(&header_id) < rank(last) {
+                        *chain.last_mut().unwrap() = header_id;
+                    }
+                }
+                _ => chain.push(header_id),
+            }
+        }
+        chain
+    }
+
+    /// Returns recorded header ids at given `height`, if any.
+    fn header_ids_at(&self, height: i32) -> Vec<String> {
+        let path = self
+            .dir
+            .join("blocks")
+            .join("at")
+            .join(format!("{height}.json"));
+        match std::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).unwrap(),
+            Err(_) => vec![],
+        }
+    }
+}
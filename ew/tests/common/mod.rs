@@ -1,2 +1,3 @@
 pub mod blocks;
+pub mod fixtures;
 pub mod node_mockup;
@@ -1,15 +1,26 @@
 use super::blocks::TestBlock;
+use super::fixtures::Fixtures;
 use axum::extract::Path;
 use axum::extract::Query;
 use axum::extract::State;
+use axum::http::Request;
+use axum::http::StatusCode;
+use axum::middleware;
+use axum::middleware::Next;
+use axum::response::IntoResponse;
 use axum::response::Json;
+use axum::response::Response;
 use axum::routing::get;
+use axum::Extension;
 use axum::Router;
 use serde::Deserialize;
 use serde_json::json;
 use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::sync::Mutex;
 // use tokio::sync::mpsc;
 use tokio::sync::oneshot;
 
@@ -19,6 +30,8 @@ const LOCALHOST: &str = "127.0.0.1";
 
 type HeaderID = String;
 type BlockIndex = usize;
+/// Unconfirmed transactions, as returned by the node api
+type Pool = Arc<Mutex<Vec<Value>>>;
 
 async fn wait_some() {
     tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
@@ -28,33 +41,108 @@ pub struct TestNode {
     address: String,
     url: String,
     term_tx: Option<oneshot::Sender<()>>,
+    faults: Arc<Faults>,
+    /// Recorded responses to serve instead of hand-written blocks, if any
+    fixtures: Option<Fixtures>,
+    /// Transactions served by `/transactions/unconfirmed`
+    pool: Pool,
+}
+
+/// Failures injected by the mock node.
+#[derive(Default)]
+struct Faults {
+    /// Number of next requests to be answered with a 503.
+    unavailable: AtomicUsize,
+    /// API key requests must carry, if any.
+    api_key: Mutex<Option<String>>,
+    /// Number of requests received.
+    requests: AtomicUsize,
+    /// Number of full block requests received.
+    block_requests: AtomicUsize,
 }
 
 /// A mock node API listening on localhost.
 impl TestNode {
     /// Create a new node on default port
+    #[allow(dead_code)]
     pub fn new() -> Self {
+        Self::on_port(DEFAULT_PORT)
+    }
+
+    /// Create a new node on given `port`
+    pub fn on_port(port: i32) -> Self {
         Self {
-            address: format!("{LOCALHOST}:{DEFAULT_PORT}"),
-            url: format!("http://{LOCALHOST}:{DEFAULT_PORT}"),
+            address: format!("{LOCALHOST}:{port}"),
+            url: format!("http://{LOCALHOST}:{port}"),
             term_tx: None,
+            faults: Arc::new(Faults::default()),
+            fixtures: None,
+            pool: Pool::default(),
         }
     }
 
     /// Create a new node on the default port and start the api server.
+    #[allow(dead_code)]
     pub async fn run(block_ids: &[&str]) -> Self {
-        let mut n = Self::new();
+        Self::run_on(DEFAULT_PORT, block_ids).await
+    }
+
+    /// Create a new node on given `port` and start the api server.
+    pub async fn run_on(port: i32, block_ids: &[&str]) -> Self {
+        let mut n = Self::on_port(port);
         n.serve(block_ids).await;
         // Give some time to server to start up
         wait_some().await;
         n
     }
 
+    /// Create a new node on the default port, serving recorded `fixtures`.
+    ///
+    /// Blocks are identified by their header id, see `run`.
+    #[allow(dead_code)]
+    pub async fn run_fixtures(fixtures: Fixtures, header_ids: &[&str]) -> Self {
+        let mut n = Self::on_port(DEFAULT_PORT);
+        n.fixtures = Some(fixtures);
+        n.serve(header_ids).await;
+        wait_some().await;
+        n
+    }
+
     /// Return the node's api url
     pub fn url(&self) -> &str {
         &self.url
     }
 
+    /// Answer the next `n` requests with a 503 Service Unavailable.
+    #[allow(dead_code)]
+    pub fn fail_next(&self, n: usize) {
+        self.faults.unavailable.store(n, Ordering::SeqCst);
+    }
+
+    /// Reject requests not carrying given `api_key` with a 403 Forbidden.
+    #[allow(dead_code)]
+    pub fn require_api_key(&self, api_key: &str) {
+        *self.faults.api_key.lock().unwrap() = Some(api_key.to_owned());
+    }
+
+    /// Number of requests received so far.
+    #[allow(dead_code)]
+    pub fn request_count(&self) -> usize {
+        self.faults.requests.load(Ordering::SeqCst)
+    }
+
+    /// Number of full block requests received so far.
+    #[allow(dead_code)]
+    pub fn block_request_count(&self) -> usize {
+        self.faults.block_requests.load(Ordering::SeqCst)
+    }
+
+    /// Serve given unconfirmed transactions, replacing previous ones.
+    #[allow(dead_code)]
+    pub fn set_unconfirmed(&self, txs: Vec<Value>) {
+        *self.pool.lock().unwrap() = txs;
+    }
+
     /// Start the api server
     #[allow(dead_code)]
     pub async fn restart(&mut self, block_ids: &[&str]) {
@@ -73,32 +161,85 @@ impl TestNode {
         if self.term_tx.is_some() {
             panic!("Can't start a TestNode that's already running");
         }
-        let data = APIData::new(block_ids);
+        let data = match &self.fixtures {
+            Some(fixtures) => APIData::from_fixtures(fixtures, block_ids),
+            None => APIData::new(block_ids),
+        };
         let shared_state = Arc::new(data);
 
         let app = Router::new()
             .route("/", get(|| async { "Hello, World!" }))
+            .route("/info", get(info))
             .route("/blocks/at/:height", get(blocks_at))
             .route("/blocks/:header_id", get(blocks))
             .route("/blocks/:header_id/header", get(blocks_header))
             .route("/blocks/chainSlice", get(chain_slice))
             .route("/utxo/genesis", get(genesis_boxes))
-            .with_state(shared_state);
+            .route("/transactions/unconfirmed", get(unconfirmed))
+            .with_state(shared_state)
+            .layer(Extension(self.pool.clone()))
+            .layer(middleware::from_fn_with_state(
+                self.faults.clone(),
+                inject_faults,
+            ));
 
         let address = self.address.to_string();
         let (tx, rx) = oneshot::channel();
         self.term_tx = Some(tx);
         tokio::spawn(async move {
             tracing::info!("Starting server listening to {}", &address);
-            tokio::select! {
-                _ = axum::Server::bind(&address.parse().unwrap())
-                    .serve(app.into_make_service()) => {},
-                _ = rx => {tracing::info!("Stopped server")},
-            }
+            // Shutting down gracefully closes kept-alive connections,
+            // like a real node restart would.
+            axum::Server::bind(&address.parse().unwrap())
+                .serve(app.into_make_service())
+                .with_graceful_shutdown(async {
+                    rx.await.ok();
+                })
+                .await
+                .unwrap();
+            tracing::info!("Stopped server");
         });
     }
 }
 
+/// Applies configured faults before handling a request.
+async fn inject_faults<B>(
+    State(faults): State<Arc<Faults>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    faults.requests.fetch_add(1, Ordering::SeqCst);
+    if is_block_request(request.uri().path()) {
+        faults.block_requests.fetch_add(1, Ordering::SeqCst);
+    }
+    if faults
+        .unavailable
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+        .is_ok()
+    {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+    let expected_key = faults.api_key.lock().unwrap().clone();
+    if let Some(expected_key) = expected_key {
+        let api_key = request
+            .headers()
+            .get("api_key")
+            .and_then(|v| v.to_str().ok());
+        if api_key != Some(expected_key.as_str()) {
+            return StatusCode::FORBIDDEN.into_response();
+        }
+    }
+    next.run(request).await
+}
+
+/// Tells if `path` is a request for a full block (`blocks/<header_id>`).
+fn is_block_request(path: &str) -> bool {
+    match path.strip_prefix("/blocks/") {
+        Some(rest) => !rest.contains('/') && rest != "chainSlice",
+        None => false,
+    }
+}
+
 /// A mock node's preprocessed test data.
 struct APIData {
     /// Collection of blocks known to the mock node
@@ -111,13 +252,31 @@ struct APIData {
     lookup: HashMap<HeaderID, BlockIndex>,
     /// Main chain headers
     height_header_lookup: HashMap<i32, BlockIndex>,
+    /// Json string of genesis boxes
+    genesis_boxes: String,
 }
 
 impl APIData {
     fn new(block_ids: &[&str]) -> Self {
         // Collect blocks from id's
         let blocks: Vec<TestBlock> = block_ids.iter().map(|id| TestBlock::from_id(id)).collect();
+        Self::from_blocks(blocks, block_ids, super::blocks::GENESIS_BOXES.to_owned())
+    }
+
+    /// Data for recorded blocks with given `header_ids`.
+    fn from_fixtures(fixtures: &Fixtures, header_ids: &[&str]) -> Self {
+        let blocks: Vec<TestBlock> = header_ids
+            .iter()
+            .map(|id| fixtures.block(id.strip_suffix('*').unwrap_or(id)))
+            .collect();
+        let genesis_boxes = fixtures
+            .genesis_boxes()
+            .unwrap_or(super::blocks::GENESIS_BOXES.to_owned());
+        Self::from_blocks(blocks, header_ids, genesis_boxes)
+    }
 
+    /// Block ids ending with a `*` are not part of the main chain.
+    fn from_blocks(blocks: Vec<TestBlock>, block_ids: &[&str], genesis_boxes: String) -> Self {
         // Extract height from each block's header
         let heights: Vec<i32> = blocks.iter().map(|b| b.height()).collect();
 
@@ -155,10 +314,23 @@ impl APIData {
             header_ids,
             lookup,
             height_header_lookup,
+            genesis_boxes,
         }
     }
 }
 
+/// Mock of `/info` node endpoint
+///
+/// Returns height and header id of main chain tip
+async fn info(State(state): State<Arc<APIData>>) -> Json<Value> {
+    let height = *state.height_header_lookup.keys().max().unwrap();
+    let header_id = &state.header_ids[state.height_header_lookup[&height]];
+    Json(json!({
+        "fullHeight": height,
+        "bestFullHeaderId": header_id,
+    }))
+}
+
 /// Mock of `/blocks/at/<height>` node endpoint
 ///
 /// Retruns collection of headers known for given `height`
@@ -247,6 +419,28 @@ async fn chain_slice(
     Json(headers)
 }
 
-async fn genesis_boxes() -> Json<Value> {
-    Json(serde_json::from_str(super::blocks::GENESIS_BOXES).unwrap())
+async fn genesis_boxes(State(state): State<Arc<APIData>>) -> Json<Value> {
+    Json(serde_json::from_str(&state.genesis_boxes).unwrap())
+}
+
+#[derive(Deserialize)]
+struct PageParameters {
+    offset: usize,
+    limit: usize,
+}
+
+/// Mock of `transactions/unconfirmed?offset=<n>&limit=<n>`
+async fn unconfirmed(
+    Extension(pool): Extension<Pool>,
+    params: Query<PageParameters>,
+) -> Json<Value> {
+    let txs: Vec<Value> = pool
+        .lock()
+        .unwrap()
+        .iter()
+        .skip(params.offset)
+        .take(params.limit)
+        .cloned()
+        .collect();
+    Json(json!(txs))
 }
@@ -48,6 +48,7 @@ impl TestDB {
     }
 
     /// Initialize core schema.
+    #[allow(dead_code)] // not used by all tests
     pub async fn init_core(&self) {
         self.client
             .batch_execute(include_str!("../../src/core/store/schema.sql"))
@@ -73,6 +74,7 @@ impl TestDB {
     /// Insert main chain header into core.headers.
     ///
     /// Needed for rollbacks.
+    #[allow(dead_code)] // not used by all tests
     pub async fn insert_core_header(&self, header: &Header) {
         tracing::trace!("insert {header:?}");
         let stmt = "
@@ -0,0 +1,448 @@
+mod db_utils;
+
+use db_utils::TestDB;
+use ew::constants::GENESIS_TIMESTAMP;
+use ew::constants::ZERO_HEADER;
+use ew::core::types::Block;
+use ew::core::types::BoxData;
+use ew::core::types::CoreData;
+use ew::core::types::Header;
+use ew::error::EwError;
+use ew::framework::fuzzing::replay;
+use ew::framework::fuzzing::ChainFuzzer;
+use ew::framework::store::MigrationEffect;
+use ew::framework::store::Revision;
+use ew::framework::EventHandling;
+use ew::workers::admin;
+use ew::workers::admin::AdminError;
+use ew::workers::admin::CheckOutcome;
+use ew::workers::erg::ErgWorkFlow;
+use ew::workers::erg_diffs::ErgDiffsWorkFlow;
+use ew::workers::registry::WorkerID;
+use ew::workers::timestamps::TimestampsWorkFlow;
+use ew::workers::tokens::TokensWorkFlow;
+
+#[tokio::test]
+async fn test_status_and_rollback() {
+    let test_db = TestDB::new("admin_rollback").await;
+    test_db.init_core().await;
+
+    // Genesis and blocks X, Y, Z
+    let dummy_genesis_boxes = vec![BoxData::dummy()
+        .creation_height(0)
+        .timestamp(GENESIS_TIMESTAMP)];
+    let genesis_block = Block::from_genesis_boxes(dummy_genesis_boxes);
+    let block_x = Block::dummy()
+        .height(1)
+        .parent_id(ZERO_HEADER)
+        .timestamp(GENESIS_TIMESTAMP + 120_000);
+    let block_y = Block::child_of(&block_x).timestamp(GENESIS_TIMESTAMP + 240_000);
+    let block_z = Block::child_of(&block_y).timestamp(GENESIS_TIMESTAMP + 360_000);
+    let blocks = [genesis_block, block_x, block_y, block_z];
+    for block in &blocks {
+        test_db
+            .insert_core_header(&Header::from(&block.header))
+            .await;
+    }
+
+    // Process all blocks
+    let mut workflow = TimestampsWorkFlow::new(&test_db.pgconf).await.unwrap();
+    for block in blocks {
+        workflow
+            .include_block(&CoreData { block }.into())
+            .await
+            .unwrap();
+    }
+    drop(workflow);
+
+    // Timestamps at tip, tokens never ran
+    let status = admin::status(&test_db.pgconf, &[WorkerID::Timestamps, WorkerID::Tokens])
+        .await
+        .unwrap();
+    assert_eq!(status.core.as_ref().unwrap().height, 3);
+    assert_eq!(status.workers[0].header.as_ref().unwrap().height, 3);
+    assert!(status.workers[0].main_chain);
+    assert!(status.workers[1].header.is_none());
+    assert!(status.workers[1].revision.is_none());
+
+    // Can't roll forward
+    let err = admin::roll_back(&test_db.pgconf, WorkerID::Timestamps, 4)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AdminError::InvalidTarget(_, 3, 4)));
+
+    // Roll back 2 blocks
+    let header = admin::roll_back(&test_db.pgconf, WorkerID::Timestamps, 1)
+        .await
+        .unwrap();
+    assert_eq!(header.height, 1);
+    let status = admin::status(&test_db.pgconf, &[WorkerID::Timestamps])
+        .await
+        .unwrap();
+    assert_eq!(status.workers[0].header.as_ref().unwrap().height, 1);
+    let n: i64 = test_db
+        .client
+        .query_one("select count(*) from timestamps.timestamps;", &[])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(n, 2);
+}
+
+#[tokio::test]
+async fn test_rewind_running_worker() {
+    let test_db = TestDB::new("admin_rewind").await;
+    test_db.init_core().await;
+
+    // Genesis and blocks X, Y, Z
+    let dummy_genesis_boxes = vec![BoxData::dummy()
+        .creation_height(0)
+        .timestamp(GENESIS_TIMESTAMP)];
+    let genesis_block = Block::from_genesis_boxes(dummy_genesis_boxes);
+    let block_x = Block::dummy()
+        .height(1)
+        .parent_id(ZERO_HEADER)
+        .timestamp(GENESIS_TIMESTAMP + 120_000);
+    let block_y = Block::child_of(&block_x).timestamp(GENESIS_TIMESTAMP + 240_000);
+    let block_z = Block::child_of(&block_y).timestamp(GENESIS_TIMESTAMP + 360_000);
+    for block in [&genesis_block, &block_x, &block_y, &block_z] {
+        test_db
+            .insert_core_header(&Header::from(&block.header))
+            .await;
+    }
+
+    // Process genesis, X and Y
+    let mut workflow = TimestampsWorkFlow::new(&test_db.pgconf).await.unwrap();
+    for block in [genesis_block, block_x, block_y] {
+        workflow
+            .include_block(&CoreData { block }.into())
+            .await
+            .unwrap();
+    }
+
+    // Not all workers can be rewound
+    let err = admin::rewind(&test_db.pgconf, WorkerID::Network, 0)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AdminError::NotRewindable(WorkerID::Network)));
+
+    // Rewind to genesis while workflow is still around
+    let header = admin::rewind(&test_db.pgconf, WorkerID::Timestamps, 0)
+        .await
+        .unwrap();
+    assert_eq!(header.height, 0);
+    let n: i64 = test_db
+        .client
+        .query_one("select count(*) from timestamps.timestamps;", &[])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(n, 1);
+
+    // Running workflow notices on next block
+    let err = workflow
+        .include_block(&CoreData { block: block_z }.into())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, EwError::StoreHeaderMoved(_, 0)));
+    drop(workflow);
+
+    // Restarted workflow resumes from rewind height
+    let workflow = TimestampsWorkFlow::new(&test_db.pgconf).await.unwrap();
+    assert_eq!(workflow.header(), &header);
+}
+
+#[tokio::test]
+async fn test_reset() {
+    let test_db = TestDB::new("admin_reset").await;
+    ew::core::migrate(&test_db.pgconf).await.unwrap();
+
+    // Initialize erg_diffs and erg stores, sharing the erg schema
+    admin::migrate(&test_db.pgconf, &[WorkerID::ErgDiffs, WorkerID::Erg])
+        .await
+        .unwrap();
+
+    // Pretend erg has processed some blocks
+    test_db
+        .client
+        .execute(
+            "update ew.headers set height = 5 where worker_id = 'erg';",
+            &[],
+        )
+        .await
+        .unwrap();
+
+    // Dependent worker erg must be reset first
+    let err = admin::reset(&test_db.pgconf, WorkerID::ErgDiffs)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        AdminError::DependentAhead(WorkerID::ErgDiffs, WorkerID::Erg, 5)
+    ));
+
+    // Resetting erg leaves erg_diffs untouched
+    admin::reset(&test_db.pgconf, WorkerID::Erg).await.unwrap();
+    assert!(test_db.get_revision("erg", "erg").await.is_none());
+    assert!(test_db.get_revision("erg", "erg_diffs").await.is_some());
+    assert!(!table_exists(&test_db, "erg.balances").await);
+    assert!(table_exists(&test_db, "erg.balance_diffs").await);
+
+    // Now erg_diffs can be reset too
+    admin::reset(&test_db.pgconf, WorkerID::ErgDiffs)
+        .await
+        .unwrap();
+    assert!(test_db.get_revision("erg", "erg_diffs").await.is_none());
+    assert!(!table_exists(&test_db, "erg.balance_diffs").await);
+
+    // Stores get initialized again when migrating
+    admin::migrate(&test_db.pgconf, &[WorkerID::ErgDiffs, WorkerID::Erg])
+        .await
+        .unwrap();
+    let status = admin::status(&test_db.pgconf, &[WorkerID::ErgDiffs, WorkerID::Erg])
+        .await
+        .unwrap();
+    for ws in status.workers {
+        assert!(ws.header.unwrap().is_initial());
+    }
+}
+
+#[tokio::test]
+async fn test_migrate_purged_store() {
+    let test_db = TestDB::new("admin_migrate_purged").await;
+    ew::core::migrate(&test_db.pgconf).await.unwrap();
+
+    // Sigmausd store at revision 1.0, purged by migration 1.1
+    test_db.init_schema("create schema sigmausd;").await;
+    test_db
+        .set_revision("sigmausd", "sigmausd", &Revision::new(1, 0))
+        .await;
+    test_db
+        .set_worker_header("sigmausd", "sigmausd", &Header::initial())
+        .await;
+
+    // Plain dry run lists all pending migrations, without running them
+    let pending = admin::pending_migrations(&test_db.pgconf, &[WorkerID::SigmaUSD], false)
+        .await
+        .unwrap();
+    let revisions: Vec<Revision> = pending.iter().map(|p| p.revision.clone()).collect();
+    assert_eq!(
+        revisions,
+        vec![
+            Revision::new(1, 1),
+            Revision::new(1, 2),
+            Revision::new(1, 3)
+        ]
+    );
+    assert!(pending.iter().all(|p| p.effect.is_none()));
+
+    // Evaluating dry run stops at the purge
+    let pending = admin::pending_migrations(&test_db.pgconf, &[WorkerID::SigmaUSD], true)
+        .await
+        .unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].revision, Revision::new(1, 1));
+    assert_eq!(pending[0].effect, Some(MigrationEffect::Purge));
+    assert!(pending[0].sql.is_some());
+
+    // Store gets recreated at latest revision
+    admin::migrate(&test_db.pgconf, &[WorkerID::SigmaUSD])
+        .await
+        .unwrap();
+    assert_eq!(
+        test_db.get_revision("sigmausd", "sigmausd").await,
+        Some(Revision::new(1, 3))
+    );
+    assert!(table_exists(&test_db, "sigmausd.noop_bank_transactions").await);
+    assert!(
+        admin::pending_migrations(&test_db.pgconf, &[WorkerID::SigmaUSD], true)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_migrate_legacy_core() {
+    let test_db = TestDB::new("admin_migrate_legacy_core").await;
+
+    // Core at revision 1.1, tracked in its own _rev table
+    test_db.init_core().await;
+    test_db
+        .init_schema(
+            "
+            drop table core.transactions, core.transactions_since, core.inputs, core.data_inputs;
+            alter table core.boxes drop column tx_id, drop column output_idx;
+            alter table core.blocks add column transactions json not null;
+            create table core._rev (
+                singleton int primary key default 1,
+                rev_major integer not null,
+                rev_minor integer not null,
+                check(singleton = 1)
+            );
+            insert into core._rev (rev_major, rev_minor) values (1, 1);
+            insert into core.headers (height, timestamp, header_id, parent_id, main_chain)
+            values (1, 1000, 'header-1', 'header-0', true);
+            insert into core.boxes (box_id, height, creation_height, address_id, value, size, registers)
+            values ('box-1', 1, 1, 11, 1000, 100, '{}');
+            insert into core.blocks (height, header, extension, size, transactions)
+            values (1, '{}', '{}', 200, '[{
+                \"id\": \"tx-1\", \"inputs\": [], \"data_inputs\": [], \"outputs\": [\"box-0\", \"box-1\"]
+            }]');
+            ",
+        )
+        .await;
+
+    // Dry run picks up from the legacy revision
+    let pending = admin::pending_migrations(&test_db.pgconf, &[], true)
+        .await
+        .unwrap();
+    assert_eq!(pending.len(), 2);
+    assert_eq!(pending[0].store, "core:core");
+    assert_eq!(pending[0].revision, Revision::new(1, 2));
+    assert_eq!(pending[1].revision, Revision::new(1, 3));
+    assert!(pending.iter().all(|p| p.sql.is_some()));
+
+    // Revision moves to ew.revisions
+    admin::migrate(&test_db.pgconf, &[]).await.unwrap();
+    assert_eq!(
+        test_db.get_revision("core", "core").await,
+        Some(Revision::new(1, 3))
+    );
+    assert!(!table_exists(&test_db, "core._rev").await);
+    assert!(table_exists(&test_db, "core.transactions").await);
+
+    // Transactions of blocks included so far are not backfilled
+    let since: i32 = test_db
+        .client
+        .query_one("select height from core.transactions_since;", &[])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(since, 2);
+
+    // Output positions are taken from stored layouts, dropping blocks
+    // that can't be rebuilt without indexed transactions.
+    let output_idx: Option<i32> = test_db
+        .client
+        .query_one(
+            "select output_idx from core.boxes where box_id = 'box-1';",
+            &[],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(output_idx, Some(1));
+    let n: i64 = test_db
+        .client
+        .query_one("select count(*) from core.blocks;", &[])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(n, 0);
+    assert!(admin::pending_migrations(&test_db.pgconf, &[], true)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Core has no header of its own
+    let n: i64 = test_db
+        .client
+        .query_one("select count(*) from ew.headers;", &[])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(n, 0);
+}
+
+#[tokio::test]
+async fn test_verify() {
+    let test_db = TestDB::new("admin_verify").await;
+    test_db.init_core().await;
+    let workers = [WorkerID::ErgDiffs, WorkerID::Erg, WorkerID::Tokens];
+
+    // Nothing to verify yet
+    let verification = admin::verify(&test_db.pgconf, &workers).await.unwrap();
+    assert_eq!(verification.mismatches(), 0);
+    assert!(matches!(
+        verification.checks[0].outcome,
+        CheckOutcome::Skipped(_)
+    ));
+
+    // Sync a random chain
+    let scenario = ChainFuzzer::new(1).length(20).generate();
+    let genesis = &scenario.main_chain[0].data.block;
+    for output in genesis.transactions.iter().flat_map(|tx| &tx.outputs) {
+        test_db
+            .client
+            .execute(
+                "insert into core.boxes (box_id, height, creation_height, address_id, value, size, registers)
+                values ($1, 0, 0, $2, $3, 100, '{}');",
+                &[&output.box_id, &output.address_id, &output.value],
+            )
+            .await
+            .unwrap();
+    }
+    let events = scenario.sync_events();
+    let mut erg_diffs = ErgDiffsWorkFlow::new(&test_db.pgconf).await.unwrap();
+    let diffs = replay(&mut erg_diffs, &test_db.client, &events)
+        .await
+        .unwrap();
+    let mut erg = ErgWorkFlow::new(&test_db.pgconf).await.unwrap();
+    replay(&mut erg, &test_db.client, &diffs).await.unwrap();
+    let mut tokens = TokensWorkFlow::new(&test_db.pgconf).await.unwrap();
+    replay(&mut tokens, &test_db.client, &events).await.unwrap();
+
+    let verification = admin::verify(&test_db.pgconf, &workers).await.unwrap();
+    assert_eq!(verification.checks.len(), 4);
+    for check in &verification.checks {
+        assert!(
+            matches!(check.outcome, CheckOutcome::Passed),
+            "{verification}"
+        );
+    }
+
+    // Tamper with derived tables and main chain
+    test_db
+        .client
+        .batch_execute(
+            "
+            update erg.balances set nano = nano + 1
+            where address_id = (select min(address_id) from erg.balances);
+            update erg.supply_composition set p2pks = p2pks + 1 where height >= 18;
+            delete from tokens.balances
+            where address_id = (select min(address_id) from tokens.balances);
+            update core.headers set main_chain = False where height = 20;
+            insert into ew.headers (schema_name, worker_id, height, timestamp, header_id, parent_id)
+            values ('timestamps', 'timestamps', 5, 0, 'stale', '');
+            ",
+        )
+        .await
+        .unwrap();
+    let verification = admin::verify(&test_db.pgconf, &workers).await.unwrap();
+    let counts: Vec<i64> = verification
+        .checks
+        .iter()
+        .map(|check| match check.outcome {
+            CheckOutcome::Failed(n, _) => n,
+            _ => 0,
+        })
+        .collect();
+    // Erg balances, supply composition at heights 18 to 20, token balances
+    // of one address and headers of all 3 workers, plus the stale header
+    // of a disabled one.
+    assert_eq!(counts[0], 1);
+    assert_eq!(counts[1], 3);
+    assert!(counts[2] >= 1);
+    assert_eq!(counts[3], 4);
+    assert_eq!(verification.mismatches(), counts.iter().sum::<i64>());
+}
+
+async fn table_exists(test_db: &TestDB, table: &str) -> bool {
+    test_db
+        .client
+        .query_one("select to_regclass($1) is not null;", &[&table])
+        .await
+        .unwrap()
+        .get(0)
+}
@@ -0,0 +1,252 @@
+// cargo test --test '*' -- --test-threads=1
+mod db_utils;
+
+use async_trait::async_trait;
+use serde_json::json;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+use tokio::sync::broadcast;
+use tokio::sync::Mutex;
+
+use db_utils::TestDB;
+use ew::alerts::notifier::Notifier;
+use ew::alerts::AlertState;
+use ew::alerts::Alerter;
+use ew::config::AlertMetric;
+use ew::config::AlertRule;
+use ew::config::AlertsConfig;
+use ew::config::MonitorConfig;
+use ew::core::types::Timestamp;
+use ew::monitor::events::LiveEvent;
+use ew::monitor::Monitor;
+use ew::shutdown::CancellationToken;
+
+const MONITOR_PORT: u16 = 3110;
+const HOUR: Timestamp = 3_600_000;
+
+/// Records notified rules and whether they were firing.
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<(String, bool)>>>);
+
+#[async_trait]
+impl Notifier for Capture {
+    async fn notify(&self, rule: &AlertRule, state: &AlertState) {
+        self.0.lock().await.push((rule.name.clone(), state.firing));
+    }
+}
+
+impl Capture {
+    /// Waits for `n` notifications and returns them.
+    async fn wait_for(&self, n: usize) -> Vec<(String, bool)> {
+        for _ in 0..100 {
+            let notified = self.0.lock().await.clone();
+            if notified.len() >= n {
+                return notified;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("expected {n} notifications");
+    }
+}
+
+fn now() -> Timestamp {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as Timestamp
+}
+
+fn config() -> AlertsConfig {
+    AlertsConfig {
+        rules: vec![
+            AlertRule {
+                name: "hash-drop".to_owned(),
+                metric: AlertMetric::HashRateChange24h,
+                above: None,
+                below: Some(-20.0),
+            },
+            // No exchange data yet, so never evaluated
+            AlertRule {
+                name: "cex-flows".to_owned(),
+                metric: AlertMetric::CexSupplyChange24h,
+                above: Some(5.0),
+                below: Some(-5.0),
+            },
+            AlertRule {
+                name: "undercollateralized".to_owned(),
+                metric: AlertMetric::SigmaUSDReserveRatio,
+                above: None,
+                below: Some(400.0),
+            },
+        ],
+        ..AlertsConfig::default()
+    }
+}
+
+/// Fills core headers, mining and SigmaUSD records.
+///
+/// Hash rate dropped by 30% over the last 24h and reserve ratio is at 350%.
+async fn populate(test_db: &TestDB, now: Timestamp) {
+    test_db.init_core().await;
+    test_db.init_ew().await;
+    for sql in [
+        include_str!("../src/workers/network/store/schema.sql"),
+        include_str!("../src/workers/exchanges/store/schema.sql"),
+        include_str!("../src/workers/sigmausd/store/schema.sql"),
+    ] {
+        test_db.init_schema(sql).await;
+    }
+    let sql = format!(
+        "
+        insert into core.headers (height, timestamp, header_id, parent_id, main_chain) values
+            (1, {}, 'h1', 'h0', true),
+            (2, {}, 'h2', 'h1', true),
+            (3, {}, 'h3', 'h2', true);
+        insert into network.mining (height, miner_address_id, difficulty, difficulty_24h_mean, hash_rate_24h_mean, block_reward, tx_fees) values
+            (1, 1, 1, 1, 1000, 0, 0),
+            (2, 1, 1, 1, 1000, 0, 0),
+            (3, 1, 1, 1, 700, 0, 0);
+        -- 500 SigUSD at 2 ERG per USD, backed by 3500 ERG
+        delete from sigmausd.history;
+        insert into sigmausd.history (height, oracle, circ_sc, circ_rc, reserves, sc_nano_net, rc_nano_net) values
+            (3, 2000000000, 50000, 10, 3500000000000, 0, 0);
+        ",
+        now - 25 * HOUR,
+        now - HOUR,
+        now,
+    );
+    test_db.client.batch_execute(&sql).await.unwrap();
+}
+
+async fn get_alerts() -> Value {
+    let url = format!("http://localhost:{MONITOR_PORT}/alerts");
+    // Server might not be listening yet
+    for _ in 0..50 {
+        if let Ok(res) = reqwest::get(&url).await {
+            return res.json().await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("monitor not reachable");
+}
+
+#[tokio::test]
+async fn test_alerts() {
+    let test_db = TestDB::new("alerts").await;
+    let now = now();
+    populate(&test_db, now).await;
+    let mut monitor = Monitor::with_config(&MonitorConfig {
+        port: MONITOR_PORT,
+        max_lag: 10,
+    });
+    let monitor_tx = monitor.sender();
+    tokio::spawn(async move { monitor.start().await });
+    let (events_tx, events_rx) = broadcast::channel(16);
+    let shutdown = CancellationToken::new();
+
+    // Rules are evaluated on start
+    let capture = Capture::default();
+    let mut alerter = Alerter::new(
+        &test_db.pgconf,
+        &config(),
+        Box::new(capture.clone()),
+        monitor_tx.clone(),
+    )
+    .await
+    .unwrap();
+    // Alerts have no header to keep track of
+    let sql = "select count(*) from ew.headers where schema_name = 'alerts';";
+    let count: i64 = test_db.client.query_one(sql, &[]).await.unwrap().get(0);
+    assert_eq!(count, 0);
+    let stop = shutdown.clone();
+    let handle = tokio::spawn(async move { alerter.start(events_rx, stop).await });
+    assert_eq!(
+        capture.wait_for(2).await,
+        vec![
+            ("hash-drop".to_owned(), true),
+            ("undercollateralized".to_owned(), true),
+        ]
+    );
+
+    // Reserve ratio recovers to 500%
+    let sql = format!(
+        "
+        insert into core.headers (height, timestamp, header_id, parent_id, main_chain) values
+            (4, {now}, 'h4', 'h3', true);
+        insert into sigmausd.history (height, oracle, circ_sc, circ_rc, reserves, sc_nano_net, rc_nano_net) values
+            (4, 2000000000, 50000, 10, 5000000000000, 0, 0);
+        "
+    );
+    test_db.client.batch_execute(&sql).await.unwrap();
+    // Other workers' blocks don't trigger sigmausd rules
+    events_tx
+        .send(LiveEvent::Block {
+            worker: "tokens",
+            height: 4,
+            header_id: "h4".to_owned(),
+            figures: None,
+        })
+        .unwrap();
+    events_tx
+        .send(LiveEvent::Block {
+            worker: "sigmausd",
+            height: 4,
+            header_id: "h4".to_owned(),
+            figures: None,
+        })
+        .unwrap();
+    assert_eq!(
+        capture.wait_for(3).await[2],
+        ("undercollateralized".to_owned(), false)
+    );
+
+    // States are persisted
+    let sql = "select rule, firing, value, height from alerts.states order by rule;";
+    let rows: Vec<(String, bool, f64, i32)> = test_db
+        .client
+        .query(sql, &[])
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3)))
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            ("hash-drop".to_owned(), true, -30.0, 3),
+            ("undercollateralized".to_owned(), false, 500.0, 4),
+        ]
+    );
+
+    // And exposed on the monitor
+    let alerts = get_alerts().await;
+    assert_eq!(alerts[0]["rule"], json!("hash-drop"));
+    assert_eq!(alerts[0]["firing"], json!(true));
+    assert_eq!(alerts[1]["rule"], json!("undercollateralized"));
+    assert_eq!(alerts[1]["firing"], json!(false));
+    assert_eq!(alerts.as_array().unwrap().len(), 2);
+
+    shutdown.cancel();
+    handle.await.unwrap().unwrap();
+
+    // Restarts don't notify again
+    let capture = Capture::default();
+    let mut alerter = Alerter::new(
+        &test_db.pgconf,
+        &config(),
+        Box::new(capture.clone()),
+        monitor_tx,
+    )
+    .await
+    .unwrap();
+    let (_events_tx, events_rx) = broadcast::channel(16);
+    let shutdown = CancellationToken::new();
+    let stop = shutdown.clone();
+    let handle = tokio::spawn(async move { alerter.start(events_rx, stop).await });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    shutdown.cancel();
+    handle.await.unwrap().unwrap();
+    assert!(capture.0.lock().await.is_empty());
+}
@@ -0,0 +1,291 @@
+// cargo test --test '*' -- --test-threads=1
+mod db_utils;
+
+use db_utils::TestDB;
+use ew::api::QueryApi;
+use serde_json::json;
+use serde_json::Value;
+
+const PORT: u16 = 3106;
+const PORT_NO_WORKERS: u16 = 3107;
+const TOKEN_ID: &str = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+const BOX_SPENT: &str = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+const BOX_UNKNOWN: &str = "cccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccc";
+const BOX_UNSPENT: &str = "dddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddd";
+const TX_ID: &str = "eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee";
+
+/// Fills worker tables with a few records.
+async fn populate(test_db: &TestDB) {
+    test_db.init_core().await;
+    test_db.init_ew().await;
+    for sql in [
+        include_str!("../src/workers/timestamps/store/schema.sql"),
+        include_str!("../src/workers/erg_diffs/store/schema.sql"),
+        include_str!("../src/workers/erg/store/schema.sql"),
+        include_str!("../src/workers/tokens/store/schema.sql"),
+        include_str!("../src/workers/exchanges/store/schema.sql"),
+        include_str!("../src/workers/sigmausd/store/schema.sql"),
+    ] {
+        test_db.init_schema(sql).await;
+    }
+    let sql = format!(
+        "
+        insert into core.addresses (id, spot_height, address) values
+            (13, 0, 'emission'),
+            (21, 1, 'addressA'),
+            (31, 1, 'addressB'),
+            (43, 1, 'contractC');
+        insert into core.tokens (asset_id, spot_height, token_id) values
+            (1, 1, '{TOKEN_ID}');
+        -- Transactions are indexed from height 2 on
+        update core.transactions_since set height = 2;
+        insert into core.boxes (box_id, height, creation_height, address_id, value, size, registers) values
+            ('{BOX_SPENT}', 1, 1, 21, 1000, 100, '{{}}'),
+            ('{BOX_UNKNOWN}', 1, 1, 21, 1000, 100, '{{}}'),
+            ('{BOX_UNSPENT}', 2, 2, 21, 1000, 100, '{{}}');
+        insert into core.inputs (box_id, tx_id, idx, height) values
+            ('{BOX_SPENT}', '{TX_ID}', 0, 2);
+        insert into timestamps.timestamps (height, timestamp) values
+            (1, 1000),
+            (2, 2000);
+        -- Balance diffs workers are at height 2
+        insert into ew.revisions (schema_name, worker_id, major, minor) values
+            ('erg', 'erg_diffs', 1, 0),
+            ('tokens', 'tokens', 1, 0);
+        insert into ew.headers (schema_name, worker_id, height, timestamp, header_id, parent_id) values
+            ('erg', 'erg_diffs', 2, 2000, 'header2', 'header1'),
+            ('tokens', 'tokens', 2, 2000, 'header2', 'header1');
+        insert into erg.balance_diffs (address_id, height, tx_idx, nano) values
+            (21, 1, 0, 200),
+            (21, 2, 0, 300);
+        insert into erg.balances (address_id, nano, mean_age_timestamp) values
+            (13, 9000, 0),
+            (21, 500, 0),
+            (31, 300, 0),
+            (43, 100, 0);
+        -- 20 minted at height 1, 5 burned at height 2
+        insert into tokens.balance_diffs (address_id, asset_id, height, tx_idx, value) values
+            (43, 1, 1, 0, 20),
+            (43, 1, 2, 0, -10),
+            (21, 1, 2, 0, 5);
+        insert into tokens.balances (address_id, asset_id, value) values
+            (21, 1, 5),
+            (43, 1, 10);
+        insert into erg.supply_composition (height, p2pks, contracts, miners) values
+            (1, 10, 20, 30),
+            (2, 100, 200, 300);
+        insert into exchanges.supply (height, main, deposits) values
+            (2, 1000, 50);
+        insert into sigmausd.history (height, oracle, circ_sc, circ_rc, reserves, sc_nano_net, rc_nano_net) values
+            (500000, 2000, 12345, 10, 99, 0, 0);
+        "
+    );
+    test_db.client.batch_execute(&sql).await.unwrap();
+}
+
+/// GET `path` and return status code and json body.
+async fn get(path: &str) -> (u16, Value) {
+    get_on(PORT, path).await
+}
+
+/// GET `path` from api on `port` and return status code and json body.
+async fn get_on(port: u16, path: &str) -> (u16, Value) {
+    let url = format!("http://localhost:{port}{path}");
+    // Server might not be listening yet
+    for _ in 0..50 {
+        if let Ok(res) = reqwest::get(&url).await {
+            return (res.status().as_u16(), res.json().await.unwrap());
+        }
+        tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+    }
+    panic!("query api not reachable");
+}
+
+#[tokio::test]
+async fn test_query_api() {
+    let test_db = TestDB::new("query_api").await;
+    populate(&test_db).await;
+    QueryApi::new(&test_db.pgconf, PORT).start();
+
+    // Balances
+    assert_eq!(get("/addresses/addressA/balance").await, (200, json!(500)));
+    assert_eq!(
+        get(&format!("/addresses/addressA/balance?token_id={TOKEN_ID}")).await,
+        (200, json!(5))
+    );
+    assert_eq!(
+        get("/addresses/addressA/balance/at/height/1").await,
+        (200, json!(200))
+    );
+    assert_eq!(
+        get("/addresses/addressA/balance/at/timestamp/2500").await,
+        (200, json!(500))
+    );
+    assert_eq!(
+        get("/addresses/addressA/balance/at/timestamp/500").await,
+        (404, json!({"detail": "No balance found"}))
+    );
+    assert_eq!(
+        get(&format!(
+            "/addresses/contractC/balance/at/height/1?token_id={TOKEN_ID}"
+        ))
+        .await,
+        (200, json!(20))
+    );
+    assert_eq!(get("/addresses/unknown/balance").await.0, 404);
+    assert_eq!(get("/addresses/addressB/balance/at/height/2").await.0, 404);
+    assert_eq!(
+        get("/addresses/addressA/balance/at/height/3").await,
+        (404, json!({"detail": "Height not indexed yet"}))
+    );
+    assert_eq!(
+        get(&format!(
+            "/addresses/addressA/balance/at/height/3?token_id={TOKEN_ID}"
+        ))
+        .await
+        .0,
+        404
+    );
+    assert_eq!(get("/addresses/not-an-address/balance").await.0, 400);
+    assert_eq!(get("/addresses/addressA/balance?token_id=abc").await.0, 400);
+
+    // Box spending
+    assert_eq!(
+        get(&format!("/boxes/{BOX_SPENT}/spending")).await,
+        (200, json!({"status": "spent", "tx_id": TX_ID, "height": 2}))
+    );
+    assert_eq!(
+        get(&format!("/boxes/{BOX_UNKNOWN}/spending")).await,
+        (
+            200,
+            json!({"status": "unknown", "tx_id": null, "height": null})
+        )
+    );
+    assert_eq!(
+        get(&format!("/boxes/{BOX_UNSPENT}/spending")).await,
+        (
+            200,
+            json!({"status": "unspent", "tx_id": null, "height": null})
+        )
+    );
+    assert_eq!(
+        get(&format!("/boxes/{}/spending", "f".repeat(64))).await,
+        (404, json!({"detail": "Box not found"}))
+    );
+    assert_eq!(get("/boxes/abc/spending").await.0, 400);
+
+    // Rich lists
+    assert_eq!(
+        get("/lists/addresses/by/balance?limit=2").await,
+        (
+            200,
+            json!([
+                {"address": "addressA", "balance": 500},
+                {"address": "addressB", "balance": 300},
+            ])
+        )
+    );
+    assert_eq!(
+        get(&format!("/lists/addresses/by/balance?token_id={TOKEN_ID}")).await,
+        (
+            200,
+            json!([
+                {"address": "contractC", "balance": 10},
+                {"address": "addressA", "balance": 5},
+            ])
+        )
+    );
+    assert_eq!(get("/lists/addresses/by/balance?limit=0").await.0, 400);
+    assert_eq!(
+        get(&format!(
+            "/lists/addresses/by/balance?token_id={}",
+            "b".repeat(64)
+        ))
+        .await,
+        (404, json!({"detail": "Token not found"}))
+    );
+
+    // Counts
+    assert_eq!(get("/p2pk/count").await, (200, json!(2)));
+    assert_eq!(get("/p2pk/count?bal_ge=400").await, (200, json!(1)));
+    assert_eq!(get("/p2pk/count?bal_lt=400").await, (200, json!(1)));
+    assert_eq!(get("/contracts/count").await, (200, json!(2)));
+    assert_eq!(
+        get(&format!("/contracts/count?token_id={TOKEN_ID}&bal_ge=10")).await,
+        (200, json!(1))
+    );
+
+    // Supply
+    assert_eq!(
+        get("/supply/composition").await,
+        (
+            200,
+            json!({"height": 2, "p2pks": 100, "contracts": 200, "miners": 300})
+        )
+    );
+    assert_eq!(
+        get("/exchanges/supply").await,
+        (200, json!({"height": 2, "main": 1000, "deposits": 50}))
+    );
+    assert_eq!(
+        get(&format!("/tokens/{TOKEN_ID}/supply")).await,
+        (
+            200,
+            json!({"emitted": 20, "in_p2pks": 5, "in_contracts": 10, "burned": 5})
+        )
+    );
+
+    // SigmaUSD
+    assert_eq!(
+        get("/sigmausd/state").await,
+        (
+            200,
+            json!({
+                "height": 500000,
+                "reserves": 99,
+                "circ_sigusd": 123.45,
+                "circ_sigrsv": 10,
+                "peg_rate_nano": 2000,
+            })
+        )
+    );
+
+    // OpenAPI document
+    let (status, doc) = get("/openapi.json").await;
+    assert_eq!(status, 200);
+    assert_eq!(doc["info"]["title"], "ErgoWatch");
+    assert!(doc["paths"]["/sigmausd/state"]["get"].is_object());
+}
+
+#[tokio::test]
+async fn test_query_api_without_workers() {
+    let test_db = TestDB::new("query_api_no_workers").await;
+    test_db.init_core().await;
+    test_db.init_ew().await;
+    test_db
+        .client
+        .batch_execute(
+            "insert into core.addresses (id, spot_height, address) values (21, 1, 'addressA');",
+        )
+        .await
+        .unwrap();
+    QueryApi::new(&test_db.pgconf, PORT_NO_WORKERS).start();
+
+    let not_indexed = (404, json!({"detail": "Not indexed by this instance"}));
+    assert_eq!(
+        get_on(PORT_NO_WORKERS, "/addresses/addressA/balance").await,
+        not_indexed
+    );
+    assert_eq!(
+        get_on(PORT_NO_WORKERS, "/addresses/addressA/balance/at/height/1").await,
+        (404, json!({"detail": "Height not indexed yet"}))
+    );
+    assert_eq!(
+        get_on(PORT_NO_WORKERS, "/exchanges/supply").await,
+        not_indexed
+    );
+    assert_eq!(
+        get_on(PORT_NO_WORKERS, "/sigmausd/state").await,
+        not_indexed
+    );
+}
@@ -0,0 +1,106 @@
+// cargo test --test '*' -- --test-threads=1
+mod common;
+mod db_utils;
+
+
+use common::blocks::TestBlock as TB;
+use common::node_mockup::TestNode;
+use db_utils::TestDB;
+use ew::core::archive::BlockArchive;
+use ew::core::tracking::ArchiveTracker;
+use ew::core::tracking::Tracker;
+use ew::core::types::Header;
+use ew::core::Node;
+use ew::framework::Event;
+use ew::framework::Source;
+use ew::monitor::Monitor;
+use ew::shutdown::CancellationToken;
+
+/// Empty archive directory for given test `name`.
+fn archive_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(name);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    dir
+}
+
+#[tokio::test]
+async fn test_record_and_replay() {
+    // Only blocks more than 1 block below the node's tip get archived.
+    // Runs in its own test binary as the horizon is set process wide.
+    ew::constants::settings::set_rollback_horizon(1);
+
+    let block_ids = ["1", "2", "3", "4", "5"];
+    let mock_node = TestNode::run(&block_ids).await;
+    let node = Node::new("test-node", mock_node.url());
+
+    // Record part of the chain, then resume up to the settled height
+    let archive = BlockArchive::new(archive_dir("ew_test_archive"));
+    assert_eq!(archive.header().await.unwrap(), Header::initial());
+    let shutdown = CancellationToken::new();
+    let header = archive.record(&node, Some(2), &shutdown).await.unwrap();
+    assert_eq!(header.height, 2);
+    let header = archive.record(&node, None, &shutdown).await.unwrap();
+    assert_eq!(header.height, 4);
+    assert_eq!(header.header_id, TB::from_id("4").header_id());
+    assert_eq!(archive.header().await.unwrap(), header);
+
+    // Replay archive into a fresh db
+    let test_db = TestDB::new("test_archive").await;
+    let monitor = Monitor::new();
+    let mut tracker = ArchiveTracker::new(archive, test_db.pgconf, monitor.sender())
+        .await
+        .unwrap();
+    let mut rx = tracker.subscribe(Header::initial(), "C1").await.unwrap();
+    tokio::spawn(async move {
+        tracker.start(CancellationToken::new()).await.unwrap();
+    });
+
+    // Genesis, then archived blocks in order
+    let mut heights = vec![];
+    for _ in 0..5 {
+        match rx.recv().await.unwrap() {
+            Event::Include(data) => heights.push((data.height, data.header_id.clone())),
+            Event::Rollback(h) => panic!("unexpected rollback of {h}"),
+        }
+    }
+    assert_eq!(heights[0].0, 0);
+    for (i, id) in block_ids[..4].iter().enumerate() {
+        assert_eq!(heights[i + 1].0, i as i32 + 1);
+        assert_eq!(heights[i + 1].1, TB::from_id(id).header_id());
+    }
+}
+
+#[tokio::test]
+async fn test_tracker_records_archive() {
+    // Only blocks more than 1 block below the node's tip get archived.
+    ew::constants::settings::set_rollback_horizon(1);
+
+    let block_ids = ["1", "2", "3", "4", "5"];
+    let mock_node = TestNode::run(&block_ids).await;
+    let node = Node::new("test-node", mock_node.url());
+
+    // Tracker syncing from scratch records settled blocks it fetches
+    let dir = archive_dir("ew_test_archive_tracker");
+    let archive = BlockArchive::new(&dir);
+    let test_db = TestDB::new("test_archive_tracker").await;
+    let monitor = Monitor::new();
+    let mut tracker = Tracker::new(vec![node], test_db.pgconf, monitor.sender())
+        .await
+        .unwrap();
+    tracker.record_to(archive.clone()).await.unwrap();
+    let mut rx = tracker.subscribe(Header::initial(), "C1").await.unwrap();
+    tokio::spawn(async move {
+        tracker.start(CancellationToken::new()).await.unwrap();
+    });
+    for _ in 0..6 {
+        rx.recv().await.unwrap();
+    }
+
+    // Unsettled tip is left out
+    let header = archive.header().await.unwrap();
+    assert_eq!(header.height, 4);
+    assert_eq!(header.header_id, TB::from_id("4").header_id());
+    assert!(dir.join("genesis.json").exists());
+}
@@ -0,0 +1,50 @@
+// cargo test --test '*' -- --test-threads=1
+mod db_utils;
+
+use db_utils::TestDB;
+use ew::error::EwError;
+use ew::utils::copy_in;
+use ew::utils::CopyTypes;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::NoTls;
+
+async fn copy_value(
+    test_db: &TestDB,
+    copy_types: &CopyTypes,
+    value: &(dyn ToSql + Sync),
+) -> Result<u64, EwError> {
+    copy_in(
+        &test_db.client,
+        copy_types,
+        "records",
+        &["value"],
+        &[vec![value]],
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_stale_types_are_looked_up_again() {
+    let test_db = TestDB::new("copy_in_stale_types").await;
+    test_db
+        .client
+        .batch_execute("create table records (value integer);")
+        .await
+        .unwrap();
+    let copy_types = CopyTypes::default();
+    assert_eq!(copy_value(&test_db, &copy_types, &1i32).await.unwrap(), 1);
+
+    // Another connection recreates the table with another column type
+    let (client, connection) = tokio_postgres::connect(&test_db.pgconf.connection_uri, NoTls)
+        .await
+        .unwrap();
+    tokio::spawn(connection);
+    client
+        .batch_execute("drop table records; create table records (value bigint);")
+        .await
+        .unwrap();
+
+    // Cached types are rejected, then dropped from the cache
+    assert!(copy_value(&test_db, &copy_types, &2i64).await.is_err());
+    assert_eq!(copy_value(&test_db, &copy_types, &2i64).await.unwrap(), 1);
+}
@@ -0,0 +1,58 @@
+// cargo test --test '*' -- --test-threads=1
+mod common;
+mod db_utils;
+
+
+use common::blocks::TestBlock as TB;
+use common::node_mockup::TestNode;
+use db_utils::TestDB;
+use ew::config::TrackerConfig;
+use ew::core::tracking::Tracker;
+use ew::core::types::Header;
+use ew::core::Node;
+use ew::framework::Event;
+use ew::framework::Source;
+use ew::monitor::Monitor;
+use ew::shutdown::CancellationToken;
+
+#[tokio::test]
+async fn test_blocks_are_processed_in_order() {
+    // Blocks more than 1 block below the node's tip get prefetched.
+    // Runs in its own test binary as the horizon is set process wide.
+    ew::constants::settings::set_rollback_horizon(1);
+
+    let block_ids = ["1", "2", "3", "4", "5"];
+    let mock_node = TestNode::run(&block_ids).await;
+
+    // Configure tracker
+    let node = Node::new("test-node", mock_node.url());
+    let config = TrackerConfig {
+        prefetch_blocks: 3,
+        ..TrackerConfig::default()
+    };
+    let test_db = TestDB::new("test_fast_sync").await;
+    let monitor = Monitor::new();
+    let mut tracker = Tracker::new_with(vec![node], test_db.pgconf, &config, monitor.sender())
+        .await
+        .unwrap();
+    let mut rx = tracker.subscribe(Header::initial(), "C1").await.unwrap();
+
+    // Start tracker
+    tokio::spawn(async move {
+        tracker.start(CancellationToken::new()).await.unwrap();
+    });
+
+    // Genesis, then all blocks in order
+    let mut heights = vec![];
+    for _ in 0..6 {
+        match rx.recv().await.unwrap() {
+            Event::Include(data) => heights.push((data.height, data.header_id.clone())),
+            Event::Rollback(h) => panic!("unexpected rollback of {h}"),
+        }
+    }
+    assert_eq!(heights[0].0, 0);
+    for (i, id) in block_ids.iter().enumerate() {
+        assert_eq!(heights[i + 1].0, i as i32 + 1);
+        assert_eq!(heights[i + 1].1, TB::from_id(id).header_id());
+    }
+}
@@ -0,0 +1,301 @@
+// cargo test --test '*' -- --test-threads=1
+mod db_utils;
+
+use db_utils::TestDB;
+use ew::config::PostgresConfig;
+use ew::core::types::AddressID;
+use ew::core::types::BoxData;
+use ew::core::types::CoreData;
+use ew::core::types::Header;
+use ew::core::types::Height;
+use ew::core::types::Timestamp;
+use ew::core::types::Transaction;
+use ew::framework::fuzzing::assert_fork_consistency;
+use ew::framework::fuzzing::connect;
+use ew::framework::fuzzing::replay_event;
+use ew::framework::fuzzing::ChainFuzzer;
+use ew::framework::fuzzing::Rng;
+use ew::framework::fuzzing::Snapshot;
+use ew::framework::fuzzing::StdRng;
+use ew::framework::Event;
+use ew::framework::EventHandling;
+use ew::framework::QueryHandler;
+use ew::framework::Querying;
+use ew::workers::erg::ErgWorkFlow;
+use ew::workers::erg_diffs::ErgDiffsWorkFlow;
+use ew::workers::erg_diffs::QueryWorker;
+use ew::workers::exchanges::CexWorkFlow;
+use ew::workers::network::Network;
+use ew::workers::sigmausd::constants::BANK_NFT;
+use ew::workers::sigmausd::constants::CONTRACT_ADDRESS_ID;
+use ew::workers::sigmausd::constants::CONTRACT_CREATION_HEIGHT;
+use ew::workers::sigmausd::constants::ORACLE_NFT;
+use ew::workers::sigmausd::constants::RC_ASSET_ID;
+use ew::workers::sigmausd::constants::SC_ASSET_ID;
+use ew::workers::sigmausd::SigmaUSD;
+use ew::workers::timestamps::TimestampsWorkFlow;
+use ew::workers::tokens::TokensWorkFlow;
+
+const SEEDS: [u64; 3] = [1, 2, 3];
+
+/// Fuzzed and fresh test dbs with a core schema.
+async fn test_dbs(name: &str, seed: u64) -> (TestDB, TestDB) {
+    let fuzzed = TestDB::new(&format!("{name}_{seed}_fuzzed")).await;
+    let fresh = TestDB::new(&format!("{name}_{seed}_fresh")).await;
+    fuzzed.init_core().await;
+    fresh.init_core().await;
+    (fuzzed, fresh)
+}
+
+#[tokio::test]
+async fn test_timestamps() {
+    for seed in SEEDS {
+        let scenario = ChainFuzzer::new(seed).generate();
+        let (fuzzed, fresh) = test_dbs("fuzz_timestamps", seed).await;
+        assert_fork_consistency::<TimestampsWorkFlow>(
+            &scenario,
+            &fuzzed.pgconf,
+            &fresh.pgconf,
+            &["timestamps"],
+        )
+        .await
+        .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_tokens() {
+    for seed in SEEDS {
+        let scenario = ChainFuzzer::new(seed).generate();
+        let (fuzzed, fresh) = test_dbs("fuzz_tokens", seed).await;
+        assert_fork_consistency::<TokensWorkFlow>(
+            &scenario,
+            &fuzzed.pgconf,
+            &fresh.pgconf,
+            &["tokens"],
+        )
+        .await
+        .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_network() {
+    for seed in SEEDS {
+        let scenario = ChainFuzzer::new(seed).generate();
+        let (fuzzed, fresh) = test_dbs("fuzz_network", seed).await;
+        assert_fork_consistency::<Network>(&scenario, &fuzzed.pgconf, &fresh.pgconf, &["network"])
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_sigmausd() {
+    // Start right after contract creation, as recorded in sigmausd schema
+    let creation = Header {
+        height: CONTRACT_CREATION_HEIGHT,
+        timestamp: 1616706545437,
+        header_id: "fd35b157811f0950169e0f86b8f7e9ae0f13c49a46848ff40aa8dad26b030fde".to_owned(),
+        parent_id: "6fbf04c19bf97a558b0254cd09f77813b91cd5cdb40d22613bb8512046924dbd".to_owned(),
+    };
+    for seed in SEEDS {
+        let scenario = ChainFuzzer::new(seed)
+            .start_at(creation.clone())
+            .extra_txs(sigmausd_txs)
+            .generate();
+        let (fuzzed, fresh) = test_dbs("fuzz_sigmausd", seed).await;
+        assert_fork_consistency::<SigmaUSD>(
+            &scenario,
+            &fuzzed.pgconf,
+            &fresh.pgconf,
+            &["sigmausd"],
+        )
+        .await
+        .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_erg_diffs_and_erg() {
+    for seed in SEEDS {
+        let scenario = ChainFuzzer::new(seed).generate();
+        let (fuzzed, fresh) = test_dbs("fuzz_erg", seed).await;
+        // Erg worker is fed with erg_diffs output
+        let diffs = assert_fork_consistency::<ErgDiffsWorkFlow>(
+            &scenario,
+            &fuzzed.pgconf,
+            &fresh.pgconf,
+            &["erg"],
+        )
+        .await
+        .unwrap();
+        assert_fork_consistency::<ErgWorkFlow>(&diffs, &fuzzed.pgconf, &fresh.pgconf, &["erg"])
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_exchanges() {
+    for seed in SEEDS {
+        let scenario = ChainFuzzer::new(seed).generate();
+        let (fuzzed, fresh) = test_dbs("fuzz_exchanges", seed).await;
+        // Exchanges queries erg_diffs while processing blocks, so both
+        // are driven in lockstep, erg_diffs first.
+        run_erg_diffs_and_exchanges(&fuzzed.pgconf, &scenario.events).await;
+        run_erg_diffs_and_exchanges(&fresh.pgconf, &scenario.sync_events()).await;
+
+        let fuzzed_state = Snapshot::take(&fuzzed.client, &["exchanges"])
+            .await
+            .unwrap();
+        let fresh_state = Snapshot::take(&fresh.client, &["exchanges"]).await.unwrap();
+        if let Some(diff) = fuzzed_state.diff(&fresh_state) {
+            panic!(
+                "state after {} rollbacks differs from fresh sync (seed {seed}):\n{diff}",
+                scenario.rollbacks()
+            );
+        }
+    }
+}
+
+/// Feed `events` to erg_diffs and its output to exchanges.
+async fn run_erg_diffs_and_exchanges(pgconf: &PostgresConfig, events: &[Event<CoreData>]) {
+    let client = connect(pgconf).await.unwrap();
+    let mut erg_diffs = ErgDiffsWorkFlow::new(pgconf).await.unwrap();
+
+    // Init exchanges schema, then declare a fuzzed address as cex main
+    // address before the parser cache gets loaded.
+    let _ = CexWorkFlow::new(pgconf).await.unwrap();
+    client
+        .execute(
+            "insert into exchanges.exchanges (id, name, text_id)
+            values (10000, 'Exchange 1', 'cex_1');",
+            &[],
+        )
+        .await
+        .unwrap();
+    client
+        .execute(
+            "insert into exchanges.main_addresses (cex_id, address_id, address)
+            values (10000, $1, 'dummy-address');",
+            &[&AddressID(41)],
+        )
+        .await
+        .unwrap();
+    let mut exchanges = CexWorkFlow::new(pgconf).await.unwrap();
+    let mut query_handler = QueryWorker::new(pgconf).await.unwrap();
+    exchanges.set_query_sender(query_handler.connect());
+    tokio::spawn(async move {
+        query_handler.start().await.unwrap();
+    });
+
+    for event in events {
+        let diffs = replay_event(&mut erg_diffs, &client, event).await.unwrap();
+        erg_diffs.flush().await.unwrap();
+        replay_event(&mut exchanges, &client, &diffs).await.unwrap();
+    }
+    exchanges.flush().await.unwrap();
+}
+
+/// Random oracle posting and bank transaction.
+fn sigmausd_txs(rng: &mut StdRng, _height: Height, _timestamp: Timestamp) -> Vec<Transaction> {
+    if !rng.gen_bool(0.5) {
+        return vec![];
+    }
+    let users = [AddressID::p2pk(1001), AddressID::p2pk(1002)];
+    let services = [AddressID::p2pk(2001), AddressID::p2pk(2002)];
+    let user = users[rng.gen_range(0..users.len())];
+
+    // Price of 1 USD cent in nanoERG
+    let price: i64 = rng.gen_range(100_000_000..500_000_000);
+    let oracle_posting = Transaction::dummy()
+        .add_input(BoxData::dummy())
+        .add_data_input(BoxData::dummy())
+        .add_output(
+            BoxData::dummy()
+                .add_asset(ORACLE_NFT, 1)
+                .set_registers(&format!(
+                    r#"{{"R4": "05{}", "R5": "04bca7b201"}}"#,
+                    encode_long(price)
+                )),
+        );
+
+    // Mint or redeem SigUSD or SigRSV, with or without a service
+    let asset_id = if rng.gen_bool(0.5) {
+        SC_ASSET_ID
+    } else {
+        RC_ASSET_ID
+    };
+    let amount: i64 = rng.gen_range(1..10_000);
+    let value: i64 = rng.gen_range(1..100) * 1_000_000_000;
+    let fee: i64 = match rng.gen_bool(0.5) {
+        true => rng.gen_range(1..10) * 1_000_000,
+        false => 0,
+    };
+    // Bank boxes with given reserves and tokens diff
+    let reserves: i64 = 1_000_000_000_000_000;
+    let supply: i64 = 1_000_000_000;
+    let bank_box = |value: i64, diff: i64| {
+        let (sc, rc) = match asset_id {
+            SC_ASSET_ID => (supply + diff, supply),
+            _ => (supply, supply + diff),
+        };
+        BoxData::dummy()
+            .address_id(CONTRACT_ADDRESS_ID)
+            .value(value)
+            .add_asset(BANK_NFT, 1)
+            .add_asset(SC_ASSET_ID, sc)
+            .add_asset(RC_ASSET_ID, rc)
+    };
+    let user_value: i64 = 1_000_000_000_000;
+    let minting = rng.gen_bool(0.5);
+    let (bank_out, user_in, user_out) = if minting {
+        (
+            bank_box(reserves + value, -amount),
+            BoxData::dummy().address_id(user).value(user_value),
+            BoxData::dummy()
+                .address_id(user)
+                .value(user_value - value - fee)
+                .add_asset(asset_id, amount),
+        )
+    } else {
+        (
+            bank_box(reserves - value, amount),
+            BoxData::dummy()
+                .address_id(user)
+                .value(user_value)
+                .add_asset(asset_id, amount),
+            BoxData::dummy()
+                .address_id(user)
+                .value(user_value + value - fee),
+        )
+    };
+    let mut bank_tx = Transaction::dummy()
+        .add_input(bank_box(reserves, 0))
+        .add_input(user_in)
+        .add_output(bank_out)
+        .add_output(user_out);
+    if fee > 0 {
+        let service = services[rng.gen_range(0..services.len())];
+        bank_tx = bank_tx.add_output(BoxData::dummy().address_id(service).value(fee));
+    }
+    vec![oracle_posting, bank_tx]
+}
+
+/// Serializes a Long register value, without type prefix.
+fn encode_long(n: i64) -> String {
+    // ZigZag, then VLQ
+    let mut v = ((n << 1) ^ (n >> 63)) as u64;
+    let mut bytes = vec![];
+    loop {
+        let byte = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 {
+            bytes.push(byte);
+            break;
+        }
+        bytes.push(byte | 0x80);
+    }
+    base16::encode_lower(&bytes)
+}
@@ -0,0 +1,75 @@
+// cargo test --test '*' -- --test-threads=1
+mod common;
+
+use pretty_assertions::assert_eq;
+use std::time::Duration;
+
+use common::blocks::TestBlock;
+use common::node_mockup::TestNode;
+use ew::config::NodeConfig;
+use ew::core::Node;
+use ew::core::NodeError;
+
+/// Node config with short retry delays.
+fn node_config(url: &str, max_retries: u32) -> NodeConfig {
+    NodeConfig {
+        max_retries,
+        retry_delay: Duration::from_millis(10),
+        ..NodeConfig::new(url)
+    }
+}
+
+#[tokio::test]
+async fn test_retries_server_errors() {
+    let block_ids = ["1", "2", "3"];
+    let mock_node = TestNode::run(&block_ids).await;
+    mock_node.fail_next(2);
+    let node = Node::with_config("test-node", &node_config(mock_node.url(), 3));
+    let header_ids = node.api.blocks_at(2).await.unwrap();
+    assert_eq!(header_ids, vec![TestBlock::from_id("2").header_id()]);
+    assert_eq!(mock_node.request_count(), 3);
+}
+
+#[tokio::test]
+async fn test_gives_up_after_max_retries() {
+    let block_ids = ["1", "2", "3"];
+    let mock_node = TestNode::run(&block_ids).await;
+    mock_node.fail_next(10);
+    let node = Node::with_config("test-node", &node_config(mock_node.url(), 2));
+    let res = node.api.blocks_at(2).await;
+    assert!(matches!(res, Err(NodeError::APIServerError(_))));
+    assert_eq!(mock_node.request_count(), 3);
+}
+
+#[tokio::test]
+async fn test_does_not_retry_client_errors() {
+    let block_ids = ["1", "2", "3"];
+    let mock_node = TestNode::run(&block_ids).await;
+    mock_node.require_api_key("secret");
+    let node = Node::with_config("test-node", &node_config(mock_node.url(), 3));
+    let res = node.api.blocks_at(2).await;
+    assert!(matches!(res, Err(NodeError::APIError(_))));
+    assert_eq!(mock_node.request_count(), 1);
+}
+
+#[tokio::test]
+async fn test_sends_api_key() {
+    let block_ids = ["1", "2", "3"];
+    let mock_node = TestNode::run(&block_ids).await;
+    mock_node.require_api_key("secret");
+    let config = NodeConfig {
+        api_key: Some("secret".to_owned()),
+        ..node_config(mock_node.url(), 0)
+    };
+    let node = Node::with_config("test-node", &config);
+    let header_ids = node.api.blocks_at(3).await.unwrap();
+    assert_eq!(header_ids, vec![TestBlock::from_id("3").header_id()]);
+}
+
+#[tokio::test]
+async fn test_unreachable_node() {
+    // Nothing listening on that port
+    let node = Node::with_config("test-node", &node_config("http://127.0.0.1:9054", 1));
+    let res = node.api.blocks_at(2).await;
+    assert!(matches!(res, Err(NodeError::NodeUnreachable)));
+}
@@ -0,0 +1,114 @@
+// cargo test --test '*' -- --test-threads=1
+mod common;
+mod db_utils;
+
+use pretty_assertions::assert_eq;
+
+use common::blocks::TestBlock as TB;
+use common::fixtures::Fixtures;
+use common::node_mockup::TestNode;
+use db_utils::TestDB;
+use ew::config::NodeConfig;
+use ew::core::tracking::Tracker;
+use ew::core::types::Header;
+use ew::core::types::Height;
+use ew::core::Node;
+use ew::framework::Event;
+use ew::framework::Source;
+use ew::monitor::Monitor;
+use ew::shutdown::CancellationToken;
+
+/// Records responses of a mock node serving blocks 1 to 5 and a fork
+/// at height 3.
+async fn record_fixtures(name: &str) -> Fixtures {
+    let dir = std::env::temp_dir().join(name);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    let mock_node = TestNode::run(&["1", "2", "3", "3bis*", "4", "5"]).await;
+    let config = NodeConfig {
+        record_dir: Some(dir.clone()),
+        ..NodeConfig::new(mock_node.url())
+    };
+    let node = Node::with_config("recording", &config);
+    node.api.info().await.unwrap();
+    node.api.utxo_genesis().await.unwrap();
+    for height in 1..=5 {
+        for header_id in node.api.blocks_at(height).await.unwrap() {
+            node.api.block(&header_id).await.unwrap();
+        }
+    }
+    Fixtures::at(dir)
+}
+
+#[tokio::test]
+async fn test_recorded_fixtures() {
+    let fixtures = record_fixtures("ew_test_fixtures_recorded").await;
+
+    let expected: Vec<String> = ["1", "2", "3", "4", "5"]
+        .iter()
+        .map(|id| TB::from_id(id).header_id().to_owned())
+        .collect();
+    assert_eq!(fixtures.main_chain(), expected);
+    assert_eq!(fixtures.block(TB::from_id("3bis").header_id()).height(), 3);
+    assert!(fixtures.genesis_boxes().is_some());
+}
+
+#[tokio::test]
+async fn test_serve_fixtures_with_fork() {
+    let fixtures = record_fixtures("ew_test_fixtures_fork").await;
+    let chain = fixtures.main_chain();
+    let id_3bis = TB::from_id("3bis").header_id().to_owned();
+
+    // First, serve chain 1-2-3bis
+    let mut mock_node = TestNode::run_fixtures(fixtures, &[&chain[0], &chain[1], &id_3bis]).await;
+
+    let test_db = TestDB::new("test_node_fixtures").await;
+    let monitor = Monitor::new();
+    let mut tracker = Tracker::new(
+        vec![Node::new("test-node", mock_node.url())],
+        test_db.pgconf,
+        monitor.sender(),
+    )
+    .await
+    .unwrap();
+    let mut rx = tracker.subscribe(Header::initial(), "C1").await.unwrap();
+    tokio::spawn(async move {
+        tracker.start(CancellationToken::new()).await.unwrap();
+    });
+
+    let mut events = vec![];
+    for _ in 0..4 {
+        events.push(rx.recv().await.unwrap());
+    }
+
+    // Then switch to main chain
+    let fork = format!("{id_3bis}*");
+    mock_node
+        .restart(&[&chain[0], &chain[1], &fork, &chain[2], &chain[3], &chain[4]])
+        .await;
+    for _ in 0..4 {
+        events.push(rx.recv().await.unwrap());
+    }
+
+    let summary: Vec<(Height, Option<String>)> = events
+        .iter()
+        .map(|event| match event {
+            Event::Include(data) => (data.height, Some(data.header_id.clone())),
+            Event::Rollback(h) => (*h, None),
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (0, Some(Header::genesis().header_id)),
+            (1, Some(chain[0].clone())),
+            (2, Some(chain[1].clone())),
+            (3, Some(id_3bis)),
+            (3, None),
+            (3, Some(chain[2].clone())),
+            (4, Some(chain[3].clone())),
+            (5, Some(chain[4].clone())),
+        ]
+    );
+}
@@ -1,17 +1,20 @@
 // cargo test --test '*' -- --test-threads=1
 mod common;
 
+use ew::config::NodeConfig;
 use ew::config::PostgresConfig;
+use ew::config::TrackerConfig;
 use ew::core::types::AddressID;
 use ew::core::types::Block;
 use ew::core::types::CoreData;
 use ew::core::types::Header;
 use ew::core::types::HeaderID;
 use ew::core::types::Height;
+use ew::error::EwError;
+use ew::framework::source_channel;
 use ew::framework::Event;
 use ew::framework::Source;
 use pretty_assertions::assert_eq;
-use tokio;
 use tokio_postgres::NoTls;
 
 use common::blocks::TestBlock as TB;
@@ -19,6 +22,7 @@ use common::node_mockup::TestNode;
 use ew::core::tracking::Tracker;
 use ew::core::Node;
 use ew::monitor::Monitor;
+use ew::shutdown::CancellationToken;
 use tokio::sync::mpsc::error::TryRecvError;
 
 fn set_tracing_subscriber(set: bool) -> Option<tracing::dispatcher::DefaultGuard> {
@@ -63,6 +67,16 @@ async fn prep_db(db_name: &str) -> PostgresConfig {
     PostgresConfig::new(&uri)
 }
 
+/// Node with given `priority`, giving up on first failed request.
+fn prioritized_node(id: &str, url: &str, priority: u32) -> Node {
+    let config = NodeConfig {
+        max_retries: 0,
+        priority,
+        ..NodeConfig::new(url)
+    };
+    Node::with_config(id, &config)
+}
+
 /// Event wrapper to provide testing helper.
 struct EventInspector(Event<CoreData>);
 
@@ -143,12 +157,18 @@ async fn test_straight_chain_single_cursor() {
     // Configure tracker
     let node = Node::new("test-node", mock_node.url());
     let monitor = Monitor::new();
-    let mut tracker = Tracker::new(node, prep_db("test_tracker_1").await, monitor.sender()).await;
-    let mut rx = tracker.subscribe(Header::initial(), "C1").await;
+    let mut tracker = Tracker::new(
+        vec![node],
+        prep_db("test_tracker_1").await,
+        monitor.sender(),
+    )
+    .await
+    .unwrap();
+    let mut rx = tracker.subscribe(Header::initial(), "C1").await.unwrap();
 
     // Start tracker
     tokio::spawn(async move {
-        tracker.start().await;
+        tracker.start(CancellationToken::new()).await.unwrap();
         sleep_some(&guard).await;
     });
 
@@ -168,6 +188,176 @@ async fn test_straight_chain_single_cursor() {
     messages[5].assert_includes_block(TB::from_id("5"));
 }
 
+#[tokio::test]
+async fn test_straight_chain_no_cursors() {
+    let guard = set_tracing_subscriber(false);
+    let block_ids = ["1", "2", "3", "4", "5"];
+
+    // Start a fake node to be queried by the tracker
+    let mock_node = TestNode::run(&block_ids).await;
+
+    // Configure tracker without any subscribers
+    let node = Node::new("test-node", mock_node.url());
+    let monitor = Monitor::new();
+    let pgconf = prep_db("test_tracker_no_cursors").await;
+    let mut tracker = Tracker::new(vec![node], pgconf.clone(), monitor.sender())
+        .await
+        .unwrap();
+
+    // Start tracker
+    tokio::spawn(async move {
+        tracker.start(CancellationToken::new()).await.unwrap();
+        sleep_some(&guard).await;
+    });
+
+    // Core should get populated regardless
+    let (client, connection) = tokio_postgres::connect(&pgconf.connection_uri, NoTls)
+        .await
+        .unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let mut height: Height = -1;
+    for _ in 0..50 {
+        height = client
+            .query_one("select max(height) from core.headers;", &[])
+            .await
+            .unwrap()
+            .get(0);
+        if height == 5 {
+            break;
+        }
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(height, 5);
+}
+
+#[tokio::test]
+async fn test_monitor_stopped_first() {
+    let guard = set_tracing_subscriber(false);
+    let block_ids = ["1", "2"];
+
+    // Start a fake node to be queried by the tracker
+    let mock_node = TestNode::run(&block_ids).await;
+
+    // Configure tracker, then drop the monitor, as may happen on shutdown
+    let node = Node::new("test-node", mock_node.url());
+    let monitor = Monitor::new();
+    let mut tracker = Tracker::new(
+        vec![node],
+        prep_db("test_tracker_monitor_stopped").await,
+        monitor.sender(),
+    )
+    .await
+    .unwrap();
+    let mut rx = tracker.subscribe(Header::initial(), "C1").await.unwrap();
+    drop(monitor);
+
+    // Start tracker
+    tokio::spawn(async move {
+        tracker.start(CancellationToken::new()).await.unwrap();
+        sleep_some(&guard).await;
+    });
+
+    // Tracker keeps going without a monitor
+    for _ in 0..3 {
+        rx.recv().await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_stops_on_shutdown() {
+    let guard = set_tracing_subscriber(false);
+    let block_ids = ["1", "2", "3", "4", "5"];
+
+    // Start a fake node to be queried by the tracker
+    let mock_node = TestNode::run(&block_ids).await;
+
+    // Configure tracker
+    let node = Node::new("test-node", mock_node.url());
+    let monitor = Monitor::new();
+    let mut tracker = Tracker::new(
+        vec![node],
+        prep_db("test_tracker_shutdown").await,
+        monitor.sender(),
+    )
+    .await
+    .unwrap();
+    let mut rx = tracker.subscribe(Header::initial(), "C1").await.unwrap();
+
+    // Start tracker
+    let shutdown = CancellationToken::new();
+    let tracker_shutdown = shutdown.clone();
+    let handle = tokio::spawn(async move {
+        tracker.start(tracker_shutdown).await.unwrap();
+        sleep_some(&guard).await;
+    });
+
+    // Wait for tracker to reach tip
+    for _ in 0..6 {
+        rx.recv().await.unwrap();
+    }
+
+    // Tracker should stop once asked to
+    shutdown.cancel();
+    tokio::time::timeout(tokio::time::Duration::from_secs(5), handle)
+        .await
+        .expect("tracker stopped in time")
+        .unwrap();
+
+    // Channel gets closed when the tracker is dropped
+    assert!(rx.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn test_subscribe_through_handle() {
+    let guard = set_tracing_subscriber(false);
+    let block_ids = ["1", "2", "3"];
+
+    // Start a fake node to be queried by the tracker
+    let mock_node = TestNode::run(&block_ids).await;
+
+    // Configure tracker, serving requests from a handle
+    let node = Node::new("test-node", mock_node.url());
+    let monitor = Monitor::new();
+    let mut tracker = Tracker::new(
+        vec![node],
+        prep_db("test_tracker_handle").await,
+        monitor.sender(),
+    )
+    .await
+    .unwrap();
+    let mut rx_a = tracker.subscribe(Header::initial(), "A").await.unwrap();
+    let (mut handle, requests) = source_channel();
+    tracker.serve(requests);
+
+    // Start tracker
+    tokio::spawn(async move {
+        tracker.start(CancellationToken::new()).await.unwrap();
+        sleep_some(&guard).await;
+    });
+
+    // Wait for tracker to reach tip
+    for _ in 0..4 {
+        rx_a.recv().await.unwrap();
+    }
+    assert_eq!(handle.header().await.unwrap(), TB::from_id("3").header());
+    assert!(handle
+        .contains_header(&TB::from_id("2").header())
+        .await
+        .unwrap());
+
+    // A late subscriber starting from scratch gets the whole chain
+    let mut rx_b = handle.subscribe(Header::initial(), "B").await.unwrap();
+    EventInspector(rx_b.recv().await.unwrap()).assert_is_genesis();
+    EventInspector(rx_b.recv().await.unwrap()).assert_includes_block(TB::from_id("1"));
+    EventInspector(rx_b.recv().await.unwrap()).assert_includes_block(TB::from_id("2"));
+    EventInspector(rx_b.recv().await.unwrap()).assert_includes_block(TB::from_id("3"));
+    assert_eq!(rx_a.try_recv().err(), Some(TryRecvError::Empty));
+}
+
 #[tokio::test]
 async fn test_straight_chain_three_cursors() {
     let guard = set_tracing_subscriber(false);
@@ -186,13 +376,15 @@ async fn test_straight_chain_three_cursors() {
     {
         // Configure tracker
         let node = Node::new("test-node", mock_node.url());
-        let mut tracker = Tracker::new(node, pgconf.clone(), monitor.sender()).await;
+        let mut tracker = Tracker::new(vec![node], pgconf.clone(), monitor.sender())
+            .await
+            .unwrap();
         // Cursor is at genesis
-        let mut rx = tracker.subscribe(Header::initial(), "dummy").await;
+        let mut rx = tracker.subscribe(Header::initial(), "dummy").await.unwrap();
 
         // Start tracker
         tokio::spawn(async move {
-            tracker.start().await;
+            tracker.start(CancellationToken::new()).await.unwrap();
         });
 
         // Collect messages to ensure tracker is done.
@@ -202,18 +394,28 @@ async fn test_straight_chain_three_cursors() {
     }
 
     // Now configure a new tracker with 3 cursors, using the same db.
+    // First tracker keeps running, so use a monitor of its own.
     let node = Node::new("test-node", mock_node.url());
-    let mut tracker = Tracker::new(node, pgconf, monitor.sender()).await;
+    let monitor = Monitor::new();
+    let mut tracker = Tracker::new(vec![node], pgconf, monitor.sender())
+        .await
+        .unwrap();
     // First cursor is on last block
-    let mut rx_a = tracker.subscribe(TB::from_id("5").header(), "A").await;
+    let mut rx_a = tracker
+        .subscribe(TB::from_id("5").header(), "A")
+        .await
+        .unwrap();
     // Second cursor starts from scratch
-    let mut rx_b = tracker.subscribe(Header::initial(), "B").await;
+    let mut rx_b = tracker.subscribe(Header::initial(), "B").await.unwrap();
     // Third cursor is at block 2
-    let mut rx_c = tracker.subscribe(TB::from_id("2").header(), "C").await;
+    let mut rx_c = tracker
+        .subscribe(TB::from_id("2").header(), "C")
+        .await
+        .unwrap();
 
     // Start tracker
     tokio::spawn(async move {
-        tracker.start().await;
+        tracker.start(CancellationToken::new()).await.unwrap();
         sleep_some(&guard).await;
     });
 
@@ -242,6 +444,116 @@ async fn test_straight_chain_three_cursors() {
     messages_c[2].assert_includes_block(TB::from_id("5"));
 }
 
+#[tokio::test]
+async fn test_lagging_cursor_served_from_store() {
+    let guard = set_tracing_subscriber(false);
+    let block_ids = ["1", "2", "3", "4", "5"];
+
+    // Start a fake node to be queried by the tracker
+    let mock_node = TestNode::run(&block_ids).await;
+
+    // Prepare empty db
+    let pgconf = prep_db("test_tracker_lagging_cursor").await;
+
+    // First, run a single cursor tracker to prepare the store.
+    let mut served_by_node: Vec<String> = vec![];
+    {
+        let node = Node::new("test-node", mock_node.url());
+        let monitor = Monitor::new();
+        let mut tracker = Tracker::new(vec![node], pgconf.clone(), monitor.sender())
+            .await
+            .unwrap();
+        let mut rx = tracker.subscribe(Header::initial(), "dummy").await.unwrap();
+        tokio::spawn(async move {
+            tracker.start(CancellationToken::new()).await.unwrap();
+        });
+        for _ in 0..6 {
+            let event = EventInspector(rx.recv().await.unwrap());
+            served_by_node.push(format!("{:?}", event.block().unwrap()));
+        }
+    }
+    let block_requests = mock_node.block_request_count();
+    assert_eq!(block_requests, 5);
+
+    // New tracker with a cursor starting from scratch, using the same db.
+    let node = Node::new("test-node", mock_node.url());
+    let monitor = Monitor::new();
+    let mut tracker = Tracker::new(vec![node], pgconf, monitor.sender())
+        .await
+        .unwrap();
+    let mut rx = tracker.subscribe(Header::initial(), "B").await.unwrap();
+    tokio::spawn(async move {
+        tracker.start(CancellationToken::new()).await.unwrap();
+        sleep_some(&guard).await;
+    });
+
+    // Cursor gets the same blocks, without requesting them from the node
+    for expected in served_by_node {
+        let event = EventInspector(rx.recv().await.unwrap());
+        assert_eq!(format!("{:?}", event.block().unwrap()), expected);
+    }
+    assert_eq!(mock_node.block_request_count(), block_requests);
+}
+
+#[tokio::test]
+async fn test_lagging_cursor_missing_box() {
+    let _guard = set_tracing_subscriber(false);
+    let block_ids = ["1", "2", "3"];
+
+    // Start a fake node to be queried by the tracker
+    let mock_node = TestNode::run(&block_ids).await;
+
+    // Prepare empty db
+    let pgconf = prep_db("test_tracker_lagging_cursor_missing_box").await;
+
+    // First, run a single cursor tracker to prepare the store.
+    {
+        let node = Node::new("test-node", mock_node.url());
+        let monitor = Monitor::new();
+        let mut tracker = Tracker::new(vec![node], pgconf.clone(), monitor.sender())
+            .await
+            .unwrap();
+        let mut rx = tracker.subscribe(Header::initial(), "dummy").await.unwrap();
+        tokio::spawn(async move {
+            tracker.start(CancellationToken::new()).await.unwrap();
+        });
+        for _ in 0..4 {
+            rx.recv().await.unwrap();
+        }
+    }
+
+    // Lose a box spent in block 2
+    let (client, connection) = tokio_postgres::connect(&pgconf.connection_uri, NoTls)
+        .await
+        .unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    client
+        .execute(
+            "delete from core.boxes where box_id = (
+                select box_id from core.inputs where height = 2 limit 1
+            );",
+            &[],
+        )
+        .await
+        .unwrap();
+
+    // New tracker with a cursor starting from scratch, using the same db.
+    let node = Node::new("test-node", mock_node.url());
+    let monitor = Monitor::new();
+    let mut tracker = Tracker::new(vec![node], pgconf, monitor.sender())
+        .await
+        .unwrap();
+    let _rx = tracker.subscribe(Header::initial(), "B").await.unwrap();
+
+    // Tracker stops on the incomplete block instead of panicking
+    let res = tracker.start(CancellationToken::new()).await;
+    assert!(matches!(res, Err(EwError::MissingBox(_, 2))));
+}
+
 #[tokio::test]
 #[ignore = "legacy"] // Untestable as head will be capped to current store's head.
 async fn test_fork_handling_not_a_child() {
@@ -254,14 +566,23 @@ async fn test_fork_handling_not_a_child() {
     // Configure tracker
     let node = Node::new("test-node", mock_node.url());
     let monitor = Monitor::new();
-    let mut tracker = Tracker::new(node, prep_db("test_tracker_3").await, monitor.sender()).await;
+    let mut tracker = Tracker::new(
+        vec![node],
+        prep_db("test_tracker_3").await,
+        monitor.sender(),
+    )
+    .await
+    .unwrap();
     // Assuming we've included 1, 2 and 3bis so far
     // Next block will be 4, which isn't a child of 3bis
-    let mut rx = tracker.subscribe(TB::from_id("3bis").header(), "C1").await;
+    let mut rx = tracker
+        .subscribe(TB::from_id("3bis").header(), "C1")
+        .await
+        .unwrap();
 
     // Start tracker
     tokio::spawn(async move {
-        tracker.start().await;
+        tracker.start(CancellationToken::new()).await.unwrap();
         sleep_some(&guard).await;
     });
 
@@ -291,16 +612,17 @@ async fn test_fork_handling_same_height() {
     // Configure tracker
     let monitor = Monitor::new();
     let mut tracker = Tracker::new(
-        Node::new("test-node", &mock_node.url()),
+        vec![Node::new("test-node", mock_node.url())],
         prep_db("test_tracker_4").await,
         monitor.sender(),
     )
-    .await;
-    let mut rx = tracker.subscribe(Header::initial(), "C1").await;
+    .await
+    .unwrap();
+    let mut rx = tracker.subscribe(Header::initial(), "C1").await.unwrap();
 
     // Start tracker
     tokio::spawn(async move {
-        tracker.start().await;
+        tracker.start(CancellationToken::new()).await.unwrap();
         sleep_some(&guard).await;
     });
 
@@ -354,3 +676,218 @@ async fn test_fork_handling_same_height() {
     assert_eq!(block3b.transactions[0].outputs[2].assets[0].asset_id, 1);
     assert_eq!(block3.transactions[0].outputs[2].assets[0].asset_id, 1);
 }
+
+#[tokio::test]
+async fn test_fork_handling_transactions() {
+    let guard = set_tracing_subscriber(false);
+
+    // First, process chain 1-2-3bis
+    let block_ids = ["1", "2", "3bis"];
+
+    // Start a fake node to be queried by the tracker
+    let mut mock_node = TestNode::run(&block_ids).await;
+
+    // Configure tracker
+    let pgconf = prep_db("test_tracker_fork_transactions").await;
+    let monitor = Monitor::new();
+    let mut tracker = Tracker::new(
+        vec![Node::new("test-node", mock_node.url())],
+        pgconf.clone(),
+        monitor.sender(),
+    )
+    .await
+    .unwrap();
+    let mut rx = tracker.subscribe(Header::initial(), "C1").await.unwrap();
+
+    // Start tracker
+    tokio::spawn(async move {
+        tracker.start(CancellationToken::new()).await.unwrap();
+        sleep_some(&guard).await;
+    });
+    let mut messages: Vec<EventInspector> = vec![];
+    for _ in 0..4 {
+        messages.push(EventInspector(rx.recv().await.unwrap()))
+    }
+
+    // Simulate fork
+    let block_ids = ["1", "2", "3bis*", "3", "4", "5"];
+    mock_node.restart(&block_ids).await;
+    for _ in 0..4 {
+        messages.push(EventInspector(rx.recv().await.unwrap()))
+    }
+    messages[4].assert_rolls_back(3);
+
+    // Transactions and inputs of 3bis are replaced by those of 3
+    let (client, connection) = tokio_postgres::connect(&pgconf.connection_uri, NoTls)
+        .await
+        .unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    let main_chain: Vec<&Block> = [1, 2, 5, 6, 7]
+        .iter()
+        .map(|i| messages[*i].block().unwrap())
+        .collect();
+    let (mut n_txs, mut n_inputs, mut n_data_inputs) = (0, 0, 0);
+    for block in &main_chain {
+        for tx in &block.transactions {
+            let row = client
+                .query_one(
+                    "select height, tx_idx from core.transactions where tx_id = $1;",
+                    &[&tx.id],
+                )
+                .await
+                .unwrap();
+            assert_eq!(row.get::<_, Height>(0), block.header.height);
+            assert_eq!(row.get::<_, i32>(1), tx.index);
+            for output in &tx.outputs {
+                let row = client
+                    .query_one(
+                        "select tx_id from core.boxes where box_id = $1;",
+                        &[&output.box_id],
+                    )
+                    .await
+                    .unwrap();
+                assert_eq!(row.get::<_, Option<String>>(0), Some(tx.id.clone()));
+            }
+            for (i, input) in tx.inputs.iter().enumerate() {
+                let row = client
+                    .query_one(
+                        "select height, tx_id, idx from core.inputs where box_id = $1;",
+                        &[&input.box_id],
+                    )
+                    .await
+                    .unwrap();
+                assert_eq!(row.get::<_, Height>(0), block.header.height);
+                assert_eq!(row.get::<_, String>(1), tx.id);
+                assert_eq!(row.get::<_, i32>(2), i as i32);
+            }
+            n_txs += 1;
+            n_inputs += tx.inputs.len() as i64;
+            n_data_inputs += tx.data_inputs.len() as i64;
+        }
+    }
+    let row = client
+        .query_one(
+            "select (select count(*) from core.transactions)
+                , (select count(*) from core.inputs)
+                , (select count(*) from core.data_inputs);",
+            &[],
+        )
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, i64>(0), n_txs);
+    assert_eq!(row.get::<_, i64>(1), n_inputs);
+    assert_eq!(row.get::<_, i64>(2), n_data_inputs);
+}
+
+#[tokio::test]
+async fn test_failover_unreachable_node() {
+    let guard = set_tracing_subscriber(false);
+    let block_ids = ["1", "2", "3"];
+
+    // Only the fallback node is running
+    let fallback_node = TestNode::run_on(9055, &block_ids).await;
+
+    // Configure tracker, preferring the node that is down
+    let nodes = vec![
+        prioritized_node("fallback", fallback_node.url(), 1),
+        prioritized_node("main", "http://127.0.0.1:9054", 0),
+    ];
+    let monitor = Monitor::new();
+    let mut tracker = Tracker::new(
+        nodes,
+        prep_db("test_tracker_failover").await,
+        monitor.sender(),
+    )
+    .await
+    .unwrap();
+    let mut rx = tracker.subscribe(Header::initial(), "C1").await.unwrap();
+
+    // Start tracker
+    tokio::spawn(async move {
+        tracker.start(CancellationToken::new()).await.unwrap();
+        sleep_some(&guard).await;
+    });
+
+    // Blocks come from the fallback node
+    EventInspector(rx.recv().await.unwrap()).assert_is_genesis();
+    EventInspector(rx.recv().await.unwrap()).assert_includes_block(TB::from_id("1"));
+    EventInspector(rx.recv().await.unwrap()).assert_includes_block(TB::from_id("2"));
+    EventInspector(rx.recv().await.unwrap()).assert_includes_block(TB::from_id("3"));
+}
+
+#[tokio::test]
+async fn test_failover_lagging_node() {
+    let guard = set_tracing_subscriber(false);
+
+    // Main node is 3 blocks behind the fallback
+    let main_node = TestNode::run(&["1", "2"]).await;
+    let fallback_node = TestNode::run_on(9055, &["1", "2", "3", "4", "5"]).await;
+
+    // Configure tracker, allowing nodes to lag 2 blocks at most
+    let nodes = vec![
+        prioritized_node("main", main_node.url(), 0),
+        prioritized_node("fallback", fallback_node.url(), 1),
+    ];
+    let config = TrackerConfig {
+        max_node_lag: 2,
+        ..TrackerConfig::default()
+    };
+    let monitor = Monitor::new();
+    let pgconf = prep_db("test_tracker_lagging_node").await;
+    let mut tracker = Tracker::new_with(nodes, pgconf, &config, monitor.sender())
+        .await
+        .unwrap();
+    let mut rx = tracker.subscribe(Header::initial(), "C1").await.unwrap();
+
+    // Start tracker
+    tokio::spawn(async move {
+        tracker.start(CancellationToken::new()).await.unwrap();
+        sleep_some(&guard).await;
+    });
+
+    // Tracker follows the fallback node
+    EventInspector(rx.recv().await.unwrap()).assert_is_genesis();
+    for id in ["1", "2", "3", "4", "5"] {
+        EventInspector(rx.recv().await.unwrap()).assert_includes_block(TB::from_id(id));
+    }
+}
+
+#[tokio::test]
+async fn test_failover_stale_fork() {
+    let guard = set_tracing_subscriber(false);
+
+    // Main node is stuck on a fork the fallback node moved away from
+    let main_node = TestNode::run(&["1", "2", "3bis"]).await;
+    let fallback_node = TestNode::run_on(9055, &["1", "2", "3bis*", "3", "4", "5"]).await;
+
+    // Configure tracker
+    let nodes = vec![
+        prioritized_node("main", main_node.url(), 0),
+        prioritized_node("fallback", fallback_node.url(), 1),
+    ];
+    let monitor = Monitor::new();
+    let mut tracker = Tracker::new(
+        nodes,
+        prep_db("test_tracker_stale_fork").await,
+        monitor.sender(),
+    )
+    .await
+    .unwrap();
+    let mut rx = tracker.subscribe(Header::initial(), "C1").await.unwrap();
+
+    // Start tracker
+    tokio::spawn(async move {
+        tracker.start(CancellationToken::new()).await.unwrap();
+        sleep_some(&guard).await;
+    });
+
+    // Tracker follows the fallback node and never sees 3bis
+    EventInspector(rx.recv().await.unwrap()).assert_is_genesis();
+    for id in ["1", "2", "3", "4", "5"] {
+        EventInspector(rx.recv().await.unwrap()).assert_includes_block(TB::from_id(id));
+    }
+}
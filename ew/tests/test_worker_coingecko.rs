@@ -5,8 +5,10 @@ use async_trait::async_trait;
 use coingecko::APIData;
 use coingecko::MockGecko;
 use db_utils::TestDB;
+use ew::config::CoingeckoConfig;
 use ew::core::types::Block;
 use ew::framework::StampedData;
+use ew::shutdown::CancellationToken;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio_postgres::Client;
@@ -14,6 +16,7 @@ use tokio_postgres::Client;
 use ew::core::types::CoreData;
 use ew::core::types::Header;
 use ew::core::types::Height;
+use ew::error::EwError;
 use ew::framework::Event;
 use ew::framework::Source;
 use ew::workers::coingecko::types::BlockRecord;
@@ -81,25 +84,25 @@ impl MockSource {
 impl Source for MockSource {
     type S = CoreData;
 
-    fn header(&self) -> &Header {
-        &self.header
+    async fn header(&self) -> Result<Header, EwError> {
+        Ok(self.header.clone())
     }
 
     /// Returns true if `head` is part of source's processed main cahin.
-    async fn contains_header(&self, _header: &Header) -> bool {
+    async fn contains_header(&self, _header: &Header) -> Result<bool, EwError> {
         // Always return true for dummy impl
-        true
+        Ok(true)
     }
 
     async fn subscribe(
         &mut self,
         _header: Header,
         _cursor_name: &str,
-    ) -> mpsc::Receiver<Event<Self::S>> {
+    ) -> Result<mpsc::Receiver<Event<Self::S>>, EwError> {
         assert!(self.tx.is_none());
         let (tx, rx) = mpsc::channel(ew::framework::EVENT_CHANNEL_CAPACITY);
         self.tx.replace(tx);
-        rx
+        Ok(rx)
     }
 }
 
@@ -145,13 +148,17 @@ async fn test_rollback() {
         &test_db.pgconf,
         &mut source,
         mon_tx,
-        Some(&mock_api.get_url()),
+        &CoingeckoConfig {
+            url: mock_api.get_url().to_owned(),
+            ..Default::default()
+        },
     )
-    .await;
+    .await
+    .unwrap();
 
     // Start worker
     tokio::spawn(async move {
-        worker.start().await;
+        worker.start(CancellationToken::new()).await.unwrap();
     });
 
     // Send some events to worker
@@ -54,9 +54,9 @@ async fn test_empty_blocks() {
     });
 
     // Process blocks
-    let mut workflow = ErgWorkFlow::new(&test_db.pgconf).await;
-    workflow.include_block(&genesis_data).await;
-    workflow.include_block(&data_1).await;
+    let mut workflow = ErgWorkFlow::new(&test_db.pgconf).await.unwrap();
+    workflow.include_block(&genesis_data).await.unwrap();
+    workflow.include_block(&data_1).await.unwrap();
 }
 
 #[tokio::test]
@@ -109,10 +109,10 @@ async fn test_rollback() {
     // Register core header for parent of rolled back blocks
     test_db.insert_core_header(&data_1.get_header()).await;
 
-    let mut workflow = ErgWorkFlow::new(&test_db.pgconf).await;
-    workflow.include_block(&genesis_data).await;
-    workflow.include_block(&data_1).await;
-    workflow.include_block(&data_2).await;
+    let mut workflow = ErgWorkFlow::new(&test_db.pgconf).await.unwrap();
+    workflow.include_block(&genesis_data).await.unwrap();
+    workflow.include_block(&data_1).await.unwrap();
+    workflow.include_block(&data_2).await.unwrap();
 
     // Check db state before rollback
     let balances = get_balances(&test_db.client).await;
@@ -121,7 +121,7 @@ async fn test_rollback() {
     assert_eq!(balances[1], (addr_c, 4_000_000_000, TS_10K + 120_000));
 
     // Do the rollback
-    workflow.roll_back(data_2.height).await;
+    workflow.roll_back(data_2.height).await.unwrap();
 
     // Check db state after rollback
     let balances = get_balances(&test_db.client).await;
@@ -130,6 +130,79 @@ async fn test_rollback() {
     assert_eq!(balances[1], (addr_b, 5_000_000_000, TS_10K));
 }
 
+/// Supply composition records used to be stored one height above
+/// their block, so rolling back a block removed its parent's record.
+#[tokio::test]
+async fn test_rollback_supply_composition() {
+    let _guard = set_tracing_subscriber(false);
+    let addr_a = AddressID::p2pk(1001);
+    let addr_b = AddressID::miner(1002);
+    let addr_c = AddressID::other(1003);
+    let test_db = TestDB::new("erg_rollback_composition").await;
+    test_db.init_core().await;
+
+    // Genesis
+    let genesis_data = StampedData {
+        height: 0,
+        timestamp: GENESIS_TIMESTAMP,
+        header_id: ZERO_HEADER.to_owned(),
+        parent_id: "".to_owned(),
+        data: DiffData {
+            diff_records: vec![DiffRecord::new(addr_a, 0, 0, 100_000_000_000)],
+        },
+    };
+
+    // Block 1
+    let data_1 = genesis_data
+        .wrap_as_child(DiffData {
+            diff_records: vec![
+                // A sends 5 to B
+                DiffRecord::new(addr_a, 1, 0, -5_000_000_000),
+                DiffRecord::new(addr_b, 1, 0, 5_000_000_000),
+            ],
+        })
+        .timestamp(TS_10K);
+
+    // Block 2
+    let data_2 = data_1
+        .wrap_as_child(DiffData {
+            diff_records: vec![
+                // B sends 5 to C
+                DiffRecord::new(addr_b, 2, 0, -5_000_000_000),
+                DiffRecord::new(addr_c, 2, 0, 5_000_000_000),
+            ],
+        })
+        .timestamp(data_1.timestamp + 120_000);
+
+    // Register core header for parent of rolled back block
+    test_db.insert_core_header(&data_1.get_header()).await;
+
+    let mut workflow = ErgWorkFlow::new(&test_db.pgconf).await.unwrap();
+    workflow.include_block(&genesis_data).await.unwrap();
+    workflow.include_block(&data_1).await.unwrap();
+    workflow.include_block(&data_2).await.unwrap();
+
+    // One record per block, at the block's height
+    assert_eq!(
+        get_supply_composition(&test_db.client).await,
+        vec![
+            (0, 100_000_000_000, 0, 0),
+            (1, 95_000_000_000, 0, 5_000_000_000),
+            (2, 95_000_000_000, 5_000_000_000, 0),
+        ]
+    );
+
+    // Rolling back block 2 removes its own record only
+    workflow.roll_back(data_2.height).await.unwrap();
+    assert_eq!(
+        get_supply_composition(&test_db.client).await,
+        vec![
+            (0, 100_000_000_000, 0, 0),
+            (1, 95_000_000_000, 0, 5_000_000_000),
+        ]
+    );
+}
+
 /// Tests the rollback of a block in which
 /// an address gets spent and then resupplied
 /// within the same block.
@@ -184,10 +257,10 @@ async fn test_rollback_with_full_cycling() {
     // Register core header for parent of rolled back blocks
     test_db.insert_core_header(&data_1.get_header()).await;
 
-    let mut workflow = ErgWorkFlow::new(&test_db.pgconf).await;
-    workflow.include_block(&genesis_data).await;
-    workflow.include_block(&data_1).await;
-    workflow.include_block(&data_2).await;
+    let mut workflow = ErgWorkFlow::new(&test_db.pgconf).await.unwrap();
+    workflow.include_block(&genesis_data).await.unwrap();
+    workflow.include_block(&data_1).await.unwrap();
+    workflow.include_block(&data_2).await.unwrap();
 
     // Check db state before rollback
     let balances = get_balances(&test_db.client).await;
@@ -196,7 +269,7 @@ async fn test_rollback_with_full_cycling() {
     assert_eq!(balances[1], (addr_b, 2_000_000_000, TS_10K + 120_000));
 
     // Do the rollback
-    workflow.roll_back(data_2.height).await;
+    workflow.roll_back(data_2.height).await.unwrap();
 
     // Check db state after rollback
     let balances = get_balances(&test_db.client).await;
@@ -224,8 +297,13 @@ async fn test_mig1_1_whithout_rollback_issue() {
         .await;
 
     // Run migration
-    let mut migrator = PgMigrator::new(&test_db.pgconf, &ew::workers::erg::testing::SCHEMA).await;
-    migrator.apply(&ew::workers::erg::testing::Mig1_1 {}).await;
+    let mut migrator = PgMigrator::new(&test_db.pgconf, &ew::workers::erg::testing::SCHEMA)
+        .await
+        .unwrap();
+    migrator
+        .apply(&ew::workers::erg::testing::Mig1_1 {})
+        .await
+        .unwrap();
 
     // Check revision
     let rev = test_db
@@ -272,8 +350,13 @@ async fn test_mig1_1_with_rollback_issue() {
         .await;
 
     // Run migration
-    let mut migrator = PgMigrator::new(&test_db.pgconf, &ew::workers::erg::testing::SCHEMA).await;
-    migrator.apply(&ew::workers::erg::testing::Mig1_1 {}).await;
+    let mut migrator = PgMigrator::new(&test_db.pgconf, &ew::workers::erg::testing::SCHEMA)
+        .await
+        .unwrap();
+    migrator
+        .apply(&ew::workers::erg::testing::Mig1_1 {})
+        .await
+        .unwrap();
 
     // Check revision
     let rev = test_db
@@ -284,6 +367,132 @@ async fn test_mig1_1_with_rollback_issue() {
     assert_eq!(rev.minor, 1);
 }
 
+/// Prepares an erg store at revision 1.1 and height 4, with given supply
+/// composition records and the balance diffs of the main chain.
+async fn prepare_mig1_2(test_db: &TestDB, composition_sql: &str) {
+    test_db.init_core().await;
+    test_db
+        .init_schema(include_str!("../src/workers/erg_diffs/store/schema.sql"))
+        .await;
+    test_db
+        .init_schema(include_str!("../src/workers/erg/store/schema.sql"))
+        .await;
+    test_db.init_ew().await;
+    test_db
+        .set_revision("erg", "erg", &Revision::new(1, 1))
+        .await;
+    test_db
+        .set_worker_header(
+            "erg",
+            "erg",
+            &Header::from(&testutils::Block::dummy().height(4).header),
+        )
+        .await;
+
+    // Emission contract (13) is left out of the composition
+    test_db
+        .init_schema(
+            "
+            insert into erg.balance_diffs (address_id, height, tx_idx, nano) values
+                (13, 0, 0, 1000),
+                (11, 0, 0, 100),
+                (13, 1, 0, -10),
+                (22, 1, 0, 10),
+                (11, 2, 0, -40),
+                (33, 2, 0, 40),
+                (33, 3, 0, -20),
+                (21, 3, 0, 20),
+                (13, 4, 0, -5),
+                (22, 4, 0, 5);
+            ",
+        )
+        .await;
+    test_db.init_schema(composition_sql).await;
+
+    // Run migration
+    let mut migrator = PgMigrator::new(&test_db.pgconf, &ew::workers::erg::testing::SCHEMA)
+        .await
+        .unwrap();
+    migrator
+        .apply(&ew::workers::erg::testing::Mig1_2 {})
+        .await
+        .unwrap();
+
+    // Check revision
+    let rev = test_db
+        .get_revision("erg", "erg")
+        .await
+        .expect("revsion should be set");
+    assert_eq!(rev.major, 1);
+    assert_eq!(rev.minor, 2);
+}
+
+/// Check migration 1.2 moves supply composition records down one height.
+#[tokio::test]
+async fn test_mig1_2_without_rollbacks() {
+    let _guard = set_tracing_subscriber(false);
+    let test_db = TestDB::new("erg_migration_1_2_a").await;
+
+    // Records one height above their block
+    prepare_mig1_2(
+        &test_db,
+        "
+        insert into erg.supply_composition (height, p2pks, contracts, miners) values
+            (1, 100, 0, 0),
+            (2, 100, 0, 10),
+            (3, 60, 40, 10),
+            (4, 80, 20, 10),
+            (5, 80, 20, 15);
+        ",
+    )
+    .await;
+
+    assert_eq!(
+        get_supply_composition(&test_db.client).await,
+        vec![
+            (0, 100, 0, 0),
+            (1, 100, 0, 10),
+            (2, 60, 40, 10),
+            (3, 80, 20, 10),
+            (4, 80, 20, 15),
+        ]
+    );
+}
+
+/// Check migration 1.2 derives records again past a rolled back block.
+#[tokio::test]
+async fn test_mig1_2_with_rollbacks() {
+    let _guard = set_tracing_subscriber(false);
+    let test_db = TestDB::new("erg_migration_1_2_b").await;
+
+    // Rolling back block 3 (previously at 4, with contracts -40 and
+    // miners +40) deleted block 2's record and left block 3's in place.
+    // Later records build on it.
+    prepare_mig1_2(
+        &test_db,
+        "
+        insert into erg.supply_composition (height, p2pks, contracts, miners) values
+            (1, 100, 0, 0),
+            (2, 100, 0, 10),
+            (4, 60, 0, 50),
+            (5, 80, -20, 50),
+            (6, 80, -20, 55);
+        ",
+    )
+    .await;
+
+    assert_eq!(
+        get_supply_composition(&test_db.client).await,
+        vec![
+            (0, 100, 0, 0),
+            (1, 100, 0, 10),
+            (2, 60, 40, 10),
+            (3, 80, 20, 10),
+            (4, 80, 20, 15),
+        ]
+    );
+}
+
 async fn get_balances(client: &Client) -> Vec<(AddressID, i64, i64)> {
     client
         .query(
@@ -306,3 +515,18 @@ async fn get_balances(client: &Client) -> Vec<(AddressID, i64, i64)> {
         })
         .collect()
 }
+
+async fn get_supply_composition(client: &Client) -> Vec<(i32, i64, i64, i64)> {
+    client
+        .query(
+            "select height, p2pks, contracts, miners
+            from erg.supply_composition
+            order by height;",
+            &[],
+        )
+        .await
+        .unwrap()
+        .iter()
+        .map(|r| (r.get(0), r.get(1), r.get(2), r.get(3)))
+        .collect()
+}
@@ -44,7 +44,7 @@ async fn test_empty_blocks() {
     let block = Block::child_of(&genesis_block).timestamp(GENESIS_TIMESTAMP + 120_000);
 
     // Process blocks
-    let mut workflow = ErgDiffsWorkFlow::new(&test_db.pgconf).await;
+    let mut workflow = ErgDiffsWorkFlow::new(&test_db.pgconf).await.unwrap();
     workflow
         .include_block(
             &CoreData {
@@ -52,8 +52,12 @@ async fn test_empty_blocks() {
             }
             .into(),
         )
-        .await;
-    workflow.include_block(&CoreData { block }.into()).await;
+        .await
+        .unwrap();
+    workflow
+        .include_block(&CoreData { block }.into())
+        .await
+        .unwrap();
 }
 
 #[tokio::test]
@@ -106,7 +110,7 @@ async fn test_rollback() {
     // Register core header for parent of rolled back blocks
     test_db.insert_core_header(&(&block_x.header).into()).await;
 
-    let mut workflow = ErgDiffsWorkFlow::new(&test_db.pgconf).await;
+    let mut workflow = ErgDiffsWorkFlow::new(&test_db.pgconf).await.unwrap();
     let height_y = block_y.header.height;
     workflow
         .include_block(
@@ -115,13 +119,16 @@ async fn test_rollback() {
             }
             .into(),
         )
-        .await;
+        .await
+        .unwrap();
     workflow
         .include_block(&CoreData { block: block_x }.into())
-        .await;
+        .await
+        .unwrap();
     workflow
         .include_block(&CoreData { block: block_y }.into())
-        .await;
+        .await
+        .unwrap();
 
     // Check db state before rollback
     let diffs = get_diffs(&test_db.client).await;
@@ -140,7 +147,7 @@ async fn test_rollback() {
     );
 
     // Do the rollback
-    workflow.roll_back(height_y).await;
+    workflow.roll_back(height_y).await.unwrap();
 
     // Check db state after rollback
     let diffs = get_diffs(&test_db.client).await;
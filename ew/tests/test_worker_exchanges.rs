@@ -5,7 +5,9 @@ use db_utils::TestDB;
 use ew::constants::GENESIS_TIMESTAMP;
 use ew::constants::ZERO_HEADER;
 use ew::core::types::AddressID;
+use ew::core::types::Header;
 use ew::core::types::Timestamp;
+use ew::framework::store::MigrationEffect;
 use ew::framework::store::PgMigrator;
 use ew::framework::store::Revision;
 use ew::framework::EventHandling;
@@ -13,6 +15,7 @@ use ew::framework::QueryHandler;
 use ew::framework::QuerySender;
 use ew::framework::QueryWrapper;
 use ew::framework::Querying;
+use ew::framework::Rewinding;
 use ew::framework::StampedData;
 use ew::workers::erg_diffs::queries::DiffsQuery;
 use ew::workers::erg_diffs::queries::DiffsQueryResponse;
@@ -85,13 +88,13 @@ async fn test_empty_blocks() {
     });
 
     // Configure worker
-    let mut workflow = CexWorkFlow::new(&test_db.pgconf).await;
+    let mut workflow = CexWorkFlow::new(&test_db.pgconf).await.unwrap();
     let mock_query_handler = MockQueryHandler::new();
     workflow.set_query_sender(QuerySender::new(mock_query_handler.connect()));
 
     // Process blocks
-    workflow.include_block(&genesis_data).await;
-    workflow.include_block(&data_1).await;
+    workflow.include_block(&genesis_data).await.unwrap();
+    workflow.include_block(&data_1).await.unwrap();
 }
 
 #[tokio::test]
@@ -103,7 +106,7 @@ async fn test_rollback() {
     test_db.init_core().await;
 
     // Init dummy workflow to initialize test db so we can fill in mock data
-    let _ = CexWorkFlow::new(&test_db.pgconf).await;
+    let _ = CexWorkFlow::new(&test_db.pgconf).await.unwrap();
 
     // Define some fake CEX's in the test db
     let cex1_address = AddressID(9101);
@@ -158,7 +161,7 @@ async fn test_rollback() {
     test_db.insert_core_header(&data_1.get_header()).await;
 
     // Configure workflow
-    let mut workflow = CexWorkFlow::new(&test_db.pgconf).await;
+    let mut workflow = CexWorkFlow::new(&test_db.pgconf).await.unwrap();
     let mut mock_query_handler = MockQueryHandler::new();
     workflow.set_query_sender(QuerySender::new(mock_query_handler.connect()));
 
@@ -198,9 +201,9 @@ async fn test_rollback() {
     });
 
     // Process blocks
-    workflow.include_block(&genesis_data).await;
-    workflow.include_block(&data_1).await;
-    workflow.include_block(&data_2).await;
+    workflow.include_block(&genesis_data).await.unwrap();
+    workflow.include_block(&data_1).await.unwrap();
+    workflow.include_block(&data_2).await.unwrap();
 
     // Check db state before rollback
     let deposit_addresses = get_deposit_addresses(&test_db.client).await;
@@ -230,7 +233,7 @@ async fn test_rollback() {
     );
 
     // Do the rollback
-    workflow.roll_back(data_2.height).await;
+    workflow.roll_back(data_2.height).await.unwrap();
 
     // Check db state after rollback
     let deposit_addresses = get_deposit_addresses(&test_db.client).await;
@@ -250,7 +253,7 @@ async fn test_reproduce_negative_deposits_bug() {
     test_db.init_core().await;
 
     // Init dummy workflow to initialize test db so we can fill in mock data
-    let _ = CexWorkFlow::new(&test_db.pgconf).await;
+    let _ = CexWorkFlow::new(&test_db.pgconf).await.unwrap();
 
     // Define a fake CEX in the test db
     let cex1_address = AddressID(9101);
@@ -330,28 +333,30 @@ async fn test_reproduce_negative_deposits_bug() {
     insert_balance_diffs(&test_db.client, &data_10.data.diff_records).await;
 
     // Configure workflow
-    let mut workflow = CexWorkFlow::new(&test_db.pgconf).await;
-    let mut query_handler = ew::workers::erg_diffs::QueryWorker::new(&test_db.pgconf).await;
+    let mut workflow = CexWorkFlow::new(&test_db.pgconf).await.unwrap();
+    let mut query_handler = ew::workers::erg_diffs::QueryWorker::new(&test_db.pgconf)
+        .await
+        .unwrap();
     workflow.set_query_sender(query_handler.connect());
 
     // Spawn query handler.
 
     tokio::spawn(async move {
-        query_handler.start().await;
+        query_handler.start().await.unwrap();
     });
 
     // Process blocks
-    workflow.include_block(&genesis_data).await;
-    workflow.include_block(&data_1).await;
-    workflow.include_block(&data_2).await;
-    workflow.include_block(&data_3).await;
-    workflow.include_block(&data_4).await;
-    workflow.include_block(&data_5).await;
-    workflow.include_block(&data_6).await;
-    workflow.include_block(&data_7).await;
-    workflow.include_block(&data_8).await;
-    workflow.include_block(&data_9).await;
-    workflow.include_block(&data_10).await;
+    workflow.include_block(&genesis_data).await.unwrap();
+    workflow.include_block(&data_1).await.unwrap();
+    workflow.include_block(&data_2).await.unwrap();
+    workflow.include_block(&data_3).await.unwrap();
+    workflow.include_block(&data_4).await.unwrap();
+    workflow.include_block(&data_5).await.unwrap();
+    workflow.include_block(&data_6).await.unwrap();
+    workflow.include_block(&data_7).await.unwrap();
+    workflow.include_block(&data_8).await.unwrap();
+    workflow.include_block(&data_9).await.unwrap();
+    workflow.include_block(&data_10).await.unwrap();
 
     tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
 
@@ -458,7 +463,7 @@ async fn test_ignored_deposit_addresses() {
     test_db.init_core().await;
 
     // Init dummy workflow to initialize test db so we can fill in mock data
-    let _ = CexWorkFlow::new(&test_db.pgconf).await;
+    let _ = CexWorkFlow::new(&test_db.pgconf).await.unwrap();
 
     // Define a fake CEX in the test db
     let cex1_address = AddressID(9101);
@@ -519,22 +524,24 @@ async fn test_ignored_deposit_addresses() {
     insert_balance_diffs(&test_db.client, &data_5.data.diff_records).await;
 
     // Configure workflow
-    let mut workflow = CexWorkFlow::new(&test_db.pgconf).await;
-    let mut query_handler = ew::workers::erg_diffs::QueryWorker::new(&test_db.pgconf).await;
+    let mut workflow = CexWorkFlow::new(&test_db.pgconf).await.unwrap();
+    let mut query_handler = ew::workers::erg_diffs::QueryWorker::new(&test_db.pgconf)
+        .await
+        .unwrap();
     workflow.set_query_sender(query_handler.connect());
 
     // Spawn query handler.
     tokio::spawn(async move {
-        query_handler.start().await;
+        query_handler.start().await.unwrap();
     });
 
     // Process blocks
-    workflow.include_block(&genesis_data).await;
-    workflow.include_block(&data_1).await;
-    workflow.include_block(&data_2).await;
-    workflow.include_block(&data_3).await;
-    workflow.include_block(&data_4).await;
-    workflow.include_block(&data_5).await;
+    workflow.include_block(&genesis_data).await.unwrap();
+    workflow.include_block(&data_1).await.unwrap();
+    workflow.include_block(&data_2).await.unwrap();
+    workflow.include_block(&data_3).await.unwrap();
+    workflow.include_block(&data_4).await.unwrap();
+    workflow.include_block(&data_5).await.unwrap();
 
     tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
 
@@ -599,7 +606,7 @@ async fn test_intra_block_conflict() {
     test_db.init_core().await;
 
     // Init dummy workflow to initialize test db so we can fill in mock data
-    let _ = CexWorkFlow::new(&test_db.pgconf).await;
+    let _ = CexWorkFlow::new(&test_db.pgconf).await.unwrap();
 
     // Define 2 fake CEX's in the test db
     let cex1_address = AddressID(9101);
@@ -653,20 +660,22 @@ async fn test_intra_block_conflict() {
     insert_balance_diffs(&test_db.client, &data_3.data.diff_records).await;
 
     // Configure workflow
-    let mut workflow = CexWorkFlow::new(&test_db.pgconf).await;
-    let mut query_handler = ew::workers::erg_diffs::QueryWorker::new(&test_db.pgconf).await;
+    let mut workflow = CexWorkFlow::new(&test_db.pgconf).await.unwrap();
+    let mut query_handler = ew::workers::erg_diffs::QueryWorker::new(&test_db.pgconf)
+        .await
+        .unwrap();
     workflow.set_query_sender(query_handler.connect());
 
     // Spawn query handler.
     tokio::spawn(async move {
-        query_handler.start().await;
+        query_handler.start().await.unwrap();
     });
 
     // Process blocks
-    workflow.include_block(&genesis_data).await;
-    workflow.include_block(&data_1).await;
-    workflow.include_block(&data_2).await;
-    workflow.include_block(&data_3).await;
+    workflow.include_block(&genesis_data).await.unwrap();
+    workflow.include_block(&data_1).await.unwrap();
+    workflow.include_block(&data_2).await.unwrap();
+    workflow.include_block(&data_3).await.unwrap();
 
     tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
 
@@ -718,7 +727,7 @@ async fn test_inter_block_conflict() {
     test_db.init_core().await;
 
     // Init dummy workflow to initialize test db so we can fill in mock data
-    let _ = CexWorkFlow::new(&test_db.pgconf).await;
+    let _ = CexWorkFlow::new(&test_db.pgconf).await.unwrap();
 
     // Define 2 fake CEX's in the test db
     let cex1_address = AddressID(9101);
@@ -777,19 +786,21 @@ async fn test_inter_block_conflict() {
     insert_balance_diffs(&test_db.client, &data_4.data.diff_records).await;
 
     // Configure workflow
-    let mut workflow = CexWorkFlow::new(&test_db.pgconf).await;
-    let mut query_handler = ew::workers::erg_diffs::QueryWorker::new(&test_db.pgconf).await;
+    let mut workflow = CexWorkFlow::new(&test_db.pgconf).await.unwrap();
+    let mut query_handler = ew::workers::erg_diffs::QueryWorker::new(&test_db.pgconf)
+        .await
+        .unwrap();
     workflow.set_query_sender(query_handler.connect());
 
     // Spawn query handler.
     tokio::spawn(async move {
-        query_handler.start().await;
+        query_handler.start().await.unwrap();
     });
 
     // Process blocks prior to conflict
-    workflow.include_block(&genesis_data).await;
-    workflow.include_block(&data_1).await;
-    workflow.include_block(&data_2).await;
+    workflow.include_block(&genesis_data).await.unwrap();
+    workflow.include_block(&data_1).await.unwrap();
+    workflow.include_block(&data_2).await.unwrap();
 
     // Check db state before conflict
     let supply_records = get_supply_records(&test_db.client).await;
@@ -820,8 +831,8 @@ async fn test_inter_block_conflict() {
     );
 
     // Process remaining blocks
-    workflow.include_block(&data_3).await;
-    workflow.include_block(&data_4).await;
+    workflow.include_block(&data_3).await.unwrap();
+    workflow.include_block(&data_4).await.unwrap();
 
     // Check db state after conflict
     let supply_records = get_supply_records(&test_db.client).await;
@@ -871,6 +882,144 @@ async fn test_inter_block_conflict() {
     assert_eq!(deposit_conflicts, vec![addr_a]);
 }
 
+#[tokio::test]
+async fn test_rewind_inter_block_conflict() {
+    let _guard = set_tracing_subscriber(false);
+    let addr_a = AddressID(8581);
+    let test_db = TestDB::new("exchanges_rewind_inter_block_conflict").await;
+    test_db.init_core().await;
+
+    // Init dummy workflow to initialize test db so we can fill in mock data
+    let _ = CexWorkFlow::new(&test_db.pgconf).await.unwrap();
+
+    // Define 2 fake CEX's in the test db
+    let cex1_address = AddressID(9101);
+    let cex1_id: i32 = 10000;
+    insert_exchange(&test_db.client, cex1_id, "Exchange 1", "cex_1").await;
+    insert_main_address(&test_db.client, cex1_id, &cex1_address).await;
+    let cex2_address = AddressID(9102);
+    let cex2_id: i32 = 20000;
+    insert_exchange(&test_db.client, cex1_id, "Exchange 2", "cex_2").await;
+    insert_main_address(&test_db.client, cex2_id, &cex2_address).await;
+
+    // Genesis
+    let genesis_data = StampedData {
+        height: 0,
+        timestamp: GENESIS_TIMESTAMP,
+        header_id: ZERO_HEADER.to_owned(),
+        parent_id: "".to_owned(),
+        data: DiffData {
+            diff_records: vec![],
+        },
+    };
+
+    // Block 1 - create some out of thin air
+    let data_1 = genesis_data.wrap_as_child(DiffData {
+        diff_records: vec![DiffRecord::new(addr_a, 1, 0, 7_000_000_000)],
+    });
+
+    // Block 2 - a sends to cex1 --> spotted as deposit
+    let data_2 = data_1.wrap_as_child(DiffData {
+        diff_records: vec![
+            DiffRecord::new(addr_a, 2, 0, -2_000_000_000),
+            DiffRecord::new(cex1_address, 2, 0, 2_000_000_000),
+        ],
+    });
+
+    // Block 3 - a sends to cex2 --> inter-block conflict
+    let data_3 = data_2.wrap_as_child(DiffData {
+        diff_records: vec![
+            DiffRecord::new(addr_a, 3, 0, -3_000_000_000),
+            DiffRecord::new(cex2_address, 3, 0, 3_000_000_000),
+        ],
+    });
+
+    // Block 4 - a receives some
+    let data_4 = data_3.wrap_as_child(DiffData {
+        diff_records: vec![DiffRecord::new(addr_a, 4, 0, 10_000_000_000)],
+    });
+
+    // Prepare erg.balance_diffs table
+    test_db
+        .init_schema(include_str!("../src/workers/erg_diffs/store/schema.sql"))
+        .await;
+    insert_balance_diffs(&test_db.client, &data_1.data.diff_records).await;
+    insert_balance_diffs(&test_db.client, &data_2.data.diff_records).await;
+    insert_balance_diffs(&test_db.client, &data_3.data.diff_records).await;
+    insert_balance_diffs(&test_db.client, &data_4.data.diff_records).await;
+
+    // Register core headers of rewind heights
+    test_db.insert_core_header(&Header::from(&data_1)).await;
+    test_db.insert_core_header(&Header::from(&data_2)).await;
+
+    // Configure workflow
+    let mut workflow = CexWorkFlow::new(&test_db.pgconf).await.unwrap();
+    let mut query_handler = ew::workers::erg_diffs::QueryWorker::new(&test_db.pgconf)
+        .await
+        .unwrap();
+    workflow.set_query_sender(query_handler.connect());
+
+    // Spawn query handler.
+    tokio::spawn(async move {
+        query_handler.start().await.unwrap();
+    });
+
+    // Process all blocks
+    for data in [&genesis_data, &data_1, &data_2, &data_3, &data_4] {
+        workflow.include_block(data).await.unwrap();
+    }
+    assert_eq!(get_deposit_conflicts(&test_db.client).await, vec![addr_a]);
+
+    // Rewind to block 2, prior to conflict
+    let header = workflow.rewind(data_2.height).await.unwrap();
+    assert_eq!(header.height, 2);
+
+    // Conflict is undone and deposit restored in supply
+    let supply_records = get_supply_records(&test_db.client).await;
+    assert_eq!(
+        supply_records,
+        vec![
+            SupplyRecord {
+                height: 0,
+                main: 0,
+                deposits: 0
+            },
+            SupplyRecord {
+                height: 1,
+                main: 0,
+                deposits: 7_000_000_000,
+            },
+            SupplyRecord {
+                height: 2,
+                main: 2_000_000_000,
+                deposits: 5_000_000_000,
+            },
+        ]
+    );
+    assert_eq!(get_deposit_addresses(&test_db.client).await, vec![addr_a]);
+    assert!(get_deposit_conflicts(&test_db.client).await.is_empty());
+
+    // Rewind to block 1, prior to deposit
+    workflow.rewind(data_1.height).await.unwrap();
+    let supply_records = get_supply_records(&test_db.client).await;
+    assert_eq!(
+        supply_records,
+        vec![
+            SupplyRecord {
+                height: 0,
+                main: 0,
+                deposits: 0
+            },
+            SupplyRecord {
+                height: 1,
+                main: 0,
+                deposits: 0,
+            },
+        ]
+    );
+    assert!(get_deposit_addresses(&test_db.client).await.is_empty());
+}
+
 #[tokio::test]
 async fn test_migrations() {
     let _guard = set_tracing_subscriber(false);
@@ -903,26 +1052,33 @@ async fn test_migrations() {
     .await;
 
     // Run migrations
-    let mut migrator =
-        PgMigrator::new(&test_db.pgconf, &ew::workers::exchanges::testing::SCHEMA).await;
+    let mut migrator = PgMigrator::new(&test_db.pgconf, &ew::workers::exchanges::testing::SCHEMA)
+        .await
+        .unwrap();
     migrator
         .apply(&ew::workers::exchanges::testing::Mig1_1 {})
-        .await;
+        .await
+        .unwrap();
     migrator
         .apply(&ew::workers::exchanges::testing::Mig1_2 {})
-        .await;
+        .await
+        .unwrap();
     migrator
         .apply(&ew::workers::exchanges::testing::Mig1_3 {})
-        .await;
+        .await
+        .unwrap();
     migrator
         .apply(&ew::workers::exchanges::testing::Mig1_4 {})
-        .await;
+        .await
+        .unwrap();
     migrator
         .apply(&ew::workers::exchanges::testing::Mig1_5 {})
-        .await;
+        .await
+        .unwrap();
     migrator
         .apply(&ew::workers::exchanges::testing::Mig1_6 {})
-        .await;
+        .await
+        .unwrap();
 
     // Check revision
     let rev = test_db
@@ -933,6 +1089,69 @@ async fn test_migrations() {
     assert_eq!(rev.minor, 6);
 }
 
+#[tokio::test]
+async fn test_pending_migrations() {
+    let _guard = set_tracing_subscriber(false);
+    let test_db = TestDB::new("exchanges_pending_migrations").await;
+    test_db.init_core().await;
+
+    // Initial schema and revision
+    test_db
+        .init_schema(include_str!(
+            "../src/workers/exchanges/store/schema.1.0.sql"
+        ))
+        .await;
+    test_db.init_ew().await;
+    test_db
+        .set_revision("exchanges", "exchanges", &Revision::new(1, 0))
+        .await;
+    test_db
+        .init_schema(include_str!("../src/workers/erg_diffs/store/schema.sql"))
+        .await;
+
+    // Dry run lists all migrations and leaves store untouched
+    let pending = PgMigrator::dry_run(
+        &test_db.pgconf,
+        &ew::workers::exchanges::testing::SCHEMA,
+        true,
+    )
+    .await
+    .unwrap();
+    let revisions: Vec<i32> = pending.iter().map(|m| m.revision.minor).collect();
+    assert_eq!(revisions, vec![1, 2, 3, 4, 5, 6, 7]);
+    assert!(pending
+        .iter()
+        .all(|m| m.effect == Some(MigrationEffect::None)));
+    let rev = test_db
+        .get_revision("exchanges", "exchanges")
+        .await
+        .unwrap();
+    assert_eq!(rev, Revision::new(1, 0));
+
+    // Apply them all
+    PgMigrator::new(&test_db.pgconf, &ew::workers::exchanges::testing::SCHEMA)
+        .await
+        .unwrap()
+        .apply_pending()
+        .await
+        .unwrap();
+    let rev = test_db
+        .get_revision("exchanges", "exchanges")
+        .await
+        .unwrap();
+    assert_eq!(rev, Revision::new(1, 7));
+
+    // Nothing left to do
+    let pending = PgMigrator::dry_run(
+        &test_db.pgconf,
+        &ew::workers::exchanges::testing::SCHEMA,
+        false,
+    )
+    .await
+    .unwrap();
+    assert!(pending.is_empty());
+}
+
 async fn insert_exchange(client: &Client, id: i32, name: &str, text_id: &str) {
     let stmt = "
         insert into exchanges.exchanges (id, name, text_id)
@@ -0,0 +1,261 @@
+// cargo test --test '*' -- --test-threads=1
+mod db_utils;
+
+use parquet::file::reader::FileReader;
+use parquet::file::reader::SerializedFileReader;
+use serde_json::Value;
+use std::path::Path;
+use std::path::PathBuf;
+
+use db_utils::TestDB;
+use ew::config::ExportConfig;
+use ew::core::types::AddressID;
+use ew::core::types::Block;
+use ew::core::types::BoxData;
+use ew::core::types::CoreData;
+use ew::core::types::Header;
+use ew::core::types::Transaction;
+use ew::framework::EventHandling;
+use ew::framework::StampedData;
+use ew::workers::erg_diffs::types::DiffData;
+use ew::workers::erg_diffs::types::DiffRecord;
+use ew::workers::export::ExportWorkFlow;
+
+/// Empty export directory for given test.
+fn export_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ew_test_export_{name}"));
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    dir
+}
+
+fn config(dir: &Path) -> ExportConfig {
+    ExportConfig {
+        dir: Some(dir.to_path_buf()),
+        partition_size: 2,
+        parquet: true,
+    }
+}
+
+/// Rows of an NDJSON file, if it exists.
+fn read_rows(path: &Path) -> Option<Vec<Value>> {
+    let content = std::fs::read_to_string(path).ok()?;
+    Some(
+        content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect(),
+    )
+}
+
+fn parquet_rows(path: &Path) -> i64 {
+    let file = std::fs::File::open(path).unwrap();
+    let reader = SerializedFileReader::new(file).unwrap();
+    reader.metadata().file_metadata().num_rows()
+}
+
+fn transfer(address_id: AddressID) -> Transaction {
+    Transaction::dummy()
+        .add_input(BoxData::dummy().address_id(AddressID(11)).value(1000))
+        .add_output(
+            BoxData::dummy()
+                .address_id(address_id)
+                .value(1000)
+                .add_asset(5, 20),
+        )
+}
+
+async fn init(test_db: &TestDB) {
+    test_db.init_core().await;
+    test_db.init_ew().await;
+    test_db
+        .client
+        .batch_execute(
+            "insert into core.addresses (id, spot_height, address) values
+                (21, 0, 'addressA'),
+                (31, 0, 'addressB');
+            insert into core.tokens (asset_id, spot_height, token_id) values
+                (5, 0, 'tokenA');",
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_partitions_and_rollbacks() {
+    let test_db = TestDB::new("export_core").await;
+    init(&test_db).await;
+    let dir = export_dir("core");
+    let config = config(&dir);
+    let core = dir.join("export_core");
+
+    let block0 = Block::dummy()
+        .height(0)
+        .parent_id("")
+        .add_tx(transfer(AddressID(21)));
+    let block1 = Block::child_of(&block0);
+    let block2 = Block::child_of(&block1).add_tx(transfer(AddressID(21)));
+    let block3 = Block::child_of(&block2).add_tx(transfer(AddressID(21)));
+    let block3b = Block::child_of(&block2).add_tx(transfer(AddressID(31)));
+    for block in [&block0, &block1, &block2, &block3] {
+        test_db.insert_core_header(&(&block.header).into()).await;
+    }
+    let block3_id = block3.header.id.clone();
+
+    let mut workflow = ExportWorkFlow::<CoreData>::with_config(&test_db.pgconf, &config)
+        .await
+        .unwrap();
+    for block in [block0, block1, block2, block3] {
+        workflow
+            .include_block(&CoreData { block }.into())
+            .await
+            .unwrap();
+    }
+
+    // Completed partitions are converted to parquet
+    let blocks = read_rows(&core.join("blocks/00000000-00000001.ndjson")).unwrap();
+    assert_eq!(blocks.len(), 2);
+    assert_eq!(blocks[1]["height"], 1);
+    assert_eq!(
+        parquet_rows(&core.join("blocks/00000000-00000001.parquet")),
+        2
+    );
+    let boxes = read_rows(&core.join("boxes/00000000-00000001.ndjson")).unwrap();
+    assert_eq!(boxes.len(), 1);
+    assert_eq!(boxes[0]["address"], "addressA");
+    assert_eq!(boxes[0]["assets"][0]["token_id"], "tokenA");
+    assert_eq!(
+        parquet_rows(&core.join("boxes/00000000-00000001.parquet")),
+        1
+    );
+    assert_eq!(
+        parquet_rows(&core.join("transactions/00000002-00000003.parquet")),
+        2
+    );
+    let manifest: Value =
+        serde_json::from_str(&std::fs::read_to_string(core.join("manifest.json")).unwrap())
+            .unwrap();
+    assert_eq!(manifest["height"], 3);
+    assert_eq!(manifest["header_id"], Value::String(block3_id));
+
+    // Rollbacks rewrite the affected partition
+    workflow.roll_back(3).await.unwrap();
+    let boxes = read_rows(&core.join("boxes/00000002-00000003.ndjson")).unwrap();
+    assert_eq!(boxes.len(), 1);
+    assert_eq!(boxes[0]["height"], 2);
+    assert!(!core.join("boxes/00000002-00000003.parquet").exists());
+    let manifest: Value =
+        serde_json::from_str(&std::fs::read_to_string(core.join("manifest.json")).unwrap())
+            .unwrap();
+    assert_eq!(manifest["height"], 2);
+    // Earlier partitions are left alone
+    assert!(core.join("boxes/00000000-00000001.parquet").exists());
+
+    workflow
+        .include_block(&CoreData { block: block3b }.into())
+        .await
+        .unwrap();
+    let boxes = read_rows(&core.join("boxes/00000002-00000003.ndjson")).unwrap();
+    assert_eq!(boxes.len(), 2);
+    assert_eq!(boxes[1]["address"], "addressB");
+    assert_eq!(
+        parquet_rows(&core.join("boxes/00000002-00000003.parquet")),
+        2
+    );
+}
+
+#[tokio::test]
+async fn test_files_are_aligned_on_start() {
+    let test_db = TestDB::new("export_align").await;
+    init(&test_db).await;
+    let dir = export_dir("align");
+    let config = config(&dir);
+    let core = dir.join("export_core");
+
+    let block0 = Block::dummy()
+        .height(0)
+        .parent_id("")
+        .add_tx(transfer(AddressID(21)));
+    let mut workflow = ExportWorkFlow::<CoreData>::with_config(&test_db.pgconf, &config)
+        .await
+        .unwrap();
+    workflow
+        .include_block(&CoreData { block: block0 }.into())
+        .await
+        .unwrap();
+
+    // Rows of blocks that never got committed, e.g. after a crash
+    let path = core.join("blocks/00000000-00000001.ndjson");
+    let mut content = std::fs::read_to_string(&path).unwrap();
+    content.push_str("{\"height\":1}\n");
+    std::fs::write(&path, content).unwrap();
+    std::fs::write(
+        core.join("blocks/00000002-00000003.ndjson"),
+        "{\"height\":2}\n",
+    )
+    .unwrap();
+
+    let _workflow = ExportWorkFlow::<CoreData>::with_config(&test_db.pgconf, &config)
+        .await
+        .unwrap();
+    assert_eq!(read_rows(&path).unwrap().len(), 1);
+    assert!(!core.join("blocks/00000002-00000003.ndjson").exists());
+
+    // Partition size of existing files cannot change
+    let config = ExportConfig {
+        partition_size: 3,
+        ..config
+    };
+    assert!(
+        ExportWorkFlow::<CoreData>::with_config(&test_db.pgconf, &config)
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_balance_diffs() {
+    let test_db = TestDB::new("export_diffs").await;
+    init(&test_db).await;
+    let dir = export_dir("diffs");
+
+    let header = Header {
+        height: 0,
+        timestamp: 1000,
+        header_id: "h0".to_owned(),
+        parent_id: "".to_owned(),
+    };
+    let data = DiffData {
+        diff_records: vec![
+            DiffRecord {
+                address_id: AddressID(21),
+                height: 0,
+                tx_idx: 0,
+                nano: 1000,
+            },
+            DiffRecord {
+                address_id: AddressID(31),
+                height: 0,
+                tx_idx: 0,
+                nano: -1000,
+            },
+        ],
+    };
+    let mut workflow = ExportWorkFlow::<DiffData>::with_config(&test_db.pgconf, &config(&dir))
+        .await
+        .unwrap();
+    workflow
+        .include_block(&StampedData::new(header, data))
+        .await
+        .unwrap();
+    let diffs =
+        read_rows(&dir.join("export_diffs/balance_diffs/00000000-00000001.ndjson")).unwrap();
+    assert_eq!(
+        diffs,
+        vec![
+            serde_json::json!({"height": 0, "tx_index": 0, "address": "addressA", "nano": 1000}),
+            serde_json::json!({"height": 0, "tx_index": 0, "address": "addressB", "nano": -1000}),
+        ]
+    );
+}
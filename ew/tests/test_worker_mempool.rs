@@ -0,0 +1,337 @@
+// cargo test --test '*' -- --test-threads=1
+mod common;
+mod db_utils;
+
+use serde_json::json;
+use serde_json::Value;
+
+use common::node_mockup::TestNode;
+use db_utils::TestDB;
+use ew::config::MempoolConfig;
+use ew::config::NodeConfig;
+use ew::core::types::Block;
+use ew::core::types::CoreData;
+use ew::core::types::Transaction;
+use ew::framework::EventHandling;
+use ew::workers::mempool::MempoolWorkFlow;
+use ew::workers::mempool::Poller;
+
+const PORT: i32 = 9057;
+
+/// 9h7L7sUHZk43VQC3PHtSp5ujAWcZtYmWATBH746wi75C5XHi68b
+const TREE_A: &str = "0008cd03553448c194fdd843c87d080f5e8ed983f5bb2807b13b45a9683bba8c7bfb5ae8";
+/// 88dhgzEuTXaTr9yGAQawohWXzEkk7bESXNuSyrC3F7xNFDq6z4S9RoefjjzTSEoHc1GnxXSE8zngaE7m
+const TREE_B: &str = "100204a00b08cd033b2ee29e9a4f9e337bf1960015a34e56d9cef041c5fb89ec44f2412ba1cd1689ea02d192a39a8cc7a70173007301";
+/// Not in core.addresses
+const TREE_C: &str = "100204a00b08cd03be7ad70c74f691345cbedba19f4844e7fc514e1188a7929f5ae261d5bb00bb66ea02d192a39a8cc7a70173007301";
+const ADDRESS_C: &str =
+    "88dhgzEuTXaVTz3coGyrAbJ7DNqH37vUMzpSe2vZaCEeBzA6K2nKTZ2JQJhEFgoWmrCQEQLyZNDYMby5";
+const FEE_TREE: &str = "1005040004000e36100204a00b08cd0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798ea02d192a39a8cc7a701730073011001020402d19683030193a38cc7b2a57300000193c2b2a57301007473027303830108cdeeac93b1a57304";
+
+/// A `mempool.token_diffs` row: tx_id, address_id, address, token_id, value
+type TokenDiffRow = (String, Option<i64>, Option<String>, String, i64);
+
+fn output(
+    tx_id: &str,
+    index: i32,
+    box_id: &str,
+    ergo_tree: &str,
+    value: i64,
+    assets: Value,
+) -> Value {
+    json!({
+        "boxId": box_id,
+        "value": value,
+        "ergoTree": ergo_tree,
+        "assets": assets,
+        "creationHeight": 10,
+        "additionalRegisters": {},
+        "transactionId": tx_id,
+        "index": index,
+    })
+}
+
+fn transaction(id: &str, inputs: &[&str], outputs: Vec<Value>, size: i32) -> Value {
+    let inputs: Vec<Value> = inputs
+        .iter()
+        .map(
+            |box_id| json!({"boxId": box_id, "spendingProof": {"proofBytes": "", "extension": {}}}),
+        )
+        .collect();
+    json!({
+        "id": id,
+        "inputs": inputs,
+        "dataInputs": [],
+        "outputs": outputs,
+        "size": size,
+    })
+}
+
+/// Spends confirmed box `in-1` and sends some of it to B, the rest back to A
+fn tx_1() -> Value {
+    transaction(
+        "tx-1",
+        &["in-1"],
+        vec![
+            output(
+                "tx-1",
+                0,
+                "box-1",
+                TREE_B,
+                900_000_000,
+                json!([{"tokenId": "tokenA", "amount": 40}]),
+            ),
+            output(
+                "tx-1",
+                1,
+                "box-2",
+                TREE_A,
+                98_900_000,
+                json!([{"tokenId": "tokenA", "amount": 60}]),
+            ),
+            output("tx-1", 2, "box-3", FEE_TREE, 1_100_000, json!([])),
+        ],
+        220,
+    )
+}
+
+/// Spends unconfirmed change of tx-1, sending it to new address C
+fn tx_2() -> Value {
+    transaction(
+        "tx-2",
+        &["box-2"],
+        vec![
+            output(
+                "tx-2",
+                0,
+                "box-4",
+                TREE_C,
+                97_900_000,
+                json!([{"tokenId": "tokenA", "amount": 60}]),
+            ),
+            output("tx-2", 1, "box-5", FEE_TREE, 1_000_000, json!([])),
+        ],
+        100,
+    )
+}
+
+/// Spends a box that isn't known yet
+fn tx_3() -> Value {
+    transaction(
+        "tx-3",
+        &["unknown"],
+        vec![output("tx-3", 0, "box-6", TREE_A, 1_000_000, json!([]))],
+        100,
+    )
+}
+
+/// Already confirmed, as its outputs are in core.boxes
+fn tx_4() -> Value {
+    transaction(
+        "tx-4",
+        &["in-1"],
+        vec![output("tx-4", 0, "confirmed", TREE_A, 1_000_000, json!([]))],
+        100,
+    )
+}
+
+async fn init(test_db: &TestDB) {
+    test_db.init_core().await;
+    test_db.init_ew().await;
+    test_db
+        .client
+        .batch_execute(
+            "insert into core.addresses (id, spot_height, address) values
+                (11, 1, '9h7L7sUHZk43VQC3PHtSp5ujAWcZtYmWATBH746wi75C5XHi68b'),
+                (23, 1, '88dhgzEuTXaTr9yGAQawohWXzEkk7bESXNuSyrC3F7xNFDq6z4S9RoefjjzTSEoHc1GnxXSE8zngaE7m'),
+                (2403, 1, '2iHkR7CWvD1R4j1yZg5bkeDRQavjAaVPeTDFGGLZduHyfWMuYpmhHocX8GJoaieTx78FntzJbCBVL6rf96ocJoZdmWBL2fci7NqWgAirppPQmZ7fN9V6z13Ay6brPriBKYqLp1bT2Fk4FkFLCfdPpe');
+            insert into core.tokens (asset_id, spot_height, token_id) values
+                (5, 1, 'tokenA');
+            insert into core.boxes (box_id, height, creation_height, address_id, value, size, assets, registers) values
+                ('in-1', 1, 1, 11, 1000000000, 100, array[(5, 100)::asset], '{}'),
+                ('confirmed', 2, 2, 11, 1000000, 100, null, '{}');",
+        )
+        .await
+        .unwrap();
+}
+
+async fn count_transactions(test_db: &TestDB) -> i64 {
+    test_db
+        .client
+        .query_one("select count(*) from mempool.transactions;", &[])
+        .await
+        .unwrap()
+        .get(0)
+}
+
+#[tokio::test]
+async fn test_mempool() {
+    let test_db = TestDB::new("worker_mempool").await;
+    init(&test_db).await;
+    let node = TestNode::run_on(PORT, &["1"]).await;
+    node.set_unconfirmed(vec![tx_1(), tx_2(), tx_3(), tx_4()]);
+
+    let mut workflow = MempoolWorkFlow::new(&test_db.pgconf).await.unwrap();
+    let mut poller = Poller::new(
+        &test_db.pgconf,
+        &NodeConfig::new(node.url()),
+        &MempoolConfig::default(),
+    )
+    .await
+    .unwrap();
+    poller.poll().await.unwrap();
+
+    // Transactions with unknown inputs or confirmed already are left out
+    let rows = test_db
+        .client
+        .query(
+            "select tx_id, size, fee, fee_per_byte, first_seen
+            from mempool.transactions order by 1;",
+            &[],
+        )
+        .await
+        .unwrap();
+    let txs: Vec<(String, i32, i64, f64)> = rows
+        .iter()
+        .map(|r| (r.get(0), r.get(1), r.get(2), r.get(3)))
+        .collect();
+    assert_eq!(
+        txs,
+        vec![
+            ("tx-1".to_owned(), 220, 1_100_000, 5000.0),
+            ("tx-2".to_owned(), 100, 1_000_000, 10000.0),
+        ]
+    );
+    let first_seen: i64 = rows[1].get(4);
+
+    let rows = test_db
+        .client
+        .query(
+            "select address_id, address, tx_count, nano
+            from mempool.addresses order by 1, 2;",
+            &[],
+        )
+        .await
+        .unwrap();
+    let addresses: Vec<(Option<i64>, Option<String>, i64, i64)> = rows
+        .iter()
+        .map(|r| (r.get(0), r.get(1), r.get(2), r.get(3)))
+        .collect();
+    assert_eq!(
+        addresses,
+        vec![
+            (Some(11), None, 2, -1_000_000_000),
+            (Some(23), None, 1, 900_000_000),
+            (Some(2403), None, 2, 2_100_000),
+            (None, Some(ADDRESS_C.to_owned()), 1, 97_900_000),
+        ]
+    );
+
+    let rows = test_db
+        .client
+        .query(
+            "select tx_id, address_id, address, token_id, value
+            from mempool.token_diffs order by 1, 2;",
+            &[],
+        )
+        .await
+        .unwrap();
+    let tokens: Vec<TokenDiffRow> = rows
+        .iter()
+        .map(|r| (r.get(0), r.get(1), r.get(2), r.get(3), r.get(4)))
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            ("tx-1".to_owned(), Some(11), None, "tokenA".to_owned(), -40),
+            ("tx-1".to_owned(), Some(23), None, "tokenA".to_owned(), 40),
+            ("tx-2".to_owned(), Some(11), None, "tokenA".to_owned(), -60),
+            (
+                "tx-2".to_owned(),
+                None,
+                Some(ADDRESS_C.to_owned()),
+                "tokenA".to_owned(),
+                60
+            ),
+        ]
+    );
+
+    let row = test_db
+        .client
+        .query_one("select tx_count, min, p50, max from mempool.fees;", &[])
+        .await
+        .unwrap();
+    let fees: (i64, f64, f64, f64) = (row.get(0), row.get(1), row.get(2), row.get(3));
+    assert_eq!(fees, (2, 5000.0, 7500.0, 10000.0));
+
+    // Dropped transactions are evicted, others keep their first-seen time
+    node.set_unconfirmed(vec![tx_2(), tx_3()]);
+    poller.poll().await.unwrap();
+    let rows = test_db
+        .client
+        .query("select tx_id, first_seen from mempool.transactions;", &[])
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get::<_, String>(0), "tx-2");
+    assert_eq!(rows[0].get::<_, i64>(1), first_seen);
+    let n: i64 = test_db
+        .client
+        .query_one("select count(*) from mempool.erg_diffs;", &[])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(n, 3);
+
+    // Diffs of new addresses get their id once core indexes them
+    let sql = format!(
+        "insert into core.addresses (id, spot_height, address) values (31, 2, '{ADDRESS_C}');"
+    );
+    test_db.client.batch_execute(&sql).await.unwrap();
+    poller.poll().await.unwrap();
+    let sql = "
+        select (select count(*) from mempool.erg_diffs where address_id = 31)
+            , (select count(*) from mempool.token_diffs where address_id = 31)
+            , (select count(*) from mempool.addresses where address is not null);";
+    let row = test_db.client.query_one(sql, &[]).await.unwrap();
+    let counts: (i64, i64, i64) = (row.get(0), row.get(1), row.get(2));
+    assert_eq!(counts, (1, 1, 0));
+
+    // Confirmed transactions are evicted
+    let block0 = Block::dummy().height(0).parent_id("");
+    test_db.insert_core_header(&(&block0.header).into()).await;
+    let mut tx = Transaction::dummy();
+    tx.id = "tx-2".to_owned();
+    let block1 = Block::child_of(&block0).add_tx(tx);
+    for block in [block0, block1] {
+        workflow
+            .include_block(&CoreData { block }.into())
+            .await
+            .unwrap();
+    }
+    assert_eq!(count_transactions(&test_db).await, 0);
+
+    // Rollbacks drop the pool, to be picked up again by next poll
+    node.set_unconfirmed(vec![tx_1()]);
+    poller.poll().await.unwrap();
+    assert_eq!(count_transactions(&test_db).await, 1);
+    workflow.roll_back(1).await.unwrap();
+    assert_eq!(count_transactions(&test_db).await, 0);
+    node.set_unconfirmed(vec![tx_1(), tx_2()]);
+    poller.poll().await.unwrap();
+    assert_eq!(count_transactions(&test_db).await, 2);
+}
+
+#[tokio::test]
+async fn test_unreachable_node() {
+    let test_db = TestDB::new("worker_mempool_unreachable").await;
+    init(&test_db).await;
+    let _workflow = MempoolWorkFlow::new(&test_db.pgconf).await.unwrap();
+    let mut node_config = NodeConfig::new("http://127.0.0.1:9058");
+    node_config.max_retries = 0;
+    let mut poller = Poller::new(&test_db.pgconf, &node_config, &MempoolConfig::default())
+        .await
+        .unwrap();
+    // Skipped, not failing the worker
+    poller.poll().await.unwrap();
+}
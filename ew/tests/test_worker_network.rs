@@ -53,7 +53,7 @@ async fn test_simple() {
         // A random unknown extension field
         .add_extension_field("e309", "0100");
 
-    let mut workflow = NetworkWorkflow::new(&test_db.pgconf).await;
+    let mut workflow = NetworkWorkflow::new(&test_db.pgconf).await.unwrap();
 
     // Process blocks
     workflow
@@ -63,8 +63,12 @@ async fn test_simple() {
             }
             .into(),
         )
-        .await;
-    workflow.include_block(&CoreData { block }.into()).await;
+        .await
+        .unwrap();
+    workflow
+        .include_block(&CoreData { block }.into())
+        .await
+        .unwrap();
 }
 
 #[tokio::test]
@@ -98,7 +102,7 @@ async fn test_rollback() {
     );
 
     // Initialize workflow a first time
-    NetworkWorkflow::new(&test_db.pgconf).await;
+    NetworkWorkflow::new(&test_db.pgconf).await.unwrap();
 
     // Change the workflow position
     test_db
@@ -116,7 +120,7 @@ async fn test_rollback() {
         .unwrap();
 
     // Re-initialize the workflow to take above change into consideration
-    let mut workflow = NetworkWorkflow::new(&test_db.pgconf).await;
+    let mut workflow = NetworkWorkflow::new(&test_db.pgconf).await.unwrap();
 
     let height_y = block_y.header.height;
 
@@ -127,10 +131,12 @@ async fn test_rollback() {
     // Process blocks
     workflow
         .include_block(&CoreData { block: block_x }.into())
-        .await;
+        .await
+        .unwrap();
     workflow
         .include_block(&CoreData { block: block_y }.into())
-        .await;
+        .await
+        .unwrap();
 
     // Check db state prior to rollback
     let proposals = get_proposal_tallies(&test_db).await;
@@ -139,7 +145,7 @@ async fn test_rollback() {
     assert_eq!(proposals[0], (1, 1, 0, 0));
 
     // Do the rollback
-    workflow.roll_back(height_y).await;
+    workflow.roll_back(height_y).await.unwrap();
 
     // Check db state after rollback
     let proposals = get_proposal_tallies(&test_db).await;
@@ -148,6 +154,60 @@ async fn test_rollback() {
     assert_eq!(proposals[0], (1, 0, 0, 0));
 }
 
+#[tokio::test]
+async fn test_rollback_without_proposal() {
+    let _guard = set_tracing_subscriber(false);
+
+    // Prepare test db
+    let test_db = TestDB::new("network_rollback_without_proposal").await;
+    test_db.init_core().await;
+
+    // Genesis
+    let dummy_genesis_boxes = vec![BoxData::dummy()
+        .creation_height(0)
+        .timestamp(GENESIS_TIMESTAMP)];
+    let genesis_block = Block::from_genesis_boxes(dummy_genesis_boxes);
+
+    // Block 1, no votes and no proposal anywhere yet
+    let block = Block::dummy()
+        .height(1)
+        .parent_id(ZERO_HEADER)
+        .timestamp(GENESIS_TIMESTAMP + 120_000)
+        .add_tx(
+            Transaction::dummy()
+                .add_input(BoxData::dummy().address_id(EMISSION))
+                .add_output(BoxData::dummy().address_id(AddressID::miner(500))),
+        );
+
+    let mut workflow = NetworkWorkflow::new(&test_db.pgconf).await.unwrap();
+
+    // Register core header for parent of rolled back block
+    let h = Header::from(&genesis_block.header);
+    test_db.insert_core_header(&h).await;
+
+    // Process blocks
+    workflow
+        .include_block(
+            &CoreData {
+                block: genesis_block,
+            }
+            .into(),
+        )
+        .await
+        .unwrap();
+    workflow
+        .include_block(&CoreData { block }.into())
+        .await
+        .unwrap();
+
+    // Do the rollback
+    workflow.roll_back(1).await.unwrap();
+
+    // Still no proposals
+    let proposals = get_proposal_tallies(&test_db).await;
+    assert!(proposals.is_empty());
+}
+
 /// Return epoch and tallies from proposals
 async fn get_proposal_tallies(test_db: &TestDB) -> Vec<(i32, i16, i16, i16)> {
     test_db
@@ -6,17 +6,23 @@ use ew::core::types::AddressID;
 use ew::core::types::Block;
 use ew::core::types::BoxData;
 use ew::core::types::CoreData;
+use ew::core::types::Header;
 use ew::core::types::Timestamp;
 use ew::core::types::Transaction;
+use ew::framework::store::PgMigrator;
+use ew::framework::store::Revision;
 use ew::framework::EventHandling;
 use ew::workers::sigmausd::constants::BANK_NFT;
 use ew::workers::sigmausd::constants::CONTRACT_ADDRESS_ID;
 use ew::workers::sigmausd::constants::CONTRACT_CREATION_HEIGHT;
 use ew::workers::sigmausd::constants::ORACLE_NFT;
 use ew::workers::sigmausd::constants::SC_ASSET_ID;
+use ew::workers::sigmausd::testing;
 use ew::workers::sigmausd::SigmaUSD;
 
 // Contract was launched 25 MAR 2021.
+// Here's a timestamp 2 minutes after launch.
+const TS_25MAR2021: Timestamp = 1616706665437;
 // Here's a timestamp rounding up to 26 MAR 2021.
 const TS_26MAR2021: Timestamp = 1616761700471;
 // This one rounds up to 1 APR 2021.
@@ -24,6 +30,8 @@ const TS_01APR2021: Timestamp = 1617283540731;
 
 const CONTRACT_CREATION_HEADER_ID: &str =
     "fd35b157811f0950169e0f86b8f7e9ae0f13c49a46848ff40aa8dad26b030fde";
+const CONTRACT_CREATION_PARENT_ID: &str =
+    "6fbf04c19bf97a558b0254cd09f77813b91cd5cdb40d22613bb8512046924dbd";
 
 pub fn set_tracing_subscriber(set: bool) -> Option<tracing::dispatcher::DefaultGuard> {
     if !set {
@@ -43,8 +51,8 @@ async fn test_empty_block_pre_launch() {
     let test_db = TestDB::new("sigmausd_empty_block_pre").await;
     let block = Block::dummy().height(100);
     let data = CoreData { block };
-    let mut workflow = SigmaUSD::new(&test_db.pgconf).await;
-    workflow.include_block(&data.into()).await;
+    let mut workflow = SigmaUSD::new(&test_db.pgconf).await.unwrap();
+    workflow.include_block(&data.into()).await.unwrap();
 }
 
 #[tokio::test]
@@ -54,8 +62,11 @@ async fn test_empty_block_post_launch() {
     let block = Block::dummy()
         .height(CONTRACT_CREATION_HEIGHT + 1)
         .parent_id(CONTRACT_CREATION_HEADER_ID);
-    let mut workflow = SigmaUSD::new(&test_db.pgconf).await;
-    workflow.include_block(&CoreData { block }.into()).await;
+    let mut workflow = SigmaUSD::new(&test_db.pgconf).await.unwrap();
+    workflow
+        .include_block(&CoreData { block }.into())
+        .await
+        .unwrap();
 }
 
 #[tokio::test]
@@ -66,8 +77,11 @@ async fn test_no_events() {
         .height(CONTRACT_CREATION_HEIGHT + 1)
         .parent_id(CONTRACT_CREATION_HEADER_ID)
         .timestamp(TS_26MAR2021);
-    let mut workflow = SigmaUSD::new(&test_db.pgconf).await;
-    workflow.include_block(&CoreData { block }.into()).await;
+    let mut workflow = SigmaUSD::new(&test_db.pgconf).await.unwrap();
+    workflow
+        .include_block(&CoreData { block }.into())
+        .await
+        .unwrap();
 }
 
 #[tokio::test]
@@ -86,30 +100,33 @@ async fn test_sc_minting() {
                 .add_input(
                     BoxData::dummy()
                         .address_id(CONTRACT_ADDRESS_ID)
-                        .value(1000_000_000_000)
+                        .value(1_000_000_000_000)
                         .add_asset(BANK_NFT, 1)
-                        .add_asset(SC_ASSET_ID, 500_00),
+                        .add_asset(SC_ASSET_ID, 50_000),
                 )
                 // User input
-                .add_input(BoxData::dummy().address_id(user).value(5000_000_000_000))
+                .add_input(BoxData::dummy().address_id(user).value(5_000_000_000_000))
                 // Bank output
                 .add_output(
                     BoxData::dummy()
                         .address_id(CONTRACT_ADDRESS_ID)
-                        .value(1100_000_000_000)
+                        .value(1_100_000_000_000)
                         .add_asset(BANK_NFT, 1)
-                        .add_asset(SC_ASSET_ID, 300_00),
+                        .add_asset(SC_ASSET_ID, 30_000),
                 )
                 // User output
                 .add_output(
                     BoxData::dummy()
                         .address_id(user)
-                        .value(4900_000_000_000)
-                        .add_asset(SC_ASSET_ID, 200_00),
+                        .value(4_900_000_000_000)
+                        .add_asset(SC_ASSET_ID, 20_000),
                 ),
         );
-    let mut workflow = SigmaUSD::new(&test_db.pgconf).await;
-    workflow.include_block(&CoreData { block }.into()).await;
+    let mut workflow = SigmaUSD::new(&test_db.pgconf).await.unwrap();
+    workflow
+        .include_block(&CoreData { block }.into())
+        .await
+        .unwrap();
 }
 
 #[tokio::test]
@@ -138,26 +155,26 @@ async fn test_rollback() {
                 .add_input(
                     BoxData::dummy()
                         .address_id(CONTRACT_ADDRESS_ID)
-                        .value(1000_000_000_000)
+                        .value(1_000_000_000_000)
                         .add_asset(BANK_NFT, 1)
-                        .add_asset(SC_ASSET_ID, 500_00),
+                        .add_asset(SC_ASSET_ID, 50_000),
                 )
                 // User input
-                .add_input(BoxData::dummy().address_id(user).value(5000_000_000_000))
+                .add_input(BoxData::dummy().address_id(user).value(5_000_000_000_000))
                 // Bank output
                 .add_output(
                     BoxData::dummy()
                         .address_id(CONTRACT_ADDRESS_ID)
-                        .value(1100_000_000_000)
+                        .value(1_100_000_000_000)
                         .add_asset(BANK_NFT, 1)
-                        .add_asset(SC_ASSET_ID, 300_00),
+                        .add_asset(SC_ASSET_ID, 30_000),
                 )
                 // User output
                 .add_output(
                     BoxData::dummy()
                         .address_id(user)
-                        .value(4899_000_000_000)
-                        .add_asset(SC_ASSET_ID, 200_00),
+                        .value(4_899_000_000_000)
+                        .add_asset(SC_ASSET_ID, 20_000),
                 )
                 // Service output
                 .add_output(BoxData::dummy().address_id(service).value(1_000_000_000)),
@@ -179,16 +196,16 @@ async fn test_rollback() {
                 .add_input(
                     BoxData::dummy()
                         .address_id(CONTRACT_ADDRESS_ID)
-                        .value(1000_000_000_000)
+                        .value(1_000_000_000_000)
                         .add_asset(BANK_NFT, 1)
-                        .add_asset(SC_ASSET_ID, 500_00),
+                        .add_asset(SC_ASSET_ID, 50_000),
                 )
                 .add_output(
                     BoxData::dummy()
                         .address_id(CONTRACT_ADDRESS_ID)
-                        .value(1000_000_000_000)
+                        .value(1_000_000_000_000)
                         .add_asset(BANK_NFT, 1)
-                        .add_asset(SC_ASSET_ID, 500_00),
+                        .add_asset(SC_ASSET_ID, 50_000),
                 ),
         );
 
@@ -196,13 +213,246 @@ async fn test_rollback() {
     // after rolling back block 2.
     test_db.insert_core_header(&(&block1.header).into()).await;
 
-    let mut workflow = SigmaUSD::new(&test_db.pgconf).await;
+    let mut workflow = SigmaUSD::new(&test_db.pgconf).await.unwrap();
     workflow
         .include_block(&CoreData { block: block1 }.into())
-        .await;
+        .await
+        .unwrap();
     let block2_height = block2.header.height;
     workflow
         .include_block(&CoreData { block: block2 }.into())
+        .await
+        .unwrap();
+    workflow.roll_back(block2_height).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_rollback_gap_filled_ohlcs() {
+    let _guard = set_tracing_subscriber(false);
+    let test_db = TestDB::new("sigmausd_rollback_gap_filled_ohlcs").await;
+    test_db.init_core().await;
+    let user = AddressID::dummy(12345);
+
+    let block1 = Block::dummy()
+        .height(CONTRACT_CREATION_HEIGHT + 1)
+        .parent_id(CONTRACT_CREATION_HEADER_ID)
+        .timestamp(TS_26MAR2021);
+
+    // Price change a few days later, so daily records
+    // get filled for the days in between.
+    let block2 = Block::child_of(&block1)
+        .timestamp(TS_01APR2021)
+        // User mints 200 SigUSD for 100 ERG
+        .add_tx(
+            Transaction::dummy()
+                // Bank input
+                .add_input(
+                    BoxData::dummy()
+                        .address_id(CONTRACT_ADDRESS_ID)
+                        .value(1_000_000_000_000)
+                        .add_asset(BANK_NFT, 1)
+                        .add_asset(SC_ASSET_ID, 50_000),
+                )
+                // User input
+                .add_input(BoxData::dummy().address_id(user).value(5_000_000_000_000))
+                // Bank output
+                .add_output(
+                    BoxData::dummy()
+                        .address_id(CONTRACT_ADDRESS_ID)
+                        .value(1_100_000_000_000)
+                        .add_asset(BANK_NFT, 1)
+                        .add_asset(SC_ASSET_ID, 30_000),
+                )
+                // User output
+                .add_output(
+                    BoxData::dummy()
+                        .address_id(user)
+                        .value(4_900_000_000_000)
+                        .add_asset(SC_ASSET_ID, 20_000),
+                ),
+        );
+
+    // Register core header for block 1 to allow worker to restore it
+    // after rolling back block 2.
+    test_db.insert_core_header(&(&block1.header).into()).await;
+
+    let mut workflow = SigmaUSD::new(&test_db.pgconf).await.unwrap();
+    workflow
+        .include_block(&CoreData { block: block1 }.into())
+        .await
+        .unwrap();
+    let daily_before = get_daily_ohlcs(&test_db).await;
+    let block2_height = block2.header.height;
+    workflow
+        .include_block(&CoreData { block: block2 }.into())
+        .await
+        .unwrap();
+    // 25 MAR to 01 APR
+    // Prior records, a gap-filled one and 01 APR
+    assert_eq!(
+        get_daily_ohlcs(&test_db).await.len(),
+        daily_before.len() + 2
+    );
+
+    workflow.roll_back(block2_height).await.unwrap();
+
+    // Gap-filled records are gone too
+    assert_eq!(get_daily_ohlcs(&test_db).await, daily_before);
+}
+
+#[tokio::test]
+async fn test_rollback_initial_ohlcs() {
+    let _guard = set_tracing_subscriber(false);
+    let test_db = TestDB::new("sigmausd_rollback_initial_ohlcs").await;
+    test_db.init_core().await;
+    let user = AddressID::dummy(12345);
+
+    // Price change on launch day, updating the initial OHLC records
+    let block = Block::dummy()
+        .height(CONTRACT_CREATION_HEIGHT + 1)
+        .parent_id(CONTRACT_CREATION_HEADER_ID)
+        .timestamp(TS_25MAR2021)
+        // User mints 200 SigUSD for 100 ERG
+        .add_tx(
+            Transaction::dummy()
+                // Bank input
+                .add_input(
+                    BoxData::dummy()
+                        .address_id(CONTRACT_ADDRESS_ID)
+                        .value(1_000_000_000_000)
+                        .add_asset(BANK_NFT, 1)
+                        .add_asset(SC_ASSET_ID, 50_000),
+                )
+                // User input
+                .add_input(BoxData::dummy().address_id(user).value(5_000_000_000_000))
+                // Bank output
+                .add_output(
+                    BoxData::dummy()
+                        .address_id(CONTRACT_ADDRESS_ID)
+                        .value(1_100_000_000_000)
+                        .add_asset(BANK_NFT, 1)
+                        .add_asset(SC_ASSET_ID, 30_000),
+                )
+                // User output
+                .add_output(
+                    BoxData::dummy()
+                        .address_id(user)
+                        .value(4_900_000_000_000)
+                        .add_asset(SC_ASSET_ID, 20_000),
+                ),
+        );
+
+    // Register core header of contract creation to allow worker to
+    // restore it after rolling back the block.
+    let mut creation: Header = (&block.header).into();
+    creation.height = CONTRACT_CREATION_HEIGHT;
+    creation.header_id = CONTRACT_CREATION_HEADER_ID.to_owned();
+    creation.parent_id = CONTRACT_CREATION_PARENT_ID.to_owned();
+    test_db.insert_core_header(&creation).await;
+
+    let mut workflow = SigmaUSD::new(&test_db.pgconf).await.unwrap();
+    let ohlcs_before = get_ohlcs(&test_db).await;
+    let height = block.header.height;
+    workflow
+        .include_block(&CoreData { block }.into())
+        .await
+        .unwrap();
+    workflow.roll_back(height).await.unwrap();
+
+    // Initial records are restored
+    assert_eq!(get_ohlcs(&test_db).await, ohlcs_before);
+}
+
+/// Return dates and prices of daily, weekly and monthly OHLC records
+async fn get_ohlcs(test_db: &TestDB) -> Vec<(time::Date, i64, i64, i64, i64)> {
+    test_db
+        .client
+        .query(
+            "select t, o, h, l, c from sigmausd.rc_ohlc_daily
+            union all
+            select t, o, h, l, c from sigmausd.rc_ohlc_weekly
+            union all
+            select t, o, h, l, c from sigmausd.rc_ohlc_monthly;",
+            &[],
+        )
+        .await
+        .unwrap()
+        .iter()
+        .map(|r| (r.get(0), r.get(1), r.get(2), r.get(3), r.get(4)))
+        .collect()
+}
+
+/// Check migration 1.3 logs the initial OHLC records.
+#[tokio::test]
+async fn test_mig1_3() {
+    let _guard = set_tracing_subscriber(false);
+    let test_db = TestDB::new("sigmausd_migration_1_3").await;
+    test_db.init_core().await;
+    test_db.init_ew().await;
+
+    // Store at revision 1.2, without initial logs.
+    // Schema also sets the worker header.
+    test_db
+        .init_schema(include_str!("../src/workers/sigmausd/store/schema.sql"))
+        .await;
+    test_db
+        .init_schema(
+            "
+            delete from sigmausd._log_rc_ohlc_daily;
+            delete from sigmausd._log_rc_ohlc_weekly;
+            delete from sigmausd._log_rc_ohlc_monthly;
+            ",
+        )
+        .await;
+    test_db
+        .set_revision("sigmausd", "sigmausd", &Revision::new(1, 2))
         .await;
-    workflow.roll_back(block2_height).await;
+
+    // Run migration
+    let mut migrator = PgMigrator::new(&test_db.pgconf, &testing::SCHEMA)
+        .await
+        .unwrap();
+    migrator.apply(&testing::Mig1_3 {}).await.unwrap();
+    assert_eq!(
+        test_db.get_revision("sigmausd", "sigmausd").await,
+        Some(Revision::new(1, 3))
+    );
+
+    // Logs match initial records
+    let logs: Vec<(i32, time::Date, i64, i64, i64, i64)> = test_db
+        .client
+        .query(
+            "select height, t, o, h, l, c from sigmausd._log_rc_ohlc_daily
+            union all
+            select height, t, o, h, l, c from sigmausd._log_rc_ohlc_weekly
+            union all
+            select height, t, o, h, l, c from sigmausd._log_rc_ohlc_monthly;",
+            &[],
+        )
+        .await
+        .unwrap()
+        .iter()
+        .map(|r| (r.get(0), r.get(1), r.get(2), r.get(3), r.get(4), r.get(5)))
+        .collect();
+    let expected: Vec<(i32, time::Date, i64, i64, i64, i64)> = get_ohlcs(&test_db)
+        .await
+        .into_iter()
+        .map(|(t, o, h, l, c)| (CONTRACT_CREATION_HEIGHT, t, o, h, l, c))
+        .collect();
+    assert_eq!(logs, expected);
+}
+
+/// Return dates and prices of daily OHLC records
+async fn get_daily_ohlcs(test_db: &TestDB) -> Vec<(time::Date, i64, i64, i64, i64)> {
+    test_db
+        .client
+        .query(
+            "select t, o, h, l, c from sigmausd.rc_ohlc_daily order by 1;",
+            &[],
+        )
+        .await
+        .unwrap()
+        .iter()
+        .map(|r| (r.get(0), r.get(1), r.get(2), r.get(3), r.get(4)))
+        .collect()
 }
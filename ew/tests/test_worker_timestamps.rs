@@ -7,7 +7,11 @@ use ew::core::types::Block;
 use ew::core::types::BoxData;
 use ew::core::types::CoreData;
 use ew::core::types::Header;
+use ew::error::EwError;
+use ew::framework::store::PgMigrator;
+use ew::framework::store::Revision;
 use ew::framework::EventHandling;
+use ew::workers::timestamps::testing;
 use ew::workers::timestamps::TimestampsWorkFlow;
 
 pub fn set_tracing_subscriber(set: bool) -> Option<tracing::dispatcher::DefaultGuard> {
@@ -41,7 +45,7 @@ async fn test_normal() {
         .parent_id(ZERO_HEADER)
         .timestamp(GENESIS_TIMESTAMP + 120_000);
 
-    let mut workflow = TimestampsWorkFlow::new(&test_db.pgconf).await;
+    let mut workflow = TimestampsWorkFlow::new(&test_db.pgconf).await.unwrap();
 
     // Process blocks
     workflow
@@ -51,8 +55,12 @@ async fn test_normal() {
             }
             .into(),
         )
-        .await;
-    workflow.include_block(&CoreData { block }.into()).await;
+        .await
+        .unwrap();
+    workflow
+        .include_block(&CoreData { block }.into())
+        .await
+        .unwrap();
 
     // Check db
     let hourly = get_hourly_timestamps(&test_db).await;
@@ -96,7 +104,7 @@ async fn test_rollback() {
     let ts_y = ts_x + 86_400_000;
     let block_y = Block::child_of(&block_x).timestamp(ts_y);
 
-    let mut workflow = TimestampsWorkFlow::new(&test_db.pgconf).await;
+    let mut workflow = TimestampsWorkFlow::new(&test_db.pgconf).await.unwrap();
     let height_y = block_y.header.height;
 
     // Register core header for parent of rolled back blocks
@@ -111,13 +119,16 @@ async fn test_rollback() {
             }
             .into(),
         )
-        .await;
+        .await
+        .unwrap();
     workflow
         .include_block(&CoreData { block: block_x }.into())
-        .await;
+        .await
+        .unwrap();
     workflow
         .include_block(&CoreData { block: block_y }.into())
-        .await;
+        .await
+        .unwrap();
 
     // Check db
     let hourly = get_hourly_timestamps(&test_db).await;
@@ -138,7 +149,7 @@ async fn test_rollback() {
     assert_eq!(weekly[1], (2, ts_y));
 
     // Do the rollback
-    workflow.roll_back(height_y).await;
+    workflow.roll_back(height_y).await.unwrap();
 
     // Recheck db
     let hourly = get_hourly_timestamps(&test_db).await;
@@ -157,6 +168,120 @@ async fn test_rollback() {
     assert_eq!(weekly[1], (1, ts_x));
 }
 
+#[tokio::test]
+async fn test_batched_commits() {
+    let _guard = set_tracing_subscriber(false);
+
+    // Prepare test db
+    let test_db = TestDB::new("timestamps_batched").await;
+    test_db.init_core().await;
+
+    // Genesis and blocks X, Y, Z
+    let dummy_genesis_boxes = vec![BoxData::dummy()
+        .creation_height(0)
+        .timestamp(GENESIS_TIMESTAMP)];
+    let genesis_block = Block::from_genesis_boxes(dummy_genesis_boxes);
+    let ts_x = GENESIS_TIMESTAMP + 120_000;
+    let block_x = Block::dummy()
+        .height(1)
+        .parent_id(ZERO_HEADER)
+        .timestamp(ts_x);
+    let ts_y = ts_x + 86_400_000;
+    let block_y = Block::child_of(&block_x).timestamp(ts_y);
+    let block_z = Block::child_of(&block_y).timestamp(ts_y + 7_200_000);
+    let height_z = block_z.header.height;
+
+    // Register core header for parent of rolled back block
+    test_db
+        .insert_core_header(&Header::from(&block_y.header))
+        .await;
+
+    // Commit by groups of 3 blocks
+    let mut pgconf = test_db.pgconf.clone();
+    pgconf.batch_blocks = 3;
+    let mut workflow = TimestampsWorkFlow::new(&pgconf).await.unwrap();
+
+    // First 2 blocks are not visible to other connections yet
+    for block in [genesis_block, block_x] {
+        workflow
+            .include_block(&CoreData { block }.into())
+            .await
+            .unwrap();
+    }
+    assert_eq!(workflow.header().height, 1);
+    assert!(get_hourly_timestamps(&test_db).await.is_empty());
+
+    // Third one completes the batch
+    workflow
+        .include_block(&CoreData { block: block_y }.into())
+        .await
+        .unwrap();
+    assert_eq!(get_hourly_timestamps(&test_db).await.len(), 26);
+
+    // Pending blocks get committed when flushing
+    workflow
+        .include_block(&CoreData { block: block_z }.into())
+        .await
+        .unwrap();
+    assert_eq!(get_hourly_timestamps(&test_db).await.len(), 26);
+    workflow.flush().await.unwrap();
+    assert_eq!(get_hourly_timestamps(&test_db).await.len(), 28);
+
+    // Rollbacks are committed right away
+    workflow.roll_back(height_z).await.unwrap();
+    assert_eq!(workflow.header().height, 2);
+    assert_eq!(get_hourly_timestamps(&test_db).await.len(), 26);
+}
+
+#[tokio::test]
+async fn test_batch_interrupted() {
+    let _guard = set_tracing_subscriber(false);
+
+    // Prepare test db
+    let test_db = TestDB::new("timestamps_batch_interrupted").await;
+    test_db.init_core().await;
+
+    // Genesis and blocks X, Y
+    let dummy_genesis_boxes = vec![BoxData::dummy()
+        .creation_height(0)
+        .timestamp(GENESIS_TIMESTAMP)];
+    let genesis_block = Block::from_genesis_boxes(dummy_genesis_boxes);
+    let ts_x = GENESIS_TIMESTAMP + 120_000;
+    let block_x = Block::dummy()
+        .height(1)
+        .parent_id(ZERO_HEADER)
+        .timestamp(ts_x);
+    let block_y = Block::child_of(&block_x).timestamp(ts_x + 86_400_000);
+
+    let mut pgconf = test_db.pgconf.clone();
+    pgconf.batch_blocks = 3;
+    let mut workflow = TimestampsWorkFlow::new(&pgconf).await.unwrap();
+    for block in [genesis_block, block_x] {
+        workflow
+            .include_block(&CoreData { block }.into())
+            .await
+            .unwrap();
+    }
+
+    // Drop third block while being written
+    let data = CoreData { block: block_y }.into();
+    let res = tokio::time::timeout(std::time::Duration::ZERO, workflow.include_block(&data)).await;
+    assert!(res.is_err());
+
+    // Partially written batch is discarded instead of committed
+    let res = workflow.flush().await;
+    assert!(matches!(res, Err(EwError::StoreInterrupted(_, -1))));
+    assert_eq!(workflow.header().height, -1);
+    assert!(get_hourly_timestamps(&test_db).await.is_empty());
+    let n: i64 = test_db
+        .client
+        .query_one("select count(*) from timestamps.timestamps;", &[])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(n, 0);
+}
+
 async fn get_hourly_timestamps(test_db: &TestDB) -> Vec<(i32, i64)> {
     test_db
         .client
@@ -207,3 +332,47 @@ async fn get_weekly_timestamps(test_db: &TestDB) -> Vec<(i32, i64)> {
         .map(|r| (r.get::<usize, i32>(0), r.get::<usize, i64>(1)))
         .collect()
 }
+
+/// Check migration 1.1 indexes block timestamps.
+#[tokio::test]
+async fn test_mig1_1() {
+    let _guard = set_tracing_subscriber(false);
+    let test_db = TestDB::new("timestamps_migration_1_1").await;
+    test_db.init_core().await;
+    test_db.init_ew().await;
+
+    // Store at revision 1.0, without timestamp index
+    test_db
+        .init_schema(include_str!("../src/workers/timestamps/store/schema.sql"))
+        .await;
+    test_db
+        .init_schema("drop index timestamps.timestamps_timestamp_idx;")
+        .await;
+    test_db
+        .set_revision("timestamps", "timestamps", &Revision::new(1, 0))
+        .await;
+    test_db
+        .set_worker_header("timestamps", "timestamps", &Header::initial())
+        .await;
+
+    // Run migration
+    let mut migrator = PgMigrator::new(&test_db.pgconf, &testing::SCHEMA)
+        .await
+        .unwrap();
+    migrator.apply(&testing::Mig1_1 {}).await.unwrap();
+    assert_eq!(
+        test_db.get_revision("timestamps", "timestamps").await,
+        Some(Revision::new(1, 1))
+    );
+
+    let n: i64 = test_db
+        .client
+        .query_one(
+            "select count(*) from pg_indexes where indexname = 'timestamps_timestamp_idx';",
+            &[],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(n, 1);
+}
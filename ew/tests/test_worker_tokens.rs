@@ -13,6 +13,7 @@ use ew::core::types::Timestamp;
 use ew::core::types::Transaction;
 use ew::core::types::Value;
 use ew::framework::EventHandling;
+use ew::framework::Rewinding;
 use ew::workers::tokens::TokensWorkFlow;
 use tokio_postgres::Client;
 
@@ -45,7 +46,7 @@ async fn test_empty_blocks() {
     let block = Block::child_of(&genesis_block).timestamp(GENESIS_TIMESTAMP + 120_000);
 
     // Process blocks
-    let mut workflow = TokensWorkFlow::new(&test_db.pgconf).await;
+    let mut workflow = TokensWorkFlow::new(&test_db.pgconf).await.unwrap();
     workflow
         .include_block(
             &CoreData {
@@ -53,8 +54,12 @@ async fn test_empty_blocks() {
             }
             .into(),
         )
-        .await;
-    workflow.include_block(&CoreData { block }.into()).await;
+        .await
+        .unwrap();
+    workflow
+        .include_block(&CoreData { block }.into())
+        .await
+        .unwrap();
 }
 
 #[tokio::test]
@@ -139,7 +144,7 @@ async fn test_rollback() {
     // Register core header for parent of rolled back blocks
     test_db.insert_core_header(&(&block_x.header).into()).await;
 
-    let mut workflow = TokensWorkFlow::new(&test_db.pgconf).await;
+    let mut workflow = TokensWorkFlow::new(&test_db.pgconf).await.unwrap();
     let height_y = block_y.header.height;
     workflow
         .include_block(
@@ -148,13 +153,16 @@ async fn test_rollback() {
             }
             .into(),
         )
-        .await;
+        .await
+        .unwrap();
     workflow
         .include_block(&CoreData { block: block_x }.into())
-        .await;
+        .await
+        .unwrap();
     workflow
         .include_block(&CoreData { block: block_y }.into())
-        .await;
+        .await
+        .unwrap();
 
     // Check db state before rollback
     let diffs = get_diffs(&test_db.client).await;
@@ -182,7 +190,7 @@ async fn test_rollback() {
     );
 
     // Do the rollback
-    workflow.roll_back(height_y).await;
+    workflow.roll_back(height_y).await.unwrap();
 
     // Check db state after rollback
     let diffs = get_diffs(&test_db.client).await;
@@ -292,7 +300,7 @@ async fn test_rollback_alt() {
     // Register core header for parent of rolled back blocks
     test_db.insert_core_header(&(&block_x.header).into()).await;
 
-    let mut workflow = TokensWorkFlow::new(&test_db.pgconf).await;
+    let mut workflow = TokensWorkFlow::new(&test_db.pgconf).await.unwrap();
     let height_y = block_y.header.height;
     workflow
         .include_block(
@@ -301,13 +309,16 @@ async fn test_rollback_alt() {
             }
             .into(),
         )
-        .await;
+        .await
+        .unwrap();
     workflow
         .include_block(&CoreData { block: block_x }.into())
-        .await;
+        .await
+        .unwrap();
     workflow
         .include_block(&CoreData { block: block_y }.into())
-        .await;
+        .await
+        .unwrap();
 
     // Check db state before rollback
     let diffs = get_diffs(&test_db.client).await;
@@ -337,7 +348,7 @@ async fn test_rollback_alt() {
     );
 
     // Do the rollback
-    workflow.roll_back(height_y).await;
+    workflow.roll_back(height_y).await.unwrap();
 
     // Check db state after rollback
     let diffs = get_diffs(&test_db.client).await;
@@ -356,6 +367,154 @@ async fn test_rollback_alt() {
     assert_eq!(balances, vec![(addr_a, asset_x, 110_000_000_000),]);
 }
 
+/// Rewinds 2 blocks at once, including a balance (B) recreated and
+/// one (C) created then spent past rewind height.
+#[tokio::test]
+async fn test_rewind() {
+    let _guard = set_tracing_subscriber(false);
+    let addr_a = AddressID::dummy(1001);
+    let addr_b = AddressID::dummy(1002);
+    let addr_c = AddressID::dummy(1003);
+    let asset_x: AssetID = 11;
+    let test_db = TestDB::new("tokens_rewind").await;
+    test_db.init_core().await;
+
+    // Genesis
+    let genesis_block = Block::from_genesis_boxes(vec![]);
+
+    // Block X
+    let ts_x = TS_10K;
+    let block_x = Block::child_of(&genesis_block)
+        .timestamp(ts_x)
+        .add_tx(
+            // Create A and B out of thin air
+            Transaction::dummy()
+                .add_output(
+                    BoxData::dummy()
+                        .address_id(addr_a)
+                        .add_asset(asset_x, 105_000_000_000),
+                )
+                .add_output(
+                    BoxData::dummy()
+                        .address_id(addr_b)
+                        .add_asset(asset_x, 5_000_000_000),
+                ),
+        )
+        .add_tx(
+            // B sends 5 to A, spending B
+            Transaction::dummy()
+                .add_input(
+                    BoxData::dummy()
+                        .address_id(addr_b)
+                        .add_asset(asset_x, 5_000_000_000),
+                )
+                .add_output(
+                    BoxData::dummy()
+                        .address_id(addr_a)
+                        .add_asset(asset_x, 5_000_000_000),
+                ),
+        );
+
+    // Block Y
+    let block_y = Block::child_of(&block_x)
+        .timestamp(ts_x + 120_000)
+        .add_tx(
+            // A sends 5 to C, creating C
+            Transaction::dummy()
+                .add_input(
+                    BoxData::dummy()
+                        .address_id(addr_a)
+                        .add_asset(asset_x, 5_000_000_000),
+                )
+                .add_output(
+                    BoxData::dummy()
+                        .address_id(addr_c)
+                        .add_asset(asset_x, 5_000_000_000),
+                ),
+        )
+        .add_tx(
+            // C sends 1 to B, recreating B
+            Transaction::dummy()
+                .add_input(
+                    BoxData::dummy()
+                        .address_id(addr_c)
+                        .add_asset(asset_x, 1_000_000_000),
+                )
+                .add_output(
+                    BoxData::dummy()
+                        .address_id(addr_b)
+                        .add_asset(asset_x, 1_000_000_000),
+                ),
+        );
+
+    // Block Z
+    let block_z = Block::child_of(&block_y).timestamp(ts_x + 240_000).add_tx(
+        // C sends 4 to A, spending C
+        Transaction::dummy()
+            .add_input(
+                BoxData::dummy()
+                    .address_id(addr_c)
+                    .add_asset(asset_x, 4_000_000_000),
+            )
+            .add_output(
+                BoxData::dummy()
+                    .address_id(addr_a)
+                    .add_asset(asset_x, 4_000_000_000),
+            ),
+    );
+
+    // Register core header of rewind height
+    test_db.insert_core_header(&(&block_x.header).into()).await;
+
+    let mut workflow = TokensWorkFlow::new(&test_db.pgconf).await.unwrap();
+    let height_x = block_x.header.height;
+    for block in [genesis_block, block_x, block_y, block_z] {
+        workflow
+            .include_block(&CoreData { block }.into())
+            .await
+            .unwrap();
+    }
+
+    // Check db state before rewind
+    assert_eq!(get_diffs(&test_db.client).await.len(), 10);
+    assert_eq!(
+        get_balances(&test_db.client).await,
+        vec![
+            (addr_a, asset_x, 109_000_000_000),
+            (addr_b, asset_x, 1_000_000_000),
+        ]
+    );
+
+    // Rewind to block X
+    let header = workflow.rewind(height_x).await.unwrap();
+    assert_eq!(header.height, height_x);
+
+    // Check db state after rewind
+    assert_eq!(
+        get_diffs(&test_db.client).await,
+        vec![
+            (addr_a, asset_x, 1, 0, 105_000_000_000),
+            (addr_b, asset_x, 1, 0, 5_000_000_000),
+            (addr_a, asset_x, 1, 1, 5_000_000_000),
+            (addr_b, asset_x, 1, 1, -5_000_000_000),
+        ]
+    );
+    assert_eq!(
+        get_balances(&test_db.client).await,
+        vec![(addr_a, asset_x, 110_000_000_000)]
+    );
+    let stored_height: Height = test_db
+        .client
+        .query_one(
+            "select height from ew.headers where worker_id = 'tokens';",
+            &[],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(stored_height, height_x);
+}
+
 async fn get_diffs(client: &Client) -> Vec<(AddressID, AssetID, Height, i16, Value)> {
     client
         .query(
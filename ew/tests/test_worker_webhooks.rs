@@ -0,0 +1,385 @@
+// cargo test --test '*' -- --test-threads=1
+mod db_utils;
+
+use axum::extract::Extension;
+use axum::routing::post;
+use axum::Json;
+use axum::Router;
+use serde_json::json;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+use tokio::sync::Mutex;
+
+use db_utils::TestDB;
+use ew::config::RuleCondition;
+use ew::config::WebhookRule;
+use ew::config::WebhooksConfig;
+use ew::constants::settings::rollback_horizon;
+use ew::core::types::AddressID;
+use ew::core::types::Block;
+use ew::core::types::BoxData;
+use ew::core::types::CoreData;
+use ew::core::types::Timestamp;
+use ew::core::types::Transaction;
+use ew::framework::EventHandling;
+use ew::framework::StampedData;
+use ew::shutdown::CancellationToken;
+use ew::workers::erg_diffs::types::DiffData;
+use ew::workers::erg_diffs::types::DiffRecord;
+use ew::workers::exchanges;
+use ew::workers::registry::WorkerID;
+use ew::workers::webhooks::Dispatcher;
+use ew::workers::webhooks::WebhooksDiffsWorkFlow;
+use ew::workers::webhooks::WebhooksWorkFlow;
+
+const PORT: u16 = 3108;
+
+type Received = Arc<Mutex<Vec<Value>>>;
+
+/// Spawns a webhook receiver recording all notifications.
+async fn receiver() -> Received {
+    let received = Received::default();
+    let app = Router::new()
+        .route(
+            "/whales",
+            post(
+                |Extension(received): Extension<Received>, Json(body): Json<Value>| async move {
+                    received.lock().await.push(body);
+                },
+            ),
+        )
+        .layer(Extension(received.clone()));
+    let address = SocketAddr::from(([127, 0, 0, 1], PORT));
+    tokio::spawn(axum::Server::bind(&address).serve(app.into_make_service()));
+    received
+}
+
+fn config() -> WebhooksConfig {
+    WebhooksConfig {
+        rules: vec![
+            WebhookRule {
+                name: "whales".to_owned(),
+                url: format!("http://localhost:{PORT}/whales"),
+                condition: RuleCondition::Transfer { min_nano: 1000 },
+            },
+            // Nothing listening there
+            WebhookRule {
+                name: "lost".to_owned(),
+                url: format!("http://localhost:{}/lost", PORT + 1),
+                condition: RuleCondition::Transfer { min_nano: 1000 },
+            },
+        ],
+        timeout: Duration::from_secs(1),
+        ..WebhooksConfig::default()
+    }
+}
+
+fn now() -> Timestamp {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as Timestamp
+}
+
+fn transfer(address_id: AddressID, nano: i64) -> Transaction {
+    Transaction::dummy()
+        .add_input(BoxData::dummy().address_id(AddressID(11)).value(nano))
+        .add_output(BoxData::dummy().address_id(address_id).value(nano))
+}
+
+/// Number of undelivered notifications for given `rule`.
+async fn count_pending(test_db: &TestDB, rule: &str) -> i64 {
+    let sql = "select count(*) from webhooks.outbox where rule = $1 and not delivered;";
+    test_db
+        .client
+        .query_one(sql, &[&rule])
+        .await
+        .unwrap()
+        .get(0)
+}
+
+/// Number of delivered notifications, retractions included.
+async fn count_delivered(test_db: &TestDB) -> i64 {
+    let sql = "select count(*) from webhooks.outbox where delivered;";
+    test_db.client.query_one(sql, &[]).await.unwrap().get(0)
+}
+
+#[tokio::test]
+async fn test_delivery_and_retraction() {
+    let test_db = TestDB::new("webhooks_delivery").await;
+    test_db.init_core().await;
+    test_db.init_ew().await;
+    test_db
+        .client
+        .batch_execute(
+            "insert into core.addresses (id, spot_height, address) values
+                (21, 1, 'addressA'),
+                (31, 1, 'addressB');",
+        )
+        .await
+        .unwrap();
+    let received = receiver().await;
+    let config = config();
+
+    let block1 = Block::dummy()
+        .height(0)
+        .parent_id("")
+        .timestamp(now())
+        .add_tx(transfer(AddressID(21), 5000))
+        // Below threshold
+        .add_tx(transfer(AddressID(31), 999));
+    let block2 = Block::child_of(&block1)
+        .timestamp(now())
+        .add_tx(transfer(AddressID(31), 2000));
+    let block2b = Block::child_of(&block1)
+        .timestamp(now())
+        .add_tx(transfer(AddressID(31), 3000));
+    // Parent of rolled back blocks
+    test_db.insert_core_header(&(&block1.header).into()).await;
+    let block1_id = block1.header.id.clone();
+    let block2_id = block2.header.id.clone();
+
+    let mut workflow = WebhooksWorkFlow::with_config(&test_db.pgconf, &config)
+        .await
+        .unwrap();
+    let mut dispatcher = Dispatcher::new(&test_db.pgconf, &config, WorkerID::Webhooks)
+        .await
+        .unwrap();
+    workflow
+        .include_block(&CoreData { block: block1 }.into())
+        .await
+        .unwrap();
+    workflow
+        .include_block(&CoreData { block: block2 }.into())
+        .await
+        .unwrap();
+
+    // Notifications are delivered in order
+    assert_eq!(dispatcher.dispatch().await.unwrap(), 2);
+    let notifications = received.lock().await.clone();
+    assert_eq!(notifications.len(), 2);
+    let (id1, id2) = (&notifications[0]["id"], &notifications[1]["id"]);
+    assert!(id1.as_i64() < id2.as_i64());
+    assert_eq!(
+        notifications[0],
+        json!({
+            "id": id1,
+            "type": "transfer",
+            "rule": "whales",
+            "height": 0,
+            "header_id": block1_id,
+            "event": {
+                "tx_id": notifications[0]["event"]["tx_id"],
+                "address": "addressA",
+                "nano": 5000,
+            },
+        })
+    );
+    assert_eq!(notifications[1]["header_id"], json!(block2_id));
+    assert_eq!(notifications[1]["event"]["address"], "addressB");
+    // Failing webhooks keep their notifications for later
+    assert_eq!(count_pending(&test_db, "lost").await, 2);
+
+    // Delivered notification of rolled back block gets retracted
+    workflow.roll_back(1).await.unwrap();
+    // Undelivered one is dropped
+    workflow
+        .include_block(&CoreData { block: block2b }.into())
+        .await
+        .unwrap();
+    workflow.roll_back(1).await.unwrap();
+    // Rolling back again doesn't retract twice
+    assert_eq!(count_pending(&test_db, "whales").await, 1);
+    // Lost notifications of rolled back blocks are dropped
+    assert_eq!(count_pending(&test_db, "lost").await, 1);
+
+    assert_eq!(dispatcher.dispatch().await.unwrap(), 1);
+    let retraction = received.lock().await[2].clone();
+    assert_eq!(retraction["type"], "retraction");
+    assert_eq!(&retraction["retracts"], id2);
+    assert_eq!(retraction["event"], notifications[1]["event"]);
+    assert_eq!(dispatcher.dispatch().await.unwrap(), 0);
+    assert_eq!(count_delivered(&test_db).await, 3);
+
+    // Delivered notifications and retractions are deleted once past the
+    // rollback horizon, undelivered ones are kept.
+    let mut parent_id = block1_id.clone();
+    for height in 1..=rollback_horizon() + 2 {
+        let block = Block::dummy()
+            .height(height)
+            .parent_id(&parent_id)
+            .timestamp(now());
+        parent_id = block.header.id.clone();
+        workflow
+            .include_block(&CoreData { block }.into())
+            .await
+            .unwrap();
+        dispatcher.dispatch().await.unwrap();
+        if height == rollback_horizon() {
+            assert_eq!(count_delivered(&test_db).await, 3);
+        }
+    }
+    assert_eq!(count_delivered(&test_db).await, 0);
+    assert_eq!(count_pending(&test_db, "lost").await, 1);
+}
+
+#[tokio::test]
+async fn test_dispatcher_task() {
+    let test_db = TestDB::new("webhooks_dispatcher_task").await;
+    test_db.init_core().await;
+    test_db.init_ew().await;
+    test_db
+        .client
+        .batch_execute(
+            "insert into core.addresses (id, spot_height, address) values (21, 1, 'addressA');",
+        )
+        .await
+        .unwrap();
+    let received = receiver().await;
+    let config = WebhooksConfig {
+        polling_interval: Duration::from_millis(20),
+        ..config()
+    };
+
+    // Started before the worker creates the outbox
+    let shutdown = CancellationToken::new();
+    let mut dispatcher = Dispatcher::new(&test_db.pgconf, &config, WorkerID::Webhooks)
+        .await
+        .unwrap();
+    let handle = tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move { dispatcher.start(shutdown).await }
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!handle.is_finished());
+
+    let mut workflow = WebhooksWorkFlow::with_config(&test_db.pgconf, &config)
+        .await
+        .unwrap();
+    let block = Block::dummy()
+        .height(0)
+        .parent_id("")
+        .timestamp(now())
+        .add_tx(transfer(AddressID(21), 5000));
+    workflow
+        .include_block(&CoreData { block }.into())
+        .await
+        .unwrap();
+
+    // Delivered in the background
+    for _ in 0..100 {
+        if !received.lock().await.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(received.lock().await.len(), 1);
+
+    shutdown.cancel();
+    assert!(handle.await.unwrap().is_ok());
+}
+
+#[tokio::test]
+async fn test_old_blocks_are_not_notified() {
+    let test_db = TestDB::new("webhooks_old_blocks").await;
+    test_db.init_core().await;
+    test_db.init_ew().await;
+    let block = Block::dummy()
+        .height(0)
+        .parent_id("")
+        .timestamp(now() - 3_600_001)
+        .add_tx(transfer(AddressID(21), 5000));
+    let mut workflow = WebhooksWorkFlow::with_config(&test_db.pgconf, &config())
+        .await
+        .unwrap();
+    workflow
+        .include_block(&CoreData { block }.into())
+        .await
+        .unwrap();
+    let sql = "select count(*) from webhooks.outbox;";
+    let count: i64 = test_db.client.query_one(sql, &[]).await.unwrap().get(0);
+    assert_eq!(count, 0);
+}
+
+#[tokio::test]
+async fn test_cex_deposits() {
+    let test_db = TestDB::new("webhooks_cex_deposits").await;
+    test_db.init_core().await;
+    test_db.init_ew().await;
+    exchanges::migrate(&test_db.pgconf).await.unwrap();
+    test_db
+        .client
+        .batch_execute(
+            "insert into core.addresses (id, spot_height, address) values
+                (1001, 0, 'addressA'),
+                (1002, 0, 'addressB'),
+                (9101, 0, 'cexMain');
+            insert into exchanges.exchanges (id, name, text_id) values
+                (10000, 'Exchange 1', 'cex_1');
+            insert into exchanges.main_addresses (address_id, cex_id, address) values
+                (9101, 10000, 'cexMain');
+            -- Spotted by the exchanges worker already
+            insert into exchanges.deposit_addresses (address_id, cex_id, spot_height) values
+                (1002, 10000, 0);",
+        )
+        .await
+        .unwrap();
+    let config = WebhooksConfig {
+        rules: vec![WebhookRule {
+            name: "deposits".to_owned(),
+            url: format!("http://localhost:{PORT}/deposits"),
+            condition: RuleCondition::CexDeposit,
+        }],
+        ..WebhooksConfig::default()
+    };
+
+    let data_0 = StampedData {
+        height: 0,
+        timestamp: now(),
+        header_id: "header0".to_owned(),
+        parent_id: "".to_owned(),
+        data: DiffData {
+            diff_records: vec![
+                DiffRecord::new(AddressID(1001), 0, 0, -1000),
+                DiffRecord::new(AddressID(9101), 0, 0, 1000),
+            ],
+        },
+    };
+    // Exchanges worker hasn't processed block 0 yet,
+    // so addressA is only known from the outbox.
+    let data_1 = data_0.wrap_as_child(DiffData {
+        diff_records: vec![
+            DiffRecord::new(AddressID(1001), 1, 0, -500),
+            DiffRecord::new(AddressID(1002), 1, 0, -500),
+            DiffRecord::new(AddressID(9101), 1, 0, 1000),
+        ],
+    });
+    let data_1 = data_1.timestamp(now());
+
+    let mut workflow = WebhooksDiffsWorkFlow::with_config(&test_db.pgconf, &config)
+        .await
+        .unwrap();
+    workflow.include_block(&data_0).await.unwrap();
+    workflow.include_block(&data_1).await.unwrap();
+
+    let sql = "select height, kind, event from webhooks_diffs.outbox order by id;";
+    let rows: Vec<(i32, String, Value)> = test_db
+        .client
+        .query(sql, &[])
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2)))
+        .collect();
+    assert_eq!(
+        rows,
+        vec![(
+            0,
+            "cex_deposit".to_owned(),
+            json!({"address": "addressA", "cex": "cex_1"})
+        )]
+    );
+}